@@ -90,4 +90,13 @@ object KeyManager : SecureStore {
     } catch (e: Exception) {
         throw CoreException.Internal("open: ${e.message}")
     }
+
+    @Synchronized
+    override fun forget() {
+        try {
+            if (keyStore.containsAlias(KEY_ALIAS)) keyStore.deleteEntry(KEY_ALIAS)
+        } catch (e: Exception) {
+            throw CoreException.Internal("forget: ${e.message}")
+        }
+    }
 }
@@ -0,0 +1,209 @@
+//! Blinded mailbox keys.
+//!
+//! Home relays queue a user's mail under a 32-byte key and route on its XOR
+//! distance. When that key is the IPK, every relay on the path can tell when
+//! the identity is active. A mailbox id is the IPK blinded by a factor derived
+//! from a seed the owner shares with their contacts and from the epoch. Only
+//! the owner and those contacts can compute it. The id changes every
+//! [`MAILBOX_EPOCH_MS`] and links to neither the IPK nor the previous mailbox.
+//!
+//! The blinding multiplies the Ed25519 point (`A' = h·A`), so a mailbox id is
+//! itself a verifying key. Only the identity secret can sign for it
+//! (`a' = h·a`). Relays check mailbox signatures exactly as they check IPK
+//! signatures; nothing on the relay side needs the seed.
+
+use std::ops::RangeInclusive;
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::Signature;
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use sha2::Digest;
+use sha2::Sha256;
+use sha2::Sha512;
+use zeroize::Zeroize;
+use zeroize::Zeroizing;
+
+/// How long one mailbox id is addressed. Equal to the home queue's retention,
+/// so a recipient keeps at most the current mailbox and the one before it
+/// open to drain (plus the next one during the overlap).
+pub const MAILBOX_EPOCH_MS: u64 = 7 * 24 * 3_600_000;
+
+/// How much clock skew a mailbox tolerates across its boundaries. The next
+/// mailbox opens this long before its epoch starts, and a sender whose clock
+/// runs late can still address the current one this long after it ends.
+pub const MAILBOX_OVERLAP_MS: u64 = 24 * 3_600_000;
+
+/// Mailboxes one connection may hold open at once. [`open_epochs`] never
+/// spans more than three epochs; the extra slot covers a reconnect that
+/// straddles a boundary.
+pub const MAX_OPEN_MAILBOXES: usize = 4;
+
+/// HKDF info for the seed a user shares with their contacts. Changing it
+/// moves every mailbox the user has.
+pub const MAILBOX_SEED_INFO: &[u8] = b"promtuz-mailbox-seed-v1";
+
+const MAILBOX_BLIND_DOMAIN: &[u8] = b"promtuz-mailbox-blind-v1";
+const MAILBOX_NONCE_DOMAIN: &[u8] = b"promtuz-mailbox-nonce-v1";
+
+/// The epoch `now_ms` falls in.
+pub fn epoch_of(now_ms: u64) -> u64 {
+    now_ms / MAILBOX_EPOCH_MS
+}
+
+/// First millisecond after `epoch`.
+pub fn epoch_end_ms(epoch: u64) -> u64 {
+    epoch.saturating_add(1).saturating_mul(MAILBOX_EPOCH_MS)
+}
+
+/// Epochs whose mailboxes the owner keeps open at `now_ms`. A mailbox opens
+/// [`MAILBOX_OVERLAP_MS`] before its epoch starts. It stays open until a
+/// late-clocked sender's mail has outlived the home queue's retention (one
+/// epoch) after the overlap past its end.
+pub fn open_epochs(now_ms: u64) -> RangeInclusive<u64> {
+    let oldest = epoch_of(now_ms.saturating_sub(MAILBOX_EPOCH_MS + MAILBOX_OVERLAP_MS));
+    oldest..=epoch_of(now_ms.saturating_add(MAILBOX_OVERLAP_MS))
+}
+
+/// The seed `identity` shares with contacts so they can compute its mailbox
+/// ids. Derived rather than stored, so every device and every restore of the
+/// identity lands on the same mailboxes.
+pub fn seed_for(identity: &SigningKey) -> [u8; 32] {
+    let secret = Zeroizing::new(identity.to_bytes());
+    let ipk = identity.verifying_key().to_bytes();
+    let mut seed = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&ipk), secret.as_slice())
+        .expand(MAILBOX_SEED_INFO, &mut seed)
+        .expect("HKDF-SHA256 expand into 32 bytes never fails");
+    seed
+}
+
+/// `ipk`'s mailbox id for `epoch`, as a contact holding `seed` computes it.
+/// `None` when `ipk` is not a curve point.
+pub fn mailbox_id(ipk: &[u8; 32], seed: &[u8; 32], epoch: u64) -> Option<[u8; 32]> {
+    let point = CompressedEdwardsY(*ipk).decompress()?;
+    Some((blinding_factor(ipk, seed, epoch) * point).compress().to_bytes())
+}
+
+/// The owner's signing half of one mailbox. Signatures verify strictly under
+/// [`Self::id`], the same id contacts get from [`mailbox_id`].
+pub struct MailboxKey {
+    id:     [u8; 32],
+    scalar: Scalar,
+    prefix: Zeroizing<[u8; 32]>,
+}
+
+impl MailboxKey {
+    pub fn derive(identity: &SigningKey, seed: &[u8; 32], epoch: u64) -> Self {
+        let ipk = identity.verifying_key().to_bytes();
+        let blind = blinding_factor(&ipk, seed, epoch);
+        let scalar = blind * identity.to_scalar();
+        let id = EdwardsPoint::mul_base(&scalar).compress().to_bytes();
+        // Deterministic nonces as in RFC 8032, from a prefix that is neither
+        // the identity's own nor shared by two epochs.
+        let secret = Zeroizing::new(identity.to_bytes());
+        let digest = Sha512::new()
+            .chain_update(MAILBOX_NONCE_DOMAIN)
+            .chain_update(secret.as_slice())
+            .chain_update(blind.as_bytes())
+            .finalize();
+        let mut prefix = Zeroizing::new([0u8; 32]);
+        prefix.copy_from_slice(&digest[..32]);
+        Self { id, scalar, prefix }
+    }
+
+    pub fn id(&self) -> [u8; 32] {
+        self.id
+    }
+
+    /// Ed25519 over `message` under the blinded scalar.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let r =
+            wide_scalar(Sha512::new().chain_update(self.prefix.as_slice()).chain_update(message));
+        let big_r = EdwardsPoint::mul_base(&r).compress();
+        let k = wide_scalar(
+            Sha512::new()
+                .chain_update(big_r.as_bytes())
+                .chain_update(self.id)
+                .chain_update(message),
+        );
+        let s = r + k * self.scalar;
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(big_r.as_bytes());
+        bytes[32..].copy_from_slice(s.as_bytes());
+        Signature::from_bytes(&bytes)
+    }
+}
+
+impl Drop for MailboxKey {
+    fn drop(&mut self) {
+        self.scalar.zeroize();
+    }
+}
+
+fn blinding_factor(ipk: &[u8; 32], seed: &[u8; 32], epoch: u64) -> Scalar {
+    wide_scalar(
+        Sha512::new()
+            .chain_update(MAILBOX_BLIND_DOMAIN)
+            .chain_update(seed)
+            .chain_update(ipk)
+            .chain_update(epoch.to_be_bytes()),
+    )
+}
+
+fn wide_scalar(hasher: Sha512) -> Scalar {
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&hasher.finalize());
+    Scalar::from_bytes_mod_order_wide(&wide)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::VerifyingKey;
+
+    use super::*;
+
+    /// A contact's id and the owner's key agree, the owner's signature passes
+    /// a relay's strict check under that id, and nothing about the id repeats
+    /// the IPK, another epoch, or another seed.
+    #[test]
+    fn a_contact_addresses_the_mailbox_only_its_owner_can_sign_for() {
+        let owner = SigningKey::from_bytes(&[0x31; 32]);
+        let ipk = owner.verifying_key().to_bytes();
+        let seed = seed_for(&owner);
+        let key = MailboxKey::derive(&owner, &seed, 2_900);
+
+        assert_eq!(mailbox_id(&ipk, &seed, 2_900), Some(key.id()));
+        assert_ne!(key.id(), ipk);
+        assert_ne!(mailbox_id(&ipk, &seed, 2_901), Some(key.id()), "rotates per epoch");
+        assert_ne!(mailbox_id(&ipk, &[0u8; 32], 2_900), Some(key.id()), "needs the seed");
+        assert_eq!(seed_for(&owner), seed, "every device derives the same seed");
+
+        let message = b"queue-fetch transcript";
+        let vk = VerifyingKey::from_bytes(&key.id()).expect("the id is a verifying key");
+        vk.verify_strict(message, &key.sign(message)).expect("owner signs for the mailbox");
+        assert!(vk.verify_strict(message, &owner.sign(message)).is_err());
+    }
+
+    /// Each mailbox is open from an overlap before its epoch until a queue
+    /// retention past its end plus overlap, and never more than the relay
+    /// cap are open together.
+    #[test]
+    fn open_mailboxes_overlap_each_boundary_and_stay_under_the_cap() {
+        let start = 2_900 * MAILBOX_EPOCH_MS;
+        assert!(!open_epochs(start - MAILBOX_OVERLAP_MS - 1).contains(&2_900));
+        assert!(open_epochs(start - MAILBOX_OVERLAP_MS).contains(&2_900));
+
+        let closes = epoch_end_ms(2_900) + MAILBOX_OVERLAP_MS + MAILBOX_EPOCH_MS;
+        assert!(open_epochs(closes - 1).contains(&2_900));
+        assert!(!open_epochs(closes).contains(&2_900));
+
+        for step in 0..(3 * MAILBOX_EPOCH_MS / 3_600_000) {
+            let open = open_epochs(start + step * 3_600_000);
+            assert!(open.end() - open.start() < MAX_OPEN_MAILBOXES as u64);
+        }
+    }
+}
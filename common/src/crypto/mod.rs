@@ -4,6 +4,8 @@ pub use ed25519_dalek::VerifyingKey as PublicKey;
 use rand::TryRng;
 use rand::rngs::SysRng;
 
+pub mod mailbox;
+pub mod shamir;
 pub mod sign;
 
 pub fn get_signing_key() -> SigningKey {
@@ -0,0 +1,169 @@
+//! Shamir secret sharing of a 32-byte secret over GF(2^8).
+//!
+//! Each byte of the secret is the constant term of its own random polynomial
+//! of degree `threshold - 1`; share `x` holds every polynomial evaluated at
+//! `x`. Any `threshold` shares interpolate back to the secret at `x = 0`,
+//! and fewer say nothing about it. Used to split the identity key across
+//! contacts for social recovery.
+//!
+//! The field is the AES one (`x^8 + x^4 + x^3 + x + 1`). Multiplication runs
+//! a fixed eight rounds with no table lookups, so timing doesn't depend on
+//! the secret.
+
+use zeroize::Zeroizing;
+
+use crate::crypto::get_nonce;
+
+/// Most shares one split produces. Indices run `1..=MAX_SHARES`; `0` is where
+/// the secret sits.
+pub const MAX_SHARES: u8 = 16;
+
+/// One share: its x coordinate and the 32 evaluated bytes.
+pub type Share = (u8, [u8; 32]);
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ShamirError {
+    #[error("threshold must be between 2 and the number of shares")]
+    BadThreshold,
+    #[error("at most {MAX_SHARES} shares")]
+    TooManyShares,
+    #[error("share index must be between 1 and {MAX_SHARES}")]
+    BadIndex,
+    #[error("two shares with the same index")]
+    DuplicateIndex,
+    #[error("need at least two shares")]
+    TooFewShares,
+}
+
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        p ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    p
+}
+
+/// `a^254`, which is `a^-1` for every nonzero `a`.
+fn inv(a: u8) -> u8 {
+    let mut r = 1u8;
+    let mut base = a;
+    let mut e = 254u8;
+    while e > 0 {
+        if e & 1 == 1 {
+            r = mul(r, base);
+        }
+        base = mul(base, base);
+        e >>= 1;
+    }
+    r
+}
+
+/// Split `secret` into `count` shares, any `threshold` of which recover it.
+pub fn split(secret: &[u8; 32], threshold: u8, count: u8) -> Result<Vec<Share>, ShamirError> {
+    if count > MAX_SHARES {
+        return Err(ShamirError::TooManyShares);
+    }
+    if threshold < 2 || threshold > count {
+        return Err(ShamirError::BadThreshold);
+    }
+    // coeffs[d][i]: degree-d coefficient of byte i's polynomial.
+    let mut coeffs = Zeroizing::new(vec![[0u8; 32]; threshold as usize]);
+    coeffs[0] = *secret;
+    for c in coeffs.iter_mut().skip(1) {
+        *c = get_nonce::<32>();
+    }
+    Ok((1..=count)
+        .map(|x| {
+            let mut y = [0u8; 32];
+            for (i, yi) in y.iter_mut().enumerate() {
+                // Horner, highest degree first.
+                *yi = coeffs.iter().rev().fold(0, |acc, c| mul(acc, x) ^ c[i]);
+            }
+            (x, y)
+        })
+        .collect())
+}
+
+/// Interpolate `shares` back to the secret. Given fewer than the split's
+/// threshold this returns *a* value, not an error — only the caller can tell
+/// whether it is the right one (for an identity key: whether it derives the
+/// expected public key).
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<[u8; 32]>, ShamirError> {
+    if shares.len() < 2 {
+        return Err(ShamirError::TooFewShares);
+    }
+    for (n, (x, _)) in shares.iter().enumerate() {
+        if *x == 0 || *x > MAX_SHARES {
+            return Err(ShamirError::BadIndex);
+        }
+        if shares[..n].iter().any(|(other, _)| other == x) {
+            return Err(ShamirError::DuplicateIndex);
+        }
+    }
+    let mut secret = Zeroizing::new([0u8; 32]);
+    for (j, (xj, yj)) in shares.iter().enumerate() {
+        // Lagrange basis at 0: prod over m != j of x_m / (x_m - x_j); in
+        // GF(2^8) subtraction is XOR.
+        let mut num = 1u8;
+        let mut den = 1u8;
+        for (m, (xm, _)) in shares.iter().enumerate() {
+            if m != j {
+                num = mul(num, *xm);
+                den = mul(den, xm ^ xj);
+            }
+        }
+        let basis = mul(num, inv(den));
+        for (s, y) in secret.iter_mut().zip(yj) {
+            *s ^= mul(basis, *y);
+        }
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_inverse_holds_for_every_nonzero_byte() {
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1, "a = {a}");
+        }
+        // The AES field's textbook example: {57} * {83} = {c1}.
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+    }
+
+    #[test]
+    fn any_threshold_subset_recovers_the_secret_and_fewer_do_not() {
+        let secret = [0xa5; 32];
+        let shares = split(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let got = combine(&[shares[a], shares[b], shares[c]]).unwrap();
+                    assert_eq!(*got, secret);
+                }
+            }
+        }
+        assert_ne!(*combine(&shares[..2]).unwrap(), secret);
+        // More than the threshold still lands on the same polynomial.
+        assert_eq!(*combine(&shares).unwrap(), secret);
+    }
+
+    #[test]
+    fn malformed_splits_and_share_sets_are_refused() {
+        let secret = [1; 32];
+        assert_eq!(split(&secret, 1, 3), Err(ShamirError::BadThreshold));
+        assert_eq!(split(&secret, 4, 3), Err(ShamirError::BadThreshold));
+        assert_eq!(split(&secret, 2, MAX_SHARES + 1), Err(ShamirError::TooManyShares));
+
+        let shares = split(&secret, 2, 3).unwrap();
+        assert_eq!(combine(&shares[..1]).unwrap_err(), ShamirError::TooFewShares);
+        assert_eq!(combine(&[shares[0], shares[0]]).unwrap_err(), ShamirError::DuplicateIndex);
+        assert_eq!(combine(&[(0, [0; 32]), shares[1]]).unwrap_err(), ShamirError::BadIndex);
+    }
+}
@@ -1,13 +1,36 @@
-/// Client ↔ relay protocol version, mixed into the relay-auth handshake and
-/// every relay-verified signing transcript.
+/// Newest client ↔ relay protocol version this build speaks.
 ///
-/// A bump is a flag day for both sides at once: the relay verifies transcripts
-/// against its own copy, so a client on either side of a change is refused. Move
-/// it only alongside a relay deploy, and prefer moving it to letting a shape
-/// change fail as an unexplained signature error.
+/// Each side advertises the range `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`
+/// in the ALPN, and again in the client and relay hellos once the ALPN has
+/// settled past 6 (a v6 session keeps v6's hellos, the only ones a relay
+/// from before negotiation reads). The two settle on [`negotiate_version`]'s
+/// pick. Transcripts bound to that
+/// session — the relay-auth proof and the `DhtHello` signature — are built
+/// at the settled version, so a relay can move forward while phones a
+/// version behind still connect.
+///
+/// Everything else signed outside a session (dispatches, tokens, records a
+/// third party re-verifies) is built at [`MIN_PROTOCOL_VERSION`], the one
+/// version every build still in the window speaks. A change to one of those
+/// shapes ships under a new `PROTOCOL_VERSION` and takes effect once
+/// `MIN_PROTOCOL_VERSION` reaches it.
 ///
 /// 6: `ActivityP` carries the conversation it happened in.
-pub static PROTOCOL_VERSION: u16 = 6;
+/// 7: dispatches carry their wake hint and delivery token; a v6 session or
+/// peer still gets `DispatchV6P`. Hellos advertise a version range.
+pub static PROTOCOL_VERSION: u16 = 7;
+
+/// Oldest protocol version still accepted. Raising it ends the deprecation
+/// window for everything older: those builds are refused at the handshake
+/// with a reason that tells the user to update.
+pub static MIN_PROTOCOL_VERSION: u16 = 6;
+
+/// The highest version both we and a peer advertising `peer_min..=peer_max`
+/// speak, or `None` if the ranges don't overlap.
+pub fn negotiate_version(peer_min: u16, peer_max: u16) -> Option<u16> {
+    let version = peer_max.min(PROTOCOL_VERSION);
+    (version >= peer_min.max(MIN_PROTOCOL_VERSION)).then_some(version)
+}
 
 #[cfg(feature = "crypto")]
 pub mod crypto;
@@ -33,3 +56,21 @@ pub mod server;
 
 #[cfg(feature = "types")]
 pub mod types;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiation_settles_on_the_highest_common_version() {
+        let ours = negotiate_version(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+        assert_eq!(ours, Some(PROTOCOL_VERSION));
+        // A newer peer is met at our newest; an older one at theirs.
+        assert_eq!(negotiate_version(MIN_PROTOCOL_VERSION, u16::MAX), Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(0, MIN_PROTOCOL_VERSION), Some(MIN_PROTOCOL_VERSION));
+        // No overlap either way.
+        assert_eq!(negotiate_version(0, MIN_PROTOCOL_VERSION - 1), None);
+        assert_eq!(negotiate_version(PROTOCOL_VERSION + 1, u16::MAX), None);
+        assert_eq!(negotiate_version(PROTOCOL_VERSION, MIN_PROTOCOL_VERSION - 1), None);
+    }
+}
@@ -51,6 +51,11 @@ impl NetworkConfig {
 pub const DEFAULT_RESOLVER_PORT: u16 = 40433;
 pub const DEFAULT_RELAY_PORT: u16 = 40432;
 pub const DEFAULT_GATEWAY_PORT: u16 = 40434;
+/// TCP port of a relay's tunnel listener (QUIC over TLS/WebSocket for
+/// UDP-hostile networks). 443 so it passes where only HTTPS does. Relays
+/// advertise the port they actually bind through the resolver; clients fall
+/// back to this one for a relay that hasn't.
+pub const DEFAULT_TUNNEL_PORT: u16 = 443;
 
 /// A `host[:port]` from config — either a literal IP or a DNS name, with an
 /// optional port. Unlike [`SocketAddr`] it accepts hostnames; the name is
@@ -0,0 +1,130 @@
+//! Relay blob store wire types: client-encrypted, content-addressed
+//! attachment bodies held by relays whose cert carries `BLOB_STORE`.
+//!
+//! The relay never sees plaintext or the attachment's `file_id`. The client
+//! encrypts each chunk under a random per-file key, and the blob is named by
+//! [`BlobManifest::blob_id`] over the *ciphertext* chunk hashes, so the relay
+//! can verify every chunk it stores without being able to read one. The key
+//! and the blob's location reach recipients inside the MLS-encrypted
+//! `AppPayload::FileBlob`.
+//!
+//! Uploads are authorized by the connection-authenticated IPK and charged
+//! against that IPK's quota until the blob expires. Fetches need only the
+//! `blob_id`: knowing it already means holding the message that named it.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::proto::pack::bounded_vec;
+use crate::types::bytes::Bytes;
+
+/// Domain tag for [`BlobManifest::blob_id`].
+const BLOB_ID_DOMAIN: &[u8] = b"promtuz/blob/manifest";
+
+/// Largest chunk a relay stores. A 256 KiB plaintext chunk plus its AEAD tag,
+/// with headroom; comfortably inside one `MAX_FRAME_BYTES` frame.
+pub const MAX_BLOB_CHUNK_BYTES: u32 = 512 * 1024;
+
+/// Chunks per blob: 1 GiB at the 256 KiB chunks libcore uses.
+pub const MAX_BLOB_CHUNKS: usize = 4096;
+
+/// Hash of one ciphertext chunk, as listed in [`BlobManifest::chunks`].
+pub fn chunk_hash(data: &[u8]) -> Bytes<32> {
+    Bytes(*blake3::hash(data).as_bytes())
+}
+
+/// The ciphertext layout of one blob. `chunks[i]` is the BLAKE3 hash of
+/// ciphertext chunk `i`; every chunk but the last is exactly `chunk_size`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobManifest {
+    pub total_size: u64,
+    pub chunk_size: u32,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_BLOB_CHUNKS>")]
+    pub chunks:     Vec<Bytes<32>>,
+}
+
+impl BlobManifest {
+    /// Content address of the blob: a hash over the manifest, so the id
+    /// commits to every chunk hash and the sizes.
+    pub fn blob_id(&self) -> [u8; 32] {
+        let mut h = blake3::Hasher::new();
+        h.update(BLOB_ID_DOMAIN);
+        h.update(&self.total_size.to_le_bytes());
+        h.update(&self.chunk_size.to_le_bytes());
+        for c in &self.chunks {
+            h.update(&c.0);
+        }
+        *h.finalize().as_bytes()
+    }
+
+    /// Sizes agree with the chunk count and sit within the store's bounds.
+    pub fn is_well_formed(&self) -> bool {
+        self.chunk_size > 0
+            && self.chunk_size <= MAX_BLOB_CHUNK_BYTES
+            && !self.chunks.is_empty()
+            && self.chunks.len() as u64 == self.total_size.div_ceil(self.chunk_size as u64)
+    }
+
+    /// Byte length of chunk `index`, or `None` past the end. Assumes
+    /// [`Self::is_well_formed`].
+    pub fn chunk_len(&self, index: u32) -> Option<usize> {
+        if index as usize >= self.chunks.len() {
+            return None;
+        }
+        let start = index as u64 * self.chunk_size as u64;
+        Some((self.total_size - start).min(self.chunk_size as u64) as usize)
+    }
+
+    /// Whether `data` is chunk `index` of this blob, by length and hash.
+    pub fn verifies(&self, index: u32, data: &[u8]) -> bool {
+        self.chunk_len(index) == Some(data.len())
+            && chunk_hash(data) == self.chunks[index as usize]
+    }
+}
+
+/// Answer to `CRelayPacket::BlobBegin`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlobBeginOutcome {
+    /// Upload these chunks; empty when the relay already holds them all.
+    Accepted {
+        #[serde(deserialize_with = "bounded_vec::<_, _, MAX_BLOB_CHUNKS>")]
+        missing:    Vec<u32>,
+        expires_at: u64,
+    },
+    /// The uploader's live blobs plus this one would exceed its quota.
+    QuotaExceeded,
+    /// Malformed manifest, or larger than this relay accepts.
+    Rejected,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::pack::Packer;
+    use crate::proto::pack::Unpacker;
+
+    fn manifest(parts: &[&[u8]], chunk_size: u32) -> BlobManifest {
+        BlobManifest {
+            total_size: parts.iter().map(|p| p.len() as u64).sum(),
+            chunk_size,
+            chunks: parts.iter().map(|p| chunk_hash(p)).collect(),
+        }
+    }
+
+    #[test]
+    fn manifest_verifies_chunks_and_names_its_content() {
+        let m = manifest(&[&[1u8; 8], &[2u8; 8], &[3u8; 3]], 8);
+        assert!(m.is_well_formed());
+        assert!(m.verifies(2, &[3u8; 3]));
+        assert!(!m.verifies(2, &[3u8; 8]), "short last chunk must be exact");
+        assert!(!m.verifies(1, &[1u8; 8]));
+        assert!(!m.verifies(3, &[]));
+
+        let other = manifest(&[&[1u8; 8], &[2u8; 8], &[4u8; 3]], 8);
+        assert_ne!(m.blob_id(), other.blob_id());
+
+        let lying = BlobManifest { total_size: 100, ..m.clone() };
+        assert!(!lying.is_well_formed());
+        assert_eq!(BlobManifest::deser(&m.ser().unwrap()).unwrap(), m);
+    }
+}
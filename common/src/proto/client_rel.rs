@@ -5,7 +5,7 @@ use std::net::SocketAddr;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::PROTOCOL_VERSION;
+use crate::MIN_PROTOCOL_VERSION;
 use crate::proto::Sender;
 use crate::types::bytes::ByteVec;
 use crate::types::bytes::Bytes;
@@ -16,7 +16,7 @@ pub const DISPATCH_SIG_DOMAIN: &[u8] = b"promtuz-dispatch-v1";
 
 /// Build the canonical bytes signed/verified for a `DispatchP`.
 ///
-/// Layout: `DISPATCH_SIG_DOMAIN || PROTOCOL_VERSION_BE || to || from || id || payload`
+/// Layout: `DISPATCH_SIG_DOMAIN || MIN_PROTOCOL_VERSION_BE || to || from || id || payload`
 pub fn dispatch_sig_message(
     to: &[u8; 32], from: &[u8; 32], id: &[u8; 16], payload: &[u8],
 ) -> Vec<u8> {
@@ -24,7 +24,7 @@ pub fn dispatch_sig_message(
         DISPATCH_SIG_DOMAIN.len() + 2 + to.len() + from.len() + id.len() + payload.len(),
     );
     buf.extend_from_slice(DISPATCH_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(to);
     buf.extend_from_slice(from);
     buf.extend_from_slice(id);
@@ -32,6 +32,30 @@ pub fn dispatch_sig_message(
     buf
 }
 
+/// Domain separator for a [`DeliveryToken`] signature.
+pub const DELIVERY_TOKEN_DOMAIN: &[u8] = b"promtuz-delivery-token-v1";
+
+/// Furthest ahead a [`DeliveryToken`] may expire. Relays refuse tokens
+/// minted to outlive this, so a leaked token dies within a bounded window
+/// even if the recipient never rotates.
+pub const MAX_DELIVERY_TOKEN_LIFETIME_MS: u64 = 30 * 24 * 3_600_000;
+
+/// Build the canonical bytes a recipient signs to mint a [`DeliveryToken`].
+///
+/// Layout: `DELIVERY_TOKEN_DOMAIN || MIN_PROTOCOL_VERSION_BE || recipient || id
+///   || expires_at_ms_be`
+pub fn delivery_token_signing_input(
+    recipient: &[u8; 32], id: &[u8; 16], expires_at_ms: u64,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(DELIVERY_TOKEN_DOMAIN.len() + 2 + 32 + 16 + 8);
+    buf.extend_from_slice(DELIVERY_TOKEN_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(recipient);
+    buf.extend_from_slice(id);
+    buf.extend_from_slice(&expires_at_ms.to_be_bytes());
+    buf
+}
+
 //===:===:===:===:===:===:=:===:===:===:===:===:===||
 //===:===:===:===:==: HANDSHAKE :==:===:===:===:===||
 //===:===:===:===:===:===:=:===:===:===:===:===:===||
@@ -55,13 +79,39 @@ pub enum ServerHandshakeResultP {
     },
 }
 
+/// The one version a [`CHandshakePacket::Hello`] implies: builds from before
+/// version negotiation spoke exactly this.
+pub const LEGACY_HELLO_VERSION: u16 = 6;
+
+/// Canonical bytes a client signs to prove possession of its identity key,
+/// at the version the handshake settled on.
+///
+/// Layout: `b"relay-auth-v" || version (BE u16) || nonce`
+///
+/// TODO: bind the responder identity and the TLS exporter — as written, a relay
+/// can forward another relay's challenge and replay the answer. Ship it under a
+/// new version; negotiation keeps the old layout working for older builds.
+pub fn relay_auth_message(version: u16, nonce: &[u8; 32]) -> Vec<u8> {
+    [b"relay-auth-v" as &[u8], &version.to_be_bytes(), nonce].concat()
+}
+
 /// Client Handshake Packet
 ///
 /// Handshake initiates from Client
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum CHandshakePacket {
+    /// Pre-negotiation hello; implies [`LEGACY_HELLO_VERSION`] only and is
+    /// answered with [`SHandshakePacket::Challenge`]. Kept so builds from
+    /// before [`Self::HelloVersions`] connect until the window closes on it,
+    /// and sent by newer ones on a `client/6` session, since a relay that
+    /// offers nothing later reads nothing else.
     Hello { ipk: Bytes<32> },
     Proof { sig: Bytes<64> },
+    /// Hello advertising every protocol version the client speaks, on a
+    /// session past [`LEGACY_HELLO_VERSION`]. The relay picks the highest it
+    /// shares with [`crate::negotiate_version`] and names it in
+    /// [`SHandshakePacket::ChallengeAt`].
+    HelloVersions { ipk: Bytes<32>, min_version: u16, max_version: u16 },
 }
 
 /// Server Handshake Packet
@@ -71,6 +121,10 @@ pub enum CHandshakePacket {
 pub enum SHandshakePacket {
     Challenge { nonce: Bytes<32> },
     HandshakeResult(ServerHandshakeResultP),
+    /// Challenge to a [`CHandshakePacket::HelloVersions`], carrying the
+    /// settled version. The proof is signed at it, as is everything else
+    /// bound to this session.
+    ChallengeAt { nonce: Bytes<32>, version: u16 },
 }
 
 #[cfg(feature = "client")]
@@ -108,7 +162,7 @@ pub enum QueryResultP {
 ///
 /// `sig` covers (in order, no separators):
 ///   `b"promtuz-dispatch-v1"`
-///   || `PROTOCOL_VERSION.to_be_bytes()` (u16, big-endian)
+///   || `MIN_PROTOCOL_VERSION.to_be_bytes()` (u16, big-endian)
 ///   || `to`      (32 bytes)
 ///   || `from`    (32 bytes)
 ///   || `id`      (16 bytes — UUIDv7 minted by the *sender*)
@@ -136,6 +190,183 @@ pub struct DispatchP {
     /// content (text/reply/welcome) that should push-wake an offline peer.
     /// Receipts/edits/deletes/reactions/pair-acks set false — queued, never woken.
     pub wake: bool,
+    /// Postcard [`crate::proto::push::SealedWakeHint`] for the recipient's
+    /// device, passed through as the wake payload. Opaque to relays, outside
+    /// `sig`, and dropped at ingress unless `wake` is set and it fits
+    /// [`crate::proto::push::MAX_WAKE_HINT_BYTES`]. Lost on any hop that
+    /// speaks only [`DispatchV6P`].
+    pub wake_hint: Option<ByteVec>,
+    /// Set on a sealed-sender dispatch, `None` otherwise. `from`/`sig` are
+    /// then a one-shot key the sender minted for this dispatch, `payload` is
+    /// an [`crate::proto::mls_wire::MlsEnvelopeP::Sealed`] only `to` can
+    /// open, and this token — issued by `to` — is what authorizes the
+    /// dispatch in place of the session binding. Appended last (postcard).
+    pub token:     Option<DeliveryToken>,
+}
+
+/// First protocol version whose sessions carry a [`DispatchP`] whole. Below
+/// it a dispatch travels as [`DispatchV6P`].
+pub const DISPATCH_V7_VERSION: u16 = 7;
+
+/// A [`DispatchP`] as protocol 6 lays it out: no `wake_hint`, no `token`.
+/// Every packet and queue row that held a dispatch before 7 still holds this
+/// shape, so peers a version behind and rows written before the upgrade
+/// decode unchanged; the whole dispatch rides variants appended for 7.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct DispatchV6P {
+    pub to:             Bytes<32>,
+    pub from:           Bytes<32>,
+    pub id:             Bytes<16>,
+    pub payload:        ByteVec,
+    pub sig:            Bytes<64>,
+    pub accepted_at_ms: u64,
+    pub wake:           bool,
+}
+
+impl From<DispatchV6P> for DispatchP {
+    fn from(d: DispatchV6P) -> Self {
+        Self {
+            to:             d.to,
+            from:           d.from,
+            id:             d.id,
+            payload:        d.payload,
+            sig:            d.sig,
+            accepted_at_ms: d.accepted_at_ms,
+            wake:           d.wake,
+            wake_hint:      None,
+            token:          None,
+        }
+    }
+}
+
+/// Serde adapter writing a [`DispatchP`] in the [`DispatchV6P`] layout, for
+/// the wire shapes protocol 6 already had. The wake hint is dropped, which
+/// only costs the recipient a contentless wake. A sealed dispatch refuses to
+/// serialize: a v6 relay would check its one-shot `sig` without the token
+/// and drop it for good.
+pub mod dispatch_v6 {
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serialize;
+    use serde::Serializer;
+    use serde::ser::Error;
+
+    use super::DispatchP;
+    use super::DispatchV6P;
+
+    pub fn serialize<S: Serializer>(d: &DispatchP, s: S) -> Result<S::Ok, S::Error> {
+        if d.token.is_some() {
+            return Err(S::Error::custom("a sealed dispatch has no v6 layout"));
+        }
+        // Postcard lays a tuple out exactly as the struct it mirrors.
+        (&d.to, &d.from, &d.id, &d.payload, &d.sig, d.accepted_at_ms, d.wake).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<DispatchP, D::Error> {
+        DispatchV6P::deserialize(de).map(Into::into)
+    }
+
+    /// Borrowing wrapper for shapes that hold a dispatch among other fields.
+    pub struct V6<'a>(pub &'a DispatchP);
+
+    impl Serialize for V6<'_> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            serialize(self.0, s)
+        }
+    }
+}
+
+impl DispatchP {
+    /// Decode a stored dispatch: a row written whole, or one written in the
+    /// v6 layout before the relay upgraded. The whole layout extends the v6
+    /// one, so a v6 row always runs out of bytes when read whole and the
+    /// fallback never misreads a whole row.
+    pub fn deser_stored(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes::<Self>(bytes)
+            .or_else(|_| postcard::from_bytes::<DispatchV6P>(bytes).map(Into::into))
+    }
+
+    /// Whose share of the recipient's queue this dispatch counts against:
+    /// the sender for an identified dispatch, the token for a sealed one. A
+    /// sealed `from` is fresh on every attempt, so it can name no one — the
+    /// token is the only stable handle a relay has, and it is per contact and
+    /// epoch.
+    pub fn quota_key(&self) -> [u8; 32] {
+        match &self.token {
+            Some(token) => token.quota_key(),
+            None => self.from.0,
+        }
+    }
+}
+
+/// A recipient's permission for one contact to reach it without naming
+/// itself: the recipient's signature over
+/// [`delivery_token_signing_input`]. Relays check it against `to` alone, so
+/// it authorizes a sealed dispatch without saying who sent it.
+///
+/// `id` is random per contact and per rotation. Two dispatches with the same
+/// token come from the same contact, which is what relay quotas need, but the
+/// id is unrelated to the contact's IPK and changes when the token does.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct DeliveryToken {
+    pub id:            Bytes<16>,
+    pub expires_at_ms: u64,
+    pub sig:           Bytes<64>,
+}
+
+/// `blake3::derive_key` context for [`DeliveryToken::quota_key`].
+const DELIVERY_TOKEN_QUOTA_CONTEXT: &str = "promtuz delivery-token quota-key v1";
+
+impl DeliveryToken {
+    /// Per-token key for queue shares and rate limits, derived from the whole
+    /// token. A contact's token changes every epoch, so the key does too:
+    /// relays can tell two dispatches apart by contact only within one.
+    pub fn quota_key(&self) -> [u8; 32] {
+        let mut input = Vec::with_capacity(16 + 8 + 64);
+        input.extend_from_slice(&self.id.0);
+        input.extend_from_slice(&self.expires_at_ms.to_be_bytes());
+        input.extend_from_slice(&self.sig.0);
+        blake3::derive_key(DELIVERY_TOKEN_QUOTA_CONTEXT, &input)
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl DeliveryToken {
+    /// Mint a token for the contact known locally by `id`. `key` is the
+    /// recipient's identity key.
+    pub fn issue(key: &ed25519_dalek::SigningKey, id: [u8; 16], expires_at_ms: u64) -> Self {
+        use ed25519_dalek::Signer;
+        let recipient = key.verifying_key().to_bytes();
+        let sig = key.sign(&delivery_token_signing_input(&recipient, &id, expires_at_ms));
+        Self { id: Bytes(id), expires_at_ms, sig: Bytes(sig.to_bytes()) }
+    }
+
+    /// [`Self::issue`] for dispatches addressed to one of our mailboxes: the
+    /// token verifies under the mailbox id, which is what relays see as `to`.
+    pub fn issue_for_mailbox(
+        key: &crate::crypto::mailbox::MailboxKey, id: [u8; 16], expires_at_ms: u64,
+    ) -> Self {
+        let sig = key.sign(&delivery_token_signing_input(&key.id(), &id, expires_at_ms));
+        Self { id: Bytes(id), expires_at_ms, sig: Bytes(sig.to_bytes()) }
+    }
+
+    /// Whether `recipient` issued this token and it is live at `now_ms`:
+    /// unexpired, no further out than [`MAX_DELIVERY_TOKEN_LIFETIME_MS`], and
+    /// signed under `recipient`.
+    pub fn verify(&self, recipient: &[u8; 32], now_ms: u64) -> bool {
+        use ed25519_dalek::Signature;
+        use ed25519_dalek::VerifyingKey;
+        if self.expires_at_ms <= now_ms
+            || self.expires_at_ms - now_ms > MAX_DELIVERY_TOKEN_LIFETIME_MS
+        {
+            return false;
+        }
+        let Ok(vk) = VerifyingKey::from_bytes(recipient) else {
+            return false;
+        };
+        let input = delivery_token_signing_input(recipient, &self.id.0, self.expires_at_ms);
+        vk.verify_strict(&input, &Signature::from_bytes(&self.sig.0)).is_ok()
+    }
 }
 
 /// Relay → Client (relay-verified delivery)
@@ -190,14 +421,14 @@ pub struct ActivityP {
 }
 
 /// Canonical bytes signed/verified for an [`ActivityP`].
-/// Layout: `ACTIVITY_SIG_DOMAIN || PROTOCOL_VERSION_BE || to || from ||
+/// Layout: `ACTIVITY_SIG_DOMAIN || MIN_PROTOCOL_VERSION_BE || to || from ||
 /// conversation || activity_be || timestamp_be`
 pub fn activity_sig_message(
     to: &[u8; 32], from: &[u8; 32], conversation: &[u8; 16], activity: u16, timestamp: u64,
 ) -> Vec<u8> {
     let mut buf = Vec::with_capacity(ACTIVITY_SIG_DOMAIN.len() + 2 + 32 + 32 + 16 + 2 + 8);
     buf.extend_from_slice(ACTIVITY_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(to);
     buf.extend_from_slice(from);
     buf.extend_from_slice(conversation);
@@ -219,6 +450,26 @@ pub struct SubscribePresenceP {
     pub lease:    crate::proto::dht_p2p::PresenceLease,
 }
 
+/// Claim one of our mailbox ids on this connection: mail addressed to it is
+/// delivered here and drained alongside the IPK's queue. Everything in it is
+/// signed by the mailbox key, so the relay checks it against `mailbox` alone
+/// and never learns which IPK the mailbox belongs to from the records.
+///
+/// `sig` is over [`crate::proto::dht_p2p::queue_fetch_signing_input`] for
+/// `mailbox` and this relay, and doubles as the `DrainAuth` for fetching the
+/// mailbox's queue from its homes. `push`, `wake` and `lease` are the
+/// mailbox's own push pseudonym, wake policy and presence lease; each has
+/// `mailbox` as its user.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct OpenMailboxP {
+    pub mailbox:   Bytes<32>,
+    pub timestamp: u64,
+    pub sig:       Bytes<64>,
+    pub push:      Option<crate::proto::dht_p2p::PushPseudonymPublish>,
+    pub wake:      Option<crate::proto::dht_p2p::WakePolicyPublish>,
+    pub lease:     Option<crate::proto::dht_p2p::PresenceLease>,
+}
+
 /// A contact's presence, one state that can't self-contradict (no separate
 /// online bool alongside a timestamp). `Idle` is client-asserted (a
 /// backgrounded app sends `SetPresence(Idle)` as its last packet before
@@ -265,6 +516,8 @@ pub enum DispatchAckP {
     Delivered {
         accepted_at_ms: u64,
     },
+    /// The recipient deleted its account; nothing was stored, and no relay
+    /// will store anything for it again. The sender should stop sending.
     NotFound,
     InvalidSig,
     /// Recipient's per-user RocksDB queue is at capacity. Sender should back
@@ -287,6 +540,21 @@ pub enum DispatchAckP {
     Error {
         reason: String,
     },
+    /// The dispatch would take the recipient's queued bytes, or this
+    /// sender's share of them, past the relay's byte quota. Not stored.
+    /// Distinct from [`Self::QueueFull`] (a row-count cap) so the sender can
+    /// tell "you have sent this person too much" from "their inbox is full";
+    /// either way it should back off until the recipient drains.
+    QuotaExceeded,
+    /// A sealed dispatch whose [`DeliveryToken`] is expired, not issued by
+    /// `to`, or over its rate limit. Not stored. The sender should drop the
+    /// token and send identified until the recipient grants a fresh one.
+    /// Appended last (postcard).
+    TokenRejected,
+    /// A sealed dispatch no home would take: this relay has no DHT, or the
+    /// recipient's homes are too old to carry a token. Not stored. The token
+    /// is fine; the sender should send identified for a while. Appended last.
+    SealedUnroutable,
 }
 
 // // // // // // // // // // // // // // // // // //
@@ -308,7 +576,9 @@ pub enum DispatchAckP {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum CRelayPacket {
     Query(QueryP),
-    Dispatch(DispatchP),
+    /// A dispatch in the [`DispatchV6P`] layout, for a session below
+    /// [`DISPATCH_V7_VERSION`]. Unsealed, and without its wake hint.
+    Dispatch(#[serde(with = "dispatch_v6")] DispatchP),
 
     /// Fire-and-forget ephemeral signal (presence/typing). The relay routes it
     /// to the recipient if online and drops it otherwise; no reply.
@@ -479,6 +749,99 @@ pub enum CRelayPacket {
         timestamp: u64,
         sig:       Bytes<64>,
     },
+
+    /// Tell this home relay when it may wake us: quiet hours, mute-all, or an
+    /// allow-list of senders. Signed over
+    /// [`crate::proto::dht_p2p::wake_policy_signing_input`] so the relay can
+    /// replicate it to the other homes verbatim. Sent alongside
+    /// [`Self::RegisterPush`]; fire-and-forget. Appended last (postcard).
+    SetWakePolicy {
+        policy:    crate::proto::dht_p2p::WakePolicy,
+        timestamp: u64,
+        sig:       Bytes<64>,
+    },
+
+    /// Publish this device's owner-signed push key so senders can seal wake
+    /// hints to it. `user_ipk` must be the connection's IPK; the relay
+    /// replicates it to the homes. Fire-and-forget. Appended last (postcard).
+    PublishPushKey(crate::proto::dht_p2p::PushKeyRecord),
+
+    /// Look up a contact's push key. Reply: [`SRelayPacket::PushKey`].
+    /// Appended last (postcard).
+    FetchPushKey {
+        target_ipk: Bytes<32>,
+    },
+
+    /// [`Self::Dispatch`] whole, wake hint and token included, for a session at
+    /// [`DISPATCH_V7_VERSION`] or later. Same replies. Appended last.
+    DispatchV7(DispatchP),
+
+    /// Open (or resume) an upload to this relay's blob store. Charged to the
+    /// connection's IPK; `ttl_secs` is capped by the relay. Reply:
+    /// [`SRelayPacket::BlobBegun`], or [`SRelayPacket::BlobUnavailable`] when
+    /// this relay lacks `BLOB_STORE`. Appended last (postcard).
+    BlobBegin {
+        manifest: crate::proto::blob::BlobManifest,
+        ttl_secs: u64,
+    },
+
+    /// One ciphertext chunk of a begun blob; the relay checks it against the
+    /// manifest hash. Reply: [`SRelayPacket::BlobStored`]. Appended last.
+    BlobPut {
+        blob_id: Bytes<32>,
+        index:   u32,
+        data:    ByteVec,
+    },
+
+    /// Fetch a blob's manifest from the relay `host` that stores it, through
+    /// this relay. Reply: [`SRelayPacket::BlobManifest`]. Appended last.
+    FetchBlobManifest {
+        host:    crate::quic::id::NodeId,
+        blob_id: Bytes<32>,
+    },
+
+    /// Fetch one ciphertext chunk, as [`Self::FetchBlobManifest`]. Reply:
+    /// [`SRelayPacket::BlobChunk`]. Appended last.
+    FetchBlobChunk {
+        host:    crate::quic::id::NodeId,
+        blob_id: Bytes<32>,
+        index:   u32,
+    },
+
+    /// Claim a mailbox id on this connection; see [`OpenMailboxP`]. Sent for
+    /// each open mailbox before `DrainQueue`, and again with each presence
+    /// renewal. Fire-and-forget. Appended last (postcard).
+    OpenMailbox(OpenMailboxP),
+
+    /// Announce that this connection's IPK succeeded an older one. `new_ipk`
+    /// must be the connection's IPK; the relay sends it to the old key's
+    /// homes, which hand its queue over. Fire-and-forget. Appended last.
+    PublishSuccession(crate::proto::dht_p2p::IdentitySuccession),
+
+    /// Delete this connection's identity from the network. `ipk` must be the
+    /// connection's IPK; the relay wipes what it holds for it and sends the
+    /// statement to every home. Reply: [`SRelayPacket::AccountDeleted`].
+    /// Appended last.
+    DeleteAccount(crate::proto::dht_p2p::AccountDeletion),
+
+    /// Withdraw delivery tokens this connection's owner issued. Signed by the
+    /// key they verify under, which may be a mailbox rather than the
+    /// connection's IPK; the relay checks it and sends it to that key's homes.
+    /// Fire-and-forget. Appended last.
+    RevokeTokens(crate::proto::dht_p2p::TokenRevocation),
+
+    /// Delete a key this connection's identity rotated away from, sent before
+    /// [`Self::DeleteAccount`]. The chain must end at the connection's IPK; the
+    /// relay sends it to the old key's homes. Reply:
+    /// [`SRelayPacket::AccountDeleted`]. Appended last.
+    DeletePredecessor(crate::proto::dht_p2p::PredecessorDeletion),
+
+    /// Delete one of this connection's owner's mailboxes, sent before
+    /// [`Self::DeleteAccount`]. Signed by the mailbox key, which is not the
+    /// connection's IPK; the relay sends it to the mailbox's homes, which
+    /// tombstone the id as they would the IPK. Reply:
+    /// [`SRelayPacket::AccountDeleted`]. Appended last.
+    DeleteMailbox(crate::proto::dht_p2p::AccountDeletion),
 }
 
 /// Server Relay Packet
@@ -584,6 +947,62 @@ pub enum SRelayPacket {
     /// deltas as contacts connect/disconnect. Appended last for postcard
     /// wire-compat (see [`CRelayPacket::SubscribePresence`]).
     Presence(Vec<PresenceP>),
+
+    /// Reply to [`CRelayPacket::FetchPushKey`]; `record` is `None` when no
+    /// home holds one. The client checks `user_sig` itself. Appended last.
+    PushKey {
+        target_ipk: Bytes<32>,
+        record:     Option<crate::proto::dht_p2p::PushKeyRecord>,
+    },
+
+    /// Reply to [`CRelayPacket::BlobBegin`]. Appended last.
+    BlobBegun {
+        blob_id: Bytes<32>,
+        outcome: crate::proto::blob::BlobBeginOutcome,
+    },
+
+    /// Reply to [`CRelayPacket::BlobPut`]; `stored = false` when the chunk
+    /// failed its hash or the blob is unknown or expired. Appended last.
+    BlobStored {
+        blob_id: Bytes<32>,
+        index:   u32,
+        stored:  bool,
+    },
+
+    /// Reply to [`CRelayPacket::FetchBlobManifest`]; `None` when the host is
+    /// unreachable or no longer holds the blob. Appended last.
+    BlobManifest {
+        blob_id:  Bytes<32>,
+        manifest: Option<crate::proto::blob::BlobManifest>,
+    },
+
+    /// Reply to [`CRelayPacket::FetchBlobChunk`]. Appended last.
+    BlobChunk {
+        blob_id: Bytes<32>,
+        index:   u32,
+        data:    Option<ByteVec>,
+    },
+
+    /// This relay does not carry `BLOB_STORE`. Appended last.
+    BlobUnavailable,
+
+    /// [`Self::AckAuthRequest`] for mail drained from one of our mailboxes:
+    /// the ack transcript names `mailbox` in place of the IPK and is signed
+    /// with that mailbox's key. Appended last (postcard).
+    MailboxAckAuthRequest {
+        mailbox:             Bytes<32>,
+        requester_relay_id:  crate::quic::id::NodeId,
+        delivered_ids:       Vec<[u8; 16]>,
+        suggested_timestamp: u64,
+    },
+
+    /// Reply to [`CRelayPacket::DeleteAccount`],
+    /// [`CRelayPacket::DeletePredecessor`] and [`CRelayPacket::DeleteMailbox`]:
+    /// how many of the key's homes wiped it, this relay included when it is
+    /// one. Appended last.
+    AccountDeleted {
+        homes: u32,
+    },
 }
 
 #[cfg(feature = "client")]
@@ -607,6 +1026,55 @@ mod tests {
     use crate::proto::pack::Packer;
     use crate::proto::pack::Unpacker;
 
+    /// A token authorizes dispatches to its issuer and nobody else, only while
+    /// live, and never for longer than relays are willing to honour.
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn a_delivery_token_is_bound_to_its_issuer_and_lifetime() {
+        use super::DeliveryToken;
+        use super::MAX_DELIVERY_TOKEN_LIFETIME_MS;
+
+        let issuer = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let other = ed25519_dalek::SigningKey::from_bytes(&[6u8; 32]);
+        let to = issuer.verifying_key().to_bytes();
+        let now = 1_700_000_000_000;
+
+        let token = DeliveryToken::issue(&issuer, [7u8; 16], now + 60_000);
+        assert!(token.verify(&to, now));
+        assert!(!token.verify(&other.verifying_key().to_bytes(), now), "another recipient");
+        assert!(!token.verify(&to, now + 60_000), "expired");
+
+        let too_far = now + MAX_DELIVERY_TOKEN_LIFETIME_MS + 1;
+        let forever = DeliveryToken::issue(&issuer, [7u8; 16], too_far);
+        assert!(!forever.verify(&to, now), "outlives what relays accept");
+
+        let mut forged = token.clone();
+        forged.expires_at_ms += 1;
+        assert!(!forged.verify(&to, now), "expiry is signed");
+
+        // Two contacts' tokens share the quota key of neither.
+        let second = DeliveryToken::issue(&issuer, [8u8; 16], now + 60_000);
+        assert_ne!(token.quota_key(), second.quota_key());
+    }
+
+    /// A token for a mailbox authorizes dispatches to that mailbox only — not
+    /// to the IPK behind it, which relays must not be able to connect it to.
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn a_mailbox_token_verifies_under_the_mailbox_alone() {
+        use super::DeliveryToken;
+        use crate::crypto::mailbox::MailboxKey;
+        use crate::crypto::mailbox::seed_for;
+
+        let owner = ed25519_dalek::SigningKey::from_bytes(&[5u8; 32]);
+        let mailbox = MailboxKey::derive(&owner, &seed_for(&owner), 2_900);
+        let now = 1_700_000_000_000;
+
+        let token = DeliveryToken::issue_for_mailbox(&mailbox, [7u8; 16], now + 60_000);
+        assert!(token.verify(&mailbox.id(), now));
+        assert!(!token.verify(&owner.verifying_key().to_bytes(), now));
+    }
+
     /// The conversation is inside the transcript, not merely beside it — so a
     /// relay cannot take a signal from one of the recipient's chats and present
     /// it as another. Two otherwise-identical signals must sign differently.
@@ -624,6 +1092,75 @@ mod tests {
         assert_ne!(a, b, "moving a signal between chats must invalidate its signature");
     }
 
+    /// Builds from before negotiation send `Hello` and expect `Challenge`, so
+    /// those keep their variant indices and bytes; the ranged packets are
+    /// appended after them.
+    #[test]
+    fn handshake_packets_stay_readable_by_pre_negotiation_builds() {
+        use super::CHandshakePacket;
+        use super::SHandshakePacket;
+
+        let legacy = CHandshakePacket::Hello { ipk: Bytes([1; 32]) }.ser().unwrap();
+        assert_eq!(legacy[0], 0);
+        assert!(legacy.ends_with(&[1; 32]));
+        let ranged =
+            CHandshakePacket::HelloVersions { ipk: Bytes([1; 32]), min_version: 6, max_version: 7 };
+        assert_eq!(ranged.ser().unwrap()[0], 2);
+        assert_eq!(CHandshakePacket::deser(&ranged.ser().unwrap()).unwrap(), ranged);
+
+        let challenge = SHandshakePacket::Challenge { nonce: Bytes([2; 32]) }.ser().unwrap();
+        assert_eq!(challenge[0], 0);
+        let at = SHandshakePacket::ChallengeAt { nonce: Bytes([2; 32]), version: 7 };
+        assert_eq!(at.ser().unwrap()[0], 2);
+        assert_eq!(SHandshakePacket::deser(&at.ser().unwrap()).unwrap(), at);
+    }
+
+    /// A v6 relay reads `Dispatch` as the old struct, and a queue row from
+    /// before the upgrade holds that struct bare. Both must keep decoding,
+    /// and a sealed dispatch must never be squeezed into the old shape.
+    #[test]
+    fn dispatches_keep_the_v6_layout_where_protocol_6_had_them() {
+        use super::CRelayPacket;
+        use super::DeliveryToken;
+        use super::DispatchP;
+        use super::DispatchV6P;
+        use crate::types::bytes::ByteVec;
+
+        #[derive(serde::Serialize)]
+        enum V6Packet {
+            _Query(super::QueryP),
+            Dispatch(DispatchV6P),
+        }
+
+        let old = DispatchV6P {
+            to:             Bytes([1; 32]),
+            from:           Bytes([2; 32]),
+            id:             Bytes([3; 16]),
+            payload:        ByteVec(vec![4; 8]),
+            sig:            Bytes([5; 64]),
+            accepted_at_ms: 6,
+            wake:           true,
+        };
+        let mut whole = DispatchP::from(old.clone());
+        whole.wake_hint = Some(ByteVec(vec![7; 4]));
+
+        let framed = CRelayPacket::Dispatch(whole.clone()).ser().unwrap();
+        assert_eq!(framed, V6Packet::Dispatch(old.clone()).ser().unwrap());
+        let CRelayPacket::Dispatch(read) = CRelayPacket::deser(&framed).unwrap() else {
+            panic!("variant index moved");
+        };
+        assert_eq!(read, DispatchP::from(old.clone()), "the hint stays behind");
+        let v7 = CRelayPacket::DispatchV7(whole.clone());
+        assert_eq!(CRelayPacket::deser(&v7.ser().unwrap()).unwrap(), v7);
+
+        assert_eq!(DispatchP::deser_stored(&old.ser().unwrap()).unwrap(), old.into());
+        assert_eq!(DispatchP::deser_stored(&whole.ser().unwrap()).unwrap(), whole);
+
+        whole.token =
+            Some(DeliveryToken { id: Bytes([8; 16]), expires_at_ms: 9, sig: Bytes([10; 64]) });
+        assert!(CRelayPacket::Dispatch(whole).ser().is_err());
+    }
+
     /// Postcard round-trip every Tier-1 wrapper request variant plus the
     /// sticky-home auth packets (`DrainAuth`, `AckAuth`). One catch-all
     /// test: a missing serde derive on any of these variants surfaces here.
@@ -741,6 +1278,12 @@ mod tests {
                 delivered_ids:       vec![[0xAA; 16], [0xBB; 16], [0xCC; 16]],
                 suggested_timestamp: 1_700_000_000_003,
             },
+            SRelayPacket::MailboxAckAuthRequest {
+                mailbox:             Bytes([0x43; 32]),
+                requester_relay_id:  NodeId::new([0x42u8; 32]),
+                delivered_ids:       vec![[0xDD; 16]],
+                suggested_timestamp: 1_700_000_000_004,
+            },
             SRelayPacket::DhtUnavailable,
         ] {
             let bytes = pkt.ser().expect("postcard ser");
@@ -1,12 +1,16 @@
 //! Client to Resolver Proto
 
+use std::net::IpAddr;
 use std::net::SocketAddr;
 
 use serde::Deserialize;
 use serde::Serialize;
 use serde_with::serde_as;
+use thiserror::Error;
 
+use crate::MIN_PROTOCOL_VERSION;
 use crate::proto::RelayId;
+use crate::proto::pack::bounded_vec;
 use crate::types::bytes::Bytes;
 
 /// Hard cap on the *combined* number of relay descriptors a single
@@ -21,6 +25,25 @@ use crate::types::bytes::Bytes;
 /// per-request work stays trivial.
 pub const MAX_BOOTSTRAP_RESULTS: u8 = 32;
 
+/// Domain separation tag for the [`RelayDirectory`] signing transcript.
+pub const RELAY_DIRECTORY_SIG_DOMAIN: &[u8] = b"promtuz-relay-directory-v1";
+
+/// Upper bound on [`RelayDirectory::relays`]. Matches the resolver's
+/// registry cap (`MAX_RELAYS`), so a full registry always fits one
+/// directory and a forged oversized one is refused while it is decoded.
+pub const MAX_DIRECTORY_RELAYS: usize = 1024;
+
+/// How long a signed directory stays usable after the resolver issues it.
+///
+/// Long on purpose: a cached or contact-shared directory is the fallback for
+/// when no resolver is reachable, and a week-old relay list still reaches
+/// most of the network. Relays that moved in the meantime just fail to dial.
+pub const RELAY_DIRECTORY_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// How far a directory's `issued_at_ms` may sit in the verifier's future
+/// before it is treated as forged or as a badly skewed resolver clock.
+pub const MAX_DIRECTORY_FUTURE_SKEW_MS: u64 = 60_000;
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RelayDescriptor {
@@ -72,6 +95,30 @@ pub enum ClientRequest {
     /// wake, verifying the `PUSH_GATEWAY` capability on the gateway's cert at
     /// dial. Appended last (postcard variant order).
     GetGateways(),
+
+    /// Fetch the relay set as a resolver-signed [`RelayDirectory`]. Auth:
+    /// none. Same content as [`Self::GetRelays`], but the reply stays
+    /// verifiable after it leaves the TLS session, so a client can cache it
+    /// or take one from a contact. Appended last (postcard variant order).
+    GetDirectory(),
+
+    /// Fetch the tunnel port of every relay that advertises one (see
+    /// [`TunnelDescriptor`]). Auth: none. A resolver that predates this
+    /// drops the stream, which a client reads as "no tunnels known".
+    /// Appended last (postcard variant order).
+    GetTunnels(),
+}
+
+/// Where a relay's TLS tunnel listener is, for clients whose network blocks
+/// UDP. The host is the relay's [`RelayDescriptor::addr`]; only the port is
+/// its own, since the listener is configured separately from QUIC.
+///
+/// Carried unsigned: a wrong port costs a failed dial, and the QUIC session
+/// inside the tunnel still authenticates the relay.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TunnelDescriptor {
+    pub id:   RelayId,
+    pub port: u16,
 }
 
 /// A push gateway's directory entry — same wire shape as [`RelayDescriptor`]
@@ -98,6 +145,170 @@ pub enum ClientResponse {
     },
     /// Resolver's response to [`ClientRequest::GetGateways`].
     GetGateways { gateways: Vec<GatewayDescriptor> },
+    /// Resolver's response to [`ClientRequest::GetDirectory`].
+    GetDirectory { directory: RelayDirectory },
+    /// Resolver's response to [`ClientRequest::GetTunnels`].
+    GetTunnels {
+        #[serde(deserialize_with = "bounded_vec::<_, _, MAX_DIRECTORY_RELAYS>")]
+        tunnels: Vec<TunnelDescriptor>,
+    },
+}
+
+//===:===:===:===:===:===:=:===:===:===:===:===:===||
+//===:===:===:===:==: DIRECTORY :==:===:===:===:===||
+//===:===:===:===:===:===:=:===:===:===:===:===:===||
+
+/// A resolver-signed snapshot of the relay registry.
+///
+/// `GetRelays` is only as trustworthy as the TLS session it arrived on. The
+/// directory carries its own signature under the resolver's identity key, so
+/// it can be persisted, handed from one device to another, and re-verified
+/// against the resolver keys the client ships with, long after that session
+/// is gone.
+///
+/// `issued_at_ms` doubles as the version: of two directories from resolvers
+/// the client trusts, the later-issued one wins.
+///
+/// `sig` is an Ed25519 signature under `resolver_key` over
+/// [`relay_directory_signing_input`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayDirectory {
+    /// Issuing resolver's Ed25519 identity key. Must be one of the keys the
+    /// verifier pins; carried so it knows which one to check against.
+    pub resolver_key:  Bytes<32>,
+    /// Resolver wall clock at signing, unix ms.
+    pub issued_at_ms:  u64,
+    /// Past this, the directory is refused. `issued_at_ms` plus
+    /// [`RELAY_DIRECTORY_TTL_MS`] on resolvers running this code.
+    pub expires_at_ms: u64,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_DIRECTORY_RELAYS>")]
+    pub relays:        Vec<RelayDescriptor>,
+    pub sig:           Bytes<64>,
+}
+
+/// Canonical bytes signed/verified for a [`RelayDirectory`].
+///
+/// Layout:
+/// ```text
+///   RELAY_DIRECTORY_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
+///     || resolver_key (32) || issued_at_ms (BE u64) || expires_at_ms (BE u64)
+///     || count (BE u32)
+///     || per relay: id (32) || ip_tag (1: 4 | 6) || ip (4 | 16) || port (BE u16) || pubkey (32)
+/// ```
+///
+/// Addresses are written as raw octets rather than through postcard or
+/// `Display`, so the transcript stays byte-stable whatever the wire codec does.
+pub fn relay_directory_signing_input(
+    resolver_key: &[u8; 32], issued_at_ms: u64, expires_at_ms: u64, relays: &[RelayDescriptor],
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(
+        RELAY_DIRECTORY_SIG_DOMAIN.len() + 2 + 32 + 8 + 8 + 4 + relays.len() * (32 + 17 + 2 + 32),
+    );
+    buf.extend_from_slice(RELAY_DIRECTORY_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(resolver_key);
+    buf.extend_from_slice(&issued_at_ms.to_be_bytes());
+    buf.extend_from_slice(&expires_at_ms.to_be_bytes());
+    buf.extend_from_slice(&(relays.len() as u32).to_be_bytes());
+    for relay in relays {
+        buf.extend_from_slice(relay.id.as_bytes());
+        match relay.addr.ip() {
+            IpAddr::V4(ip) => {
+                buf.push(4);
+                buf.extend_from_slice(&ip.octets());
+            },
+            IpAddr::V6(ip) => {
+                buf.push(6);
+                buf.extend_from_slice(&ip.octets());
+            },
+        }
+        buf.extend_from_slice(&relay.addr.port().to_be_bytes());
+        buf.extend_from_slice(&relay.pubkey.0);
+    }
+    buf
+}
+
+/// Reasons a [`RelayDirectory`] is refused.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DirectoryVerifyError {
+    /// `resolver_key` is not one of the keys the verifier trusts.
+    #[error("directory: signed by an untrusted resolver")]
+    UntrustedResolver,
+    /// `resolver_key` did not parse as an Ed25519 verifying key.
+    #[error("directory: malformed resolver key")]
+    MalformedKey,
+    /// `sig` did not validate over the canonical transcript.
+    #[error("directory: bad signature")]
+    BadSignature,
+    /// `expires_at_ms` has passed, or precedes `issued_at_ms`.
+    #[error("directory: expired")]
+    Expired,
+    /// `issued_at_ms` is more than [`MAX_DIRECTORY_FUTURE_SKEW_MS`] ahead.
+    #[error("directory: issued in the future (clock skew)")]
+    FutureIssued,
+}
+
+#[cfg(feature = "crypto")]
+mod verify_impl {
+    use ed25519_dalek::Signature;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+    use ed25519_dalek::VerifyingKey;
+
+    use super::DirectoryVerifyError;
+    use super::MAX_DIRECTORY_FUTURE_SKEW_MS;
+    use super::RELAY_DIRECTORY_TTL_MS;
+    use super::RelayDescriptor;
+    use super::RelayDirectory;
+    use super::relay_directory_signing_input;
+
+    impl RelayDirectory {
+        /// Sign `relays` as a directory issued at `now_ms`, valid for
+        /// [`RELAY_DIRECTORY_TTL_MS`].
+        pub fn sign(key: &SigningKey, relays: Vec<RelayDescriptor>, now_ms: u64) -> Self {
+            let resolver_key = key.verifying_key().to_bytes();
+            let expires_at_ms = now_ms.saturating_add(RELAY_DIRECTORY_TTL_MS);
+            let msg = relay_directory_signing_input(&resolver_key, now_ms, expires_at_ms, &relays);
+            Self {
+                resolver_key: resolver_key.into(),
+                issued_at_ms: now_ms,
+                expires_at_ms,
+                relays,
+                sig: key.sign(&msg).to_bytes().into(),
+            }
+        }
+
+        /// Check the directory against the pinned resolver keys in `trusted`
+        /// and the wall clock `now_ms`.
+        ///
+        /// Trust is checked before the signature so an unknown signer costs
+        /// a slice scan, not a curve operation.
+        pub fn verify(&self, trusted: &[[u8; 32]], now_ms: u64) -> Result<(), DirectoryVerifyError> {
+            if !trusted.contains(&self.resolver_key.0) {
+                return Err(DirectoryVerifyError::UntrustedResolver);
+            }
+
+            let vk = VerifyingKey::from_bytes(&self.resolver_key.0)
+                .map_err(|_| DirectoryVerifyError::MalformedKey)?;
+            let msg = relay_directory_signing_input(
+                &self.resolver_key.0,
+                self.issued_at_ms,
+                self.expires_at_ms,
+                &self.relays,
+            );
+            vk.verify_strict(&msg, &Signature::from_bytes(&self.sig.0))
+                .map_err(|_| DirectoryVerifyError::BadSignature)?;
+
+            if self.issued_at_ms > now_ms.saturating_add(MAX_DIRECTORY_FUTURE_SKEW_MS) {
+                return Err(DirectoryVerifyError::FutureIssued);
+            }
+            if self.expires_at_ms < self.issued_at_ms || now_ms > self.expires_at_ms {
+                return Err(DirectoryVerifyError::Expired);
+            }
+
+            Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -145,6 +356,19 @@ mod tests {
         assert_eq!(decoded, resp);
     }
 
+    #[test]
+    fn tunnels_are_appended_after_every_earlier_variant() {
+        // Old peers decode by variant index; the tunnel RPC must not shift any.
+        assert_eq!(ClientRequest::GetTunnels().ser().unwrap(), vec![4]);
+
+        let resp = ClientResponse::GetTunnels {
+            tunnels: vec![TunnelDescriptor { id: RelayId::from_bytes([3; 32]), port: 8443 }],
+        };
+        let bytes = resp.ser().expect("postcard serialize");
+        assert_eq!(bytes[0], 4);
+        assert_eq!(ClientResponse::deser(&bytes).expect("postcard deserialize"), resp);
+    }
+
     #[test]
     fn client_response_get_bootstrap_peers_round_trips_through_postcard() {
         // Both lists populated with overlapping descriptors so the
@@ -160,6 +384,61 @@ mod tests {
         assert_eq!(decoded, resp);
     }
 
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn relay_directory_round_trips_through_postcard() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let directory = RelayDirectory::sign(
+            &key,
+            vec![sample_descriptor(1), sample_descriptor(2)],
+            1_700_000_000_000,
+        );
+        let resp = ClientResponse::GetDirectory { directory };
+        let bytes = resp.ser().expect("postcard serialize");
+        let decoded = ClientResponse::deser(&bytes).expect("postcard deserialize");
+        assert_eq!(decoded, resp);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn relay_directory_verifies_only_under_a_trusted_key_and_intact() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let trusted = [key.verifying_key().to_bytes()];
+        let now = 1_700_000_000_000;
+        let directory = RelayDirectory::sign(&key, vec![sample_descriptor(1)], now);
+
+        assert_eq!(directory.verify(&trusted, now), Ok(()));
+        assert_eq!(directory.verify(&[[9u8; 32]], now), Err(DirectoryVerifyError::UntrustedResolver));
+
+        // Re-pointing a relay at an attacker's address must break the signature.
+        let mut moved = directory.clone();
+        moved.relays[0].addr = "198.51.100.9:4242".parse().expect("valid socket addr");
+        assert_eq!(moved.verify(&trusted, now), Err(DirectoryVerifyError::BadSignature));
+
+        let mut extended = directory.clone();
+        extended.expires_at_ms += 1;
+        assert_eq!(extended.verify(&trusted, now), Err(DirectoryVerifyError::BadSignature));
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn relay_directory_is_refused_outside_its_validity_window() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let trusted = [key.verifying_key().to_bytes()];
+        let issued = 1_700_000_000_000;
+        let directory = RelayDirectory::sign(&key, vec![sample_descriptor(1)], issued);
+
+        assert_eq!(directory.verify(&trusted, directory.expires_at_ms), Ok(()));
+        assert_eq!(
+            directory.verify(&trusted, directory.expires_at_ms + 1),
+            Err(DirectoryVerifyError::Expired)
+        );
+        assert_eq!(
+            directory.verify(&trusted, issued - MAX_DIRECTORY_FUTURE_SKEW_MS - 1),
+            Err(DirectoryVerifyError::FutureIssued)
+        );
+    }
+
     #[test]
     fn client_response_get_bootstrap_peers_empty_lists_round_trip() {
         // The legitimate "brand-new network, no peers known" case
@@ -7,8 +7,9 @@
 //!    `(user_ipk, relay_id, generation)`; relay_sig covering the full record).
 //! 2. The full RPC catalogue, each a `DhtRequest`/`DhtResponse` pair: `FindNode`; the sticky-home
 //!    family `Forward`, `ActivityForward`, `LiveForward`, `QueueFetch`, `QueueFetchAck`; presence
-//!    (`PresenceConsent`, `PresenceState`, `PresenceLease`); `PushPseudonymPublish`; and the MLS
-//!    families `KeyPackage{Publish,Fetch,Refill}` and `Welcome{Publish,Fetch,Ack}`.
+//!    (`PresenceConsent`, `PresenceState`, `PresenceLease`); `PushPseudonymPublish`;
+//!    `WakePolicyPublish`; `TokenRevocation`; `PushKey{Publish,Fetch}`; and the MLS families
+//!    `KeyPackage{Publish,Fetch,Refill}` and `Welcome{Publish,Fetch,Ack}`.
 //! 3. Length-bound constants that downstream handlers check at deserialization / construction time.
 //!
 //! ## Why a `DhtRequest` + `DhtResponse` split (not a single `DhtPacket`)
@@ -34,9 +35,12 @@
 //! pioneered in `common/src/proto/relay_res.rs::signing_input`:
 //!
 //! ```text
-//!   <domain> || PROTOCOL_VERSION (BE u16) || <fields in declaration order>
+//!   <domain> || MIN_PROTOCOL_VERSION (BE u16) || <fields in declaration order>
 //! ```
 //!
+//! [`DhtHello`] is the one exception: it opens a session, so it is signed
+//! at the version the two relays settle on instead.
+//!
 //! Each transcript has its own unique domain string so a captured
 //! signature for one packet kind cannot be replayed as another. Both
 //! signing and verifying sides call the same helper — it is the contract
@@ -49,7 +53,7 @@ use serde::Serialize;
 use serde_with::serde_as;
 use thiserror::Error;
 
-use crate::PROTOCOL_VERSION;
+use crate::MIN_PROTOCOL_VERSION;
 use crate::proto::RelayId;
 use crate::proto::client_rel::ActivityP;
 use crate::proto::client_rel::PresenceState;
@@ -121,15 +125,28 @@ pub const MAX_FIND_NODE_RESULTS: usize = DHT_K;
 ///   node_id:   [u8; 32],   // claimed identity = BLAKE3(pubkey)
 ///   pubkey:    [u8; 32],   // dialer's full Ed25519 identity pubkey
 ///   timestamp: u64,        // ms since epoch; ±MAX_DHT_HELLO_SKEW_MS window
+///   min_version: u16,      // oldest protocol version the dialer speaks
+///   max_version: u16,      // newest protocol version the dialer speaks
 ///   sig:       [u8; 64],   // Ed25519 signature over the canonical transcript
 /// }
 /// ```
 ///
 /// **Signed transcript** (`dht_hello_signing_input`):
 /// ```text
-/// DHT_HELLO_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+/// DHT_HELLO_SIG_DOMAIN || version (BE u16)
 ///   || node_id (32) || pubkey (32) || timestamp (BE u64)
+///   || min_version (BE u16) || max_version (BE u16)
 /// ```
+///
+/// `version` is the settled one: the highest both relays speak, which the
+/// ALPN the TLS handshake picked already names. The receiver recomputes it
+/// from the advertised range with [`crate::negotiate_version`] and refuses a
+/// hello whose range settles anywhere else, so nobody on the path can strip
+/// ALPNs to force an older version.
+///
+/// Only sessions at [`DHT_HELLO_V7_VERSION`] or later open with this shape;
+/// a `peer/6` session, the only kind a relay from before version negotiation
+/// offers, opens with a [`DhtHelloV6`].
 #[serde_as]
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DhtHello {
@@ -146,6 +163,10 @@ pub struct DhtHello {
     /// transcript so the receiver can reject replays outside an accepted
     /// clock-skew window ([`MAX_DHT_HELLO_SKEW_MS`]).
     pub timestamp: u64,
+    /// Oldest protocol version the dialer still speaks.
+    pub min_version: u16,
+    /// Newest protocol version the dialer speaks.
+    pub max_version: u16,
     /// Ed25519 signature over [`dht_hello_signing_input`]. Verified
     /// under `pubkey` using `verify_strict`, mirroring the resolver's
     /// `RelayHello` verification at
@@ -157,10 +178,14 @@ pub struct DhtHello {
 ///
 /// Layout:
 /// ```text
-///   DHT_HELLO_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+///   DHT_HELLO_SIG_DOMAIN || version (BE u16)
 ///     || node_id (32) || pubkey (32) || timestamp (BE u64)
+///     || min_version (BE u16) || max_version (BE u16)
 /// ```
 ///
+/// `version` is the settled session version, not a global; see
+/// [`DhtHello`].
+///
 /// The transcript layout deliberately mirrors
 /// [`crate::proto::relay_res::relay_hello_signing_input`] field-for-field
 /// — the only differences are the domain tag (so signatures are
@@ -173,14 +198,54 @@ pub struct DhtHello {
 /// which makes it the byte-for-byte contract — there is no second
 /// implementation to keep in sync.
 pub fn dht_hello_signing_input(
+    version: u16, node_id: &crate::quic::id::NodeId, pubkey: &[u8; 32], timestamp: u64,
+    min_version: u16, max_version: u16,
+) -> Vec<u8> {
+    // domain (varies) + version (2) + node_id (32) + pubkey (32) + ts (8)
+    // + range (4) = 78 + domain bytes.
+    let mut buf = Vec::with_capacity(
+        DHT_HELLO_SIG_DOMAIN.len() + 2 + crate::quic::id::NodeId::LEN + 32 + 8 + 4,
+    );
+    buf.extend_from_slice(DHT_HELLO_SIG_DOMAIN);
+    buf.extend_from_slice(&version.to_be_bytes());
+    buf.extend_from_slice(node_id.as_bytes());
+    buf.extend_from_slice(pubkey);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf.extend_from_slice(&min_version.to_be_bytes());
+    buf.extend_from_slice(&max_version.to_be_bytes());
+    buf
+}
+
+/// First protocol version whose `peer/` sessions open with a [`DhtHello`].
+/// Below it the dialer sends a [`DhtHelloV6`].
+pub const DHT_HELLO_V7_VERSION: u16 = 7;
+
+/// A [`DhtHello`] as protocol 6 lays it out: no version range, signed at 6.
+/// A relay from before version negotiation sends and expects exactly this on
+/// `peer/6`, so the layout and its transcript are frozen.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhtHelloV6 {
+    pub node_id:   crate::quic::id::NodeId,
+    pub pubkey:    Bytes<32>,
+    pub timestamp: u64,
+    /// Ed25519 signature over [`dht_hello_v6_signing_input`].
+    pub sig:       Bytes<64>,
+}
+
+/// Build the canonical signing transcript for [`DhtHelloV6`].
+///
+/// Layout:
+/// ```text
+///   DHT_HELLO_SIG_DOMAIN || 6 (BE u16)
+///     || node_id (32) || pubkey (32) || timestamp (BE u64)
+/// ```
+pub fn dht_hello_v6_signing_input(
     node_id: &crate::quic::id::NodeId, pubkey: &[u8; 32], timestamp: u64,
 ) -> Vec<u8> {
-    // domain (varies) + version (2) + node_id (32) + pubkey (32) + ts (8) = 76
-    // + domain bytes.
     let mut buf =
         Vec::with_capacity(DHT_HELLO_SIG_DOMAIN.len() + 2 + crate::quic::id::NodeId::LEN + 32 + 8);
     buf.extend_from_slice(DHT_HELLO_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&6u16.to_be_bytes());
     buf.extend_from_slice(node_id.as_bytes());
     buf.extend_from_slice(pubkey);
     buf.extend_from_slice(&timestamp.to_be_bytes());
@@ -194,6 +259,7 @@ pub fn dht_hello_signing_input(
 /// - [`Self::IdMismatch`], [`Self::MalformedPubkey`], [`Self::BadSignature`] → `DhtBadSignature`
 ///   (or `DhtMalformedKey` for malformed pubkey shape — caller's choice).
 /// - [`Self::ClockSkew`] → `DhtClockSkew`.
+/// - [`Self::NoCommonVersion`] → `UnsupportedVersion`.
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum DhtHelloVerifyError {
     /// `node_id != BLAKE3(pubkey)` — the dialer is presenting a pubkey
@@ -215,6 +281,10 @@ pub enum DhtHelloVerifyError {
     /// dialer.
     #[error("dht hello: stale or future timestamp (clock skew)")]
     ClockSkew,
+    /// The dialer's `min_version..=max_version` shares no version with
+    /// ours, or settles on one other than the session's ALPN names.
+    #[error("dht hello: no protocol version in common")]
+    NoCommonVersion,
 }
 
 //===:===:===:===:===:===:===:===:===:===:===:===:===||
@@ -226,14 +296,18 @@ mod verify_impl {
     use ed25519_dalek::Signature;
     use ed25519_dalek::VerifyingKey;
 
+    use super::AccountDeletion;
     use super::DhtHello;
+    use super::DhtHelloV6;
     use super::DhtHelloVerifyError;
     use super::Forward;
     use super::ForwardVerifyError;
+    use super::IdentitySuccession;
     use super::MAX_DHT_HELLO_SKEW_MS;
     use super::MAX_FETCH_QUEUE_ACK_IDS;
     use super::PRESENCE_LEASE_MAX_MS;
     use super::PRESENCE_STATE_MAX_SKEW_MS;
+    use super::PredecessorDeletion;
     use super::PresenceConsent;
     use super::PresenceLease;
     use super::QueueFetch;
@@ -241,8 +315,11 @@ mod verify_impl {
     use super::QueueFetchAckVerifyError;
     use super::QueueFetchVerifyError;
     use super::RelayPresenceState;
+    use super::account_deletion_signing_input;
     use super::dht_hello_signing_input;
+    use super::dht_hello_v6_signing_input;
     use super::forward_signing_input;
+    use super::identity_succession_signing_input;
     use super::presence_consent_signing_input;
     use super::presence_lease_signing_input;
     use super::presence_state_signing_input;
@@ -252,9 +329,9 @@ mod verify_impl {
 
     impl DhtHello {
         /// Validate a [`DhtHello`] received as the first frame on an
-        /// inbound `peer/5` connection. Returns `Ok(())` and binds the
-        /// connection's authenticated [`NodeId`] (callers stash
-        /// `self.node_id` post-success) on a clean check.
+        /// inbound `peer/` connection. Returns `Ok(())` on a clean check;
+        /// callers then bind the connection's authenticated [`NodeId`]
+        /// (`self.node_id`).
         ///
         /// Mirrors the order, semantics and failure modes of the
         /// resolver-side `verify_signed_packet`:
@@ -269,34 +346,65 @@ mod verify_impl {
         ///    the standard small-subgroup defence.
         /// 4. **Timestamp window**: `|now_ms − timestamp| ≤ MAX_DHT_HELLO_SKEW_MS`.
         ///
+        /// Before any of that, the advertised range must settle on
+        /// `session`, the version the connection's ALPN names; the signature
+        /// is then checked at it.
+        ///
         /// `now_ms` is wall-clock in milliseconds since the Unix epoch,
         /// passed in explicitly so unit tests can pin a deterministic
         /// clock.
+        pub fn verify(&self, session: u16, now_ms: u64) -> Result<(), DhtHelloVerifyError> {
+            let version = crate::negotiate_version(self.min_version, self.max_version)
+                .filter(|v| *v == session)
+                .ok_or(DhtHelloVerifyError::NoCommonVersion)?;
+            let msg = dht_hello_signing_input(
+                version,
+                &self.node_id,
+                &self.pubkey.0,
+                self.timestamp,
+                self.min_version,
+                self.max_version,
+            );
+            verify_hello(&self.node_id, &self.pubkey.0, &self.sig.0, &msg, self.timestamp, now_ms)
+        }
+    }
+
+    impl DhtHelloV6 {
+        /// [`DhtHello::verify`] for a `peer/6` session: the same checks,
+        /// over [`dht_hello_v6_signing_input`].
         pub fn verify(&self, now_ms: u64) -> Result<(), DhtHelloVerifyError> {
-            // 1. id-binding to pubkey. NodeId::new = BLAKE3(pubkey) — same construction every other
-            //    call site uses (cf. `verify_signed_packet` and `PresenceRecord::verify`).
-            let derived_id = NodeId::new(self.pubkey.as_ref());
-            if derived_id != self.node_id {
-                return Err(DhtHelloVerifyError::IdMismatch);
-            }
+            let msg = dht_hello_v6_signing_input(&self.node_id, &self.pubkey.0, self.timestamp);
+            verify_hello(&self.node_id, &self.pubkey.0, &self.sig.0, &msg, self.timestamp, now_ms)
+        }
+    }
 
-            // 2. Pubkey shape.
-            let vk = VerifyingKey::from_bytes(&self.pubkey.0)
-                .map_err(|_| DhtHelloVerifyError::MalformedPubkey)?;
+    /// Steps 1–4 of [`DhtHello::verify`], shared by both hello layouts.
+    fn verify_hello(
+        node_id: &NodeId, pubkey: &[u8; 32], sig: &[u8; 64], msg: &[u8], timestamp: u64,
+        now_ms: u64,
+    ) -> Result<(), DhtHelloVerifyError> {
+        // 1. id-binding to pubkey. NodeId::new = BLAKE3(pubkey) — same construction every other
+        //    call site uses (cf. `verify_signed_packet` and `PresenceRecord::verify`).
+        let derived_id = NodeId::new(pubkey);
+        if derived_id != *node_id {
+            return Err(DhtHelloVerifyError::IdMismatch);
+        }
 
-            // 3. Signature.
-            let sig = Signature::from_bytes(&self.sig.0);
-            let msg = dht_hello_signing_input(&self.node_id, &self.pubkey.0, self.timestamp);
-            vk.verify_strict(&msg, &sig).map_err(|_| DhtHelloVerifyError::BadSignature)?;
+        // 2. Pubkey shape.
+        let vk =
+            VerifyingKey::from_bytes(pubkey).map_err(|_| DhtHelloVerifyError::MalformedPubkey)?;
 
-            // 4. Timestamp freshness (replay protection).
-            let skew = now_ms.abs_diff(self.timestamp);
-            if skew > MAX_DHT_HELLO_SKEW_MS {
-                return Err(DhtHelloVerifyError::ClockSkew);
-            }
+        // 3. Signature.
+        let sig = Signature::from_bytes(sig);
+        vk.verify_strict(msg, &sig).map_err(|_| DhtHelloVerifyError::BadSignature)?;
 
-            Ok(())
+        // 4. Timestamp freshness (replay protection).
+        let skew = now_ms.abs_diff(timestamp);
+        if skew > MAX_DHT_HELLO_SKEW_MS {
+            return Err(DhtHelloVerifyError::ClockSkew);
         }
+
+        Ok(())
     }
 
     impl PresenceConsent {
@@ -347,6 +455,49 @@ mod verify_impl {
         }
     }
 
+    impl IdentitySuccession {
+        /// Both keys signed the hand-over, and they are different keys.
+        /// Freshness is the caller's call: a relay takes only recent ones,
+        /// a contact honours one however late it arrives.
+        pub fn verify(&self) -> bool {
+            if self.old_ipk == self.new_ipk {
+                return false;
+            }
+            let msg =
+                identity_succession_signing_input(&self.old_ipk.0, &self.new_ipk.0, self.timestamp);
+            [(&self.old_ipk, &self.old_sig), (&self.new_ipk, &self.new_sig)].into_iter().all(
+                |(ipk, sig)| {
+                    VerifyingKey::from_bytes(&ipk.0).is_ok_and(|key| {
+                        key.verify_strict(&msg, &Signature::from_bytes(&sig.0)).is_ok()
+                    })
+                },
+            )
+        }
+    }
+
+    impl AccountDeletion {
+        /// The key being deleted signed it. Freshness is the caller's call,
+        /// as for a succession.
+        pub fn verify(&self) -> bool {
+            let msg = account_deletion_signing_input(&self.ipk.0, self.timestamp);
+            VerifyingKey::from_bytes(&self.ipk.0).is_ok_and(|key| {
+                key.verify_strict(&msg, &Signature::from_bytes(&self.sig.0)).is_ok()
+            })
+        }
+    }
+
+    impl PredecessorDeletion {
+        /// Every succession holds, each takes over from the one before, the
+        /// last ends at the deleting key, and that key signed the deletion.
+        pub fn verify(&self) -> bool {
+            let Some(last) = self.chain.last() else { return false };
+            last.new_ipk == self.deletion.ipk
+                && self.chain.windows(2).all(|w| w[0].new_ipk == w[1].old_ipk)
+                && self.chain.iter().all(IdentitySuccession::verify)
+                && self.deletion.verify()
+        }
+    }
+
     impl RelayPresenceState {
         pub fn verify(&self, authenticated_relay: &NodeId, now_ms: u64) -> bool {
             self.who == self.lease.user
@@ -599,7 +750,7 @@ pub fn push_pseudonym_signing_input(
 ) -> Vec<u8> {
     let mut buf = Vec::with_capacity(DHT_PUSH_PSEUDONYM_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
     buf.extend_from_slice(DHT_PUSH_PSEUDONYM_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(user_ipk);
     buf.extend_from_slice(pseudonym);
     buf.extend_from_slice(&timestamp.to_be_bytes());
@@ -620,6 +771,330 @@ pub struct PushPseudonymPublishResp {
     pub accepted: bool,
 }
 
+// --- Wake policy (owner → home relays) -----------------------------------
+
+/// Domain for an owner-signed [`WakePolicy`]. Distinct from the pseudonym
+/// domain so neither signature can stand in for the other.
+pub const DHT_WAKE_POLICY_SIG_DOMAIN: &[u8] = b"promtuz-dht-wake-policy-v1";
+
+/// Senders a [`WakePolicy`] may allow-list.
+pub const MAX_WAKE_ALLOWED_SENDERS: usize = 64;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// A daily window, in minutes since **UTC** midnight, during which no wake is
+/// sent. The device converts the user's local hours before signing and
+/// re-signs when its timezone changes. `start > end` wraps past midnight;
+/// `start == end` is an empty window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_min: u16,
+    pub end_min:   u16,
+}
+
+impl QuietHours {
+    pub fn contains(&self, now_ms: u64) -> bool {
+        let minute = ((now_ms / 60_000) % MINUTES_PER_DAY as u64) as u16;
+        if self.start_min <= self.end_min {
+            (self.start_min..self.end_min).contains(&minute)
+        } else {
+            minute >= self.start_min || minute < self.end_min
+        }
+    }
+}
+
+/// When a home relay may push-wake the owner's devices. Evaluated on
+/// metadata the relay already holds (the dispatch's `from` and the clock), so
+/// enforcing it never needs message content. The default wakes for
+/// everything.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakePolicy {
+    /// Never wake; messages still queue and deliver on the next drain.
+    pub mute_all:        bool,
+    pub quiet_hours:     Option<QuietHours>,
+    /// If non-empty, only dispatches from these IPKs wake. A sealed-sender
+    /// dispatch names nobody the relay can match, so it never wakes under a
+    /// non-empty list. Bounded by [`MAX_WAKE_ALLOWED_SENDERS`].
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_WAKE_ALLOWED_SENDERS>")]
+    pub allowed_senders: Vec<Bytes<32>>,
+}
+
+impl WakePolicy {
+    /// Whether a dispatch from `sender` arriving at `now_ms` may wake.
+    pub fn allows(&self, sender: &[u8; 32], now_ms: u64) -> bool {
+        if self.mute_all || self.quiet_hours.is_some_and(|q| q.contains(now_ms)) {
+            return false;
+        }
+        self.allowed_senders.is_empty() || self.allowed_senders.iter().any(|s| &s.0 == sender)
+    }
+}
+
+pub fn wake_policy_signing_input(
+    user_ipk: &[u8; 32], policy: &WakePolicy, timestamp: u64,
+) -> Vec<u8> {
+    let senders = policy.allowed_senders.len();
+    let mut buf =
+        Vec::with_capacity(DHT_WAKE_POLICY_SIG_DOMAIN.len() + 2 + 32 + 6 + 2 + senders * 32 + 8);
+    buf.extend_from_slice(DHT_WAKE_POLICY_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(user_ipk);
+    buf.push(policy.mute_all as u8);
+    match policy.quiet_hours {
+        Some(q) => {
+            buf.push(1);
+            buf.extend_from_slice(&q.start_min.to_be_bytes());
+            buf.extend_from_slice(&q.end_min.to_be_bytes());
+        },
+        None => buf.extend_from_slice(&[0; 5]),
+    }
+    buf.extend_from_slice(&(senders as u16).to_be_bytes());
+    for sender in &policy.allowed_senders {
+        buf.extend_from_slice(&sender.0);
+    }
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// Owner-signed [`WakePolicy`], replicated to each home like
+/// [`PushPseudonymPublish`]. A home keeps the newest by `timestamp`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakePolicyPublish {
+    pub user_ipk:  Bytes<32>,
+    pub policy:    WakePolicy,
+    pub timestamp: u64,
+    pub user_sig:  Bytes<64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakePolicyPublishResp {
+    pub accepted: bool,
+}
+
+// --- Delivery-token revocation (owner → home relays) ---------------------
+
+/// Domain for an owner-signed [`TokenRevocation`].
+pub const DHT_TOKEN_REVOCATION_SIG_DOMAIN: &[u8] = b"promtuz-dht-token-revocation-v1";
+
+/// Token ids one [`TokenRevocation`] may list. Tokens live an epoch or two,
+/// so this is many contacts forgotten within that window.
+pub const MAX_REVOKED_TOKENS: usize = 256;
+
+pub fn token_revocation_signing_input(
+    user_ipk: &[u8; 32], ids: &[Bytes<16>], expires_at_ms: u64, timestamp: u64,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(
+        DHT_TOKEN_REVOCATION_SIG_DOMAIN.len() + 2 + 32 + 2 + ids.len() * 16 + 8 + 8,
+    );
+    buf.extend_from_slice(DHT_TOKEN_REVOCATION_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(user_ipk);
+    buf.extend_from_slice(&(ids.len() as u16).to_be_bytes());
+    for id in ids {
+        buf.extend_from_slice(&id.0);
+    }
+    buf.extend_from_slice(&expires_at_ms.to_be_bytes());
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// The [`crate::proto::client_rel::DeliveryToken`]s `user_ipk` withdrew, by
+/// id, signed under the key the tokens verify under: the IPK, or one of its
+/// mailboxes. Replicated to the homes like [`WakePolicyPublish`], which keep
+/// the newest by `timestamp` and refuse a sealed dispatch carrying a listed
+/// token. The list is whole each time, so a newer one replaces the older;
+/// it is kept until `expires_at_ms`, when every listed token has lapsed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRevocation {
+    pub user_ipk:      Bytes<32>,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_REVOKED_TOKENS>")]
+    pub ids:           Vec<Bytes<16>>,
+    pub expires_at_ms: u64,
+    pub timestamp:     u64,
+    pub user_sig:      Bytes<64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRevocationResp {
+    pub accepted: bool,
+}
+
+// --- Device push key (owner → home relays → senders) ---------------------
+
+/// Domain for an owner-signed [`PushKeyRecord`].
+pub const DHT_PUSH_KEY_SIG_DOMAIN: &[u8] = b"promtuz-dht-push-key-v1";
+
+pub fn push_key_signing_input(user_ipk: &[u8; 32], push_pk: &[u8; 32], timestamp: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(DHT_PUSH_KEY_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
+    buf.extend_from_slice(DHT_PUSH_KEY_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(user_ipk);
+    buf.extend_from_slice(push_pk);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// The X25519 key senders seal wake hints to
+/// ([`crate::proto::push::SealedWakeHint`]), signed by the owner's IPK. Held
+/// by the same homes as the KeyPackage stash, which keep the newest by
+/// `timestamp` and hand it to any relay that asks; it is public by design.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeyRecord {
+    pub user_ipk:  Bytes<32>,
+    pub push_pk:   Bytes<32>,
+    pub timestamp: u64,
+    pub user_sig:  Bytes<64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeyPublishResp {
+    pub accepted: bool,
+}
+
+/// Sender-relay → home-relay: the target's current [`PushKeyRecord`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeyFetch {
+    pub target_ipk: Bytes<32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeyFetchResp {
+    /// `None` when this home holds no key for the target (or isn't a home).
+    pub record: Option<PushKeyRecord>,
+}
+
+/// Recipient-relay → blob host: the manifest (`index = None`) or one
+/// ciphertext chunk of `blob_id`. Served only by relays carrying
+/// `BLOB_STORE`; anyone else answers empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobFetch {
+    pub blob_id: Bytes<32>,
+    pub index:   Option<u32>,
+}
+
+/// Whichever of the two [`BlobFetch`] asked for; both `None` when the host
+/// doesn't hold the blob.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobFetchResp {
+    pub manifest: Option<crate::proto::blob::BlobManifest>,
+    pub chunk:    Option<crate::types::bytes::ByteVec>,
+}
+
+// --- Identity succession (old IPK → new IPK) -----------------------------
+
+/// Domain for both signatures on an [`IdentitySuccession`].
+pub const IDENTITY_SUCCESSION_SIG_DOMAIN: &[u8] = b"promtuz-identity-succession-v1";
+
+/// How far ahead of a relay's clock an [`IdentitySuccession`]'s timestamp may
+/// sit. Any age is fine: both keys signed it, so a replay only repeats it, and
+/// a device that rotated offline publishes it whenever it next connects.
+pub const MAX_SUCCESSION_SKEW_MS: u64 = 5 * 60 * 1000;
+
+pub fn identity_succession_signing_input(
+    old_ipk: &[u8; 32], new_ipk: &[u8; 32], timestamp: u64,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(IDENTITY_SUCCESSION_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
+    buf.extend_from_slice(IDENTITY_SUCCESSION_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(old_ipk);
+    buf.extend_from_slice(new_ipk);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// "`old_ipk` is now `new_ipk`." The old key hands the identity on and the
+/// new key takes it, both signing the same transcript, so neither half can be
+/// forged from the other. Self-contained: contacts, relays and anyone carrying
+/// it can check it without asking either key's owner.
+///
+/// The old key's homes keep it, hand their queue to the new key's homes
+/// ([`QueueHandover`]) and retire its KeyPackage and Welcome stashes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentitySuccession {
+    pub old_ipk:   Bytes<32>,
+    pub new_ipk:   Bytes<32>,
+    pub timestamp: u64,
+    pub old_sig:   Bytes<64>,
+    pub new_sig:   Bytes<64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentitySuccessionResp {
+    pub accepted: bool,
+}
+
+/// Old-key home → new-key home: dispatches queued for `succession.old_ipk`,
+/// to be queued for `new_ipk` instead. Each keeps the sender's signature,
+/// which still names the old key as `to`; the device checks it against the
+/// keys it succeeded. Bounded by [`MAX_FETCH_QUEUE_BATCH`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueHandover {
+    pub succession: IdentitySuccession,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_FETCH_QUEUE_BATCH>")]
+    pub dispatches: Vec<crate::proto::client_rel::DispatchP>,
+}
+
+/// The dispatch ids the new home now holds; the old home drops exactly those.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueHandoverResp {
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_FETCH_QUEUE_BATCH>")]
+    pub stored: Vec<Bytes<16>>,
+}
+
+// --- Account deletion -----------------------------------------------------
+
+/// Domain for the owner's signature on an [`AccountDeletion`].
+pub const ACCOUNT_DELETION_SIG_DOMAIN: &[u8] = b"promtuz-account-deletion-v1";
+
+/// How far ahead of a relay's clock an [`AccountDeletion`] may be dated. Any
+/// age is fine: a replay only deletes an account that is already gone.
+pub const MAX_ACCOUNT_DELETION_SKEW_MS: u64 = 5 * 60 * 1000;
+
+pub fn account_deletion_signing_input(ipk: &[u8; 32], timestamp: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(ACCOUNT_DELETION_SIG_DOMAIN.len() + 2 + 32 + 8);
+    buf.extend_from_slice(ACCOUNT_DELETION_SIG_DOMAIN);
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(ipk);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// "Forget `ipk`", signed by that key. Each of its homes wipes everything it
+/// holds under the key and keeps the statement as a tombstone, so a replica
+/// that missed the wipe can't publish the state back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDeletion {
+    pub ipk:       Bytes<32>,
+    pub timestamp: u64,
+    pub sig:       Bytes<64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDeletionResp {
+    pub accepted: bool,
+}
+
+/// Longest chain of rotations a [`PredecessorDeletion`] may carry.
+pub const MAX_PREDECESSOR_CHAIN: usize = 16;
+
+/// "Forget `chain[0].old_ipk` too", for a key its owner rotated away from and
+/// so can no longer sign for. `chain` leads from that key, one succession per
+/// rotation, to `deletion.ipk`, whose signature stands for the lot. The old
+/// key's homes hold its succession and whatever was queued after the
+/// hand-over; they wipe and tombstone it as for the current key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PredecessorDeletion {
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_PREDECESSOR_CHAIN>")]
+    pub chain:    Vec<IdentitySuccession>,
+    pub deletion: AccountDeletion,
+}
+
+impl PredecessorDeletion {
+    /// The retired key being deleted.
+    pub fn ipk(&self) -> Option<[u8; 32]> {
+        self.chain.first().map(|s| s.old_ipk.0)
+    }
+}
+
 /// Sender-relay → home-relay request: please deliver-or-queue this
 /// dispatch on behalf of the sending relay.
 ///
@@ -662,7 +1137,7 @@ pub struct Forward {
 ///
 /// Layout:
 /// ```text
-///   DHT_FORWARD_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+///   DHT_FORWARD_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
 ///     || dispatch_id (16) || sender_relay_id (32) || timestamp (BE u64)
 /// ```
 ///
@@ -686,7 +1161,7 @@ pub fn forward_signing_input(
         DHT_FORWARD_SIG_DOMAIN.len() + 2 + 16 + crate::quic::id::NodeId::LEN + 8,
     );
     buf.extend_from_slice(DHT_FORWARD_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(dispatch_id);
     buf.extend_from_slice(sender_relay_id.as_bytes());
     buf.extend_from_slice(&timestamp.to_be_bytes());
@@ -712,11 +1187,39 @@ pub enum ForwardOutcome {
     /// not stored; sender should back off.
     QueueFull,
     /// Either the embedded `dispatch.sig` (user-layer) or the outer `sig`
-    /// (sender-relay-layer) failed verification.
+    /// (sender-relay-layer) failed verification. Only ever a checked
+    /// signature: the one outcome a home charges to the sender relay.
     BadSig,
     /// Per-peer rate-limit class tripped at the home relay. Sender retries
     /// after backoff.
     RateLimited,
+    /// Storing the dispatch would take the recipient's queue, or this
+    /// sender's share of it, past the home's byte quota. Not stored. Unlike
+    /// [`Self::QueueFull`] this is about payload volume, not row count, so
+    /// retrying the same dispatch only helps once the recipient drains.
+    QuotaExceeded,
+    /// The home failed on its own side (encode, storage or fsync error).
+    /// Says nothing about the dispatch; the sender may retry. Appended
+    /// (postcard variant order), as are the three below.
+    Internal,
+    /// The home has no key for `sender_relay_id` yet, so it could not check
+    /// the outer signature at all — not evidence of a forgery.
+    UnknownSender,
+    /// `dispatch.id` is already queued for this recipient under another
+    /// sender. Not stored; ids are random, so this is a squat or a clash,
+    /// and either way not this sender's signature at fault.
+    IdConflict,
+    /// The outer signature held but its timestamp is outside the skew
+    /// window: a clock problem, charged as one rather than as a forgery.
+    StaleTimestamp,
+    /// A sealed dispatch whose token is expired, not issued by `to`, or
+    /// revoked. The recipient's doing, not the sender relay's, so never
+    /// charged to it. Appended last.
+    BadToken,
+    /// The recipient deleted its account and this home holds its tombstone.
+    /// Not stored, and no other home will take it either; the sender should
+    /// give up rather than re-dispatch. Appended last.
+    Deleted,
 }
 
 /// Reply to a [`Forward`] RPC.
@@ -780,7 +1283,7 @@ pub fn presence_consent_signing_input(
 ) -> Vec<u8> {
     let mut out = Vec::with_capacity(PRESENCE_CONSENT_SIG_DOMAIN.len() + 2 + 32 + 32 + 8 + 8 + 1);
     out.extend_from_slice(PRESENCE_CONSENT_SIG_DOMAIN);
-    out.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    out.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     out.extend_from_slice(owner);
     out.extend_from_slice(recipient);
     out.extend_from_slice(&version.to_be_bytes());
@@ -795,7 +1298,7 @@ pub fn presence_lease_signing_input(
 ) -> Vec<u8> {
     let mut out = Vec::with_capacity(PRESENCE_LEASE_SIG_DOMAIN.len() + 2 + 32 + 32 + 8 * 3);
     out.extend_from_slice(PRESENCE_LEASE_SIG_DOMAIN);
-    out.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    out.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     out.extend_from_slice(user);
     out.extend_from_slice(relay_id.as_bytes());
     out.extend_from_slice(&version.to_be_bytes());
@@ -807,7 +1310,7 @@ pub fn presence_lease_signing_input(
 pub fn presence_state_signing_input(record: &RelayPresenceState) -> Vec<u8> {
     let mut out = Vec::new();
     out.extend_from_slice(PRESENCE_STATE_SIG_DOMAIN);
-    out.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    out.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     out.extend_from_slice(&record.recipient.0);
     out.extend_from_slice(&record.who.0);
     out.extend_from_slice(&record.lease.user.0);
@@ -855,7 +1358,7 @@ pub fn live_forward_signing_input(
 ) -> Vec<u8> {
     let mut out = Vec::with_capacity(DHT_LIVE_FORWARD_SIG_DOMAIN.len() + 2 + 16 + 32 + 8 + 32 + 8);
     out.extend_from_slice(DHT_LIVE_FORWARD_SIG_DOMAIN);
-    out.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    out.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     out.extend_from_slice(dispatch_id);
     out.extend_from_slice(&lease.user.0);
     out.extend_from_slice(&lease.version.to_be_bytes());
@@ -909,7 +1412,7 @@ pub struct QueueFetch {
 ///
 /// Layout:
 /// ```text
-///   DHT_QUEUE_FETCH_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+///   DHT_QUEUE_FETCH_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
 ///     || user_ipk (32) || requester_relay_id (32) || timestamp (BE u64)
 /// ```
 pub fn queue_fetch_signing_input(
@@ -920,7 +1423,7 @@ pub fn queue_fetch_signing_input(
         DHT_QUEUE_FETCH_SIG_DOMAIN.len() + 2 + 32 + crate::quic::id::NodeId::LEN + 8,
     );
     buf.extend_from_slice(DHT_QUEUE_FETCH_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(user_ipk);
     buf.extend_from_slice(requester_relay_id.as_bytes());
     buf.extend_from_slice(&timestamp.to_be_bytes());
@@ -1031,7 +1534,7 @@ pub struct QueueFetchAck {
 ///
 /// Layout:
 /// ```text
-///   DHT_QUEUE_FETCH_ACK_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+///   DHT_QUEUE_FETCH_ACK_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
 ///     || user_ipk (32) || requester_relay_id (32)
 ///     || count (BE u32) || id_0 (16) || ... || id_n (16)
 ///     || timestamp (BE u64)
@@ -1065,7 +1568,7 @@ pub fn queue_fetch_ack_signing_input(
             + 8,
     );
     buf.extend_from_slice(DHT_QUEUE_FETCH_ACK_SIG_DOMAIN);
-    buf.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(user_ipk);
     buf.extend_from_slice(requester_relay_id.as_bytes());
     buf.extend_from_slice(&count.to_be_bytes());
@@ -1194,14 +1697,15 @@ pub enum DhtRequest {
     /// The only surviving read RPC — used by bootstrap's self-lookup.
     FindNode(FindNode),
     /// Sticky-home: sender-relay → home-relay deliver-or-queue. Handled
-    /// in `relay/src/dht/handler.rs`.
-    Forward(Forward),
+    /// in `relay/src/dht/handler.rs`. The dispatch is in the v6 layout;
+    /// [`Self::ForwardV7`] carries it whole.
+    Forward(#[serde(with = "v6::forward")] Forward),
     /// Ephemeral activity fan-out to recipient homes; never persisted.
     ActivityForward(ActivityForward),
     PresenceConsent(PresenceConsent),
     PresenceState(RelayPresenceState),
     PresenceLease(PresenceLease),
-    LiveForward(LiveForward),
+    LiveForward(#[serde(with = "v6::live_forward")] LiveForward),
     PushPseudonymPublish(PushPseudonymPublish),
     /// Sticky-home: recipient-relay → home-relay drain request.
     QueueFetch(QueueFetch),
@@ -1236,6 +1740,37 @@ pub enum DhtRequest {
     /// Domain-separated from `WelcomeFetch` so a captured fetch sig
     /// can't be replayed as an ack.
     WelcomeAck(crate::proto::mls_wire::WelcomeAckReq),
+
+    /// Owner-signed wake policy for the homes holding its pseudonym.
+    /// Appended last (postcard variant order).
+    WakePolicyPublish(WakePolicyPublish),
+    /// Owner-signed device push key for the homes. Appended last.
+    PushKeyPublish(PushKeyRecord),
+    /// Sender-relay lookup of a target's push key. Appended last.
+    PushKeyFetch(PushKeyFetch),
+    /// [`Self::Forward`] with the dispatch whole, for a peer at
+    /// [`crate::proto::client_rel::DISPATCH_V7_VERSION`]. Appended last.
+    ForwardV7(Forward),
+    /// [`Self::LiveForward`] with the dispatch whole, likewise. Appended last.
+    LiveForwardV7(LiveForward),
+    /// Read from the blob store of the relay being asked, on behalf of a
+    /// recipient connected to the requester. Appended last.
+    BlobFetch(BlobFetch),
+    /// An identity moved to a new key: sent to the old key's queue,
+    /// KeyPackage and Welcome homes. Appended last.
+    IdentitySuccession(IdentitySuccession),
+    /// Old-key home → new-key home transfer of a succeeded identity's queue.
+    /// Appended last.
+    QueueHandover(QueueHandover),
+    /// An identity deleted itself: sent to its queue, KeyPackage and Welcome
+    /// homes. Appended last.
+    DeleteAccount(AccountDeletion),
+    /// Owner-signed list of withdrawn delivery tokens for the homes that
+    /// admit sealed dispatches. Appended last.
+    TokenRevocation(TokenRevocation),
+    /// A deleted identity's retired key: sent to that key's queue, KeyPackage
+    /// and Welcome homes. Appended last.
+    DeletePredecessor(PredecessorDeletion),
 }
 
 /// All outbound DHT response payloads. Mirrored 1:1 with [`DhtRequest`]
@@ -1254,7 +1789,7 @@ pub enum DhtResponse {
     LiveForward(LiveForwardResp),
     PushPseudonymPublish(PushPseudonymPublishResp),
     /// Sticky-home — reply to [`DhtRequest::QueueFetch`].
-    QueueFetch(QueueFetchResp),
+    QueueFetch(#[serde(with = "v6::queue_fetch_resp")] QueueFetchResp),
     /// Sticky-home — reply to [`DhtRequest::QueueFetchAck`].
     /// `QueueFetchAck` itself has no semantically meaningful return
     /// payload (it's a fire-and-forget GC nudge), but the per-stream
@@ -1279,6 +1814,123 @@ pub enum DhtResponse {
     WelcomeFetch(crate::proto::mls_wire::WelcomeFetchResp),
     /// MLS — reply to [`DhtRequest::WelcomeAck`].
     WelcomeAck(crate::proto::mls_wire::WelcomeAckResp),
+
+    /// Reply to [`DhtRequest::WakePolicyPublish`].
+    WakePolicyPublish(WakePolicyPublishResp),
+    /// Reply to [`DhtRequest::PushKeyPublish`].
+    PushKeyPublish(PushKeyPublishResp),
+    /// Reply to [`DhtRequest::PushKeyFetch`].
+    PushKeyFetch(PushKeyFetchResp),
+    /// [`Self::QueueFetch`] with the dispatches whole, sent instead of it on
+    /// a connection at [`crate::proto::client_rel::DISPATCH_V7_VERSION`].
+    /// Appended last.
+    QueueFetchV7(QueueFetchResp),
+    /// Reply to [`DhtRequest::BlobFetch`].
+    BlobFetch(BlobFetchResp),
+    /// Reply to [`DhtRequest::IdentitySuccession`].
+    IdentitySuccession(IdentitySuccessionResp),
+    /// Reply to [`DhtRequest::QueueHandover`].
+    QueueHandover(QueueHandoverResp),
+    /// Reply to [`DhtRequest::DeleteAccount`].
+    DeleteAccount(AccountDeletionResp),
+    /// Reply to [`DhtRequest::TokenRevocation`].
+    TokenRevocation(TokenRevocationResp),
+    /// Reply to [`DhtRequest::DeletePredecessor`].
+    DeletePredecessor(AccountDeletionResp),
+}
+
+/// Serde adapters for the request and response shapes that held a dispatch
+/// in protocol 6. Each writes and reads the dispatch as
+/// [`crate::proto::client_rel::DispatchV6P`], so peers a version behind keep
+/// decoding them; the `*V7` variants carry the same types whole.
+mod v6 {
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serialize;
+    use serde::Serializer;
+
+    use crate::proto::client_rel::DispatchP;
+    use crate::proto::client_rel::DispatchV6P;
+    use crate::proto::client_rel::dispatch_v6;
+    use crate::proto::client_rel::dispatch_v6::V6;
+    use crate::proto::pack::bounded_vec;
+    use crate::quic::id::NodeId;
+    use crate::types::bytes::Bytes;
+
+    // Postcard lays a tuple out exactly as the struct it mirrors, so each
+    // `serialize` writes the fields in declaration order.
+
+    pub mod forward {
+        use super::*;
+        use crate::proto::dht_p2p::Forward;
+
+        #[derive(Deserialize)]
+        struct Wire {
+            #[serde(with = "dispatch_v6")]
+            dispatch:        DispatchP,
+            sender_relay_id: NodeId,
+            timestamp:       u64,
+            sig:             Bytes<64>,
+        }
+
+        pub fn serialize<S: Serializer>(f: &Forward, s: S) -> Result<S::Ok, S::Error> {
+            (V6(&f.dispatch), &f.sender_relay_id, f.timestamp, &f.sig).serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Forward, D::Error> {
+            let Wire { dispatch, sender_relay_id, timestamp, sig } = Wire::deserialize(de)?;
+            Ok(Forward { dispatch, sender_relay_id, timestamp, sig })
+        }
+    }
+
+    pub mod live_forward {
+        use super::*;
+        use crate::proto::dht_p2p::LiveForward;
+        use crate::proto::dht_p2p::PresenceLease;
+
+        #[derive(Deserialize)]
+        struct Wire {
+            #[serde(with = "dispatch_v6")]
+            dispatch:        DispatchP,
+            lease:           PresenceLease,
+            sender_relay_id: NodeId,
+            timestamp:       u64,
+            sig:             Bytes<64>,
+        }
+
+        pub fn serialize<S: Serializer>(f: &LiveForward, s: S) -> Result<S::Ok, S::Error> {
+            (V6(&f.dispatch), &f.lease, &f.sender_relay_id, f.timestamp, &f.sig).serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<LiveForward, D::Error> {
+            let Wire { dispatch, lease, sender_relay_id, timestamp, sig } = Wire::deserialize(de)?;
+            Ok(LiveForward { dispatch, lease, sender_relay_id, timestamp, sig })
+        }
+    }
+
+    pub mod queue_fetch_resp {
+        use super::*;
+        use crate::proto::dht_p2p::MAX_FETCH_QUEUE_BATCH;
+        use crate::proto::dht_p2p::QueueFetchResp;
+
+        #[derive(Deserialize)]
+        struct Wire {
+            #[serde(deserialize_with = "bounded_vec::<_, _, MAX_FETCH_QUEUE_BATCH>")]
+            messages:  Vec<DispatchV6P>,
+            exhausted: bool,
+        }
+
+        pub fn serialize<S: Serializer>(r: &QueueFetchResp, s: S) -> Result<S::Ok, S::Error> {
+            let messages: Vec<V6<'_>> = r.messages.iter().map(V6).collect();
+            (messages, r.exhausted).serialize(s)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<QueueFetchResp, D::Error> {
+            let Wire { messages, exhausted } = Wire::deserialize(de)?;
+            let messages = messages.into_iter().map(Into::into).collect();
+            Ok(QueueFetchResp { messages, exhausted })
+        }
+    }
 }
 
 /// Outer DHT framing wrapper. The wire grammar is open to non-RPC traffic
@@ -1302,6 +1954,7 @@ mod tests {
     use ed25519_dalek::SigningKey;
 
     use super::*;
+    use crate::MIN_PROTOCOL_VERSION;
     use crate::PROTOCOL_VERSION;
     use crate::crypto::get_signing_key;
     use crate::proto::pack::Packer;
@@ -1322,9 +1975,24 @@ mod tests {
     fn build_dht_hello(key: &SigningKey, timestamp: u64) -> DhtHello {
         let pubkey: [u8; 32] = key.verifying_key().to_bytes();
         let node_id = NodeId::new(pubkey);
-        let msg = dht_hello_signing_input(&node_id, &pubkey, timestamp);
+        let (min_version, max_version) = (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+        let msg = dht_hello_signing_input(
+            PROTOCOL_VERSION,
+            &node_id,
+            &pubkey,
+            timestamp,
+            min_version,
+            max_version,
+        );
         let sig = key.sign(&msg);
-        DhtHello { node_id, pubkey: pubkey.into(), timestamp, sig: sig.to_bytes().into() }
+        DhtHello {
+            node_id,
+            pubkey: pubkey.into(),
+            timestamp,
+            min_version,
+            max_version,
+            sig: sig.to_bytes().into(),
+        }
     }
 
     #[test]
@@ -1350,23 +2018,25 @@ mod tests {
         let node_id = NodeId::from_bytes(bytes);
         let timestamp: u64 = 0xDEAD_BEEF_CAFE_F00D;
 
-        let buf = dht_hello_signing_input(&node_id, &pubkey, timestamp);
+        let buf = dht_hello_signing_input(7, &node_id, &pubkey, timestamp, 6, 8);
 
         // Domain (20) + version (2) + node_id (32) + pubkey (32) +
-        // ts (8) = 94 bytes. Anchor on the total length so a stray
-        // field change is caught immediately.
-        assert_eq!(buf.len(), DHT_HELLO_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
+        // ts (8) + range (4) = 98 bytes. Anchor on the total length so a
+        // stray field change is caught immediately.
+        assert_eq!(buf.len(), DHT_HELLO_SIG_DOMAIN.len() + 2 + 32 + 32 + 8 + 4);
 
         // Spot-check the header.
         assert!(buf.starts_with(DHT_HELLO_SIG_DOMAIN));
         let off = DHT_HELLO_SIG_DOMAIN.len();
-        assert_eq!(&buf[off..off + 2], &PROTOCOL_VERSION.to_be_bytes());
+        assert_eq!(&buf[off..off + 2], &7u16.to_be_bytes());
         let off = off + 2;
         assert_eq!(&buf[off..off + 32], node_id.as_bytes());
         let off = off + 32;
         assert_eq!(&buf[off..off + 32], &pubkey);
         let off = off + 32;
         assert_eq!(&buf[off..off + 8], &timestamp.to_be_bytes());
+        let off = off + 8;
+        assert_eq!(&buf[off..], &[0, 6, 0, 8]);
     }
 
     #[test]
@@ -1375,10 +2045,10 @@ mod tests {
         let now: u64 = 1_700_000_000_000;
         let hello = build_dht_hello(&key, now);
         // ±0 skew → must accept.
-        hello.verify(now).expect("freshly-signed hello must verify");
+        assert_eq!(hello.verify(PROTOCOL_VERSION, now), Ok(()));
         // Inside the skew window → must accept.
-        hello.verify(now + MAX_DHT_HELLO_SKEW_MS - 1).expect("inside skew");
-        hello.verify(now - (MAX_DHT_HELLO_SKEW_MS - 1)).expect("inside skew");
+        hello.verify(PROTOCOL_VERSION, now + MAX_DHT_HELLO_SKEW_MS - 1).expect("inside skew");
+        hello.verify(PROTOCOL_VERSION, now - (MAX_DHT_HELLO_SKEW_MS - 1)).expect("inside skew");
     }
 
     #[test]
@@ -1394,7 +2064,7 @@ mod tests {
         // the original (a-derived) pubkey + sig.
         let fake_id = NodeId::new(key_b.verifying_key().to_bytes());
         hello.node_id = fake_id;
-        match hello.verify(now) {
+        match hello.verify(PROTOCOL_VERSION, now) {
             Err(DhtHelloVerifyError::IdMismatch) => {},
             other => panic!("expected IdMismatch, got {other:?}"),
         }
@@ -1408,19 +2078,78 @@ mod tests {
 
         // Stale: timestamp ~2 minutes in the past.
         let stale = build_dht_hello(&key, now - 120_000);
-        match stale.verify(now) {
+        match stale.verify(PROTOCOL_VERSION, now) {
             Err(DhtHelloVerifyError::ClockSkew) => {},
             other => panic!("expected ClockSkew (stale), got {other:?}"),
         }
 
         // Future: timestamp ~2 minutes in the future.
         let future = build_dht_hello(&key, now + 120_000);
-        match future.verify(now) {
+        match future.verify(PROTOCOL_VERSION, now) {
             Err(DhtHelloVerifyError::ClockSkew) => {},
             other => panic!("expected ClockSkew (future), got {other:?}"),
         }
     }
 
+    #[test]
+    fn dht_hello_verify_rejects_a_range_outside_ours() {
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let mut hello = build_dht_hello(&key, now);
+        hello.min_version = PROTOCOL_VERSION + 1;
+        hello.max_version = PROTOCOL_VERSION + 3;
+        assert_eq!(hello.verify(PROTOCOL_VERSION, now), Err(DhtHelloVerifyError::NoCommonVersion));
+    }
+
+    #[test]
+    fn dht_hello_verify_rejects_a_session_below_the_range() {
+        // Both of us speak the newest version, so a session the ALPN settled
+        // lower was narrowed by someone on the path.
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let hello = build_dht_hello(&key, now);
+        assert_eq!(
+            hello.verify(PROTOCOL_VERSION - 1, now),
+            Err(DhtHelloVerifyError::NoCommonVersion)
+        );
+    }
+
+    /// A relay from before version negotiation sends this on `peer/6` and
+    /// verifies it against this transcript; both are frozen.
+    #[test]
+    fn dht_hello_v6_keeps_protocol_6s_layout() {
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let pubkey: [u8; 32] = key.verifying_key().to_bytes();
+        let node_id = NodeId::new(pubkey);
+
+        let msg = dht_hello_v6_signing_input(&node_id, &pubkey, now);
+        assert_eq!(msg.len(), DHT_HELLO_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
+        let off = DHT_HELLO_SIG_DOMAIN.len();
+        assert_eq!(&msg[off..off + 2], &6u16.to_be_bytes());
+
+        let sig = key.sign(&msg).to_bytes().into();
+        let hello = DhtHelloV6 { node_id, pubkey: pubkey.into(), timestamp: now, sig };
+        let bytes = hello.ser().unwrap();
+        assert_eq!(DhtHelloV6::deser(&bytes).unwrap(), hello);
+        assert_eq!(hello.verify(now), Ok(()));
+
+        let mut forged = hello;
+        forged.timestamp += 1;
+        assert_eq!(forged.verify(now), Err(DhtHelloVerifyError::BadSignature));
+    }
+
+    #[test]
+    fn dht_hello_verify_rejects_a_rewritten_range() {
+        // The range is signed, so nobody in between can narrow it to force
+        // an older version.
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let mut hello = build_dht_hello(&key, now);
+        hello.min_version = 0;
+        assert_eq!(hello.verify(PROTOCOL_VERSION, now), Err(DhtHelloVerifyError::BadSignature));
+    }
+
     #[test]
     fn dht_hello_verify_rejects_bad_signature() {
         // Flip one bit in the signature — verify must fail.
@@ -1428,7 +2157,7 @@ mod tests {
         let now: u64 = 1_700_000_000_000;
         let mut hello = build_dht_hello(&key, now);
         hello.sig.0[0] ^= 0x01;
-        match hello.verify(now) {
+        match hello.verify(PROTOCOL_VERSION, now) {
             Err(DhtHelloVerifyError::BadSignature) => {},
             other => panic!("expected BadSignature, got {other:?}"),
         }
@@ -1463,6 +2192,8 @@ mod tests {
             sig:            sig.to_bytes().into(),
             accepted_at_ms: 1,
             wake:           false,
+            wake_hint:      None,
+            token:          None,
         }
     }
 
@@ -1492,6 +2223,63 @@ mod tests {
         }
     }
 
+    /// `Forward` and `QueueFetch` replies go out in the v6 layout unless the
+    /// connection is at 7, so a relay a version behind decodes them as the
+    /// structs it knows.
+    #[test]
+    fn dispatch_carriers_keep_the_v6_layout_for_older_peers() {
+        use crate::proto::client_rel::DispatchV6P;
+
+        #[derive(Serialize)]
+        struct V6Forward {
+            dispatch:        DispatchV6P,
+            sender_relay_id: NodeId,
+            timestamp:       u64,
+            sig:             Bytes<64>,
+        }
+
+        let user = SigningKey::from_bytes(&[1; 32]);
+        let relay = SigningKey::from_bytes(&[2; 32]);
+        let mut dispatch = build_dispatch(&user, &[3; 32], [4; 16], b"hi");
+        let old = DispatchV6P {
+            to:             dispatch.to,
+            from:           dispatch.from,
+            id:             dispatch.id,
+            payload:        dispatch.payload.clone(),
+            sig:            dispatch.sig,
+            accepted_at_ms: dispatch.accepted_at_ms,
+            wake:           dispatch.wake,
+        };
+        dispatch.wake_hint = Some(vec![5; 4].into());
+        let fwd = build_forward(&relay, dispatch.clone(), 6);
+
+        let bytes = DhtRequest::Forward(fwd.clone()).ser().unwrap();
+        let v6 = V6Forward {
+            dispatch:        old.clone(),
+            sender_relay_id: fwd.sender_relay_id,
+            timestamp:       fwd.timestamp,
+            sig:             fwd.sig,
+        };
+        assert_eq!(bytes[0], 1, "Forward keeps its variant index");
+        assert_eq!(&bytes[1..], &v6.ser().unwrap()[..]);
+        let DhtRequest::Forward(read) = DhtRequest::deser(&bytes).unwrap() else {
+            panic!("not a Forward");
+        };
+        assert_eq!(read.dispatch, old.clone().into());
+
+        let v7 = DhtRequest::ForwardV7(fwd);
+        assert_eq!(DhtRequest::deser(&v7.ser().unwrap()).unwrap(), v7);
+
+        let resp = QueueFetchResp { messages: vec![dispatch.clone()], exhausted: true };
+        let bytes = DhtResponse::QueueFetch(resp.clone()).ser().unwrap();
+        let DhtResponse::QueueFetch(read) = DhtResponse::deser(&bytes).unwrap() else {
+            panic!("not a QueueFetch");
+        };
+        assert_eq!(read.messages, vec![old.into()]);
+        let v7 = DhtResponse::QueueFetchV7(resp);
+        assert_eq!(DhtResponse::deser(&v7.ser().unwrap()).unwrap(), v7);
+    }
+
     fn build_queue_fetch_ack(
         user: &SigningKey, requester_relay_id: NodeId, delivered_ids: Vec<[u8; 16]>, timestamp: u64,
     ) -> QueueFetchAck {
@@ -1533,7 +2321,7 @@ mod tests {
         assert_eq!(buf.len(), DHT_FORWARD_SIG_DOMAIN.len() + 2 + 16 + 32 + 8);
         assert!(buf.starts_with(DHT_FORWARD_SIG_DOMAIN));
         let off = DHT_FORWARD_SIG_DOMAIN.len();
-        assert_eq!(&buf[off..off + 2], &PROTOCOL_VERSION.to_be_bytes());
+        assert_eq!(&buf[off..off + 2], &MIN_PROTOCOL_VERSION.to_be_bytes());
         let off = off + 2;
         assert_eq!(&buf[off..off + 16], &id_bytes);
         let off = off + 16;
@@ -1555,7 +2343,7 @@ mod tests {
         assert_eq!(buf.len(), DHT_QUEUE_FETCH_SIG_DOMAIN.len() + 2 + 32 + 32 + 8);
         assert!(buf.starts_with(DHT_QUEUE_FETCH_SIG_DOMAIN));
         let off = DHT_QUEUE_FETCH_SIG_DOMAIN.len();
-        assert_eq!(&buf[off..off + 2], &PROTOCOL_VERSION.to_be_bytes());
+        assert_eq!(&buf[off..off + 2], &MIN_PROTOCOL_VERSION.to_be_bytes());
         let off = off + 2;
         assert_eq!(&buf[off..off + 32], &ipk);
         let off = off + 32;
@@ -1580,7 +2368,7 @@ mod tests {
         assert_eq!(buf.len(), DHT_QUEUE_FETCH_ACK_SIG_DOMAIN.len() + 2 + 32 + 32 + 4 + 2 * 16 + 8);
         assert!(buf.starts_with(DHT_QUEUE_FETCH_ACK_SIG_DOMAIN));
         let off = DHT_QUEUE_FETCH_ACK_SIG_DOMAIN.len();
-        assert_eq!(&buf[off..off + 2], &PROTOCOL_VERSION.to_be_bytes());
+        assert_eq!(&buf[off..off + 2], &MIN_PROTOCOL_VERSION.to_be_bytes());
         let off = off + 2;
         assert_eq!(&buf[off..off + 32], &ipk);
         let off = off + 32;
@@ -1914,6 +2702,8 @@ mod tests {
             sig:            [4u8; 64].into(),
             accepted_at_ms: 1,
             wake:           false,
+            wake_hint:      None,
+            token:          None,
         };
         let resp = QueueFetchResp {
             messages:  vec![dispatch; MAX_FETCH_QUEUE_BATCH + 1],
@@ -1922,4 +2712,119 @@ mod tests {
         let bytes = resp.ser().unwrap();
         assert!(QueueFetchResp::deser(&bytes).is_err());
     }
+
+    #[test]
+    fn quiet_hours_wrap_past_midnight() {
+        const MIN: u64 = 60_000;
+        let night = QuietHours { start_min: 22 * 60, end_min: 7 * 60 };
+        assert!(night.contains(23 * 60 * MIN));
+        assert!(night.contains(3 * 60 * MIN));
+        assert!(!night.contains(12 * 60 * MIN));
+        // The next day wraps back onto the same window.
+        assert!(night.contains((24 + 3) * 60 * MIN));
+        assert!(!QuietHours { start_min: 60, end_min: 60 }.contains(60 * MIN));
+    }
+
+    #[test]
+    fn wake_policy_gates_on_mute_and_allow_list() {
+        let friend = [7u8; 32];
+        let stranger = [8u8; 32];
+        assert!(WakePolicy::default().allows(&stranger, 0));
+
+        let allow = WakePolicy { allowed_senders: vec![Bytes(friend)], ..Default::default() };
+        assert!(allow.allows(&friend, 0));
+        assert!(!allow.allows(&stranger, 0));
+
+        let muted = WakePolicy { mute_all: true, ..allow };
+        assert!(!muted.allows(&friend, 0));
+    }
+
+    #[test]
+    fn wake_policy_signature_covers_the_policy() {
+        let ipk = [1u8; 32];
+        let open = WakePolicy::default();
+        let muted = WakePolicy { mute_all: true, ..Default::default() };
+        assert_ne!(
+            wake_policy_signing_input(&ipk, &open, 5),
+            wake_policy_signing_input(&ipk, &muted, 5)
+        );
+        assert!(wake_policy_signing_input(&ipk, &open, 5).starts_with(DHT_WAKE_POLICY_SIG_DOMAIN));
+    }
+
+    #[test]
+    fn succession_needs_both_keys_to_sign() {
+        let (old, new) = (fresh_signing_key(), fresh_signing_key());
+        let (old_ipk, new_ipk) = (old.verifying_key().to_bytes(), new.verifying_key().to_bytes());
+        let msg = identity_succession_signing_input(&old_ipk, &new_ipk, 7);
+        let succession = IdentitySuccession {
+            old_ipk:   old_ipk.into(),
+            new_ipk:   new_ipk.into(),
+            timestamp: 7,
+            old_sig:   old.sign(&msg).to_bytes().into(),
+            new_sig:   new.sign(&msg).to_bytes().into(),
+        };
+        assert!(succession.verify());
+
+        // A stolen old key alone can't name an heir it doesn't hold.
+        let squatter = fresh_signing_key();
+        let hijack = IdentitySuccession {
+            new_ipk: squatter.verifying_key().to_bytes().into(),
+            ..succession.clone()
+        };
+        assert!(!hijack.verify());
+        // Nor can anyone claim an identity without its old key.
+        let claim = IdentitySuccession { new_sig: old.sign(&msg).to_bytes().into(), ..succession };
+        assert!(!claim.verify());
+    }
+
+    #[test]
+    fn account_deletion_needs_the_deleted_keys_signature() {
+        let (key, other) = (fresh_signing_key(), fresh_signing_key());
+        let ipk = key.verifying_key().to_bytes();
+        let msg = account_deletion_signing_input(&ipk, 9);
+        let sig = key.sign(&msg).to_bytes().into();
+        let deletion = AccountDeletion { ipk: ipk.into(), timestamp: 9, sig };
+        assert!(deletion.verify());
+
+        let sig = other.sign(&msg).to_bytes().into();
+        let forged = AccountDeletion { sig, ..deletion.clone() };
+        assert!(!forged.verify(), "nobody else can delete an account");
+        let redated = AccountDeletion { timestamp: 10, ..deletion };
+        assert!(!redated.verify());
+    }
+
+    #[test]
+    fn a_predecessor_deletion_needs_an_unbroken_chain_to_the_deleting_key() {
+        let link = |old: &SigningKey, new: &SigningKey| {
+            let old_ipk = old.verifying_key().to_bytes();
+            let new_ipk = new.verifying_key().to_bytes();
+            let msg = identity_succession_signing_input(&old_ipk, &new_ipk, 7);
+            IdentitySuccession {
+                old_ipk:   old_ipk.into(),
+                new_ipk:   new_ipk.into(),
+                timestamp: 7,
+                old_sig:   old.sign(&msg).to_bytes().into(),
+                new_sig:   new.sign(&msg).to_bytes().into(),
+            }
+        };
+        let keys = [fresh_signing_key(), fresh_signing_key(), fresh_signing_key()];
+        let ipk = keys[2].verifying_key().to_bytes();
+        let sig = keys[2].sign(&account_deletion_signing_input(&ipk, 9)).to_bytes().into();
+        let deletion = AccountDeletion { ipk: ipk.into(), timestamp: 9, sig };
+        let chain = vec![link(&keys[0], &keys[1]), link(&keys[1], &keys[2])];
+        let whole = PredecessorDeletion { chain: chain.clone(), deletion: deletion.clone() };
+        assert!(whole.verify());
+        assert_eq!(whole.ipk(), Some(keys[0].verifying_key().to_bytes()));
+
+        let gap = PredecessorDeletion { chain: vec![chain[0].clone()], deletion: deletion.clone() };
+        assert!(!gap.verify(), "the chain must reach the deleting key");
+        let stranger = fresh_signing_key();
+        let spliced = PredecessorDeletion {
+            chain:    vec![link(&stranger, &keys[1]), link(&keys[0], &keys[2])],
+            deletion: deletion.clone(),
+        };
+        assert!(!spliced.verify(), "each link takes over from the last");
+        let empty = PredecessorDeletion { chain: Vec::new(), deletion };
+        assert!(!empty.verify());
+    }
 }
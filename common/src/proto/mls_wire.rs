@@ -70,8 +70,53 @@ use crate::types::bytes::Bytes;
 /// It does *not* gate [`AppPayload`]: nothing reads it to accept or reject a
 /// decrypted plaintext. That union stays compatible by ordinal stability —
 /// append new variants, never reorder — so adding one needs no bump here.
+/// An appended ordinal is still unreadable to a client built before it, so
+/// new payloads ride [`AppPayload::Extension`] / [`Body::Extension`] instead:
+/// a decoder that predates a tag skips it whole and keeps the bytes.
 pub const MLS_WIRE_VERSION: u16 = 12;
 
+/// Most options a [`Body::Poll`] may offer, and so the most choices one
+/// [`AppPayload::Vote`] may carry.
+pub const MAX_POLL_OPTIONS: usize = 12;
+
+/// Bars a [`Body::Voice`] waveform may carry: what the bubble draws.
+pub const MAX_WAVEFORM_BARS: usize = 64;
+
+/// Largest AVIF poster a [`Body::Video`] may carry: a 320px frame sits well
+/// inside it.
+pub const MAX_POSTER_BYTES: usize = 24 * 1024;
+
+/// Tokens one [`AppPayload::MailboxGrant`] carries: the current epoch's
+/// mailbox and the next.
+pub const MAX_MAILBOX_TOKENS: usize = 2;
+
+/// [`AppPayload::Extension`] tag of a [`RecoveryShareP`].
+pub const EXT_RECOVERY_SHARE: u32 = 1;
+
+/// [`AppPayload::Extension`] tag of a [`RecoveryRevokeP`].
+pub const EXT_RECOVERY_REVOKE: u32 = 2;
+
+/// [`AppPayload::Extension`] and [`Body::Extension`] tags this build reads.
+/// Anything else is kept undecoded until an update learns it. Tags are never
+/// reused: one names the same payload layout forever.
+pub const KNOWN_EXTENSIONS: &[u32] = &[EXT_RECOVERY_SHARE, EXT_RECOVERY_REVOKE];
+
+/// Variants of [`AppPayload`] this build decodes. With [`BODY_VARIANTS`] and
+/// [`KNOWN_EXTENSIONS`] it says what a build can read, so a payload kept
+/// undecoded is tried again once any of them grows. Raise it with every
+/// appended variant.
+pub const APP_PAYLOAD_VARIANTS: u8 = 25;
+
+/// Variants of [`Body`] this build decodes; see [`APP_PAYLOAD_VARIANTS`].
+pub const BODY_VARIANTS: u8 = 8;
+
+/// Most contacts one identity key is split across for social recovery, and so
+/// the most holders a [`RecoveryShareP`] lists.
+pub const MAX_RECOVERY_HOLDERS: usize = 10;
+
+/// Bytes of the name a [`RecoveryRequestP`] may claim.
+pub const MAX_RECOVERY_NAME_BYTES: usize = 64;
+
 /// The decrypted MLS application plaintext. Was raw UTF-8; now a tagged
 /// union so receipts/edits/etc. ride the same encrypted channel. The
 /// relay never sees this (it's inside the MLS ciphertext). Edit/Delete
@@ -185,6 +230,77 @@ pub enum AppPayload {
     Profile {
         name: String,
     },
+    /// The sender finished downloading attachment `file_id` and now serves it
+    /// too, so other members can pull chunks from them rather than only from
+    /// the original sender. A control message — routed, never stored, never
+    /// wakes. Appended after Profile so postcard ordinals hold.
+    FileHave { file_id: [u8; 32] },
+    /// The sender parked an encrypted copy of `file_id` in the blob store of
+    /// relay `host`, as `blob_id` under `key`, until `expires_at` (ms). Lets
+    /// members pull it while no device holding it is reachable. A control
+    /// message — routed, never stored, never wakes. Appended after FileHave
+    /// so postcard ordinals hold.
+    FileBlob {
+        file_id:    [u8; 32],
+        host:       [u8; 32],
+        blob_id:    [u8; 32],
+        key:        [u8; 32],
+        expires_at: u64,
+    },
+    /// The sender was shown a sticker from `pack` and has no copy of it; any
+    /// member holding the pack may answer with a [`AppPayload::PackOffer`].
+    /// A control message — routed, never stored, never wakes. Appended after
+    /// FileBlob so postcard ordinals hold.
+    PackWant { pack: [u8; 16] },
+    /// The sender serves sticker pack `pack` as the file `file_id` of `size`
+    /// bytes — a serialized, author-signed `sticker::StickerPackP` — pulled
+    /// like an attachment. A control message — routed, never stored, never
+    /// wakes. Appended after PackWant so postcard ordinals hold.
+    PackOffer {
+        pack:    [u8; 16],
+        file_id: [u8; 32],
+        size:    u64,
+    },
+    /// The sender's ballot on the [`Body::Poll`] with dispatch_id `poll`:
+    /// indices into its options, at most one unless it allows several. The
+    /// voter is implicit — the MLS sender, as for [`AppPayload::React`] — and
+    /// each vote replaces that member's previous one; an empty `choices`
+    /// withdraws it. Appended after PackOffer so postcard ordinals hold.
+    Vote {
+        poll:    [u8; 16],
+        #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_POLL_OPTIONS>")]
+        choices: Vec<u32>,
+    },
+    /// The sender stops the poll with dispatch_id `poll` taking votes. Only
+    /// its author may. Appended after Vote so postcard ordinals hold.
+    PollClose { poll: [u8; 16] },
+    /// A message the sender is passing along from another chat, shown as
+    /// forwarded. Carries the body alone: a quote target wouldn't resolve
+    /// here, and naming the chat or author it came from would tell this room
+    /// about that one. Media bodies reuse their bytes or `file_id` as they
+    /// were. Appended after PollClose so postcard ordinals hold.
+    Forward { body: Body },
+    /// The sender's [`crate::proto::client_rel::DeliveryToken`] for us: attach
+    /// it to sealed dispatches addressed to the sender. Replaces any token held
+    /// from them before. A control message — routed, never stored, never
+    /// wakes. Appended after Forward so postcard ordinals hold.
+    DeliveryToken { token: crate::proto::client_rel::DeliveryToken },
+    /// The sender's mailbox seed and tokens for the mailboxes it is about to
+    /// address: with the seed we compute its mailbox id for any epoch, and
+    /// each token, issued by one mailbox's key, authorizes sealed dispatches
+    /// to that mailbox. Replaces any grant held from them before. A control
+    /// message like [`Self::DeliveryToken`]. Appended after DeliveryToken so
+    /// postcard ordinals hold.
+    MailboxGrant {
+        seed:   [u8; 32],
+        #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_MAILBOX_TOKENS>")]
+        tokens: Vec<crate::proto::client_rel::DeliveryToken>,
+    },
+    /// Every payload added from here on: a tag from [`KNOWN_EXTENSIONS`] and
+    /// the payload's own postcard bytes, length-prefixed so a decoder that
+    /// doesn't know the tag still reads past it. The last ordinal this union
+    /// gets — appended after MailboxGrant so postcard ordinals hold.
+    Extension { tag: u32, body: ByteVec },
 }
 
 /// What happened to a group. The *actor* is implicit — the MLS sender of the
@@ -231,11 +347,66 @@ pub enum Body {
     /// Named by pack + index rather than carrying bytes, so re-sending one
     /// costs a handful of bytes. Atomic: no caption, and nothing to pair with
     /// text — which is what keeps it off every other row of the revision
-    /// matrix. Pack distribution is not built yet.
+    /// matrix. A receiver without the pack asks for it with
+    /// [`AppPayload::PackWant`].
     Sticker {
         pack: [u8; 16],
         id:   u32,
     },
+    /// Voice note: Ogg Opus, with its length and a peak waveform so the bubble
+    /// draws before any audio arrives. Inline when it fits the frame (`data`
+    /// set, `file_id` none); otherwise `data` is empty and the bytes are pulled
+    /// like an [`Body::Attachment`]'s. Atomic like a sticker: no caption.
+    /// Appended after Sticker so postcard ordinals hold.
+    Voice {
+        group_id:    Option<[u8; 16]>,
+        mime:        String,
+        duration_ms: u32,
+        #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_WAVEFORM_BARS>")]
+        waveform:    Vec<u8>,
+        size:        u64,
+        data:        Vec<u8>,
+        file_id:     Option<[u8; 32]>,
+    },
+    /// Video: bytes pulled by `file_id` like an [`Body::Attachment`]'s, plus
+    /// what the bubble needs before any of them arrive — dimensions, length
+    /// and a sharp AVIF poster frame. The receiver pulls front-first, so
+    /// playback can start on a partial file. Appended after Voice so postcard
+    /// ordinals hold.
+    Video {
+        caption:     String,
+        group_id:    Option<[u8; 16]>,
+        mime:        String,
+        name:        String,
+        size:        u64,
+        width:       u32,
+        height:      u32,
+        duration_ms: u32,
+        #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_POSTER_BYTES>")]
+        poster:      Vec<u8>,
+        file_id:     [u8; 32],
+    },
+    /// A question put to the chat. Votes arrive as [`AppPayload::Vote`] naming
+    /// this message's dispatch_id; the tally is derived from them, never sent.
+    /// `anonymous` asks clients not to show who chose what — every member's
+    /// device still learns it, since each vote is an ordinary MLS message from
+    /// its voter. `closes_at` (unix seconds) stops votes without a
+    /// [`AppPayload::PollClose`]. Atomic like a sticker: it revises to
+    /// nothing, so nobody's vote can end up under a different question.
+    /// Appended after Video so postcard ordinals hold.
+    Poll {
+        question:  String,
+        #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_POLL_OPTIONS>")]
+        options:   Vec<String>,
+        multi:     bool,
+        anonymous: bool,
+        closes_at: Option<u64>,
+    },
+    /// Every body kind added from here on, framed like
+    /// [`AppPayload::Extension`] so an older receiver still learns that a
+    /// message arrived and who sent it. Appended after Poll so postcard
+    /// ordinals hold.
+    Extension { tag: u32, body: ByteVec },
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -278,7 +449,12 @@ pub const MAX_EPOCH_AHEAD: u64 = 64;
 /// Hard ceiling on the TLS-encoded `Welcome` bytes carried in
 /// [`WelcomeEnvelopeP::welcome_blob`]. Welcomes are bigger than
 /// applications because they carry per-recipient encrypted group
-/// secrets, so they get their own 256 KiB ceiling.
+/// secrets and the whole ratchet tree, so they get their own 256 KiB ceiling.
+///
+/// Every relay enforces this, older ones included, so it cannot grow without
+/// stranding Welcomes at them. Under the X-Wing suite each tree node holds a
+/// ~1.2 KiB public key; libcore keeps such groups small enough to fit
+/// (`MAX_PQ_GROUP_MEMBERS`) instead.
 pub const MAX_WELCOME_BYTES: usize = 256 * 1024;
 
 /// Maximum KeyPackages the publisher may pack into a single
@@ -457,6 +633,52 @@ pub enum MlsEnvelopeP {
     /// the same dispatch/queue channel; the relay treats it as opaque payload.
     /// Appended last so postcard's ordinal tags for Application/Welcome hold.
     PairDecline(PairDeclineP),
+    /// Sealed-sender wrapper around any of the above. The outer
+    /// `DispatchP::from` is a one-shot key; the real sender and its dispatch
+    /// signature are inside, readable only by the recipient. Appended last
+    /// (postcard).
+    Sealed(SealedSenderP),
+    /// A member that lost its state for a group asking to be let back in.
+    /// Not MLS, like PairDecline — the sender holds no group keys to encrypt
+    /// under. Appended after Sealed so postcard ordinals hold.
+    Rejoin(RejoinRequestP),
+    /// A fresh install asking a contact for the recovery share they hold.
+    /// Not MLS: the asker is a stranger until the identity is back. Appended
+    /// after Rejoin so postcard ordinals hold.
+    RecoveryRequest(RecoveryRequestP),
+    /// A holder's answer to a [`Self::RecoveryRequest`], the share sealed to
+    /// the key the request named. Appended after RecoveryRequest.
+    RecoveryReturn(RecoveryReturnP),
+    /// The sender moved its identity to a new key. Not MLS, and sent from
+    /// the new key, which no contact knows yet — the old key's signature is
+    /// what they check it by. Appended after RecoveryReturn.
+    Succession(crate::proto::dht_p2p::IdentitySuccession),
+}
+
+/// HKDF info for the key a [`SealedSenderP`] is encrypted under.
+pub const SEALED_SENDER_SEAL_INFO: &[u8] = b"promtuz-sealed-sender-v1";
+
+/// A [`SealedInnerP`] sealed to the recipient's X25519 push key (the key
+/// wake hints are sealed to): an ephemeral-static DH, HKDF-SHA256 with
+/// [`SEALED_SENDER_SEAL_INFO`], and XChaCha20-Poly1305 with
+/// `to || dispatch_id` as associated data, so a relay can neither re-address
+/// it nor splice it under another dispatch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedSenderP {
+    pub eph_pk:     Bytes<32>,
+    pub nonce:      Bytes<24>,
+    pub ciphertext: ByteVec,
+}
+
+/// Plaintext of a [`SealedSenderP`]: the dispatch as the sender would have
+/// sent it identified. `sig` is the sender's IPK signature over
+/// `dispatch_sig_message(to, from, id, payload)` with the outer `to`/`id`, so
+/// once opened the recipient checks it exactly as it checks a `DeliverP`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedInnerP {
+    pub from:    Bytes<32>,
+    pub sig:     Bytes<64>,
+    pub payload: ByteVec,
 }
 
 /// Application-tier envelope: encrypted MLS message addressed to a
@@ -606,6 +828,172 @@ pub fn pair_decline_signing_input(
     buf
 }
 
+/// Domain separator for the rejoin-request signature.
+pub const REJOIN_SIG_DOMAIN: &[u8] = b"promtuz-rejoin-v1";
+
+/// "I lost my state for `group_id`; re-add me" ([`MlsEnvelopeP::Rejoin`]).
+/// Sent by a device restored from a backup, which carries history and rosters
+/// but no MLS secrets, to a member who may add: that member swaps the dead
+/// leaf for one of the sender's fresh KeyPackages and Welcomes it back.
+///
+/// Signed by the requester so a relay can't make an admin churn a member's
+/// leaf on its own say-so. It names the MLS group rather than a conversation:
+/// conversation ids are minted per device and mean nothing to the recipient.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejoinRequestP {
+    /// Requester IPK (also `DispatchP::from`).
+    pub sender_ipk:    Bytes<32>,
+    /// Who is asked (also `DispatchP::to`); bound so the request can't be
+    /// replayed at another member.
+    pub recipient_ipk: Bytes<32>,
+    /// The group the requester's backup last knew the chat by.
+    pub group_id:      Bytes<32>,
+    /// Unix ms. A recipient acts on a request only once, and not when stale.
+    pub timestamp:     u64,
+    /// Ed25519 signature over [`rejoin_signing_input`] under `sender_ipk`.
+    pub sig:           Bytes<64>,
+}
+
+/// Canonical bytes signed/verified for a [`RejoinRequestP`].
+/// Layout: `REJOIN_SIG_DOMAIN || MLS_WIRE_VERSION_BE || sender || recipient || group_id ||
+/// timestamp_be`
+pub fn rejoin_signing_input(
+    sender_ipk: &[u8; 32], recipient_ipk: &[u8; 32], group_id: &[u8; 32], timestamp: u64,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(REJOIN_SIG_DOMAIN.len() + 2 + 32 + 32 + 32 + 8);
+    buf.extend_from_slice(REJOIN_SIG_DOMAIN);
+    buf.extend_from_slice(&MLS_WIRE_VERSION.to_be_bytes());
+    buf.extend_from_slice(sender_ipk);
+    buf.extend_from_slice(recipient_ipk);
+    buf.extend_from_slice(group_id);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf
+}
+
+/// One Shamir share of the sender's identity key (`common::crypto::shamir`),
+/// sent over MLS to a contact chosen to hold it ([`EXT_RECOVERY_SHARE`]).
+/// Replaces any share held from the sender before.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryShareP {
+    /// Names one split. A new split gets a new id, and shares of different
+    /// splits never combine.
+    pub set_id:    Bytes<16>,
+    pub index:     u8,
+    pub threshold: u8,
+    pub share:     Bytes<32>,
+    /// Everyone holding a share of this split, so a recovering device that
+    /// reaches one of them learns whom else to ask.
+    #[serde(deserialize_with = "crate::proto::pack::bounded_vec::<_, _, MAX_RECOVERY_HOLDERS>")]
+    pub holders:   Vec<Bytes<32>>,
+}
+
+/// Forget the share of `set_id` ([`EXT_RECOVERY_REVOKE`]): the sender split
+/// again without us, or turned social recovery off.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryRevokeP {
+    pub set_id: Bytes<16>,
+}
+
+/// Domain separator for the recovery-request signature.
+pub const RECOVERY_REQUEST_SIG_DOMAIN: &[u8] = b"promtuz-recovery-request-v1";
+
+/// Domain separator for the recovery-return signature.
+pub const RECOVERY_RETURN_SIG_DOMAIN: &[u8] = b"promtuz-recovery-return-v1";
+
+/// HKDF info for the key a [`RecoveryReturnP`] is sealed under.
+pub const RECOVERY_SEAL_INFO: &[u8] = b"promtuz-recovery-share-seal-v1";
+
+/// "I lost my identity; send back the share you hold for me"
+/// ([`MlsEnvelopeP::RecoveryRequest`]). Sent from a fresh install, under the
+/// throwaway identity it enrolled to reach a relay at all. The signature
+/// proves only that; the holder's user has to confirm out of band — by the
+/// code both screens show — that the asker is who they claim.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryRequestP {
+    /// The fresh install's IPK (also `DispatchP::from`).
+    pub sender_ipk:    Bytes<32>,
+    /// The holder asked (also `DispatchP::to`).
+    pub recipient_ipk: Bytes<32>,
+    /// X25519 key the share is to be sealed to.
+    pub seal_pk:       Bytes<32>,
+    /// Who the asker says they are, for the holder's prompt. Unverified.
+    pub name:          String,
+    /// Unix ms.
+    pub timestamp:     u64,
+    /// Ed25519 signature over [`recovery_request_signing_input`] under
+    /// `sender_ipk`.
+    pub sig:           Bytes<64>,
+}
+
+/// Canonical bytes signed/verified for a [`RecoveryRequestP`].
+/// Layout: `RECOVERY_REQUEST_SIG_DOMAIN || MLS_WIRE_VERSION_BE || sender || recipient ||
+/// seal_pk || timestamp_be || name_len_be16 || name`
+pub fn recovery_request_signing_input(
+    sender_ipk: &[u8; 32], recipient_ipk: &[u8; 32], seal_pk: &[u8; 32], name: &str,
+    timestamp: u64,
+) -> Vec<u8> {
+    let mut buf =
+        Vec::with_capacity(RECOVERY_REQUEST_SIG_DOMAIN.len() + 2 + 96 + 8 + 2 + name.len());
+    buf.extend_from_slice(RECOVERY_REQUEST_SIG_DOMAIN);
+    buf.extend_from_slice(&MLS_WIRE_VERSION.to_be_bytes());
+    buf.extend_from_slice(sender_ipk);
+    buf.extend_from_slice(recipient_ipk);
+    buf.extend_from_slice(seal_pk);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    buf
+}
+
+/// What a [`RecoveryReturnP`] seals: the share, and whose identity it is a
+/// share of — the recovering device doesn't know its own IPK until told.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReturnedShareP {
+    pub owner_ipk: Bytes<32>,
+    pub share:     RecoveryShareP,
+}
+
+/// A holder's answer ([`MlsEnvelopeP::RecoveryReturn`]): a [`ReturnedShareP`]
+/// sealed to the request's `seal_pk` — ephemeral-static X25519, HKDF-SHA256
+/// with [`RECOVERY_SEAL_INFO`], XChaCha20-Poly1305 with the recipient IPK as
+/// associated data — and signed by the holder.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReturnP {
+    /// The holder's IPK (also `DispatchP::from`).
+    pub sender_ipk:    Bytes<32>,
+    /// The fresh install that asked (also `DispatchP::to`).
+    pub recipient_ipk: Bytes<32>,
+    pub eph_pk:        Bytes<32>,
+    pub nonce:         Bytes<24>,
+    pub ciphertext:    ByteVec,
+    /// Unix ms.
+    pub timestamp:     u64,
+    /// Ed25519 signature over [`recovery_return_signing_input`] under
+    /// `sender_ipk`.
+    pub sig:           Bytes<64>,
+}
+
+/// Canonical bytes signed/verified for a [`RecoveryReturnP`].
+/// Layout: `RECOVERY_RETURN_SIG_DOMAIN || MLS_WIRE_VERSION_BE || sender || recipient ||
+/// eph_pk || nonce || timestamp_be || ciphertext`
+pub fn recovery_return_signing_input(
+    sender_ipk: &[u8; 32], recipient_ipk: &[u8; 32], eph_pk: &[u8; 32], nonce: &[u8; 24],
+    ciphertext: &[u8], timestamp: u64,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(
+        RECOVERY_RETURN_SIG_DOMAIN.len() + 2 + 96 + 24 + 8 + ciphertext.len(),
+    );
+    buf.extend_from_slice(RECOVERY_RETURN_SIG_DOMAIN);
+    buf.extend_from_slice(&MLS_WIRE_VERSION.to_be_bytes());
+    buf.extend_from_slice(sender_ipk);
+    buf.extend_from_slice(recipient_ipk);
+    buf.extend_from_slice(eph_pk);
+    buf.extend_from_slice(nonce);
+    buf.extend_from_slice(&timestamp.to_be_bytes());
+    buf.extend_from_slice(ciphertext);
+    buf
+}
+
 /// Build the canonical signing transcript for
 /// [`MlsApplicationEnvelopeP::sender_sig`].
 ///
@@ -1463,6 +1851,90 @@ mod tests {
         );
     }
 
+    /// A rejoin request is bound to the group and the member asked: the same
+    /// signature can't re-admit its sender to another group, or be spent on
+    /// another member. Its envelope ordinal follows Sealed.
+    #[test]
+    fn rejoin_request_binds_group_and_recipient() {
+        use crate::proto::pack::Packer;
+        use ed25519_dalek::Verifier;
+        let requester = fresh_signing_key();
+        let from = requester.verifying_key().to_bytes();
+        let admin: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let other: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let msg = rejoin_signing_input(&from, &admin, &[7; 32], 42);
+        let sig = requester.sign(&msg);
+        assert!(requester.verifying_key().verify(&msg, &sig).is_ok());
+        for moved in [
+            rejoin_signing_input(&from, &admin, &[8; 32], 42),
+            rejoin_signing_input(&from, &other, &[7; 32], 42),
+            rejoin_signing_input(&from, &admin, &[7; 32], 43),
+        ] {
+            assert!(requester.verifying_key().verify(&moved, &sig).is_err());
+        }
+        let env = MlsEnvelopeP::Rejoin(RejoinRequestP {
+            sender_ipk:    Bytes(from),
+            recipient_ipk: Bytes(admin),
+            group_id:      Bytes([7; 32]),
+            timestamp:     42,
+            sig:           Bytes(sig.to_bytes()),
+        });
+        assert_eq!(env.ser().unwrap()[0], 4);
+    }
+
+    #[test]
+    fn recovery_envelopes_bind_their_fields_and_cap_holders() {
+        use crate::proto::pack::Packer;
+        use ed25519_dalek::Verifier;
+        let asker = fresh_signing_key();
+        let from = asker.verifying_key().to_bytes();
+        let holder: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let msg = recovery_request_signing_input(&from, &holder, &[3; 32], "ana", 42);
+        let sig = asker.sign(&msg);
+        for moved in [
+            recovery_request_signing_input(&from, &holder, &[4; 32], "ana", 42),
+            recovery_request_signing_input(&from, &holder, &[3; 32], "ann", 42),
+            recovery_request_signing_input(&from, &[0; 32], &[3; 32], "ana", 42),
+        ] {
+            assert!(asker.verifying_key().verify(&moved, &sig).is_err());
+        }
+        let req = MlsEnvelopeP::RecoveryRequest(RecoveryRequestP {
+            sender_ipk:    Bytes(from),
+            recipient_ipk: Bytes(holder),
+            seal_pk:       Bytes([3; 32]),
+            name:          "ana".into(),
+            timestamp:     42,
+            sig:           Bytes(sig.to_bytes()),
+        });
+        // Appended after Rejoin (4).
+        assert_eq!(req.ser().unwrap()[0], 5);
+
+        let share = |n| RecoveryShareP {
+            set_id:    Bytes([1; 16]),
+            index:     1,
+            threshold: 2,
+            share:     Bytes([9; 32]),
+            holders:   vec![Bytes([2; 32]); n],
+        };
+        let ok = share(MAX_RECOVERY_HOLDERS);
+        assert_eq!(RecoveryShareP::deser(&ok.ser().unwrap()).unwrap(), ok);
+        assert!(RecoveryShareP::deser(&share(MAX_RECOVERY_HOLDERS + 1).ser().unwrap()).is_err());
+    }
+
+    #[test]
+    fn succession_envelope_is_appended_after_recovery_return() {
+        use crate::proto::dht_p2p::IdentitySuccession;
+        use crate::proto::pack::Packer;
+        let env = MlsEnvelopeP::Succession(IdentitySuccession {
+            old_ipk:   Bytes([1; 32]),
+            new_ipk:   Bytes([2; 32]),
+            timestamp: 42,
+            old_sig:   Bytes([3; 64]),
+            new_sig:   Bytes([4; 64]),
+        });
+        assert_eq!(env.ser().unwrap()[0], 7);
+    }
+
     /// Build a `KeyPackageRecord` with internally-consistent fields.
     /// The `kp_bytes` field is opaque (we just stuff `payload` in;
     /// the openmls TLS-encoded form is produced by libcore client code).
@@ -1976,5 +2448,71 @@ mod tests {
         assert_eq!(AppPayload::deser(&att.ser().unwrap()).unwrap(), att);
         let want = AppPayload::FileWant { file_id: [7u8;32] };
         assert_eq!(AppPayload::deser(&want.ser().unwrap()).unwrap(), want);
+        let have = AppPayload::FileHave { file_id: [7u8;32] };
+        assert_eq!(AppPayload::deser(&have.ser().unwrap()).unwrap(), have);
+        let voice = AppPayload::Post { reply_to: None, body: Body::Voice {
+            group_id: Some([2u8;16]), mime: "audio/ogg".into(), duration_ms: 1500,
+            waveform: vec![0, 9, 255], size: 3, data: vec![1, 2, 3], file_id: None } };
+        assert_eq!(AppPayload::deser(&voice.ser().unwrap()).unwrap(), voice);
+        let flood = AppPayload::Post { reply_to: None, body: Body::Voice {
+            group_id: None, mime: "audio/ogg".into(), duration_ms: 1500,
+            waveform: vec![7; MAX_WAVEFORM_BARS + 1], size: 3, data: vec![1, 2, 3],
+            file_id: None } };
+        assert!(AppPayload::deser(&flood.ser().unwrap()).is_err());
+        let video = AppPayload::Post { reply_to: None, body: Body::Video {
+            caption: "clip".into(), group_id: None, mime: "video/mp4".into(), name: "a.mp4".into(),
+            size: 9, width: 1920, height: 1080, duration_ms: 4200, poster: vec![4, 5],
+            file_id: [8u8;32] } };
+        assert_eq!(AppPayload::deser(&video.ser().unwrap()).unwrap(), video);
+        let huge = AppPayload::Post { reply_to: None, body: Body::Video {
+            caption: "".into(), group_id: None, mime: "video/mp4".into(), name: "a.mp4".into(),
+            size: 9, width: 1, height: 1, duration_ms: 1, poster: vec![0; MAX_POSTER_BYTES + 1],
+            file_id: [8u8;32] } };
+        assert!(AppPayload::deser(&huge.ser().unwrap()).is_err());
+        let blob = AppPayload::FileBlob { file_id: [7u8;32], host: [1u8;32], blob_id: [2u8;32],
+            key: [3u8;32], expires_at: 9 };
+        assert_eq!(AppPayload::deser(&blob.ser().unwrap()).unwrap(), blob);
+        let pack_want = AppPayload::PackWant { pack: [5u8;16] };
+        assert_eq!(AppPayload::deser(&pack_want.ser().unwrap()).unwrap(), pack_want);
+        let pack_offer = AppPayload::PackOffer { pack: [5u8;16], file_id: [6u8;32], size: 77 };
+        assert_eq!(AppPayload::deser(&pack_offer.ser().unwrap()).unwrap(), pack_offer);
+        let poll = AppPayload::Post { reply_to: None, body: Body::Poll {
+            question: "lunch?".into(), options: vec!["yes".into(), "no".into()], multi: false,
+            anonymous: true, closes_at: Some(99) } };
+        assert_eq!(AppPayload::deser(&poll.ser().unwrap()).unwrap(), poll);
+        let vote = AppPayload::Vote { poll: [4u8;16], choices: vec![1] };
+        assert_eq!(AppPayload::deser(&vote.ser().unwrap()).unwrap(), vote);
+        let close = AppPayload::PollClose { poll: [4u8;16] };
+        assert_eq!(AppPayload::deser(&close.ser().unwrap()).unwrap(), close);
+        // A ballot past the option cap is refused at decode, not tallied.
+        let stuffed = AppPayload::Vote { poll: [4u8;16], choices: (0..13).collect() };
+        assert!(AppPayload::deser(&stuffed.ser().unwrap()).is_err());
+        let fwd = AppPayload::Forward { body: Body::Sticker { pack: [5u8;16], id: 2 } };
+        assert_eq!(AppPayload::deser(&fwd.ser().unwrap()).unwrap(), fwd);
+    }
+
+    #[test]
+    fn extensions_frame_unknown_payloads_and_keep_ordinals() {
+        use crate::proto::pack::Packer;
+        use crate::proto::pack::Unpacker;
+        let ext = AppPayload::Extension { tag: 7, body: ByteVec(vec![1, 2, 3]) };
+        let bytes = ext.ser().unwrap();
+        // Appended last: MailboxGrant is 23, so Extension is 24.
+        assert_eq!(bytes[0], 24);
+        assert_eq!(bytes[0], APP_PAYLOAD_VARIANTS - 1, "count the new variant");
+        let body = postcard::to_allocvec(&Body::Extension { tag: 9, body: ByteVec(vec![]) });
+        assert_eq!(body.unwrap()[0], BODY_VARIANTS - 1, "count the new variant");
+        assert_eq!(AppPayload::deser(&bytes).unwrap(), ext);
+        let post = AppPayload::Post {
+            reply_to: None,
+            body:     Body::Extension { tag: 9, body: ByteVec(vec![4; 40]) },
+        };
+        assert_eq!(AppPayload::deser(&post.ser().unwrap()).unwrap(), post);
+        // A body this build can't name is still a Post with a reply target.
+        let quoted = AppPayload::Post {
+            reply_to: Some([2; 16]),
+            body:     Body::Extension { tag: 9, body: ByteVec(vec![]) },
+        };
+        assert_eq!(AppPayload::deser(&quoted.ser().unwrap()).unwrap(), quoted);
     }
 }
@@ -9,6 +9,7 @@ use tokio::io::AsyncWriteExt;
 use crate::proto::pack::Packer;
 use crate::quic::id::NodeId;
 
+pub mod blob;
 pub mod client_peer;
 pub mod client_rel;
 pub mod client_res;
@@ -20,6 +21,7 @@ pub mod peer;
 pub mod push;
 #[cfg(feature = "server")]
 pub mod relay_res;
+pub mod sticker;
 
 pub type RelayId = NodeId;
 pub type ResolverId = NodeId;
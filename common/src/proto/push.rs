@@ -23,6 +23,28 @@ pub const MAX_PUSH_TOKEN_BYTES: usize = 512;
 /// FCM's own limit on a data message.
 pub const MAX_WAKE_PAYLOAD_BYTES: usize = 4096;
 
+/// Sealed wake hint a sender may attach to a dispatch
+/// (`DispatchP::wake_hint`). Well under [`MAX_WAKE_PAYLOAD_BYTES`]: a
+/// [`WakeHint`] seals to about 200 bytes, and the hint is queued with every
+/// copy of the dispatch.
+pub const MAX_WAKE_HINT_BYTES: usize = 512;
+
+/// HKDF info for the key a [`SealedWakeHint`] is encrypted under.
+pub const WAKE_HINT_SEAL_INFO: &[u8] = b"promtuz-wake-hint-seal-v1";
+
+/// Domain for the sender's signature inside a [`WakeHint`].
+pub const WAKE_HINT_SIG_DOMAIN: &[u8] = b"promtuz-wake-hint-sig-v1";
+
+/// Wakes per [`WakeBatch`]. With contentless payloads a full batch is ~2 KiB;
+/// the bound keeps a hostile relay from handing the gateway an unbounded
+/// dispatch fan-out in one frame.
+pub const MAX_WAKE_BATCH: usize = 64;
+
+/// The byte a gateway writes back once it has decoded a [`WakeBatch`]. A
+/// gateway that predates `WakeBatch` drops the stream without it, which is
+/// how the relay learns to send that gateway single `Wake`s instead.
+pub const WAKE_BATCH_ACK: u8 = 1;
+
 /// Which platform wake service a token targets. The tag travels with every
 /// registration so the gateway can add APNs / UnifiedPush as new dispatch arms
 /// without a registry migration — the iOS-readiness pin.
@@ -80,20 +102,69 @@ pub struct RegisterToken {
 pub struct WakeRequest {
     /// Recipient's push pseudonym `P` (the relay holds `IPK → P`).
     pub pseudonym: Bytes<32>,
-    /// Wake payload: the queued MLS ciphertext envelope or a contentless
-    /// pointer, bounded by [`MAX_WAKE_PAYLOAD_BYTES`]. The gateway forwards
-    /// it blind.
+    /// Wake payload: a postcard [`SealedWakeHint`] when the sender attached
+    /// one, else empty (contentless), bounded by [`MAX_WAKE_PAYLOAD_BYTES`].
+    /// The gateway forwards it blind.
     #[serde(deserialize_with = "bounded_vec::<_, _, MAX_WAKE_PAYLOAD_BYTES>")]
     pub payload:   Vec<u8>,
 }
 
+/// What the recipient's notification shows before it has drained: who wrote
+/// and in which conversation. Signed by the sender so a relay or a stranger
+/// holding the public push key can't seal a hint in someone else's name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeHint {
+    pub sender:       Bytes<32>,
+    pub conversation: Bytes<16>,
+    /// The dispatch this hint announces; the later drain dedups against it.
+    pub dispatch_id:  Bytes<16>,
+    /// Sender's IPK signature over [`wake_hint_signing_input`].
+    pub sig:          Bytes<64>,
+}
+
+pub fn wake_hint_signing_input(
+    recipient: &[u8; 32], sender: &[u8; 32], conversation: &[u8; 16], dispatch_id: &[u8; 16],
+) -> Vec<u8> {
+    let mut v = Vec::with_capacity(WAKE_HINT_SIG_DOMAIN.len() + 32 + 32 + 16 + 16);
+    v.extend_from_slice(WAKE_HINT_SIG_DOMAIN);
+    v.extend_from_slice(recipient);
+    v.extend_from_slice(sender);
+    v.extend_from_slice(conversation);
+    v.extend_from_slice(dispatch_id);
+    v
+}
+
+/// A [`WakeHint`] sealed to the recipient device's X25519 push key: an
+/// ephemeral-static DH, HKDF-SHA256 with [`WAKE_HINT_SEAL_INFO`], and
+/// XChaCha20-Poly1305 with the recipient IPK as associated data. Rides in
+/// `DispatchP::wake_hint` and becomes the [`WakeRequest`] payload, so relay
+/// and gateway only ever hold ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedWakeHint {
+    pub eph_pk:     Bytes<32>,
+    pub nonce:      Bytes<24>,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_WAKE_HINT_BYTES>")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// Several wakes coalesced into one frame, so a burst of offline deliveries
+/// costs the relay one stream on a pooled connection rather than one per
+/// recipient. The gateway dispatches each entry as if it were a lone `Wake`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WakeBatch {
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_WAKE_BATCH>")]
+    pub wakes: Vec<WakeRequest>,
+}
+
 /// One-RPC-per-bi-stream request the gateway unpacks (mirrors the resolver's
-/// `ClientRequest`). `Register` arrives over `client/5`, `Wake` over
-/// `relay/5`.
+/// `ClientRequest`). `Register` arrives over `client/5`, `Wake` and
+/// `WakeBatch` over `relay/5`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PushRequest {
     Register(RegisterToken),
     Wake(WakeRequest),
+    /// Appended last (postcard variant order).
+    WakeBatch(WakeBatch),
 }
 
 #[cfg(feature = "crypto")]
@@ -181,4 +252,36 @@ mod tests {
         let bytes = wake.ser().unwrap();
         assert!(WakeRequest::deser(&bytes).is_err());
     }
+
+    #[test]
+    fn oversize_wake_batch_fails_to_deserialize() {
+        use crate::proto::pack::Packer;
+        use crate::proto::pack::Unpacker;
+
+        let wake = WakeRequest { pseudonym: Bytes([1u8; 32]), payload: Vec::new() };
+        let full = WakeBatch { wakes: vec![wake.clone(); MAX_WAKE_BATCH] };
+        assert_eq!(WakeBatch::deser(&full.ser().unwrap()).unwrap().wakes.len(), MAX_WAKE_BATCH);
+
+        let over = WakeBatch { wakes: vec![wake; MAX_WAKE_BATCH + 1] };
+        assert!(WakeBatch::deser(&over.ser().unwrap()).is_err());
+    }
+
+    #[test]
+    fn sealed_wake_hint_fits_its_bound() {
+        use crate::proto::pack::Packer;
+
+        let hint = WakeHint {
+            sender:       Bytes([1u8; 32]),
+            conversation: Bytes([2u8; 16]),
+            dispatch_id:  Bytes([3u8; 16]),
+            sig:          Bytes([4u8; 64]),
+        };
+        // XChaCha20-Poly1305 appends a 16-byte tag.
+        let sealed = SealedWakeHint {
+            eph_pk:     Bytes([5u8; 32]),
+            nonce:      Bytes([6u8; 24]),
+            ciphertext: vec![0u8; hint.ser().unwrap().len() + 16],
+        };
+        assert!(sealed.ser().unwrap().len() <= MAX_WAKE_HINT_BYTES);
+    }
 }
@@ -24,6 +24,9 @@ pub const RELAY_HEARTBEAT_SIG_DOMAIN: &[u8] = b"promtuz-relay-heartbeat-v1";
 /// relay tags so a relay's hello can't be replayed as a gateway registration.
 pub const GATEWAY_HELLO_SIG_DOMAIN: &[u8] = b"promtuz-gateway-hello-v1";
 
+/// Domain separation tag for [`LifetimeP::RelayTunnel`].
+pub const RELAY_TUNNEL_SIG_DOMAIN: &[u8] = b"promtuz-relay-tunnel-v1";
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum LifetimeP {
     /// Initial registration message sent by a relay node to a resolver.
@@ -33,7 +36,7 @@ pub enum LifetimeP {
     /// binding (`BLAKE3(pubkey)`) and check the attached signature.
     ///
     /// `sig` is an Ed25519 signature over:
-    /// `RELAY_HELLO_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+    /// `RELAY_HELLO_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
     ///   || relay_id (32 bytes) || pubkey (32 bytes) || timestamp (BE u128)`
     RelayHello {
         /// Stable cryptographic ID derived from the node's public key.
@@ -67,7 +70,7 @@ pub enum LifetimeP {
     /// `relay_id` could spoof liveness signals once liveness logic lands.
     ///
     /// `sig` is an Ed25519 signature over:
-    /// `RELAY_HEARTBEAT_SIG_DOMAIN || PROTOCOL_VERSION (BE u16)
+    /// `RELAY_HEARTBEAT_SIG_DOMAIN || MIN_PROTOCOL_VERSION (BE u16)
     ///   || relay_id (32 bytes) || pubkey (32 bytes) || timestamp (BE u128)`
     RelayHeartbeat {
         /// The node's stable cryptographic ID.
@@ -109,6 +112,21 @@ pub enum LifetimeP {
         timestamp:  u128,
         sig:        Bytes<64>,
     },
+
+    /// Advertises the TCP port of the relay's tunnel listener (`[tunnel]
+    /// address` in its config), sent once the resolver has acked
+    /// `RelayHello`. Signed like [`LifetimeP::RelayHello`] with `port` (BE
+    /// u16) appended to the transcript. Its own variant rather than a field
+    /// on the hello, so a resolver that predates it still admits the relay
+    /// and only drops this packet's stream. Appended last (postcard variant
+    /// order).
+    RelayTunnel {
+        relay_id:  RelayId,
+        pubkey:    Bytes<32>,
+        timestamp: u128,
+        port:      u16,
+        sig:       Bytes<64>,
+    },
 }
 
 /// Builds the canonical signing transcript for [`LifetimeP::RelayHello`].
@@ -141,6 +159,16 @@ pub fn gateway_hello_signing_input(
     signing_input(GATEWAY_HELLO_SIG_DOMAIN, gateway_id, pubkey, timestamp)
 }
 
+/// Builds the canonical signing transcript for [`LifetimeP::RelayTunnel`]:
+/// the relay helpers' layout under its own domain tag, then `port`.
+pub fn relay_tunnel_signing_input(
+    relay_id: &RelayId, pubkey: &[u8; 32], timestamp: u128, port: u16,
+) -> Vec<u8> {
+    let mut buf = signing_input(RELAY_TUNNEL_SIG_DOMAIN, relay_id, pubkey, timestamp);
+    buf.extend_from_slice(&port.to_be_bytes());
+    buf
+}
+
 /// Shared low-level transcript builder. Kept private so callers go through
 /// the per-packet helpers above and can't accidentally pass the wrong
 /// domain tag.
@@ -149,7 +177,7 @@ fn signing_input(
 ) -> Vec<u8> {
     let mut buf = Vec::with_capacity(domain.len() + 2 + RelayId::LEN + 32 + 16);
     buf.extend_from_slice(domain);
-    buf.extend_from_slice(&crate::PROTOCOL_VERSION.to_be_bytes());
+    buf.extend_from_slice(&crate::MIN_PROTOCOL_VERSION.to_be_bytes());
     buf.extend_from_slice(relay_id.as_bytes());
     buf.extend_from_slice(pubkey);
     buf.extend_from_slice(&timestamp.to_be_bytes());
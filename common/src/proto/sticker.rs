@@ -0,0 +1,179 @@
+//! Sticker pack manifests: a signed, content-addressed bundle of AVIF frames
+//! that `Body::Sticker` names by `pack` + index.
+//!
+//! The pack id is a hash over the title, the author and every frame, so a
+//! pack fetched from whichever member offered it either is the pack the
+//! sticker named or is discarded. The author's IPK signs that id: anyone may
+//! pass a pack along, nobody may alter one and keep the author's name on it.
+//!
+//! Packs travel as files — the serialized [`StickerPackP`] — over the same
+//! transfer engine and relay blob path as attachments, announced in the
+//! MLS-encrypted `AppPayload::PackOffer`.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::proto::pack::bounded_vec;
+use crate::types::bytes::ByteVec;
+use crate::types::bytes::Bytes;
+
+/// Domain tag for [`StickerPackP::pack_id`].
+const PACK_ID_DOMAIN: &[u8] = b"promtuz/sticker/pack";
+
+/// Domain separator for the author's signature over a pack id.
+pub const PACK_SIG_DOMAIN: &[u8] = b"promtuz-sticker-pack-v1";
+
+/// Stickers per pack. Indices in `Body::Sticker::id` range below this.
+pub const MAX_STICKERS: usize = 120;
+
+/// Largest single frame: a 512px AVIF sits well inside it.
+pub const MAX_STICKER_BYTES: usize = 64 * 1024;
+
+/// Longest title, in bytes.
+pub const MAX_PACK_TITLE_BYTES: usize = 64;
+
+/// Upper bound on a serialized pack: every frame at its cap plus its length
+/// prefix, the title, the author and the signature. An offer claiming more is
+/// not a pack.
+pub const MAX_PACK_BYTES: u64 =
+    (MAX_STICKERS * (MAX_STICKER_BYTES + 4) + MAX_PACK_TITLE_BYTES + 4 + 32 + 64 + 4) as u64;
+
+/// One sticker pack as it is stored and shipped. `frames[i]` is sticker `i`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StickerPackP {
+    pub title:  String,
+    /// IPK of whoever made the pack; `sig` is theirs.
+    pub author: Bytes<32>,
+    #[serde(deserialize_with = "bounded_vec::<_, _, MAX_STICKERS>")]
+    pub frames: Vec<ByteVec>,
+    /// Ed25519 by `author` over [`pack_sig_message`].
+    pub sig:    Bytes<64>,
+}
+
+impl StickerPackP {
+    /// Content address of the pack: commits to the title, the author and
+    /// every frame's hash, in order. Truncated to the 16 bytes
+    /// `Body::Sticker` carries.
+    pub fn pack_id(&self) -> [u8; 16] {
+        pack_id(&self.title, &self.author.0, &self.frames)
+    }
+
+    /// Within the bounds a receiver accepts: a titled, non-empty pack whose
+    /// frames are each non-empty and under [`MAX_STICKER_BYTES`].
+    pub fn is_well_formed(&self) -> bool {
+        !self.title.is_empty()
+            && self.title.len() <= MAX_PACK_TITLE_BYTES
+            && !self.frames.is_empty()
+            && self.frames.len() <= MAX_STICKERS
+            && self.frames.iter().all(|f| !f.is_empty() && f.len() <= MAX_STICKER_BYTES)
+    }
+}
+
+/// [`StickerPackP::pack_id`] before the pack exists, so its author can sign
+/// the id the finished pack will carry.
+pub fn pack_id(title: &str, author: &[u8; 32], frames: &[ByteVec]) -> [u8; 16] {
+    let mut h = blake3::Hasher::new();
+    h.update(PACK_ID_DOMAIN);
+    h.update(&(title.len() as u32).to_le_bytes());
+    h.update(title.as_bytes());
+    h.update(author);
+    h.update(&(frames.len() as u32).to_le_bytes());
+    for f in frames {
+        h.update(blake3::hash(f).as_bytes());
+    }
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&h.finalize().as_bytes()[..16]);
+    id
+}
+
+/// Canonical bytes the author signs for a pack.
+/// Layout: `PACK_SIG_DOMAIN || pack_id`
+pub fn pack_sig_message(pack_id: &[u8; 16]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(PACK_SIG_DOMAIN.len() + 16);
+    buf.extend_from_slice(PACK_SIG_DOMAIN);
+    buf.extend_from_slice(pack_id);
+    buf
+}
+
+#[cfg(feature = "crypto")]
+impl StickerPackP {
+    /// Well-formed, and `sig` is `author`'s over this pack's id.
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::Signature;
+        use ed25519_dalek::VerifyingKey;
+        if !self.is_well_formed() {
+            return false;
+        }
+        let Ok(vk) = VerifyingKey::from_bytes(&self.author.0) else {
+            return false;
+        };
+        let sig = Signature::from_bytes(&self.sig.0);
+        vk.verify_strict(&pack_sig_message(&self.pack_id()), &sig).is_ok()
+    }
+
+    /// A pack of `frames` authored and signed by `key`.
+    pub fn signed(key: &ed25519_dalek::SigningKey, title: &str, frames: Vec<ByteVec>) -> Self {
+        use ed25519_dalek::Signer;
+        let author = key.verifying_key().to_bytes();
+        let id = pack_id(title, &author, &frames);
+        Self {
+            title: title.into(),
+            author: Bytes(author),
+            frames,
+            sig: Bytes(key.sign(&pack_sig_message(&id)).to_bytes()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+    use crate::proto::pack::Packer;
+    use crate::proto::pack::Unpacker;
+
+    #[test]
+    fn signed_pack_verifies_and_round_trips() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let frames = vec![ByteVec(vec![1, 2, 3]), ByteVec(vec![4])];
+        let pack = StickerPackP::signed(&key, "cats", frames);
+        assert!(pack.verify());
+        let back = StickerPackP::deser(&pack.ser().unwrap()).unwrap();
+        assert_eq!(back.pack_id(), pack.pack_id());
+        assert!(back.verify());
+    }
+
+    #[test]
+    fn any_change_moves_the_id_and_breaks_the_signature() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let frames = vec![ByteVec(vec![1, 2, 3]), ByteVec(vec![4])];
+        let pack = StickerPackP::signed(&key, "cats", frames);
+
+        let mut swapped = pack.clone();
+        swapped.frames.swap(0, 1);
+        assert_ne!(swapped.pack_id(), pack.pack_id());
+        assert!(!swapped.verify());
+
+        let mut retitled = pack.clone();
+        retitled.title = "dogs".into();
+        assert!(!retitled.verify());
+
+        // Re-signing under another key is a different pack, not a forgery of
+        // this one: the author is part of the id.
+        let other = SigningKey::from_bytes(&[5u8; 32]);
+        let claimed = StickerPackP::signed(&other, "cats", pack.frames.clone());
+        assert!(claimed.verify());
+        assert_ne!(claimed.pack_id(), pack.pack_id());
+    }
+
+    #[test]
+    fn oversized_or_empty_packs_are_rejected() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        assert!(!StickerPackP::signed(&key, "cats", vec![]).verify());
+        assert!(!StickerPackP::signed(&key, "", vec![ByteVec(vec![1])]).verify());
+        let huge = vec![ByteVec(vec![0; MAX_STICKER_BYTES + 1])];
+        assert!(!StickerPackP::signed(&key, "cats", huge).verify());
+        assert!(!StickerPackP::signed(&key, "cats", vec![ByteVec(vec![])]).verify());
+    }
+}
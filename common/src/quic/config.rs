@@ -68,6 +68,41 @@ pub fn setup_crypto_provider() -> Result<()> {
     Ok(())
 }
 
+/// The provider every QUIC handshake is built on: aws-lc-rs with X25519MLKEM768
+/// as its only key exchange, so a recorded handshake stays sealed against a
+/// future quantum adversary as long as either X25519 or ML-KEM-768 holds.
+///
+/// Every role speaks this build, so nothing needs a classical fallback, and a
+/// peer that cannot do the hybrid fails the handshake rather than downgrading.
+/// The process default from [`setup_crypto_provider`] stays classical-first for
+/// HTTPS to third parties (FCM, APNs). The tunnel's outer TLS also keeps it on
+/// purpose: its job is to look like a browser, and the QUIC session it carries
+/// is hybrid already.
+pub fn hybrid_kx_provider() -> Arc<CryptoProvider> {
+    static PROVIDER: std::sync::LazyLock<Arc<CryptoProvider>> = std::sync::LazyLock::new(|| {
+        Arc::new(CryptoProvider {
+            kx_groups: vec![rustls::crypto::aws_lc_rs::kx_group::X25519MLKEM768],
+            ..rustls::crypto::aws_lc_rs::default_provider()
+        })
+    });
+    PROVIDER.clone()
+}
+
+/// A server config builder on [`hybrid_kx_provider`], TLS 1.3 only as QUIC
+/// requires.
+pub fn hybrid_server_tls() -> rustls::ConfigBuilder<RustlsServerConfig, rustls::WantsVerifier> {
+    RustlsServerConfig::builder_with_provider(hybrid_kx_provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .expect("aws-lc-rs supports TLS 1.3 with X25519MLKEM768")
+}
+
+/// The client counterpart of [`hybrid_server_tls`].
+pub fn hybrid_client_tls() -> rustls::ConfigBuilder<rustls::ClientConfig, rustls::WantsVerifier> {
+    rustls::ClientConfig::builder_with_provider(hybrid_kx_provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .expect("aws-lc-rs supports TLS 1.3 with X25519MLKEM768")
+}
+
 pub fn load_root_ca_bytes(bytes: &[u8]) -> Result<rustls::RootCertStore> {
     let mut store = rustls::RootCertStore::empty();
     let mut reader = std::io::BufReader::new(bytes);
@@ -162,14 +197,11 @@ pub fn build_server_cfg(
     // can't read a connecting node's capability cert. mTLS the node ALPNs
     // (keep client/5 open — phones are pseudonymous) to verify node
     // certs/capabilities directly. See dht/tls_extract.rs.
-    let mut tls = RustlsServerConfig::builder()
+    let mut tls = hybrid_server_tls()
         .with_no_client_auth()
         .with_single_cert(certs, key)?;
 
-    tls.alpn_protocols = alpn_protocols
-        .iter()
-        .map(|prot| prot.alpn().into())
-        .collect::<Vec<Vec<u8>>>();
+    tls.alpn_protocols = alpn_protocols.iter().flat_map(|prot| prot.alpns()).collect();
 
     let quic_crypto = QuicServerConfig::try_from(tls)?;
     let mut server_cfg = QuinnServerConfig::with_crypto(Arc::new(quic_crypto));
@@ -178,6 +210,45 @@ pub fn build_server_cfg(
     Ok(server_cfg)
 }
 
+/// Outer TLS for the relay's tunnel listener (see [`crate::quic::tunnel`]):
+/// the operator's CA-issued cert, offering only the HTTPS-looking
+/// [`TUNNEL_ALPN`](crate::quic::tunnel::TUNNEL_ALPN). Nothing inside is
+/// trusted on the strength of this session — the QUIC handshake it carries
+/// authenticates the relay again.
+#[cfg(feature = "tunnel")]
+pub fn build_tunnel_server_tls(cert_path: &Path, key_path: &Path) -> Result<RustlsServerConfig> {
+    let mut cert_reader = BufReader::new(
+        File::open(cert_path).with_context(|| format!("reading TLS cert at {}", cert_path.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut cert_reader).flatten().collect();
+
+    let mut key_reader = BufReader::new(
+        File::open(key_path).with_context(|| format!("reading TLS key at {}", key_path.display()))?,
+    );
+    let key = rustls_pemfile::private_key(&mut key_reader)?.ok_or(anyhow!("No Private Key"))?;
+
+    let mut tls = RustlsServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    tls.alpn_protocols = vec![crate::quic::tunnel::TUNNEL_ALPN.to_vec()];
+
+    Ok(tls)
+}
+
+/// Outer TLS for dialing a relay's tunnel listener, verified against the same
+/// roots as the QUIC session. SNI is off: the relay id it would carry is the
+/// one thing a censor could match on, and the relay serves a single cert
+/// without it anyway.
+#[cfg(feature = "tunnel")]
+pub fn build_tunnel_client_tls(roots: &RootCertStore) -> rustls::ClientConfig {
+    let mut tls = rustls::ClientConfig::builder()
+        .with_root_certificates(roots.clone())
+        .with_no_client_auth();
+    tls.alpn_protocols = vec![crate::quic::tunnel::TUNNEL_ALPN.to_vec()];
+    tls.enable_sni = false;
+    tls
+}
+
 /// Builds a `quinn::ClientConfig` configured for a specific ALPN protocol.
 ///
 /// This function is used whenever an outbound QUIC connection is made
@@ -223,13 +294,12 @@ pub fn build_server_cfg(
 /// ```
 pub fn build_client_cfg(role: ProtoRole, roots: &RootCertStore) -> Result<quinn::ClientConfig> {
     // --- rustls TLS config ---
-    let mut tls = rustls::ClientConfig::builder()
-        // .with_safe_defaults()
+    let mut tls = hybrid_client_tls()
         .with_root_certificates(roots.clone())
         .with_no_client_auth(); // no client certificate auth
 
-    // Set ALPN (only one per outbound role)
-    tls.alpn_protocols = vec![role.alpn().into()];
+    // One role per outbound config, offered at every version we speak.
+    tls.alpn_protocols = role.alpns();
 
     let quic_config = quinn::crypto::rustls::QuicClientConfig::try_from(tls)?;
 
@@ -449,11 +519,11 @@ pub struct AlpnAwareCertResolver {
 #[cfg(feature = "crypto")]
 impl ResolvesServerCert for AlpnAwareCertResolver {
     fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
-        let peer_alpn = ProtoRole::Peer.alpn();
-        let peer_alpn_bytes = peer_alpn.as_bytes();
         if let Some(alpns) = hello.alpn() {
             for alpn in alpns {
-                if alpn == peer_alpn_bytes {
+                if std::str::from_utf8(alpn).ok().and_then(ProtoRole::from_alpn)
+                    == Some(ProtoRole::Peer)
+                {
                     return Some(Arc::clone(&self.peer_cert));
                 }
             }
@@ -492,8 +562,7 @@ pub fn build_server_cfg_with_alpn_split(
 
     // Wrap the CA-issued cert+key into a CertifiedKey via rustls's
     // "any_supported_type" key parser.
-    let signing_key = rustls::crypto::CryptoProvider::get_default()
-        .ok_or_else(|| anyhow!("crypto provider not installed"))?
+    let signing_key = hybrid_kx_provider()
         .key_provider
         .load_private_key(key)
         .map_err(|e| anyhow!("load default cert key: {e}"))?;
@@ -503,14 +572,11 @@ pub fn build_server_cfg_with_alpn_split(
 
     let resolver = Arc::new(AlpnAwareCertResolver { peer_cert, default_cert });
 
-    let mut tls = RustlsServerConfig::builder()
+    let mut tls = hybrid_server_tls()
         .with_no_client_auth()
         .with_cert_resolver(resolver);
 
-    tls.alpn_protocols = alpn_protocols
-        .iter()
-        .map(|prot| prot.alpn().into())
-        .collect::<Vec<Vec<u8>>>();
+    tls.alpn_protocols = alpn_protocols.iter().flat_map(|prot| prot.alpns()).collect();
 
     let quic_crypto = QuicServerConfig::try_from(tls)?;
     let mut server_cfg = QuinnServerConfig::with_crypto(Arc::new(quic_crypto));
@@ -522,6 +588,15 @@ pub fn build_server_cfg_with_alpn_split(
 mod tests {
     use super::*;
 
+    #[test]
+    fn quic_tls_offers_only_the_hybrid_key_exchange() {
+        let client = hybrid_client_tls()
+            .with_root_certificates(RootCertStore::empty())
+            .with_no_client_auth();
+        let groups: Vec<_> = client.crypto_provider().kx_groups.iter().map(|g| g.name()).collect();
+        assert_eq!(groups, [rustls::NamedGroup::X25519MLKEM768]);
+    }
+
     #[test]
     fn der_integer_pads_a_high_bit_magnitude() {
         assert_eq!(der_integer(&[0x80, 0x01]), vec![0x02, 0x03, 0x00, 0x80, 0x01]);
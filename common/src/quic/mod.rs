@@ -7,6 +7,8 @@ pub mod id;
 #[cfg(feature = "server")]
 pub mod p256;
 pub mod protorole;
+#[cfg(feature = "tunnel")]
+pub mod tunnel;
 pub mod xor;
 
 pub use xor::xor32;
@@ -65,7 +67,7 @@ pub enum CloseReason {
     /// `QueueFetchAck` RPC was rejected for a hard protocol violation
     /// the wire-format validator surfaced (e.g. bad outer signature on
     /// `Forward`, ack-id list overflow on `QueueFetchAck`). The
-    /// soft-reject outcomes (`QueueFull`, `NotOwner`, `RateLimited`)
+    /// soft-reject outcomes (`QueueFull`, `QuotaExceeded`, `NotOwner`, `RateLimited`)
     /// are returned in the response body and do **not** close the
     /// connection.
     DhtForwardRejected,
@@ -115,6 +117,16 @@ pub enum CloseReason {
     /// Distinct from [`Self::DhtFlood`] for the same reason
     /// [`Self::KeyPackageRateLimited`] is.
     WelcomeRateLimited,
+    /// DHT (`peer/5`): the peer's reputation crossed the ban threshold
+    /// (see `dht::reputation`). Sent on the connection that tipped it
+    /// over and on every accept until the ban lapses.
+    DhtBanned,
+    /// DHT (`peer/5`): the dialed peer's leaf cert lacks the CA-attested
+    /// `RELAY` capability and this relay requires it.
+    DhtUnattested,
+    /// The peer's advertised protocol-version range shares no version with
+    /// ours: one side is past the other's deprecation window.
+    UnsupportedVersion,
 }
 
 impl CloseReason {
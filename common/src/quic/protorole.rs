@@ -3,6 +3,7 @@ use std::{fmt, str::FromStr};
 use quinn::{Connection, crypto::rustls::HandshakeData};
 use serde::{Deserialize, Serialize};
 
+use crate::MIN_PROTOCOL_VERSION;
 use crate::PROTOCOL_VERSION;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -18,12 +19,23 @@ impl ProtoRole {
     ///
     /// Example: `"relay/5"`
     pub fn alpn(self) -> String {
-        match self {
-            ProtoRole::Resolver => format!("resolver/{PROTOCOL_VERSION}"),
-            ProtoRole::Relay => format!("relay/{PROTOCOL_VERSION}"),
-            ProtoRole::Peer => format!("peer/{PROTOCOL_VERSION}"),
-            ProtoRole::Client => format!("client/{PROTOCOL_VERSION}"),
-        }
+        self.alpn_at(PROTOCOL_VERSION)
+    }
+
+    fn alpn_at(self, version: u16) -> String {
+        format!("{}/{version}", self.as_ref())
+    }
+
+    /// Every ALPN this role answers to, newest version first.
+    ///
+    /// Offered as-is by dialers and listed as-is by listeners: rustls picks
+    /// the first of the listener's entries the dialer also offered, so the
+    /// TLS handshake itself lands on the highest common version.
+    pub fn alpns(self) -> Vec<Vec<u8>> {
+        (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION)
+            .rev()
+            .map(|v| self.alpn_at(v).into_bytes())
+            .collect()
     }
 
     /// Convert ALPN string to ProtoRole.
@@ -41,19 +53,26 @@ impl ProtoRole {
     }
 
     pub fn from_conn(conn: &Connection) -> Option<Self> {
-        // Get handshake data
-        let any = conn.handshake_data()?;
-        let hs = any.downcast_ref::<HandshakeData>()?;
+        negotiated_alpn(conn)?.parse::<ProtoRole>().ok()
+    }
+
+    /// The protocol version the TLS handshake settled on, read back from the
+    /// ALPN `role/version`.
+    pub fn version_from_conn(conn: &Connection) -> Option<u16> {
+        negotiated_alpn(conn)?.split_once('/')?.1.parse().ok()
+    }
+}
 
-        // hs.protocol is Option<Vec<u8>>
-        let alpn_bytes = hs.protocol.as_ref()?;
+fn negotiated_alpn(conn: &Connection) -> Option<String> {
+    // Get handshake data
+    let any = conn.handshake_data()?;
+    let hs = any.downcast_ref::<HandshakeData>()?;
 
-        // Convert &[u8] → &str
-        let alpn_str = std::str::from_utf8(alpn_bytes).ok()?;
+    // hs.protocol is Option<Vec<u8>>
+    let alpn_bytes = hs.protocol.as_ref()?;
 
-        // Convert &str → ProtoRole
-        alpn_str.parse::<ProtoRole>().ok()
-    }
+    // Convert &[u8] → String
+    String::from_utf8(alpn_bytes.clone()).ok()
 }
 
 impl fmt::Display for ProtoRole {
@@ -0,0 +1,525 @@
+//! QUIC over a byte stream, for networks that drop UDP.
+//!
+//! [`StreamSocket`] is a quinn [`AsyncUdpSocket`] whose "datagrams" travel as
+//! frames over TCP streams instead — so the same QUIC handshake, the same
+//! ALPNs and the same pinned identities run unchanged on top, and neither
+//! side's connection code learns which transport carried it. The stream is
+//! camouflage and reachability only; all security still comes from the QUIC
+//! session inside it.
+//!
+//! Two framings share the socket:
+//!
+//! - [`Framing::Length`]: a `u16` length prefix per datagram, behind the
+//!   [`TUNNEL_PREAMBLE`]. Used for the direct TLS-over-TCP fallback.
+//! - [`Framing::WebSocket`]: one binary RFC 6455 frame per datagram, after a
+//!   normal HTTP upgrade. Used through WebSocket bridges and domain-fronting
+//!   CDNs, which forward WebSocket but not arbitrary bytes.
+//!
+//! A listener reads the first four bytes of a stream to tell the two apart
+//! (see [`sniff`]); anything else is closed like a web server would.
+//!
+//! ponytail: QUIC over TCP stacks two congestion controllers and brings back
+//! head-of-line blocking. It is a fallback for when UDP is gone, not a mode
+//! anyone should prefer.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use quinn::AsyncUdpSocket;
+use quinn::UdpPoller;
+use quinn::udp;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// First bytes a length-framed tunnel client sends, so a listener can tell it
+/// from a WebSocket upgrade (`GET `) on the same port.
+pub const TUNNEL_PREAMBLE: &[u8; 4] = b"PZT1";
+
+/// ALPN offered on the outer TLS of a tunnel. The outer session must look like
+/// an ordinary HTTPS client; the real role ALPN rides the inner QUIC.
+pub const TUNNEL_ALPN: &[u8] = b"http/1.1";
+
+/// Largest datagram a tunnel frame may carry — the `u16` length prefix's
+/// range, comfortably above any QUIC packet quinn will build.
+pub const MAX_TUNNEL_DATAGRAM: usize = u16::MAX as usize;
+
+/// Cap on an HTTP upgrade request/response head. Real ones are a few hundred
+/// bytes; this only stops a peer from streaming headers forever.
+const MAX_HTTP_HEAD: usize = 8 * 1024;
+
+/// Datagrams buffered per direction. Like a congested UDP path, a full queue
+/// drops rather than blocks; QUIC's loss recovery resends.
+const QUEUE_CAP: usize = 1024;
+
+/// RFC 6455 §1.3 handshake GUID.
+const WS_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+type Datagram = (SocketAddr, Vec<u8>);
+
+/// How datagrams are delimited on one tunnel stream.
+#[derive(Debug, Clone, Copy)]
+pub enum Framing {
+    /// `u16` big-endian length, then the datagram.
+    Length,
+    /// One binary WebSocket frame per datagram. `mask` is `Some` on the
+    /// client side, which RFC 6455 requires to mask every frame it sends.
+    WebSocket { mask: Option<[u8; 4]> },
+}
+
+impl Framing {
+    /// Client-side WebSocket framing with a fresh random mask.
+    pub fn ws_client() -> Self {
+        Framing::WebSocket { mask: Some(fresh_mask()) }
+    }
+}
+
+/// Masking state used when the OS RNG fails. Any non-zero value will do;
+/// the mask only has to vary, not be secret (see [`write_frame`]).
+const FALLBACK_MASK: [u8; 4] = [0x9e, 0x37, 0x79, 0xb9];
+
+/// A random, non-zero masking key. Zero is the one state xorshift never
+/// leaves, and it masks nothing, so it is never handed out.
+fn fresh_mask() -> [u8; 4] {
+    use ring::rand::SecureRandom;
+
+    let mut key = [0u8; 4];
+    while key == [0; 4] {
+        if ring::rand::SystemRandom::new().fill(&mut key).is_err() {
+            return FALLBACK_MASK;
+        }
+    }
+    key
+}
+
+/// What the first bytes of an accepted stream asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sniffed {
+    /// Length-framed tunnel; the preamble has been consumed.
+    Length,
+    /// HTTP request; `GET ` has been consumed, the rest of the head has not.
+    WebSocket,
+}
+
+/// Reads the four bytes that decide a stream's framing. `None` means neither
+/// — the caller should just close it.
+pub async fn sniff<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Option<Sniffed>> {
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    Ok(match &head {
+        TUNNEL_PREAMBLE => Some(Sniffed::Length),
+        b"GET " => Some(Sniffed::WebSocket),
+        _ => None,
+    })
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+//                        HTTP upgrade                        //
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+
+fn ws_accept(key: &str) -> String {
+    let digest = ring::digest::digest(
+        &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+        &[key.as_bytes(), WS_GUID].concat(),
+    );
+    B64.encode(digest.as_ref())
+}
+
+/// Reads an HTTP head up to and including the blank line.
+async fn read_http_head<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<String> {
+    let mut head = Vec::with_capacity(512);
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HTTP_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "HTTP head too large"));
+        }
+        stream.read_exact(&mut byte).await?;
+        head.push(byte[0]);
+    }
+    String::from_utf8(head)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "non-UTF-8 HTTP head"))
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        k.trim().eq_ignore_ascii_case(name).then(|| v.trim())
+    })
+}
+
+/// Client half of the upgrade. `host` goes in the `Host` header — for a
+/// domain-fronted bridge that is the bridge, not the TLS SNI the stream was
+/// opened with. `key` is the 16 random bytes of `Sec-WebSocket-Key`.
+pub async fn ws_client_handshake<S>(
+    stream: &mut S, host: &str, path: &str, key: [u8; 16],
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let key = B64.encode(key);
+    let req = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    );
+    stream.write_all(req.as_bytes()).await?;
+    stream.flush().await?;
+
+    let head = read_http_head(stream).await?;
+    let status = head.lines().next().unwrap_or_default();
+    if !status.starts_with("HTTP/1.1 101") {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("upgrade refused: {status}"),
+        ));
+    }
+    if header(&head, "sec-websocket-accept") != Some(ws_accept(&key).as_str()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "bad Sec-WebSocket-Accept"));
+    }
+    Ok(())
+}
+
+/// Server half of the upgrade, called after [`sniff`] consumed `GET `. Any
+/// path is accepted: the bridge in front decides which paths reach us.
+pub async fn ws_server_handshake<S>(stream: &mut S) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let head = read_http_head(stream).await?;
+    let upgrade = header(&head, "upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    let key = header(&head, "sec-websocket-key");
+
+    let (Some(key), true) = (key, upgrade) else {
+        stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await?;
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket upgrade"));
+    };
+
+    let resp = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        ws_accept(key)
+    );
+    stream.write_all(resp.as_bytes()).await?;
+    stream.flush().await
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+//                          Framing                           //
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+
+/// Reads one datagram. `Ok(None)` is a clean end of stream (EOF, or a
+/// WebSocket close frame).
+pub async fn read_frame<R: AsyncRead + Unpin>(
+    r: &mut R, framing: Framing,
+) -> io::Result<Option<Vec<u8>>> {
+    match framing {
+        Framing::Length => {
+            let mut len = [0u8; 2];
+            match r.read_exact(&mut len).await {
+                Ok(_) => {},
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+            r.read_exact(&mut buf).await?;
+            Ok(Some(buf))
+        },
+        Framing::WebSocket { .. } => read_ws_message(r).await,
+    }
+}
+
+/// Collects one WebSocket data message, reassembling continuation frames an
+/// intermediary may have split it into. Ping/pong are dropped: nothing on our
+/// side sends them, and a bridge that does will time the stream out rather
+/// than corrupt it.
+async fn read_ws_message<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut message = Vec::new();
+    loop {
+        let mut hdr = [0u8; 2];
+        match r.read_exact(&mut hdr).await {
+            Ok(_) => {},
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let fin = hdr[0] & 0x80 != 0;
+        let opcode = hdr[0] & 0x0f;
+        let masked = hdr[1] & 0x80 != 0;
+        let len = match hdr[1] & 0x7f {
+            126 => r.read_u16().await? as usize,
+            127 => usize::try_from(r.read_u64().await?).unwrap_or(usize::MAX),
+            n => n as usize,
+        };
+        if message.len().saturating_add(len) > MAX_TUNNEL_DATAGRAM {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "WebSocket frame too large"));
+        }
+        let mut key = [0u8; 4];
+        if masked {
+            r.read_exact(&mut key).await?;
+        }
+        let mut payload = vec![0u8; len];
+        r.read_exact(&mut payload).await?;
+        if masked {
+            payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= key[i % 4]);
+        }
+
+        match opcode {
+            0x8 => return Ok(None),
+            0x9 | 0xA => continue,
+            0x0 | 0x2 => message.extend_from_slice(&payload),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpected WebSocket opcode",
+                ));
+            },
+        }
+        if fin {
+            return Ok(Some(message));
+        }
+    }
+}
+
+/// Writes one datagram. `mask` is advanced per frame so successive
+/// WebSocket frames don't share a masking key.
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    w: &mut W, framing: &mut Framing, payload: &[u8],
+) -> io::Result<()> {
+    if payload.len() > MAX_TUNNEL_DATAGRAM {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "datagram too large for tunnel"));
+    }
+    let mut out = Vec::with_capacity(payload.len() + 8);
+    match framing {
+        Framing::Length => {
+            out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            out.extend_from_slice(payload);
+        },
+        Framing::WebSocket { mask } => {
+            out.push(0x80 | 0x2);
+            let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+            match payload.len() {
+                n @ 0..=125 => out.push(mask_bit | n as u8),
+                n => {
+                    out.push(mask_bit | 126);
+                    out.extend_from_slice(&(n as u16).to_be_bytes());
+                },
+            }
+            match mask {
+                Some(key) => {
+                    if *key == [0; 4] {
+                        *key = fresh_mask();
+                    }
+                    out.extend_from_slice(key);
+                    out.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+                    // The key only has to be unpredictable to a proxy that
+                    // never sees plaintext (we're inside TLS); a cheap
+                    // xorshift off the random seed is plenty — as long as
+                    // it never sits at zero, checked above.
+                    let mut x = u32::from_be_bytes(*key);
+                    x ^= x << 13;
+                    x ^= x >> 17;
+                    x ^= x << 5;
+                    *key = x.to_be_bytes();
+                },
+                None => out.extend_from_slice(payload),
+            }
+        },
+    }
+    w.write_all(&out).await?;
+    w.flush().await
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+//                        StreamSocket                        //
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+
+/// A quinn socket backed by any number of tunnel streams, one per remote
+/// address. The client attaches exactly one (the relay it dials); a relay's
+/// tunnel listener attaches one per accepted TCP connection, keyed on that
+/// connection's peer address so each shows up to quinn as its own "UDP"
+/// peer.
+#[derive(Debug)]
+pub struct StreamSocket {
+    local: SocketAddr,
+    inbound_tx: mpsc::Sender<Datagram>,
+    inbound: Mutex<mpsc::Receiver<Datagram>>,
+    routes: Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>,
+}
+
+impl StreamSocket {
+    pub fn new(local: SocketAddr) -> Arc<Self> {
+        let (inbound_tx, inbound) = mpsc::channel(QUEUE_CAP);
+        Arc::new(Self {
+            local,
+            inbound_tx,
+            inbound: Mutex::new(inbound),
+            routes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Carries datagrams for `peer` over `stream` until either side closes
+    /// it. The pump holds only a weak handle, so dropping the endpoint (and
+    /// with it this socket) tears the stream down too.
+    pub fn attach<S>(self: &Arc<Self>, peer: SocketAddr, stream: S, framing: Framing)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (out_tx, mut out_rx) = mpsc::channel::<Vec<u8>>(QUEUE_CAP);
+        self.routes.lock().unwrap().insert(peer, out_tx);
+
+        let inbound = self.inbound_tx.clone();
+        let socket: Weak<Self> = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            let (mut rd, mut wr) = tokio::io::split(stream);
+            let mut write_framing = framing;
+
+            let reader = async {
+                while let Ok(Some(datagram)) = read_frame(&mut rd, framing).await {
+                    // Full queue = congested path; drop like UDP would.
+                    if let Err(mpsc::error::TrySendError::Closed(_)) =
+                        inbound.try_send((peer, datagram))
+                    {
+                        break;
+                    }
+                }
+            };
+            let writer = async {
+                while let Some(datagram) = out_rx.recv().await {
+                    if write_frame(&mut wr, &mut write_framing, &datagram).await.is_err() {
+                        break;
+                    }
+                }
+            };
+            tokio::select! {
+                _ = reader => {},
+                _ = writer => {},
+            }
+
+            if let Some(socket) = socket.upgrade() {
+                socket.routes.lock().unwrap().remove(&peer);
+            }
+            log::debug!("tunnel stream for {peer} closed");
+        });
+    }
+
+    /// Number of live tunnel streams.
+    pub fn streams(&self) -> usize {
+        self.routes.lock().unwrap().len()
+    }
+}
+
+impl AsyncUdpSocket for StreamSocket {
+    fn create_io_poller(self: Arc<Self>) -> Pin<Box<dyn UdpPoller>> {
+        Box::pin(AlwaysWritable)
+    }
+
+    fn try_send(&self, transmit: &udp::Transmit) -> io::Result<()> {
+        // No route (stream gone) or a full queue both look like loss to
+        // quinn, which is what they are.
+        if let Some(route) = self.routes.lock().unwrap().get(&transmit.destination) {
+            let _ = route.try_send(transmit.contents.to_vec());
+        }
+        Ok(())
+    }
+
+    fn poll_recv(
+        &self, cx: &mut Context, bufs: &mut [io::IoSliceMut<'_>], meta: &mut [udp::RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        let mut inbound = self.inbound.lock().unwrap();
+        match inbound.poll_recv(cx) {
+            Poll::Ready(Some((addr, datagram))) => {
+                let len = datagram.len().min(bufs[0].len());
+                bufs[0][..len].copy_from_slice(&datagram[..len]);
+                meta[0] = udp::RecvMeta { addr, len, stride: len, ecn: None, dst_ip: None };
+                Poll::Ready(Ok(1))
+            },
+            // Unreachable while `self` holds `inbound_tx`, but don't spin if so.
+            Poll::Ready(None) => Poll::Pending,
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local)
+    }
+
+    fn may_fragment(&self) -> bool {
+        false
+    }
+}
+
+/// Sends never block (they drop when full), so the socket is always writable.
+#[derive(Debug)]
+struct AlwaysWritable;
+
+impl UdpPoller for AlwaysWritable {
+    fn poll_writable(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_accept_matches_the_rfc_example() {
+        // RFC 6455 §1.3.
+        assert_eq!(ws_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip_in_both_framings() {
+        let payloads: [&[u8]; 3] = [b"", &[7u8; 125], &[9u8; 1500]];
+        for framing in [
+            Framing::Length,
+            Framing::WebSocket { mask: Some([1, 2, 3, 4]) },
+            Framing::WebSocket { mask: None },
+        ] {
+            let mut wire = Vec::new();
+            let mut writer = framing;
+            for p in payloads {
+                write_frame(&mut wire, &mut writer, p).await.unwrap();
+            }
+            let mut rd = wire.as_slice();
+            for p in payloads {
+                assert_eq!(read_frame(&mut rd, framing).await.unwrap().as_deref(), Some(p));
+            }
+            assert_eq!(read_frame(&mut rd, framing).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_zero_mask_is_reseeded_before_it_is_used() {
+        let mut framing = Framing::WebSocket { mask: Some([0; 4]) };
+        let mut wire = Vec::new();
+        write_frame(&mut wire, &mut framing, &[0xaa; 8]).await.unwrap();
+        assert_ne!(&wire[2..6], &[0; 4], "frame went out with a zero key");
+        assert!(matches!(framing, Framing::WebSocket { mask: Some(k) } if k != [0; 4]));
+
+        let mut rd = wire.as_slice();
+        let got = read_frame(&mut rd, Framing::WebSocket { mask: None }).await.unwrap();
+        assert_eq!(got.as_deref(), Some(&[0xaa; 8][..]));
+    }
+
+    #[tokio::test]
+    async fn ws_handshake_completes_and_sniffs_as_websocket() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let srv = tokio::spawn(async move {
+            assert_eq!(sniff(&mut server).await.unwrap(), Some(Sniffed::WebSocket));
+            ws_server_handshake(&mut server).await
+        });
+        ws_client_handshake(&mut client, "bridge.example", "/t", [5u8; 16]).await.unwrap();
+        srv.await.unwrap().unwrap();
+    }
+}
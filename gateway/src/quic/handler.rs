@@ -4,6 +4,7 @@ use common::debug;
 use common::proto::pack::Unpacker;
 use common::proto::push::PushProvider;
 use common::proto::push::PushRequest;
+use common::proto::push::WAKE_BATCH_ACK;
 use common::proto::push::WakeRequest;
 use common::quic::protorole::ProtoRole;
 use common::warn;
@@ -17,7 +18,10 @@ use crate::gateway::Gateway;
 /// head-of-line block the connection's other streams.
 ///
 /// `Register` (devices, `client/5`) verifies + stores `P → token`. `Wake`
-/// (home relays, `relay/5`) resolves `P → token` and pushes it.
+/// (home relays, `relay/5`) resolves `P → token` and pushes it; `WakeBatch`
+/// does the same for each entry, acknowledged with [`WAKE_BATCH_ACK`] once
+/// decoded. Relays keep their connection open across batches, so this loop
+/// usually outlives many requests.
 pub struct Handler;
 
 impl Handler {
@@ -32,7 +36,7 @@ impl Handler {
             None => return conn.close(0u32.into(), b"NoALPN"),
         }
 
-        while let Ok((_send, mut recv)) = conn.accept_bi().await {
+        while let Ok((mut send, mut recv)) = conn.accept_bi().await {
             let gateway = gateway.clone();
             tokio::spawn(async move {
                 match PushRequest::unpack(&mut recv).await {
@@ -46,6 +50,20 @@ impl Handler {
                         Err(e) => warn!("gateway: rejected registration from {addr}: {e}"),
                     },
                     Ok(PushRequest::Wake(req)) => Self::dispatch_wake(&gateway, req).await,
+                    Ok(PushRequest::WakeBatch(batch)) => {
+                        debug!("gateway: wake batch of {} from {addr}", batch.wakes.len());
+                        // Tells the relay this gateway reads batches; without
+                        // it the relay resends each wake on its own.
+                        if send.write_all(&[WAKE_BATCH_ACK]).await.is_ok() {
+                            let _ = send.finish();
+                        }
+                        // Same isolation as per-stream dispatch: one slow FCM
+                        // call must not hold up the rest of the batch.
+                        for req in batch.wakes {
+                            let gateway = gateway.clone();
+                            tokio::spawn(async move { Self::dispatch_wake(&gateway, req).await });
+                        }
+                    },
                     Err(e) => warn!("gateway: request decode failed from {addr}: {e}"),
                 }
             });
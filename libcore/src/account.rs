@@ -0,0 +1,265 @@
+//! Deleting our account. [`delete`] has the network forget our key: the
+//! connected relay wipes what it and every home hold for it, and tombstones
+//! it so nothing is published under it again (`relay/src/dht/account.rs`).
+//! Keys we rotated away from go first, each vouched for by the successions
+//! leading from it to our key, then every mailbox a contact may still address
+//! sealed mail to, each under its own mailbox key. [`wipe_local`] then
+//! forgets everything on this device, leaving it as a fresh install would.
+//!
+//! Contacts aren't told. Their sends to us, identified or sealed, come back
+//! as `NotFound` from our homes and fail, and our chats stay in their history
+//! under our last name.
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::SRelayPacket;
+use common::proto::dht_p2p::AccountDeletion;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::dht_p2p::MAX_PREDECESSOR_CHAIN;
+use common::proto::dht_p2p::PredecessorDeletion;
+use common::proto::dht_p2p::account_deletion_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use log::info;
+use log::warn;
+use rusqlite::Connection;
+
+use crate::data::identity::Identity;
+use crate::data::identity::IdentitySigner;
+use crate::platform::SECURE_STORE;
+use crate::state::RELAY;
+
+/// Homes that must confirm a wipe before this device forgets the key. With
+/// none, the network may still hold everything, and only the key can ask
+/// again.
+const MIN_HOMES: u32 = 1;
+
+/// Delete our identity from the network, then from this device. Needs a
+/// relay connection, and nothing local goes until a home has confirmed each
+/// key: a failure leaves the account whole, and the connection up, to try
+/// again. Returns how many homes wiped the current key.
+pub async fn delete() -> Result<u32> {
+    let ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let timestamp = crate::utils::systime().as_millis() as u64;
+    let sig = IdentitySigner::sign(&account_deletion_signing_input(&ipk, timestamp))?;
+    let deletion = AccountDeletion { ipk: ipk.into(), timestamp, sig: sig.to_bytes().into() };
+
+    let conn = {
+        let relay = RELAY.read();
+        relay.as_ref().and_then(|r| r.connection.clone())
+    };
+    let Some(conn) = conn else { bail!("not connected to a relay") };
+    for predecessor in predecessor_deletions(&Identity::successions(), &deletion) {
+        let old = predecessor.ipk().unwrap_or_default();
+        let homes = request(&conn, CRelayPacket::DeletePredecessor(predecessor)).await?;
+        confirmed(homes).with_context(|| format!("retired key {}", hex::encode(&old[..4])))?;
+    }
+    let identity = crate::data::identity::secret_key_signing(&ipk)?;
+    for mailbox in crate::mailbox::deletions(&identity, timestamp) {
+        let id = mailbox.ipk.0;
+        let homes = request(&conn, CRelayPacket::DeleteMailbox(mailbox)).await?;
+        confirmed(homes).with_context(|| format!("mailbox {}", hex::encode(&id[..4])))?;
+    }
+    let homes = request(&conn, CRelayPacket::DeleteAccount(deletion)).await?;
+    confirmed(homes)?;
+    info!("ACCOUNT: deleted {} at {homes} homes", hex::encode(&ipk[..4]));
+
+    wipe_local()?;
+    Ok(homes)
+}
+
+/// Send one deletion on its own stream and read how many homes wiped it.
+async fn request(conn: &quinn::Connection, packet: CRelayPacket) -> Result<u32> {
+    let bytes = packet.pack().map_err(|e| anyhow!("pack deletion: {e}"))?;
+    let (mut tx, mut rx) = conn.open_bi().await?;
+    tx.write_all(&bytes).await?;
+    tx.finish()?;
+    match SRelayPacket::unpack(&mut rx).await? {
+        SRelayPacket::AccountDeleted { homes } => Ok(homes),
+        other => bail!("deletion: unexpected variant {other:?}"),
+    }
+}
+
+fn confirmed(homes: u32) -> Result<()> {
+    if homes < MIN_HOMES {
+        bail!("no home confirmed the deletion; the account is kept to try again");
+    }
+    Ok(())
+}
+
+/// One deletion per key we rotated away from, each carrying the successions,
+/// oldest first, from that key to ours. A key more than
+/// [`MAX_PREDECESSOR_CHAIN`] rotations back is left to expire at its homes.
+fn predecessor_deletions(
+    successions: &[IdentitySuccession], deletion: &AccountDeletion,
+) -> Vec<PredecessorDeletion> {
+    (0..successions.len())
+        .map(|from| &successions[from..])
+        .filter(|chain| chain.len() <= MAX_PREDECESSOR_CHAIN)
+        .map(|chain| PredecessorDeletion { chain: chain.to_vec(), deletion: deletion.clone() })
+        .collect()
+}
+
+/// Forget every trace of the account on this device: the identity, contacts,
+/// chats, group state, the outbox, transfer records and the files behind voice
+/// notes, sticker packs and transfers, what is cached of them in memory, the
+/// relay connection and the platform key that sealed our secrets. The relay
+/// directory stays, as it isn't ours. The process should still be restarted
+/// afterwards, as MLS groups and tasks already under way hold the old
+/// identity until they end.
+pub fn wipe_local() -> Result<()> {
+    if let Some(conn) = RELAY.write().take().and_then(|r| r.connection) {
+        conn.close(quinn::VarInt::from_u32(0), b"account deleted");
+    }
+    clear_in(&mut crate::db::identity::IDENTITY_DB.lock(), None)?;
+    Identity::forget_cached_secret();
+    clear_in(&mut crate::db::peers::CONTACTS_DB.lock(), None)?;
+    clear_in(&mut crate::db::messages::MESSAGES_DB.lock(), None)?;
+    clear_in(&mut crate::db::mls::MLS_DB.lock(), None)?;
+    clear_in(&mut crate::db::outbox::OUTBOX_DB.lock(), None)?;
+    clear_in(&mut crate::transfer::store::TRANSFERS_DB.lock(), None)?;
+    clear_in(
+        &mut crate::db::network::NETWORK_DB.lock(),
+        Some(&["presence_contacts", "presence_state"]),
+    )?;
+    for sub in ["voice", "stickers", "transfers"] {
+        let _ = std::fs::remove_dir_all(crate::db::files_dir(sub));
+    }
+    crate::messaging::forget_all();
+    crate::push::forget_all();
+    crate::sealed::forget_all();
+    crate::stickers::forget_all();
+    crate::p2p::forget_all();
+    crate::staging::clear();
+    if let Some(store) = SECURE_STORE.get()
+        && let Err(e) = store.forget()
+    {
+        warn!("ACCOUNT: platform key not deleted: {e}");
+    }
+    info!("ACCOUNT: local data wiped");
+    Ok(())
+}
+
+/// Empty `tables` in `conn`, or every table it has when `None`. The schema
+/// and its migration version stay. Foreign keys are checked once at commit,
+/// when nothing is left to point anywhere.
+fn clear_in(conn: &mut Connection, tables: Option<&[&str]>) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.pragma_update(None, "defer_foreign_keys", "ON")?;
+    let names: Vec<String> = match tables {
+        Some(tables) => tables.iter().map(|t| (*t).to_string()).collect(),
+        None => {
+            let mut stmt = tx.prepare(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )?;
+            stmt.query_map([], |r| r.get(0))?.collect::<rusqlite::Result<_>>()?
+        },
+    };
+    for name in names {
+        tx.execute(&format!("DELETE FROM \"{name}\""), [])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn clearing_empties_every_table_and_keeps_the_schema() {
+        let mut conn = crate::db::messages::open_in_memory();
+        conn.execute(
+            "INSERT INTO conversations (id, kind, title, created_at) VALUES (?1, 0, '', 0)",
+            [[1u8; 16].as_slice()],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO conversation_members (conversation_id, member_ipk, role, joined_at, active) \
+             VALUES (?1, ?2, 0, 0, 1)",
+            ([1u8; 16].as_slice(), [5u8; 32].as_slice()),
+        )
+        .unwrap();
+        let version: i64 = conn.pragma_query_value(None, "user_version", |r| r.get(0)).unwrap();
+
+        clear_in(&mut conn, None).unwrap();
+        assert_eq!(count(&conn, "conversations"), 0);
+        assert_eq!(count(&conn, "conversation_members"), 0);
+        let after: i64 = conn.pragma_query_value(None, "user_version", |r| r.get(0)).unwrap();
+        assert_eq!(after, version, "a wiped store is not migrated again");
+    }
+
+    #[test]
+    fn clearing_named_tables_leaves_the_rest() {
+        let mut conn = crate::db::peers::open_in_memory();
+        for ipk in [[1u8; 32], [2; 32]] {
+            conn.execute(
+                "INSERT INTO contacts (ipk, name, added_at) VALUES (?1, 'bo', 1)",
+                [ipk.as_slice()],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO successions (old_ipk, new_ipk, statement, received_at) \
+             VALUES (?1, ?2, x'00', 0)",
+            ([3u8; 32].as_slice(), [1u8; 32].as_slice()),
+        )
+        .unwrap();
+
+        clear_in(&mut conn, Some(&["successions"])).unwrap();
+        assert_eq!(count(&conn, "successions"), 0);
+        assert_eq!(count(&conn, "contacts"), 2);
+    }
+
+    /// Nothing local goes on a deletion no home confirmed: the key is all
+    /// that could ask the network again.
+    #[test]
+    fn a_deletion_no_home_confirmed_keeps_the_account() {
+        assert!(confirmed(0).is_err());
+        assert!(confirmed(1).is_ok());
+    }
+
+    #[test]
+    fn each_retired_key_is_deleted_with_the_chain_to_ours() {
+        use common::proto::dht_p2p::identity_succession_signing_input;
+        use ed25519_dalek::Signer;
+        use ed25519_dalek::SigningKey;
+
+        let keys: Vec<SigningKey> = (1..=3u8).map(|n| SigningKey::from_bytes(&[n; 32])).collect();
+        let ipk = |k: &SigningKey| k.verifying_key().to_bytes();
+        let successions: Vec<IdentitySuccession> = keys
+            .windows(2)
+            .map(|pair| {
+                let input = identity_succession_signing_input(&ipk(&pair[0]), &ipk(&pair[1]), 5);
+                IdentitySuccession {
+                    old_ipk:   ipk(&pair[0]).into(),
+                    new_ipk:   ipk(&pair[1]).into(),
+                    timestamp: 5,
+                    old_sig:   pair[0].sign(&input).to_bytes().into(),
+                    new_sig:   pair[1].sign(&input).to_bytes().into(),
+                }
+            })
+            .collect();
+        let current = &keys[2];
+        let sig = current.sign(&account_deletion_signing_input(&ipk(current), 9)).to_bytes();
+        let deletion = AccountDeletion { ipk: ipk(current).into(), timestamp: 9, sig: sig.into() };
+
+        let deletions = predecessor_deletions(&successions, &deletion);
+        let olds: Vec<_> = deletions.iter().map(|d| d.ipk()).collect();
+        assert_eq!(olds, vec![Some(ipk(&keys[0])), Some(ipk(&keys[1]))]);
+        assert!(deletions.iter().all(PredecessorDeletion::verify));
+        assert!(predecessor_deletions(&[], &deletion).is_empty(), "never rotated");
+
+        let long = vec![successions[0].clone(); MAX_PREDECESSOR_CHAIN + 1];
+        let reachable = predecessor_deletions(&long, &deletion);
+        assert_eq!(reachable.len(), MAX_PREDECESSOR_CHAIN, "the oldest is out of reach");
+        assert!(reachable.iter().all(|d| d.chain.len() <= MAX_PREDECESSOR_CHAIN));
+    }
+}
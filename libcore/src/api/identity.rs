@@ -1,9 +1,11 @@
-//! Identity exports: enrollment + QR invite pairing.
+//! Identity exports: enrollment, QR invite pairing, key rotation, contested
+//! successions and account deletion.
 
 use common::proto::mls_wire::PairingP;
 use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
 
+use crate::api::messaging::on_runtime;
 use crate::data::contact::Contact;
 use crate::data::identity::Identity;
 use crate::data::idqr::IdentityQr;
@@ -101,3 +103,73 @@ pub fn preview_invite(qr_bytes: Vec<u8>) -> Result<InvitePreview, CoreError> {
         expiry_ms: qr.invite.expiry_ms,
     })
 }
+
+/// Retire the identity key for a new one, for when the old one may have been
+/// exposed. Contacts and groups carry over; nobody needs to pair again. The
+/// recovery phrase, escrowed secret and backups all derive from the key, so
+/// the platform must export them afresh afterwards.
+#[uniffi::export]
+pub fn rotate_identity_key() -> Result<(), CoreError> {
+    crate::succession::rotate()?;
+    Ok(())
+}
+
+/// One of the statements naming an heir for a contact's contested old key.
+#[derive(uniffi::Record)]
+pub struct ContestedSuccession {
+    /// The retired 32-byte identity key both statements name.
+    pub old_ipk: Vec<u8>,
+    /// The heir this statement names.
+    pub new_ipk: Vec<u8>,
+    /// Unix-ms the statement was signed, as its signers claim.
+    pub timestamp: u64,
+    /// Unix seconds it reached us.
+    pub received_at: u64,
+}
+
+/// Contacts whose old key named more than one new key: whoever else held it
+/// rotated too. Nothing is sent to any of these heirs until the user asks the
+/// contact, out of band, which is theirs and calls [`settle_succession`].
+#[uniffi::export]
+pub fn contested_successions() -> Vec<ContestedSuccession> {
+    crate::data::succession::contested()
+        .into_iter()
+        .map(|(s, received_at)| ContestedSuccession {
+            old_ipk: s.old_ipk.0.to_vec(),
+            new_ipk: s.new_ipk.0.to_vec(),
+            timestamp: s.timestamp,
+            received_at,
+        })
+        .collect()
+}
+
+/// Settle a contested key on `new_ipk`, the heir the contact confirmed. The
+/// other is forgotten, and what was held for this one goes out.
+#[uniffi::export]
+pub fn settle_succession(old_ipk: Vec<u8>, new_ipk: Vec<u8>) -> Result<(), CoreError> {
+    let old: [u8; 32] =
+        old_ipk.try_into().map_err(|_| CoreError::Internal { msg: "bad old key".into() })?;
+    let heir: [u8; 32] =
+        new_ipk.try_into().map_err(|_| CoreError::Internal { msg: "bad new key".into() })?;
+    crate::succession::settle(&old, &heir)?;
+    Ok(())
+}
+
+/// Delete the account: the relay and every home forget our key and the keys
+/// it succeeded, then this device forgets everything. Fails, with nothing
+/// deleted here, when no relay answers or no home confirms. Returns how many
+/// homes confirmed, the connected relay among them when it is one.
+/// Irreversible; the platform should confirm first and restart afterwards.
+#[uniffi::export]
+pub async fn delete_account() -> Result<u32, CoreError> {
+    on_runtime(crate::account::delete()).await
+}
+
+/// Forget the account on this device only; the network keeps holding what it
+/// holds until it expires. For handing the device on with the account kept
+/// in a recovery phrase, or when [`delete_account`] can't reach a relay.
+#[uniffi::export]
+pub fn wipe_local_data() -> Result<(), CoreError> {
+    crate::account::wipe_local()?;
+    Ok(())
+}
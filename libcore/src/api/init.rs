@@ -19,6 +19,7 @@ use common::quic::config::setup_crypto_provider;
 use common::quic::protorole::ProtoRole;
 use log::debug;
 use log::error;
+use log::info;
 use log::trace;
 use once_cell::sync::Lazy;
 use quinn::Endpoint;
@@ -95,6 +96,9 @@ fn init_inner(
     ));
     client_cfg.transport_config(Arc::new(transport_cfg));
 
+    // Same QUIC config for the TCP/WebSocket fallbacks, which dial on
+    // endpoints of their own (see `quic::dialer::dial_relay`).
+    crate::quic::dialer::init_tunnels(client_cfg.clone(), &roots);
     endpoint.set_default_client_config(client_cfg);
     ENDPOINT.set(Arc::new(endpoint)).map_err(|_| anyhow::anyhow!("init called twice"))?;
 
@@ -109,6 +113,12 @@ fn init_inner(
         }
     });
 
+    // Payloads an earlier build kept because it couldn't read them.
+    RUNTIME.spawn(crate::quic::server::reparse_unsupported());
+
+    // A key rotation a crash cut short, finished before the first connect.
+    crate::succession::resume_moves();
+
     start_relay_loop(seeds);
     Ok(())
 }
@@ -218,7 +228,16 @@ fn start_relay_loop(seeds: Vec<ResolverSeed>) {
                         Err(ResolveError::EmptyResponse) => {
                             error!("resolver returned no relays; retrying")
                         },
-                        Err(err) => error!("resolver failed: {err}; retrying"),
+                        Err(err) => {
+                            error!("resolver failed: {err}; retrying");
+                            // Unreachable resolver: fall back to the last
+                            // signed directory so the relays it named get
+                            // another try while we keep retrying.
+                            match Relay::restore_cached_directory(&seeds) {
+                                Ok(n) => info!("restored {n} relay(s) from cached directory"),
+                                Err(err) => debug!("no usable cached directory: {err}"),
+                            }
+                        },
                     }
                     // Short backoff: a fresh resolve may have just populated the
                     // table, or all known relays are circuit-open and will reset.
@@ -1,4 +1,7 @@
-//! Inline-image send: FFI entry point for `send_image`.
+//! Media send: FFI entry points for images, attachments, voice notes and video,
+//! and for telling a chat we're recording a voice note.
+
+use common::proto::client_rel::ACTIVITY_RECORDING_VN;
 
 use crate::api::messaging::to_did16;
 use crate::api::messaging::to_fid32;
@@ -18,12 +21,33 @@ pub struct MediaRecord {
     pub blob: Option<Vec<u8>>,
     pub thumb: Option<Vec<u8>>,
     pub file_id: Option<Vec<u8>>,
+    pub duration_ms: u32,
+    pub waveform: Option<Vec<u8>>,
+    /// For a sticker: the pack and index to draw with
+    /// [`crate::api::stickers::get_sticker`].
+    pub sticker_pack: Option<Vec<u8>>,
+    pub sticker_id: u32,
     pub transfer_state: u8,
     pub transfer_have: u32,
     pub transfer_total: u32,
+    /// Bytes readable from the start of `local_path`: the whole file once
+    /// DONE, and for a video mid-pull the verified prefix a player may stream.
+    pub transfer_playable: u64,
     pub local_path: Option<String>,
 }
 
+/// What the platform read off a video before sending it. The poster is one
+/// decoded frame as RGBA; libcore encodes it, as with an image.
+#[derive(uniffi::Record)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: u32,
+    pub poster_rgba: Option<Vec<u8>>,
+    pub poster_w: u32,
+    pub poster_h: u32,
+}
+
 /// Compress `rgba` to AVIF (≤256KB) and send it to `to_ipk` as an inline
 /// `Image` message, with an optional `caption` and album `group_id`.
 /// Fire-and-forget like [`crate::api::messaging::send_message`]: the
@@ -112,6 +136,119 @@ pub fn send_attachment(
     Ok(())
 }
 
+/// Encode `pcm` (mono, `sample_rate` Hz, from the platform recorder) to Ogg
+/// Opus and send it as a `Voice` message. A note that fits the frame goes
+/// inline; a longer one is written under `files/voice` and offered by
+/// `file_id` like an attachment. Fire-and-forget like [`send_image`].
+#[uniffi::export]
+pub fn send_voice(
+    conversation_id: Vec<u8>, pcm: Vec<i16>, sample_rate: u32, group_id: Option<Vec<u8>>,
+) -> Result<(), CoreError> {
+    let to = to_conv16(&conversation_id)?;
+    let gid = group_id.as_deref().map(to_did16).transpose()?;
+    // Encode before the placeholder, unlike send_image: Opus at speech rates
+    // is far faster than realtime, and a bad rate should leave no bubble.
+    let note = crate::media::voice::encode_voice(&pcm, sample_rate)?;
+    let inline = note.data.len() <= crate::messaging::VOICE_INLINE_MAX;
+    let msg = crate::messaging::build_voice_message(to, &note, inline, gid)?;
+    let did: [u8; 16] = msg
+        .inner
+        .dispatch_id
+        .as_deref()
+        .and_then(|d| d.try_into().ok())
+        .expect("save_outgoing mints a dispatch_id");
+    if inline {
+        let payload_bytes = crate::messaging::rebuild_pending_payload(&to, &msg)?;
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = crate::messaging::send_prepared(to, &msg, payload_bytes).await {
+                log::warn!("MEDIA: send_voice failed: {e}");
+            }
+        });
+        return Ok(());
+    }
+    let path = format!("{}/{}.ogg", crate::db::files_dir("voice"), hex::encode(did));
+    if let Err(e) = std::fs::write(&path, &note.data) {
+        let _ = crate::data::media::discard_outgoing(&to, &did);
+        return Err(anyhow::anyhow!("write {path}: {e}").into());
+    }
+    crate::RUNTIME.spawn(async move {
+        // Same split as send_attachment: only a prepare failure discards.
+        let file_id = match crate::transfer::prepare_send(&path, 7 * 24 * 3600) {
+            Ok((file_id, _size)) => file_id,
+            Err(e) => {
+                log::warn!("MEDIA: send_voice prepare failed: {e}");
+                let _ = crate::data::media::discard_outgoing(&to, &did);
+                return;
+            },
+        };
+        if let Err(e) = crate::messaging::finish_attachment(to, did, file_id).await {
+            log::warn!("MEDIA: send_voice send deferred to retry: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Tell the chat we're recording a voice note (`recording = true`), or that
+/// we stopped. The composer calls it as the mic is held and released.
+#[uniffi::export]
+pub fn set_recording_voice(conversation_id: Vec<u8>, recording: bool) -> Result<(), CoreError> {
+    let activity = if recording { ACTIVITY_RECORDING_VN } else { 0 };
+    crate::api::messaging::set_activity(conversation_id, activity)
+}
+
+/// Offer the video at `source_path` like [`send_attachment`], carrying its
+/// dimensions, duration and an AVIF poster encoded from `info`'s frame, so the
+/// recipient's bubble is complete before a byte is pulled. Fire-and-forget;
+/// the `Result` reports only synchronous input errors.
+#[uniffi::export]
+pub fn send_video(
+    conversation_id: Vec<u8>, source_path: String, name: String, mime: String, info: VideoInfo,
+    caption: String, group_id: Option<Vec<u8>>,
+) -> Result<(), CoreError> {
+    let to = to_conv16(&conversation_id)?;
+    let gid = group_id.as_deref().map(to_did16).transpose()?;
+    let size = std::fs::metadata(&source_path)
+        .map_err(|e| anyhow::anyhow!("stat {source_path}: {e}"))?
+        .len();
+    // Sync like send_attachment's blur: one small frame, and a bad one should
+    // fail the call rather than send a bubble with no poster.
+    let poster = info
+        .poster_rgba
+        .map(|r| crate::media::poster_frame(&r, info.poster_w, info.poster_h))
+        .transpose()?;
+    let video = crate::messaging::VideoMeta {
+        size,
+        name,
+        mime,
+        width: info.width,
+        height: info.height,
+        duration_ms: info.duration_ms,
+        poster,
+    };
+    let msg = crate::messaging::build_video_message(to, video, &caption, gid)?;
+    let did: [u8; 16] = msg
+        .inner
+        .dispatch_id
+        .as_deref()
+        .and_then(|d| d.try_into().ok())
+        .expect("save_outgoing mints a dispatch_id");
+    crate::RUNTIME.spawn(async move {
+        // Same split as send_attachment: only a prepare failure discards.
+        let file_id = match crate::transfer::prepare_send(&source_path, 7 * 24 * 3600) {
+            Ok((file_id, _size)) => file_id,
+            Err(e) => {
+                log::warn!("MEDIA: send_video prepare failed: {e}");
+                let _ = crate::data::media::discard_outgoing(&to, &did);
+                return;
+            },
+        };
+        if let Err(e) = crate::messaging::finish_attachment(to, did, file_id).await {
+            log::warn!("MEDIA: send_video send deferred to retry: {e}");
+        }
+    });
+    Ok(())
+}
+
 /// Pull a received attachment's bytes by `file_id`. Fire-and-forget: dials the
 /// sender (or reverse-wakes them if offline) and drives the resumable transfer;
 /// progress and completion surface through `get_media`'s transfer_state.
@@ -129,7 +266,9 @@ pub fn download_attachment(file_id: Vec<u8>) -> Result<(), CoreError> {
 /// Read media records for a peer from the message_media table, with transfer
 /// progress (state/have/total in chunks) joined in from the transfer store.
 /// `local_path` is only exposed once the download is DONE — until then the
-/// `.part` file holds unverified-tail bytes no platform should open.
+/// `.part` file holds unverified-tail bytes no platform should open. A video
+/// is the one exception: its path shows as soon as a verified prefix exists,
+/// and `transfer_playable` bounds what a player may read.
 #[uniffi::export]
 pub fn get_media(conversation_id: Vec<u8>) -> Result<Vec<MediaRecord>, CoreError> {
     use crate::transfer::store;
@@ -137,22 +276,27 @@ pub fn get_media(conversation_id: Vec<u8>) -> Result<Vec<MediaRecord>, CoreError
     let rows = crate::data::media::for_conversation(&conv)?;
     Ok(rows.into_iter().map(|(did, r)| {
         let fid = r.file_id.as_deref().and_then(|f| <&[u8; 32]>::try_from(f).ok());
-        let (transfer_state, transfer_have, transfer_total, local_path) = match fid.and_then(store::partial_get) {
-            Some(p) => (
-                p.state,
-                p.have,
-                p.total.div_ceil(p.chunk_size.max(1) as u64) as u32,
-                (p.state == store::DONE).then(|| p.path.clone()),
-            ),
+        let (transfer_state, transfer_have, transfer_total, transfer_playable, local_path) = match fid.and_then(store::partial_get) {
+            Some(p) => {
+                let playable = crate::transfer::playable_bytes(&p);
+                let streamable = r.kind == crate::data::media::KIND_VIDEO && playable > 0;
+                (
+                    p.state,
+                    p.have,
+                    p.total.div_ceil(p.chunk_size.max(1) as u64) as u32,
+                    playable,
+                    (p.state == store::DONE || streamable).then(|| p.path.clone()),
+                )
+            },
             // No receiver partial: this may be our OWN sent attachment, whose
             // file lives in `retention` under the same file_id. Surface it as a
             // complete local file so the sender can open what they sent.
             None => match fid.and_then(store::retention_get) {
                 Some(ret) => {
                     let chunks = ret.size.div_ceil(ret.chunk_size.max(1) as u64) as u32;
-                    (store::DONE, chunks, chunks, Some(ret.path))
+                    (store::DONE, chunks, chunks, ret.size, Some(ret.path))
                 },
-                None => (store::PENDING, 0, 0, None),
+                None => (store::PENDING, 0, 0, 0, None),
             },
         };
         MediaRecord {
@@ -167,9 +311,14 @@ pub fn get_media(conversation_id: Vec<u8>) -> Result<Vec<MediaRecord>, CoreError
             blob: r.blob,
             thumb: r.thumb,
             file_id: r.file_id,
+            duration_ms: r.duration_ms,
+            waveform: r.waveform,
+            sticker_pack: r.sticker_pack,
+            sticker_id: r.sticker_id,
             transfer_state,
             transfer_have,
             transfer_total,
+            transfer_playable,
             local_path,
         }
     }).collect())
@@ -242,6 +391,49 @@ mod tests {
         assert_eq!(row.file_id.as_deref(), Some(file_id.as_slice()));
     }
 
+    /// An inline voice note is final at build time: bytes, duration and
+    /// waveform land together, and the row rebuilds as a `Voice` post. A
+    /// file-backed one holds no bytes and stays unsendable until its file_id.
+    #[cfg(feature = "voice")]
+    #[test]
+    fn voice_note_persists_inline_or_waits_for_file_id() {
+        let dir = std::env::temp_dir().join("promtuz-send-voice-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        use common::proto::mls_wire::AppPayload;
+        use common::proto::mls_wire::Body;
+        use common::proto::pack::Unpacker;
+
+        let conv = [0x0au8; 16];
+        let pcm: Vec<i16> = (0..16_000).map(|i| ((i % 64) * 200) as i16).collect();
+        let note = crate::media::voice::encode_voice(&pcm, 16_000).unwrap();
+
+        let msg = crate::messaging::build_voice_message(conv, &note, true, None).unwrap();
+        let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
+        let row = media::get(&conv, &did).unwrap().expect("voice row");
+        assert_eq!(row.kind, media::KIND_VOICE);
+        assert_eq!(row.duration_ms, 1_000);
+        assert_eq!(row.waveform.as_deref(), Some(note.waveform.as_slice()));
+        let bytes = crate::messaging::rebuild_pending_payload(&conv, &msg).unwrap();
+        assert!(matches!(
+            AppPayload::deser(&bytes).unwrap(),
+            AppPayload::Post { body: Body::Voice { data, file_id: None, .. }, .. }
+                if data == note.data
+        ));
+
+        let msg = crate::messaging::build_voice_message(conv, &note, false, None).unwrap();
+        let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
+        assert!(crate::messaging::rebuild_pending_payload(&conv, &msg).is_err());
+        media::set_file_id(&conv, &did, &[0x5au8; 32]).unwrap();
+        let bytes = crate::messaging::rebuild_pending_payload(&conv, &msg).unwrap();
+        assert!(matches!(
+            AppPayload::deser(&bytes).unwrap(),
+            AppPayload::Post { body: Body::Voice { data, file_id: Some(f), .. }, .. }
+                if data.is_empty() && f == [0x5au8; 32]
+        ));
+    }
+
     #[test]
     fn get_media_returns_media_records_for_peer() {
         let dir = std::env::temp_dir().join("promtuz-get-media-test");
@@ -297,4 +489,66 @@ mod tests {
         assert_eq!(record.transfer_total, 2); // 300KB / 256KB, div_ceil
         assert_eq!(record.transfer_have, record.transfer_total, "all chunks present");
     }
+
+    /// A video rebuilds as a `Video` post once its file_id lands, and a
+    /// receiver mid-pull sees its `.part` path with the verified prefix as
+    /// the playable bound — unlike an attachment, which stays hidden.
+    #[test]
+    fn video_rebuilds_once_hashed_and_streams_its_prefix() {
+        let dir = std::env::temp_dir().join("promtuz-send-video-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        use common::proto::mls_wire::AppPayload;
+        use common::proto::mls_wire::Body;
+        use common::proto::pack::Unpacker;
+        use crate::transfer::store;
+        use crate::transfer::wire::ChunkSet;
+
+        let conv = [0x0bu8; 16];
+        let file_id = [0x6bu8; 32];
+        let poster = crate::media::poster_frame(&vec![90u8; 64 * 36 * 4], 64, 36).unwrap();
+        let video = crate::messaging::VideoMeta {
+            size:        1000,
+            name:        "clip.mp4".into(),
+            mime:        "video/mp4".into(),
+            width:       1920,
+            height:      1080,
+            duration_ms: 4200,
+            poster:      Some(poster.clone()),
+        };
+        let msg = crate::messaging::build_video_message(conv, video, "look", None).unwrap();
+        let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
+        assert!(crate::messaging::rebuild_pending_payload(&conv, &msg).is_err(), "not hashed yet");
+        media::set_file_id(&conv, &did, &file_id).unwrap();
+        let bytes = crate::messaging::rebuild_pending_payload(&conv, &msg).unwrap();
+        assert!(matches!(
+            AppPayload::deser(&bytes).unwrap(),
+            AppPayload::Post { body: Body::Video { caption, width: 1920, duration_ms: 4200,
+                poster: p, file_id: f, .. }, .. }
+                if caption == "look" && p == poster && f == file_id
+        ));
+
+        let mut got = ChunkSet::empty(4);
+        got.insert(0);
+        got.insert(2);
+        store::partial_put(&store::Partial {
+            file_id,
+            source_ipk: [1u8; 32],
+            total: 1000,
+            chunk_size: 256,
+            manifest: None,
+            have: got.count(),
+            bitmap: Some(got.as_bytes().to_vec()),
+            state: store::ACTIVE,
+            path: "/tmp/clip.part".into(),
+            updated_at: 0,
+        })
+        .unwrap();
+        let records = super::get_media(conv.to_vec()).unwrap();
+        let record = records.iter().find(|r| r.dispatch_id == did.to_vec()).expect("record found");
+        assert_eq!(record.kind, media::KIND_VIDEO);
+        assert_eq!(record.transfer_playable, 256, "chunk 2 sits past the gap");
+        assert_eq!(record.local_path.as_deref(), Some("/tmp/clip.part"));
+    }
 }
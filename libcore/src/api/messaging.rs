@@ -31,6 +31,8 @@ pub struct MessageRecord {
     /// `sender_ipk` is who acted and `content` names the target — a hex IPK
     /// for the membership events, the new title for a rename.
     pub system: u8,
+    /// Passed along from another chat. Says nothing of where from.
+    pub forwarded: bool,
     /// When this row heads an album, the dispatch ids it collapses — itself
     /// first. Empty otherwise.
     ///
@@ -180,6 +182,30 @@ pub fn edit_message(
     Ok(())
 }
 
+/// Forward message `dispatch_id` of `src_conversation` into each of
+/// `dst_conversations`, marked as forwarded. Media goes as it was stored — the
+/// same AVIF bytes, or the same `file_id`, which we keep serving to the new
+/// recipients — so nothing is re-encoded. Fire-and-forget per destination;
+/// the `Result` reports a message that can't be forwarded (a tombstone, a
+/// system line, an attachment we never downloaded) or an unknown chat.
+#[uniffi::export]
+pub fn forward_message(
+    src_conversation: Vec<u8>, dispatch_id: Vec<u8>, dst_conversations: Vec<Vec<u8>>,
+) -> Result<(), CoreError> {
+    let src = to_conv16(&src_conversation)?;
+    let did = to_did16(&dispatch_id)?;
+    let dsts = dst_conversations.iter().map(|d| to_conv16(d)).collect::<Result<Vec<_>, _>>()?;
+    for dst in dsts {
+        let msg = crate::messaging::build_forwarded_message(src, did, dst)?;
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = crate::messaging::send_forwarded(dst, msg).await {
+                log::error!("MESSAGE: forward failed: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
 /// Emit an ephemeral activity signal to `peer` — an OR of `ACTIVITY_*` bits
 /// (0 = present-idle). Fire-and-forget; dropped if we or the peer are offline.
 /// The peer sees it via `on_activity`. Call on typing start/stop (throttled).
@@ -319,6 +345,56 @@ pub fn register_push() {
     });
 }
 
+/// Limit when our home relays may wake this device. `mute_all` stops every
+/// wake; `quiet_start_min`/`quiet_end_min` (both or neither, minutes since
+/// **UTC** midnight — convert from local time, and call again when the timezone
+/// changes) silence a daily window; a non-empty `allowed_senders` (32-byte
+/// IPKs) wakes only for those contacts. Messages still queue either way.
+///
+/// Errors on a quiet range with only one end, or an end past 23:59, rather
+/// than sign a policy the user did not ask for.
+#[uniffi::export]
+pub fn set_wake_policy(
+    mute_all: bool, quiet_start_min: Option<u16>, quiet_end_min: Option<u16>,
+    allowed_senders: Vec<Vec<u8>>,
+) -> Result<(), CoreError> {
+    use common::proto::dht_p2p::QuietHours;
+    use common::proto::dht_p2p::WakePolicy;
+
+    let quiet_hours = match (quiet_start_min, quiet_end_min) {
+        (None, None) => None,
+        (Some(start_min), Some(end_min)) if start_min < 1440 && end_min < 1440 => {
+            Some(QuietHours { start_min, end_min })
+        },
+        (Some(_), Some(_)) => return Err(bad_quiet_hours("minutes must be below 1440")),
+        _ => return Err(bad_quiet_hours("need both a start and an end")),
+    };
+    let allowed_senders = allowed_senders
+        .into_iter()
+        .filter_map(|ipk| <[u8; 32]>::try_from(ipk).ok().map(Into::into))
+        .take(common::proto::dht_p2p::MAX_WAKE_ALLOWED_SENDERS)
+        .collect();
+    let policy = WakePolicy { mute_all, quiet_hours, allowed_senders };
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::push::set_wake_policy(policy).await {
+            log::debug!("PUSH: set_wake_policy failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+fn bad_quiet_hours(why: &str) -> CoreError {
+    CoreError::Internal { msg: format!("quiet hours: {why}") }
+}
+
+/// How much to pad outgoing messages so their size does not give away what
+/// they are: the privacy/bandwidth tradeoff. Process-lifetime; call on launch
+/// and whenever the user changes it.
+#[uniffi::export]
+pub fn set_padding_policy(policy: crate::mls::padding::PaddingPolicy) {
+    crate::mls::padding::set_policy(policy);
+}
+
 /// Provide/refresh the platform push token — call from the FCM `onNewToken`
 /// callback. Stores it and registers `P → token` with a gateway so a wake can
 /// reach this device.
@@ -329,6 +405,28 @@ pub fn register_push_token(token: Vec<u8>) {
     });
 }
 
+/// What a sealed wake payload says, for the notification shown before the
+/// device drains.
+#[derive(uniffi::Record)]
+pub struct WakePreview {
+    pub sender_ipk: Vec<u8>,
+    pub conversation_id: Vec<u8>,
+    pub dispatch_id: Vec<u8>,
+}
+
+/// Open the payload of a push wake — call from the FCM handler with the
+/// decoded `p` field. `None` for a contentless wake or one that doesn't open
+/// and verify; the app then shows a generic notification and drains.
+#[uniffi::export]
+pub fn open_wake_payload(payload: Vec<u8>) -> Option<WakePreview> {
+    let hint = crate::push::open_wake_hint(&payload)?;
+    Some(WakePreview {
+        sender_ipk: hint.sender.0.to_vec(),
+        conversation_id: hint.conversation.0.to_vec(),
+        dispatch_id: hint.dispatch_id.0.to_vec(),
+    })
+}
+
 /// Delete a prior message. `for_everyone` tombstones both sides; otherwise it's
 /// a local-only removal. Surfaces via `on_message(Deleted)`.
 #[uniffi::export]
@@ -690,6 +788,7 @@ pub fn forget_contact(ipk: Vec<u8>) -> Result<(), CoreError> {
         }
     }
     crate::delivery::forget_target(&ipk);
+    crate::sealed::forget(&ipk);
     // Sever any live direct link so a forgotten contact can't keep talking
     // over an already-open P2P connection.
     crate::p2p::drop_link(&ipk);
@@ -741,6 +840,7 @@ impl From<MessageRow> for MessageRecord {
             deleted: r.deleted,
             reply_to: r.reply_to,
             system: r.system,
+            forwarded: r.forwarded,
             album_items: Vec::new(),
             in_album: false,
         }
@@ -774,14 +874,15 @@ pub(crate) fn to_fid32(bytes: &[u8]) -> Result<[u8; 32], CoreError> {
 // All four need a live relay (a KeyPackage fetch and a Welcome), so unlike a
 // message they report their outcome synchronously rather than outboxing.
 //
-// These are the only `async` exports on the surface, and uniffi polls them on
-// its own executor — no Tokio reactor in scope, so QUIC I/O inside would fail
-// with "there is no reactor running". [`on_runtime`] moves the work onto the
-// global runtime; the JoinHandle we await back is a plain future the runtime
-// wakes, so uniffi's executor is fine holding it.
+// These and social recovery's are the only `async` exports on the surface,
+// and uniffi polls them on its own executor — no Tokio reactor in scope, so
+// QUIC I/O inside would fail with "there is no reactor running".
+// [`on_runtime`] moves the work onto the global runtime; the JoinHandle we
+// await back is a plain future the runtime wakes, so uniffi's executor is fine
+// holding it.
 
 /// Run `fut` on [`crate::RUNTIME`] and await its result.
-async fn on_runtime<T, F>(fut: F) -> Result<T, CoreError>
+pub(crate) async fn on_runtime<T, F>(fut: F) -> Result<T, CoreError>
 where
     T: Send + 'static,
     F: std::future::Future<Output = anyhow::Result<T>> + Send + 'static,
@@ -829,3 +930,11 @@ pub async fn leave_group(conversation_id: Vec<u8>) -> Result<(), CoreError> {
     let conv = to_conv16(&conversation_id)?;
     on_runtime(crate::groups::leave(conv)).await
 }
+
+/// Move a group onto post-quantum hybrid encryption. Admin-only, and every
+/// member needs an app that supports it.
+#[uniffi::export]
+pub async fn upgrade_group_encryption(conversation_id: Vec<u8>) -> Result<(), CoreError> {
+    let conv = to_conv16(&conversation_id)?;
+    on_runtime(crate::groups::upgrade_suite(conv)).await
+}
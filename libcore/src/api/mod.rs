@@ -12,11 +12,13 @@ pub mod identity;
 pub mod init;
 pub mod media;
 pub mod messaging;
+pub mod polls;
 pub mod portable;
 pub mod qr;
 pub mod recovery;
 pub mod relays;
 pub mod staging;
+pub mod stickers;
 pub mod update;
 
 use crate::data::identity::Identity;
@@ -0,0 +1,205 @@
+//! Polls: FFI entry points for asking, voting, closing and reading results.
+
+use crate::api::messaging::to_conv16;
+use crate::api::messaging::to_did16;
+use crate::data::poll::Poll;
+use crate::platform::CoreError;
+
+/// One member's current vote. Only listed on a poll that isn't anonymous.
+#[derive(uniffi::Record)]
+pub struct PollVoteRecord {
+    pub voter: Vec<u8>,
+    pub choices: Vec<u32>,
+    pub voted_at: u64,
+}
+
+/// A poll with its tally, projected for the client.
+#[derive(uniffi::Record)]
+pub struct PollRecord {
+    pub dispatch_id: Vec<u8>,
+    pub question: String,
+    pub options: Vec<String>,
+    pub multi: bool,
+    pub anonymous: bool,
+    pub closes_at: Option<u64>,
+    /// No longer taking votes, by deadline or by its author closing it.
+    pub closed: bool,
+    /// Votes per option, in option order.
+    pub tally: Vec<u32>,
+    /// Members who currently have a vote in.
+    pub voters_count: u32,
+    /// What we chose. Ours to see even on an anonymous poll.
+    pub mine: Vec<u32>,
+    /// Who chose what; empty when `anonymous`.
+    pub votes: Vec<PollVoteRecord>,
+    /// We asked it, so we may close it.
+    pub can_close: bool,
+}
+
+/// Ask the chat `question`. `closes_at` (unix seconds) stops votes without a
+/// [`close_poll`]. Fire-and-forget like [`crate::api::stickers::send_sticker`];
+/// the `Result` reports only a poll not worth sending — a blank question, or
+/// fewer than two distinct options.
+#[uniffi::export]
+pub fn send_poll(
+    conversation_id: Vec<u8>, question: String, options: Vec<String>, multi: bool,
+    anonymous: bool, closes_at: Option<u64>,
+) -> Result<(), CoreError> {
+    let to = to_conv16(&conversation_id)?;
+    let poll = Poll { question, options, multi, anonymous, closes_at, closed_at: None };
+    let msg = crate::messaging::build_poll_message(to, &poll)?;
+    let payload_bytes = crate::messaging::rebuild_pending_payload(&to, &msg)?;
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::messaging::send_prepared(to, &msg, payload_bytes).await {
+            log::warn!("POLL: send_poll failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Vote on the poll with `dispatch_id`, replacing any vote we had in; an
+/// empty `choices` withdraws it. Fire-and-forget; the tally moves via
+/// `on_db_changed`.
+#[uniffi::export]
+pub fn vote_poll(
+    conversation_id: Vec<u8>, dispatch_id: Vec<u8>, choices: Vec<u32>,
+) -> Result<(), CoreError> {
+    let conv = to_conv16(&conversation_id)?;
+    let poll = to_did16(&dispatch_id)?;
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::messaging::vote(conv, poll, choices).await {
+            log::error!("POLL: vote failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Close a poll we asked. Fire-and-forget, like [`vote_poll`].
+#[uniffi::export]
+pub fn close_poll(conversation_id: Vec<u8>, dispatch_id: Vec<u8>) -> Result<(), CoreError> {
+    let conv = to_conv16(&conversation_id)?;
+    let poll = to_did16(&dispatch_id)?;
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::messaging::close_poll(conv, poll).await {
+            log::error!("POLL: close failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Every poll in a conversation with its current tally. The UI matches each to
+/// its message by `dispatch_id`.
+#[uniffi::export]
+pub fn polls_for(conversation_id: Vec<u8>) -> Result<Vec<PollRecord>, CoreError> {
+    let conv = to_conv16(&conversation_id)?;
+    let me = crate::data::identity::Identity::get().map(|i| i.ipk());
+    let now = crate::utils::systime().as_secs();
+    let votes = crate::data::poll::votes_for(&conv);
+    Ok(crate::data::poll::for_conversation(&conv)
+        .into_iter()
+        .map(|(did, poll)| {
+            let current: Vec<_> =
+                votes.iter().filter(|v| v.dispatch_id == did && v.choices != 0).cloned().collect();
+            let mine = current
+                .iter()
+                .find(|v| me.as_ref().is_some_and(|m| m == &v.voter))
+                .map(|v| v.choices())
+                .unwrap_or_default();
+            let can_close = poll.closed_at.is_none()
+                && crate::data::message::Message::get_by_dispatch(&conv, &did)
+                    .is_some_and(|m| m.inner.outgoing);
+            PollRecord {
+                dispatch_id: did.to_vec(),
+                tally: crate::data::poll::tally(&poll, &current),
+                voters_count: current.len() as u32,
+                mine,
+                votes: if poll.anonymous {
+                    Vec::new()
+                } else {
+                    current
+                        .iter()
+                        .map(|v| PollVoteRecord {
+                            voter: v.voter.to_vec(),
+                            choices: v.choices(),
+                            voted_at: v.voted_at,
+                        })
+                        .collect()
+                },
+                closed: !poll.is_open_at(now),
+                can_close,
+                question: poll.question,
+                options: poll.options,
+                multi: poll.multi,
+                anonymous: poll.anonymous,
+                closes_at: poll.closes_at,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    /// A sent poll rebuilds as a `Poll` post, takes our vote, and reports it
+    /// as ours on an anonymous poll without listing who voted.
+    #[test]
+    fn poll_rebuilds_tallies_and_hides_voters_when_anonymous() {
+        let dir = std::env::temp_dir().join("promtuz-send-poll-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        use common::proto::mls_wire::AppPayload;
+        use common::proto::mls_wire::Body;
+        use common::proto::pack::Unpacker;
+
+        let conv = [0x0du8; 16];
+        let poll = crate::data::poll::Poll {
+            question: "lunch?".into(),
+            options: vec!["noodles".into(), "tacos".into()],
+            multi: false,
+            anonymous: true,
+            closes_at: None,
+            closed_at: None,
+        };
+        let msg = crate::messaging::build_poll_message(conv, &poll).unwrap();
+        let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
+        let bytes = crate::messaging::rebuild_pending_payload(&conv, &msg).unwrap();
+        assert_eq!(AppPayload::deser(&bytes).unwrap(), AppPayload::Post {
+            reply_to: None,
+            body:     Body::Poll {
+                question:  "lunch?".into(),
+                options:   vec!["noodles".into(), "tacos".into()],
+                multi:     false,
+                anonymous: true,
+                closes_at: None,
+            },
+        });
+
+        let voter = [0x5au8; 32];
+        assert!(crate::data::poll::apply_vote(&conv, &did, &voter, &[1], 10).unwrap());
+        assert!(crate::data::poll::apply_vote(&conv, &did, &voter, &[0, 1], 11).is_err());
+
+        let records = super::polls_for(conv.to_vec()).unwrap();
+        let record = records.iter().find(|r| r.dispatch_id == did.to_vec()).expect("poll listed");
+        assert_eq!(record.tally, vec![0, 1]);
+        assert_eq!(record.voters_count, 1);
+        assert!(record.votes.is_empty(), "anonymous: nobody's ballot is listed");
+        assert!(record.can_close);
+
+        assert!(crate::messaging::apply_revise_body(&conv, &did, Body::Text("dinner?".into()), true)
+            .is_err(), "a poll revises to nothing");
+
+        let repeated = Body::Poll {
+            question:  "lunch?".into(),
+            options:   vec!["tacos".into(), "tacos ".into()],
+            multi:     false,
+            anonymous: false,
+            closes_at: None,
+        };
+        let (sender, did2) = ([0x5bu8; 32], [0x5cu8; 16]);
+        assert!(
+            crate::messaging::save_inbound_body(&conv, &sender, &did2, 1, None, repeated, false)
+                .is_err(),
+            "a received poll is held to what we'd send"
+        );
+    }
+}
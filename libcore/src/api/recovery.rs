@@ -2,9 +2,14 @@
 //! (IDENTITY_RECOVERY.md §6). The platform MUST device-auth-gate
 //! [`export_recovery_phrase`] and [`escrow_secret`] (biometric / device
 //! credential) — libcore cannot enforce that from below the boundary.
+//! [`social_recovery_secret`] hands back a key rebuilt on a throwaway install,
+//! for [`adopt_escrowed_secret`] on a clean one.
 
+use crate::api::messaging::on_runtime;
+use crate::api::messaging::to_ipk32;
 use crate::data::recovery;
 use crate::platform::CoreError;
+use crate::social_recovery;
 
 /// The identity as a 24-word BIP39 phrase (Channel B). **Auth-gate on the
 /// platform side is mandatory** — this is the private key, in words.
@@ -52,6 +57,32 @@ pub fn backup_import(blob: Vec<u8>) -> Result<(), CoreError> {
     Ok(crate::data::backup::import(&blob)?)
 }
 
+/// Where one restored conversation stands on getting back into its group.
+/// `state` is a `REJOIN_*` constant: 0 pending, 1 asked the admin, 2 back in,
+/// 3 failed (`detail` says why; retried on the next connect).
+#[derive(uniffi::Record)]
+pub struct RejoinProgress {
+    pub conversation: Vec<u8>,
+    pub state:        u8,
+    pub detail:       String,
+    pub updated_at:   u64,
+}
+
+/// Per-conversation rejoin progress after a [`backup_import`]. Empty when
+/// nothing was restored. Re-read on `on_db_changed`.
+#[uniffi::export]
+pub fn rejoin_progress() -> Vec<RejoinProgress> {
+    crate::data::rejoin::all()
+        .into_iter()
+        .map(|r| RejoinProgress {
+            conversation: r.conversation.to_vec(),
+            state:        r.state,
+            detail:       r.detail,
+            updated_at:   r.updated_at,
+        })
+        .collect()
+}
+
 /// Per-table account of a [`backup_import_merge`] run, so the caller can
 /// report exactly what a blob carried and what was taken from it.
 #[derive(uniffi::Record)]
@@ -73,6 +104,10 @@ pub struct BackupMergeReport {
     pub conversations_added:   u32,
     pub media_in_blob:         u32,
     pub media_added:           u32,
+    pub polls_in_blob:         u32,
+    pub polls_added:           u32,
+    pub votes_in_blob:         u32,
+    pub votes_added:           u32,
 }
 
 /// Additive restore: insert only what we don't already have, never replace,
@@ -98,5 +133,147 @@ pub fn backup_import_merge(blob: Vec<u8>) -> Result<BackupMergeReport, CoreError
         conversations_added:   r.conversations_added,
         media_in_blob:         r.media_in_blob,
         media_added:           r.media_added,
+        polls_in_blob:         r.polls_in_blob,
+        polls_added:           r.polls_added,
+        votes_in_blob:         r.votes_in_blob,
+        votes_added:           r.votes_added,
     })
 }
+
+// ── Social recovery ───────────────────────────────────────────────────────
+
+/// A contact holding a share of our key.
+#[derive(uniffi::Record)]
+pub struct RecoveryHolder {
+    pub ipk:       Vec<u8>,
+    pub index:     u8,
+    pub threshold: u8,
+    pub sent_at:   u64,
+}
+
+/// A fresh install asking for a share we hold. Approve only once the person
+/// asking has read out the same `code` from their screen.
+#[derive(uniffi::Record)]
+pub struct RecoveryAsk {
+    pub asker:       Vec<u8>,
+    /// Who they say they are. Unverified.
+    pub name:        String,
+    pub code:        String,
+    pub received_at: u64,
+}
+
+/// A holder this install asked, and whether their share is back.
+#[derive(uniffi::Record)]
+pub struct RecoveryAsked {
+    pub holder:   Vec<u8>,
+    pub returned: bool,
+}
+
+/// Where this install's recovery stands. `owner` and `threshold` are unknown
+/// (`None`, 0) until the first share is back.
+#[derive(uniffi::Record)]
+pub struct RecoverySession {
+    /// Read this to each holder.
+    pub code:      String,
+    pub owner:     Option<Vec<u8>>,
+    pub threshold: u8,
+    pub asked:     Vec<RecoveryAsked>,
+}
+
+/// Split our key across `holders` (paired contacts), any `threshold` of
+/// whom can give it back. Replaces the previous split.
+#[uniffi::export]
+pub async fn setup_social_recovery(
+    holders: Vec<Vec<u8>>, threshold: u8,
+) -> Result<(), CoreError> {
+    let list = holders.iter().map(|h| to_ipk32(h)).collect::<Result<Vec<_>, _>>()?;
+    on_runtime(social_recovery::distribute(list, threshold)).await
+}
+
+/// Tell every holder to drop its share.
+#[uniffi::export]
+pub async fn disable_social_recovery() -> Result<(), CoreError> {
+    on_runtime(social_recovery::disable()).await
+}
+
+/// Who holds a share of our key. Re-read on `on_db_changed`.
+#[uniffi::export]
+pub fn social_recovery_holders() -> Vec<RecoveryHolder> {
+    social_recovery::holders()
+        .into_iter()
+        .map(|h| RecoveryHolder {
+            ipk:       h.holder.to_vec(),
+            index:     h.index,
+            threshold: h.threshold,
+            sent_at:   h.sent_at,
+        })
+        .collect()
+}
+
+/// Contacts whose share we keep — the choices for [`approve_recovery_ask`].
+#[uniffi::export]
+pub fn recovery_shares_held() -> Vec<Vec<u8>> {
+    social_recovery::held_for().into_iter().map(|o| o.to_vec()).collect()
+}
+
+/// Requests for a share awaiting the user, newest first.
+#[uniffi::export]
+pub fn recovery_asks() -> Vec<RecoveryAsk> {
+    social_recovery::asks()
+        .into_iter()
+        .map(|a| RecoveryAsk {
+            asker:       a.asker.to_vec(),
+            name:        a.name,
+            code:        a.code,
+            received_at: a.received_at,
+        })
+        .collect()
+}
+
+/// Send `owner`'s share back to `asker`. **Only after the user compared the
+/// code** — that comparison is the whole check.
+#[uniffi::export]
+pub async fn approve_recovery_ask(asker: Vec<u8>, owner: Vec<u8>) -> Result<(), CoreError> {
+    let asker = to_ipk32(&asker)?;
+    let owner = to_ipk32(&owner)?;
+    on_runtime(social_recovery::approve(asker, owner)).await
+}
+
+/// Drop a request unanswered.
+#[uniffi::export]
+pub fn decline_recovery_ask(asker: Vec<u8>) -> Result<(), CoreError> {
+    social_recovery::decline(to_ipk32(&asker)?);
+    Ok(())
+}
+
+/// From a throwaway install: ask `holder` (scanned from their contact QR) for
+/// the share they keep. The holders its share lists are asked automatically.
+#[uniffi::export]
+pub async fn ask_for_recovery_share(holder: Vec<u8>) -> Result<(), CoreError> {
+    let holder = to_ipk32(&holder)?;
+    on_runtime(social_recovery::ask(holder)).await
+}
+
+/// This install's recovery progress. Re-read on `on_db_changed`.
+#[uniffi::export]
+pub fn social_recovery_session() -> RecoverySession {
+    let s = social_recovery::session();
+    RecoverySession {
+        code:      s.code,
+        owner:     s.owner.map(|o| o.to_vec()),
+        threshold: s.threshold,
+        asked:     s
+            .asked
+            .into_iter()
+            .map(|(holder, returned)| RecoveryAsked { holder: holder.to_vec(), returned })
+            .collect(),
+    }
+}
+
+/// The rebuilt isk, once enough shares are back. Treat like
+/// [`escrow_secret`]: the platform discards this throwaway install and passes
+/// it to [`adopt_escrowed_secret`] on a clean one.
+#[uniffi::export]
+pub fn social_recovery_secret() -> Result<Vec<u8>, CoreError> {
+    Ok(social_recovery::recovered_isk()?.to_vec())
+}
@@ -1,10 +1,16 @@
 //! Relay diagnostics exports: read the stored relay set + health/latency,
-//! and the three dev actions (reset circuit, forget, reconnect).
+//! the three dev actions (reset circuit, forget, reconnect), the signed
+//! relay directory export/import for sharing relays out of band, and the
+//! per-relay WebSocket bridge setting.
 
 use std::collections::HashMap;
 
+use common::proto::client_res::RelayDirectory;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
 use rusqlite::params;
 
+use crate::data::relay::Relay;
 use crate::db::network::CircuitState;
 use crate::db::network::NETWORK_DB;
 use crate::platform::CoreError;
@@ -45,6 +51,10 @@ pub struct RelayStat {
     pub backoff_until:        Option<u64>,
     /// True for the one relay currently serving as the live home connection.
     pub is_connected:         bool,
+    /// Transport the next dial uses: `quic`, `tls` or `websocket`.
+    pub transport:            String,
+    /// Configured WebSocket bridge URL, if any.
+    pub bridge:               Option<String>,
     /// RTT history (ms), oldest→newest, for the latency graph.
     pub latency_samples:      Vec<u64>,
 }
@@ -77,7 +87,8 @@ pub fn get_relays() -> Result<Vec<RelayStat>, CoreError> {
         .prepare(
             "SELECT id, host, port, circuit_state, consecutive_failures,
                     window_attempts, window_successes, last_latency,
-                    last_connect, backoff_until
+                    last_connect, backoff_until, transport,
+                    (SELECT url FROM relay_bridges WHERE relay_id = relays.id) AS bridge
              FROM relays
              ORDER BY last_connect DESC",
         )
@@ -101,6 +112,8 @@ pub fn get_relays() -> Result<Vec<RelayStat>, CoreError> {
                 last_latency:         row.get::<_, Option<i64>>("last_latency")?.map(|v| v as u64),
                 last_connect:         row.get::<_, Option<i64>>("last_connect")?.map(|v| v as u64),
                 backoff_until:        row.get::<_, Option<i64>>("backoff_until")?.map(|v| v as u64),
+                transport:            row.get("transport")?,
+                bridge:               row.get("bridge")?,
                 id,
             })
         })
@@ -148,6 +161,47 @@ pub fn connect_relay(id: String) -> Result<(), CoreError> {
     Ok(())
 }
 
+/// Reach relay `id` through a WebSocket bridge — `wss://host[:port]/path`,
+/// optionally domain-fronted via `front` (the name dialed and sent as SNI).
+/// Used once direct QUIC and the TLS fallback have both failed.
+#[uniffi::export]
+pub fn set_relay_bridge(id: String, url: String, front: Option<String>) -> Result<(), CoreError> {
+    Relay::set_bridge(&id, &url, front).map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// Forget relay `id`'s bridge.
+#[uniffi::export]
+pub fn remove_relay_bridge(id: String) -> Result<(), CoreError> {
+    Relay::remove_bridge(&id).map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// The cached resolver-signed relay directory as opaque bytes, for handing
+/// to a contact whose resolver is unreachable. `None` before the first
+/// successful resolve.
+#[uniffi::export]
+pub fn export_relay_directory() -> Result<Option<Vec<u8>>, CoreError> {
+    let Some(directory) = Relay::cached_directory().map_err(anyhow::Error::from)? else {
+        return Ok(None);
+    };
+    Ok(Some(directory.ser().map_err(anyhow::Error::from)?))
+}
+
+/// Adopt a directory exported by [`export_relay_directory`] on another
+/// device. Refused unless a bundled resolver key signed it and it is still
+/// valid; returns the number of relays learned (`0` if it wasn't newer than
+/// the cached one).
+#[uniffi::export]
+pub fn import_relay_directory(directory: Vec<u8>) -> Result<u32, CoreError> {
+    let seeds = crate::RESOLVER_SEEDS
+        .get()
+        .ok_or_else(|| CoreError::Internal { msg: "resolver seeds not set".into() })?;
+    let directory = RelayDirectory::deser(&directory).map_err(anyhow::Error::from)?;
+    let learned = Relay::import_directory(&directory, seeds).map_err(anyhow::Error::from)?;
+    Ok(learned as u32)
+}
+
 fn db_err(e: rusqlite::Error) -> CoreError {
     CoreError::Internal { msg: e.to_string() }
 }
@@ -0,0 +1,138 @@
+//! Sticker packs: FFI entry points for making, installing, browsing and
+//! sending stickers, and for telling a chat we're picking one.
+
+use common::proto::client_rel::ACTIVITY_CHOOSING_STICKER;
+
+use crate::api::messaging::to_conv16;
+use crate::platform::CoreError;
+
+#[derive(uniffi::Record)]
+pub struct StickerPackRecord {
+    pub pack_id: Vec<u8>,
+    pub title: String,
+    /// IPK of the pack's author, whose signature it carries.
+    pub author: Vec<u8>,
+    pub count: u32,
+}
+
+/// One sticker as the platform decoded it. libcore encodes it, as with an
+/// image.
+#[derive(uniffi::Record)]
+pub struct StickerFrame {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn to_pack16(bytes: &[u8]) -> Result<[u8; 16], CoreError> {
+    bytes.try_into().map_err(|_| CoreError::Internal { msg: "pack id must be 16 bytes".into() })
+}
+
+/// Encode `frames` to AVIF, sign them as a pack under our identity and install
+/// it. Returns the new pack's id. Blocks for the encodes.
+#[uniffi::export]
+pub fn create_sticker_pack(title: String, frames: Vec<StickerFrame>) -> Result<Vec<u8>, CoreError> {
+    let frames = frames
+        .iter()
+        .map(|f| crate::media::sticker_frame(&f.rgba, f.width, f.height))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(crate::stickers::create(&title, frames)?.to_vec())
+}
+
+/// Install the pack file at `path` (shared in from elsewhere) once its
+/// author's signature checks out. Returns its id.
+#[uniffi::export]
+pub fn install_sticker_pack(path: String) -> Result<Vec<u8>, CoreError> {
+    let bytes = std::fs::read(&path).map_err(|e| anyhow::anyhow!("read {path}: {e}"))?;
+    Ok(crate::stickers::install(&bytes)?.to_vec())
+}
+
+#[uniffi::export]
+pub fn remove_sticker_pack(pack_id: Vec<u8>) -> Result<(), CoreError> {
+    Ok(crate::stickers::remove(&to_pack16(&pack_id)?)?)
+}
+
+/// Installed packs, most recent first — the picker's tabs.
+#[uniffi::export]
+pub fn list_sticker_packs() -> Result<Vec<StickerPackRecord>, CoreError> {
+    Ok(crate::data::sticker::all()?
+        .into_iter()
+        .map(|p| StickerPackRecord {
+            pack_id: p.pack_id.to_vec(),
+            title: p.title,
+            author: p.author.to_vec(),
+            count: p.count,
+        })
+        .collect())
+}
+
+/// Sticker `id` of `pack_id` as AVIF. `None` while the pack is still being
+/// fetched from whoever sent it; the bubble shows a placeholder and asks again
+/// on the next `sticker_packs` change.
+#[uniffi::export]
+pub fn get_sticker(pack_id: Vec<u8>, id: u32) -> Result<Option<Vec<u8>>, CoreError> {
+    Ok(crate::stickers::frame(&to_pack16(&pack_id)?, id)?)
+}
+
+/// Send sticker `id` from an installed pack. Fire-and-forget like
+/// [`crate::api::media::send_image`]; the `Result` reports only a pack or
+/// index we can't send from.
+#[uniffi::export]
+pub fn send_sticker(conversation_id: Vec<u8>, pack_id: Vec<u8>, id: u32) -> Result<(), CoreError> {
+    let to = to_conv16(&conversation_id)?;
+    let pack = to_pack16(&pack_id)?;
+    let row = crate::data::sticker::get(&pack)
+        .ok_or_else(|| anyhow::anyhow!("sticker pack not installed"))?;
+    if id >= row.count {
+        return Err(anyhow::anyhow!("sticker {id} is past the end of its pack").into());
+    }
+    let msg = crate::messaging::build_sticker_message(to, pack, id)?;
+    let payload_bytes = crate::messaging::rebuild_pending_payload(&to, &msg)?;
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::messaging::send_prepared(to, &msg, payload_bytes).await {
+            log::warn!("MEDIA: send_sticker failed: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Tell the chat we're browsing stickers or emoji (`choosing = true`), or
+/// that we stopped. The composer calls it as the picker opens and closes.
+#[uniffi::export]
+pub fn set_choosing_sticker(conversation_id: Vec<u8>, choosing: bool) -> Result<(), CoreError> {
+    let activity = if choosing { ACTIVITY_CHOOSING_STICKER } else { 0 };
+    crate::api::messaging::set_activity(conversation_id, activity)
+}
+
+#[cfg(test)]
+mod tests {
+    /// A sent sticker stores its pack and index, rebuilds as a `Sticker`
+    /// post, and lists on `get_media` for the bubble to draw.
+    #[test]
+    fn sticker_rebuilds_and_lists_with_its_pack() {
+        let dir = std::env::temp_dir().join("promtuz-send-sticker-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        use common::proto::mls_wire::AppPayload;
+        use common::proto::mls_wire::Body;
+        use common::proto::pack::Unpacker;
+
+        let conv = [0x0cu8; 16];
+        let pack = [0x7cu8; 16];
+        let msg = crate::messaging::build_sticker_message(conv, pack, 3).unwrap();
+        let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
+        let bytes = crate::messaging::rebuild_pending_payload(&conv, &msg).unwrap();
+        assert_eq!(
+            AppPayload::deser(&bytes).unwrap(),
+            AppPayload::Post { reply_to: None, body: Body::Sticker { pack, id: 3 } }
+        );
+        assert!(crate::data::sticker::is_referenced(&pack));
+
+        let records = crate::api::media::get_media(conv.to_vec()).unwrap();
+        let record = records.iter().find(|r| r.dispatch_id == did.to_vec()).expect("record found");
+        assert_eq!(record.kind, crate::data::media::KIND_STICKER);
+        assert_eq!((record.sticker_pack.as_deref(), record.sticker_id), (Some(&pack[..]), 3));
+        assert_eq!(super::get_sticker(pack.to_vec(), 3).unwrap(), None, "pack not here yet");
+    }
+}
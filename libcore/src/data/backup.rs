@@ -19,9 +19,12 @@ use sha2::Sha256;
 
 use crate::data::contact::Contact;
 use crate::data::conversation::Conversation;
+use crate::data::conversation::KIND_GROUP;
 use crate::data::identity::Identity;
 use crate::data::media::MediaBackupRow;
 use crate::data::message::Message;
+use crate::data::poll::PollBackupRow;
+use crate::data::poll::VoteRow;
 use crate::data::reaction::Reaction;
 use crate::db::messages::ConversationRow;
 use crate::db::messages::MemberRow;
@@ -40,7 +43,28 @@ const MAGIC: &[u8; 4] = b"PZBK";
 /// conversations it did not save, so a restore produced a full messages table
 /// that nothing could reach — every chat read as empty and the home list was
 /// blank. Media rows and read state travel with them.
-const VERSION: u8 = 3;
+///
+/// 4: media rows carry a voice note's length and waveform. Without them a
+/// restored voice note was a zero-length, flat bubble.
+///
+/// 5: media rows carry a sticker's pack and index. Without them a restored
+/// sticker was an empty bubble with nothing to fetch.
+///
+/// 6: polls and their votes. A restored poll was otherwise a bare question
+/// with nothing to vote on.
+///
+/// 7: `MessageRow` carries `forwarded`, which changes its serialized shape.
+///
+/// 8: the suite each group ran on. Founding a group again after a restore had
+/// only the members' KeyPackages to go on, which could settle a PQ group on
+/// the classical suite.
+const VERSION: u8 = 8;
+
+/// Oldest blob [`decode`] still reads. Every version since only added fields,
+/// so an older blob decodes through its own shape (see [`legacy`]) and the
+/// newer fields restore empty. v2 and older keyed rows on the peer's IPK and
+/// can't be read as anything meaningful.
+const OLDEST_VERSION: u8 = 3;
 
 #[derive(Serialize, Deserialize)]
 struct BackupPayload {
@@ -57,6 +81,10 @@ struct BackupPayload {
     /// Pictures and attachment stubs. The inline image bytes live here, so this
     /// is what makes a restored photo a photo rather than an empty caption.
     media:         Vec<MediaBackupRow>,
+    /// Options and rules for the questions among `messages`, and each
+    /// member's current vote — the tally is counted from these.
+    polls:         Vec<PollBackupRow>,
+    poll_votes:    Vec<VoteRow>,
     /// How far we had read, and how far each member had. Purely cosmetic, but
     /// losing it makes every chat in a restored app scream unread.
     read_state:    Vec<ReadRow>,
@@ -64,6 +92,17 @@ struct BackupPayload {
     /// App-wide settings. They used to sit in platform preferences, which the
     /// backup rules do not ship, so a reinstall reset them every time.
     prefs:         Vec<(String, String)>,
+    /// The MLS suite behind each group, so a restore gets back in on the same
+    /// one (see [`crate::rejoin`]).
+    suites:        Vec<SuiteRow>,
+}
+
+/// The ciphersuite a group conversation's MLS group runs on, as its wire value.
+#[derive(Serialize, Deserialize)]
+pub struct SuiteRow {
+    #[serde(with = "serde_bytes")]
+    pub conversation_id: [u8; 16],
+    pub suite:           u16,
 }
 
 /// Our own read watermark for a conversation.
@@ -94,7 +133,12 @@ fn backup_key(isk: &[u8; 32]) -> [u8; 32] {
 
 fn encode(key: &[u8; 32], payload: &BackupPayload) -> Result<Vec<u8>> {
     let plain = postcard::to_allocvec(payload).map_err(|e| anyhow!("encode payload: {e}"))?;
-    let compressed = lz4_flex::compress_prepend_size(&plain);
+    seal(key, VERSION, &plain)
+}
+
+/// Frame and encrypt an already-serialized payload of `version`'s shape.
+fn seal(key: &[u8; 32], version: u8, plain: &[u8]) -> Result<Vec<u8>> {
+    let compressed = lz4_flex::compress_prepend_size(plain);
 
     let mut nonce = [0u8; 24];
     {
@@ -108,16 +152,18 @@ fn encode(key: &[u8; 32], payload: &BackupPayload) -> Result<Vec<u8>> {
 
     let mut out = Vec::with_capacity(4 + 1 + 24 + ct.len());
     out.extend_from_slice(MAGIC);
-    out.push(VERSION);
+    out.push(version);
     out.extend_from_slice(&nonce);
     out.extend_from_slice(&ct);
     Ok(out)
 }
 
-fn decode(key: &[u8; 32], blob: &[u8]) -> Result<BackupPayload> {
+/// Open a blob of any version from [`OLDEST_VERSION`] on, as the current
+/// payload shape. Returns the version it was written at alongside.
+fn decode(key: &[u8; 32], blob: &[u8]) -> Result<(u8, BackupPayload)> {
     let rest = blob.strip_prefix(MAGIC.as_slice()).ok_or_else(|| anyhow!("not a backup blob"))?;
     let (&version, rest) = rest.split_first().ok_or_else(|| anyhow!("truncated blob"))?;
-    if version != VERSION {
+    if !(OLDEST_VERSION..=VERSION).contains(&version) {
         return Err(anyhow!("unsupported backup version {version}"));
     }
     if rest.len() < 24 {
@@ -130,7 +176,237 @@ fn decode(key: &[u8; 32], blob: &[u8]) -> Result<BackupPayload> {
         .map_err(|_| anyhow!("decrypt failed — wrong identity or corrupted blob"))?;
     let plain = lz4_flex::decompress_size_prepended(&compressed)
         .map_err(|e| anyhow!("decompress: {e}"))?;
-    postcard::from_bytes(&plain).map_err(|e| anyhow!("decode payload: {e}"))
+    let payload = match version {
+        VERSION => postcard::from_bytes(&plain),
+        7 => postcard::from_bytes::<legacy::PayloadV7>(&plain).map(Into::into),
+        6 => postcard::from_bytes::<legacy::PayloadV6>(&plain).map(Into::into),
+        5 => postcard::from_bytes::<legacy::PayloadV3<MediaBackupRow>>(&plain).map(Into::into),
+        4 => postcard::from_bytes::<legacy::PayloadV3<legacy::MediaV4>>(&plain).map(Into::into),
+        _ => postcard::from_bytes::<legacy::PayloadV3<legacy::MediaV3>>(&plain).map(Into::into),
+    };
+    Ok((version, payload.map_err(|e| anyhow!("decode v{version} payload: {e}"))?))
+}
+
+/// The shapes older blobs were written in, each lifted into the current one.
+mod legacy {
+    use serde::Deserialize;
+
+    use super::BackupPayload;
+    use super::MemberReadRow;
+    use super::ReadRow;
+    use crate::data::media::MediaBackupRow;
+    use crate::data::poll::PollBackupRow;
+    use crate::data::poll::VoteRow;
+    use crate::db::messages::ConversationRow;
+    use crate::db::messages::MemberRow;
+    use crate::db::messages::MessageRow;
+    use crate::db::messages::ReactionRow;
+    use crate::db::peers::ContactRow;
+    use crate::db::utils::ulid::ULID;
+
+    /// v3–v6 message: no `forwarded`.
+    #[derive(Deserialize)]
+    pub(super) struct MessageV6 {
+        id:              ULID,
+        #[serde(with = "serde_bytes")]
+        conversation_id: [u8; 16],
+        sender_ipk:      Option<Vec<u8>>,
+        content:         String,
+        outgoing:        bool,
+        timestamp:       u64,
+        status:          u8,
+        dispatch_id:     Option<Vec<u8>>,
+        edited:          bool,
+        deleted:         bool,
+        reply_to:        Option<Vec<u8>>,
+        system:          u8,
+    }
+
+    impl From<MessageV6> for MessageRow {
+        fn from(m: MessageV6) -> Self {
+            Self {
+                id:              m.id,
+                conversation_id: m.conversation_id,
+                sender_ipk:      m.sender_ipk,
+                content:         m.content,
+                outgoing:        m.outgoing,
+                timestamp:       m.timestamp,
+                status:          m.status,
+                dispatch_id:     m.dispatch_id,
+                edited:          m.edited,
+                deleted:         m.deleted,
+                reply_to:        m.reply_to,
+                system:          m.system,
+                forwarded:       false,
+            }
+        }
+    }
+
+    /// v3 media row: no voice length or waveform, no sticker.
+    #[derive(Deserialize)]
+    pub(super) struct MediaV3 {
+        #[serde(with = "serde_bytes")]
+        conversation_id: [u8; 16],
+        #[serde(with = "serde_bytes")]
+        dispatch_id:     [u8; 16],
+        kind:            u8,
+        group_id:        Option<Vec<u8>>,
+        mime:            String,
+        name:            String,
+        size:            u64,
+        width:           u32,
+        height:          u32,
+        blob:            Option<Vec<u8>>,
+        thumb:           Option<Vec<u8>>,
+        file_id:         Option<Vec<u8>>,
+    }
+
+    /// v4 media row: a voice note's length and waveform, no sticker yet.
+    #[derive(Deserialize)]
+    pub(super) struct MediaV4 {
+        base:        MediaV3,
+        duration_ms: u32,
+        waveform:    Option<Vec<u8>>,
+    }
+
+    impl From<MediaV3> for MediaBackupRow {
+        fn from(m: MediaV3) -> Self {
+            MediaV4 { base: m, duration_ms: 0, waveform: None }.into()
+        }
+    }
+
+    impl From<MediaV4> for MediaBackupRow {
+        fn from(m: MediaV4) -> Self {
+            let b = m.base;
+            Self {
+                conversation_id: b.conversation_id,
+                dispatch_id:     b.dispatch_id,
+                kind:            b.kind,
+                group_id:        b.group_id,
+                mime:            b.mime,
+                name:            b.name,
+                size:            b.size,
+                width:           b.width,
+                height:          b.height,
+                blob:            b.blob,
+                thumb:           b.thumb,
+                file_id:         b.file_id,
+                duration_ms:     m.duration_ms,
+                waveform:        m.waveform,
+                sticker_pack:    None,
+                sticker_id:      0,
+            }
+        }
+    }
+
+    /// v3–v5 payload: no polls, and media rows of shape `D`.
+    #[derive(Deserialize)]
+    pub(super) struct PayloadV3<D> {
+        name:          String,
+        contacts:      Vec<ContactRow>,
+        conversations: Vec<ConversationRow>,
+        members:       Vec<MemberRow>,
+        messages:      Vec<MessageV6>,
+        reactions:     Vec<ReactionRow>,
+        media:         Vec<D>,
+        read_state:    Vec<ReadRow>,
+        member_read:   Vec<MemberReadRow>,
+        prefs:         Vec<(String, String)>,
+    }
+
+    impl<D: Into<MediaBackupRow>> From<PayloadV3<D>> for BackupPayload {
+        fn from(p: PayloadV3<D>) -> Self {
+            Self {
+                name:          p.name,
+                contacts:      p.contacts,
+                conversations: p.conversations,
+                members:       p.members,
+                messages:      p.messages.into_iter().map(Into::into).collect(),
+                reactions:     p.reactions,
+                media:         p.media.into_iter().map(Into::into).collect(),
+                polls:         Vec::new(),
+                poll_votes:    Vec::new(),
+                read_state:    p.read_state,
+                member_read:   p.member_read,
+                prefs:         p.prefs,
+                suites:        Vec::new(),
+            }
+        }
+    }
+
+    /// v6 payload: polls, but messages without `forwarded`.
+    #[derive(Deserialize)]
+    pub(super) struct PayloadV6 {
+        name:          String,
+        contacts:      Vec<ContactRow>,
+        conversations: Vec<ConversationRow>,
+        members:       Vec<MemberRow>,
+        messages:      Vec<MessageV6>,
+        reactions:     Vec<ReactionRow>,
+        media:         Vec<MediaBackupRow>,
+        polls:         Vec<PollBackupRow>,
+        poll_votes:    Vec<VoteRow>,
+        read_state:    Vec<ReadRow>,
+        member_read:   Vec<MemberReadRow>,
+        prefs:         Vec<(String, String)>,
+    }
+
+    impl From<PayloadV6> for BackupPayload {
+        fn from(p: PayloadV6) -> Self {
+            Self {
+                name:          p.name,
+                contacts:      p.contacts,
+                conversations: p.conversations,
+                members:       p.members,
+                messages:      p.messages.into_iter().map(Into::into).collect(),
+                reactions:     p.reactions,
+                media:         p.media,
+                polls:         p.polls,
+                poll_votes:    p.poll_votes,
+                read_state:    p.read_state,
+                member_read:   p.member_read,
+                prefs:         p.prefs,
+                suites:        Vec::new(),
+            }
+        }
+    }
+
+    /// v7 payload: everything but the groups' suites.
+    #[derive(Deserialize)]
+    pub(super) struct PayloadV7 {
+        name:          String,
+        contacts:      Vec<ContactRow>,
+        conversations: Vec<ConversationRow>,
+        members:       Vec<MemberRow>,
+        messages:      Vec<MessageRow>,
+        reactions:     Vec<ReactionRow>,
+        media:         Vec<MediaBackupRow>,
+        polls:         Vec<PollBackupRow>,
+        poll_votes:    Vec<VoteRow>,
+        read_state:    Vec<ReadRow>,
+        member_read:   Vec<MemberReadRow>,
+        prefs:         Vec<(String, String)>,
+    }
+
+    impl From<PayloadV7> for BackupPayload {
+        fn from(p: PayloadV7) -> Self {
+            Self {
+                name:          p.name,
+                contacts:      p.contacts,
+                conversations: p.conversations,
+                members:       p.members,
+                messages:      p.messages,
+                reactions:     p.reactions,
+                media:         p.media,
+                polls:         p.polls,
+                poll_votes:    p.poll_votes,
+                read_state:    p.read_state,
+                member_read:   p.member_read,
+                prefs:         p.prefs,
+                suites:        Vec::new(),
+            }
+        }
+    }
 }
 
 /// Snapshot everything restorable into one encrypted blob. The platform
@@ -140,6 +416,17 @@ pub fn export() -> Result<Vec<u8>> {
     let identity = Identity::get().ok_or_else(|| anyhow!("no identity"))?;
     let (conversations, members) = Conversation::dump_all();
     let (read_state, member_read) = crate::data::message::dump_read_state();
+    let (polls, poll_votes) = crate::data::poll::dump_all();
+    let provider = crate::mls::PromtuzMlsProvider::shared();
+    let suites = conversations
+        .iter()
+        .filter(|c| c.kind == KIND_GROUP)
+        .filter_map(|c| {
+            let group: [u8; 32] = c.mls_group_id.as_deref()?.try_into().ok()?;
+            let suite = crate::rejoin::suite_of(&provider, &group)?;
+            Some(SuiteRow { conversation_id: c.id, suite })
+        })
+        .collect();
     let payload = BackupPayload {
         name: identity.name(),
         contacts: Contact::list(),
@@ -148,9 +435,12 @@ pub fn export() -> Result<Vec<u8>> {
         messages: Message::dump_all(),
         reactions: Reaction::dump_all(),
         media: crate::data::media::dump_all(),
+        polls,
+        poll_votes,
         read_state,
         member_read,
         prefs: crate::data::app_prefs::dump_all(),
+        suites,
     };
     let secret = Identity::secret_key_with_manager()?;
     encode(&backup_key(&secret), &payload)
@@ -160,7 +450,7 @@ pub fn export() -> Result<Vec<u8>> {
 /// restored (the key derives from the isk). Idempotent — upserts throughout.
 pub fn import(blob: &[u8]) -> Result<()> {
     let secret = Identity::secret_key_with_manager()?;
-    let payload = decode(&backup_key(&secret), blob)?;
+    let (_, payload) = decode(&backup_key(&secret), blob)?;
 
     let contacts = Contact::import_rows(&payload.contacts)?;
     // Conversations first: everything below hangs off them.
@@ -168,13 +458,17 @@ pub fn import(blob: &[u8]) -> Result<()> {
     let messages = Message::import_rows(&payload.messages)?;
     let reactions = Reaction::import_rows(&payload.reactions)?;
     let media = crate::data::media::import_rows(&payload.media)?;
+    let (polls, votes) = crate::data::poll::import_rows(&payload.polls, &payload.poll_votes)?;
     crate::data::message::import_read_state(&payload.read_state, &payload.member_read)?;
     crate::data::app_prefs::import_rows(&payload.prefs)?;
     Identity::set_name(&payload.name)?;
+    // The blob carries no group secrets: every chat has to be let back in.
+    crate::rejoin::after_restore(&payload.suites)?;
 
     log::info!(
         "BACKUP: imported {contacts} contacts, {conversations} conversations, \
-         {messages} messages, {reactions} reactions, {media} media"
+         {messages} messages, {reactions} reactions, {media} media, {polls} polls, \
+         {votes} votes"
     );
     Ok(())
 }
@@ -201,6 +495,10 @@ pub struct MergeReport {
     pub conversations_added:   u32,
     pub media_in_blob:         u32,
     pub media_added:           u32,
+    pub polls_in_blob:         u32,
+    pub polls_added:           u32,
+    pub votes_in_blob:         u32,
+    pub votes_added:           u32,
 }
 
 /// Additive restore for the Backup & Restore dev screen: every row the blob
@@ -215,7 +513,7 @@ pub struct MergeReport {
 /// [`Identity::set_name`] — existing state always wins a collision.
 pub fn import_merge(blob: &[u8]) -> Result<MergeReport> {
     let secret = Identity::secret_key_with_manager()?;
-    let payload = decode(&backup_key(&secret), blob)?;
+    let (version, payload) = decode(&backup_key(&secret), blob)?;
 
     let contacts_added = Contact::merge_rows(&payload.contacts)?;
     let conversations_added =
@@ -223,10 +521,12 @@ pub fn import_merge(blob: &[u8]) -> Result<MergeReport> {
     let messages_added = Message::merge_rows(&payload.messages)?;
     let reactions_added = Reaction::merge_rows(&payload.reactions)?;
     let media_added = crate::data::media::import_rows(&payload.media)?;
+    let (polls_added, votes_added) =
+        crate::data::poll::import_rows(&payload.polls, &payload.poll_votes)?;
     crate::data::app_prefs::import_rows(&payload.prefs)?;
 
     let report = MergeReport {
-        version: VERSION,
+        version,
         blob_bytes: blob.len() as u64,
         backup_name: payload.name,
         current_name: Identity::get().map(|i| i.name()).unwrap_or_default(),
@@ -240,6 +540,10 @@ pub fn import_merge(blob: &[u8]) -> Result<MergeReport> {
         conversations_added: conversations_added as u32,
         media_in_blob: payload.media.len() as u32,
         media_added: media_added as u32,
+        polls_in_blob: payload.polls.len() as u32,
+        polls_added: polls_added as u32,
+        votes_in_blob: payload.poll_votes.len() as u32,
+        votes_added: votes_added as u32,
     };
     log::info!("BACKUP: merge {report:?}");
     Ok(report)
@@ -248,6 +552,7 @@ pub fn import_merge(blob: &[u8]) -> Result<MergeReport> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::utils::ulid::ULID;
 
     fn payload() -> BackupPayload {
         BackupPayload {
@@ -265,9 +570,12 @@ mod tests {
             messages:      Vec::new(),
             reactions:     Vec::new(),
             media:         Vec::new(),
+            polls:         Vec::new(),
+            poll_votes:    Vec::new(),
             read_state:    Vec::new(),
             member_read:   Vec::new(),
             prefs:         Vec::new(),
+            suites:        Vec::new(),
         }
     }
 
@@ -275,7 +583,8 @@ mod tests {
     fn blob_roundtrips() {
         let key = backup_key(&[7u8; 32]);
         let blob = encode(&key, &payload()).unwrap();
-        let back = decode(&key, &blob).unwrap();
+        let (version, back) = decode(&key, &blob).unwrap();
+        assert_eq!(version, VERSION);
         assert_eq!(back.name, "bhuv");
         assert_eq!(back.contacts.len(), 1);
         assert_eq!(back.contacts[0].mls_group_id, Some([9u8; 32]));
@@ -309,7 +618,7 @@ mod tests {
         }];
 
         let key = backup_key(&[7u8; 32]);
-        let back = decode(&key, &encode(&key, &p).unwrap()).unwrap();
+        let (_, back) = decode(&key, &encode(&key, &p).unwrap()).unwrap();
 
         assert_eq!(back.conversations.len(), 1, "the chat itself must survive the round trip");
         assert_eq!(back.conversations[0].id, conv);
@@ -318,6 +627,106 @@ mod tests {
         assert_eq!(back.members[0].conversation_id, conv);
     }
 
+    /// A v3–v6 message as it went on the wire: everything up to `system`.
+    type MessageV6<'a> = (
+        ULID, &'a serde_bytes::Bytes, Option<Vec<u8>>, &'a str, bool, u64, u8,
+        Option<Vec<u8>>, bool, bool, Option<Vec<u8>>, u8,
+    );
+
+    fn message_v6(conv: &[u8; 16]) -> MessageV6<'_> {
+        let id = ULID::from(ulid::Ulid::from_parts(1_000, 7));
+        (id, serde_bytes::Bytes::new(conv), None, "hi", true, 1_000, 1, None, false, false, None, 0)
+    }
+
+    /// A v3 blob predates voice, stickers, polls and forwarding. It still
+    /// restores, with each of those reading empty.
+    #[test]
+    fn a_v3_blob_restores_with_the_newer_fields_empty() {
+        let conv = [0xC3u8; 16];
+        let media = (
+            serde_bytes::Bytes::new(&conv), serde_bytes::Bytes::new(&[0xD1u8; 16]), 1u8,
+            None::<Vec<u8>>, "image/jpeg", "cat.jpg", 10u64, 4u32, 3u32, Some(vec![1u8, 2]),
+            None::<Vec<u8>>, None::<Vec<u8>>,
+        );
+        let plain = postcard::to_allocvec(&(
+            "bhuv", Vec::<ContactRow>::new(), Vec::<ConversationRow>::new(),
+            Vec::<MemberRow>::new(), vec![message_v6(&conv)], Vec::<ReactionRow>::new(),
+            vec![media], Vec::<ReadRow>::new(), Vec::<MemberReadRow>::new(),
+            Vec::<(String, String)>::new(),
+        ))
+        .unwrap();
+
+        let key = backup_key(&[7u8; 32]);
+        let (version, back) = decode(&key, &seal(&key, 3, &plain).unwrap()).unwrap();
+
+        assert_eq!(version, 3);
+        assert_eq!(back.messages.len(), 1);
+        assert_eq!(back.messages[0].content, "hi");
+        assert!(!back.messages[0].forwarded);
+        assert_eq!(back.media.len(), 1);
+        assert_eq!(back.media[0].name, "cat.jpg");
+        assert_eq!(back.media[0].blob, Some(vec![1, 2]));
+        assert_eq!(back.media[0].duration_ms, 0);
+        assert_eq!(back.media[0].sticker_pack, None);
+        assert!(back.polls.is_empty());
+    }
+
+    /// v6 carried polls but not the forwarded flag.
+    #[test]
+    fn a_v6_blob_keeps_its_votes() {
+        let conv = [0xC6u8; 16];
+        let vote = VoteRow {
+            conversation_id: conv,
+            dispatch_id:     [0xD6; 16],
+            voter:           [3; 32],
+            choices:         0b10,
+            voted_at:        9,
+        };
+        let plain = postcard::to_allocvec(&(
+            "bhuv", Vec::<ContactRow>::new(), Vec::<ConversationRow>::new(),
+            Vec::<MemberRow>::new(), vec![message_v6(&conv)], Vec::<ReactionRow>::new(),
+            Vec::<MediaBackupRow>::new(), Vec::<PollBackupRow>::new(), vec![vote],
+            Vec::<ReadRow>::new(), Vec::<MemberReadRow>::new(), Vec::<(String, String)>::new(),
+        ))
+        .unwrap();
+
+        let key = backup_key(&[7u8; 32]);
+        let (version, back) = decode(&key, &seal(&key, 6, &plain).unwrap()).unwrap();
+
+        assert_eq!(version, 6);
+        assert!(!back.messages[0].forwarded);
+        assert_eq!(back.poll_votes.len(), 1);
+        assert_eq!(back.poll_votes[0].choices, 0b10);
+    }
+
+    /// v7 carried everything but the groups' suites, which restore unknown.
+    #[test]
+    fn a_v7_blob_restores_without_suites() {
+        let mut p = payload();
+        p.suites = vec![SuiteRow { conversation_id: [0xC7; 16], suite: 0x4D }];
+        let current = postcard::to_allocvec(&p).unwrap();
+        let suites_len = postcard::to_allocvec(&p.suites).unwrap().len();
+        // The suites list is the last field, so v7 is everything before it.
+        let plain = &current[..current.len() - suites_len];
+
+        let key = backup_key(&[7u8; 32]);
+        let (version, back) = decode(&key, &seal(&key, 7, plain).unwrap()).unwrap();
+        assert_eq!(version, 7);
+        assert_eq!(back.name, "bhuv");
+        assert!(back.suites.is_empty());
+
+        let (_, back) = decode(&key, &encode(&key, &p).unwrap()).unwrap();
+        assert_eq!(back.suites[0].suite, 0x4D, "v8 carries them");
+    }
+
+    #[test]
+    fn pre_conversation_blobs_are_refused() {
+        let key = backup_key(&[7u8; 32]);
+        let plain = postcard::to_allocvec(&payload()).unwrap();
+        assert!(decode(&key, &seal(&key, OLDEST_VERSION - 1, &plain).unwrap()).is_err());
+        assert!(decode(&key, &seal(&key, VERSION + 1, &plain).unwrap()).is_err());
+    }
+
     #[test]
     fn tampered_blob_fails_auth() {
         let key = backup_key(&[7u8; 32]);
@@ -1,13 +1,19 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
 use anyhow::anyhow;
 use common::crypto::PublicKey;
 use common::crypto::SecretKey;
 use common::crypto::get_signing_key;
 use common::crypto::sign::derive_p2p_tls_key;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::dht_p2p::identity_succession_signing_input;
 use common::proto::mls_wire::Invite;
 use common::proto::mls_wire::MLS_WIRE_VERSION;
 use common::proto::mls_wire::WELCOME_LIFETIME_MS;
 use common::proto::mls_wire::invite_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
 use ed25519_dalek::Signature;
 use ed25519_dalek::Signer;
 use ed25519_dalek::SigningKey;
@@ -164,6 +170,145 @@ impl Identity {
         Ok(())
     }
 
+    /// Retire the current key for a freshly generated one. Both keys sign the
+    /// returned succession, which is kept with the retired key until
+    /// [`Self::mark_announced`]; so is its sealed-sender secret, so dispatches
+    /// already on their way to it still open. Entry point for
+    /// `crate::succession::rotate`, which moves the rest of our state and then
+    /// [`Self::mark_moved`].
+    pub fn rotate() -> Result<IdentitySuccession> {
+        let store = SECURE_STORE.get().ok_or(anyhow!("API is not initialized"))?;
+        let old = SigningKey::from_bytes(&*Identity::secret_key_with_manager()?);
+        let old_seal = IdentitySigner::push_seal_secret()?;
+        let new = get_signing_key();
+        let (old_ipk, new_ipk) = (old.verifying_key().to_bytes(), new.verifying_key().to_bytes());
+
+        let timestamp = systime().as_millis() as u64;
+        let input = identity_succession_signing_input(&old_ipk, &new_ipk, timestamp);
+        let succession = IdentitySuccession {
+            old_ipk: old_ipk.into(),
+            new_ipk: new_ipk.into(),
+            timestamp,
+            old_sig: old.sign(&input).to_bytes().into(),
+            new_sig: new.sign(&input).to_bytes().into(),
+        };
+        let blob = succession.ser().map_err(|e| anyhow!("encode succession: {e}"))?;
+        let enc_isk =
+            store.seal(new.as_bytes().to_vec()).map_err(|e| anyhow!("seal failed: {e}"))?;
+        let enc_seal =
+            store.seal(old_seal.to_bytes().to_vec()).map_err(|e| anyhow!("seal failed: {e}"))?;
+
+        let mut conn = IDENTITY_DB.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO predecessor (ipk, succession, enc_seal, retired_at) \
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![&old_ipk[..], blob, enc_seal, timestamp],
+        )?;
+        let updated = tx.execute(
+            "UPDATE identity SET ipk = ?1, enc_isk = ?2 WHERE id = 0 AND ipk = ?3",
+            rusqlite::params![&new_ipk[..], enc_isk, &old_ipk[..]],
+        )?;
+        if updated != 1 {
+            return Err(anyhow!("identity changed while rotating"));
+        }
+        tx.commit()?;
+        Ok(succession)
+    }
+
+    /// Every key this identity has rotated away from, oldest first.
+    pub fn predecessors() -> Vec<[u8; 32]> {
+        let conn = IDENTITY_DB.lock();
+        let Ok(mut stmt) = conn.prepare("SELECT ipk FROM predecessor ORDER BY retired_at ASC")
+        else {
+            return Vec::new();
+        };
+        stmt.query_map([], |r| r.get::<_, [u8; 32]>(0))
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every succession this identity made, oldest first: the chain from its
+    /// first key to the current one.
+    pub fn successions() -> Vec<IdentitySuccession> {
+        let conn = IDENTITY_DB.lock();
+        let Ok(mut stmt) =
+            conn.prepare("SELECT succession FROM predecessor ORDER BY retired_at ASC")
+        else {
+            return Vec::new();
+        };
+        stmt.query_map([], |r| r.get::<_, Vec<u8>>(0))
+            .map(|rows| rows.flatten().filter_map(|b| IdentitySuccession::deser(&b).ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Successions not yet settled: some contact still to be told, or our
+    /// recovery shares not yet split for the new key. Each comes with whether
+    /// the shares are.
+    pub fn unsettled_successions() -> Vec<(IdentitySuccession, bool)> {
+        let conn = IDENTITY_DB.lock();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT succession, reshared_at IS NOT NULL FROM predecessor \
+             WHERE announced_at IS NULL OR reshared_at IS NULL ORDER BY retired_at ASC",
+        ) else {
+            return Vec::new();
+        };
+        stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get::<_, bool>(1)?)))
+            .map(|rows| {
+                rows.flatten()
+                    .filter_map(|(b, done)| Some((IdentitySuccession::deser(&b).ok()?, done)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Rotations whose local move didn't finish, oldest first.
+    pub fn unmoved_successions() -> Vec<IdentitySuccession> {
+        let conn = IDENTITY_DB.lock();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT succession FROM predecessor WHERE moved_at IS NULL ORDER BY retired_at ASC",
+        ) else {
+            return Vec::new();
+        };
+        stmt.query_map([], |r| r.get::<_, Vec<u8>>(0))
+            .map(|rows| rows.flatten().filter_map(|b| IdentitySuccession::deser(&b).ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Everything we held under `old_ipk` now stands under its successor.
+    pub fn mark_moved(old_ipk: &[u8; 32]) {
+        Self::stamp_predecessor("moved_at", old_ipk);
+    }
+
+    /// Stop re-sending the succession that retired `old_ipk`.
+    pub fn mark_announced(old_ipk: &[u8; 32]) {
+        Self::stamp_predecessor("announced_at", old_ipk);
+    }
+
+    /// Our recovery shares were split anew after `old_ipk` retired.
+    pub fn mark_reshared(old_ipk: &[u8; 32]) {
+        Self::stamp_predecessor("reshared_at", old_ipk);
+    }
+
+    fn stamp_predecessor(column: &str, old_ipk: &[u8; 32]) {
+        let conn = IDENTITY_DB.lock();
+        let _ = conn.execute(
+            &format!("UPDATE predecessor SET {column} = ?1 WHERE ipk = ?2"),
+            rusqlite::params![systime().as_millis() as u64, &old_ipk[..]],
+        );
+    }
+
+    /// Who already has the succession that retired `old_ipk`.
+    pub fn told(old_ipk: &[u8; 32]) -> HashSet<[u8; 32]> {
+        told_in(&IDENTITY_DB.lock(), old_ipk)
+    }
+
+    /// `peer` took the succession that retired `old_ipk`; don't send it again.
+    pub fn mark_told(old_ipk: &[u8; 32], peer: &[u8; 32]) {
+        let conn = IDENTITY_DB.lock();
+        mark_told_in(&conn, old_ipk, peer, systime().as_millis() as u64);
+    }
+
     /// Mint a bearer pairing invite valid for ~10 minutes. Signed by our
     /// long-term IPK; whoever holds it may add us until it expires. Used
     /// by `api::identity::make_invite_qr`.
@@ -264,6 +409,11 @@ impl Identity {
             .map_err(Into::into)
         })
     }
+
+    /// Drop the in-memory copy of the isk, once the identity row is gone.
+    pub(crate) fn forget_cached_secret() {
+        *ISK_CACHE.write() = None;
+    }
 }
 
 #[derive(Debug)]
@@ -299,6 +449,59 @@ impl IdentitySigner {
         Ok(derive_p2p_tls_key(&secret, public.as_bytes()))
     }
 
+    /// Derive the X25519 secret wake hints are sealed to
+    /// (`crate::push::open_wake_hint`): HKDF-SHA256 over the identity secret
+    /// with the IPK as salt. Deterministic, so a push handler running in a
+    /// freshly spawned process re-derives the key it published.
+    pub fn push_seal_secret() -> Result<x25519_dalek::StaticSecret> {
+        use hkdf::Hkdf;
+        use sha2::Sha256;
+
+        let secret = Identity::secret_key_with_manager()?;
+        let public = SigningKey::from_bytes(&secret).verifying_key();
+        let hk = Hkdf::<Sha256>::new(Some(public.as_bytes()), &secret[..]);
+        let mut okm = Zeroizing::new([0u8; 32]);
+        hk.expand(b"promtuz-push-seal-key-v1", okm.as_mut())
+            .map_err(|_| anyhow!("push seal key expand"))?;
+        Ok(x25519_dalek::StaticSecret::from(*okm))
+    }
+
+    /// Derive the X25519 secret a recovery share is sealed back to
+    /// (`crate::social_recovery`), the same way as [`Self::push_seal_secret`]
+    /// under its own label so the two never share a key.
+    pub fn recovery_seal_secret() -> Result<x25519_dalek::StaticSecret> {
+        use hkdf::Hkdf;
+        use sha2::Sha256;
+
+        let secret = Identity::secret_key_with_manager()?;
+        let public = SigningKey::from_bytes(&secret).verifying_key();
+        let hk = Hkdf::<Sha256>::new(Some(public.as_bytes()), &secret[..]);
+        let mut okm = Zeroizing::new([0u8; 32]);
+        hk.expand(b"promtuz-recovery-seal-key-v1", okm.as_mut())
+            .map_err(|_| anyhow!("recovery seal key expand"))?;
+        Ok(x25519_dalek::StaticSecret::from(*okm))
+    }
+
+    /// The sealed-sender secret of `ipk`, which is either the current key or
+    /// one it succeeded. Dispatches sealed to a retired key before its
+    /// contacts heard of the rotation open with the secret kept at
+    /// [`Identity::rotate`].
+    pub fn seal_secret_for(ipk: &[u8; 32]) -> Result<x25519_dalek::StaticSecret> {
+        if Identity::public_key()?.to_bytes() == *ipk {
+            return Self::push_seal_secret();
+        }
+        let store = SECURE_STORE.get().ok_or(anyhow!("API is not initialized"))?;
+        let enc_seal: Vec<u8> = IDENTITY_DB.lock().query_one(
+            "SELECT enc_seal FROM predecessor WHERE ipk = ?1",
+            [&ipk[..]],
+            |r| r.get(0),
+        )?;
+        let secret = Zeroizing::new(store.open(enc_seal).map_err(|e| anyhow!("open failed: {e}"))?);
+        let secret: [u8; 32] =
+            secret.as_slice().try_into().map_err(|_| anyhow!("bad sealed secret length"))?;
+        Ok(x25519_dalek::StaticSecret::from(secret))
+    }
+
     /// Sign a message with the long-term identity key, returning both the
     /// signature and the long-term IPK pubkey.
     ///
@@ -341,6 +544,22 @@ pub(crate) fn secret_key_signing(expected_ipk: &[u8; 32]) -> Result<SigningKey>
 /// Normalize + validate a user-chosen nickname (NFC, trimmed, ≤32 chars,
 /// no control/zero-width characters). Returns the cleaned name or a
 /// user-facing error message.
+fn told_in(conn: &rusqlite::Connection, old_ipk: &[u8; 32]) -> HashSet<[u8; 32]> {
+    let Ok(mut stmt) = conn.prepare("SELECT peer FROM succession_told WHERE old_ipk = ?1") else {
+        return HashSet::new();
+    };
+    stmt.query_map([&old_ipk[..]], |r| r.get::<_, [u8; 32]>(0))
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default()
+}
+
+fn mark_told_in(conn: &rusqlite::Connection, old_ipk: &[u8; 32], peer: &[u8; 32], now: u64) {
+    let _ = conn.execute(
+        "INSERT OR IGNORE INTO succession_told (old_ipk, peer, told_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![&old_ipk[..], &peer[..], now],
+    );
+}
+
 fn validate_nickname(name: &str) -> std::result::Result<String, String> {
     let normalized: String = name.nfc().collect();
     let trimmed = normalized.trim();
@@ -405,3 +624,19 @@ mod cache_tests {
         assert_eq!(&b[..], &[7u8; 32][..], "must serve the new identity's secret");
     }
 }
+
+#[cfg(test)]
+mod succession_tests {
+    use super::*;
+
+    #[test]
+    fn each_contact_is_told_once() {
+        let conn = crate::db::identity::open_in_memory();
+        mark_told_in(&conn, &[1; 32], &[2; 32], 10);
+        mark_told_in(&conn, &[1; 32], &[2; 32], 20);
+        mark_told_in(&conn, &[1; 32], &[3; 32], 20);
+        mark_told_in(&conn, &[4; 32], &[5; 32], 20);
+        assert_eq!(told_in(&conn, &[1; 32]), HashSet::from([[2; 32], [3; 32]]));
+        assert!(told_in(&conn, &[9; 32]).is_empty());
+    }
+}
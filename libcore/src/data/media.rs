@@ -1,12 +1,20 @@
-//! Per-message media metadata (Image inline bytes / Attachment thumb + file_id),
-//! keyed by (conversation_id, dispatch_id). The caption itself lives on
-//! messages.content.
+//! Per-message media metadata (Image inline bytes / Attachment thumb + file_id /
+//! Voice duration + waveform / Video poster + dims + duration / Sticker pack +
+//! index), keyed by
+//! (conversation_id, dispatch_id). The caption itself lives on messages.content.
 use anyhow::Result;
 use rusqlite::OptionalExtension;
 use crate::db::messages::MESSAGES_DB;
 
 pub const KIND_IMAGE: u8 = 1;
 pub const KIND_ATTACHMENT: u8 = 2;
+/// Inline bytes in `blob`, or pulled by `file_id` when too big for the frame.
+pub const KIND_VOICE: u8 = 3;
+/// Poster in `thumb`, dimensions and duration alongside; bytes by `file_id`.
+pub const KIND_VIDEO: u8 = 4;
+/// No bytes of its own: `sticker_pack` + `sticker_id` name a frame of an
+/// installed pack (see [`crate::data::sticker`]).
+pub const KIND_STICKER: u8 = 5;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MediaRow {
@@ -20,6 +28,14 @@ pub struct MediaRow {
     pub blob: Option<Vec<u8>>,
     pub thumb: Option<Vec<u8>>,
     pub file_id: Option<Vec<u8>>,
+    /// Playback length; 0 for stills and documents.
+    pub duration_ms: u32,
+    /// Peak amplitude per bar, for a voice note's bubble.
+    pub waveform: Option<Vec<u8>>,
+    /// The 16-byte pack id, for a sticker.
+    pub sticker_pack: Option<Vec<u8>>,
+    /// Index into the pack's frames, for a sticker.
+    pub sticker_id: u32,
 }
 
 pub fn save(conv: &[u8; 16], dispatch_id: &[u8; 16], r: &MediaRow) -> Result<()> {
@@ -34,10 +50,12 @@ pub fn save_tx(
 ) -> Result<()> {
     conn.execute(
         "INSERT OR REPLACE INTO message_media
-         (conversation_id,dispatch_id,kind,group_id,mime,name,size,width,height,blob,thumb,file_id)
-         VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12)",
+         (conversation_id,dispatch_id,kind,group_id,mime,name,size,width,height,blob,thumb,file_id,
+          duration_ms,waveform,sticker_pack,sticker_id)
+         VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16)",
         rusqlite::params![conv.as_slice(), dispatch_id.as_slice(), r.kind, r.group_id,
-            r.mime, r.name, r.size, r.width, r.height, r.blob, r.thumb, r.file_id],
+            r.mime, r.name, r.size, r.width, r.height, r.blob, r.thumb, r.file_id,
+            r.duration_ms, r.waveform, r.sticker_pack, r.sticker_id],
     )?;
     Ok(())
 }
@@ -177,34 +195,57 @@ pub fn discard_outgoing(conv: &[u8; 16], dispatch_id: &[u8; 16]) -> Result<()> {
 pub fn get(conv: &[u8; 16], dispatch_id: &[u8; 16]) -> Result<Option<MediaRow>> {
     let db = MESSAGES_DB.lock();
     db.query_row(
-        "SELECT kind,group_id,mime,name,size,width,height,blob,thumb,file_id
+        "SELECT kind,group_id,mime,name,size,width,height,blob,thumb,file_id,duration_ms,waveform,
+                sticker_pack,sticker_id
          FROM message_media WHERE conversation_id=?1 AND dispatch_id=?2",
         rusqlite::params![conv.as_slice(), dispatch_id.as_slice()],
         |row| Ok(MediaRow {
             kind: row.get(0)?, group_id: row.get(1)?, mime: row.get(2)?, name: row.get(3)?,
             size: row.get(4)?, width: row.get(5)?, height: row.get(6)?,
             blob: row.get(7)?, thumb: row.get(8)?, file_id: row.get(9)?,
+            duration_ms: row.get(10)?, waveform: row.get(11)?,
+            sticker_pack: row.get(12)?, sticker_id: row.get(13)?,
         }),
     )
     .optional()
     .map_err(Into::into)
 }
 
-/// The member to dial for an incoming attachment and the size they advertised
-/// in the offer — the pull rejects a manifest whose `total_size` belies it.
-/// Read off `messages.sender_ipk`, so in a group the file is pulled from
-/// whoever actually sent it rather than from the conversation at large.
+/// Who offered an incoming attachment, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentOffer {
+    pub sender:       [u8; 32],
+    /// The size advertised in the offer — the pull rejects a manifest whose
+    /// `total_size` belies it.
+    pub size:         u64,
+    /// Where it was posted: the members who may also serve it, and where a
+    /// completed download is announced.
+    pub conversation: [u8; 16],
+    /// The media row's kind; a video is pulled front-first for playback.
+    pub kind:         u8,
+}
+
+/// The member to dial for an incoming attachment, read off
+/// `messages.sender_ipk`, so in a group the file is pulled from whoever
+/// actually sent it rather than from the conversation at large.
 /// Restricted to the INCOMING row (`m.outgoing = 0`): if we both received and
 /// re-sent the same content-addressed file, the outgoing row names our own
 /// recipient (who serves `Gone`), not the sender we must pull from.
-pub fn attachment_offer(file_id: &[u8; 32]) -> Result<Option<([u8; 32], u64)>> {
+pub fn attachment_offer(file_id: &[u8; 32]) -> Result<Option<AttachmentOffer>> {
     let db = MESSAGES_DB.lock();
     db.query_row(
-        "SELECT m.sender_ipk, mm.size FROM message_media mm
+        "SELECT m.sender_ipk, mm.size, mm.conversation_id, mm.kind FROM message_media mm
            JOIN messages m ON m.conversation_id = mm.conversation_id AND m.dispatch_id = mm.dispatch_id
          WHERE mm.file_id = ?1 AND m.outgoing = 0 AND m.sender_ipk IS NOT NULL LIMIT 1",
         [file_id.as_slice()],
-        |row| Ok((row.get(0)?, row.get(1)?)),
+        |row| {
+            Ok(AttachmentOffer {
+                sender:       row.get(0)?,
+                size:         row.get(1)?,
+                conversation: row.get(2)?,
+                kind:         row.get(3)?,
+            })
+        },
     )
     .optional()
     .map_err(Into::into)
@@ -213,7 +254,8 @@ pub fn attachment_offer(file_id: &[u8; 32]) -> Result<Option<([u8; 32], u64)>> {
 pub fn for_conversation(conv: &[u8; 16]) -> Result<Vec<([u8; 16], MediaRow)>> {
     let db = MESSAGES_DB.lock();
     let mut stmt = db.prepare(
-        "SELECT dispatch_id,kind,group_id,mime,name,size,width,height,blob,thumb,file_id
+        "SELECT dispatch_id,kind,group_id,mime,name,size,width,height,blob,thumb,file_id,
+                duration_ms,waveform,sticker_pack,sticker_id
          FROM message_media WHERE conversation_id=?1")?;
     let rows = stmt.query_map([conv.as_slice()], |row| {
         let did: Vec<u8> = row.get(0)?;
@@ -222,6 +264,8 @@ pub fn for_conversation(conv: &[u8; 16]) -> Result<Vec<([u8; 16], MediaRow)>> {
             kind: row.get(1)?, group_id: row.get(2)?, mime: row.get(3)?, name: row.get(4)?,
             size: row.get(5)?, width: row.get(6)?, height: row.get(7)?,
             blob: row.get(8)?, thumb: row.get(9)?, file_id: row.get(10)?,
+            duration_ms: row.get(11)?, waveform: row.get(12)?,
+            sticker_pack: row.get(13)?, sticker_id: row.get(14)?,
         }))
     })?.collect::<std::result::Result<Vec<_>, _>>()?;
     Ok(rows)
@@ -247,7 +291,8 @@ mod tests {
         let conv = [3u8; 16]; let did = [4u8; 16];
         let row = MediaRow { kind: KIND_IMAGE, group_id: Some(vec![1u8;16]),
             mime: "image/avif".into(), name: "".into(), size: 3, width: 4, height: 3,
-            blob: Some(vec![9,9,9]), thumb: None, file_id: None };
+            blob: Some(vec![9,9,9]), thumb: None, file_id: None,
+            duration_ms: 0, waveform: None, sticker_pack: None, sticker_id: 0 };
         save(&conv, &did, &row).unwrap();
         let got = for_conversation(&conv).unwrap();
         assert!(got.iter().any(|(d, r)| *d == did && r.blob == row.blob && r.kind == KIND_IMAGE));
@@ -265,7 +310,8 @@ mod tests {
         let did = [0x22u8; 16];
         let row = MediaRow { kind: KIND_IMAGE, group_id: None, mime: "image/avif".into(),
             name: "".into(), size: 0, width: 4, height: 3,
-            blob: None, thumb: None, file_id: None };
+            blob: None, thumb: None, file_id: None,
+            duration_ms: 0, waveform: None, sticker_pack: None, sticker_id: 0 };
         save(&conv, &did, &row).unwrap();
         assert!(get(&conv, &did).unwrap().unwrap().blob.is_none());
 
@@ -287,7 +333,8 @@ mod tests {
         let conv = [0x23u8; 16];
         let row = MediaRow { kind: KIND_ATTACHMENT, group_id: None,
             mime: "application/pdf".into(), name: "a.pdf".into(), size: 9,
-            width: 0, height: 0, blob: None, thumb: None, file_id: None };
+            width: 0, height: 0, blob: None, thumb: None, file_id: None,
+            duration_ms: 0, waveform: None, sticker_pack: None, sticker_id: 0 };
         let msg = save_outgoing_with_media(&conv, "cap", None, &row).unwrap();
         let did: [u8; 16] = msg.inner.dispatch_id.clone().unwrap().try_into().unwrap();
         assert!(get(&conv, &did).unwrap().is_some());
@@ -319,7 +366,8 @@ mod tests {
         let did = [6u8; 16];
         let media = MediaRow { kind: KIND_IMAGE, group_id: None, mime: "image/avif".into(),
             name: String::new(), size: 3, width: 1, height: 1,
-            blob: Some(vec![1, 2, 3]), thumb: None, file_id: None };
+            blob: Some(vec![1, 2, 3]), thumb: None, file_id: None,
+            duration_ms: 0, waveform: None, sticker_pack: None, sticker_id: 0 };
         {
             let tx = conn.transaction().unwrap();
             assert!(Message::save_incoming_tx(&tx, conv, SENDER, &did, "cap", 100, None).unwrap().is_some());
@@ -362,7 +410,8 @@ mod tests {
         let conv = [8u8; 16];
         let media = MediaRow { kind: KIND_IMAGE, group_id: None, mime: "image/avif".into(),
             name: String::new(), size: 3, width: 4, height: 3,
-            blob: Some(vec![1, 2, 3]), thumb: None, file_id: None };
+            blob: Some(vec![1, 2, 3]), thumb: None, file_id: None,
+            duration_ms: 0, waveform: None, sticker_pack: None, sticker_id: 0 };
 
         // Happy path: caption + media land in one committed transaction.
         let did: [u8; 16] = {
@@ -415,6 +464,12 @@ pub struct MediaBackupRow {
     pub blob: Option<Vec<u8>>,
     pub thumb: Option<Vec<u8>>,
     pub file_id: Option<Vec<u8>>,
+    pub duration_ms: u32,
+    pub waveform: Option<Vec<u8>>,
+    /// A sticker names its pack; a restored one is fetched again like any
+    /// sticker from a pack we lack.
+    pub sticker_pack: Option<Vec<u8>>,
+    pub sticker_id: u32,
 }
 
 pub fn dump_all() -> Vec<MediaBackupRow> {
@@ -436,6 +491,10 @@ pub fn dump_all() -> Vec<MediaBackupRow> {
             blob: r.get("blob")?,
             thumb: r.get("thumb")?,
             file_id: r.get("file_id")?,
+            duration_ms: r.get("duration_ms")?,
+            waveform: r.get("waveform")?,
+            sticker_pack: r.get("sticker_pack")?,
+            sticker_id: r.get("sticker_id")?,
         })
     })
     .map(|rows| rows.flatten().collect())
@@ -451,8 +510,9 @@ pub fn import_rows(rows: &[MediaBackupRow]) -> Result<usize> {
     for r in rows {
         n += tx.execute(
             "INSERT OR IGNORE INTO message_media \
-             (conversation_id, dispatch_id, kind, group_id, mime, name, size, width, height, blob, thumb, file_id) \
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             (conversation_id, dispatch_id, kind, group_id, mime, name, size, width, height, blob, thumb, file_id, \
+              duration_ms, waveform, sticker_pack, sticker_id) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
             rusqlite::params![
                 r.conversation_id.as_slice(),
                 r.dispatch_id.as_slice(),
@@ -466,6 +526,10 @@ pub fn import_rows(rows: &[MediaBackupRow]) -> Result<usize> {
                 r.blob.as_deref(),
                 r.thumb.as_deref(),
                 r.file_id.as_deref(),
+                r.duration_ms,
+                r.waveform.as_deref(),
+                r.sticker_pack.as_deref(),
+                r.sticker_id,
             ],
         )?;
     }
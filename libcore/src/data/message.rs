@@ -90,6 +90,7 @@ impl Message {
                 deleted: false,
                 reply_to: reply_to.map(|r| r.to_vec()),
                 system: crate::db::messages::SYSTEM_NONE,
+                forwarded: false,
             },
         })
     }
@@ -143,6 +144,7 @@ impl Message {
                 deleted: false,
                 reply_to: reply_to.map(|r| r.to_vec()),
                 system: crate::db::messages::SYSTEM_NONE,
+                forwarded: false,
             },
         }))
     }
@@ -160,6 +162,21 @@ impl Message {
         .map(|inner| Self { inner })
     }
 
+    /// Any row for (conversation, dispatch_id), ours or a member's — what a
+    /// forward copies from.
+    pub fn get_any_by_dispatch(
+        conversation_id: &[u8; 16], dispatch_id: &[u8; 16],
+    ) -> Option<Self> {
+        let conn = MESSAGES_DB.lock();
+        conn.query_row(
+            "SELECT * FROM messages WHERE conversation_id = ?1 AND dispatch_id = ?2",
+            (conversation_id.as_slice(), dispatch_id.as_slice()),
+            MessageRow::from_row,
+        )
+        .ok()
+        .map(|inner| Self { inner })
+    }
+
     /// Mark an outgoing message as sent (relay accepted).
     pub fn mark_sent(id: &Ulid, timestamp: u64) {
         let conn = MESSAGES_DB.lock();
@@ -411,6 +428,7 @@ impl Message {
                 deleted: false,
                 reply_to: None,
                 system,
+                forwarded: false,
             },
         }))
     }
@@ -436,8 +454,8 @@ impl Message {
         for r in rows {
             n += tx.execute(
                 "INSERT OR IGNORE INTO messages \
-                 (id, conversation_id, sender_ipk, content, outgoing, timestamp, status, dispatch_id, edited, deleted, reply_to, system) \
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                 (id, conversation_id, sender_ipk, content, outgoing, timestamp, status, dispatch_id, edited, deleted, reply_to, system, forwarded) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
                 (
                     &r.id,
                     r.conversation_id.as_slice(),
@@ -451,6 +469,7 @@ impl Message {
                     r.deleted,
                     &r.reply_to,
                     r.system,
+                    r.forwarded,
                 ),
             )?;
         }
@@ -465,6 +484,75 @@ impl Message {
         Self::import_rows(rows)
     }
 
+    /// Save a message forwarded from another chat in ONE transaction: the row,
+    /// its media or poll side-row, and the `forwarded` flag. A crash between
+    /// them would otherwise leave a forward that reads as our own words.
+    pub fn save_forwarded(
+        conversation_id: [u8; 16], content: &str, media: Option<&crate::data::media::MediaRow>,
+        poll: Option<&crate::data::poll::Poll>,
+    ) -> Result<Self> {
+        let mut db = MESSAGES_DB.lock();
+        let tx = db.transaction()?;
+        let mut msg = Self::save_outgoing_tx(&tx, conversation_id, content, None)?;
+        let did: [u8; 16] = msg
+            .inner
+            .dispatch_id
+            .as_deref()
+            .expect("save_outgoing mints a dispatch_id")
+            .try_into()
+            .expect("dispatch_id is 16 bytes");
+        if let Some(r) = media {
+            crate::data::media::save_tx(&tx, &conversation_id, &did, r)?;
+        }
+        if let Some(p) = poll {
+            crate::data::poll::save_tx(&tx, &conversation_id, &did, p)?;
+        }
+        tx.execute(
+            "UPDATE messages SET forwarded = 1 WHERE conversation_id = ?1 AND dispatch_id = ?2",
+            (conversation_id.as_slice(), did.as_slice()),
+        )?;
+        tx.commit()?;
+        msg.inner.forwarded = true;
+        Ok(msg)
+    }
+
+    /// Inbound mirror of [`Self::save_forwarded`]: the received row, its side
+    /// row and the flag land together, with the same `Ok(None)`-on-duplicate
+    /// contract as [`Self::save_incoming`]. A forward never quotes.
+    pub fn save_incoming_forwarded(
+        conversation_id: [u8; 16], sender: [u8; 32], dispatch_id: &[u8; 16], content: &str,
+        timestamp: u64, media: Option<&crate::data::media::MediaRow>,
+        poll: Option<&crate::data::poll::Poll>,
+    ) -> Result<Option<Self>> {
+        let mut db = MESSAGES_DB.lock();
+        let tx = db.transaction()?;
+        let Some(mut msg) = Self::save_incoming_tx(
+            &tx,
+            conversation_id,
+            sender,
+            dispatch_id,
+            content,
+            timestamp,
+            None,
+        )?
+        else {
+            return Ok(None);
+        };
+        if let Some(r) = media {
+            crate::data::media::save_tx(&tx, &conversation_id, dispatch_id, r)?;
+        }
+        if let Some(p) = poll {
+            crate::data::poll::save_tx(&tx, &conversation_id, dispatch_id, p)?;
+        }
+        tx.execute(
+            "UPDATE messages SET forwarded = 1 WHERE conversation_id = ?1 AND dispatch_id = ?2",
+            (conversation_id.as_slice(), dispatch_id.as_slice()),
+        )?;
+        tx.commit()?;
+        msg.inner.forwarded = true;
+        Ok(Some(msg))
+    }
+
     /// Delete every message in a conversation (forget-contact / leave cascade).
     pub fn delete_in(conversation_id: &[u8; 16]) {
         let conn = MESSAGES_DB.lock();
@@ -7,10 +7,15 @@ pub mod media;
 pub mod message;
 pub mod app_prefs;
 pub mod peer_name;
+pub mod poll;
 pub mod reaction;
 pub mod recovery;
+pub mod rejoin;
 pub mod relay;
 pub mod seen;
+pub mod sticker;
+pub mod succession;
+pub mod unsupported;
 
 use std::str::FromStr;
 
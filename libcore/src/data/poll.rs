@@ -0,0 +1,601 @@
+//! Polls — a side-table next to `messages`, keyed like a media row.
+//!
+//! The question is the message's own content, so previews and the home list
+//! read it like any text; the options and rules sit here. Votes are one row
+//! per member per poll, replaced whole by that member's next vote —
+//! last-writer-wins on `voted_at`, the relay's acceptance time, as reactions
+//! take theirs. A withdrawn vote keeps its row with no choices, so an older
+//! ballot that arrives late can't bring it back. Tallies are counted from
+//! these rows on read; nobody sends one.
+//!
+//! A vote can overtake its poll — the relay makes no promise about order
+//! across senders. It waits in `poll_votes_early` until the poll lands and is
+//! then held to it like any other ballot.
+
+use anyhow::Result;
+use anyhow::bail;
+use common::proto::mls_wire::Body;
+use common::proto::mls_wire::MAX_POLL_OPTIONS;
+use rusqlite::OptionalExtension;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::db::messages::MESSAGES_DB;
+
+/// Votes held per conversation for polls we haven't seen. Enough for a big
+/// group voting before a slow poll lands; a member spraying ballots at
+/// made-up polls fills it and no more.
+const MAX_EARLY_VOTES: u32 = 512;
+
+/// How long a vote waits for its poll: a poll later than this isn't coming.
+const EARLY_VOTE_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// A blob column that didn't decode, as rusqlite reports one.
+fn corrupt(col: usize, e: impl std::error::Error + Send + Sync + 'static) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(col, rusqlite::types::Type::Blob, Box::new(e))
+}
+
+/// A fixed-size key column, refusing a row whose bytes are the wrong length.
+fn key<const N: usize>(row: &rusqlite::Row, col: &str) -> rusqlite::Result<[u8; N]> {
+    let bytes: Vec<u8> = row.get(col)?;
+    let idx = row.as_ref().column_index(col)?;
+    bytes.try_into().map_err(|b: Vec<u8>| {
+        rusqlite::Error::FromSqlConversionFailure(
+            idx,
+            rusqlite::types::Type::Blob,
+            format!("{col} is {} bytes, not {N}", b.len()).into(),
+        )
+    })
+}
+
+/// One poll as stored. Keyless, like `MediaRow`: live callers already know
+/// the message they are asking about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Poll {
+    pub question:  String,
+    pub options:   Vec<String>,
+    /// More than one option may be chosen.
+    pub multi:     bool,
+    /// Clients don't show who chose what.
+    pub anonymous: bool,
+    /// Unix seconds after which votes are refused, as the author set it.
+    pub closes_at: Option<u64>,
+    /// When the author closed it early.
+    pub closed_at: Option<u64>,
+}
+
+impl Poll {
+    /// The poll a [`Body::Poll`] describes; `None` for any other body.
+    pub fn from_body(body: &Body) -> Option<Self> {
+        match body {
+            Body::Poll { question, options, multi, anonymous, closes_at } => Some(Self {
+                question:  question.clone(),
+                options:   options.clone(),
+                multi:     *multi,
+                anonymous: *anonymous,
+                closes_at: *closes_at,
+                closed_at: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Back to the wire body, for a resend. An early close travels on its
+    /// own, so it isn't part of the body.
+    pub fn body(&self) -> Body {
+        Body::Poll {
+            question:  self.question.clone(),
+            options:   self.options.clone(),
+            multi:     self.multi,
+            anonymous: self.anonymous,
+            closes_at: self.closes_at,
+        }
+    }
+
+    /// Still taking votes cast at `at` (unix seconds).
+    pub fn is_open_at(&self, at: u64) -> bool {
+        self.closes_at.is_none_or(|c| at <= c) && self.closed_at.is_none_or(|c| at <= c)
+    }
+
+    /// A poll worth sending: a question and two to [`MAX_POLL_OPTIONS`]
+    /// distinct, non-blank options.
+    pub fn check(&self) -> Result<()> {
+        if self.question.trim().is_empty() {
+            bail!("a poll needs a question");
+        }
+        if self.options.len() < 2 || self.options.len() > MAX_POLL_OPTIONS {
+            bail!("a poll takes 2 to {MAX_POLL_OPTIONS} options");
+        }
+        if self.options.iter().any(|o| o.trim().is_empty()) {
+            bail!("poll options can't be blank");
+        }
+        let mut seen = std::collections::HashSet::new();
+        if !self.options.iter().all(|o| seen.insert(o.trim())) {
+            bail!("poll options must differ");
+        }
+        Ok(())
+    }
+
+    /// `choices` as a stored bitmask, refusing what this poll can't take: an
+    /// index past its options, the same option twice, or several on a
+    /// single-choice poll.
+    fn mask(&self, choices: &[u32]) -> Result<u32> {
+        if !self.multi && choices.len() > 1 {
+            bail!("single-choice poll");
+        }
+        let mut mask = 0u32;
+        for &c in choices {
+            if c as usize >= self.options.len() {
+                bail!("no option {c}");
+            }
+            if mask & (1 << c) != 0 {
+                bail!("option {c} chosen twice");
+            }
+            mask |= 1 << c;
+        }
+        Ok(mask)
+    }
+
+    /// A row whose options don't decode is an error, not a poll with none.
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let options: Vec<u8> = row.get("options")?;
+        let options = postcard::from_bytes(&options)
+            .map_err(|e| corrupt(row.as_ref().column_index("options").unwrap_or(0), e))?;
+        Ok(Self {
+            question:  row.get("question")?,
+            options,
+            multi:     row.get("multi")?,
+            anonymous: row.get("anonymous")?,
+            closes_at: row.get("closes_at")?,
+            closed_at: row.get("closed_at")?,
+        })
+    }
+}
+
+/// One member's current vote. `choices` is a bitmask over the poll's options;
+/// zero means they withdrew it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoteRow {
+    #[serde(with = "serde_bytes")]
+    pub conversation_id: [u8; 16],
+    #[serde(with = "serde_bytes")]
+    pub dispatch_id:     [u8; 16],
+    #[serde(with = "serde_bytes")]
+    pub voter:           [u8; 32],
+    pub choices:         u32,
+    pub voted_at:        u64,
+}
+
+impl VoteRow {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            conversation_id: key(row, "conversation_id")?,
+            dispatch_id:     key(row, "dispatch_id")?,
+            voter:           key(row, "voter")?,
+            choices:         row.get("choices")?,
+            voted_at:        row.get("voted_at")?,
+        })
+    }
+
+    /// The chosen option indices, ascending.
+    pub fn choices(&self) -> Vec<u32> {
+        (0..32).filter(|i| self.choices & (1 << i) != 0).collect()
+    }
+}
+
+/// Votes per option, in option order.
+pub fn tally(poll: &Poll, votes: &[VoteRow]) -> Vec<u32> {
+    (0..poll.options.len())
+        .map(|i| votes.iter().filter(|v| v.choices & (1 << i) != 0).count() as u32)
+        .collect()
+}
+
+/// Transaction-scoped insert, so a poll shares one transaction with its
+/// question's message row — the same all-or-nothing as a media message.
+pub(crate) fn save_tx(
+    conn: &rusqlite::Connection, conv: &[u8; 16], dispatch_id: &[u8; 16], p: &Poll,
+) -> Result<()> {
+    let options = postcard::to_allocvec(&p.options)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO polls \
+         (conversation_id, dispatch_id, question, options, multi, anonymous, closes_at, closed_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![conv.as_slice(), dispatch_id.as_slice(), p.question, options, p.multi,
+            p.anonymous, p.closes_at, p.closed_at],
+    )?;
+    Ok(())
+}
+
+/// Persist an incoming poll: the question as the message, the rest here, in
+/// ONE transaction. `None` on a redelivered dispatch_id, exactly as
+/// [`crate::data::media::save_incoming_with_media`].
+pub fn save_incoming_with_poll(
+    conv: &[u8; 16], sender: &[u8; 32], dispatch_id: &[u8; 16], timestamp: u64,
+    reply_to: Option<[u8; 16]>, p: &Poll,
+) -> Result<Option<crate::data::message::Message>> {
+    let mut db = MESSAGES_DB.lock();
+    let tx = db.transaction()?;
+    let saved = crate::data::message::Message::save_incoming_tx(
+        &tx, *conv, *sender, dispatch_id, &p.question, timestamp, reply_to,
+    )?;
+    if saved.is_some() {
+        save_tx(&tx, conv, dispatch_id, p)?;
+    }
+    tx.commit()?;
+    drop(db);
+    if saved.is_some() {
+        replay_early(conv, dispatch_id);
+    }
+    Ok(saved)
+}
+
+/// Send-side mirror of [`save_incoming_with_poll`]; the poll keys off the
+/// freshly-minted dispatch_id.
+pub fn save_outgoing_with_poll(
+    conv: &[u8; 16], reply_to: Option<[u8; 16]>, p: &Poll,
+) -> Result<crate::data::message::Message> {
+    let mut db = MESSAGES_DB.lock();
+    let tx = db.transaction()?;
+    let msg = crate::data::message::Message::save_outgoing_tx(&tx, *conv, &p.question, reply_to)?;
+    let did: [u8; 16] = msg
+        .inner
+        .dispatch_id
+        .as_deref()
+        .expect("save_outgoing mints a dispatch_id")
+        .try_into()
+        .expect("dispatch_id is 16 bytes");
+    save_tx(&tx, conv, &did, p)?;
+    tx.commit()?;
+    Ok(msg)
+}
+
+pub fn get(conv: &[u8; 16], dispatch_id: &[u8; 16]) -> Option<Poll> {
+    MESSAGES_DB
+        .lock()
+        .query_row(
+            "SELECT * FROM polls WHERE conversation_id = ?1 AND dispatch_id = ?2",
+            [conv.as_slice(), dispatch_id.as_slice()],
+            Poll::from_row,
+        )
+        .optional()
+        .unwrap_or_else(|e| {
+            log::warn!("POLL: unreadable poll {}: {e}", hex::encode(dispatch_id));
+            None
+        })
+}
+
+/// Every poll in a conversation with its dispatch_id.
+pub fn for_conversation(conv: &[u8; 16]) -> Vec<([u8; 16], Poll)> {
+    let conn = MESSAGES_DB.lock();
+    let Ok(mut stmt) = conn.prepare("SELECT * FROM polls WHERE conversation_id = ?1") else {
+        return Vec::new();
+    };
+    stmt.query_map([conv.as_slice()], |r| Ok((key(r, "dispatch_id")?, Poll::from_row(r)?)))
+    .map(|rows| rows.flatten().collect())
+    .unwrap_or_default()
+}
+
+/// Every vote in a conversation. The UI groups by `dispatch_id`.
+pub fn votes_for(conv: &[u8; 16]) -> Vec<VoteRow> {
+    let conn = MESSAGES_DB.lock();
+    let Ok(mut stmt) = conn.prepare("SELECT * FROM poll_votes WHERE conversation_id = ?1") else {
+        return Vec::new();
+    };
+    stmt.query_map([conv.as_slice()], VoteRow::from_row)
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default()
+}
+
+/// Record `voter`'s ballot on a poll, replacing their previous one unless that
+/// one is newer. Only `voted_at` decides: a re-vote for the same options still
+/// moves it forward, so an older, different ballot arriving late stays out.
+/// Refused when the poll is closed at `at` or can't take these choices. A
+/// vote for a poll we don't hold yet waits for it. `Ok(true)` when the stored
+/// vote was replaced.
+pub fn apply_vote(
+    conv: &[u8; 16], dispatch_id: &[u8; 16], voter: &[u8; 32], choices: &[u32], at: u64,
+) -> Result<bool> {
+    let Some(poll) = get(conv, dispatch_id) else {
+        hold_early(conv, dispatch_id, voter, choices, at)?;
+        return Ok(false);
+    };
+    if !poll.is_open_at(at) {
+        bail!("poll is closed");
+    }
+    let mask = poll.mask(choices)?;
+    let n = MESSAGES_DB.lock().execute(
+        "INSERT INTO poll_votes (conversation_id, dispatch_id, voter, choices, voted_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5) \
+         ON CONFLICT(conversation_id, dispatch_id, voter) DO UPDATE \
+         SET choices = excluded.choices, voted_at = excluded.voted_at \
+         WHERE excluded.voted_at >= poll_votes.voted_at",
+        rusqlite::params![conv.as_slice(), dispatch_id.as_slice(), voter.as_slice(), mask, at],
+    )?;
+    Ok(n > 0)
+}
+
+/// Park a vote for a poll we don't hold, newest per voter, as
+/// [`apply_vote`] would keep it. Refused once the conversation holds
+/// [`MAX_EARLY_VOTES`]; votes older than [`EARLY_VOTE_TTL_SECS`] go first.
+fn hold_early(
+    conv: &[u8; 16], dispatch_id: &[u8; 16], voter: &[u8; 32], choices: &[u32], at: u64,
+) -> Result<()> {
+    if choices.len() > MAX_POLL_OPTIONS {
+        bail!("no poll takes {} choices", choices.len());
+    }
+    let now = crate::utils::systime().as_secs();
+    let conn = MESSAGES_DB.lock();
+    conn.execute(
+        "DELETE FROM poll_votes_early WHERE voted_at < ?1",
+        [now.saturating_sub(EARLY_VOTE_TTL_SECS)],
+    )?;
+    let held: u32 = conn.query_row(
+        "SELECT COUNT(*) FROM poll_votes_early WHERE conversation_id = ?1",
+        [conv.as_slice()],
+        |r| r.get(0),
+    )?;
+    if held >= MAX_EARLY_VOTES {
+        bail!("too many votes waiting for their polls");
+    }
+    conn.execute(
+        "INSERT INTO poll_votes_early (conversation_id, dispatch_id, voter, choices, voted_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5) \
+         ON CONFLICT(conversation_id, dispatch_id, voter) DO UPDATE \
+         SET choices = excluded.choices, voted_at = excluded.voted_at \
+         WHERE excluded.voted_at >= poll_votes_early.voted_at",
+        rusqlite::params![conv.as_slice(), dispatch_id.as_slice(), voter.as_slice(),
+            postcard::to_allocvec(choices)?, at],
+    )?;
+    Ok(())
+}
+
+/// The poll `dispatch_id` has landed: apply what waited for it. A ballot it
+/// refuses is dropped, as it would have been on time.
+pub(crate) fn replay_early(conv: &[u8; 16], dispatch_id: &[u8; 16]) {
+    let waiting: Vec<([u8; 32], Vec<u8>, u64)> = {
+        let conn = MESSAGES_DB.lock();
+        let rows = conn
+            .prepare(
+                "DELETE FROM poll_votes_early WHERE conversation_id = ?1 AND dispatch_id = ?2 \
+                 RETURNING voter, choices, voted_at",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map([conv.as_slice(), dispatch_id.as_slice()], |r| {
+                    Ok((key(r, "voter")?, r.get("choices")?, r.get("voted_at")?))
+                })
+                .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            });
+        match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::warn!("POLL: early votes for {} unreadable: {e}", hex::encode(dispatch_id));
+                return;
+            },
+        }
+    };
+    for (voter, choices, at) in waiting {
+        let applied = postcard::from_bytes::<Vec<u32>>(&choices)
+            .map_err(Into::into)
+            .and_then(|choices| apply_vote(conv, dispatch_id, &voter, &choices, at));
+        if let Err(e) = applied {
+            log::debug!("POLL: early vote from {} refused: {e}", hex::encode(&voter[..4]));
+        }
+    }
+}
+
+/// Close a poll at `at`. Only its author may: `by = None` is us, closing one
+/// we sent; `Some(ipk)` a member closing one they sent us. `Ok(false)` when it
+/// is not theirs, unknown, or already closed.
+pub fn close(
+    conv: &[u8; 16], dispatch_id: &[u8; 16], by: Option<&[u8; 32]>, at: u64,
+) -> Result<bool> {
+    let n = MESSAGES_DB.lock().execute(
+        "UPDATE polls SET closed_at = ?3 \
+         WHERE conversation_id = ?1 AND dispatch_id = ?2 AND closed_at IS NULL \
+           AND EXISTS (SELECT 1 FROM messages m \
+                       WHERE m.conversation_id = ?1 AND m.dispatch_id = ?2 \
+                         AND ((?4 IS NULL AND m.outgoing = 1) \
+                              OR (m.outgoing = 0 AND m.sender_ipk = ?4)))",
+        rusqlite::params![conv.as_slice(), dispatch_id.as_slice(), at, by.map(|b| b.as_slice())],
+    )?;
+    Ok(n > 0)
+}
+
+/// One poll with the key it hangs off, for the backup snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollBackupRow {
+    #[serde(with = "serde_bytes")]
+    pub conversation_id: [u8; 16],
+    #[serde(with = "serde_bytes")]
+    pub dispatch_id:     [u8; 16],
+    pub poll:            Poll,
+}
+
+/// Every poll and every vote — the backup dump.
+pub fn dump_all() -> (Vec<PollBackupRow>, Vec<VoteRow>) {
+    let conn = MESSAGES_DB.lock();
+    let polls = conn
+        .prepare("SELECT * FROM polls")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |r| {
+                Ok(PollBackupRow {
+                    conversation_id: key(r, "conversation_id")?,
+                    dispatch_id:     key(r, "dispatch_id")?,
+                    poll:            Poll::from_row(r)?,
+                })
+            })
+            .map(|rows| rows.flatten().collect())
+        })
+        .unwrap_or_default();
+    let votes = conn
+        .prepare("SELECT * FROM poll_votes")
+        .and_then(|mut stmt| {
+            stmt.query_map([], VoteRow::from_row).map(|rows| rows.flatten().collect())
+        })
+        .unwrap_or_default();
+    (polls, votes)
+}
+
+/// Restore dumped polls and votes. `INSERT OR IGNORE` on both, so a poll we
+/// hold keeps its close and a member's live vote outranks the snapshot's.
+/// Returns `(polls, votes)` actually inserted.
+pub fn import_rows(polls: &[PollBackupRow], votes: &[VoteRow]) -> Result<(usize, usize)> {
+    let mut conn = MESSAGES_DB.lock();
+    let tx = conn.transaction()?;
+    let mut np = 0usize;
+    for r in polls {
+        let options = postcard::to_allocvec(&r.poll.options)?;
+        np += tx.execute(
+            "INSERT OR IGNORE INTO polls \
+             (conversation_id, dispatch_id, question, options, multi, anonymous, closes_at, \
+              closed_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![r.conversation_id.as_slice(), r.dispatch_id.as_slice(),
+                r.poll.question, options, r.poll.multi, r.poll.anonymous, r.poll.closes_at,
+                r.poll.closed_at],
+        )?;
+    }
+    let mut nv = 0usize;
+    for v in votes {
+        nv += tx.execute(
+            "INSERT OR IGNORE INTO poll_votes \
+             (conversation_id, dispatch_id, voter, choices, voted_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![v.conversation_id.as_slice(), v.dispatch_id.as_slice(),
+                v.voter.as_slice(), v.choices, v.voted_at],
+        )?;
+    }
+    tx.commit()?;
+    Ok((np, nv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(multi: bool) -> Poll {
+        Poll {
+            question: "lunch?".into(),
+            options: vec!["noodles".into(), "tacos".into(), "skip".into()],
+            multi,
+            anonymous: false,
+            closes_at: Some(100),
+            closed_at: None,
+        }
+    }
+
+    fn vote(voter: u8, choices: u32) -> VoteRow {
+        VoteRow {
+            conversation_id: [1u8; 16],
+            dispatch_id: [2u8; 16],
+            voter: [voter; 32],
+            choices,
+            voted_at: 0,
+        }
+    }
+
+    #[test]
+    fn ballots_are_held_to_the_poll() {
+        let single = poll(false);
+        assert_eq!(single.mask(&[1]).unwrap(), 0b010);
+        assert_eq!(single.mask(&[]).unwrap(), 0, "an empty ballot withdraws");
+        assert!(single.mask(&[0, 1]).is_err(), "one choice on a single-choice poll");
+        assert!(single.mask(&[3]).is_err(), "no such option");
+
+        let multi = poll(true);
+        assert_eq!(multi.mask(&[0, 2]).unwrap(), 0b101);
+        assert!(multi.mask(&[2, 2]).is_err(), "the same option twice is not two votes");
+    }
+
+    #[test]
+    fn a_poll_stops_at_its_deadline_or_its_close() {
+        let mut p = poll(false);
+        assert!(p.is_open_at(100));
+        assert!(!p.is_open_at(101));
+        p.closed_at = Some(50);
+        assert!(p.is_open_at(50));
+        assert!(!p.is_open_at(51));
+    }
+
+    #[test]
+    fn tally_counts_current_ballots_per_option() {
+        let votes = [vote(1, 0b001), vote(2, 0b011), vote(3, 0)];
+        assert_eq!(tally(&poll(true), &votes), vec![2, 1, 0]);
+        assert_eq!(votes[1].choices(), vec![0, 1]);
+    }
+
+    /// A poll from `[0x4a; 32]` saved as `did` in `conv`, open forever.
+    fn receive(conv: [u8; 16], did: [u8; 16]) {
+        let mut p = poll(false);
+        p.closes_at = None;
+        save_incoming_with_poll(&conv, &[0x4au8; 32], &did, 1, None, &p).unwrap();
+    }
+
+    fn current(conv: [u8; 16], voter: [u8; 32]) -> Option<(u32, u64)> {
+        votes_for(&conv).into_iter().find(|v| v.voter == voter).map(|v| (v.choices, v.voted_at))
+    }
+
+    /// Last-writer-wins per member on `voted_at` alone: a newer ballot
+    /// replaces, an older late one is ignored, a same-choice re-vote still
+    /// moves the clock so an older different ballot can't slip past it, and a
+    /// withdrawal holds its place.
+    #[test]
+    fn a_late_older_ballot_never_overwrites_a_newer_one() {
+        let dir = std::env::temp_dir().join("promtuz-poll-votes-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        // Fresh ids: the data dir, and so every earlier run's ballots, persist.
+        let (conv, did, voter) = (rand::random(), rand::random(), [0x43u8; 32]);
+        receive(conv, did);
+
+        assert!(apply_vote(&conv, &did, &voter, &[0], 10).unwrap());
+        assert!(apply_vote(&conv, &did, &voter, &[1], 20).unwrap(), "a newer ballot replaces");
+        assert!(!apply_vote(&conv, &did, &voter, &[0], 15).unwrap(), "an older one is ignored");
+        assert_eq!(current(conv, voter), Some((0b010, 20)));
+
+        assert!(apply_vote(&conv, &did, &voter, &[1], 30).unwrap(), "same choice, newer");
+        assert!(!apply_vote(&conv, &did, &voter, &[2], 25).unwrap(), "older than the re-vote");
+        assert_eq!(current(conv, voter), Some((0b010, 30)));
+
+        assert!(apply_vote(&conv, &did, &voter, &[], 40).unwrap(), "withdrawn");
+        assert!(!apply_vote(&conv, &did, &voter, &[1], 35).unwrap(), "and stays withdrawn");
+        assert_eq!(current(conv, voter), Some((0, 40)));
+    }
+
+    /// A vote that overtakes its poll waits, newest per voter, and is held to
+    /// the poll once it lands; one the poll can't take is dropped then.
+    #[test]
+    fn a_vote_before_its_poll_waits_for_it() {
+        let dir = std::env::temp_dir().join("promtuz-poll-votes-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        let (conv, did) = (rand::random(), rand::random());
+        let (early, greedy) = ([0x46u8; 32], [0x47u8; 32]);
+        let now = crate::utils::systime().as_secs();
+        assert!(!apply_vote(&conv, &did, &early, &[0], now - 2).unwrap());
+        assert!(!apply_vote(&conv, &did, &early, &[2], now - 1).unwrap());
+        assert!(!apply_vote(&conv, &did, &early, &[1], now - 3).unwrap(), "older, held back");
+        assert!(!apply_vote(&conv, &did, &greedy, &[0, 1], now).unwrap());
+        assert!(votes_for(&conv).is_empty(), "nothing counted before the poll");
+
+        receive(conv, did);
+        assert_eq!(current(conv, early), Some((0b100, now - 1)));
+        assert_eq!(current(conv, greedy), None, "two choices on a single-choice poll");
+    }
+
+    /// A stored poll whose options don't decode reads as no poll, not as one
+    /// with no options that every ballot fails against.
+    #[test]
+    fn a_corrupt_poll_row_is_not_a_poll() {
+        let conn = crate::db::messages::open_in_memory();
+        conn.execute(
+            "INSERT INTO polls (conversation_id, dispatch_id, question, options, multi, \
+             anonymous, closes_at, closed_at) VALUES (?1, ?2, 'q', X'ff', 0, 0, NULL, NULL)",
+            [[1u8; 16].as_slice(), [2u8; 16].as_slice()],
+        )
+        .unwrap();
+        let read = conn.query_row("SELECT * FROM polls", [], Poll::from_row);
+        assert!(read.is_err());
+    }
+}
@@ -1,12 +1,17 @@
 //! Recovery channels (IDENTITY_RECOVERY.md): the isk rendered as a BIP39
-//! phrase (Channel B) or handed raw to platform escrow (Channel A). Lives
-//! inside `data/` so it can reach `Identity::secret_key_with_manager` — raw
-//! key bytes still never leave the data layer except through the two
-//! documented escrow/phrase exports.
+//! phrase (Channel B), handed raw to platform escrow (Channel A), or split
+//! into Shamir shares held by contacts (social recovery,
+//! `crate::social_recovery`). Lives inside `data/` so it can reach
+//! `Identity::secret_key_with_manager` — raw key bytes still never leave the
+//! data layer except through the documented escrow/phrase exports; shares
+//! leave only sent to a holder, and fewer than the threshold reveal nothing.
 
 use anyhow::Result;
 use anyhow::anyhow;
 use bip39::Mnemonic;
+use common::crypto::shamir;
+use common::crypto::shamir::Share;
+use ed25519_dalek::SigningKey;
 use zeroize::Zeroizing;
 
 use crate::data::identity::Identity;
@@ -62,6 +67,42 @@ pub fn adopt_escrowed(isk: &[u8], name: &str) -> Result<()> {
     Identity::restore(&isk, name)
 }
 
+/// Split the current isk into `count` shares, any `threshold` of which
+/// rebuild it.
+pub(crate) fn split_isk(threshold: u8, count: u8) -> Result<Vec<Share>> {
+    let secret = Identity::secret_key_with_manager()?;
+    Ok(shamir::split(&secret, threshold, count)?)
+}
+
+/// Rebuild the isk of `owner` from returned shares. Tries every
+/// `threshold`-sized subset, so one holder sending garbage (or a share of
+/// another split) costs a retry rather than the recovery.
+pub(crate) fn isk_from_shares(
+    owner: &[u8; 32], shares: &[Share], threshold: u8,
+) -> Result<Zeroizing<[u8; 32]>> {
+    let k = threshold as usize;
+    if k < 2 || shares.len() < k {
+        return Err(anyhow!("{} of {threshold} shares so far", shares.len()));
+    }
+    let mut pick: Vec<usize> = (0..k).collect();
+    loop {
+        let subset: Vec<Share> = pick.iter().map(|&i| shares[i]).collect();
+        if let Ok(isk) = shamir::combine(&subset)
+            && SigningKey::from_bytes(&isk).verifying_key().as_bytes() == owner
+        {
+            return Ok(isk);
+        }
+        // Next combination in lexicographic order.
+        let Some(i) = (0..k).rev().find(|&i| pick[i] < shares.len() - k + i) else {
+            return Err(anyhow!("the shares returned don't rebuild this identity"));
+        };
+        pick[i] += 1;
+        for j in i + 1..k {
+            pick[j] = pick[j - 1] + 1;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,6 +111,17 @@ mod tests {
         Mnemonic::from_entropy(&entropy).unwrap().words().map(str::to_string).collect()
     }
 
+    #[test]
+    fn shares_rebuild_the_isk_past_a_bad_one() {
+        let isk = [0x5a; 32];
+        let owner = SigningKey::from_bytes(&isk).verifying_key().to_bytes();
+        let mut shares = shamir::split(&isk, 2, 4).unwrap();
+        shares[0].1[0] ^= 1;
+        assert_eq!(*isk_from_shares(&owner, &shares, 2).unwrap(), isk);
+        assert!(isk_from_shares(&owner, &shares[..1], 2).is_err(), "below threshold");
+        assert!(isk_from_shares(&[0; 32], &shares, 2).is_err(), "someone else's identity");
+    }
+
     #[test]
     fn phrase_roundtrips_to_same_isk() {
         let entropy = [7u8; 32];
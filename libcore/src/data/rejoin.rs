@@ -0,0 +1,222 @@
+//! Where each conversation stands on getting back into its MLS group after a
+//! restore. A backup carries chats but no group state, so every restored
+//! conversation starts [`REJOIN_PENDING`]; [`crate::rejoin`] drives it from
+//! there. Rows live in `messages.db`, so every change rings `on_db_changed`
+//! and the UI re-reads [`all`] for its per-chat progress.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+
+use crate::db::messages::MESSAGES_DB;
+use crate::utils::systime;
+
+/// Restored without group state; nothing sent yet.
+pub const REJOIN_PENDING: u8 = 0;
+/// Asked the group's admin to let us back in; waiting on their Welcome.
+pub const REJOIN_REQUESTED: u8 = 1;
+/// Back in: the conversation decrypts and sends again.
+pub const REJOIN_DONE: u8 = 2;
+/// Tried and failed; `detail` says why. Retried on the next connect.
+pub const REJOIN_FAILED: u8 = 3;
+
+/// One conversation's progress. `updated_at` is unix seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejoinRow {
+    pub conversation: [u8; 16],
+    pub state:        u8,
+    pub detail:       String,
+    pub updated_at:   u64,
+    /// The MLS ciphersuite its group ran on, if known.
+    pub suite:        Option<u16>,
+}
+
+/// Note that `conversation` needs rejoining, and which `suite` its group ran
+/// on if known; a suite already recorded stays when it isn't. Restarts it
+/// from [`REJOIN_PENDING`] even if an earlier restore had finished it.
+pub fn mark_needed(conversation: &[u8; 16], suite: Option<u16>) -> Result<()> {
+    let conn = MESSAGES_DB.lock();
+    mark_needed_in(&conn, conversation, suite, systime().as_secs())
+}
+
+fn mark_needed_in(
+    conn: &Connection, conversation: &[u8; 16], suite: Option<u16>, now: u64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO rejoins (conversation_id, state, detail, updated_at, suite) \
+         VALUES (?1, ?2, '', ?3, ?4) \
+         ON CONFLICT(conversation_id) DO UPDATE SET state = excluded.state, detail = '', \
+         updated_at = excluded.updated_at, suite = COALESCE(excluded.suite, suite)",
+        (conversation.as_slice(), REJOIN_PENDING, now, suite),
+    )?;
+    Ok(())
+}
+
+/// Move a tracked conversation to `state`. A conversation with no row never
+/// needed rejoining and stays untracked.
+pub fn set(conversation: &[u8; 16], state: u8, detail: &str) {
+    let conn = MESSAGES_DB.lock();
+    set_in(&conn, conversation, state, detail, systime().as_secs());
+}
+
+fn set_in(conn: &Connection, conversation: &[u8; 16], state: u8, detail: &str, now: u64) {
+    let _ = conn.execute(
+        "UPDATE rejoins SET state = ?2, detail = ?3, updated_at = ?4 \
+         WHERE conversation_id = ?1",
+        (conversation.as_slice(), state, detail, now),
+    );
+}
+
+/// A Welcome put us back into `conversation`'s group. No-op when it wasn't
+/// waiting on one.
+pub fn completed(conversation: &[u8; 16]) { set(conversation, REJOIN_DONE, "") }
+
+/// The tracked row for `conversation`, if any.
+pub fn get(conversation: &[u8; 16]) -> Option<RejoinRow> {
+    let conn = MESSAGES_DB.lock();
+    conn.query_row(
+        "SELECT conversation_id, state, detail, updated_at, suite FROM rejoins \
+         WHERE conversation_id = ?1",
+        [conversation.as_slice()],
+        row,
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Every tracked conversation, oldest change first.
+pub fn all() -> Vec<RejoinRow> {
+    let conn = MESSAGES_DB.lock();
+    all_in(&conn)
+}
+
+fn all_in(conn: &Connection) -> Vec<RejoinRow> {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT conversation_id, state, detail, updated_at, suite FROM rejoins \
+         ORDER BY updated_at",
+    ) else {
+        return Vec::new();
+    };
+    let Ok(rows) = stmt.query_map([], row) else { return Vec::new() };
+    rows.flatten().collect()
+}
+
+/// Rows still to act on: not started, failed, or asked so long ago the
+/// request was probably lost (`asked_before`, unix seconds).
+pub fn open(asked_before: u64) -> Vec<RejoinRow> {
+    all().into_iter().filter(|r| is_open(r, asked_before)).collect()
+}
+
+fn is_open(r: &RejoinRow, asked_before: u64) -> bool {
+    match r.state {
+        REJOIN_PENDING | REJOIN_FAILED => true,
+        REJOIN_REQUESTED => r.updated_at < asked_before,
+        _ => false,
+    }
+}
+
+/// Whether to act on `requester`'s ask back into `group`, stamped
+/// `timestamp`, at `now_ms`: only if it is newer than the last one acted on
+/// and that was at least `min_gap_ms` ago. Acting is recorded here, so the
+/// answer holds across restarts.
+pub fn admit_request(
+    requester: &[u8; 32], group: &[u8; 32], timestamp: u64, now_ms: u64, min_gap_ms: u64,
+) -> Result<bool> {
+    let conn = MESSAGES_DB.lock();
+    admit_request_in(&conn, requester, group, timestamp, now_ms, min_gap_ms)
+}
+
+fn admit_request_in(
+    conn: &Connection, requester: &[u8; 32], group: &[u8; 32], timestamp: u64, now_ms: u64,
+    min_gap_ms: u64,
+) -> Result<bool> {
+    let last: Option<(u64, u64)> = conn
+        .query_row(
+            "SELECT timestamp, acted_at FROM rejoin_requests \
+             WHERE requester = ?1 AND group_id = ?2",
+            (requester.as_slice(), group.as_slice()),
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    if let Some((seen, acted_at)) = last
+        && (timestamp <= seen || now_ms.saturating_sub(acted_at) < min_gap_ms)
+    {
+        return Ok(false);
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO rejoin_requests (requester, group_id, timestamp, acted_at) \
+         VALUES (?1, ?2, ?3, ?4)",
+        (requester.as_slice(), group.as_slice(), timestamp, now_ms),
+    )?;
+    Ok(true)
+}
+
+fn row(r: &rusqlite::Row<'_>) -> rusqlite::Result<RejoinRow> {
+    let id: Vec<u8> = r.get(0)?;
+    Ok(RejoinRow {
+        conversation: id.try_into().unwrap_or([0; 16]),
+        state:        r.get(1)?,
+        detail:       r.get(2)?,
+        updated_at:   r.get(3)?,
+        suite:        r.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::messages::open_in_memory;
+
+    #[test]
+    fn only_tracked_conversations_move_and_a_restore_restarts_them() {
+        let conn = open_in_memory();
+        mark_needed_in(&conn, &[1; 16], Some(0x4D), 10).unwrap();
+        set_in(&conn, &[1; 16], REJOIN_DONE, "", 20);
+        // Never restored, so there's nothing to report for it.
+        set_in(&conn, &[2; 16], REJOIN_DONE, "", 20);
+
+        let rows = all_in(&conn);
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].state, rows[0].updated_at), (REJOIN_DONE, 20));
+        assert_eq!(rows[0].suite, Some(0x4D), "moving on keeps the suite");
+
+        mark_needed_in(&conn, &[1; 16], None, 30).unwrap();
+        assert_eq!(all_in(&conn)[0].state, REJOIN_PENDING);
+        assert_eq!(all_in(&conn)[0].suite, Some(0x4D), "an unknown suite keeps the recorded one");
+        mark_needed_in(&conn, &[1; 16], Some(0x1), 40).unwrap();
+        assert_eq!(all_in(&conn)[0].suite, Some(0x1));
+    }
+
+    #[test]
+    fn requests_are_acted_on_once_each_and_no_more_often_than_the_gap() {
+        const GAP: u64 = 1_000;
+        let conn = open_in_memory();
+        let (alice, group) = ([1; 32], [9; 32]);
+        assert!(admit_request_in(&conn, &alice, &group, 10, 5_000, GAP).unwrap());
+        assert!(!admit_request_in(&conn, &alice, &group, 10, 9_000, GAP).unwrap(), "replay");
+        assert!(!admit_request_in(&conn, &alice, &group, 5, 9_000, GAP).unwrap(), "older");
+        assert!(
+            !admit_request_in(&conn, &alice, &group, 11, 5_500, GAP).unwrap(),
+            "newer, but inside the gap"
+        );
+        assert!(admit_request_in(&conn, &[2; 32], &group, 10, 5_500, GAP).unwrap(), "another");
+        assert!(admit_request_in(&conn, &alice, &group, 11, 6_000, GAP).unwrap(), "past it");
+    }
+
+    #[test]
+    fn a_request_is_reopened_only_once_it_is_old() {
+        let at = |state, updated_at| RejoinRow {
+            conversation: [0; 16],
+            state,
+            detail: String::new(),
+            updated_at,
+            suite: None,
+        };
+        assert!(is_open(&at(REJOIN_PENDING, 50), 10));
+        assert!(is_open(&at(REJOIN_FAILED, 50), 10));
+        assert!(!is_open(&at(REJOIN_REQUESTED, 50), 10));
+        assert!(is_open(&at(REJOIN_REQUESTED, 5), 10));
+        assert!(!is_open(&at(REJOIN_DONE, 5), 10));
+    }
+}
@@ -2,14 +2,19 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use anyhow::anyhow;
+use common::MIN_PROTOCOL_VERSION;
 use common::PROTOCOL_VERSION;
 use common::proto::client_res::ClientRequest;
 use common::proto::client_res::ClientResponse;
+use common::proto::client_res::DirectoryVerifyError;
 use common::proto::client_res::RelayDescriptor;
+use common::proto::client_res::RelayDirectory;
+use common::proto::pack::PackError;
 use common::proto::pack::Packer;
 use common::proto::pack::UnpackError;
 use common::proto::pack::Unpacker;
 use log::info;
+use log::warn;
 use quinn::Connection;
 use rusqlite::params;
 use serde::Serialize;
@@ -19,8 +24,10 @@ use tokio::io::AsyncWriteExt;
 use crate::data::ResolverSeed;
 use crate::db::network::CircuitState;
 use crate::db::network::NETWORK_DB;
+use crate::db::network::Transport;
 use crate::events::Emittable;
 use crate::events::connection::ConnectionState;
+use crate::quic::dialer::Bridge;
 use crate::quic::dialer::DialerError;
 use crate::quic::dialer::connect_to_any_seed;
 use crate::quic::dialer::quinn_err;
@@ -44,6 +51,8 @@ const SCORE_WEIGHT_SUCCESS: f64 = 0.6;
 const SCORE_WEIGHT_LATENCY: f64 = 0.4;
 const EXPLORE_PROBABILITY: f64 = 0.2;
 const TOP_N: usize = 3;
+/// How long `resolve` waits on the tunnel-port lookup before going without.
+const TUNNEL_LOOKUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Resolver upsert behind [`Relay::refresh`]. The `CASE` arms read the stored
 /// row (SQL evaluates every assignment's right side against the pre-update
@@ -64,6 +73,18 @@ const REFRESH_UPSERT: &str = "\
       protocol_version     = excluded.protocol_version,
       pubkey               = excluded.pubkey";
 
+/// Keeps the newest signed directory only. The `WHERE` makes an older
+/// snapshot (a stale one a contact shared, say) a no-op, so callers read
+/// `changes()` to learn whether it was adopted.
+const DIRECTORY_UPSERT: &str = "\
+    INSERT INTO relay_directory (singleton, issued_at, expires_at, directory)
+    VALUES (1, ?1, ?2, ?3)
+    ON CONFLICT(singleton) DO UPDATE SET
+      issued_at  = excluded.issued_at,
+      expires_at = excluded.expires_at,
+      directory  = excluded.directory
+    WHERE excluded.issued_at > relay_directory.issued_at";
+
 // // // // // // // // // // // // // // // // // //
 
 //===:===:===:===:===:===:=:===:===:===:===:===:===||
@@ -115,6 +136,16 @@ pub struct Relay {
     /// disabled — those wrappers can't be signed and the home would
     /// reply `DhtUnavailable` regardless.
     pub home_node_id: Option<[u8; 32]>,
+    /// Protocol version the relay settled on in `connect()`; rows loaded from
+    /// the DB carry [`MIN_PROTOCOL_VERSION`] until then.
+    pub version:    u16,
+    /// Transport the next dial uses — the relay's stored one, or for a relay
+    /// never reached yet, whichever last worked for any relay (a network
+    /// that blocks UDP blocks it for all of them).
+    pub transport:  Transport,
+    /// Port of the relay's tunnel listener, if the resolver advertised one;
+    /// [`Transport::Tls`] dials it instead of the default.
+    pub tunnel_port: Option<u16>,
 }
 
 impl std::fmt::Debug for Relay {
@@ -127,6 +158,9 @@ impl std::fmt::Debug for Relay {
             .field("dht_client", &self.dht_client.as_ref().map(|_| "<RelayDhtClient>"))
             .field("pubkey", &self.pubkey.as_ref().map(|pk| hex::encode(&pk[..4])))
             .field("home_node_id", &self.home_node_id.as_ref().map(|id| hex::encode(&id[..4])))
+            .field("version", &self.version)
+            .field("transport", &self.transport)
+            .field("tunnel_port", &self.tunnel_port)
             .finish()
     }
 }
@@ -144,6 +178,9 @@ pub enum RelayError {
 
     #[error("database error: {0}")]
     Db(#[from] rusqlite::Error),
+
+    #[error("encode error: {0}")]
+    Encode(#[from] PackError),
 }
 
 #[derive(Error, Debug)]
@@ -159,6 +196,12 @@ pub enum ResolveError {
 
     #[error("relay error: {0}")]
     RelayError(#[from] RelayError),
+
+    #[error("relay directory rejected: {0}")]
+    Directory(#[from] DirectoryVerifyError),
+
+    #[error("no cached relay directory")]
+    NoDirectory,
 }
 
 // // // // // // // // // // // // // // // // // //
@@ -279,6 +322,9 @@ impl Relay {
             latency:      Option<i64>,
             success_rate: f64,
             pubkey:       Option<[u8; 32]>,
+            transport:    String,
+            untried:      bool,
+            tunnel_port:  Option<u16>,
         }
 
         let mut stmt = conn.prepare(
@@ -287,7 +333,10 @@ impl Relay {
                     CAST(window_successes AS REAL) / MAX(window_attempts, 1) AS success_rate,
                     pubkey,
                     MIN(last_latency) OVER () AS min_lat,
-                    MAX(last_latency) OVER () AS max_lat
+                    MAX(last_latency) OVER () AS max_lat,
+                    transport,
+                    last_connect IS NULL AND consecutive_failures = 0 AS untried,
+                    tunnel_port
              FROM relays
              WHERE protocol_version = ?1
                AND circuit_state IN ('closed', 'half_open')",
@@ -315,6 +364,9 @@ impl Relay {
                     latency,
                     success_rate,
                     pubkey,
+                    transport: row.get(8)?,
+                    untried: row.get(9)?,
+                    tunnel_port: row.get(10)?,
                 })
             })?
             .collect::<rusqlite::Result<_>>()?;
@@ -367,6 +419,14 @@ impl Relay {
             chosen
         };
 
+        // The CHECK constraint keeps the column to known values; a stray one
+        // just means "start from UDP".
+        let stored = Transport::try_from(chosen.transport.clone()).unwrap_or(Transport::Quic);
+        let transport = match chosen.untried {
+            true => last_working_transport(&conn)?.unwrap_or(stored),
+            false => stored,
+        };
+
         Ok(Self {
             id:         Arc::from(chosen.id.as_str()),
             host:       Arc::from(chosen.host.as_str()),
@@ -375,6 +435,9 @@ impl Relay {
             dht_client: None,
             pubkey:     chosen.pubkey,
             home_node_id: None,
+            version:    MIN_PROTOCOL_VERSION,
+            transport,
+            tunnel_port: chosen.tunnel_port,
         })
     }
 
@@ -383,12 +446,14 @@ impl Relay {
     pub fn fetch_by_id(id: &str) -> Result<Self, RelayError> {
         let conn = NETWORK_DB.lock();
         conn.query_row(
-            "SELECT id, host, port, pubkey FROM relays WHERE id = ?1",
+            "SELECT id, host, port, pubkey, transport, tunnel_port FROM relays WHERE id = ?1",
             params![id],
             |row| {
                 let pubkey: Option<[u8; 32]> = row
                     .get::<_, Option<Vec<u8>>>(3)?
                     .and_then(|v| v.try_into().ok());
+                let transport =
+                    Transport::try_from(row.get::<_, String>(4)?).unwrap_or(Transport::Quic);
                 Ok(Self {
                     id:           Arc::from(row.get::<_, String>(0)?.as_str()),
                     host:         Arc::from(row.get::<_, String>(1)?.as_str()),
@@ -397,6 +462,9 @@ impl Relay {
                     dht_client:   None,
                     pubkey,
                     home_node_id: None,
+                    version:      MIN_PROTOCOL_VERSION,
+                    transport,
+                    tunnel_port:  row.get(5)?,
                 })
             },
         )
@@ -453,9 +521,10 @@ impl Relay {
                    last_connect         = ?1,
                    window_attempts      = CASE WHEN window_start < ?2 THEN 1 ELSE window_attempts + 1 END,
                    window_successes     = CASE WHEN window_start < ?2 THEN 1 ELSE window_successes + 1 END,
-                   window_start         = CASE WHEN window_start < ?2 THEN ?1 ELSE window_start END
+                   window_start         = CASE WHEN window_start < ?2 THEN ?1 ELSE window_start END,
+                   transport            = ?4
                  WHERE id = ?3",
-            params![now, window_threshold, self.id.as_ref()],
+            params![now, window_threshold, self.id.as_ref(), self.transport.as_str()],
         )?;
 
         Ok(())
@@ -566,6 +635,66 @@ impl Relay {
 
         Ok(())
     }
+
+    /// A dial that never reached the relay (timeout, refused, tunnel setup
+    /// failed): a failure like any other, and also the signal that this path
+    /// may be blocked, so the next dial moves down [`Transport::fallback`].
+    /// Handshake rejections and cert failures don't come here — the path
+    /// worked.
+    pub fn record_dial_failure(&self) -> Result<(), RelayError> {
+        self.record_failure()?;
+
+        let bridged = self.bridge()?.is_some();
+        let next = self.transport.fallback(bridged);
+        NETWORK_DB.lock().execute(
+            "UPDATE relays SET transport = ?1 WHERE id = ?2",
+            params![next.as_str(), self.id.as_ref()],
+        )?;
+
+        info!("relay({}) {} dial failed, next try over {next}", self.id, self.transport);
+        Ok(())
+    }
+
+    /// The user-configured WebSocket bridge for this relay, if any.
+    pub fn bridge(&self) -> Result<Option<Bridge>, RelayError> {
+        let conn = NETWORK_DB.lock();
+        let row = conn
+            .query_row(
+                "SELECT url, front FROM relay_bridges WHERE relay_id = ?1",
+                params![self.id.as_ref()],
+                |r| Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?)),
+            )
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                other => Err(other),
+            })?;
+
+        // Validated on the way in (`set_bridge`); a row that no longer
+        // parses is treated as absent rather than failing every dial.
+        Ok(row.and_then(|(url, front)| Bridge::parse(&url, front).ok()))
+    }
+
+    /// Stores (or replaces) the bridge for relay `id`.
+    pub fn set_bridge(id: &str, url: &str, front: Option<String>) -> Result<(), ResolveError> {
+        Bridge::parse(url, front.clone())?;
+        NETWORK_DB
+            .lock()
+            .execute(
+                "INSERT INTO relay_bridges (relay_id, url, front) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(relay_id) DO UPDATE SET url = excluded.url, front = excluded.front",
+                params![id, url, front],
+            )
+            .map_err(RelayError::Db)?;
+        Ok(())
+    }
+
+    /// Drops the bridge for relay `id`; a relay on `websocket` falls back to
+    /// UDP on its next failure.
+    pub fn remove_bridge(id: &str) -> Result<(), RelayError> {
+        NETWORK_DB.lock().execute("DELETE FROM relay_bridges WHERE relay_id = ?1", params![id])?;
+        Ok(())
+    }
 }
 
 // // // // // // // // // // // // // // // // // //
@@ -576,36 +705,198 @@ impl Relay {
 
 impl Relay {
     /// Resolves relays by connecting to one of the resolver seeds provided.
+    ///
+    /// Asks for the signed directory rather than the bare relay list, and
+    /// refuses it unless one of `seeds` signed it. The TLS session already
+    /// authenticates the resolver; the signature is what lets the result
+    /// outlive the session as the cached fallback.
+    ///
+    /// A resolver that predates `GetDirectory` can't decode the request and
+    /// drops the stream; it is asked for the bare list instead, which is used
+    /// for this run but never cached since nothing signed it.
     pub async fn resolve(seeds: &[ResolverSeed]) -> Result<(), ResolveError> {
         use ConnectionState as CS;
+        use DirectoryVerifyError as DVE;
 
         CS::Resolving.emit();
 
         let conn = connect_to_any_seed(seeds).await.inspect_err(|_| CS::Failed.emit())?;
 
-        let req = ClientRequest::GetRelays().pack().unwrap();
+        let directory = match Relay::request(&conn, ClientRequest::GetDirectory()).await {
+            Ok(ClientResponse::GetDirectory { directory }) => directory,
+            Ok(_) => return Relay::resolve_unsigned(&conn).await,
+            Err(e) => {
+                info!("resolver gave no directory ({e}); asking for its relay list");
+                return Relay::resolve_unsigned(&conn).await;
+            },
+        };
+
+        // A skewed local clock must not lock the client out: the signature
+        // held and the live session vouches for the list, so only the dates
+        // are in doubt. Use the relays, but don't cache a directory whose
+        // freshness we can't judge as the fallback.
+        let now = systime().as_millis() as u64;
+        let fresh = match directory.verify(&trusted_keys(seeds), now) {
+            Ok(()) => true,
+            Err(e @ (DVE::FutureIssued | DVE::Expired)) => {
+                warn!("resolver directory {e}; using it without caching");
+                false
+            },
+            Err(e) => return Err(e.into()),
+        };
+        if directory.relays.is_empty() {
+            return Err(ResolveError::EmptyResponse);
+        }
+
+        info!("resolver returned {} relay(s)", directory.relays.len());
+        if fresh {
+            Relay::store_directory(&directory)?;
+        }
+        Relay::refresh(&directory.relays)?;
+        Relay::fetch_tunnels(&conn).await;
+        conn.close(quinn::VarInt::from_u32(1), &[]);
+
+        Ok(())
+    }
+
+    /// The pre-directory path: `GetRelays` on `conn`, trusted for as long as
+    /// the session that carried it and so refreshed into the relay table only.
+    async fn resolve_unsigned(conn: &Connection) -> Result<(), ResolveError> {
+        let relays = match Relay::request(conn, ClientRequest::GetRelays()).await? {
+            ClientResponse::GetRelays { relays } => relays,
+            _ => Vec::new(),
+        };
+        if relays.is_empty() {
+            return Err(ResolveError::EmptyResponse);
+        }
+
+        info!("resolver returned {} unsigned relay(s)", relays.len());
+        Relay::refresh(&relays)?;
+        conn.close(quinn::VarInt::from_u32(1), &[]);
+
+        Ok(())
+    }
+
+    /// Sends `req` on a fresh stream of `conn` and reads the one reply.
+    async fn request(
+        conn: &Connection, req: ClientRequest,
+    ) -> Result<ClientResponse, ResolveError> {
+        let req = req.pack().unwrap();
 
         let (mut send, mut recv) = conn.open_bi().await.map_err(quinn_err)?;
         send.write_all(&req).await.map_err(quinn_err)?;
         send.flush().await.map_err(quinn_err)?;
 
-        loop {
-            let client_resp = ClientResponse::unpack(&mut recv).await?;
-
-            #[allow(irrefutable_let_patterns)]
-            if let ClientResponse::GetRelays { relays } = client_resp {
-                if relays.is_empty() {
-                    break Err(ResolveError::EmptyResponse);
-                }
+        Ok(ClientResponse::unpack(&mut recv).await?)
+    }
 
-                info!("resolver returned {} relay(s)", relays.len());
-                Relay::refresh(&relays)?;
-                conn.close(quinn::VarInt::from_u32(1), &[]);
+    /// Asks the resolver on `conn` where each relay's tunnel listener is and
+    /// records the ports. Best effort: a resolver that predates `GetTunnels`
+    /// drops the stream, and the dialer then falls back to the default port.
+    async fn fetch_tunnels(conn: &Connection) {
+        let ask = async {
+            let req = ClientRequest::GetTunnels().pack()?;
+            let (mut send, mut recv) = conn.open_bi().await?;
+            send.write_all(&req).await?;
+            send.finish()?;
+            match ClientResponse::unpack(&mut recv).await? {
+                ClientResponse::GetTunnels { tunnels } => anyhow::Ok(tunnels),
+                _ => Err(anyhow!("unexpected response")),
+            }
+        };
+        let tunnels = match tokio::time::timeout(TUNNEL_LOOKUP_TIMEOUT, ask).await {
+            Ok(Ok(tunnels)) => tunnels,
+            Ok(Err(e)) => return info!("resolver gave no tunnel ports: {e}"),
+            Err(_) => return info!("resolver gave no tunnel ports: timed out"),
+        };
 
-                break Ok(());
+        let db = NETWORK_DB.lock();
+        for t in &tunnels {
+            if let Err(e) = db.execute(
+                "UPDATE relays SET tunnel_port = ?2 WHERE id = ?1",
+                params![t.id.to_string(), t.port],
+            ) {
+                warn!("storing tunnel port for {}: {e}", t.id);
             }
         }
     }
+
+    /// Re-seeds the relay table from the cached directory — the fallback for
+    /// when no resolver answers. Re-verified on the way out of the DB, so an
+    /// expired one is refused rather than silently reused.
+    pub fn restore_cached_directory(seeds: &[ResolverSeed]) -> Result<usize, ResolveError> {
+        let directory = Relay::cached_directory()?.ok_or(ResolveError::NoDirectory)?;
+        directory.verify(&trusted_keys(seeds), systime().as_millis() as u64)?;
+        Relay::refresh(&directory.relays)?;
+        Ok(directory.relays.len())
+    }
+
+    /// Adopts a directory received out of band, e.g. from a contact. Only a
+    /// directory newer than the cached one replaces it and updates the relay
+    /// table, so replaying an old snapshot can't roll relay addresses back.
+    /// Returns how many relays were learned (`0` when it wasn't newer).
+    pub fn import_directory(
+        directory: &RelayDirectory, seeds: &[ResolverSeed],
+    ) -> Result<usize, ResolveError> {
+        directory.verify(&trusted_keys(seeds), systime().as_millis() as u64)?;
+        if !Relay::store_directory(directory)? {
+            return Ok(0);
+        }
+        Relay::refresh(&directory.relays)?;
+        Ok(directory.relays.len())
+    }
+
+    /// The cached signed directory, if any. Not verified here.
+    pub fn cached_directory() -> Result<Option<RelayDirectory>, RelayError> {
+        let conn = NETWORK_DB.lock();
+        let blob: Option<Vec<u8>> = conn
+            .query_row("SELECT directory FROM relay_directory WHERE singleton = 1", [], |r| {
+                r.get(0)
+            })
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                other => Err(other),
+            })?;
+
+        // A row that no longer decodes (a wire change across an update) is
+        // as good as no row; the next resolve replaces it.
+        Ok(blob.and_then(|b| RelayDirectory::deser(&b).ok()))
+    }
+
+    /// Persists `directory` if it is newer than the cached one.
+    fn store_directory(directory: &RelayDirectory) -> Result<bool, RelayError> {
+        let blob = directory.ser()?;
+        let conn = NETWORK_DB.lock();
+        let changed = conn.execute(
+            DIRECTORY_UPSERT,
+            params![directory.issued_at_ms as i64, directory.expires_at_ms as i64, blob],
+        )?;
+        Ok(changed > 0)
+    }
+}
+
+/// Transport of the most recently connected relay, for a relay with no
+/// history of its own.
+fn last_working_transport(conn: &rusqlite::Connection) -> Result<Option<Transport>, RelayError> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT transport FROM relays WHERE last_connect IS NOT NULL
+             ORDER BY last_connect DESC LIMIT 1",
+            [],
+            |r| r.get(0),
+        )
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            other => Err(other),
+        })?;
+    Ok(stored.and_then(|t| Transport::try_from(t).ok()))
+}
+
+/// The resolver keys a directory may be signed by: the bundled seed list.
+fn trusted_keys(seeds: &[ResolverSeed]) -> Vec<[u8; 32]> {
+    seeds.iter().map(|s| s.key.to_bytes()).collect()
 }
 
 // // // // // // // // // // // // // // // // // //
@@ -661,6 +952,39 @@ mod tests {
         .unwrap();
     }
 
+    fn directory_table() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE relay_directory (
+               singleton  INTEGER PRIMARY KEY CHECK(singleton = 1),
+               issued_at  INTEGER NOT NULL,
+               expires_at INTEGER NOT NULL,
+               directory  BLOB NOT NULL
+             );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn store(conn: &Connection, issued_at: i64, blob: &[u8]) -> usize {
+        conn.execute(DIRECTORY_UPSERT, params![issued_at, issued_at + 10, blob]).unwrap()
+    }
+
+    #[test]
+    fn directory_upsert_keeps_only_the_newest_snapshot() {
+        let conn = directory_table();
+        assert_eq!(store(&conn, 100, b"first"), 1);
+        assert_eq!(store(&conn, 200, b"newer"), 1);
+
+        // A replayed older (or same-age) snapshot must not roll the cache back.
+        assert_eq!(store(&conn, 150, b"older"), 0);
+        assert_eq!(store(&conn, 200, b"same"), 0);
+
+        let blob: Vec<u8> =
+            conn.query_row("SELECT directory FROM relay_directory", [], |r| r.get(0)).unwrap();
+        assert_eq!(blob, b"newer");
+    }
+
     #[test]
     fn refresh_clears_the_circuit_for_a_relay_that_moved() {
         let conn = relays_table();
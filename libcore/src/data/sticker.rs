@@ -0,0 +1,168 @@
+//! Installed sticker packs, and offers of packs we don't have yet.
+//!
+//! A pack is a signed file (`common::proto::sticker::StickerPackP`) under
+//! `files/stickers`; this table indexes it by the content-derived `pack_id`
+//! every `Body::Sticker` names, and remembers the transfer `file_id` it is
+//! served under. Installation goes through [`crate::stickers`], which checks
+//! the author's signature first — nothing lands here unverified.
+
+use anyhow::Result;
+use rusqlite::OptionalExtension;
+
+use crate::data::media::AttachmentOffer;
+use crate::data::media::KIND_STICKER;
+use crate::db::messages::MESSAGES_DB;
+use crate::utils::systime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackRow {
+    pub pack_id: [u8; 16],
+    pub title:   String,
+    pub author:  [u8; 32],
+    /// Stickers in the pack.
+    pub count:   u32,
+    /// The pack file, as shipped.
+    pub path:    String,
+    /// What the pack file is pulled as over the transfer engine.
+    pub file_id: [u8; 32],
+}
+
+impl PackRow {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            pack_id: row.get(0)?,
+            title:   row.get(1)?,
+            author:  row.get(2)?,
+            count:   row.get(3)?,
+            path:    row.get(4)?,
+            file_id: row.get(5)?,
+        })
+    }
+}
+
+/// Record an installed pack. Re-installing the same content is a no-op beyond
+/// refreshing where it lives.
+pub fn put(p: &PackRow) -> Result<()> {
+    MESSAGES_DB.lock().execute(
+        "INSERT INTO sticker_packs (pack_id, title, author, count, path, file_id, installed_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) \
+         ON CONFLICT(pack_id) DO UPDATE SET path = excluded.path, file_id = excluded.file_id",
+        rusqlite::params![p.pack_id.as_slice(), p.title, p.author.as_slice(), p.count, p.path,
+            p.file_id.as_slice(), systime().as_secs()],
+    )?;
+    Ok(())
+}
+
+pub fn get(pack_id: &[u8; 16]) -> Option<PackRow> {
+    MESSAGES_DB
+        .lock()
+        .query_row(
+            "SELECT pack_id, title, author, count, path, file_id FROM sticker_packs \
+             WHERE pack_id = ?1",
+            [pack_id.as_slice()],
+            PackRow::from_row,
+        )
+        .ok()
+}
+
+pub fn is_installed(pack_id: &[u8; 16]) -> bool {
+    get(pack_id).is_some()
+}
+
+/// Every installed pack, most recently installed first — the picker's order.
+pub fn all() -> Result<Vec<PackRow>> {
+    let db = MESSAGES_DB.lock();
+    let mut stmt = db.prepare(
+        "SELECT pack_id, title, author, count, path, file_id FROM sticker_packs \
+         ORDER BY installed_at DESC",
+    )?;
+    let rows = stmt.query_map([], PackRow::from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Forget an installed pack. Messages that used it keep their rows and
+/// simply stop rendering until it is fetched again.
+pub fn remove(pack_id: &[u8; 16]) -> Result<Option<PackRow>> {
+    let row = get(pack_id);
+    MESSAGES_DB
+        .lock()
+        .execute("DELETE FROM sticker_packs WHERE pack_id = ?1", [pack_id.as_slice()])?;
+    Ok(row)
+}
+
+/// Whether any message we hold names `pack_id` — the only reason to fetch a
+/// pack someone offers. An unsolicited offer is ignored.
+pub fn is_referenced(pack_id: &[u8; 16]) -> bool {
+    MESSAGES_DB
+        .lock()
+        .query_row(
+            "SELECT 1 FROM message_media WHERE sticker_pack = ?1 LIMIT 1",
+            [pack_id.as_slice()],
+            |_| Ok(()),
+        )
+        .is_ok()
+}
+
+/// Remember that `sender` serves `pack_id` as `file_id`. The first offer for
+/// a file wins the row; `false` when one was already held, so the caller can
+/// add the sender as another source instead.
+pub fn offer_put(
+    file_id: &[u8; 32], pack_id: &[u8; 16], sender: &[u8; 32], conversation: &[u8; 16],
+    size: u64,
+) -> Result<bool> {
+    let n = MESSAGES_DB.lock().execute(
+        "INSERT OR IGNORE INTO sticker_pack_offers \
+         (file_id, pack_id, sender, conversation_id, size, offered_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![file_id.as_slice(), pack_id.as_slice(), sender.as_slice(),
+            conversation.as_slice(), size, systime().as_secs()],
+    )?;
+    Ok(n == 1)
+}
+
+/// The pull for a pack file, in the shape the transfer engine takes for an
+/// attachment: who offered it, its size, and where.
+pub fn offer(file_id: &[u8; 32]) -> Result<Option<AttachmentOffer>> {
+    MESSAGES_DB
+        .lock()
+        .query_row(
+            "SELECT sender, size, conversation_id FROM sticker_pack_offers WHERE file_id = ?1",
+            [file_id.as_slice()],
+            |row| {
+                Ok(AttachmentOffer {
+                    sender:       row.get(0)?,
+                    size:         row.get(1)?,
+                    conversation: row.get(2)?,
+                    kind:         KIND_STICKER,
+                })
+            },
+        )
+        .optional()
+        .map_err(Into::into)
+}
+
+/// Pack files offered for `pack_id`.
+pub fn offers_for(pack_id: &[u8; 16]) -> Result<Vec<[u8; 32]>> {
+    let db = MESSAGES_DB.lock();
+    let mut stmt = db.prepare("SELECT file_id FROM sticker_pack_offers WHERE pack_id = ?1")?;
+    let rows = stmt
+        .query_map([pack_id.as_slice()], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Drop one offer — its file turned out not to be the pack it claimed.
+pub fn offer_remove(file_id: &[u8; 32]) -> Result<()> {
+    MESSAGES_DB
+        .lock()
+        .execute("DELETE FROM sticker_pack_offers WHERE file_id = ?1", [file_id.as_slice()])?;
+    Ok(())
+}
+
+/// Drop every offer for a pack once it installed.
+pub fn offers_clear(pack_id: &[u8; 16]) -> Result<()> {
+    MESSAGES_DB
+        .lock()
+        .execute("DELETE FROM sticker_pack_offers WHERE pack_id = ?1", [pack_id.as_slice()])?;
+    Ok(())
+}
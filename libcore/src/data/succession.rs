@@ -0,0 +1,362 @@
+//! The local side of a key rotation (see [`crate::succession`]): which keys
+//! our contacts retired for which, and moving everything we hold under a
+//! retired key over to its successor. The address book lives in `contacts.db`
+//! and rosters and history in `messages.db`, so each moves in its own
+//! transaction. An old key that names two heirs is contested until the user
+//! [`settle`]s it.
+
+use anyhow::Result;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+
+use crate::data::conversation::KIND_DIRECT;
+use crate::db::messages::MESSAGES_DB;
+use crate::db::peers::CONTACTS_DB;
+use crate::utils::systime;
+
+/// Record a contact's succession and move their address-book row to the new
+/// key. What they gave us under the old key goes: delivery tokens, mailbox
+/// grants and the recovery share all belong to the key they were minted for.
+/// The pair group goes with them, since it carries the old credential.
+pub fn record(succession: &IdentitySuccession) -> Result<()> {
+    let mut conn = CONTACTS_DB.lock();
+    record_in(&mut conn, succession, systime().as_secs())
+}
+
+fn record_in(conn: &mut Connection, succession: &IdentitySuccession, now: u64) -> Result<()> {
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    let statement = succession.ser().map_err(|e| anyhow::anyhow!("encode succession: {e}"))?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO successions (old_ipk, new_ipk, statement, received_at) \
+         VALUES (?1, ?2, ?3, ?4)",
+        (old.as_slice(), new.as_slice(), statement, now),
+    )?;
+    move_peer_in(&tx, &old, &new)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Move `from`'s address-book row to `to`, and drop what was minted for
+/// `from` alone.
+fn move_peer_in(tx: &rusqlite::Transaction, from: &[u8; 32], to: &[u8; 32]) -> Result<()> {
+    tx.execute(
+        "UPDATE OR REPLACE contacts SET ipk = ?2, mls_group_id = NULL WHERE ipk = ?1",
+        (from.as_slice(), to.as_slice()),
+    )?;
+    for table in ["delivery_tokens", "delivery_grants", "mailbox_grants"] {
+        tx.execute(&format!("DELETE FROM {table} WHERE peer = ?1"), [from.as_slice()])?;
+    }
+    tx.execute(
+        "UPDATE issued_tokens SET peer = ?2 WHERE peer = ?1",
+        (from.as_slice(), to.as_slice()),
+    )?;
+    tx.execute("DELETE FROM recovery_held WHERE owner = ?1", [from.as_slice()])?;
+    tx.execute(
+        "UPDATE OR REPLACE recovery_holders SET holder = ?2 WHERE holder = ?1",
+        (from.as_slice(), to.as_slice()),
+    )?;
+    Ok(())
+}
+
+/// The heir recorded for `old`, if a contact rotated away from it.
+pub fn successor(old: &[u8; 32]) -> Option<[u8; 32]> {
+    let conn = CONTACTS_DB.lock();
+    successor_in(&conn, old)
+}
+
+fn successor_in(conn: &Connection, old: &[u8; 32]) -> Option<[u8; 32]> {
+    conn.query_row("SELECT new_ipk FROM successions WHERE old_ipk = ?1", [old.as_slice()], |r| {
+        r.get::<_, [u8; 32]>(0)
+    })
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Keep `succession`, which names another heir than the one recorded for its
+/// old key, beside that one. Until [`settle`] neither heir is sent anything.
+pub fn contest(succession: &IdentitySuccession) -> Result<()> {
+    let mut conn = CONTACTS_DB.lock();
+    contest_in(&mut conn, succession, systime().as_secs())
+}
+
+fn contest_in(conn: &mut Connection, succession: &IdentitySuccession, now: u64) -> Result<()> {
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    let statement = succession.ser().map_err(|e| anyhow::anyhow!("encode succession: {e}"))?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO contested_successions (old_ipk, new_ipk, statement, received_at) \
+         SELECT old_ipk, new_ipk, statement, received_at FROM successions WHERE old_ipk = ?1",
+        [old.as_slice()],
+    )?;
+    tx.execute(
+        "INSERT OR IGNORE INTO contested_successions (old_ipk, new_ipk, statement, received_at) \
+         VALUES (?1, ?2, ?3, ?4)",
+        (old.as_slice(), new.as_slice(), statement, now),
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Whether `ipk` is one of several heirs named for the same old key.
+pub fn is_contested(ipk: &[u8; 32]) -> bool {
+    let conn = CONTACTS_DB.lock();
+    is_contested_in(&conn, ipk)
+}
+
+fn is_contested_in(conn: &Connection, ipk: &[u8; 32]) -> bool {
+    conn.query_row("SELECT 1 FROM contested_successions WHERE new_ipk = ?1", [ipk.as_slice()], |_| {
+        Ok(())
+    })
+    .is_ok()
+}
+
+/// Every contested statement with when it reached us, grouped by old key.
+pub fn contested() -> Vec<(IdentitySuccession, u64)> {
+    let conn = CONTACTS_DB.lock();
+    contested_in(&conn)
+}
+
+fn contested_in(conn: &Connection) -> Vec<(IdentitySuccession, u64)> {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT statement, received_at FROM contested_successions ORDER BY old_ipk, received_at",
+    ) else {
+        return Vec::new();
+    };
+    stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get::<_, u64>(1)?)))
+        .map(|rows| {
+            rows.flatten()
+                .filter_map(|(b, at)| Some((IdentitySuccession::deser(&b).ok()?, at)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The user says `heir` is who `old` became. Its statement replaces the one
+/// recorded, the address-book row follows it, and the contest is over.
+/// Returns the other heir when the row was taken from it, so the caller can
+/// move rosters and history too.
+pub fn settle(old: &[u8; 32], heir: &[u8; 32]) -> Result<Option<[u8; 32]>> {
+    let mut conn = CONTACTS_DB.lock();
+    settle_in(&mut conn, old, heir)
+}
+
+fn settle_in(conn: &mut Connection, old: &[u8; 32], heir: &[u8; 32]) -> Result<Option<[u8; 32]>> {
+    let tx = conn.transaction()?;
+    let statement: Vec<u8> = tx
+        .query_row(
+            "SELECT statement FROM contested_successions WHERE old_ipk = ?1 AND new_ipk = ?2",
+            (old.as_slice(), heir.as_slice()),
+            |r| r.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("no contested succession names that heir"))?;
+    let taken = successor_in(&tx, old).filter(|taken| taken != heir);
+    if let Some(taken) = taken {
+        tx.execute(
+            "UPDATE successions SET new_ipk = ?2, statement = ?3 WHERE old_ipk = ?1",
+            (old.as_slice(), heir.as_slice(), statement),
+        )?;
+        move_peer_in(&tx, &taken, heir)?;
+    }
+    tx.execute("DELETE FROM contested_successions WHERE old_ipk = ?1", [old.as_slice()])?;
+    tx.commit()?;
+    Ok(taken)
+}
+
+/// Every key `ipk` has rotated away from, most recent first.
+pub fn predecessors_of(ipk: &[u8; 32]) -> Vec<[u8; 32]> {
+    let conn = CONTACTS_DB.lock();
+    predecessors_in(&conn, ipk)
+}
+
+fn predecessors_in(conn: &Connection, ipk: &[u8; 32]) -> Vec<[u8; 32]> {
+    let mut out = Vec::new();
+    let mut current = *ipk;
+    while let Ok(Some(old)) = conn
+        .query_row("SELECT old_ipk FROM successions WHERE new_ipk = ?1", [current.as_slice()], |r| {
+            r.get::<_, [u8; 32]>(0)
+        })
+        .optional()
+    {
+        // A chain that loops back was forged somewhere; stop rather than spin.
+        if old == *ipk || out.contains(&old) {
+            break;
+        }
+        out.push(old);
+        current = old;
+    }
+    out
+}
+
+/// Put `new` wherever `old` stands in a roster, and on everything `old` wrote,
+/// reacted or voted. Direct chats with them lose their pair group, which
+/// carries the old credential; its id is returned so the caller can drop the
+/// local state behind it.
+pub fn replace_member(old: &[u8; 32], new: &[u8; 32]) -> Result<Vec<[u8; 32]>> {
+    let mut conn = MESSAGES_DB.lock();
+    replace_member_in(&mut conn, old, new)
+}
+
+fn replace_member_in(
+    conn: &mut Connection, old: &[u8; 32], new: &[u8; 32],
+) -> Result<Vec<[u8; 32]>> {
+    let tx = conn.transaction()?;
+    for (table, column) in [
+        ("conversation_members", "member_ipk"),
+        ("member_read_state", "member_ipk"),
+        ("reactions", "reactor"),
+        ("poll_votes", "voter"),
+        ("peer_names", "ipk"),
+    ] {
+        tx.execute(
+            &format!("UPDATE OR REPLACE {table} SET {column} = ?2 WHERE {column} = ?1"),
+            (old.as_slice(), new.as_slice()),
+        )?;
+    }
+    tx.execute(
+        "UPDATE messages SET sender_ipk = ?2 WHERE sender_ipk = ?1",
+        (old.as_slice(), new.as_slice()),
+    )?;
+    let unbound: Vec<[u8; 32]> = {
+        let mut stmt = tx.prepare(
+            "SELECT mls_group_id FROM conversations \
+             WHERE kind = ?1 AND mls_group_id IS NOT NULL AND id IN \
+             (SELECT conversation_id FROM conversation_members WHERE member_ipk = ?2)",
+        )?;
+        stmt.query_map((KIND_DIRECT, new.as_slice()), |r| r.get::<_, Vec<u8>>(0))?
+            .flatten()
+            .filter_map(|g| g.try_into().ok())
+            .collect()
+    };
+    tx.execute(
+        "UPDATE conversations SET mls_group_id = NULL \
+         WHERE kind = ?1 AND id IN \
+         (SELECT conversation_id FROM conversation_members WHERE member_ipk = ?2)",
+        (KIND_DIRECT, new.as_slice()),
+    )?;
+    tx.commit()?;
+    Ok(unbound)
+}
+
+/// Forget every contact's pair group. Ours carry the key we just retired.
+pub fn unbind_pair_groups() -> Result<()> {
+    let conn = CONTACTS_DB.lock();
+    conn.execute("UPDATE contacts SET mls_group_id = NULL", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::types::bytes::Bytes;
+
+    use super::*;
+
+    fn succession(old: u8, new: u8) -> IdentitySuccession {
+        IdentitySuccession {
+            old_ipk:   Bytes([old; 32]),
+            new_ipk:   Bytes([new; 32]),
+            timestamp: 1,
+            old_sig:   Bytes([0; 64]),
+            new_sig:   Bytes([0; 64]),
+        }
+    }
+
+    #[test]
+    fn a_succession_moves_the_contact_and_chains_back() {
+        let mut conn = crate::db::peers::open_in_memory();
+        conn.execute(
+            "INSERT INTO contacts (ipk, name, added_at, mls_group_id) VALUES (?1, 'bo', 1, ?2)",
+            ([1u8; 32].as_slice(), [9u8; 32].as_slice()),
+        )
+        .unwrap();
+        record_in(&mut conn, &succession(1, 2), 10).unwrap();
+        record_in(&mut conn, &succession(2, 3), 20).unwrap();
+
+        let (ipk, group): (Vec<u8>, Option<Vec<u8>>) = conn
+            .query_row("SELECT ipk, mls_group_id FROM contacts", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(ipk, vec![3u8; 32], "two rotations move the row twice");
+        assert_eq!(group, None, "the old pair group carries a retired credential");
+        assert_eq!(predecessors_in(&conn, &[3; 32]), vec![[2; 32], [1; 32]]);
+        assert_eq!(successor_in(&conn, &[1; 32]), Some([2; 32]));
+        assert!(predecessors_in(&conn, &[1; 32]).is_empty());
+    }
+
+    #[test]
+    fn a_second_heir_contests_the_key_until_the_user_settles_it() {
+        let mut conn = crate::db::peers::open_in_memory();
+        conn.execute(
+            "INSERT INTO contacts (ipk, name, added_at, mls_group_id) VALUES (?1, 'bo', 1, NULL)",
+            [[1u8; 32].as_slice()],
+        )
+        .unwrap();
+        record_in(&mut conn, &succession(1, 2), 10).unwrap();
+        assert!(!is_contested_in(&conn, &[2; 32]));
+
+        contest_in(&mut conn, &succession(1, 3), 20).unwrap();
+        contest_in(&mut conn, &succession(1, 3), 30).unwrap();
+        assert!(is_contested_in(&conn, &[2; 32]) && is_contested_in(&conn, &[3; 32]));
+        let held: Vec<_> =
+            contested_in(&conn).into_iter().map(|(s, at)| (s.new_ipk.0, at)).collect();
+        assert_eq!(held, vec![([2; 32], 10), ([3; 32], 20)], "both, each once");
+
+        assert!(settle_in(&mut conn, &[1; 32], &[4; 32]).is_err(), "an heir nobody named");
+        assert_eq!(settle_in(&mut conn, &[1; 32], &[3; 32]).unwrap(), Some([2; 32]));
+        let ipk: Vec<u8> = conn.query_row("SELECT ipk FROM contacts", [], |r| r.get(0)).unwrap();
+        assert_eq!(ipk, vec![3u8; 32], "the contact follows the heir the user named");
+        assert_eq!(successor_in(&conn, &[1; 32]), Some([3; 32]));
+        assert!(contested_in(&conn).is_empty());
+        assert!(!is_contested_in(&conn, &[2; 32]) && !is_contested_in(&conn, &[3; 32]));
+    }
+
+    #[test]
+    fn replacing_a_member_keeps_their_history_and_unbinds_only_the_pair_chat() {
+        let mut conn = crate::db::messages::open_in_memory();
+        for (id, kind, group) in [([1u8; 16], KIND_DIRECT, [7u8; 32]), ([2; 16], 1, [8; 32])] {
+            conn.execute(
+                "INSERT INTO conversations (id, kind, title, mls_group_id, created_at) \
+                 VALUES (?1, ?2, '', ?3, 0)",
+                (id.as_slice(), kind, group.as_slice()),
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO conversation_members (conversation_id, member_ipk, role, joined_at, active) \
+                 VALUES (?1, ?2, 0, 0, 1)",
+                (id.as_slice(), [5u8; 32].as_slice()),
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, sender_ipk, content, outgoing, timestamp, status) \
+             VALUES ('m1', ?1, ?2, 'hi', 0, 0, 0)",
+            ([2u8; 16].as_slice(), [5u8; 32].as_slice()),
+        )
+        .unwrap();
+
+        let unbound = replace_member_in(&mut conn, &[5; 32], &[6; 32]).unwrap();
+        assert_eq!(unbound, vec![[7u8; 32]]);
+
+        let members: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM conversation_members WHERE member_ipk = ?1",
+                [[6u8; 32].as_slice()],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(members, 2);
+        let sender: Vec<u8> =
+            conn.query_row("SELECT sender_ipk FROM messages", [], |r| r.get(0)).unwrap();
+        assert_eq!(sender, vec![6u8; 32], "their past messages still name them");
+        let group: Option<Vec<u8>> = conn
+            .query_row("SELECT mls_group_id FROM conversations WHERE id = ?1", [[2u8; 16].as_slice()], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(group, Some(vec![8u8; 32]), "a group keeps its group; the member rejoins it");
+    }
+}
@@ -0,0 +1,277 @@
+//! Payloads this build can't read, kept verbatim until one can. A newer
+//! contact's message is decrypted exactly once — the ratchet key is spent the
+//! moment it opens — so dropping a plaintext we don't understand loses it for
+//! good. Instead it waits here with everything needed to apply it later, and
+//! each row remembers what the build that last tried could read: the first
+//! start of a build that reads more re-parses it
+//! ([`crate::quic::server::reparse_unsupported`]).
+//!
+//! What counts as unreadable: a plaintext that doesn't decode at all, an
+//! [`AppPayload::Extension`] whose tag isn't in [`KNOWN_EXTENSIONS`], or
+//! content whose [`Body::Extension`] tag isn't. Content also leaves a
+//! placeholder row in the chat (`SYSTEM_UNSUPPORTED`) so the user sees that
+//! something arrived; it is replaced once the message can be read.
+//!
+//! Any member can send bytes that decode to nothing, so what waits here is
+//! bounded: a payload past [`MAX_KEPT_BYTES`] isn't kept, one sender keeps at
+//! most [`MAX_KEPT_PER_SENDER`] rows, and rows go after [`KEEP_FOR_MS`].
+
+use anyhow::Result;
+use common::proto::mls_wire::APP_PAYLOAD_VARIANTS;
+use common::proto::mls_wire::AppPayload;
+use common::proto::mls_wire::BODY_VARIANTS;
+use common::proto::mls_wire::Body;
+use common::proto::mls_wire::KNOWN_EXTENSIONS;
+use common::proto::mls_wire::MLS_WIRE_VERSION;
+
+use crate::db::messages::MESSAGES_DB;
+use crate::utils::systime;
+
+/// Largest plaintext kept: room for any extension a real client frames,
+/// nowhere near what a member could fill the disk with.
+pub const MAX_KEPT_BYTES: usize = 64 * 1024;
+
+/// Rows one sender may have waiting; past it their new ones are dropped.
+pub const MAX_KEPT_PER_SENDER: usize = 256;
+
+/// How long a row waits for a build that reads it: 90 days.
+pub const KEEP_FOR_MS: u64 = 90 * 24 * 60 * 60 * 1000;
+
+/// What this build reads, stamped on every row so a build that reads more
+/// knows to try it again. Not the crate version: that stays put across
+/// updates, while this moves whenever a variant or extension tag is learned.
+fn reads() -> String {
+    let tags: Vec<String> = KNOWN_EXTENSIONS.iter().map(u32::to_string).collect();
+    format!(
+        "w{MLS_WIRE_VERSION}.p{APP_PAYLOAD_VARIANTS}.b{BODY_VARIANTS}.x{}",
+        tags.join(",")
+    )
+}
+
+/// A decrypted plaintext and the delivery facts it arrived with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unsupported {
+    pub conversation:   [u8; 16],
+    pub from:           [u8; 32],
+    pub author:         [u8; 32],
+    pub dispatch_id:    [u8; 16],
+    pub accepted_at_ms: u64,
+    pub plaintext:      Vec<u8>,
+}
+
+/// Whether this build can act on `payload`, rather than keep it.
+pub fn readable(payload: &AppPayload) -> bool {
+    match payload {
+        AppPayload::Extension { tag, .. } => KNOWN_EXTENSIONS.contains(tag),
+        AppPayload::Post { body, .. }
+        | AppPayload::Forward { body }
+        | AppPayload::Revise { body, .. } => match body {
+            Body::Extension { tag, .. } => KNOWN_EXTENSIONS.contains(tag),
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Keep a payload for a later build. Idempotent per dispatch: a redelivery
+/// the seen ledger missed stores nothing new. Returns whether it was kept: an
+/// oversized payload, or one from a sender already at their cap, is dropped.
+pub fn keep(u: &Unsupported) -> Result<bool> {
+    let conn = MESSAGES_DB.lock();
+    keep_in(&conn, u, now_ms())
+}
+
+fn keep_in(conn: &rusqlite::Connection, u: &Unsupported, now_ms: u64) -> Result<bool> {
+    expire_in(conn, now_ms)?;
+    if u.plaintext.len() > MAX_KEPT_BYTES {
+        return Ok(false);
+    }
+    let held: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM unsupported_payloads WHERE sender = ?1",
+        [u.from.as_slice()],
+        |r| r.get(0),
+    )?;
+    if held as usize >= MAX_KEPT_PER_SENDER {
+        return Ok(false);
+    }
+    let kept = conn.execute(
+        "INSERT OR IGNORE INTO unsupported_payloads \
+         (conversation_id, dispatch_id, sender, author, accepted_at_ms, plaintext, kept_by) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (
+            u.conversation.as_slice(),
+            u.dispatch_id.as_slice(),
+            u.from.as_slice(),
+            u.author.as_slice(),
+            u.accepted_at_ms,
+            &u.plaintext,
+            reads(),
+        ),
+    )?;
+    Ok(kept > 0)
+}
+
+/// Drop rows that waited past [`KEEP_FOR_MS`].
+fn expire_in(conn: &rusqlite::Connection, now_ms: u64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM unsupported_payloads WHERE accepted_at_ms < ?1",
+        [now_ms.saturating_sub(KEEP_FOR_MS)],
+    )?;
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    systime().as_millis() as u64
+}
+
+/// Rows no build that reads what this one does has tried yet, oldest first.
+/// Expired rows go first.
+pub fn untried() -> Vec<Unsupported> {
+    let conn = MESSAGES_DB.lock();
+    untried_in(&conn, now_ms())
+}
+
+fn untried_in(conn: &rusqlite::Connection, now_ms: u64) -> Vec<Unsupported> {
+    if expire_in(conn, now_ms).is_err() {
+        return Vec::new();
+    }
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT conversation_id, dispatch_id, sender, author, accepted_at_ms, plaintext \
+         FROM unsupported_payloads WHERE kept_by != ?1 ORDER BY accepted_at_ms",
+    ) else {
+        return Vec::new();
+    };
+    let rows = stmt.query_map([reads()], |r| {
+        let conversation: Vec<u8> = r.get(0)?;
+        let dispatch_id: Vec<u8> = r.get(1)?;
+        let from: Vec<u8> = r.get(2)?;
+        let author: Vec<u8> = r.get(3)?;
+        Ok((conversation, dispatch_id, from, author, r.get(4)?, r.get(5)?))
+    });
+    let Ok(rows) = rows else { return Vec::new() };
+    rows.flatten()
+        .filter_map(|(conversation, dispatch_id, from, author, accepted_at_ms, plaintext)| {
+            Some(Unsupported {
+                conversation: conversation.try_into().ok()?,
+                dispatch_id: dispatch_id.try_into().ok()?,
+                from: from.try_into().ok()?,
+                author: author.try_into().ok()?,
+                accepted_at_ms,
+                plaintext,
+            })
+        })
+        .collect()
+}
+
+/// Still unreadable by this build: don't try again until the next one.
+pub fn tried(conversation: &[u8; 16], dispatch_id: &[u8; 16]) {
+    let conn = MESSAGES_DB.lock();
+    let _ = conn.execute(
+        "UPDATE unsupported_payloads SET kept_by = ?3 \
+         WHERE conversation_id = ?1 AND dispatch_id = ?2",
+        (conversation.as_slice(), dispatch_id.as_slice(), reads()),
+    );
+}
+
+/// Read at last, or its chat is gone.
+pub fn remove(conversation: &[u8; 16], dispatch_id: &[u8; 16]) {
+    let conn = MESSAGES_DB.lock();
+    let _ = conn.execute(
+        "DELETE FROM unsupported_payloads WHERE conversation_id = ?1 AND dispatch_id = ?2",
+        (conversation.as_slice(), dispatch_id.as_slice()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::mls_wire::EXT_RECOVERY_REVOKE;
+    use common::proto::mls_wire::EXT_RECOVERY_SHARE;
+    use common::types::bytes::ByteVec;
+
+    use super::*;
+    use crate::db::messages::open_in_memory;
+
+    fn row(did: u8, at: u64) -> Unsupported {
+        Unsupported {
+            conversation:   [1; 16],
+            from:           [2; 32],
+            author:         [3; 32],
+            dispatch_id:    [did; 16],
+            accepted_at_ms: at,
+            plaintext:      vec![0xff, did],
+        }
+    }
+
+    #[test]
+    fn unknown_tags_are_unreadable_and_known_payloads_are_not() {
+        const UNKNOWN: u32 = u32::MAX;
+        let ext = |tag| Body::Extension { tag, body: ByteVec(vec![1]) };
+        assert!(!readable(&AppPayload::Extension { tag: UNKNOWN, body: ByteVec(vec![]) }));
+        assert!(!readable(&AppPayload::Post { reply_to: None, body: ext(UNKNOWN) }));
+        assert!(!readable(&AppPayload::Forward { body: ext(UNKNOWN) }));
+        assert!(!readable(&AppPayload::Revise { target: [0; 16], body: ext(UNKNOWN) }));
+        assert!(readable(&AppPayload::Post { reply_to: None, body: Body::Text("hi".into()) }));
+        assert!(readable(&AppPayload::PairAck));
+    }
+
+    #[test]
+    fn kept_rows_wait_for_a_build_that_reads_more() {
+        const NOW: u64 = 100;
+        let conn = open_in_memory();
+        assert!(keep_in(&conn, &row(9, 20), NOW).unwrap());
+        assert!(keep_in(&conn, &row(8, 10), NOW).unwrap());
+        // A redelivery keeps the first copy.
+        assert!(!keep_in(&conn, &Unsupported { plaintext: vec![0], ..row(9, 20) }, NOW).unwrap());
+        // This build stored them, so this build has nothing new to try.
+        assert!(untried_in(&conn, NOW).is_empty());
+
+        // A build that only bumped its version reads the same: the stamp
+        // doesn't name the crate version at all.
+        assert!(!reads().contains(env!("CARGO_PKG_VERSION")));
+        // Rows from before the fingerprint carry a crate version instead.
+        conn.execute("UPDATE unsupported_payloads SET kept_by = '0.1.0'", []).unwrap();
+        assert_eq!(untried_in(&conn, NOW), vec![row(8, 10), row(9, 20)], "oldest first, intact");
+    }
+
+    #[test]
+    fn the_stamp_moves_with_what_the_build_reads() {
+        let stamp = reads();
+        assert!(stamp.contains(&format!(".p{APP_PAYLOAD_VARIANTS}.")));
+        assert!(stamp.contains(&format!(".b{BODY_VARIANTS}.")));
+        assert!(stamp.ends_with(&format!(".x{EXT_RECOVERY_SHARE},{EXT_RECOVERY_REVOKE}")));
+    }
+
+    #[test]
+    fn oversized_and_overflowing_senders_keep_nothing() {
+        const NOW: u64 = 100;
+        let conn = open_in_memory();
+        let big = Unsupported { plaintext: vec![0; MAX_KEPT_BYTES + 1], ..row(1, 10) };
+        assert!(!keep_in(&conn, &big, NOW).unwrap());
+
+        for n in 0..MAX_KEPT_PER_SENDER {
+            let mut u = row(0, 10);
+            u.dispatch_id[..8].copy_from_slice(&(n as u64).to_le_bytes());
+            assert!(keep_in(&conn, &u, NOW).unwrap());
+        }
+        let mut over = row(0, 10);
+        over.dispatch_id = [0xee; 16];
+        assert!(!keep_in(&conn, &over, NOW).unwrap(), "this sender is at their cap");
+        // Someone else's payload still gets in.
+        assert!(keep_in(&conn, &Unsupported { from: [7; 32], ..over }, NOW).unwrap());
+    }
+
+    #[test]
+    fn rows_expire_after_waiting_long_enough() {
+        let conn = open_in_memory();
+        keep_in(&conn, &row(1, 10), 10).unwrap();
+        keep_in(&conn, &row(2, 20), 20).unwrap();
+        conn.execute("UPDATE unsupported_payloads SET kept_by = 'older'", []).unwrap();
+
+        assert_eq!(untried_in(&conn, 10 + KEEP_FOR_MS).len(), 2, "not past the window yet");
+        assert_eq!(untried_in(&conn, 15 + KEEP_FOR_MS), vec![row(2, 20)]);
+        let left: i64 = conn
+            .query_row("SELECT COUNT(*) FROM unsupported_payloads", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(left, 1, "the expired row is gone, not just skipped");
+    }
+}
@@ -40,6 +40,35 @@ const MIGRATION_ARRAY: &[M] = &[
             unusable_at_ms INTEGER NOT NULL
         );",
     ),
+    // Keys this identity has rotated away from, newest last (see
+    // `crate::succession`). `succession` is the signed statement, re-sent until
+    // `announced_at` is set; `enc_seal` the retired key's sealed-sender secret,
+    // kept so dispatches still in flight to it can be opened.
+    M::up(
+        "CREATE TABLE predecessor (
+            ipk          BLOB PRIMARY KEY CHECK(length(ipk) = 32),
+            succession   BLOB NOT NULL,
+            enc_seal     BLOB NOT NULL,
+            retired_at   INTEGER NOT NULL,
+            announced_at INTEGER
+        );",
+    ),
+    // A rotation moves our local state over several databases; `moved_at` is
+    // set once all of it has, so one cut short is finished on the next start.
+    // `reshared_at` marks our recovery shares split anew for the new key, and
+    // `succession_told` which contacts already have the statement.
+    M::up(
+        "ALTER TABLE predecessor ADD COLUMN moved_at INTEGER;
+        UPDATE predecessor SET moved_at = retired_at;
+        ALTER TABLE predecessor ADD COLUMN reshared_at INTEGER;
+        UPDATE predecessor SET reshared_at = announced_at;
+        CREATE TABLE succession_told (
+            old_ipk BLOB NOT NULL CHECK(length(old_ipk) = 32),
+            peer    BLOB NOT NULL CHECK(length(peer) = 32),
+            told_at INTEGER NOT NULL,
+            PRIMARY KEY (old_ipk, peer)
+        ) WITHOUT ROWID;",
+    ),
 ];
 const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATION_ARRAY);
 
@@ -49,3 +78,10 @@ pub static IDENTITY_DB: Lazy<Mutex<Connection>> = Lazy::new(|| {
 
     Mutex::new(conn)
 });
+
+#[cfg(test)]
+pub(crate) fn open_in_memory() -> Connection {
+    let mut conn = Connection::open_in_memory().expect("open in-memory db");
+    PRAGMA!(conn, MIGRATIONS);
+    conn
+}
@@ -44,8 +44,11 @@ pub struct MessageRow {
     /// 0 for an ordinary message, else a `SYSTEM_*` code narrating a
     /// membership or title change. On a system row `sender_ipk` is who acted
     /// and `content` names the target — a hex IPK for the membership events,
-    /// the new title for a rename.
+    /// the new title for a rename. [`SYSTEM_UNSUPPORTED`] stands in for a
+    /// message this build can't read yet; its `content` is empty.
     pub system: u8,
+    /// Passed along from another chat rather than written here.
+    pub forwarded: bool,
 }
 
 /// Not a system row — an ordinary message.
@@ -54,8 +57,11 @@ pub const SYSTEM_ADDED: u8 = 1;
 pub const SYSTEM_LEFT: u8 = 2;
 pub const SYSTEM_REMOVED: u8 = 3;
 pub const SYSTEM_TITLED: u8 = 4;
+/// A message from `sender_ipk` that needs a newer build to show. Replaced by
+/// the real row once one reads it (see `data::unsupported`).
+pub const SYSTEM_UNSUPPORTED: u8 = 5;
 
-from_row!(MessageRow { id, conversation_id, sender_ipk, content, outgoing, timestamp, status, dispatch_id, edited, deleted, reply_to, system });
+from_row!(MessageRow { id, conversation_id, sender_ipk, content, outgoing, timestamp, status, dispatch_id, edited, deleted, reply_to, system, forwarded });
 
 /// One emoji reaction on a message. Keyed by `reactor` (an IPK, not a
 /// me/them bool) so a multi-member group attributes each reaction to its
@@ -383,6 +389,114 @@ const MIGRATION_ARRAY: &[M] = &[
              value TEXT NOT NULL \
          ) WITHOUT ROWID;",
     ),
+    // Voice notes: how long, and the peaks the bubble draws before the audio
+    // is there. Duration is kind-neutral so other timed media can share it.
+    M::up(
+        "ALTER TABLE message_media ADD COLUMN duration_ms INTEGER NOT NULL DEFAULT 0; \
+         ALTER TABLE message_media ADD COLUMN waveform BLOB;",
+    ),
+    // Sticker packs. A sticker's media row names a pack and an index; the pack
+    // itself is a signed file under `files/stickers`, its id a hash of its
+    // content. Offers are members saying they'll serve a pack we lack, kept
+    // until the pull lands and the pack installs.
+    M::up(
+        "ALTER TABLE message_media ADD COLUMN sticker_pack BLOB; \
+         ALTER TABLE message_media ADD COLUMN sticker_id INTEGER NOT NULL DEFAULT 0; \
+         CREATE INDEX idx_message_media_sticker_pack ON message_media(sticker_pack); \
+         CREATE TABLE sticker_packs ( \
+             pack_id      BLOB PRIMARY KEY CHECK(length(pack_id) = 16), \
+             title        TEXT NOT NULL, \
+             author       BLOB NOT NULL CHECK(length(author) = 32), \
+             count        INTEGER NOT NULL, \
+             path         TEXT NOT NULL, \
+             file_id      BLOB NOT NULL CHECK(length(file_id) = 32), \
+             installed_at INTEGER NOT NULL \
+         ) WITHOUT ROWID; \
+         CREATE TABLE sticker_pack_offers ( \
+             file_id         BLOB PRIMARY KEY CHECK(length(file_id) = 32), \
+             pack_id         BLOB NOT NULL CHECK(length(pack_id) = 16), \
+             sender          BLOB NOT NULL CHECK(length(sender) = 32), \
+             conversation_id BLOB NOT NULL, \
+             size            INTEGER NOT NULL, \
+             offered_at      INTEGER NOT NULL \
+         ) WITHOUT ROWID;",
+    ),
+    // Polls. The question is the message's content; options and rules sit
+    // beside it, keyed like a media row. A vote is one row per member,
+    // replaced whole by their next one; `choices` is a bitmask over options.
+    M::up(
+        "CREATE TABLE polls ( \
+             conversation_id BLOB NOT NULL, \
+             dispatch_id     BLOB NOT NULL, \
+             question        TEXT NOT NULL, \
+             options         BLOB NOT NULL, \
+             multi           INTEGER NOT NULL, \
+             anonymous       INTEGER NOT NULL, \
+             closes_at       INTEGER, \
+             closed_at       INTEGER, \
+             PRIMARY KEY (conversation_id, dispatch_id) \
+         ) WITHOUT ROWID; \
+         CREATE TABLE poll_votes ( \
+             conversation_id BLOB NOT NULL, \
+             dispatch_id     BLOB NOT NULL, \
+             voter           BLOB NOT NULL CHECK(length(voter) = 32), \
+             choices         INTEGER NOT NULL, \
+             voted_at        INTEGER NOT NULL, \
+             PRIMARY KEY (conversation_id, dispatch_id, voter) \
+         ) WITHOUT ROWID;",
+    ),
+    M::up("ALTER TABLE messages ADD COLUMN forwarded INTEGER NOT NULL DEFAULT 0;"),
+    // Decrypted payloads this build couldn't read, kept until one can (see
+    // `data::unsupported`). `sender` sent the dispatch, `author` wrote it.
+    M::up(
+        "CREATE TABLE unsupported_payloads ( \
+             conversation_id BLOB NOT NULL, \
+             dispatch_id     BLOB NOT NULL CHECK(length(dispatch_id) = 16), \
+             sender          BLOB NOT NULL CHECK(length(sender) = 32), \
+             author          BLOB NOT NULL CHECK(length(author) = 32), \
+             accepted_at_ms  INTEGER NOT NULL, \
+             plaintext       BLOB NOT NULL, \
+             kept_by         TEXT NOT NULL, \
+             PRIMARY KEY (conversation_id, dispatch_id) \
+         ) WITHOUT ROWID;",
+    ),
+    // Per-conversation progress of getting back into its MLS group after a
+    // restore (see `crate::rejoin`). A row exists only while one was needed.
+    M::up(
+        "CREATE TABLE rejoins ( \
+             conversation_id BLOB PRIMARY KEY, \
+             state           INTEGER NOT NULL, \
+             detail          TEXT NOT NULL DEFAULT '', \
+             updated_at      INTEGER NOT NULL \
+         ) WITHOUT ROWID;",
+    ),
+    // Votes that overtook their poll, held until it lands (see `data::poll`).
+    // `choices` is the ballot as sent, postcard-encoded: without the poll
+    // there is nothing yet to check it against or fold it into a mask.
+    M::up(
+        "CREATE TABLE poll_votes_early ( \
+             conversation_id BLOB NOT NULL, \
+             dispatch_id     BLOB NOT NULL, \
+             voter           BLOB NOT NULL CHECK(length(voter) = 32), \
+             choices         BLOB NOT NULL, \
+             voted_at        INTEGER NOT NULL, \
+             PRIMARY KEY (conversation_id, dispatch_id, voter) \
+         ) WITHOUT ROWID;",
+    ),
+    // The suite a rejoining group ran on, so founding it again can't move it
+    // onto a weaker one. NULL when the backup predates recording it.
+    M::up("ALTER TABLE rejoins ADD COLUMN suite INTEGER;"),
+    // Newest rejoin request acted on per requester and group, and when (see
+    // `crate::rejoin`). Kept on disk so a restart doesn't reopen either.
+    M::up(
+        "CREATE TABLE rejoin_requests ( \
+             requester BLOB NOT NULL CHECK(length(requester) = 32), \
+             group_id  BLOB NOT NULL CHECK(length(group_id) = 32), \
+             timestamp INTEGER NOT NULL, \
+             acted_at  INTEGER NOT NULL, \
+             PRIMARY KEY (requester, group_id) \
+         ) WITHOUT ROWID;",
+    ),
 ];
 /// A migration's index in the array *is* its schema version, so the array is
 /// append-only: inserting one shifts every later version, and a device already
@@ -399,6 +513,10 @@ pub static MESSAGES_DB: Lazy<Mutex<Connection>> = Lazy::new(|| {
         "conversations",
         "conversation_members",
         "peer_names",
+        "sticker_packs",
+        "polls",
+        "poll_votes",
+        "rejoins",
     ]);
 
     Mutex::new(conn)
@@ -88,7 +88,7 @@ const MIGRATION_ARRAY: &[M] = &[
     //
     // Columns:
     // - `kp_ref`: RFC 9420 §5.2 KeyPackageRef (label-prefixed SHA-256
-    //   over the TLS-encoded KP under suite 0x0003). PRIMARY KEY
+    //   over the TLS-encoded KP under its suite). PRIMARY KEY
     //   because it's globally unique.
     // - `generated_at_ms`: when the client minted this KP. Drives the
     //   anti-pinning rotation cadence (`KP_SCHEDULED_ROTATION_MS`).
@@ -173,6 +173,35 @@ const MIGRATION_ARRAY: &[M] = &[
         ALTER TABLE mls_epoch_ahead ADD COLUMN accepted_at_ms INTEGER NOT NULL DEFAULT 0;
     "#,
     ),
+    // Welcomes into a successor group (`groups::found_successor`), each
+    // member's built before any goes out. The conversation moves onto the
+    // successor only once every row is `delivered`; until then a failed
+    // delivery is retried, and the rows go when the move is done.
+    M::up(
+        r#"--sql
+        CREATE TABLE successor_welcomes (
+            group_id     BLOB NOT NULL,
+            conversation BLOB NOT NULL,
+            member       BLOB NOT NULL,
+            envelope     BLOB NOT NULL,
+            delivered    INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (group_id, member)
+        );
+    "#,
+    ),
+    // When a 1:1 group on the classical suite next looks for a hybrid-suite
+    // KeyPackage of its peer's (`messaging::upgrade_pair_group`), and the
+    // gap to wait after that. Each look spends one of the peer's KeyPackages,
+    // so the gap doubles while they stay on an older build.
+    M::up(
+        r#"--sql
+        CREATE TABLE suite_probes (
+            group_id   BLOB PRIMARY KEY,
+            next_at_ms INTEGER NOT NULL,
+            gap_ms     INTEGER NOT NULL
+        );
+    "#,
+    ),
 ];
 const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATION_ARRAY);
 
@@ -30,6 +30,60 @@ impl TryFrom<String> for CircuitState {
     }
 }
 
+/// How the client reaches a relay. Stored per relay as the last one that
+/// worked (or the next one to try); see [`Transport::fallback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// Plain QUIC on the relay's UDP port.
+    Quic,
+    /// QUIC inside TLS on the relay's TCP tunnel port.
+    Tls,
+    /// QUIC inside a WebSocket through a configured bridge (possibly
+    /// domain-fronted).
+    WebSocket,
+}
+
+impl Transport {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Quic => "quic",
+            Self::Tls => "tls",
+            Self::WebSocket => "websocket",
+        }
+    }
+
+    /// What to try after a dial over `self` failed: UDP first, then the TCP
+    /// fallback, then the bridge if the relay has one, then round again —
+    /// a network that blocked UDP an hour ago may not now.
+    pub fn fallback(self, bridged: bool) -> Self {
+        match self {
+            Self::Quic => Self::Tls,
+            Self::Tls if bridged => Self::WebSocket,
+            Self::Tls | Self::WebSocket => Self::Quic,
+        }
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for Transport {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        match s.as_str() {
+            "quic" => Ok(Self::Quic),
+            "tls" => Ok(Self::Tls),
+            "websocket" => Ok(Self::WebSocket),
+            other => bail!("unknown transport: {}", other),
+        }
+    }
+}
+
 const MIGRATION_ARRAY: &[M] = &[
     M::up(
         r#"--sql
@@ -97,6 +151,41 @@ const MIGRATION_ARRAY: &[M] = &[
             );
         "#,
     ),
+    // Newest resolver-signed relay directory, stored as received so it can
+    // be re-verified on load and handed on to a contact.
+    M::up(
+        r#"--sql
+            CREATE TABLE relay_directory (
+              singleton  INTEGER PRIMARY KEY CHECK(singleton = 1),
+              issued_at  INTEGER NOT NULL,
+              expires_at INTEGER NOT NULL,
+              directory  BLOB NOT NULL
+            );
+        "#,
+    ),
+    // Per-relay transport: the one the next dial uses. Advanced on each dial
+    // failure and kept on success, so a relay reached over TLS stays on TLS.
+    // `relay_bridges` is user-configured and deliberately not keyed by FK: a
+    // relay forgotten and re-resolved keeps its bridge.
+    M::up(
+        r#"--sql
+            ALTER TABLE relays ADD COLUMN transport TEXT NOT NULL DEFAULT 'quic'
+                CHECK(transport IN ('quic', 'tls', 'websocket'));
+            CREATE TABLE relay_bridges (
+              relay_id TEXT PRIMARY KEY,
+              url      TEXT NOT NULL,
+              front    TEXT
+            );
+        "#,
+    ),
+    // The relay's tunnel listener port, as advertised through the resolver's
+    // `GetTunnels`. NULL until one is learned; dials then assume the default.
+    M::up(
+        r#"--sql
+            ALTER TABLE relays ADD COLUMN tunnel_port INTEGER
+                CHECK(tunnel_port IS NULL OR tunnel_port BETWEEN 1 AND 65535);
+        "#,
+    ),
 ];
 const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATION_ARRAY);
 
@@ -106,3 +195,24 @@ pub static NETWORK_DB: Lazy<Mutex<Connection>> = Lazy::new(|| {
 
     Mutex::new(conn)
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transport_fallback_tries_the_bridge_only_when_there_is_one() {
+        assert_eq!(Transport::Quic.fallback(false), Transport::Tls);
+        assert_eq!(Transport::Tls.fallback(false), Transport::Quic);
+        assert_eq!(Transport::Tls.fallback(true), Transport::WebSocket);
+        // The bridge failing too starts the round again from UDP.
+        assert_eq!(Transport::WebSocket.fallback(true), Transport::Quic);
+    }
+
+    #[test]
+    fn transport_round_trips_through_its_column_value() {
+        for t in [Transport::Quic, Transport::Tls, Transport::WebSocket] {
+            assert_eq!(Transport::try_from(t.as_str().to_owned()).unwrap(), t);
+        }
+    }
+}
@@ -69,13 +69,141 @@ const MIGRATION_ARRAY: &[M] = &[
     // mark_rejected gated on group-presence): a live MLS group is proof of a
     // working pair, so restore PAIRED and clear the stale reason.
     M::up("UPDATE contacts SET status = 1, reject_reason = NULL WHERE status = 2 AND mls_group_id IS NOT NULL;"),
+    // Sealed-sender delivery tokens (`crate::sealed`): the one each contact
+    // issued us, and when the one we last issued them expires.
+    M::up(
+        r#"
+        CREATE TABLE delivery_tokens (
+            peer BLOB PRIMARY KEY CHECK(length(peer) = 32),
+            token BLOB NOT NULL
+        );
+        CREATE TABLE delivery_grants (
+            peer BLOB PRIMARY KEY CHECK(length(peer) = 32),
+            expires_at INTEGER NOT NULL
+        );
+        "#,
+    ),
+    // Blinded mailboxes (`crate::mailbox`): the seed each contact shared so
+    // we can compute their mailbox ids, with the tokens valid at those ids.
+    M::up(
+        r#"
+        CREATE TABLE mailbox_grants (
+            peer BLOB PRIMARY KEY CHECK(length(peer) = 32),
+            seed BLOB NOT NULL CHECK(length(seed) = 32),
+            tokens BLOB NOT NULL
+        );
+        "#,
+    ),
+    // Social recovery (`crate::social_recovery`). `recovery_held`: the share
+    // each contact gave us to keep. `recovery_holders`: whom we gave ours.
+    // `recovery_asks`: fresh installs asking for a share, awaiting our user.
+    // `recovery_session`: on a fresh install, whom we asked and what came back.
+    M::up(
+        r#"
+        CREATE TABLE recovery_held (
+            owner BLOB PRIMARY KEY CHECK(length(owner) = 32),
+            share BLOB NOT NULL,
+            received_at INTEGER NOT NULL
+        );
+        CREATE TABLE recovery_holders (
+            holder BLOB PRIMARY KEY CHECK(length(holder) = 32),
+            set_id BLOB NOT NULL CHECK(length(set_id) = 16),
+            idx INTEGER NOT NULL,
+            threshold INTEGER NOT NULL,
+            sent_at INTEGER NOT NULL
+        );
+        CREATE TABLE recovery_asks (
+            asker BLOB PRIMARY KEY CHECK(length(asker) = 32),
+            request BLOB NOT NULL,
+            received_at INTEGER NOT NULL
+        );
+        CREATE TABLE recovery_session (
+            holder BLOB PRIMARY KEY CHECK(length(holder) = 32),
+            asked_at INTEGER NOT NULL,
+            owner BLOB CHECK(owner IS NULL OR length(owner) = 32),
+            share BLOB
+        );
+        "#,
+    ),
+    // Contacts who rotated their key (`crate::succession`): which key each
+    // retired key became, with the statement both signed.
+    M::up(
+        r#"
+        CREATE TABLE successions (
+            old_ipk     BLOB PRIMARY KEY CHECK(length(old_ipk) = 32),
+            new_ipk     BLOB NOT NULL CHECK(length(new_ipk) = 32),
+            statement   BLOB NOT NULL,
+            received_at INTEGER NOT NULL
+        );
+        CREATE INDEX idx_successions_new ON successions(new_ipk);
+        "#,
+    ),
+    // Every mailbox token we granted (`crate::sealed`), by id and the epoch
+    // whose mailbox it verifies under, so a forgotten contact's can be revoked.
+    M::up(
+        r#"
+        CREATE TABLE issued_tokens (
+            id         BLOB PRIMARY KEY CHECK(length(id) = 16),
+            peer       BLOB NOT NULL CHECK(length(peer) = 32),
+            epoch      INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            revoked    INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_issued_tokens_peer ON issued_tokens(peer);
+        "#,
+    ),
+    // Tokens granted on the IPK itself, for contacts whose build predates
+    // mailboxes; `epoch` is then only the epoch it was granted in.
+    M::up("ALTER TABLE issued_tokens ADD COLUMN on_ipk INTEGER NOT NULL DEFAULT 0;"),
+    // A holder can have a share of the current split and an older one still to
+    // revoke, so `recovery_holders` keys on both, and `revoking` marks the
+    // shares that are only waiting for their revoke to go out.
+    M::up(
+        r#"
+        CREATE TABLE recovery_holders_v2 (
+            holder BLOB NOT NULL CHECK(length(holder) = 32),
+            set_id BLOB NOT NULL CHECK(length(set_id) = 16),
+            idx INTEGER NOT NULL,
+            threshold INTEGER NOT NULL,
+            sent_at INTEGER NOT NULL,
+            revoking INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (holder, set_id)
+        );
+        INSERT INTO recovery_holders_v2 (holder, set_id, idx, threshold, sent_at)
+            SELECT holder, set_id, idx, threshold, sent_at FROM recovery_holders;
+        DROP TABLE recovery_holders;
+        ALTER TABLE recovery_holders_v2 RENAME TO recovery_holders;
+        "#,
+    ),
+    // A contact's old key that named a second heir is in two hands. Every
+    // statement for it, the one `successions` took included, waits here until
+    // the user says which heir is the contact; neither is sent anything.
+    M::up(
+        r#"
+        CREATE TABLE contested_successions (
+            old_ipk     BLOB NOT NULL CHECK(length(old_ipk) = 32),
+            new_ipk     BLOB NOT NULL CHECK(length(new_ipk) = 32),
+            statement   BLOB NOT NULL,
+            received_at INTEGER NOT NULL,
+            PRIMARY KEY (old_ipk, new_ipk)
+        ) WITHOUT ROWID;
+        CREATE INDEX idx_contested_successions_new ON contested_successions(new_ipk);
+        "#,
+    ),
 ];
 const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATION_ARRAY);
 
 pub static CONTACTS_DB: Lazy<Mutex<Connection>> = Lazy::new(|| {
     let mut conn = Connection::open(super::db("contacts")).expect("db open failed");
     PRAGMA!(conn, MIGRATIONS);
-    super::register_change_hook(&conn, &["contacts"]);
+    super::register_change_hook(&conn, &[
+        "contacts",
+        "recovery_held",
+        "recovery_holders",
+        "recovery_asks",
+        "recovery_session",
+        "contested_successions",
+    ]);
 
     Mutex::new(conn)
 });
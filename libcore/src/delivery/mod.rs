@@ -43,7 +43,13 @@ pub fn outcome_for_ack(ack: &DispatchAckP) -> LastOutcome {
         DispatchAckP::Forwarded { .. }
         | DispatchAckP::Delivered { .. }
         | DispatchAckP::Queued { .. } => Durable,
-        DispatchAckP::QueueFull | DispatchAckP::Error { .. } => Reachable,
+        // Quota refusals clear once the recipient drains, so keep retrying.
+        DispatchAckP::QueueFull | DispatchAckP::QuotaExceeded | DispatchAckP::Error { .. } => {
+            Reachable
+        },
+        // The token is dropped on sight (`sealed::note_ack`); the retry goes
+        // identified. Likewise a sealed dispatch no home could take.
+        DispatchAckP::TokenRejected | DispatchAckP::SealedUnroutable => Reachable,
         DispatchAckP::NotFound | DispatchAckP::InvalidSig => Terminal,
     }
 }
@@ -226,6 +232,10 @@ pub async fn reconcile() {
         let op = OpType::from_u8(row.op_type).unwrap_or(OpType::Message);
         let target: Option<[u8; 32]> =
             row.target_ipk.as_ref().and_then(|t| t.as_slice().try_into().ok());
+        // Held, not failed, until the user settles which heir is the contact.
+        if target.is_some_and(|to| crate::data::succession::is_contested(&to)) {
+            continue;
+        }
         let mut accepted_timestamp = None;
         let outcome = match op {
             OpType::KpPublish => {
@@ -242,18 +252,23 @@ pub async fn reconcile() {
                 }
             },
             // Message/Welcome ride the framed-Dispatch stream. Re-send the STORED
-            // framed bytes verbatim (already `.pack()`-framed from Task 6). Any
+            // framed bytes verbatim (already `.pack()`-framed from Task 6),
+            // sealed afresh when we hold the member's token. Any
             // open/write/finish/read error, or a non-DispatchAck reply, reads as
             // Silence (transport drop / no answer).
             _ => match conn.open_bi().await {
                 Ok((mut send, mut recv)) => {
-                    if send.write_all(&row.payload).await.is_ok()
+                    let (wire, sealed) = crate::sealed::seal_frame(&row.payload);
+                    if send.write_all(&wire).await.is_ok()
                         && send.finish().is_ok()
                         && let Ok(SRelayPacket::DispatchAck(ack)) =
                             SRelayPacket::unpack(&mut recv).await
                     {
                         accepted_timestamp = accepted_at_secs(&ack);
-                        outcome_for_ack(&ack)
+                        match &target {
+                            Some(to) => crate::sealed::note_ack(to, &ack, sealed),
+                            None => outcome_for_ack(&ack),
+                        }
                     } else {
                         LastOutcome::Silence
                     }
@@ -341,7 +356,10 @@ mod tests {
         assert!(matches!(outcome_for_ack(&DispatchAckP::Forwarded { accepted_at_ms: 1 }), Durable));
         assert!(matches!(outcome_for_ack(&DispatchAckP::Queued { accepted_at_ms: 1 }), Durable));
         assert!(matches!(outcome_for_ack(&DispatchAckP::QueueFull), Reachable));
+        assert!(matches!(outcome_for_ack(&DispatchAckP::QuotaExceeded), Reachable));
         assert!(matches!(outcome_for_ack(&DispatchAckP::Error { reason: String::new() }), Reachable));
+        assert!(matches!(outcome_for_ack(&DispatchAckP::TokenRejected), Reachable));
+        assert!(matches!(outcome_for_ack(&DispatchAckP::SealedUnroutable), Reachable));
         assert!(matches!(outcome_for_ack(&DispatchAckP::NotFound), Terminal));
         assert!(matches!(outcome_for_ack(&DispatchAckP::InvalidSig), Terminal));
     }
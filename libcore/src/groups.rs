@@ -19,9 +19,15 @@ use anyhow::anyhow;
 use anyhow::bail;
 use common::proto::mls_wire::AppPayload;
 use common::proto::mls_wire::SystemEvent;
+use common::proto::mls_wire::WelcomeEnvelopeP;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
 use ed25519_dalek::SigningKey;
 use log::info;
 use log::warn;
+use openmls::prelude::Ciphersuite;
+use openmls::prelude::KeyPackage;
+use rusqlite::Connection;
 
 use crate::data::conversation::Conversation;
 use crate::data::conversation::KIND_GROUP;
@@ -29,7 +35,9 @@ use crate::data::identity::Identity;
 use crate::mls::EpochCatchupBuffer;
 use crate::mls::KeyPackageStash;
 use crate::mls::MlsGroupHandle;
+use crate::mls::PROMTUZ_CIPHERSUITE;
 use crate::mls::PromtuzMlsProvider;
+use crate::mls::group::negotiate_ciphersuite;
 use crate::db::mls::stash_db_handle;
 use crate::db::outbox::OpType;
 use crate::messaging::MlsContext;
@@ -85,14 +93,10 @@ pub async fn create_group(title: String, members: Vec<[u8; 32]>) -> Result<[u8;
     with_mls!(ctx, {
         // Every member's KeyPackage first: a member who has never published one
         // can't be added, and finding that out after minting the group would
-        // leave a half-built group behind.
-        let mut kps = Vec::with_capacity(members.len());
-        for m in &members {
-            let (kp, kp_ref) = crate::messaging::fetch_verified_keypackage(&ctx, m)
-                .await
-                .map_err(|e| no_keys_error(m, e))?;
-            kps.push((*m, kp, kp_ref));
-        }
+        // leave a half-built group behind. A member still on an older app
+        // decides the suite for the group.
+        let (suite, kps) = member_keypackages(&ctx, &members, None).await?;
+        let member_kps = kps.iter().map(|(_, kp, _)| kp.clone()).collect::<Vec<_>>();
 
         let group_id = crate::messaging::mint_group_id(&our_ipk);
         let (leaf_kp, _cwk) = crate::messaging::build_self_credential(&our_ipk)
@@ -110,13 +114,14 @@ pub async fn create_group(title: String, members: Vec<[u8; 32]>) -> Result<[u8;
             leaf_kp.public(),
             &group_id,
             Some(&meta),
+            suite,
         )
         .map_err(|e| anyhow!("create group: {e}"))?;
 
         // One Commit adds everyone, and one Welcome covers them all — each
         // joiner finds their own secret inside it.
         let (_commit, welcome) = group
-            .add_members(ctx.provider, &leaf_kp, &kps.iter().map(|(_, kp, _)| kp.clone()).collect::<Vec<_>>())
+            .add_members(ctx.provider, &leaf_kp, &member_kps)
             .map_err(|e| anyhow!("add_members: {e}"))?;
 
         for (member, _, kp_ref) in &kps {
@@ -166,14 +171,28 @@ pub async fn add_member(conversation: [u8; 16], who: [u8; 32]) -> Result<()> {
     }
 
     with_mls!(ctx, {
-        let (kp, kp_ref) = crate::messaging::fetch_verified_keypackage(&ctx, &who)
-            .await
-            .map_err(|e| no_keys_error(&who, e))?;
         let mut group = load_group(ctx.provider, &group_id)?;
-
         if group.member_count() + 1 > crate::mls::MAX_GROUP_MEMBERS {
             bail!("a group is limited to {} members", crate::mls::MAX_GROUP_MEMBERS);
         }
+        if group.ciphersuite() == PROMTUZ_CIPHERSUITE
+            && group.member_count() + 1 > crate::mls::MAX_PQ_GROUP_MEMBERS
+        {
+            bail!(
+                "a group on the strongest encryption is limited to {} members",
+                crate::mls::MAX_PQ_GROUP_MEMBERS
+            );
+        }
+        let (kp, kp_ref) =
+            crate::messaging::fetch_keypackage_on(&ctx, &who, group.ciphersuite())
+                .await
+                .map_err(|e| no_keys_error(&who, e))?;
+        if kp.ciphersuite() != group.ciphersuite() {
+            // A KeyPackage joins only a group on its own suite, and theirs
+            // doesn't even declare this one.
+            bail!("their app is out of date; ask them to update before adding them");
+        }
+
         // Existing members apply this Commit at the epoch it was built in, so
         // capture that before the merge moves us on.
         let commit_epoch = group.epoch();
@@ -232,9 +251,7 @@ pub async fn remove_member(conversation: [u8; 16], who: [u8; 32]) -> Result<()>
 
     with_mls!(ctx, {
         let mut group = load_group(ctx.provider, &group_id)?;
-        let idx = group
-            .member_index_by_ipk(&who)
-            .ok_or_else(|| anyhow!("that member is not in this group"))?;
+        let idx = member_index(&group, &who)?;
 
         // Address the Commit to the roster as it stands *now*, the removed
         // member included: they need it to learn they're out, and everyone
@@ -311,6 +328,385 @@ pub async fn leave(conversation: [u8; 16]) -> Result<()> {
     })
 }
 
+/// Move a group founded on the classical suite onto the PQ-hybrid one.
+///
+/// A group's suite is fixed for its life, so this founds a successor group on
+/// [`PROMTUZ_CIPHERSUITE`] with the same members and meta, naming the old
+/// group id in its context. Joiners see that and carry the existing
+/// conversation over instead of opening a new one. Every member must already
+/// run a build that mints PQ KeyPackages; one who doesn't blocks the upgrade
+/// until they update. So does a group too big for an X-Wing Welcome.
+pub async fn upgrade_suite(conversation: [u8; 16]) -> Result<()> {
+    let (our_ipk, ipk_signer) = local_signer()?;
+    require_admin(&conversation, &our_ipk)?;
+    let old_id = require_group(&conversation)?;
+    let members = Conversation::recipients(&conversation);
+    if members.len() + 1 > crate::mls::MAX_PQ_GROUP_MEMBERS {
+        bail!(
+            "the strongest encryption is limited to groups of {} members",
+            crate::mls::MAX_PQ_GROUP_MEMBERS
+        );
+    }
+
+    with_mls!(ctx, {
+        let old = load_group(ctx.provider, &old_id)?;
+        if old.ciphersuite() == PROMTUZ_CIPHERSUITE {
+            bail!("this group already uses the strongest encryption available");
+        }
+        let meta = old.group_meta().ok_or_else(|| anyhow!("not a group"))?;
+        let plan = SuccessorPlan {
+            conversation,
+            old_id,
+            meta: &meta,
+            suite: Some(PROMTUZ_CIPHERSUITE),
+            members,
+        };
+        found_successor(&ctx, &plan, &our_ipk, &ipk_signer).await?;
+        // The old group is kept: anything already in flight on it still has
+        // to decrypt.
+        info!("GROUP: moved {} onto the PQ-hybrid suite", hex::encode(&conversation[..4]));
+        Ok(())
+    })
+}
+
+/// Found the group again after our state for it was lost (a restore from
+/// backup). We run it, so nobody can re-add us: instead we found a successor
+/// naming the old group, exactly as [`upgrade_suite`] does, and the members
+/// carry their conversation over to it. The suite is the one the group ran
+/// on, as the backup recorded it; letting the members' KeyPackages pick
+/// could move a PQ group onto the classical suite. A backup from before
+/// suites were recorded is taken as the hybrid suite, so not knowing can only
+/// move a group up.
+pub(crate) async fn refound(conversation: [u8; 16]) -> Result<()> {
+    let (our_ipk, ipk_signer) = local_signer()?;
+    require_admin(&conversation, &our_ipk)?;
+    let old_id = require_group(&conversation)?;
+    let members = Conversation::recipients(&conversation);
+    if members.is_empty() {
+        bail!("nobody else is left in this group");
+    }
+    let title = Conversation::get(&conversation).map(|c| c.title).unwrap_or_default();
+    let meta = crate::mls::GroupMeta { title, founder: our_ipk };
+    let suite = match crate::data::rejoin::get(&conversation).and_then(|r| r.suite) {
+        Some(s) => Ciphersuite::try_from(s).map_err(|_| anyhow!("unknown ciphersuite {s:#06x}"))?,
+        None => PROMTUZ_CIPHERSUITE,
+    };
+
+    with_mls!(ctx, {
+        let plan =
+            SuccessorPlan { conversation, old_id, meta: &meta, suite: Some(suite), members };
+        found_successor(&ctx, &plan, &our_ipk, &ipk_signer).await?;
+        info!("GROUP: re-founded {} after a restore", hex::encode(&conversation[..4]));
+        Ok(())
+    })
+}
+
+/// Let `who` back in after they lost their state for this group and asked
+/// (a `RejoinRequestP`): one Commit swaps their dead leaf for a fresh
+/// KeyPackage, and a Welcome brings them in at the new epoch. Like any add,
+/// nothing sent before it reaches them.
+pub(crate) async fn readmit(conversation: [u8; 16], who: [u8; 32]) -> Result<()> {
+    let (our_ipk, ipk_signer) = local_signer()?;
+    with_mls!(ctx, { readmit_on(&ctx, conversation, who, &our_ipk, &ipk_signer).await })
+}
+
+async fn readmit_on<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, conversation: [u8; 16], who: [u8; 32], our_ipk: &[u8; 32],
+    ipk_signer: &SigningKey,
+) -> Result<()> {
+    require_admin(&conversation, our_ipk)?;
+    let group_id = require_group(&conversation)?;
+    if !Conversation::members(&conversation).iter().any(|m| m.active && m.member_ipk == who) {
+        bail!("only a member of this group can be let back in");
+    }
+
+    let mut group = load_group(ctx.provider, &group_id)?;
+    let (kp, kp_ref) = crate::messaging::fetch_keypackage_on(ctx, &who, group.ciphersuite())
+        .await
+        .map_err(|e| no_keys_error(&who, e))?;
+    if kp.ciphersuite() != group.ciphersuite() {
+        bail!("their keys don't match this group's encryption; upgrade it first");
+    }
+    let idx = member_index(&group, &who)?;
+    let commit_epoch = group.epoch();
+    let (commit, welcome) = group
+        .swap_members(ctx.provider, &leaf_for(ctx.provider, &group, our_ipk)?, &[idx], &[kp])
+        .map_err(|e| anyhow!("swap_members: {e}"))?;
+
+    let env =
+        crate::mls::make_welcome_envelope(welcome, group_id, *our_ipk, who, kp_ref, ipk_signer)
+            .map_err(|e| anyhow!("make_welcome_envelope: {e}"))?;
+    ctx.dht.deliver_welcome(&env).await.map_err(|e| anyhow!("deliver_welcome: {e}"))?;
+
+    // Everyone but the returning member: their old leaf is gone, and the
+    // Welcome is what puts them at the new epoch.
+    let others: Vec<[u8; 32]> =
+        Conversation::recipients(&conversation).into_iter().filter(|m| *m != who).collect();
+    fan_out_commit_to(&others, &commit, group_id, commit_epoch, our_ipk, ipk_signer).await?;
+    group
+        .merge_pending_commit(ctx.provider)
+        .map_err(|e| anyhow!("merge_pending_commit: {e}"))?;
+    info!(
+        "GROUP: let {} back into {}",
+        hex::encode(&who[..4]),
+        hex::encode(&conversation[..4])
+    );
+    Ok(())
+}
+
+/// A successor group to found: the group it replaces, the conversation moving
+/// onto it, and who is in it.
+struct SuccessorPlan<'a> {
+    conversation: [u8; 16],
+    old_id:       [u8; 32],
+    meta:         &'a crate::mls::GroupMeta,
+    /// The suite every member must join on; `None` lets their KeyPackages
+    /// decide, as at creation.
+    suite:        Option<Ciphersuite>,
+    members:      Vec<[u8; 32]>,
+}
+
+/// Found the successor `plan` describes with every member Welcomed in, then
+/// repoint the conversation at it.
+///
+/// Every Welcome is built and kept before the first goes out, so one member
+/// being unreachable strands nobody: whoever already joined has a live group,
+/// the rest get theirs on a later reconnect ([`resume_successors`]), and the
+/// conversation moves only once all of them have. Until then we keep sending
+/// on the old group, which everyone still holds.
+async fn found_successor<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, plan: &SuccessorPlan<'_>, our_ipk: &[u8; 32],
+    ipk_signer: &SigningKey,
+) -> Result<()> {
+    if pending_successor_in(&ctx.provider.storage().conn().lock(), &plan.conversation).is_some() {
+        bail!("this group is already moving; it finishes once every member has been reached");
+    }
+    let group_id = stage_successor(ctx, plan, our_ipk, ipk_signer).await?;
+    if !deliver_successor(ctx, &group_id).await {
+        bail!("not every member could be reached yet; the change finishes once they are");
+    }
+    complete_successor(ctx.provider, &group_id, &plan.conversation)
+}
+
+/// Found the successor, add everyone, and keep a Welcome for each member in
+/// `successor_welcomes` — nothing is sent yet. Any failure up to the point
+/// the Welcomes are kept drops the successor again; none of it left the
+/// device.
+async fn stage_successor<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, plan: &SuccessorPlan<'_>, our_ipk: &[u8; 32],
+    ipk_signer: &SigningKey,
+) -> Result<[u8; 32]> {
+    let (suite, kps) = member_keypackages(ctx, &plan.members, plan.suite).await?;
+    let member_kps = kps.iter().map(|(_, kp, _)| kp.clone()).collect::<Vec<_>>();
+
+    let group_id = crate::messaging::mint_group_id(our_ipk);
+    let (leaf_kp, _cwk) = crate::messaging::build_self_credential(our_ipk)
+        .map_err(|e| anyhow!("build credential: {e}"))?;
+    leaf_kp.store(ctx.provider.storage()).map_err(|e| anyhow!("store leaf kp: {e:?}"))?;
+    let mut group = MlsGroupHandle::create_successor(
+        ctx.provider,
+        &leaf_kp,
+        our_ipk,
+        leaf_kp.public(),
+        &group_id,
+        suite,
+        (plan.meta, &plan.old_id),
+    )
+    .map_err(|e| anyhow!("create successor group: {e}"))?;
+
+    let staged = (|| {
+        let (_commit, welcome) = group
+            .add_members(ctx.provider, &leaf_kp, &member_kps)
+            .map_err(|e| anyhow!("add_members: {e}"))?;
+        let mut envelopes = Vec::with_capacity(kps.len());
+        for (member, _, kp_ref) in &kps {
+            let env = crate::mls::make_welcome_envelope(
+                welcome.clone(),
+                group_id,
+                *our_ipk,
+                *member,
+                *kp_ref,
+                ipk_signer,
+            )
+            .map_err(|e| anyhow!("make_welcome_envelope: {e}"))?;
+            envelopes.push((*member, env));
+        }
+        // Nobody else has this group yet, so there's no fan-out to wait for.
+        group
+            .merge_pending_commit(ctx.provider)
+            .map_err(|e| anyhow!("merge_pending_commit: {e}"))?;
+        let mut conn = ctx.provider.storage().conn().lock();
+        stage_welcomes_in(&mut conn, &group_id, &plan.conversation, &envelopes)
+    })();
+    if let Err(e) = staged {
+        if let Err(de) = group.delete(ctx.provider) {
+            warn!("GROUP: dropping an unsent successor also failed: {de}");
+        }
+        return Err(e);
+    }
+    Ok(group_id)
+}
+
+/// Send every staged Welcome of `group_id` that hasn't gone out yet. True
+/// once all of them have.
+async fn deliver_successor<C: DhtClient>(ctx: &MlsContext<'_, C>, group_id: &[u8; 32]) -> bool {
+    let pending = undelivered_welcomes_in(&ctx.provider.storage().conn().lock(), group_id);
+    let mut all = true;
+    for (member, env) in pending {
+        match ctx.dht.deliver_welcome(&env).await {
+            Ok(()) => {
+                let conn = ctx.provider.storage().conn().lock();
+                if let Err(e) = mark_delivered_in(&conn, group_id, &member) {
+                    warn!("GROUP: could not note a successor Welcome as sent: {e}");
+                }
+            },
+            Err(e) => {
+                warn!(
+                    "GROUP: successor Welcome to {} not delivered yet: {e}",
+                    hex::encode(&member[..4])
+                );
+                all = false;
+            },
+        }
+    }
+    all
+}
+
+/// Every member has the successor: move the conversation onto it and drop the
+/// staged Welcomes.
+fn complete_successor(
+    provider: &PromtuzMlsProvider, group_id: &[u8; 32], conversation: &[u8; 16],
+) -> Result<()> {
+    Conversation::bind_group(conversation, group_id)?;
+    provider
+        .storage()
+        .conn()
+        .lock()
+        .execute("DELETE FROM successor_welcomes WHERE group_id = ?1", [&group_id[..]])?;
+    Ok(())
+}
+
+/// Reconnect hook: finish every successor whose Welcomes didn't all go out
+/// when it was founded.
+pub(crate) async fn resume_successors<C: DhtClient>(ctx: &MlsContext<'_, C>) {
+    let pending = pending_successors_in(&ctx.provider.storage().conn().lock());
+    for (group_id, conversation) in pending {
+        if !deliver_successor(ctx, &group_id).await {
+            continue;
+        }
+        match complete_successor(ctx.provider, &group_id, &conversation) {
+            Ok(()) => info!("GROUP: finished moving {}", hex::encode(&conversation[..4])),
+            Err(e) => warn!("GROUP: could not move the conversation onto its successor: {e}"),
+        }
+    }
+}
+
+/// One KeyPackage per member, all on one suite: `suite` if given, else the
+/// one [`negotiate_ciphersuite`] settles on from a first fetch of each. A
+/// member whose first KeyPackage is on the other suite but declares this one
+/// is fetched again.
+async fn member_keypackages<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, members: &[[u8; 32]],
+    suite: Option<Ciphersuite>,
+) -> Result<(Ciphersuite, Vec<([u8; 32], KeyPackage, [u8; 32])>)> {
+    let out_of_date = || anyhow!("not every member's app is up to date; ask them to update");
+    let mut kps = Vec::with_capacity(members.len());
+    for m in members {
+        let found = match suite {
+            Some(s) => crate::messaging::fetch_keypackage_on(ctx, m, s).await,
+            None => crate::messaging::fetch_verified_keypackage(ctx, m).await,
+        };
+        let (kp, kp_ref) = found.map_err(|e| no_keys_error(m, e))?;
+        kps.push((*m, kp, kp_ref));
+    }
+    let suite = match suite {
+        Some(s) => s,
+        None => {
+            let member_kps = kps.iter().map(|(_, kp, _)| kp.clone()).collect::<Vec<_>>();
+            negotiate_ciphersuite(&member_kps).map_err(|_| out_of_date())?
+        },
+    };
+    for (m, kp, kp_ref) in &mut kps {
+        if kp.ciphersuite() != suite {
+            (*kp, *kp_ref) = crate::messaging::fetch_keypackage_on(ctx, m, suite)
+                .await
+                .map_err(|e| no_keys_error(m, e))?;
+        }
+        if kp.ciphersuite() != suite {
+            return Err(out_of_date());
+        }
+    }
+    Ok((suite, kps))
+}
+
+/// Keep a successor's Welcomes, one per member, in a single transaction.
+fn stage_welcomes_in(
+    conn: &mut Connection, group_id: &[u8; 32], conversation: &[u8; 16],
+    envelopes: &[([u8; 32], WelcomeEnvelopeP)],
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    for (member, env) in envelopes {
+        tx.execute(
+            "INSERT INTO successor_welcomes (group_id, conversation, member, envelope) \
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![&group_id[..], &conversation[..], &member[..], env.ser()?],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn undelivered_welcomes_in(
+    conn: &Connection, group_id: &[u8; 32],
+) -> Vec<([u8; 32], WelcomeEnvelopeP)> {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT member, envelope FROM successor_welcomes WHERE group_id = ?1 AND delivered = 0",
+    ) else {
+        return Vec::new();
+    };
+    let rows = stmt.query_map([&group_id[..]], |r| {
+        Ok((r.get::<_, [u8; 32]>(0)?, r.get::<_, Vec<u8>>(1)?))
+    });
+    let Ok(rows) = rows else { return Vec::new() };
+    rows.flatten()
+        .filter_map(|(member, blob)| {
+            Some((member, WelcomeEnvelopeP::deser(&blob).ok()?))
+        })
+        .collect()
+}
+
+fn mark_delivered_in(
+    conn: &Connection, group_id: &[u8; 32], member: &[u8; 32],
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE successor_welcomes SET delivered = 1 WHERE group_id = ?1 AND member = ?2",
+        [&group_id[..], &member[..]],
+    )
+}
+
+/// The successor `conversation` is partway onto, if any.
+fn pending_successor_in(conn: &Connection, conversation: &[u8; 16]) -> Option<[u8; 32]> {
+    conn.query_row(
+        "SELECT group_id FROM successor_welcomes WHERE conversation = ?1 LIMIT 1",
+        [&conversation[..]],
+        |r| r.get(0),
+    )
+    .ok()
+}
+
+/// Every successor still being moved onto, with its conversation.
+fn pending_successors_in(conn: &Connection) -> Vec<([u8; 32], [u8; 16])> {
+    let Ok(mut stmt) =
+        conn.prepare("SELECT DISTINCT group_id, conversation FROM successor_welcomes")
+    else {
+        return Vec::new();
+    };
+    let Ok(rows) = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))) else {
+        return Vec::new();
+    };
+    rows.flatten().collect()
+}
+
 /// Fan a Commit out to every current member of `conversation`.
 async fn fan_out_commit(
     conversation: &[u8; 16], commit: &openmls::prelude::MlsMessageOut, group_id: [u8; 32],
@@ -345,6 +741,7 @@ async fn fan_out_commit_to(
             env,
             OpType::Control,
             true,
+            None,
         )
         .await;
     }
@@ -432,3 +829,222 @@ fn leaf_for(
 ) -> Result<openmls_basic_credential::SignatureKeyPair> {
     crate::messaging::leaf_signer_for_group(provider, group, our_ipk)
 }
+
+/// Where `who` sits in `group`. A member who rotated their key keeps a leaf
+/// under an old one until they are let back in.
+fn member_index(group: &MlsGroupHandle, who: &[u8; 32]) -> Result<openmls::prelude::LeafNodeIndex> {
+    std::iter::once(*who)
+        .chain(crate::data::succession::predecessors_of(who))
+        .find_map(|ipk| group.member_index_by_ipk(&ipk))
+        .ok_or_else(|| anyhow!("that member is not in this group"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::db::mls::apply_mls_migrations;
+    use crate::mls::GroupMeta;
+    use crate::mls::group::CLASSICAL_CIPHERSUITE;
+    use crate::mls::process_welcome;
+    use crate::quic::dht_client::KpOutcomeFilter;
+    use crate::quic::dht_client::tests::FakeDhtClient;
+
+    /// A device of its own: provider, stash and epoch buffer over a private
+    /// MLS database, as in the messaging tests.
+    struct Node {
+        ipk_signer: SigningKey,
+        ipk:        [u8; 32],
+        provider:   PromtuzMlsProvider,
+        stash:      KeyPackageStash,
+        buffer:     EpochCatchupBuffer,
+    }
+
+    impl Node {
+        fn new(seed: u8) -> Self {
+            let ipk_signer = SigningKey::from_bytes(&[seed; 32]);
+            let ipk = ipk_signer.verifying_key().to_bytes();
+            let mut conn = Connection::open_in_memory().expect("in-memory db");
+            apply_mls_migrations(&mut conn);
+            let conn = Arc::new(Mutex::new(conn));
+            let provider = PromtuzMlsProvider::new(conn.clone());
+            let stash = KeyPackageStash::new(conn.clone());
+            let buffer = EpochCatchupBuffer::new(conn);
+            Self { ipk_signer, ipk, provider, stash, buffer }
+        }
+
+        fn ctx<'a, C: DhtClient>(&'a self, dht: &'a C) -> MlsContext<'a, C> {
+            MlsContext { provider: &self.provider, stash: &self.stash, buffer: &self.buffer, dht }
+        }
+
+        async fn publish(&self, dht: &FakeDhtClient) {
+            let kps = self.stash.ensure_stash_full(&self.provider, &self.ipk_signer).unwrap();
+            dht.publish_keypackages(&kps, KpOutcomeFilter::Default).await.unwrap();
+        }
+    }
+
+    /// Point the conversation store at a scratch dir before anything opens it.
+    fn scratch_data_dir() {
+        let dir = std::env::temp_dir().join("promtuz-groups-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+    }
+
+    /// An upgrade with one member out of reach: the member who was reached
+    /// has a working successor naming the old group, the conversation stays
+    /// on the old group, and the reconnect retry finishes the move once the
+    /// last Welcome goes out.
+    #[tokio::test(flavor = "current_thread")]
+    async fn an_upgrade_moves_the_chat_only_once_every_welcome_is_out() {
+        scratch_data_dir();
+        let (alice, bob, carol) = (Node::new(0x71), Node::new(0x72), Node::new(0x73));
+        let dht = FakeDhtClient::new_arc();
+        bob.publish(&dht).await;
+        carol.publish(&dht).await;
+
+        let old_id: [u8; 32] = rand::random();
+        let conversation = Conversation::create_group("g", &[bob.ipk, carol.ipk]).unwrap();
+        Conversation::bind_group(&conversation, &old_id).unwrap();
+        let meta = GroupMeta { title: "g".into(), founder: alice.ipk };
+        let plan = SuccessorPlan {
+            conversation,
+            old_id,
+            meta: &meta,
+            suite: Some(PROMTUZ_CIPHERSUITE),
+            members: vec![bob.ipk, carol.ipk],
+        };
+        let ctx = alice.ctx(dht.as_ref());
+
+        dht.unreachable.lock().push(carol.ipk);
+        assert!(found_successor(&ctx, &plan, &alice.ipk, &alice.ipk_signer).await.is_err());
+        assert_eq!(Conversation::group_of(&conversation), Some(old_id), "must not move yet");
+        assert!(
+            found_successor(&ctx, &plan, &alice.ipk, &alice.ipk_signer).await.is_err(),
+            "a second upgrade must not found another successor over the pending one",
+        );
+
+        let sent = dht.welcomes_published.lock().clone();
+        assert_eq!(sent.len(), 1, "only bob's Welcome went out");
+        let bob_group = process_welcome(&bob.provider, &sent[0]).unwrap();
+        assert_eq!(bob_group.predecessor(), Some(old_id));
+        assert_eq!(bob_group.ciphersuite(), PROMTUZ_CIPHERSUITE);
+
+        // Still unreachable: the retry changes nothing and resends nothing.
+        resume_successors(&ctx).await;
+        assert_eq!(Conversation::group_of(&conversation), Some(old_id));
+        assert_eq!(dht.welcomes_published.lock().len(), 1);
+
+        dht.unreachable.lock().clear();
+        resume_successors(&ctx).await;
+        assert_eq!(Conversation::group_of(&conversation), Some(bob_group.group_id()));
+        let sent = dht.welcomes_published.lock().clone();
+        assert_eq!(sent.len(), 2, "bob must not get a second Welcome");
+        let carol_group = process_welcome(&carol.provider, &sent[1]).unwrap();
+        assert_eq!(carol_group.group_id(), bob_group.group_id());
+        assert_eq!(carol_group.epoch(), bob_group.epoch());
+        assert!(pending_successors_in(&alice.provider.storage().conn().lock()).is_empty());
+    }
+
+    /// A member with no KeyPackage on the wanted suite stops the upgrade
+    /// before anything is sent or kept.
+    #[tokio::test(flavor = "current_thread")]
+    async fn an_upgrade_that_cannot_add_everyone_sends_nothing() {
+        let (alice, bob, carol) = (Node::new(0x74), Node::new(0x75), Node::new(0x76));
+        let dht = FakeDhtClient::new_arc();
+        bob.publish(&dht).await;
+
+        let meta = GroupMeta { title: "g".into(), founder: alice.ipk };
+        let plan = SuccessorPlan {
+            conversation: rand::random(),
+            old_id:       rand::random(),
+            meta:         &meta,
+            suite:        Some(PROMTUZ_CIPHERSUITE),
+            members:      vec![bob.ipk, carol.ipk],
+        };
+        let ctx = alice.ctx(dht.as_ref());
+        assert!(found_successor(&ctx, &plan, &alice.ipk, &alice.ipk_signer).await.is_err());
+        assert!(dht.welcomes_published.lock().is_empty());
+        assert!(pending_successors_in(&alice.provider.storage().conn().lock()).is_empty());
+    }
+
+    /// With no suite pinned the members' KeyPackages decide, and one that
+    /// leads with the classical suite but declares the hybrid one is fetched
+    /// again rather than holding the group back.
+    #[tokio::test(flavor = "current_thread")]
+    async fn member_keypackages_settle_on_the_suite_everyone_declares() {
+        let (alice, bob) = (Node::new(0x77), Node::new(0x78));
+        let dht = FakeDhtClient::new_arc();
+        let kps = bob.stash.ensure_stash_full(&bob.provider, &bob.ipk_signer).unwrap();
+        let (pq, classical): (Vec<_>, Vec<_>) = kps.into_iter().partition(|r| {
+            crate::mls::keypackage::minted_on(&r.kp_bytes.0) == Some(PROMTUZ_CIPHERSUITE)
+        });
+        // Bob's first KeyPackage is classical; the second is the hybrid one.
+        dht.seed_kp(&bob.ipk, classical[0].clone());
+        dht.seed_kp(&bob.ipk, pq[0].clone());
+
+        let (suite, got) = member_keypackages(&alice.ctx(dht.as_ref()), &[bob.ipk], None)
+            .await
+            .unwrap();
+        assert_eq!(suite, PROMTUZ_CIPHERSUITE);
+        assert_eq!(got[0].1.ciphersuite(), PROMTUZ_CIPHERSUITE);
+
+        dht.seed_kp(&bob.ipk, classical[1].clone());
+        let (suite, _) = member_keypackages(
+            &alice.ctx(dht.as_ref()),
+            &[bob.ipk],
+            Some(CLASSICAL_CIPHERSUITE),
+        )
+        .await
+        .unwrap();
+        assert_eq!(suite, CLASSICAL_CIPHERSUITE);
+    }
+
+    /// A member who lost their group state is swapped back in: one Welcome
+    /// brings their fresh device to the group's current epoch. Nobody off the
+    /// roster gets one.
+    #[tokio::test(flavor = "current_thread")]
+    async fn readmit_welcomes_a_member_back_at_the_current_epoch() {
+        scratch_data_dir();
+        let (alice, bob, carol) = (Node::new(0x79), Node::new(0x7a), Node::new(0x7b));
+        let dht = FakeDhtClient::new_arc();
+        bob.publish(&dht).await;
+        carol.publish(&dht).await;
+        let conversation =
+            Conversation::join_group(&alice.ipk, &[alice.ipk, bob.ipk, carol.ipk]).unwrap();
+        let meta = GroupMeta { title: "g".into(), founder: alice.ipk };
+        let plan = SuccessorPlan {
+            conversation,
+            old_id: rand::random(),
+            meta: &meta,
+            suite: Some(PROMTUZ_CIPHERSUITE),
+            members: vec![bob.ipk, carol.ipk],
+        };
+        let ctx = alice.ctx(dht.as_ref());
+        found_successor(&ctx, &plan, &alice.ipk, &alice.ipk_signer).await.unwrap();
+        let group_id = Conversation::group_of(&conversation).unwrap();
+
+        // Bob restores onto a fresh device: the same key, no group state, and
+        // only the new device's KeyPackages out there.
+        let bob = Node::new(0x7a);
+        dht.published_kps.lock().remove(&bob.ipk);
+        bob.publish(&dht).await;
+        let before = dht.welcomes_published.lock().len();
+        readmit_on(&ctx, conversation, bob.ipk, &alice.ipk, &alice.ipk_signer).await.unwrap();
+
+        let sent = dht.welcomes_published.lock().clone();
+        assert_eq!(sent.len(), before + 1, "one Welcome, for bob");
+        let back = process_welcome(&bob.provider, &sent[before]).unwrap();
+        assert_eq!(back.group_id(), group_id);
+        assert_eq!(back.epoch(), load_group(&alice.provider, &group_id).unwrap().epoch());
+
+        let stranger = Node::new(0x7c);
+        stranger.publish(&dht).await;
+        let refused =
+            readmit_on(&ctx, conversation, stranger.ipk, &alice.ipk, &alice.ipk_signer).await;
+        assert!(refused.is_err());
+        assert_eq!(dht.welcomes_published.lock().len(), before + 1);
+    }
+}
@@ -5,6 +5,7 @@ use once_cell::sync::OnceCell;
 use quinn::Endpoint;
 use tokio::runtime::Runtime;
 
+pub mod account;
 pub mod api;
 pub mod data;
 pub mod db;
@@ -12,14 +13,20 @@ pub mod delivery;
 pub mod events;
 pub mod media;
 pub mod groups;
+pub mod mailbox;
 pub mod messaging;
 pub mod mls;
 pub mod p2p;
 pub mod platform;
 pub mod push;
 pub mod quic;
+pub mod rejoin;
+pub mod sealed;
+pub mod social_recovery;
 pub mod staging;
 pub mod state;
+pub mod stickers;
+pub mod succession;
 pub mod transfer;
 pub mod utils;
 
@@ -0,0 +1,327 @@
+//! Blinded mailboxes. Contacts address our sealed dispatches to a mailbox id
+//! that rotates every epoch instead of to the IPK, so the homes that queue
+//! them and every relay on the path see a key they cannot link to us or to
+//! last week's (see `common::crypto::mailbox`).
+//!
+//! We share one seed with each paired contact, in the same grant that carries
+//! the tokens for our next two mailboxes ([`grant_payload`]), and only while
+//! our relay has a DHT to open them on (`crate::sealed`). On every relay
+//! connect and presence renewal we open each mailbox in
+//! [`open_epochs`]: the relay takes it as an alias of our connection, drains
+//! its homes alongside the IPK's, and publishes a presence lease, a push
+//! pseudonym and our wake policy under it, each signed by that mailbox alone.
+//! Only the push gateway can tie the mailboxes together: it holds one device
+//! token under every pseudonym (see `crate::push`).
+//!
+//! What stays on the IPK: identified dispatches (strangers, pair requests, a
+//! contact with no live grant), Welcomes, and the presence subscription we opt
+//! into for contacts. A device offline across a whole epoch is woken only for
+//! the mailboxes it opened before it went away.
+
+use anyhow::Result;
+use anyhow::anyhow;
+use common::crypto::mailbox::MAILBOX_OVERLAP_MS;
+use common::crypto::mailbox::MailboxKey;
+use common::crypto::mailbox::epoch_end_ms;
+use common::crypto::mailbox::epoch_of;
+use common::crypto::mailbox::mailbox_id;
+use common::crypto::mailbox::open_epochs;
+use common::crypto::mailbox::seed_for;
+use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::DeliveryToken;
+use common::proto::client_rel::OpenMailboxP;
+use common::proto::dht_p2p::AccountDeletion;
+use common::proto::dht_p2p::PRESENCE_LEASE_MAX_MS;
+use common::proto::dht_p2p::PresenceLease;
+use common::proto::dht_p2p::PushPseudonymPublish;
+use common::proto::dht_p2p::WakePolicyPublish;
+use common::proto::dht_p2p::account_deletion_signing_input;
+use common::proto::dht_p2p::presence_lease_signing_input;
+use common::proto::dht_p2p::push_pseudonym_signing_input;
+use common::proto::dht_p2p::queue_fetch_signing_input;
+use common::proto::dht_p2p::wake_policy_signing_input;
+use common::proto::mls_wire::AppPayload;
+use common::proto::mls_wire::MAX_MAILBOX_TOKENS;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use common::types::bytes::Bytes;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::ed25519::signature::rand_core::OsRng;
+use ed25519_dalek::ed25519::signature::rand_core::RngCore;
+use log::debug;
+use rusqlite::params;
+
+use crate::data::identity::Identity;
+use crate::db::peers::CONTACTS_DB;
+use crate::state::RELAY;
+use crate::utils::systime;
+
+/// A held token this close to expiry is not attached, for the same reason as
+/// in [`crate::sealed`].
+const TOKEN_EXPIRY_MARGIN_MS: u64 = 3_600_000;
+
+fn now_ms() -> u64 {
+    systime().as_millis() as u64
+}
+
+fn identity_key() -> Result<SigningKey> {
+    let ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    crate::data::identity::secret_key_signing(&ipk)
+}
+
+/// Expiry for the token of `epoch`'s mailbox: its end, plus the overlap a
+/// late-clocked contact may still address it for.
+pub(crate) fn token_expiry(epoch: u64) -> u64 {
+    epoch_end_ms(epoch) + MAILBOX_OVERLAP_MS
+}
+
+/// What we grant a contact at `now_ms`: our seed, and a token for the current
+/// mailbox and the next.
+pub(crate) fn grant_payload(identity: &SigningKey, now_ms: u64) -> AppPayload {
+    let seed = seed_for(identity);
+    let current = epoch_of(now_ms);
+    let tokens: Vec<DeliveryToken> = (current..current + MAX_MAILBOX_TOKENS as u64)
+        .map(|epoch| {
+            let mut id = [0u8; 16];
+            OsRng.fill_bytes(&mut id);
+            let key = MailboxKey::derive(identity, &seed, epoch);
+            DeliveryToken::issue_for_mailbox(&key, id, token_expiry(epoch))
+        })
+        .collect();
+    AppPayload::MailboxGrant { seed, tokens }
+}
+
+/// Keep the grant `peer` sent us, replacing any held before. Tokens that do
+/// not verify under one of `peer`'s mailboxes are dropped; a grant left with
+/// none is refused.
+pub(crate) fn accept(peer: &[u8; 32], seed: [u8; 32], tokens: Vec<DeliveryToken>) -> Result<()> {
+    let now = now_ms();
+    let current = epoch_of(now);
+    let tokens: Vec<DeliveryToken> = tokens
+        .into_iter()
+        .filter(|token| {
+            (current..current + MAX_MAILBOX_TOKENS as u64)
+                .filter_map(|epoch| mailbox_id(peer, &seed, epoch))
+                .any(|mailbox| token.verify(&mailbox, now))
+        })
+        .collect();
+    if tokens.is_empty() {
+        return Err(anyhow!("no token in the grant from {} is live", hex::encode(&peer[..4])));
+    }
+    let bytes = tokens.ser().map_err(|e| anyhow!("encode tokens: {e}"))?;
+    CONTACTS_DB.lock().execute(
+        "INSERT INTO mailbox_grants (peer, seed, tokens) VALUES (?1, ?2, ?3) \
+         ON CONFLICT(peer) DO UPDATE SET seed = excluded.seed, tokens = excluded.tokens",
+        params![peer.as_slice(), seed.as_slice(), bytes],
+    )?;
+    Ok(())
+}
+
+/// Where to send a sealed dispatch for `peer` at `now_ms`: their current
+/// mailbox and the token that authorizes it, if they granted us one.
+pub(crate) fn address(peer: &[u8; 32], now_ms: u64) -> Option<([u8; 32], DeliveryToken)> {
+    let (seed, bytes): (Vec<u8>, Vec<u8>) = CONTACTS_DB
+        .lock()
+        .query_row(
+            "SELECT seed, tokens FROM mailbox_grants WHERE peer = ?1",
+            [peer.as_slice()],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .ok()?;
+    let seed: [u8; 32] = seed.try_into().ok()?;
+    let tokens = Vec::<DeliveryToken>::deser(&bytes).ok()?;
+    pick(peer, &seed, tokens, now_ms)
+}
+
+fn pick(
+    peer: &[u8; 32], seed: &[u8; 32], tokens: Vec<DeliveryToken>, now_ms: u64,
+) -> Option<([u8; 32], DeliveryToken)> {
+    let mailbox = mailbox_id(peer, seed, epoch_of(now_ms))?;
+    let token = tokens
+        .into_iter()
+        .find(|t| t.verify(&mailbox, now_ms.saturating_add(TOKEN_EXPIRY_MARGIN_MS)))?;
+    Some((mailbox, token))
+}
+
+/// Drop the grant `peer` sent us: a relay refused its token, or we forgot them.
+pub(crate) fn forget(peer: &[u8; 32]) {
+    CONTACTS_DB
+        .lock()
+        .execute("DELETE FROM mailbox_grants WHERE peer = ?1", [peer.as_slice()])
+        .ok();
+}
+
+fn open_keys(identity: &SigningKey, now_ms: u64) -> Vec<MailboxKey> {
+    let seed = seed_for(identity);
+    open_epochs(now_ms).map(|epoch| MailboxKey::derive(identity, &seed, epoch)).collect()
+}
+
+/// Ids of the mailboxes open at `now_ms`, oldest epoch first.
+pub(crate) fn open_ids(now_ms: u64) -> Result<Vec<[u8; 32]>> {
+    Ok(open_keys(&identity_key()?, now_ms).iter().map(MailboxKey::id).collect())
+}
+
+/// Our key for `mailbox`, if it is one we keep open now.
+pub(crate) fn key_for(mailbox: &[u8; 32]) -> Result<Option<MailboxKey>> {
+    Ok(open_keys(&identity_key()?, now_ms()).into_iter().find(|key| key.id() == *mailbox))
+}
+
+/// Deletions, dated `now_ms`, for every mailbox a contact may still address:
+/// those in [`open_epochs`] and up to the last epoch a grant made now would
+/// carry a token for. Each is signed by its mailbox key, the one its homes
+/// check it under.
+pub(crate) fn deletions(identity: &SigningKey, now_ms: u64) -> Vec<AccountDeletion> {
+    let seed = seed_for(identity);
+    let open = open_epochs(now_ms);
+    let last = (*open.end()).max(epoch_of(now_ms) + MAX_MAILBOX_TOKENS as u64 - 1);
+    (*open.start()..=last)
+        .map(|epoch| {
+            let key = MailboxKey::derive(identity, &seed, epoch);
+            let mailbox = key.id();
+            let sig = key.sign(&account_deletion_signing_input(&mailbox, now_ms));
+            AccountDeletion { ipk: mailbox.into(), timestamp: now_ms, sig: sig.to_bytes().into() }
+        })
+        .collect()
+}
+
+/// Open every mailbox in [`open_epochs`] on the connected relay. Re-run on
+/// each presence renewal, which renews the leases and opens the next mailbox
+/// as its overlap begins, registering that one's pseudonym with the gateway.
+pub(crate) async fn open_all() -> Result<()> {
+    let (conn, relay_id) = {
+        let relay = RELAY.read();
+        let relay = relay.as_ref().ok_or_else(|| anyhow!("no relay"))?;
+        (relay.connection.clone(), relay.home_node_id)
+    };
+    let (Some(conn), Some(relay_id)) = (conn, relay_id) else {
+        return Ok(());
+    };
+    open_on(&conn, relay_id).await?;
+    // The relay now wakes us through a new mailbox's homes; the gateway has to
+    // know its pseudonym too.
+    if let Err(e) = crate::push::register_new_mailboxes().await {
+        debug!("MAILBOX: push registration for new mailboxes failed: {e}");
+    }
+    Ok(())
+}
+
+/// [`open_all`] on `conn`, a relay whose DHT node id is `relay_id`: every
+/// signature binds it. Oldest epoch first, so the relay's cap evicts the
+/// oldest.
+pub(crate) async fn open_on(conn: &quinn::Connection, relay_id: [u8; 32]) -> Result<()> {
+    let relay_id = NodeId::from_bytes(relay_id);
+    let now = now_ms();
+    for key in open_keys(&identity_key()?, now) {
+        let bytes = CRelayPacket::OpenMailbox(open_packet(&key, &relay_id, now))
+            .pack()
+            .map_err(|e| anyhow!("pack open_mailbox: {e}"))?;
+        if let Ok((mut tx, _rx)) = conn.open_bi().await {
+            let _ = tx.write_all(&bytes).await;
+            let _ = tx.finish();
+        }
+    }
+    debug!("MAILBOX: opened epochs {:?}", open_epochs(now));
+    Ok(())
+}
+
+fn open_packet(key: &MailboxKey, relay_id: &NodeId, now_ms: u64) -> OpenMailboxP {
+    let mailbox = key.id();
+    let pseudonym = crate::push::mailbox_pseudonym(&mailbox);
+    let push = PushPseudonymPublish {
+        user_ipk:  Bytes(mailbox),
+        pseudonym: Bytes(pseudonym),
+        timestamp: now_ms,
+        user_sig:  Bytes(
+            key.sign(&push_pseudonym_signing_input(&mailbox, &pseudonym, now_ms)).to_bytes(),
+        ),
+    };
+    let wake = crate::push::wake_policy().map(|policy| WakePolicyPublish {
+        user_ipk: Bytes(mailbox),
+        user_sig: Bytes(key.sign(&wake_policy_signing_input(&mailbox, &policy, now_ms)).to_bytes()),
+        policy,
+        timestamp: now_ms,
+    });
+    let expires_at_ms = now_ms + PRESENCE_LEASE_MAX_MS;
+    let lease = PresenceLease {
+        user: Bytes(mailbox),
+        relay_id: *relay_id,
+        version: now_ms,
+        issued_at_ms: now_ms,
+        expires_at_ms,
+        user_sig: Bytes(
+            key.sign(&presence_lease_signing_input(
+                &mailbox,
+                relay_id,
+                now_ms,
+                now_ms,
+                expires_at_ms,
+            ))
+            .to_bytes(),
+        ),
+    };
+    OpenMailboxP {
+        mailbox: Bytes(mailbox),
+        timestamp: now_ms,
+        sig: Bytes(key.sign(&queue_fetch_signing_input(&mailbox, relay_id, now_ms)).to_bytes()),
+        push: Some(push),
+        wake,
+        lease: Some(lease),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::crypto::mailbox::MAX_OPEN_MAILBOXES;
+
+    use super::*;
+
+    /// A contact holding our grant addresses the mailbox we open, with a token
+    /// a relay accepts on that mailbox alone, across the whole of the next
+    /// epoch; past the grant's epochs they have nothing to attach.
+    #[test]
+    fn a_grant_addresses_our_open_mailboxes_until_it_lapses() {
+        let owner = SigningKey::from_bytes(&[0x41; 32]);
+        let ipk = owner.verifying_key().to_bytes();
+        let now = 1_700_000_000_000;
+        let AppPayload::MailboxGrant { seed, tokens } = grant_payload(&owner, now) else {
+            panic!("grants carry a mailbox grant");
+        };
+        assert_eq!(tokens.len(), MAX_MAILBOX_TOKENS);
+
+        let next_epoch = epoch_end_ms(epoch_of(now));
+        let after_next = epoch_end_ms(epoch_of(now) + 1);
+        for at in [now, next_epoch + 1, after_next - 1] {
+            let (mailbox, token) = pick(&ipk, &seed, tokens.clone(), at).expect("addressable");
+            assert_ne!(mailbox, ipk);
+            assert!(token.verify(&mailbox, at), "authorizes the mailbox it is sent to");
+            let opened = open_keys(&owner, at).iter().map(MailboxKey::id).collect::<Vec<_>>();
+            assert!(opened.contains(&mailbox), "we drain what they address");
+        }
+        assert!(pick(&ipk, &seed, tokens, after_next).is_none(), "no token past the grant");
+    }
+
+    /// Deleting covers every mailbox a contact can still reach us at — the
+    /// open ones and the next, which a grant already holds a token for — each
+    /// signed so its homes accept it.
+    #[test]
+    fn deletions_cover_every_addressable_mailbox() {
+        let owner = SigningKey::from_bytes(&[0x42; 32]);
+        let ipk = owner.verifying_key().to_bytes();
+        let now = 1_700_000_000_000;
+        let seed = seed_for(&owner);
+
+        let deleted: Vec<[u8; 32]> = deletions(&owner, now)
+            .iter()
+            .inspect(|d| assert!(d.verify(), "signed by the mailbox key"))
+            .map(|d| d.ipk.0)
+            .collect();
+        assert!(deleted.len() <= MAX_OPEN_MAILBOXES);
+        for key in open_keys(&owner, now) {
+            assert!(deleted.contains(&key.id()));
+        }
+        let next = mailbox_id(&ipk, &seed, epoch_of(now) + 1).unwrap();
+        assert!(deleted.contains(&next));
+        assert!(!deleted.contains(&ipk));
+    }
+}
@@ -1,11 +1,16 @@
 //! Still-image pipeline: RGBA (from the platform decoder) → AVIF, plus a
 //! gaussian-blurred thumbnail. libcore owns encode/blur so it's one impl for
-//! every platform; the platform owns decode (HEIC/HDR/EXIF) + video/PDF.
+//! every platform; the platform owns decode (HEIC/HDR/EXIF, video frames,
+//! PDF pages). A video's poster is one such decoded frame, encoded here, and
+//! so is each frame of a sticker pack.
+//! Voice notes follow the same split in [`voice`].
 
 use anyhow::{bail, Result};
 use ravif::{Encoder, Img};
 use rgb::FromSlice;
 
+pub mod voice;
+
 fn encode_avif(rgba: &[u8], w: u32, h: u32, quality: f32) -> Result<Vec<u8>> {
     Ok(Encoder::new()
         .with_quality(quality)
@@ -86,6 +91,58 @@ pub fn blur_thumb(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     encode_avif(blurred.as_raw(), tw, th, 50.0)
 }
 
+/// Longest side of a video poster. Sharp rather than blurred — it is the
+/// frame the bubble shows until playback starts — but small enough to ride
+/// the `Video` control.
+const POSTER_EDGE: u32 = 320;
+
+/// Budget for a poster — the wire's cap; [`compress_image`]'s ladder shrinks
+/// it further if a busy frame overshoots.
+const POSTER_MAX_BYTES: usize = common::proto::mls_wire::MAX_POSTER_BYTES;
+
+/// Encode a decoded video frame (RGBA from the platform) as the AVIF poster
+/// for a `Video` message: downscaled to ≤[`POSTER_EDGE`] on its longest side,
+/// then held under [`POSTER_MAX_BYTES`].
+pub fn poster_frame(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    fit_and_compress(rgba, width, height, POSTER_EDGE, POSTER_MAX_BYTES)
+}
+
+/// Longest side of a sticker frame: a sticker bubble drawn at 2x.
+const STICKER_EDGE: u32 = 512;
+
+/// Encode one sticker (RGBA, alpha kept) for a pack: downscaled to
+/// ≤[`STICKER_EDGE`], then held under the pack format's per-frame cap.
+pub fn sticker_frame(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    use common::proto::sticker::MAX_STICKER_BYTES;
+    fit_and_compress(rgba, width, height, STICKER_EDGE, MAX_STICKER_BYTES)
+}
+
+/// Downscale to ≤`edge` on the longest side, then [`compress_image`] under
+/// `max_bytes`.
+fn fit_and_compress(
+    rgba: &[u8], width: u32, height: u32, edge: u32, max_bytes: usize,
+) -> Result<Vec<u8>> {
+    if width == 0 || height == 0 {
+        bail!("zero dimension");
+    }
+    if rgba.len() != width as usize * height as usize * 4 {
+        bail!("rgba len mismatch");
+    }
+    if width.max(height) <= edge {
+        return Ok(compress_image(rgba, width, height, max_bytes)?.0);
+    }
+
+    let img = image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(width, height, rgba)
+        .expect("len checked above");
+    let scale = edge as f32 / width.max(height) as f32;
+    let (pw, ph) = (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    );
+    let small = image::imageops::resize(&img, pw, ph, image::imageops::FilterType::Triangle);
+    Ok(compress_image(small.as_raw(), pw, ph, max_bytes)?.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,4 +218,14 @@ mod tests {
     fn blur_thumb_rejects_zero_dimension() {
         assert!(blur_thumb(&[], 0, 0).is_err());
     }
+
+    #[test]
+    fn poster_frame_is_downscaled_bounded_avif() {
+        let (w, h) = (1280, 720);
+        let out = poster_frame(&noisy_rgba(w, h), w, h).unwrap();
+        assert!(is_avif(&out), "not a valid avif");
+        assert!(out.len() <= POSTER_MAX_BYTES, "poster too big: {}", out.len());
+        assert!(poster_frame(&[], 0, 0).is_err());
+        assert!(poster_frame(&[0u8; 12], 2, 2).is_err(), "short buffer refused");
+    }
 }
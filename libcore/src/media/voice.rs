@@ -0,0 +1,178 @@
+//! Voice-note pipeline: mono PCM (from the platform recorder) → Ogg Opus,
+//! plus the duration and peak waveform the bubble draws. Mirrors the image
+//! side: the platform owns the microphone, libcore owns the encode so every
+//! platform sends the same bytes.
+
+use anyhow::{Result, bail};
+
+/// Bars in the waveform the bubble draws; the wire refuses more.
+pub const WAVEFORM_BARS: usize = common::proto::mls_wire::MAX_WAVEFORM_BARS;
+
+pub struct VoiceNote {
+    /// A complete `audio/ogg` Opus file.
+    pub data: Vec<u8>,
+    pub duration_ms: u32,
+    /// [`WAVEFORM_BARS`] peaks, 0–255, normalised to the loudest.
+    pub waveform: Vec<u8>,
+}
+
+/// Encode `pcm` (mono, `sample_rate` Hz) to Ogg Opus. The tail is padded with
+/// silence to a whole frame; the final granule position trims it back off, so
+/// players report the true length.
+pub fn encode_voice(pcm: &[i16], sample_rate: u32) -> Result<VoiceNote> {
+    if pcm.is_empty() {
+        bail!("empty recording");
+    }
+    Ok(VoiceNote {
+        data: ogg_opus::encode(pcm, sample_rate)?,
+        duration_ms: (pcm.len() as u64 * 1000 / sample_rate as u64) as u32,
+        waveform: waveform(pcm),
+    })
+}
+
+/// libopus through audiopus, which builds it from bundled C sources with
+/// cmake. Behind the `voice` feature so a build without that toolchain
+/// still links; it then refuses to record instead.
+#[cfg(feature = "voice")]
+mod ogg_opus {
+    use anyhow::{Result, anyhow};
+    use audiopus::coder::Encoder;
+    use audiopus::{Application, Bitrate, Channels, SampleRate};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    /// Speech at this rate is indistinguishable from higher ones on a phone
+    /// speaker, and keeps a minute of audio near 180 KB.
+    const BITRATE: i32 = 24_000;
+
+    /// One Opus frame per 20 ms: the codec's sweet spot for speech.
+    const FRAME_MS: u32 = 20;
+
+    /// Largest packet libopus can emit for one frame (RFC 6716 §3.2.1).
+    const MAX_PACKET: usize = 1275;
+
+    /// Ogg granule positions always count 48 kHz samples, whatever the input rate.
+    const GRANULE_RATE: u64 = 48_000;
+
+    /// Single logical stream per file.
+    const STREAM_SERIAL: u32 = 0x7072_6f6d;
+
+    pub(super) fn encode(pcm: &[i16], sample_rate: u32) -> Result<Vec<u8>> {
+        // Only the rates Opus encodes natively; the platform resamples anything else.
+        let rate = SampleRate::try_from(sample_rate as i32)
+            .map_err(|_| anyhow!("unsupported sample rate {sample_rate}"))?;
+        let mut enc = Encoder::new(rate, Channels::Mono, Application::Voip)?;
+        enc.set_bitrate(Bitrate::BitsPerSecond(BITRATE))?;
+        let to_granule = |samples: u64| samples * GRANULE_RATE / sample_rate as u64;
+        let pre_skip = to_granule(enc.lookahead()? as u64);
+
+        let mut data = Vec::new();
+        let mut w = PacketWriter::new(&mut data);
+        w.write_packet(
+            opus_head(pre_skip as u16, sample_rate).into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+        w.write_packet(
+            opus_tags().into_boxed_slice(),
+            STREAM_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        let frame = (sample_rate * FRAME_MS / 1000) as usize;
+        let mut buf = vec![0i16; frame];
+        let mut out = [0u8; MAX_PACKET];
+        let chunks = pcm.len().div_ceil(frame);
+        for (i, chunk) in pcm.chunks(frame).enumerate() {
+            buf[..chunk.len()].copy_from_slice(chunk);
+            buf[chunk.len()..].fill(0);
+            let len = enc.encode(&buf, &mut out)?;
+            let last = i + 1 == chunks;
+            let (end, played) = if last {
+                (PacketWriteEndInfo::EndStream, pcm.len() as u64)
+            } else {
+                (PacketWriteEndInfo::NormalPacket, ((i + 1) * frame) as u64)
+            };
+            w.write_packet(out[..len].into(), STREAM_SERIAL, end, pre_skip + to_granule(played))?;
+        }
+        drop(w);
+        Ok(data)
+    }
+
+    /// RFC 7845 §5.1 identification header, channel mapping family 0.
+    fn opus_head(pre_skip: u16, input_rate: u32) -> Vec<u8> {
+        let mut h = b"OpusHead".to_vec();
+        h.push(1); // version
+        h.push(1); // channels
+        h.extend_from_slice(&pre_skip.to_le_bytes());
+        h.extend_from_slice(&input_rate.to_le_bytes());
+        h.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        h.push(0); // mapping family
+        h
+    }
+
+    /// RFC 7845 §5.2 comment header: a vendor string and no comments — nothing
+    /// about the recording leaves the device in metadata.
+    fn opus_tags() -> Vec<u8> {
+        let vendor = b"promtuz";
+        let mut t = b"OpusTags".to_vec();
+        t.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        t.extend_from_slice(vendor);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+mod ogg_opus {
+    pub(super) fn encode(_pcm: &[i16], _sample_rate: u32) -> anyhow::Result<Vec<u8>> {
+        anyhow::bail!("built without the `voice` feature: no Opus encoder")
+    }
+}
+
+/// Peak magnitude per bar, scaled so the loudest bar is 255. Silence stays
+/// flat rather than being scaled up into noise.
+fn waveform(pcm: &[i16]) -> Vec<u8> {
+    let per_bar = pcm.len().div_ceil(WAVEFORM_BARS).max(1);
+    let mut peaks: Vec<u32> = pcm
+        .chunks(per_bar)
+        .map(|c| c.iter().map(|s| s.unsigned_abs() as u32).max().unwrap_or(0))
+        .collect();
+    peaks.resize(WAVEFORM_BARS, 0);
+    let loudest = peaks.iter().copied().max().unwrap_or(0).max(1);
+    peaks.iter().map(|&p| (p * 255 / loudest) as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(rate: u32, ms: u32) -> Vec<i16> {
+        (0..rate * ms / 1000)
+            .map(|i| {
+                ((i as f32 * 440.0 * std::f32::consts::TAU / rate as f32).sin() * 8000.0) as i16
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "voice")]
+    #[test]
+    fn encodes_ogg_opus_with_duration_and_waveform() {
+        let note = encode_voice(&tone(16_000, 1_010), 16_000).unwrap();
+        assert_eq!(&note.data[..4], b"OggS");
+        assert!(note.data.windows(8).any(|w| w == b"OpusHead"));
+        assert_eq!(note.duration_ms, 1_010);
+        assert_eq!(note.waveform.len(), WAVEFORM_BARS);
+        assert_eq!(note.waveform.iter().max(), Some(&255));
+        // 24 kbps for a second, plus headers and Ogg framing.
+        assert!(note.data.len() < 6 * 1024, "too big: {}", note.data.len());
+    }
+
+    #[test]
+    fn silence_stays_flat_and_bad_input_is_refused() {
+        assert!(waveform(&[0i16; 500]).iter().all(|&b| b == 0));
+        assert!(encode_voice(&[], 16_000).is_err());
+        assert!(encode_voice(&tone(44_100, 100), 44_100).is_err());
+    }
+}
@@ -64,6 +64,7 @@ use anyhow::bail;
 use common::PROTOCOL_VERSION;
 use common::proto::client_rel::ActivityP;
 use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::DISPATCH_V7_VERSION;
 use common::proto::client_rel::DispatchP;
 use common::proto::client_rel::SRelayPacket;
 use common::proto::client_rel::SubscribePresenceP;
@@ -79,10 +80,12 @@ use common::proto::mls_wire::MlsEnvelopeP;
 use common::proto::mls_wire::PairDeclineP;
 use common::proto::mls_wire::PairingP;
 use common::proto::mls_wire::ReceiptKind;
+use common::proto::mls_wire::RejoinRequestP;
 use common::proto::mls_wire::SystemEvent;
 use common::proto::mls_wire::WelcomeEnvelopeP;
 use common::proto::mls_wire::envelope_signing_input;
 use common::proto::mls_wire::pair_decline_signing_input;
+use common::proto::mls_wire::rejoin_signing_input;
 use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
 use common::types::bytes::ByteVec;
@@ -105,6 +108,8 @@ use openmls::prelude::tls_codec::Serialize as _;
 use openmls_traits::OpenMlsProvider;
 use openmls_traits::types::SignatureScheme;
 use parking_lot::Mutex as PlMutex;
+use rusqlite::Connection;
+use rusqlite::params;
 use tokio::sync::Mutex as TokMutex;
 
 use crate::data::contact::Contact;
@@ -121,11 +126,14 @@ use crate::events::messaging::MessageEv;
 use crate::events::messaging::ReactionEv;
 use crate::mls::EpochCatchupBuffer;
 use crate::mls::KeyPackageStash;
+use crate::mls::CLASSICAL_CIPHERSUITE;
 use crate::mls::MlsGroupHandle;
 use crate::mls::PROMTUZ_CIPHERSUITE;
+use crate::mls::SUPPORTED_CIPHERSUITES;
 use crate::mls::PromtuzMlsProvider;
 use crate::mls::make_welcome_envelope;
 use crate::mls::process_welcome;
+use crate::mls::group::joins_on;
 use crate::mls::types::MlsGroupError;
 use crate::quic::dht_client::DhtClient;
 use crate::quic::dht_client::DhtClientError;
@@ -160,6 +168,54 @@ fn group_create_lock(scope: &[u8]) -> Arc<TokMutex<()>> {
     map.entry(scope.to_vec()).or_insert_with(|| Arc::new(TokMutex::new(()))).clone()
 }
 
+/// How long a 1:1 group still on [`CLASSICAL_CIPHERSUITE`] first waits
+/// between checks of whether its peer can move to [`PROMTUZ_CIPHERSUITE`].
+/// Each check spends one of the peer's KeyPackages, so it runs at most this
+/// often rather than per send, and the wait doubles each time the peer turns
+/// out to be on an older build still ([`SUITE_PROBE_MAX_GAP_MS`]).
+const SUITE_PROBE_INTERVAL_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Longest wait between suite checks of one group: about one KeyPackage a
+/// month from a peer who never updates.
+const SUITE_PROBE_MAX_GAP_MS: u64 = 32 * SUITE_PROBE_INTERVAL_MS;
+
+/// Whether `gid` is due a suite check at `now`; if so, its next one is pushed
+/// out by the current wait, so concurrent sends don't all check. Kept in the
+/// MLS database, so a restart doesn't make every classical contact due.
+fn claim_suite_probe_in(conn: &Connection, gid: &[u8; 32], now: u64) -> bool {
+    let row: Option<(i64, i64)> = conn
+        .query_row(
+            "SELECT next_at_ms, gap_ms FROM suite_probes WHERE group_id = ?1",
+            [&gid[..]],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .ok();
+    if row.is_some_and(|(next, _)| now < next as u64) {
+        return false;
+    }
+    let gap = row.map_or(SUITE_PROBE_INTERVAL_MS, |(_, gap)| gap as u64);
+    conn.execute(
+        "INSERT OR REPLACE INTO suite_probes (group_id, next_at_ms, gap_ms) VALUES (?1, ?2, ?3)",
+        params![&gid[..], now.saturating_add(gap) as i64, gap as i64],
+    )
+    .is_ok()
+}
+
+/// The peer of `gid` is still on a build without the hybrid suite: double the
+/// wait before the next check.
+fn back_off_suite_probe_in(conn: &Connection, gid: &[u8; 32], now: u64) {
+    let _ = conn.execute(
+        "UPDATE suite_probes SET gap_ms = MIN(gap_ms * 2, ?3), \
+             next_at_ms = ?2 + MIN(gap_ms * 2, ?3) \
+         WHERE group_id = ?1",
+        params![&gid[..], now as i64, SUITE_PROBE_MAX_GAP_MS as i64],
+    );
+}
+
+/// How many of a peer's KeyPackages [`fetch_keypackage_on`] goes through
+/// looking for one on the suite it wants. Their stash is half on each.
+const SUITE_FETCH_ATTEMPTS: usize = 4;
+
 #[cfg(not(test))]
 const _: () = ();
 
@@ -188,23 +244,23 @@ pub(crate) fn mint_group_id(creator_ipk: &[u8; 32]) -> [u8; 32] {
 }
 
 /// Decode the recipient's fetched KeyPackage bytes into an openmls
-/// `KeyPackage`. Validates the cipher suite is exactly `0x0003`
-/// (`PROMTUZ_CIPHERSUITE`); other suites are spec-incompatible.
+/// `KeyPackage`. Validates the cipher suite is one we can found a group
+/// on (`SUPPORTED_CIPHERSUITES`); which one is for the founder to negotiate.
 fn decode_keypackage_bytes(kp_bytes: &[u8]) -> Result<KeyPackage, MlsGroupError> {
     use openmls::prelude::KeyPackageIn;
     use openmls::prelude::ProtocolVersion;
     let kp_in = KeyPackageIn::tls_deserialize_exact(kp_bytes).map_err(MlsGroupError::from_codec)?;
     let kp = kp_in
         .validate(
-            // We use openmls's `RustCrypto` provider — provided here via
-            // the workspace dep so we don't have to reach into
-            // `PromtuzMlsProvider`'s `crypto()` and risk circular
-            // dependency in tests.
-            &openmls_rust_crypto::RustCrypto::default(),
+            // A bare `PromtuzCrypto` rather than `PromtuzMlsProvider`'s
+            // `crypto()` so we don't reach into storage and risk circular
+            // dependency in tests. Stock `RustCrypto` would reject the
+            // hybrid suite outright.
+            &crate::mls::crypto::PromtuzCrypto::default(),
             ProtocolVersion::Mls10,
         )
         .map_err(|e| MlsGroupError::Internal(format!("KeyPackageIn::validate: {e:?}")))?;
-    if kp.ciphersuite() != PROMTUZ_CIPHERSUITE {
+    if !SUPPORTED_CIPHERSUITES.contains(&kp.ciphersuite()) {
         return Err(MlsGroupError::BadCipherSuite);
     }
     Ok(kp)
@@ -350,6 +406,25 @@ pub async fn react(
     send_control(conversation, AppPayload::React { target, emoji, add }).await
 }
 
+/// Cast, change or (with no `choices`) withdraw our vote on a poll, then
+/// propagate it. Checked against the poll locally first, so a ballot it would
+/// refuse never reaches the wire; each member applies the same check.
+pub async fn vote(conversation: [u8; 16], poll: [u8; 16], choices: Vec<u32>) -> Result<()> {
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let ts = crate::utils::systime().as_secs();
+    crate::data::poll::apply_vote(&conversation, &poll, &our_ipk, &choices, ts)?;
+    send_control(conversation, AppPayload::Vote { poll, choices }).await
+}
+
+/// Stop a poll we sent taking votes, here and for everyone.
+pub async fn close_poll(conversation: [u8; 16], poll: [u8; 16]) -> Result<()> {
+    let ts = crate::utils::systime().as_secs();
+    if !crate::data::poll::close(&conversation, &poll, None, ts)? {
+        bail!("not an open poll of ours");
+    }
+    send_control(conversation, AppPayload::PollClose { poll }).await
+}
+
 /// Send a read/delivered receipt: tell `to` we've received-or-read their
 /// messages up to `upto` (a 16-byte dispatch_id). High-water-mark — one
 /// receipt supersedes earlier ones. Best-effort, like the other control sends.
@@ -485,6 +560,7 @@ async fn send_control_inner(
             env,
             outbox.unwrap_or(OpType::Control),
             wake,
+            None,
         )
         .await;
         if matches!(outcome, LastOutcome::Durable) {
@@ -505,6 +581,14 @@ async fn send_control_inner(
     Ok(())
 }
 
+/// Whether our relay settled on a version that takes a dispatch whole, wake
+/// hint included. Below [`DISPATCH_V7_VERSION`] it reads only the v6
+/// `Dispatch`, which `sealed::seal_frame` reframes the outbox's
+/// [`CRelayPacket::DispatchV7`] into on the way out.
+pub(crate) fn relay_takes_whole_dispatches() -> bool {
+    RELAY.read().as_ref().is_some_and(|r| r.version >= DISPATCH_V7_VERSION)
+}
+
 /// Sign a `DispatchP` over `env_bytes` and send it (the relay queues it for an
 /// offline peer). Shared tail of the MLS control path and the non-MLS
 /// PairDecline — both just need an authenticated dispatch of opaque bytes.
@@ -519,6 +603,9 @@ async fn dispatch_envelope(
     to: [u8; 32], our_ipk: [u8; 32], ipk_signer: &SigningKey, env_bytes: Vec<u8>, wake: bool,
     outbox: Option<OpType>,
 ) -> Result<()> {
+    if crate::data::succession::is_contested(&to) {
+        bail!("{} is one of two heirs to a contested key", hex::encode(&to[..4]));
+    }
     let id = crate::data::message::next_dispatch_id();
     let sig_message = dispatch_sig_message(&to, &our_ipk, &id, &env_bytes);
     let sig = {
@@ -533,8 +620,10 @@ async fn dispatch_envelope(
         sig:            Bytes(sig),
         accepted_at_ms: 0,
         wake,
+        wake_hint: None,
+        token: None,
     };
-    let bytes = CRelayPacket::Dispatch(fwd).pack().map_err(|e| anyhow!("pack dispatch: {e}"))?;
+    let bytes = CRelayPacket::DispatchV7(fwd).pack().map_err(|e| anyhow!("pack dispatch: {e}"))?;
     if let Some(op) = outbox {
         delivery::enqueue(&id, op, Some(to), &bytes);
     }
@@ -549,14 +638,15 @@ async fn dispatch_envelope(
     };
     let (mut tx, mut rx) =
         conn.open_bi().await.map_err(|e| anyhow!("open dispatch stream: {e}"))?;
-    tx.write_all(&bytes).await.map_err(|e| anyhow!("write dispatch: {e}"))?;
+    let (wire, sealed) = crate::sealed::seal_frame(&bytes);
+    tx.write_all(&wire).await.map_err(|e| anyhow!("write dispatch: {e}"))?;
     tx.finish().map_err(|e| anyhow!("finish dispatch: {e}"))?;
     let ack = match SRelayPacket::unpack(&mut rx).await {
         Ok(SRelayPacket::DispatchAck(ack)) => ack,
         Ok(other) => bail!("unexpected dispatch reply: {other:?}"),
         Err(e) => bail!("dispatch ack: {e}"),
     };
-    if delivery::outcome_for_ack(&ack) != LastOutcome::Durable {
+    if crate::sealed::note_ack(&to, &ack, sealed) != LastOutcome::Durable {
         bail!("relay did not accept dispatch: {ack:?}");
     }
     if outbox.is_some() {
@@ -588,6 +678,40 @@ pub async fn send_pair_decline(to: [u8; 32], reason: u8) -> Result<()> {
     dispatch_envelope(to, our_ipk, &ipk_signer, env_bytes, false, Some(OpType::Control)).await
 }
 
+/// Ask `to`, who runs `group_id`, to let us back in after a restore lost our
+/// state for it. Like a decline, a signed control message outside MLS — we
+/// have no group to send it in.
+pub(crate) async fn send_rejoin_request(to: [u8; 32], group_id: [u8; 32]) -> Result<()> {
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let ipk_signer = crate::data::identity::secret_key_signing(&our_ipk)?;
+    let ts = crate::utils::systime().as_millis() as u64;
+    let sig = {
+        use ed25519_dalek::Signer;
+        ipk_signer.sign(&rejoin_signing_input(&our_ipk, &to, &group_id, ts)).to_bytes()
+    };
+    let envelope = MlsEnvelopeP::Rejoin(RejoinRequestP {
+        sender_ipk: Bytes(our_ipk),
+        recipient_ipk: Bytes(to),
+        group_id: Bytes(group_id),
+        timestamp: ts,
+        sig: Bytes(sig),
+    });
+    let env_bytes = envelope.ser().map_err(|e| anyhow!("encode rejoin: {e}"))?;
+    dispatch_envelope(to, our_ipk, &ipk_signer, env_bytes, false, Some(OpType::Control)).await
+}
+
+/// Send a control envelope that rides outside MLS to `to`, signed at the
+/// dispatch layer only — for parties with no group between them, like a
+/// fresh install recovering its identity and the contacts it asks.
+pub(crate) async fn send_plain_envelope(
+    to: [u8; 32], envelope: MlsEnvelopeP, wake: bool,
+) -> Result<()> {
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let ipk_signer = crate::data::identity::secret_key_signing(&our_ipk)?;
+    let env_bytes = envelope.ser().map_err(|e| anyhow!("encode envelope: {e}"))?;
+    dispatch_envelope(to, our_ipk, &ipk_signer, env_bytes, wake, Some(OpType::Control)).await
+}
+
 /// Emit an ephemeral activity signal to `peer` — an OR of `ACTIVITY_*` bits
 /// (`0` = present-idle). Fire-and-forget over the relay, cleartext (not MLS);
 /// dropped if we're offline or the peer isn't online. The relay never queues it.
@@ -770,8 +894,12 @@ pub async fn set_presence(idle: bool) -> Result<()> {
 ///
 /// Order matters — `forward_to_homes` signs the state against whatever lease the
 /// relay currently holds, and a home rejects a record whose lease has expired.
+/// Our mailboxes' leases are renewed alongside, by reopening them.
 pub async fn renew_presence() -> Result<()> {
     renew_presence_lease().await?;
+    if let Err(e) = crate::mailbox::open_all().await {
+        debug!("MAILBOX: reopen on renewal failed: {e}");
+    }
     send_presence(false).await
 }
 
@@ -930,57 +1058,44 @@ pub(crate) async fn fetch_verified_keypackage<C: DhtClient>(
     Ok((kp, kp_ref))
 }
 
+/// Fetch `who`'s KeyPackage on `suite` if their build mints one.
+///
+/// Their stash holds KeyPackages on every suite their build runs and the home
+/// hands out whichever it picks, so one on another suite that still
+/// [declares](joins_on) `suite` is worth another fetch. One that doesn't is an
+/// older build's and comes back as is, as does the last one fetched when the
+/// stash runs dry, for the caller to refuse or settle for.
+pub(crate) async fn fetch_keypackage_on<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, who: &[u8; 32], suite: openmls::prelude::Ciphersuite,
+) -> Result<(KeyPackage, [u8; 32])> {
+    let mut found = fetch_verified_keypackage(ctx, who).await?;
+    for _ in 1..SUITE_FETCH_ATTEMPTS {
+        if found.0.ciphersuite() == suite || !joins_on(&found.0, suite) {
+            break;
+        }
+        match fetch_verified_keypackage(ctx, who).await {
+            Ok(next) => found = next,
+            Err(_) => break,
+        }
+    }
+    Ok(found)
+}
+
 pub async fn lazy_create_group_paired<C: DhtClient>(
     ctx: &MlsContext<'_, C>, our_ipk: &[u8; 32], ipk_signer: &SigningKey, to: &[u8; 32],
     pairing: Option<PairingP>,
 ) -> Result<MlsGroupHandle> {
-    // 1. Fetch peer's KP.
-    // Keep the concrete `DhtClientError` downcastable through the anyhow
-    // chain (do NOT stringify): `attempt_send` inspects it to detect a
-    // `NoStash` KP-miss and defer the send instead of hard-failing.
-    let fetched = ctx
-        .dht
-        .fetch_keypackage_for(to)
-        .await
-        .map_err(|e| anyhow::Error::new(e).context("fetch_keypackage_for"))?;
-
-    // Verify the per-record `owner_sig` re-validates under the peer's
-    // IPK (defence-in-depth — the home should have already done this,
-    // but a malicious replica might forward stale-but-tampered records).
-    {
-        use common::proto::mls_wire::MLS_WIRE_VERSION;
-        use common::proto::mls_wire::kp_record_signing_input;
-        let vk = VerifyingKey::from_bytes(&fetched.record.ipk.0)
-            .map_err(|e| anyhow!("recipient ipk is not valid Ed25519: {e}"))?;
-        if &fetched.record.ipk.0 != to {
-            bail!("fetched KP's owner ipk does not match `to`");
-        }
-        let sig = Signature::from_bytes(&fetched.record.owner_sig.0);
-        // The transcript folds `BLAKE3(kp_bytes)` so the full record
-        // (incl. body) is bound by `owner_sig`.
-        let msg = kp_record_signing_input(
-            MLS_WIRE_VERSION,
-            &fetched.record.ipk.0,
-            &fetched.record.kp_ref.0,
-            &fetched.record.kp_bytes.0,
-            fetched.record.expires_at_ms,
-        );
-        // `verify_strict` rejects non-canonical sigs and small-order R
-        // values; mirrors the relay-side discipline.
-        vk.verify_strict(&msg, &sig).map_err(|e| anyhow!("owner_sig invalid: {e}"))?;
-    }
-
-    let kp = decode_keypackage_bytes(&fetched.record.kp_bytes.0)
-        .map_err(|e| anyhow!("decode KP: {e}"))?;
-    let kp_ref_used: [u8; 32] = {
-        let r = kp.hash_ref(ctx.provider.crypto()).map_err(|e| anyhow!("kp hash_ref: {e:?}"))?;
-        let mut out = [0u8; 32];
-        let s = r.as_slice();
-        let copy = s.len().min(32);
-        out[..copy].copy_from_slice(&s[..copy]);
-        out
-    };
+    // 1. Fetch peer's KP, on the hybrid suite if their build has it.
+    let (kp, kp_ref_used) = fetch_keypackage_on(ctx, to, PROMTUZ_CIPHERSUITE).await?;
+    found_pair_group(ctx, our_ipk, ipk_signer, to, kp, kp_ref_used, pairing).await
+}
 
+/// Steps 2–7 of [`lazy_create_group`]: found a 1:1 group on `kp`'s suite and
+/// Welcome `to` into it with `kp`.
+async fn found_pair_group<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, our_ipk: &[u8; 32], ipk_signer: &SigningKey, to: &[u8; 32],
+    kp: KeyPackage, kp_ref_used: [u8; 32], pairing: Option<PairingP>,
+) -> Result<MlsGroupHandle> {
     // 2. Mint group id.
     let group_id = mint_group_id(our_ipk);
 
@@ -993,8 +1108,16 @@ pub async fn lazy_create_group_paired<C: DhtClient>(
     let mut group =
         // No meta: a pairing group is a 1:1, and that absence is exactly how
         // the far side knows not to open a group chat for it.
-        MlsGroupHandle::create(ctx.provider, &leaf_kp, our_ipk, leaf_kp.public(), &group_id, None)
-            .map_err(|e| anyhow!("create group: {e}"))?;
+        MlsGroupHandle::create(
+            ctx.provider,
+            &leaf_kp,
+            our_ipk,
+            leaf_kp.public(),
+            &group_id,
+            None,
+            kp.ciphersuite(),
+        )
+        .map_err(|e| anyhow!("create group: {e}"))?;
 
     // 5. Add the recipient via their KP.
     let (_commit, welcome) = group
@@ -1072,10 +1195,16 @@ pub struct SealedMessage {
 }
 
 /// Encrypt `plaintext` to the group once, advancing the ratchet one step.
+/// Padded to its size class under the current [`crate::mls::padding`] policy.
 pub fn seal_application_message<C: DhtClient>(
     ctx: &MlsContext<'_, C>, group: &mut MlsGroupHandle,
     leaf_signer: &openmls_basic_credential::SignatureKeyPair, plaintext: &[u8],
 ) -> Result<SealedMessage, MlsGroupError> {
+    let padding = crate::mls::padding::padding_size_for(
+        crate::mls::padding::policy(),
+        plaintext.len(),
+    );
+    group.set_padding_size(ctx.provider, padding)?;
     let mls_msg = group.create_application_message(ctx.provider, leaf_signer, plaintext)?;
     let mls_bytes = mls_msg.tls_serialize_detached().map_err(MlsGroupError::from_codec)?;
 
@@ -1184,6 +1313,10 @@ pub(crate) fn build_image_message(
         blob: None,
         thumb: None,
         file_id: None,
+        duration_ms: 0,
+        waveform: None,
+        sticker_pack: None,
+        sticker_id: 0,
     })
 }
 
@@ -1206,9 +1339,176 @@ pub(crate) fn build_attachment_message(
         blob: None,
         thumb,
         file_id: None,
+        duration_ms: 0,
+        waveform: None,
+        sticker_pack: None,
+        sticker_id: 0,
     })
 }
 
+/// What the platform read off an outgoing video — dimensions, duration — and
+/// the AVIF poster libcore encoded from its frame.
+pub(crate) struct VideoMeta {
+    pub size: u64,
+    pub name: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub duration_ms: u32,
+    pub poster: Option<Vec<u8>>,
+}
+
+/// Sync, pure-DB prep for an outgoing video: the attachment placeholder plus
+/// its [`VideoMeta`]. [`finish_attachment`] lands the file_id and sends,
+/// exactly as for any attachment.
+pub(crate) fn build_video_message(
+    conversation: [u8; 16], video: VideoMeta, caption: &str, group_id: Option<[u8; 16]>,
+) -> Result<Message> {
+    crate::data::media::save_outgoing_with_media(&conversation, caption, None, &crate::data::media::MediaRow {
+        kind: crate::data::media::KIND_VIDEO,
+        group_id: group_id.map(|g| g.to_vec()),
+        mime: video.mime,
+        name: video.name,
+        size: video.size,
+        width: video.width,
+        height: video.height,
+        blob: None,
+        thumb: video.poster,
+        file_id: None,
+        duration_ms: video.duration_ms,
+        waveform: None,
+        sticker_pack: None,
+        sticker_id: 0,
+    })
+}
+
+/// Largest voice note that rides inline in its `Post`. Headroom under the
+/// frame cap covers the MLS ciphertext overhead, the waveform and the
+/// envelope; anything longer goes out as a file by `file_id`.
+pub(crate) const VOICE_INLINE_MAX: usize = MAX_FRAMED_MLS_BYTES - 64 * 1024;
+
+/// Sync, pure-DB prep for an outgoing voice note. Unlike an image the encode
+/// has already run, so the row is final when `inline`: the Ogg bytes land on
+/// it and [`send_prepared`] can go straight out. Otherwise the blob stays null
+/// and [`finish_attachment`] lands the file_id once the manifest is hashed.
+pub(crate) fn build_voice_message(
+    conversation: [u8; 16], note: &crate::media::voice::VoiceNote, inline: bool,
+    group_id: Option<[u8; 16]>,
+) -> Result<Message> {
+    crate::data::media::save_outgoing_with_media(&conversation, "", None, &crate::data::media::MediaRow {
+        kind: crate::data::media::KIND_VOICE,
+        group_id: group_id.map(|g| g.to_vec()),
+        mime: "audio/ogg".into(),
+        name: String::new(),
+        size: note.data.len() as u64,
+        width: 0,
+        height: 0,
+        blob: inline.then(|| note.data.clone()),
+        thumb: None,
+        file_id: None,
+        duration_ms: note.duration_ms,
+        waveform: Some(note.waveform.clone()),
+        sticker_pack: None,
+        sticker_id: 0,
+    })
+}
+
+/// Sync, pure-DB prep for an outgoing sticker: no caption, and a media row
+/// naming the pack and index. Complete as built, so the caller sends at once.
+pub(crate) fn build_sticker_message(
+    conversation: [u8; 16], pack: [u8; 16], id: u32,
+) -> Result<Message> {
+    crate::data::media::save_outgoing_with_media(&conversation, "", None, &crate::data::media::MediaRow {
+        kind: crate::data::media::KIND_STICKER,
+        group_id: None,
+        mime: "image/avif".into(),
+        name: String::new(),
+        size: 0,
+        width: 0,
+        height: 0,
+        blob: None,
+        thumb: None,
+        file_id: None,
+        duration_ms: 0,
+        waveform: None,
+        sticker_pack: Some(pack.to_vec()),
+        sticker_id: id,
+    })
+}
+
+/// Sync, pure-DB prep for an outgoing poll: the question as the message, the
+/// options and rules beside it. Complete as built, so the caller sends at once.
+pub(crate) fn build_poll_message(
+    conversation: [u8; 16], poll: &crate::data::poll::Poll,
+) -> Result<Message> {
+    poll.check()?;
+    crate::data::poll::save_outgoing_with_poll(&conversation, None, poll)
+}
+
+/// How long a forwarded file stays servable: a fresh send's retention.
+const FORWARD_RETAIN_SECS: u64 = 7 * 24 * 3600;
+
+/// Sync, pure-DB prep for forwarding message `did` of `src` into `dst`: the
+/// same body stored as a new outgoing row flagged forwarded. Inline bytes are
+/// copied as they are; a file-backed body keeps its `file_id`, and we keep
+/// serving that file for the people it now reaches — so one we only hold an
+/// offer for, never downloaded, can't be forwarded. Complete as built.
+pub(crate) fn build_forwarded_message(
+    src: [u8; 16], did: [u8; 16], dst: [u8; 16],
+) -> Result<Message> {
+    let original =
+        Message::get_any_by_dispatch(&src, &did).ok_or_else(|| anyhow!("no such message"))?;
+    if original.inner.deleted || original.inner.system != crate::db::messages::SYSTEM_NONE {
+        bail!("only an ordinary message can be forwarded");
+    }
+    if crate::data::conversation::Conversation::get(&dst).is_none() {
+        bail!("no such conversation");
+    }
+    let body = stored_body(&src, &original)?;
+    if let Some(file_id) = body_file_id(&body) {
+        crate::transfer::retain(&file_id, FORWARD_RETAIN_SECS)?;
+    }
+    match crate::data::poll::Poll::from_body(&body) {
+        Some(poll) => Message::save_forwarded(dst, &poll.question, None, Some(&poll)),
+        None => {
+            let (content, media) = split_body(body);
+            Message::save_forwarded(dst, &content, media.as_ref(), None)
+        },
+    }
+}
+
+/// Send a row [`build_forwarded_message`] stored, then park a relay copy of
+/// a file-backed body for members of `dst` who can't reach us, as
+/// [`finish_attachment`] does for a fresh one.
+pub(crate) async fn send_forwarded(dst: [u8; 16], msg: Message) -> Result<()> {
+    let payload_bytes = rebuild_pending_payload(&dst, &msg)?;
+    send_prepared(dst, &msg, payload_bytes).await?;
+    publish_forwarded(&dst, &msg);
+    Ok(())
+}
+
+/// Park the relay copy of a forwarded file-backed body once a send of it
+/// lands — after the first attempt or any retry, since a forward that failed
+/// first time out never got one from [`send_forwarded`].
+fn publish_forwarded(conversation: &[u8; 16], msg: &Message) {
+    if !msg.inner.forwarded {
+        return;
+    }
+    if let Some(file_id) = stored_body(conversation, msg).ok().as_ref().and_then(body_file_id) {
+        crate::transfer::blob::publish(*conversation, file_id);
+    }
+}
+
+/// The transfer `file_id` a body is pulled by, when its bytes don't ride the
+/// frame.
+fn body_file_id(body: &Body) -> Option<[u8; 32]> {
+    match body {
+        Body::Attachment { file_id, .. } | Body::Video { file_id, .. } => Some(*file_id),
+        Body::Voice { file_id, .. } => *file_id,
+        _ => None,
+    }
+}
+
 /// Finalize an optimistic image placeholder: land the compressed bytes on the
 /// row, then send. Called after the sync compress succeeds.
 pub(crate) async fn finish_image(
@@ -1226,6 +1526,8 @@ pub(crate) async fn finish_image(
 
 /// Finalize an optimistic attachment placeholder: land the content-addressed
 /// file_id on the row, then send. Called after the manifest pass succeeds.
+/// Once the offer is out, a copy is parked on our relay for members who
+/// can't reach us.
 pub(crate) async fn finish_attachment(
     conversation: [u8; 16], did: [u8; 16], file_id: [u8; 32],
 ) -> Result<()> {
@@ -1233,7 +1535,9 @@ pub(crate) async fn finish_attachment(
     let msg = Message::get_by_dispatch(&conversation, &did)
         .ok_or_else(|| anyhow!("attachment row vanished"))?;
     let payload_bytes = rebuild_pending_payload(&conversation, &msg)?;
-    send_prepared(conversation, &msg, payload_bytes).await
+    send_prepared(conversation, &msg, payload_bytes).await?;
+    crate::transfer::blob::publish(conversation, file_id);
+    Ok(())
 }
 
 /// Drive one send attempt for an already-persisted outgoing `msg` row:
@@ -1249,44 +1553,90 @@ pub async fn attempt_send<C: DhtClient>(
     ctx: &MlsContext<'_, C>, conversation: [u8; 16], msg: Message,
 ) -> Result<()> {
     let payload_bytes = rebuild_pending_payload(&conversation, &msg)?;
-    send_payload(ctx, conversation, &msg, payload_bytes).await
+    send_payload(ctx, conversation, &msg, payload_bytes).await?;
+    publish_forwarded(&conversation, &msg);
+    Ok(())
 }
 
 /// Persist an inbound [`Body`] under `did`, handing back the stored row with the
 /// text to surface (a caption, for media). Single home for the storage shape:
 /// pre-v12 payloads convert through [`legacy_body`] and land here too, so the
-/// wire vintage stops being visible past this point.
+/// wire vintage stops being visible past this point. `forwarded` marks a body
+/// that arrived as an [`AppPayload::Forward`].
 pub(crate) fn save_inbound_body(
     conversation: &[u8; 16], sender: &[u8; 32], did: &[u8; 16], timestamp: u64,
-    reply_to: Option<[u8; 16]>, body: Body,
+    reply_to: Option<[u8; 16]>, body: Body, forwarded: bool,
 ) -> Result<Option<(Message, String)>> {
-    let Some((content, media)) = split_body(body) else { return Ok(None) };
-    Ok(match media {
-        None => Message::save_incoming(*conversation, *sender, did, &content, timestamp, reply_to)?
-            .map(|m| (m, content)),
-        Some(r) => crate::data::media::save_incoming_with_media(
+    if forwarded {
+        let poll = crate::data::poll::Poll::from_body(&body);
+        let (content, media) = match &poll {
+            Some(poll) => {
+                poll.check()?;
+                (poll.question.clone(), None)
+            },
+            None => split_body(body),
+        };
+        let saved = Message::save_incoming_forwarded(
+            *conversation,
+            *sender,
+            did,
+            &content,
+            timestamp,
+            media.as_ref(),
+            poll.as_ref(),
+        )?;
+        if saved.is_some() && poll.is_some() {
+            crate::data::poll::replay_early(conversation, did);
+        }
+        return Ok(saved.map(|m| (m, content)));
+    }
+    let saved = if let Some(poll) = crate::data::poll::Poll::from_body(&body) {
+        // Held to what we'd let ourselves send: no blank or repeated options.
+        poll.check()?;
+        crate::data::poll::save_incoming_with_poll(
             conversation,
             sender,
             did,
-            &content,
             timestamp,
             reply_to,
-            &r,
+            &poll,
         )?
-        .map(|m| (m, content)),
-    })
+        .map(|m| (m, poll.question))
+    } else {
+        let (content, media) = split_body(body);
+        match media {
+            None => {
+                Message::save_incoming(*conversation, *sender, did, &content, timestamp, reply_to)?
+                    .map(|m| (m, content))
+            },
+            Some(r) => crate::data::media::save_incoming_with_media(
+                conversation,
+                sender,
+                did,
+                &content,
+                timestamp,
+                reply_to,
+                &r,
+            )?
+            .map(|m| (m, content)),
+        }
+    };
+    Ok(saved)
 }
 
 /// A body as storage holds it: the text to surface (a caption, for media) and
-/// the media side-row it needs, if any. `None` for a sticker — pack
-/// distribution isn't built, so there's nothing to resolve an id against and a
-/// stored row would be unrenderable.
-fn split_body(body: Body) -> Option<(String, Option<crate::data::media::MediaRow>)> {
+/// the media side-row it needs, if any. A sticker stores only its pack and
+/// index; the frame is drawn from the installed pack. A poll keeps its own
+/// table and never revises, so only its question shows here.
+fn split_body(body: Body) -> (String, Option<crate::data::media::MediaRow>) {
     use crate::data::media::KIND_ATTACHMENT;
     use crate::data::media::KIND_IMAGE;
+    use crate::data::media::KIND_STICKER;
+    use crate::data::media::KIND_VIDEO;
+    use crate::data::media::KIND_VOICE;
     use crate::data::media::MediaRow;
 
-    Some(match body {
+    match body {
         Body::Text(content) => (content, None),
         Body::Image { caption, group_id, mime, width, height, data } => (
             caption,
@@ -1301,6 +1651,10 @@ fn split_body(body: Body) -> Option<(String, Option<crate::data::media::MediaRow
                 blob: Some(data),
                 thumb: None,
                 file_id: None,
+                duration_ms: 0,
+                waveform: None,
+                sticker_pack: None,
+                sticker_id: 0,
             }),
         ),
         Body::Attachment { caption, group_id, mime, name, size, thumb, file_id } => (
@@ -1316,10 +1670,85 @@ fn split_body(body: Body) -> Option<(String, Option<crate::data::media::MediaRow
                 blob: None,
                 thumb: (!thumb.is_empty()).then_some(thumb),
                 file_id: Some(file_id.to_vec()),
+                duration_ms: 0,
+                waveform: None,
+                sticker_pack: None,
+                sticker_id: 0,
             }),
         ),
-        Body::Sticker { .. } => return None,
-    })
+        Body::Sticker { pack, id } => (
+            String::new(),
+            Some(MediaRow {
+                kind: KIND_STICKER,
+                group_id: None,
+                mime: "image/avif".into(),
+                name: String::new(),
+                size: 0,
+                width: 0,
+                height: 0,
+                blob: None,
+                thumb: None,
+                file_id: None,
+                duration_ms: 0,
+                waveform: None,
+                sticker_pack: Some(pack.to_vec()),
+                sticker_id: id,
+            }),
+        ),
+        Body::Voice { group_id, mime, duration_ms, waveform, size, data, file_id } => (
+            String::new(),
+            Some(MediaRow {
+                kind: KIND_VOICE,
+                group_id: group_id.map(|g| g.to_vec()),
+                mime,
+                name: String::new(),
+                size,
+                width: 0,
+                height: 0,
+                blob: (!data.is_empty()).then_some(data),
+                thumb: None,
+                file_id: file_id.map(|f| f.to_vec()),
+                duration_ms,
+                waveform: Some(waveform),
+                sticker_pack: None,
+                sticker_id: 0,
+            }),
+        ),
+        Body::Video {
+            caption,
+            group_id,
+            mime,
+            name,
+            size,
+            width,
+            height,
+            duration_ms,
+            poster,
+            file_id,
+        } => (
+            caption,
+            Some(MediaRow {
+                kind: KIND_VIDEO,
+                group_id: group_id.map(|g| g.to_vec()),
+                mime,
+                name,
+                size,
+                width,
+                height,
+                blob: None,
+                thumb: (!poster.is_empty()).then_some(poster),
+                file_id: Some(file_id.to_vec()),
+                duration_ms,
+                waveform: None,
+                sticker_pack: None,
+                sticker_id: 0,
+            }),
+        ),
+        Body::Poll { question, .. } => (question, None),
+        // Never persisted as content: the receive path keeps it aside until a
+        // build can read it (see `data::unsupported`).
+        Body::Extension { .. } => (String::new(), None),
+    }
 }
 
 /// Apply a [`AppPayload::Revise`] over its target: refuse the swaps the matrix
@@ -1329,12 +1758,15 @@ fn split_body(body: Body) -> Option<(String, Option<crate::data::media::MediaRow
 pub(crate) fn apply_revise_body(
     conversation: &[u8; 16], target: &[u8; 16], body: Body, own: bool,
 ) -> Result<Option<(crate::db::messages::MessageRow, String)>> {
-    let current = BodyKind::stored(crate::data::media::get(conversation, target)?.map(|m| m.kind));
+    let current = match crate::data::poll::get(conversation, target) {
+        Some(_) => BodyKind::Poll,
+        None => BodyKind::stored(crate::data::media::get(conversation, target)?.map(|m| m.kind)),
+    };
     let incoming = BodyKind::of(&body);
     if !current.revisable_to(incoming) {
         bail!("revision {current:?} -> {incoming:?} is not permitted");
     }
-    let Some((content, media)) = split_body(body) else { bail!("sticker revision unsupported") };
+    let (content, media) = split_body(body);
     Ok(crate::data::media::apply_revise(conversation, target, &content, media.as_ref(), own)?
         .map(|row| (row, content)))
 }
@@ -1364,6 +1796,11 @@ pub(crate) enum BodyKind {
     Image,
     Attachment,
     Sticker,
+    Voice,
+    Video,
+    Poll,
+    /// A [`Body::Extension`] this build can't read. Revises to nothing.
+    Extension,
 }
 
 impl BodyKind {
@@ -1373,6 +1810,10 @@ impl BodyKind {
             Body::Image { .. } => Self::Image,
             Body::Attachment { .. } => Self::Attachment,
             Body::Sticker { .. } => Self::Sticker,
+            Body::Voice { .. } => Self::Voice,
+            Body::Video { .. } => Self::Video,
+            Body::Poll { .. } => Self::Poll,
+            Body::Extension { .. } => Self::Extension,
         }
     }
 
@@ -1382,6 +1823,9 @@ impl BodyKind {
         match media_kind {
             Some(crate::data::media::KIND_IMAGE) => Self::Image,
             Some(crate::data::media::KIND_ATTACHMENT) => Self::Attachment,
+            Some(crate::data::media::KIND_VOICE) => Self::Voice,
+            Some(crate::data::media::KIND_VIDEO) => Self::Video,
+            Some(crate::data::media::KIND_STICKER) => Self::Sticker,
             _ => Self::Text,
         }
     }
@@ -1393,12 +1837,17 @@ impl BodyKind {
     /// transfer for a message they consider delivered, and revising out of one
     /// orphans a transfer they may be mid-download on. Stickers are atomic (no
     /// caption, nothing to pair with text), so they only revise to a sticker.
+    /// A voice note is a recording of what was said: it revises to nothing.
+    /// A video is pulled like an attachment, so it shares that diagonal. A
+    /// poll revises to nothing: votes name options by index, so rewording one
+    /// would move every ballot already cast onto whatever now sits there.
     pub(crate) fn revisable_to(self, to: Self) -> bool {
         matches!(
             (self, to),
             (Self::Text | Self::Image, Self::Text | Self::Image)
                 | (Self::Attachment, Self::Attachment)
                 | (Self::Sticker, Self::Sticker)
+                | (Self::Video, Self::Video)
         )
     }
 }
@@ -1407,17 +1856,35 @@ impl BodyKind {
 /// (re)send. A row carrying a stored `KIND_IMAGE` media side-row resends as
 /// [`Body::Image`] (caption + AVIF blob), so a first-send deferred while the
 /// peer had no published KeyPackage doesn't silently downgrade to a
-/// bare-caption text. Everything else — including media kinds not yet re-driven
-/// here, whose bytes live off-row — falls through to [`Body::Text`]. The quote
-/// target rides the envelope, so it survives on every body kind.
+/// bare-caption text; `KIND_ATTACHMENT`, `KIND_VOICE`, `KIND_VIDEO` and
+/// `KIND_STICKER` likewise resend as their own bodies, and a poll from its own
+/// table. Everything else — including media kinds not yet re-driven here,
+/// whose bytes live off-row — falls through to [`Body::Text`]. The quote
+/// target rides the envelope, so it survives on every body kind. A forwarded
+/// row goes out as [`AppPayload::Forward`], which carries no quote.
 pub(crate) fn rebuild_pending_payload(
     conversation: &[u8; 16], msg: &Message,
 ) -> Result<Vec<u8>> {
-    let did: Option<[u8; 16]> = msg.inner.dispatch_id.as_deref().and_then(|r| r.try_into().ok());
-    let media = did.and_then(|d| crate::data::media::get(conversation, &d).ok().flatten());
     let reply_to: Option<[u8; 16]> =
         msg.inner.reply_to.as_deref().and_then(|r| r.try_into().ok());
-    let body = match media {
+    let body = stored_body(conversation, msg)?;
+    let payload = if msg.inner.forwarded {
+        AppPayload::Forward { body }
+    } else {
+        AppPayload::Post { reply_to, body }
+    };
+    payload.ser().map_err(|e| anyhow!("encode AppPayload: {e}"))
+}
+
+/// The [`Body`] a stored row holds, rebuilt from its side tables. Errors on
+/// a media placeholder whose prep hasn't finished.
+fn stored_body(conversation: &[u8; 16], msg: &Message) -> Result<Body> {
+    let did: Option<[u8; 16]> = msg.inner.dispatch_id.as_deref().and_then(|r| r.try_into().ok());
+    if let Some(poll) = did.and_then(|d| crate::data::poll::get(conversation, &d)) {
+        return Ok(poll.body());
+    }
+    let media = did.and_then(|d| crate::data::media::get(conversation, &d).ok().flatten());
+    Ok(match media {
         Some(m) if m.kind == crate::data::media::KIND_IMAGE => {
             // Empty blob = un-finalized placeholder (compress still running).
             // Bail so a reconnect retry leaves the row pending; the finish_*
@@ -1451,9 +1918,53 @@ pub(crate) fn rebuild_pending_payload(
                 file_id,
             }
         },
+        Some(m) if m.kind == crate::data::media::KIND_VOICE => {
+            // Inline notes carry their bytes; a file-backed one is ready once
+            // its file_id lands. Neither = placeholder still hashing.
+            let file_id: Option<[u8; 32]> = m.file_id.as_deref().and_then(|f| f.try_into().ok());
+            let data = m.blob.unwrap_or_default();
+            if data.is_empty() && file_id.is_none() {
+                bail!("media not ready");
+            }
+            Body::Voice {
+                group_id:    m.group_id.as_deref().and_then(|g| g.try_into().ok()),
+                mime:        m.mime,
+                duration_ms: m.duration_ms,
+                waveform:    m.waveform.unwrap_or_default(),
+                size:        m.size,
+                data,
+                file_id,
+            }
+        },
+        Some(m) if m.kind == crate::data::media::KIND_VIDEO => {
+            // Null file_id = un-finalized placeholder, as for an attachment.
+            let file_id = match m.file_id.as_deref().and_then(|f| f.try_into().ok()) {
+                Some(f) => f,
+                None => bail!("media not ready"),
+            };
+            Body::Video {
+                caption:     msg.inner.content.clone(),
+                group_id:    m.group_id.as_deref().and_then(|g| g.try_into().ok()),
+                mime:        m.mime,
+                name:        m.name,
+                size:        m.size,
+                width:       m.width,
+                height:      m.height,
+                duration_ms: m.duration_ms,
+                poster:      m.thumb.unwrap_or_default(),
+                file_id,
+            }
+        },
+        Some(m) if m.kind == crate::data::media::KIND_STICKER => {
+            let pack = m
+                .sticker_pack
+                .as_deref()
+                .and_then(|p| p.try_into().ok())
+                .ok_or_else(|| anyhow!("sticker row names no pack"))?;
+            Body::Sticker { pack, id: m.sticker_id }
+        },
         _ => Body::Text(msg.inner.content.clone()),
-    };
-    AppPayload::Post { reply_to, body }.ser().map_err(|e| anyhow!("encode AppPayload: {e}"))
+    })
 }
 
 /// Shared tail of [`attempt_send`] and [`send_prepared`]: resolve or
@@ -1472,7 +1983,7 @@ pub(crate) fn rebuild_pending_payload(
 ///
 /// A group conversation is never lazy-created: its MLS group is minted
 /// explicitly at creation time, with Welcomes to every founding member.
-async fn group_for_conversation<C: DhtClient>(
+pub(crate) async fn group_for_conversation<C: DhtClient>(
     ctx: &MlsContext<'_, C>, conversation: &[u8; 16], our_ipk: &[u8; 32], ipk_signer: &SigningKey,
 ) -> Result<MlsGroupHandle> {
     let lock = group_create_lock(conversation);
@@ -1486,7 +1997,10 @@ async fn group_for_conversation<C: DhtClient>(
 
     if let Some(gid) = bound {
         match MlsGroupHandle::load(ctx.provider, &gid) {
-            Ok(Some(g)) => return Ok(g),
+            Ok(Some(g)) if row.kind == crate::data::conversation::KIND_GROUP => return Ok(g),
+            Ok(Some(g)) => {
+                return Ok(upgrade_pair_group(ctx, conversation, g, our_ipk, ipk_signer).await);
+            }
             // The conversation points at a group we no longer hold state for
             // — openmls storage and SQLite drifted. Re-establish and repoint.
             // History is keyed on the conversation, so it rides through
@@ -1511,12 +2025,76 @@ async fn group_for_conversation<C: DhtClient>(
     Ok(group)
 }
 
+/// Move a 1:1 group on [`CLASSICAL_CIPHERSUITE`] to [`PROMTUZ_CIPHERSUITE`] if
+/// the peer has updated, returning whichever group to send on.
+///
+/// A group cannot change suite, so this founds a fresh one on one of the
+/// peer's hybrid-suite KeyPackages — only if their build mints them — and
+/// repoints the conversation. The peer's Welcome handling repoints theirs the
+/// same way it does after a heal. The old group's state stays so anything the
+/// peer sent on it before the Welcome landed still decrypts.
+async fn upgrade_pair_group<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, conversation: &[u8; 16], group: MlsGroupHandle,
+    our_ipk: &[u8; 32], ipk_signer: &SigningKey,
+) -> MlsGroupHandle {
+    let gid = group.group_id();
+    if group.ciphersuite() != CLASSICAL_CIPHERSUITE {
+        return group;
+    }
+    let Some(peer) = Conversation::peer_of(conversation) else { return group };
+    let Some((kp, kp_ref)) = pair_upgrade_keypackage(ctx, &gid, &peer).await else {
+        return group;
+    };
+    let upgraded = match found_pair_group(ctx, our_ipk, ipk_signer, &peer, kp, kp_ref, None).await
+    {
+        Ok(g) => g,
+        Err(e) => {
+            warn!("MLS: moving {} to the hybrid suite failed: {e}", hex::encode(&peer[..4]));
+            return group;
+        },
+    };
+    if let Err(e) = Conversation::bind_group(conversation, &upgraded.group_id()) {
+        warn!("MLS: could not repoint conversation at the hybrid-suite group: {e}");
+        return group;
+    }
+    let _ = Contact::set_mls_group_id(&peer, &upgraded.group_id());
+    info!("MLS: moved the chat with {} to the hybrid suite", hex::encode(&peer[..4]));
+    upgraded
+}
+
+/// One of `peer`'s hybrid-suite KeyPackages to move the classical group `gid`
+/// onto, if a check is due and their build mints them. A peer still on an
+/// older build makes the next check wait twice as long.
+async fn pair_upgrade_keypackage<C: DhtClient>(
+    ctx: &MlsContext<'_, C>, gid: &[u8; 32], peer: &[u8; 32],
+) -> Option<(KeyPackage, [u8; 32])> {
+    let now = crate::utils::systime().as_millis() as u64;
+    let conn = ctx.provider.storage().conn();
+    if !claim_suite_probe_in(&conn.lock(), gid, now) {
+        return None;
+    }
+    let (kp, kp_ref) = match fetch_keypackage_on(ctx, peer, PROMTUZ_CIPHERSUITE).await {
+        Ok(found) => found,
+        Err(e) => {
+            debug!("MLS: suite check for {} found no KeyPackage: {e}", hex::encode(&peer[..4]));
+            return None;
+        },
+    };
+    if kp.ciphersuite() == PROMTUZ_CIPHERSUITE {
+        return Some((kp, kp_ref));
+    }
+    if !joins_on(&kp, PROMTUZ_CIPHERSUITE) {
+        back_off_suite_probe_in(&conn.lock(), gid, now);
+    }
+    None
+}
+
 /// Sign, frame, enqueue and send one member's copy of an already-sealed
 /// dispatch. Returns the relay's durability verdict; `Silence` covers every
 /// transport failure, which leaves the outbox row for the reconciler.
 pub(crate) async fn dispatch_to_member(
     to: &[u8; 32], our_ipk: &[u8; 32], ipk_signer: &SigningKey, id: &[u8; 16], payload: Vec<u8>,
-    op: OpType, wake: bool,
+    op: OpType, wake: bool, wake_hint: Option<ByteVec>,
 ) -> LastOutcome {
     let sig_message = dispatch_sig_message(to, our_ipk, id, &payload);
     let sig = {
@@ -1531,15 +2109,23 @@ pub(crate) async fn dispatch_to_member(
         sig:            Bytes(sig),
         accepted_at_ms: 0,
         wake,
+        wake_hint,
+        token: None,
     };
     // Frame once, enqueue before the wire. `.pack()` (not `.ser()`) yields the
     // length-prefixed bytes `send()` writes; the relay's read side is
     // length-prefixed, so storing raw postcard would desync every frame. Store
     // framed, send framed, reconciler re-sends framed — all byte-identical.
-    let Ok(bytes) = CRelayPacket::Dispatch(fwd).pack() else {
+    let Ok(bytes) = CRelayPacket::DispatchV7(fwd).pack() else {
         return LastOutcome::Terminal;
     };
     delivery::enqueue(id, op, Some(*to), &bytes);
+    // One of two heirs to a contested key may be whoever stole it. Hold what
+    // is meant for it until the user says which is the contact.
+    if crate::data::succession::is_contested(to) {
+        info!("MESSAGE: {} is a contested heir; held in outbox", hex::encode(&to[..4]));
+        return LastOutcome::Silence;
+    }
 
     let conn = {
         let relay = RELAY.read();
@@ -1553,13 +2139,16 @@ pub(crate) async fn dispatch_to_member(
         debug!("MESSAGE: {} send stream failed to open; left in outbox", hex::encode(&to[..4]));
         return LastOutcome::Silence;
     };
-    if send.write_all(&bytes).await.is_err() || send.finish().is_err() {
+    // The outbox holds the identified frame; what goes on the wire is sealed
+    // when we hold `to`'s token.
+    let (wire, sealed) = crate::sealed::seal_frame(&bytes);
+    if send.write_all(&wire).await.is_err() || send.finish().is_err() {
         debug!("MESSAGE: {} interrupted mid-send; left in outbox", hex::encode(&to[..4]));
         return LastOutcome::Silence;
     }
     match SRelayPacket::unpack(&mut recv).await {
         Ok(SRelayPacket::DispatchAck(ack)) => {
-            let outcome = delivery::outcome_for_ack(&ack);
+            let outcome = crate::sealed::note_ack(to, &ack, sealed);
             if matches!(outcome, LastOutcome::Durable) {
                 delivery::retire(id, Some(*to));
                 LAST_ACCEPTED_AT.lock().insert(*id, delivery::accepted_at_secs(&ack).unwrap_or(0));
@@ -1652,9 +2241,20 @@ async fn send_payload<C: DhtClient>(
         let payload = sealed
             .address_to(to, &ipk_signer)
             .map_err(|e| anyhow!("address envelope to member: {e}"))?;
-        // New content: push-wake an offline member.
-        let outcome =
-            dispatch_to_member(to, &our_ipk, &ipk_signer, &id, payload, OpType::Message, true).await;
+        // New content: push-wake an offline member, with a sealed hint so the
+        // notification can name us before the member drains.
+        let hint = crate::push::seal_wake_hint(to, &our_ipk, &ipk_signer, conversation, &id);
+        let outcome = dispatch_to_member(
+            to,
+            &our_ipk,
+            &ipk_signer,
+            &id,
+            payload,
+            OpType::Message,
+            true,
+            hint,
+        )
+        .await;
         terminal |= matches!(outcome, LastOutcome::Terminal);
     }
 
@@ -1777,7 +2377,13 @@ pub async fn process_inbound_envelope<C: DhtClient>(
                 );
             }
         },
-        MlsEnvelopeP::PairDecline(_) => {}, // fixed-size, no cap
+        // No cap / refused below.
+        MlsEnvelopeP::PairDecline(_)
+        | MlsEnvelopeP::Sealed(_)
+        | MlsEnvelopeP::Rejoin(_)
+        | MlsEnvelopeP::RecoveryRequest(_)
+        | MlsEnvelopeP::RecoveryReturn(_)
+        | MlsEnvelopeP::Succession(_) => {},
     }
 
     match envelope {
@@ -1798,6 +2404,25 @@ pub async fn process_inbound_envelope<C: DhtClient>(
             process_pair_decline_inbound(sender_ipk, d)?;
             Ok(Some(InboundDecoded::PairDeclined))
         },
+        MlsEnvelopeP::Rejoin(req) => {
+            crate::rejoin::on_request(sender_ipk, req)?;
+            Ok(Some(InboundDecoded::RejoinRequest))
+        },
+        MlsEnvelopeP::RecoveryRequest(req) => {
+            crate::social_recovery::on_request(sender_ipk, req)?;
+            Ok(Some(InboundDecoded::Recovery))
+        },
+        MlsEnvelopeP::RecoveryReturn(ret) => {
+            crate::social_recovery::on_return(sender_ipk, ret)?;
+            Ok(Some(InboundDecoded::Recovery))
+        },
+        MlsEnvelopeP::Succession(succession) => {
+            crate::succession::on_succession(sender_ipk, succession)?;
+            Ok(Some(InboundDecoded::Succession))
+        },
+        // Opened before it gets here (`sealed::open`); one still sealed was
+        // nested, or skipped the opening, and is refused either way.
+        MlsEnvelopeP::Sealed(_) => bail!("sealed envelope reached the MLS layer"),
     }
 }
 
@@ -1917,6 +2542,15 @@ pub enum InboundDecoded {
     /// The invitee declined our pair; already applied (contact REJECTED,
     /// PENDING-era messages failed). Terminal — the caller just acks.
     PairDeclined,
+    /// A member who lost their state for one of our groups asked back in;
+    /// the re-add is already under way. Terminal — the caller just acks.
+    RejoinRequest,
+    /// A social-recovery request or returned share; already stored for the
+    /// user. Terminal — the caller just acks.
+    Recovery,
+    /// A contact moved to a new key; they're already re-keyed locally.
+    /// Terminal — the caller just acks.
+    Succession,
 }
 
 /// Outcome of accepting a pairing Welcome. A gate/auth failure is still an
@@ -1969,6 +2603,17 @@ pub(crate) fn home_for_group(group: &MlsGroupHandle, from: &[u8; 32]) -> Result<
     if let Some(id) = Conversation::for_group(&gid) {
         return Ok(id); // already homed; a redelivered Welcome mints no second one
     }
+    // A group re-founded on a new suite takes over its predecessor's
+    // conversation, history and all — but only from the admin who runs it,
+    // or any member who knew the old id could hijack the chat.
+    if let Some(id) = group
+        .predecessor()
+        .and_then(|p| Conversation::for_group(&p))
+        .filter(|id| Conversation::is_admin(id, from))
+    {
+        Conversation::bind_group(&id, &gid)?;
+        return Ok(id);
+    }
     let Some(meta) = group.group_meta() else {
         let id = Conversation::for_peer(from)?;
         Conversation::bind_group(&id, &gid)?;
@@ -2064,10 +2709,12 @@ fn process_welcome_inbound<C: DhtClient>(
             Identity::spend_invite(&invite);
         }
     }
-    if let Err(e) = home_for_group(&group, &sender_ipk) {
+    match home_for_group(&group, &sender_ipk) {
+        // Back in, if a restore had left it waiting on this.
+        Ok(id) => crate::data::rejoin::completed(&id),
         // The MLS state is sound; we just have nowhere to show it. Say so
         // loudly rather than silently filing a group under someone's DM.
-        warn!("MLS: welcomed into a group we could not open a chat for: {e}");
+        Err(e) => warn!("MLS: welcomed into a group we could not open a chat for: {e}"),
     }
 
     info!(
@@ -2134,13 +2781,33 @@ fn persist_drained(
         let ts = crate::quic::server::accepted_at_secs(m.accepted_at_ms);
         // Post carries the quote target alongside the body; pre-v12 payloads
         // reach the same persist through legacy_body.
+        // What this build can't read is kept for a later one, as the live
+        // path does.
+        let keep = |content| {
+            if let Err(e) = crate::quic::server::keep_unreadable(
+                conversation,
+                fallback_sender,
+                sender_ipk,
+                did,
+                m.accepted_at_ms,
+                &m.plaintext,
+                content,
+            ) {
+                warn!("MESSAGE: could not keep a drained payload: {e}");
+            }
+            None
+        };
         let parsed = match AppPayload::deser(&m.plaintext) {
-            Ok(AppPayload::Post { reply_to, body }) => Some((reply_to, body)),
-            Ok(p) => legacy_body(p),
-            Err(_) => None,
+            Ok(p) if !crate::data::unsupported::readable(&p) => {
+                keep(matches!(p, AppPayload::Post { .. } | AppPayload::Forward { .. }))
+            },
+            Ok(AppPayload::Post { reply_to, body }) => Some((reply_to, body, false)),
+            Ok(AppPayload::Forward { body }) => Some((None, body, true)),
+            Ok(p) => legacy_body(p).map(|(reply_to, body)| (reply_to, body, false)),
+            Err(_) => keep(false),
         };
-        let Some((reply_to, body)) = parsed else { continue };
-        match save_inbound_body(&conversation, &sender_ipk, &did, ts, reply_to, body) {
+        let Some((reply_to, body, forwarded)) = parsed else { continue };
+        match save_inbound_body(&conversation, &sender_ipk, &did, ts, reply_to, body, forwarded) {
             Ok(Some((saved, content))) => MessageEv::Received {
                 id: saved.inner.id,
                 conversation,
@@ -2333,6 +3000,12 @@ const POLL_WELCOMES_MAX_RETRY: u8 = 5;
 static WELCOME_RETRY_COUNTS: once_cell::sync::Lazy<parking_lot::Mutex<HashMap<[u8; 8], u8>>> =
     once_cell::sync::Lazy::new(|| parking_lot::Mutex::new(HashMap::new()));
 
+/// Drop the fan-out and Welcome bookkeeping; the account it was for is gone.
+pub(crate) fn forget_all() {
+    LAST_ACCEPTED_AT.lock().clear();
+    WELCOME_RETRY_COUNTS.lock().clear();
+}
+
 /// Drain pending Welcomes from the K=3 homes of our IPK. Run once on
 /// every reconnect.
 ///
@@ -2606,15 +3279,18 @@ mod tests {
 
     /// Every cell of the revision matrix. Text and image both ride inside the
     /// frame the peer already holds, so those interchange; an attachment is
-    /// fetched device-to-device and a sticker is atomic, so each stays on its
-    /// own diagonal. Exhaustive rather than sampled — the refusals are the
-    /// half that protects an in-flight transfer.
+    /// fetched device-to-device (a video too) and a sticker is atomic, so each
+    /// stays on its own diagonal; a voice note or a poll revises to nothing. Exhaustive rather than
+    /// sampled — the refusals are the half that protects an in-flight transfer.
     #[test]
     fn revision_matrix_permits_inline_swaps_only() {
         use BodyKind::Attachment;
         use BodyKind::Image;
+        use BodyKind::Poll;
         use BodyKind::Sticker;
         use BodyKind::Text;
+        use BodyKind::Video;
+        use BodyKind::Voice;
 
         let allowed = [
             (Text, Text),
@@ -2623,11 +3299,10 @@ mod tests {
             (Image, Image),
             (Attachment, Attachment),
             (Sticker, Sticker),
+            (Video, Video),
         ];
-        for (from, to) in [Text, Image, Attachment, Sticker]
-            .into_iter()
-            .flat_map(|f| [Text, Image, Attachment, Sticker].into_iter().map(move |t| (f, t)))
-        {
+        let all = [Text, Image, Attachment, Sticker, Voice, Video, Poll];
+        for (from, to) in all.into_iter().flat_map(|f| all.into_iter().map(move |t| (f, t))) {
             let want = allowed.contains(&(from, to));
             assert_eq!(
                 from.revisable_to(to),
@@ -2660,6 +3335,10 @@ mod tests {
             blob: Some(vec![1, 2, 3]),
             thumb: None,
             file_id: None,
+            duration_ms: 0,
+            waveform: None,
+            sticker_pack: None,
+            sticker_id: 0,
         };
         let msg =
             crate::data::media::save_outgoing_with_media(&to, "cap", Some(quoted), &media).unwrap();
@@ -2674,6 +3353,165 @@ mod tests {
         );
     }
 
+    /// Point the data dir at the forward tests' own scratch dir. The dir
+    /// persists between runs, so every test below mints fresh ids.
+    fn forward_scratch() {
+        let dir = std::env::temp_dir().join("promtuz-forward-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+    }
+
+    fn image_row() -> crate::data::media::MediaRow {
+        crate::data::media::MediaRow {
+            kind: crate::data::media::KIND_IMAGE,
+            group_id: None,
+            mime: "image/avif".into(),
+            name: String::new(),
+            size: 3,
+            width: 2,
+            height: 2,
+            blob: Some(vec![1, 2, 3]),
+            thumb: None,
+            file_id: None,
+            duration_ms: 0,
+            waveform: None,
+            sticker_pack: None,
+            sticker_id: 0,
+        }
+    }
+
+    fn did_of(msg: &Message) -> [u8; 16] {
+        msg.inner.dispatch_id.clone().unwrap().try_into().unwrap()
+    }
+
+    /// A forward lands in the destination as a new row flagged forwarded, its
+    /// picture saved alongside, and goes out as a quote-less `Forward`
+    /// carrying the very same bytes.
+    #[test]
+    fn a_forward_resends_the_stored_body() {
+        forward_scratch();
+        let src: [u8; 16] = rand::random();
+        let dst = crate::data::conversation::Conversation::for_peer(&rand::random()).unwrap();
+        let original = crate::data::media::save_outgoing_with_media(
+            &src,
+            "cap",
+            Some([0x77u8; 16]),
+            &image_row(),
+        )
+        .unwrap();
+
+        let copy = build_forwarded_message(src, did_of(&original), dst).unwrap();
+        assert!(copy.inner.forwarded);
+        let stored = Message::get_by_dispatch(&dst, &did_of(&copy)).unwrap();
+        assert!(stored.inner.forwarded, "the flag is saved with the row, not after it");
+        assert!(crate::data::media::get(&dst, &did_of(&copy)).unwrap().is_some());
+        assert_eq!(
+            AppPayload::deser(&rebuild_pending_payload(&dst, &copy).unwrap()).unwrap(),
+            AppPayload::Forward {
+                body: Body::Image {
+                    caption:  "cap".into(),
+                    group_id: None,
+                    mime:     "image/avif".into(),
+                    width:    2,
+                    height:   2,
+                    data:     vec![1, 2, 3],
+                },
+            },
+        );
+    }
+
+    /// A poll forwards as a fresh poll: its own options under the new id, and
+    /// none of the source's ballots.
+    #[test]
+    fn a_forwarded_poll_brings_its_options_but_not_its_votes() {
+        forward_scratch();
+        let src: [u8; 16] = rand::random();
+        let dst = crate::data::conversation::Conversation::for_peer(&rand::random()).unwrap();
+        let poll = crate::data::poll::Poll {
+            question:  "lunch?".into(),
+            options:   vec!["yes".into(), "no".into()],
+            multi:     false,
+            anonymous: false,
+            closes_at: None,
+            closed_at: None,
+        };
+        let original = crate::data::poll::save_outgoing_with_poll(&src, None, &poll).unwrap();
+        crate::data::poll::apply_vote(&src, &did_of(&original), &[3u8; 32], &[0], 10).unwrap();
+
+        let copy = build_forwarded_message(src, did_of(&original), dst).unwrap();
+        let forwarded = crate::data::poll::get(&dst, &did_of(&copy)).unwrap();
+        assert_eq!(forwarded.options, poll.options);
+        assert!(crate::data::poll::votes_for(&dst).is_empty());
+        assert!(Message::get_by_dispatch(&dst, &did_of(&copy)).unwrap().inner.forwarded);
+    }
+
+    /// Only an ordinary message we can serve is forwarded: not a tombstone,
+    /// not a system line, not an attachment we hold no copy of — that last one
+    /// would be offered to people nobody can serve.
+    #[test]
+    fn a_forward_refuses_what_it_cannot_resend() {
+        forward_scratch();
+        let src: [u8; 16] = rand::random();
+        let dst = crate::data::conversation::Conversation::for_peer(&rand::random()).unwrap();
+
+        let gone = Message::save_outgoing(src, "oops", None).unwrap();
+        Message::apply_delete(&src, &did_of(&gone), true, None).unwrap();
+        assert!(build_forwarded_message(src, did_of(&gone), dst).is_err());
+
+        let system_did: [u8; 16] = rand::random();
+        Message::save_system(
+            src,
+            [5u8; 32],
+            &system_did,
+            crate::db::messages::SYSTEM_TITLED,
+            "new title",
+            1,
+            false,
+        )
+        .unwrap();
+        assert!(build_forwarded_message(src, system_did, dst).is_err());
+
+        let mut media = image_row();
+        media.kind = crate::data::media::KIND_ATTACHMENT;
+        media.blob = None;
+        media.file_id = Some(rand::random::<[u8; 32]>().to_vec());
+        let offer = crate::data::media::save_outgoing_with_media(&src, "", None, &media).unwrap();
+        assert!(build_forwarded_message(src, did_of(&offer), dst).is_err());
+
+        let missing: [u8; 16] = rand::random();
+        let text = Message::save_outgoing(src, "hi", None).unwrap();
+        assert!(build_forwarded_message(src, did_of(&text), missing).is_err());
+    }
+
+    /// An inbound `Forward` stores flagged with its picture in one go, and a
+    /// redelivery of it is a duplicate rather than a second row.
+    #[test]
+    fn an_inbound_forward_saves_flagged_once() {
+        forward_scratch();
+        let conv = crate::data::conversation::Conversation::for_peer(&rand::random()).unwrap();
+        let (sender, did): ([u8; 32], [u8; 16]) = (rand::random(), rand::random());
+        let body = Body::Image {
+            caption:  "cap".into(),
+            group_id: None,
+            mime:     "image/avif".into(),
+            width:    2,
+            height:   2,
+            data:     vec![1, 2, 3],
+        };
+
+        let (msg, text) =
+            save_inbound_body(&conv, &sender, &did, 5, None, body.clone(), true).unwrap().unwrap();
+        assert!(msg.inner.forwarded);
+        assert_eq!(text, "cap");
+        assert!(Message::get_any_by_dispatch(&conv, &did).unwrap().inner.forwarded);
+        assert!(crate::data::media::get(&conv, &did).unwrap().is_some());
+        assert!(save_inbound_body(&conv, &sender, &did, 5, None, body, true).unwrap().is_none());
+
+        // And what came in forwarded can be passed on again.
+        let dst = crate::data::conversation::Conversation::for_peer(&rand::random()).unwrap();
+        assert!(build_forwarded_message(conv, did, dst).unwrap().inner.forwarded);
+    }
+
     /// The defer predicate `attempt_send` relies on: a lazy-create that
     /// fails because the peer never published a KeyPackage must surface a
     /// `DhtClientError::NoStash` reachable through the anyhow chain
@@ -3159,4 +3997,182 @@ mod tests {
         let msg = format!("{:?}", r.unwrap_err());
         assert!(msg.contains("MAX_WELCOME_BYTES"), "error must cite MAX_WELCOME_BYTES, got: {msg}");
     }
+
+    /// Point the process-global stores at a scratch dir before anything opens them.
+    fn scratch_data_dir() {
+        let dir = std::env::temp_dir().join("promtuz-suite-move-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+    }
+
+    /// A suite check is spent at most once per wait, and each one that finds
+    /// the peer still on an old build doubles the wait, up to the cap.
+    #[test]
+    fn suite_probes_back_off_while_the_peer_stays_old() {
+        let conn = fresh_mls_conn();
+        let conn = conn.lock();
+        let gid = [0x5au8; 32];
+        let day = SUITE_PROBE_INTERVAL_MS;
+        let t0 = 1_000_000;
+
+        assert!(claim_suite_probe_in(&conn, &gid, t0), "first check is due at once");
+        assert!(
+            !claim_suite_probe_in(&conn, &gid, t0 + 1),
+            "concurrent sends must not check again",
+        );
+        assert!(claim_suite_probe_in(&conn, &gid, t0 + day));
+
+        back_off_suite_probe_in(&conn, &gid, t0 + day);
+        assert!(!claim_suite_probe_in(&conn, &gid, t0 + 2 * day));
+        assert!(claim_suite_probe_in(&conn, &gid, t0 + 3 * day));
+
+        let mut now = t0 + 3 * day;
+        for _ in 0..10 {
+            back_off_suite_probe_in(&conn, &gid, now);
+            now += SUITE_PROBE_MAX_GAP_MS;
+            assert!(
+                claim_suite_probe_in(&conn, &gid, now),
+                "the wait must stop growing at the cap",
+            );
+        }
+
+        assert!(claim_suite_probe_in(&conn, &[0x5bu8; 32], t0), "each group waits on its own");
+    }
+
+    /// A pair founded on the classical suite with a peer on an old build stays
+    /// there, without spending their KeyPackages on every send, and moves to the
+    /// hybrid suite once they update.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_classical_pair_moves_to_the_hybrid_suite_once_the_peer_updates() {
+        scratch_data_dir();
+        let alice = Node::new(0x61);
+        let bob = Node::new(0x62);
+        let dht = FakeDhtClient::new_arc();
+        let ctx = alice.ctx(dht.as_ref());
+        let old_build_kp =
+            || bob.stash.generate_as_old_build(&bob.provider, &bob.ipk_signer).unwrap();
+
+        dht.seed_kp(&bob.ipk, old_build_kp());
+        let group = lazy_create_group_paired(&ctx, &alice.ipk, &alice.ipk_signer, &bob.ipk, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            group.ciphersuite(),
+            CLASSICAL_CIPHERSUITE,
+            "an old build can only pair classically",
+        );
+        let gid = group.group_id();
+        let conversation = Conversation::for_peer(&bob.ipk).unwrap();
+        Conversation::bind_group(&conversation, &gid).unwrap();
+
+        // Still on the old build: the check spends one KeyPackage and backs off.
+        dht.seed_kp(&bob.ipk, old_build_kp());
+        let group =
+            upgrade_pair_group(&ctx, &conversation, group, &alice.ipk, &alice.ipk_signer)
+                .await;
+        assert_eq!(group.group_id(), gid);
+        assert!(dht.published_kps.lock()[&bob.ipk].is_empty());
+
+        // The next send checks nothing.
+        dht.seed_kp(&bob.ipk, old_build_kp());
+        let group =
+            upgrade_pair_group(&ctx, &conversation, group, &alice.ipk, &alice.ipk_signer)
+                .await;
+        assert_eq!(group.group_id(), gid);
+        assert_eq!(
+            dht.published_kps.lock()[&bob.ipk].len(),
+            1,
+            "a check that isn't due fetches nothing",
+        );
+        let now = crate::utils::systime().as_millis() as u64;
+        let probes = alice.provider.storage().conn().clone();
+        assert!(
+            !claim_suite_probe_in(&probes.lock(), &gid, now + SUITE_PROBE_INTERVAL_MS + 60_000),
+            "an old build must double the wait",
+        );
+
+        // Bob updates; his stash now leads with a classical KeyPackage that
+        // declares the hybrid suite, which is fetched past.
+        dht.published_kps.lock().clear();
+        probes.lock().execute("DELETE FROM suite_probes", []).unwrap();
+        let kps = bob.stash.ensure_stash_full(&bob.provider, &bob.ipk_signer).unwrap();
+        let (pq, classical): (Vec<_>, Vec<_>) = kps.into_iter().partition(|r| {
+            crate::mls::keypackage::minted_on(&r.kp_bytes.0) == Some(PROMTUZ_CIPHERSUITE)
+        });
+        dht.seed_kp(&bob.ipk, classical[0].clone());
+        dht.seed_kp(&bob.ipk, pq[0].clone());
+        let moved =
+            upgrade_pair_group(&ctx, &conversation, group, &alice.ipk, &alice.ipk_signer)
+                .await;
+        assert_ne!(moved.group_id(), gid);
+        assert_eq!(moved.ciphersuite(), PROMTUZ_CIPHERSUITE);
+        assert_eq!(Conversation::group_of(&conversation), Some(moved.group_id()));
+
+        let env = dht.welcomes_published.lock().last().cloned().unwrap();
+        let bob_group = process_welcome(&bob.provider, &env).unwrap();
+        assert_eq!(bob_group.group_id(), moved.group_id());
+    }
+
+    /// `founder` re-founds `old_id` on the hybrid suite with `joiner` in it,
+    /// and `joiner` takes the Welcome.
+    async fn successor_welcomed(
+        founder: &Node, joiner: &Node, old_id: &[u8; 32], meta: &crate::mls::GroupMeta,
+    ) -> MlsGroupHandle {
+        let dht = FakeDhtClient::new_arc();
+        let rec =
+            joiner.stash.generate_on(&joiner.provider, &joiner.ipk_signer, PROMTUZ_CIPHERSUITE);
+        dht.seed_kp(&joiner.ipk, rec.unwrap());
+        let (kp, kp_ref) =
+            fetch_verified_keypackage(&founder.ctx(dht.as_ref()), &joiner.ipk).await.unwrap();
+
+        let gid = mint_group_id(&founder.ipk);
+        let (leaf, _) = build_self_credential(&founder.ipk).unwrap();
+        leaf.store(founder.provider.storage()).unwrap();
+        let mut group = MlsGroupHandle::create_successor(
+            &founder.provider,
+            &leaf,
+            &founder.ipk,
+            leaf.public(),
+            &gid,
+            PROMTUZ_CIPHERSUITE,
+            (meta, old_id),
+        )
+        .unwrap();
+        let (_commit, welcome) = group.add_members(&founder.provider, &leaf, &[kp]).unwrap();
+        group.merge_pending_commit(&founder.provider).unwrap();
+        let env = make_welcome_envelope(
+            welcome,
+            gid,
+            founder.ipk,
+            joiner.ipk,
+            kp_ref,
+            &founder.ipk_signer,
+        )
+        .unwrap();
+        process_welcome(&joiner.provider, &env).unwrap()
+    }
+
+    /// A successor naming our group takes its conversation over only when the
+    /// group's admin sent it. Anyone else who knew the old id gets a
+    /// conversation of their own, and ours stays where it was.
+    #[tokio::test(flavor = "current_thread")]
+    async fn home_for_group_carries_a_chat_over_only_from_its_admin() {
+        scratch_data_dir();
+        let alice = Node::new(0x63);
+        let mallory = Node::new(0x64);
+        let bob = Node::new(0x65);
+        let old_id: [u8; 32] = rand::random();
+        let conversation = Conversation::join_group(&alice.ipk, &[alice.ipk, mallory.ipk]).unwrap();
+        Conversation::bind_group(&conversation, &old_id).unwrap();
+        let meta = crate::mls::GroupMeta { title: "g".into(), founder: alice.ipk };
+
+        let forged = successor_welcomed(&mallory, &bob, &old_id, &meta).await;
+        let home = home_for_group(&forged, &mallory.ipk).unwrap();
+        assert_ne!(home, conversation, "a member must not take over the chat");
+        assert_eq!(Conversation::group_of(&conversation), Some(old_id));
+
+        let real = successor_welcomed(&alice, &bob, &old_id, &meta).await;
+        assert_eq!(home_for_group(&real, &alice.ipk).unwrap(), conversation);
+        assert_eq!(Conversation::group_of(&conversation), Some(real.group_id()));
+    }
 }
@@ -0,0 +1,265 @@
+//! `PromtuzCrypto`: the `OpenMlsCrypto` behind [`super::PromtuzMlsProvider`].
+//!
+//! `openmls_rust_crypto::RustCrypto` implements the classical suites only.
+//! The PQ-hybrid [`super::group::PROMTUZ_CIPHERSUITE`] shares every
+//! primitive with the classical one — Ed25519, SHA-256, ChaCha20-Poly1305 —
+//! except its HPKE KEM, X-Wing (X25519 + ML-KEM-768). So this wraps
+//! `RustCrypto` and routes only HPKE under X-Wing to hpke-rs's libcrux
+//! backend; everything else, randomness included, is the stock provider.
+//!
+//! HPKE is what seals the path secrets of every commit and the joiner secret
+//! in every Welcome, so it is what a recorded transcript falls to. With
+//! X-Wing, decrypting one needs both X25519 and ML-KEM-768 broken.
+//!
+//! X-Wing is run under its assigned HPKE code point (`0x647a`), not the
+//! draft one openmls carries in `HpkeKemType`. The KEM id enters HPKE's key
+//! schedule, so every promtuz client must agree on it; nothing outside
+//! promtuz ever opens our HPKE ciphertexts.
+
+use hpke_rs::Hpke;
+use hpke_rs::HpkeError;
+use hpke_rs::hpke_types::AeadAlgorithm;
+use hpke_rs::hpke_types::KdfAlgorithm;
+use hpke_rs::hpke_types::KemAlgorithm;
+use hpke_rs::libcrux::HpkeLibcrux;
+use openmls_rust_crypto::RustCrypto;
+use openmls_traits::crypto::OpenMlsCrypto;
+use openmls_traits::random::OpenMlsRand;
+use openmls_traits::types::AeadType;
+use openmls_traits::types::Ciphersuite;
+use openmls_traits::types::CryptoError;
+use openmls_traits::types::ExporterSecret;
+use openmls_traits::types::HashType;
+use openmls_traits::types::HpkeAeadType;
+use openmls_traits::types::HpkeCiphertext;
+use openmls_traits::types::HpkeConfig;
+use openmls_traits::types::HpkeKdfType;
+use openmls_traits::types::HpkeKemType;
+use openmls_traits::types::HpkeKeyPair;
+use openmls_traits::types::KemOutput;
+use openmls_traits::types::SignatureScheme;
+use tls_codec::SecretVLBytes;
+
+use super::group::SUPPORTED_CIPHERSUITES;
+
+/// `RustCrypto` plus X-Wing HPKE. See the module docs.
+#[derive(Default)]
+pub struct PromtuzCrypto {
+    classical: RustCrypto,
+}
+
+/// The hpke-rs instance for `config` if its KEM is X-Wing, `None` for the
+/// classical KEMs `RustCrypto` handles itself.
+fn xwing(config: &HpkeConfig) -> Option<Hpke<HpkeLibcrux>> {
+    if config.0 != HpkeKemType::XWingKemDraft6 {
+        return None;
+    }
+    let kdf = match config.1 {
+        HpkeKdfType::HkdfSha256 => KdfAlgorithm::HkdfSha256,
+        HpkeKdfType::HkdfSha384 => KdfAlgorithm::HkdfSha384,
+        HpkeKdfType::HkdfSha512 => KdfAlgorithm::HkdfSha512,
+    };
+    let aead = match config.2 {
+        HpkeAeadType::AesGcm128 => AeadAlgorithm::Aes128Gcm,
+        HpkeAeadType::AesGcm256 => AeadAlgorithm::Aes256Gcm,
+        HpkeAeadType::ChaCha20Poly1305 => AeadAlgorithm::ChaCha20Poly1305,
+        HpkeAeadType::Export => AeadAlgorithm::HpkeExport,
+    };
+    Some(Hpke::new(hpke_rs::Mode::Base, KemAlgorithm::XWingDraft06, kdf, aead))
+}
+
+fn invalid_input_or(e: HpkeError, otherwise: CryptoError) -> CryptoError {
+    match e {
+        HpkeError::InvalidInput => CryptoError::InvalidLength,
+        _ => otherwise,
+    }
+}
+
+impl OpenMlsCrypto for PromtuzCrypto {
+    fn supports(&self, ciphersuite: Ciphersuite) -> Result<(), CryptoError> {
+        if SUPPORTED_CIPHERSUITES.contains(&ciphersuite) {
+            Ok(())
+        } else {
+            Err(CryptoError::UnsupportedCiphersuite)
+        }
+    }
+
+    fn supported_ciphersuites(&self) -> Vec<Ciphersuite> {
+        SUPPORTED_CIPHERSUITES.to_vec()
+    }
+
+    fn hkdf_extract(
+        &self, hash_type: HashType, salt: &[u8], ikm: &[u8],
+    ) -> Result<SecretVLBytes, CryptoError> {
+        self.classical.hkdf_extract(hash_type, salt, ikm)
+    }
+
+    fn hmac(
+        &self, hash_type: HashType, key: &[u8], message: &[u8],
+    ) -> Result<SecretVLBytes, CryptoError> {
+        self.classical.hmac(hash_type, key, message)
+    }
+
+    fn hkdf_expand(
+        &self, hash_type: HashType, prk: &[u8], info: &[u8], okm_len: usize,
+    ) -> Result<SecretVLBytes, CryptoError> {
+        self.classical.hkdf_expand(hash_type, prk, info, okm_len)
+    }
+
+    fn hash(&self, hash_type: HashType, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.classical.hash(hash_type, data)
+    }
+
+    fn aead_encrypt(
+        &self, alg: AeadType, key: &[u8], data: &[u8], nonce: &[u8], aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.classical.aead_encrypt(alg, key, data, nonce, aad)
+    }
+
+    fn aead_decrypt(
+        &self, alg: AeadType, key: &[u8], ct_tag: &[u8], nonce: &[u8], aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.classical.aead_decrypt(alg, key, ct_tag, nonce, aad)
+    }
+
+    fn signature_key_gen(&self, alg: SignatureScheme) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        self.classical.signature_key_gen(alg)
+    }
+
+    fn verify_signature(
+        &self, alg: SignatureScheme, data: &[u8], pk: &[u8], signature: &[u8],
+    ) -> Result<(), CryptoError> {
+        self.classical.verify_signature(alg, data, pk, signature)
+    }
+
+    fn sign(&self, alg: SignatureScheme, data: &[u8], key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.classical.sign(alg, data, key)
+    }
+
+    fn hpke_seal(
+        &self, config: HpkeConfig, pk_r: &[u8], info: &[u8], aad: &[u8], ptxt: &[u8],
+    ) -> Result<HpkeCiphertext, CryptoError> {
+        let Some(mut hpke) = xwing(&config) else {
+            return self.classical.hpke_seal(config, pk_r, info, aad, ptxt);
+        };
+        let (kem_output, ciphertext) = hpke
+            .seal(&pk_r.into(), info, aad, ptxt, None, None, None)
+            .map_err(|e| invalid_input_or(e, CryptoError::CryptoLibraryError))?;
+        Ok(HpkeCiphertext { kem_output: kem_output.into(), ciphertext: ciphertext.into() })
+    }
+
+    fn hpke_open(
+        &self, config: HpkeConfig, input: &HpkeCiphertext, sk_r: &[u8], info: &[u8], aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        let Some(hpke) = xwing(&config) else {
+            return self.classical.hpke_open(config, input, sk_r, info, aad);
+        };
+        hpke.open(
+            input.kem_output.as_slice(),
+            &sk_r.into(),
+            info,
+            aad,
+            input.ciphertext.as_slice(),
+            None,
+            None,
+            None,
+        )
+        .map_err(|_| CryptoError::HpkeDecryptionError)
+    }
+
+    fn hpke_setup_sender_and_export(
+        &self, config: HpkeConfig, pk_r: &[u8], info: &[u8], exporter_context: &[u8],
+        exporter_length: usize,
+    ) -> Result<(KemOutput, ExporterSecret), CryptoError> {
+        let Some(mut hpke) = xwing(&config) else {
+            return self.classical.hpke_setup_sender_and_export(
+                config,
+                pk_r,
+                info,
+                exporter_context,
+                exporter_length,
+            );
+        };
+        let (kem_output, context) = hpke
+            .setup_sender(&pk_r.into(), info, None, None, None)
+            .map_err(|_| CryptoError::SenderSetupError)?;
+        let exported = context
+            .export(exporter_context, exporter_length)
+            .map_err(|_| CryptoError::ExporterError)?;
+        Ok((kem_output, exported.into()))
+    }
+
+    fn hpke_setup_receiver_and_export(
+        &self, config: HpkeConfig, enc: &[u8], sk_r: &[u8], info: &[u8], exporter_context: &[u8],
+        exporter_length: usize,
+    ) -> Result<ExporterSecret, CryptoError> {
+        let Some(hpke) = xwing(&config) else {
+            return self.classical.hpke_setup_receiver_and_export(
+                config,
+                enc,
+                sk_r,
+                info,
+                exporter_context,
+                exporter_length,
+            );
+        };
+        let context = hpke
+            .setup_receiver(enc, &sk_r.into(), info, None, None, None)
+            .map_err(|_| CryptoError::ReceiverSetupError)?;
+        let exported = context
+            .export(exporter_context, exporter_length)
+            .map_err(|_| CryptoError::ExporterError)?;
+        Ok(exported.into())
+    }
+
+    fn derive_hpke_keypair(
+        &self, config: HpkeConfig, ikm: &[u8],
+    ) -> Result<HpkeKeyPair, CryptoError> {
+        let Some(hpke) = xwing(&config) else {
+            return self.classical.derive_hpke_keypair(config, ikm);
+        };
+        let (private, public) = hpke
+            .derive_key_pair(ikm)
+            .map_err(|e| invalid_input_or(e, CryptoError::CryptoLibraryError))?
+            .into_keys();
+        Ok(HpkeKeyPair { private: private.as_slice().into(), public: public.as_slice().into() })
+    }
+}
+
+impl OpenMlsRand for PromtuzCrypto {
+    type Error = <RustCrypto as OpenMlsRand>::Error;
+
+    fn random_array<const N: usize>(&self) -> Result<[u8; N], Self::Error> {
+        self.classical.random_array()
+    }
+
+    fn random_vec(&self, len: usize) -> Result<Vec<u8>, Self::Error> {
+        self.classical.random_vec(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mls::group::PROMTUZ_CIPHERSUITE;
+
+    /// An X-Wing key derived from a seed opens what was sealed to it, and a
+    /// classical suite still takes the stock path.
+    #[test]
+    fn xwing_hpke_round_trips_beside_the_classical_suite() {
+        let crypto = PromtuzCrypto::default();
+        for suite in SUPPORTED_CIPHERSUITES {
+            let config = || suite.hpke_config();
+            let kp = crypto.derive_hpke_keypair(config(), &[7u8; 32]).expect("derive");
+            let sealed = crypto
+                .hpke_seal(config(), &kp.public, b"info", b"aad", b"path secret")
+                .expect("seal");
+            let opened =
+                crypto.hpke_open(config(), &sealed, &kp.private, b"info", b"aad").expect("open");
+            assert_eq!(opened, b"path secret");
+        }
+        let xwing_pk = crypto.derive_hpke_keypair(PROMTUZ_CIPHERSUITE.hpke_config(), &[7u8; 32]);
+        // X-Wing public keys carry the ML-KEM-768 encapsulation key.
+        assert!(xwing_pk.expect("derive").public.len() > 1_000);
+    }
+}
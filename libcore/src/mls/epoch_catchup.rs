@@ -572,6 +572,7 @@ mod tests {
             alice.sig_kp.public(),
             &[0xAA; 32],
             None,
+            PROMTUZ_CIPHERSUITE,
         )
         .expect("create alice group");
         let bob_kp = make_kp(&provider_b, &bob);
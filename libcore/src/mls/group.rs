@@ -9,16 +9,25 @@
 //!   `messaging.rs` wiring).
 //! - **Export secret** for SFrame integration.
 //!
-//! # Cipher suite pin
+//! # Cipher suites
 //!
-//! Hard-pinned to
-//! `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519` (suite
-//! `0x0003`). Note that openmls 0.8's
-//! `MlsGroupCreateConfig::default()` selects a *different* suite
-//! (`MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519`) — we override at
-//! construction time. **Mismatch on this would silently shift the
+//! New KeyPackages and groups use [`PROMTUZ_CIPHERSUITE`],
+//! `MLS_256_XWING_CHACHA20POLY1305_SHA256_Ed25519` (suite `0x004D`): the
+//! classical suite with its HPKE KEM swapped for X-Wing (X25519 +
+//! ML-KEM-768), so a recorded Welcome or commit stays sealed against a
+//! future quantum adversary. [`CLASSICAL_CIPHERSUITE`] (`0x0003`) remains
+//! supported for groups founded before it and peers still publishing
+//! classical KeyPackages.
+//!
+//! A group's suite is fixed for its lifetime, so it is negotiated at
+//! founding from the members' KeyPackages ([`negotiate_ciphersuite`]). A
+//! 1:1 group on the classical suite migrates by being re-founded once the
+//! peer's KeyPackages are on the hybrid one (`messaging.rs`).
+//!
+//! Note that openmls 0.8's `MlsGroupCreateConfig::default()` selects
+//! `MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519` — we always pass the
+//! suite explicitly. **Leaving it to the default would silently shift the
 //! AEAD from ChaCha20-Poly1305 to AES-128-GCM, breaking the spec.**
-//! Pinned in [`PROMTUZ_CIPHERSUITE`].
 //!
 //! # Group ID shape
 //!
@@ -52,10 +61,46 @@ use serde::Serialize;
 use super::provider::PromtuzMlsProvider;
 use super::types::MlsGroupError;
 
-/// The single cipher suite used across promtuz.
+/// The cipher suite promtuz mints KeyPackages on and founds groups on
+/// whenever every member can join it.
 pub const PROMTUZ_CIPHERSUITE: Ciphersuite =
+    Ciphersuite::MLS_256_XWING_CHACHA20POLY1305_SHA256_Ed25519;
+
+/// The suite every group used before [`PROMTUZ_CIPHERSUITE`]. Still accepted
+/// so those groups keep working and older peers can still be reached.
+pub const CLASSICAL_CIPHERSUITE: Ciphersuite =
     Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519;
 
+/// Every suite this build can run a group on, most preferred first. Leaves
+/// declare all of them in their capabilities.
+pub const SUPPORTED_CIPHERSUITES: [Ciphersuite; 2] = [PROMTUZ_CIPHERSUITE, CLASSICAL_CIPHERSUITE];
+
+/// The suite to found a group of us plus `members` on, from one KeyPackage
+/// of each.
+///
+/// A KeyPackage names exactly one suite and can only join a group on it, but
+/// its leaf declares every suite its owner's build runs. This build stashes
+/// KeyPackages on both suites side by side, so a member whose KeyPackage only
+/// *declares* the chosen suite has one on it too, one fetch away
+/// ([`joins_on`]). A peer on an older build declares the classical suite
+/// alone, and a group with them in it has to be classical; so does one too
+/// big for an X-Wing Welcome ([`super::MAX_PQ_GROUP_MEMBERS`]).
+pub fn negotiate_ciphersuite(members: &[KeyPackage]) -> Result<Ciphersuite> {
+    let pq_fits = members.len() < super::MAX_PQ_GROUP_MEMBERS;
+    SUPPORTED_CIPHERSUITES
+        .into_iter()
+        .filter(|&suite| suite != PROMTUZ_CIPHERSUITE || pq_fits)
+        .find(|&suite| members.iter().all(|kp| joins_on(kp, suite)))
+        .ok_or(MlsGroupError::BadCipherSuite)
+}
+
+/// Whether `kp`'s owner can join a group on `suite`: it is `kp`'s own suite,
+/// or one its leaf declares and so one its owner also mints KeyPackages on.
+pub fn joins_on(kp: &KeyPackage, suite: Ciphersuite) -> bool {
+    kp.ciphersuite() == suite
+        || kp.leaf_node().capabilities().ciphersuites().contains(&suite.into())
+}
+
 /// Convenience type alias — every result in this module funnels
 /// failures through [`MlsGroupError`].
 type Result<T> = std::result::Result<T, MlsGroupError>;
@@ -86,6 +131,22 @@ const PROMTUZ_GROUP_META_EXT: u16 = 0xF100;
 /// requires a joining leaf to support every extension in the group context.
 pub const GROUP_META_EXTENSION: ExtensionType = ExtensionType::Unknown(PROMTUZ_GROUP_META_EXT);
 
+/// Extension type naming the group this one replaces — see
+/// [`MlsGroupHandle::create_successor`]. Its body is the predecessor's 32-byte
+/// group id.
+const PROMTUZ_PREDECESSOR_EXT: u16 = 0xF101;
+
+/// [`PROMTUZ_PREDECESSOR_EXT`] as openmls names it. Declared by every
+/// KeyPackage for the same reason as [`GROUP_META_EXTENSION`].
+pub const PREDECESSOR_EXTENSION: ExtensionType =
+    ExtensionType::Unknown(PROMTUZ_PREDECESSOR_EXT);
+
+/// `meta` as the group-context extension that carries it.
+fn meta_extension(meta: &GroupMeta) -> Result<Extension> {
+    let bytes = postcard::to_allocvec(meta).map_err(|e| MlsGroupError::Codec(e.to_string()))?;
+    Ok(Extension::Unknown(PROMTUZ_GROUP_META_EXT, UnknownExtension(bytes)))
+}
+
 /// What a group *is*, decided by whoever created it and carried in the MLS
 /// group context.
 ///
@@ -137,10 +198,38 @@ impl MlsGroupHandle {
     /// `group_id` is the 32-byte promtuz group identifier. `meta` marks this a
     /// group chat rather than a 1:1 — see [`GroupMeta`]; `None` builds a pair.
     ///
-    /// **Cipher suite is pinned** to [`PROMTUZ_CIPHERSUITE`].
+    /// `ciphersuite` must be the suite of every KeyPackage the group will add
+    /// — see [`negotiate_ciphersuite`].
     pub fn create<S: Signer>(
         provider: &PromtuzMlsProvider, signer: &S, own_ipk: &[u8; 32],
         leaf_signing_public: &[u8], group_id: &[u8; 32], meta: Option<&GroupMeta>,
+        ciphersuite: Ciphersuite,
+    ) -> Result<Self> {
+        let context = meta.map(meta_extension).transpose()?.into_iter().collect();
+        Self::found(provider, signer, own_ipk, leaf_signing_public, group_id, ciphersuite, context)
+    }
+
+    /// Found the group that replaces a predecessor on `ciphersuite`.
+    /// `succeeding` is the predecessor's meta, carried over, and its group id.
+    /// Members Welcomed into it re-home the predecessor's conversation onto it
+    /// instead of opening a new one (see [`Self::predecessor`]).
+    pub fn create_successor<S: Signer>(
+        provider: &PromtuzMlsProvider, signer: &S, own_ipk: &[u8; 32],
+        leaf_signing_public: &[u8], group_id: &[u8; 32], ciphersuite: Ciphersuite,
+        succeeding: (&GroupMeta, &[u8; 32]),
+    ) -> Result<Self> {
+        let (meta, predecessor) = succeeding;
+        let context = vec![
+            meta_extension(meta)?,
+            Extension::Unknown(PROMTUZ_PREDECESSOR_EXT, UnknownExtension(predecessor.to_vec())),
+        ];
+        Self::found(provider, signer, own_ipk, leaf_signing_public, group_id, ciphersuite, context)
+    }
+
+    fn found<S: Signer>(
+        provider: &PromtuzMlsProvider, signer: &S, own_ipk: &[u8; 32],
+        leaf_signing_public: &[u8], group_id: &[u8; 32], ciphersuite: Ciphersuite,
+        context: Vec<Extension>,
     ) -> Result<Self> {
         let credential = BasicCredential::new(own_ipk.to_vec());
         let credential_with_key = CredentialWithKey {
@@ -148,8 +237,12 @@ impl MlsGroupHandle {
             signature_key: leaf_signing_public.to_vec().into(),
         };
 
-        let mut create_config = MlsGroupCreateConfig::builder()
-            .ciphersuite(PROMTUZ_CIPHERSUITE)
+        // The founder's own leaf has to declare every context extension too,
+        // not just the leaves it adds — RFC 9420 holds every member to the
+        // same bar, including whoever put the extension there.
+        let declared: Vec<ExtensionType> = context.iter().map(Extension::extension_type).collect();
+        let create_config = MlsGroupCreateConfig::builder()
+            .ciphersuite(ciphersuite)
             // Handshake framing stays opaque to the relay. Pinned rather than
             // inherited from the openmls default so it cannot drift.
             .wire_format_policy(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY)
@@ -158,28 +251,19 @@ impl MlsGroupHandle {
             // inside the GroupInfo / Welcome rather than out-of-band.
             // Without it joiners would require a separately-conveyed
             // RatchetTreeIn — we don't have that channel today.
-            .use_ratchet_tree_extension(true);
-
-        if let Some(meta) = meta {
-            let bytes = postcard::to_allocvec(meta)
-                .map_err(|e| MlsGroupError::Codec(e.to_string()))?;
-            let ext = Extension::Unknown(PROMTUZ_GROUP_META_EXT, UnknownExtension(bytes));
-            let exts = Extensions::single(ext)
-                .map_err(|e| MlsGroupError::Codec(format!("group meta extension: {e}")))?;
-            // The founder's own leaf has to declare the extension too, not just
-            // the leaves it adds — RFC 9420 holds every member to the same bar,
-            // including whoever put the extension there.
-            create_config = create_config
-                .with_group_context_extensions(exts)
-                .capabilities(Capabilities::new(
-                    None,
-                    Some(&[PROMTUZ_CIPHERSUITE]),
-                    Some(&[GROUP_META_EXTENSION]),
-                    None,
-                    None,
-                ));
-        }
-        let create_config = create_config.build();
+            .use_ratchet_tree_extension(true)
+            .with_group_context_extensions(
+                Extensions::from_vec(context)
+                    .map_err(|e| MlsGroupError::Codec(format!("group context extension: {e}")))?,
+            )
+            .capabilities(Capabilities::new(
+                None,
+                Some(&SUPPORTED_CIPHERSUITES),
+                Some(&declared),
+                None,
+                None,
+            ))
+            .build();
 
         let mls_group = MlsGroup::new_with_group_id(
             provider,
@@ -244,6 +328,22 @@ impl MlsGroupHandle {
         Ok(commit)
     }
 
+    /// Replace the leaves at `members` with the matching `key_packages` in
+    /// one Commit — how a member who lost its state is brought back without
+    /// a window where it is out of the group. Returns `(commit, welcome)`.
+    ///
+    /// Caller must merge pending commit afterwards.
+    pub fn swap_members<S: Signer>(
+        &mut self, provider: &PromtuzMlsProvider, signer: &S, members: &[LeafNodeIndex],
+        key_packages: &[KeyPackage],
+    ) -> Result<(MlsMessageOut, MlsMessageOut)> {
+        let out = self
+            .inner
+            .swap_members(provider, signer, members, key_packages)
+            .map_err(MlsGroupError::from_openmls)?;
+        Ok((out.commit, out.welcome))
+    }
+
     /// Rotate own leaf key (Update commit — PCS).
     ///
     /// The new leaf's HPKE init key + signature key are derived
@@ -287,6 +387,22 @@ impl MlsGroupHandle {
             .map_err(MlsGroupError::from_openmls)
     }
 
+    /// Pad application messages from here on to multiples of
+    /// `padding_size` (see [`super::padding`]). openmls keeps the padding in
+    /// the group's stored configuration, so only a change of size class costs
+    /// a write.
+    pub fn set_padding_size(
+        &mut self, provider: &PromtuzMlsProvider, padding_size: usize,
+    ) -> Result<()> {
+        let current = self.inner.configuration();
+        if current.padding_size() == padding_size {
+            return Ok(());
+        }
+        self.inner
+            .set_configuration(provider.storage(), &join_config(padding_size))
+            .map_err(MlsGroupError::Storage)
+    }
+
     /// Process an incoming MLS message.
     ///
     /// Returns `ProcessedMessageContent` — application payloads,
@@ -336,6 +452,11 @@ impl MlsGroupHandle {
         self.inner.epoch().as_u64()
     }
 
+    /// The suite this group was founded on, fixed for its lifetime.
+    pub fn ciphersuite(&self) -> Ciphersuite {
+        self.inner.ciphersuite()
+    }
+
     /// Current group ID as a 32-byte array.
     ///
     /// Returns the first 32 bytes of the underlying `GroupId` (the
@@ -373,6 +494,17 @@ impl MlsGroupHandle {
         })
     }
 
+    /// The group this one replaced, if it was founded by
+    /// [`Self::create_successor`].
+    pub fn predecessor(&self) -> Option<[u8; 32]> {
+        self.inner.extensions().iter().find_map(|e| match e {
+            Extension::Unknown(PROMTUZ_PREDECESSOR_EXT, UnknownExtension(bytes)) => {
+                bytes.as_slice().try_into().ok()
+            },
+            _ => None,
+        })
+    }
+
     /// Iterate members. Returned items expose `index: LeafNodeIndex`
     /// and `credential: Credential`; the
     /// `BasicCredential::identity` carries each member's IPK bytes.
@@ -437,6 +569,20 @@ pub fn mls_message_to_bytes(msg: &MlsMessageOut) -> Result<Vec<u8>> {
     msg.tls_serialize_detached().map_err(MlsGroupError::from_codec)
 }
 
+/// The configuration every group runs on once joined, padded to
+/// `padding_size`. openmls has no setter for a single field, so a change of
+/// padding rebuilds the whole of it here, the one place it is defined; the
+/// founder's create config above pins the same values.
+pub(crate) fn join_config(padding_size: usize) -> MlsGroupJoinConfig {
+    MlsGroupJoinConfig::builder()
+        .wire_format_policy(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY)
+        .padding_size(padding_size)
+        // A member who joined by Welcome adds and heals too, and the Welcomes
+        // it sends need the tree for the same reason the founder's do.
+        .use_ratchet_tree_extension(true)
+        .build()
+}
+
 /// TLS-deserialise an `MlsMessageIn` from envelope bytes. Returned
 /// type carries the wire-format tag and gives the caller access to
 /// `extract()` / `try_into_protocol_message()` to dispatch into
@@ -493,6 +639,18 @@ mod tests {
     /// `provider`'s storage. The KeyPackage itself ships across to a
     /// counterparty's group; the bundle (init+enc keys) stays local.
     fn make_kp(provider: &PromtuzMlsProvider, party: &Party) -> KeyPackage {
+        make_kp_on(provider, party, PROMTUZ_CIPHERSUITE)
+    }
+
+    /// [`make_kp`] on an explicit suite, as a peer on an older build mints.
+    fn make_kp_on(provider: &PromtuzMlsProvider, party: &Party, suite: Ciphersuite) -> KeyPackage {
+        make_kp_declaring(provider, party, suite, &[suite])
+    }
+
+    /// [`make_kp_on`] declaring `declared`, as this build mints on either suite.
+    fn make_kp_declaring(
+        provider: &PromtuzMlsProvider, party: &Party, suite: Ciphersuite, declared: &[Ciphersuite],
+    ) -> KeyPackage {
         let credential = BasicCredential::new(party.ipk.to_vec());
         let cwk = CredentialWithKey {
             credential: credential.into(),
@@ -501,12 +659,12 @@ mod tests {
         let bundle = KeyPackage::builder()
             .leaf_node_capabilities(Capabilities::new(
                 None,
-                Some(&[PROMTUZ_CIPHERSUITE]),
+                Some(declared),
                 None,
                 None,
                 None,
             ))
-            .build(PROMTUZ_CIPHERSUITE, provider, &party.sig_kp, cwk)
+            .build(suite, provider, &party.sig_kp, cwk)
             .expect("build kp");
         bundle.key_package().clone()
     }
@@ -520,6 +678,7 @@ mod tests {
             party.sig_kp.public(),
             gid,
             None,
+            PROMTUZ_CIPHERSUITE,
         )
         .expect("create group")
     }
@@ -721,6 +880,90 @@ mod tests {
         assert_eq!(seal(&mut group, b"ok"), seal(&mut group, &vec![b'x'; 60]));
     }
 
+    /// What a relay sees of a receipt, a reaction and a short text is one
+    /// length under every policy, and under size classes so is a longer
+    /// text of the same order. Bob still reads each one.
+    #[test]
+    fn receipts_reactions_and_short_posts_seal_to_one_size() {
+        use common::proto::mls_wire::AppPayload;
+        use common::proto::mls_wire::Body;
+        use common::proto::mls_wire::ReceiptKind;
+        use common::proto::pack::Packer as _;
+
+        use crate::mls::padding::PaddingPolicy;
+        use crate::mls::padding::padding_size_for;
+
+        let provider_a = build_provider();
+        let provider_b = build_provider();
+        let alice = Party::new(&provider_a, 1);
+        let bob = Party::new(&provider_b, 2);
+        let mut alice_group = create_group(&provider_a, &alice, &[0xAA; 32]);
+        let (_c, welcome) = alice_group
+            .add_members(&provider_a, &alice.sig_kp, &[make_kp(&provider_b, &bob)])
+            .expect("add");
+        alice_group.merge_pending_commit(&provider_a).expect("merge");
+        let staged = StagedWelcome::new_from_welcome(
+            &provider_b,
+            &MlsGroupJoinConfig::default(),
+            extract_welcome_via_tls(welcome),
+            None,
+        )
+        .expect("staged");
+        let mut bob_group = MlsGroupHandle::wrap(staged.into_group(&provider_b).expect("into"));
+
+        let post = |len: usize| AppPayload::Post {
+            reply_to: None,
+            body:     Body::Text("x".repeat(len)),
+        };
+        let payloads = [
+            AppPayload::Receipt { kind: ReceiptKind::Read, upto: [7; 16] },
+            AppPayload::React { target: [7; 16], emoji: "\u{1F44D}".into(), add: true },
+            post(5),
+            post(120),
+        ];
+        let mut seal = |policy: PaddingPolicy, payload: &AppPayload| {
+            let plaintext = payload.ser().expect("encode");
+            let padding = padding_size_for(policy, plaintext.len());
+            alice_group.set_padding_size(&provider_a, padding).expect("pad");
+            let msg = alice_group
+                .create_application_message(&provider_a, &alice.sig_kp, &plaintext)
+                .expect("encrypt");
+            let bytes = mls_message_to_bytes(&msg).expect("ser");
+            let protocol = mls_message_from_bytes(&bytes)
+                .expect("deser")
+                .try_into_protocol_message()
+                .expect("protocol");
+            match bob_group.process_incoming(&provider_b, protocol).expect("decrypt").content {
+                ProcessedMessageContent::ApplicationMessage(app) => {
+                    assert_eq!(app.into_bytes(), plaintext, "padding is stripped on decrypt")
+                },
+                other => panic!("expected app msg, got {other:?}"),
+            }
+            bytes.len()
+        };
+
+        for policy in [PaddingPolicy::Block, PaddingPolicy::SizeClass, PaddingPolicy::Uniform] {
+            let sizes: Vec<usize> = payloads.iter().map(|p| seal(policy, p)).collect();
+            assert!(sizes.windows(2).all(|w| w[0] == w[1]), "{policy:?}: {sizes:?}");
+        }
+        assert_eq!(
+            seal(PaddingPolicy::SizeClass, &post(600)),
+            seal(PaddingPolicy::SizeClass, &post(900)),
+            "one class"
+        );
+        assert_ne!(
+            seal(PaddingPolicy::Block, &post(600)),
+            seal(PaddingPolicy::Block, &post(900)),
+            "blocks show more"
+        );
+        let config = alice_group.inner.configuration();
+        assert_eq!(
+            config.wire_format_policy(),
+            PURE_CIPHERTEXT_WIRE_FORMAT_POLICY,
+            "a new padding keeps the rest of the configuration"
+        );
+    }
+
     // -------------------------------------------------------------
     // Test 6: Leave produces a Remove proposal.
     // -------------------------------------------------------------
@@ -738,15 +981,61 @@ mod tests {
     }
 
     // -------------------------------------------------------------
-    // Test 8: Cipher suite is fixed to 0x0003.
+    // Test 8: The suite follows the members' KeyPackages.
     // -------------------------------------------------------------
     #[test]
-    fn ciphersuite_is_pinned() {
+    fn suite_follows_the_members_keypackages() {
+        assert_eq!(PROMTUZ_CIPHERSUITE as u16, 0x004D);
+        assert_eq!(CLASSICAL_CIPHERSUITE as u16, 0x0003);
+
+        let provider = build_provider();
+        let hybrid = make_kp(&provider, &Party::new(&provider, 2));
+        let classical = make_kp_on(&provider, &Party::new(&provider, 3), CLASSICAL_CIPHERSUITE);
+        assert!(matches!(
+            negotiate_ciphersuite(&[hybrid.clone(), classical.clone()]),
+            Err(MlsGroupError::BadCipherSuite)
+        ));
+
+        // A classical KeyPackage from this build declares both suites: its
+        // owner has a hybrid one too, so it doesn't hold the group back. One
+        // from an older build does.
+        let bob = Party::new(&provider, 4);
+        let current =
+            make_kp_declaring(&provider, &bob, CLASSICAL_CIPHERSUITE, &SUPPORTED_CIPHERSUITES);
+        assert!(joins_on(&current, PROMTUZ_CIPHERSUITE));
+        assert!(!joins_on(&classical, PROMTUZ_CIPHERSUITE));
         assert_eq!(
-            PROMTUZ_CIPHERSUITE,
-            Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519
+            negotiate_ciphersuite(&[hybrid.clone(), current.clone()]).unwrap(),
+            PROMTUZ_CIPHERSUITE
         );
-        assert_eq!(PROMTUZ_CIPHERSUITE as u16, 0x0003);
+        assert_eq!(
+            negotiate_ciphersuite(&[classical.clone(), current.clone()]).unwrap(),
+            CLASSICAL_CIPHERSUITE
+        );
+        // Too many for an X-Wing Welcome: classical, even though all could.
+        let crowd = vec![current.clone(); crate::mls::MAX_PQ_GROUP_MEMBERS];
+        assert_eq!(negotiate_ciphersuite(&crowd[1..]).unwrap(), PROMTUZ_CIPHERSUITE);
+        assert_eq!(negotiate_ciphersuite(&crowd).unwrap(), CLASSICAL_CIPHERSUITE);
+
+        for (i, kp) in [hybrid, classical].into_iter().enumerate() {
+            let suite = negotiate_ciphersuite(std::slice::from_ref(&kp)).expect("negotiate");
+            assert_eq!(suite, kp.ciphersuite());
+            let alice = Party::new(&provider, 1);
+            let mut group = MlsGroupHandle::create(
+                &provider,
+                &alice.sig_kp,
+                &alice.ipk,
+                alice.sig_kp.public(),
+                &[0xB0 + i as u8; 32],
+                None,
+                suite,
+            )
+            .expect("create");
+            group.add_members(&provider, &alice.sig_kp, &[kp]).expect("add");
+            group.merge_pending_commit(&provider).expect("merge");
+            assert_eq!(group.ciphersuite(), suite);
+            assert_eq!(group.member_count(), 2);
+        }
     }
 
     // -------------------------------------------------------------
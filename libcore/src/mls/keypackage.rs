@@ -14,7 +14,7 @@
 //! - **`kp_ref`** — RFC 9420 §5.2 `KeyPackageRef`. We obtain it via
 //!   [`openmls::prelude::KeyPackage::hash_ref`], which evaluates
 //!   `SHA-256("MLS 1.0 KeyPackage Reference" ‖ tls_encode(kp))` for
-//!   our cipher suite (suite `0x0003` mandates SHA-256). We do **not**
+//!   our cipher suite (both `0x004D` and `0x0003` mandate SHA-256). We do **not**
 //!   compute a separate BLAKE3 ref — the codebase otherwise prefers
 //!   BLAKE3, but `kp_ref` is RFC-mandated SHA-256.
 //! - **`kp_bytes`** — the TLS-encoded `KeyPackage` itself, opaque to
@@ -86,6 +86,7 @@ use ed25519_dalek::Signer;
 use ed25519_dalek::SigningKey;
 use openmls::prelude::BasicCredential;
 use openmls::prelude::Capabilities;
+use openmls::prelude::Ciphersuite;
 use openmls::prelude::CredentialWithKey;
 use openmls::prelude::KeyPackage;
 use openmls::prelude::Lifetime;
@@ -98,14 +99,23 @@ use rusqlite::Connection;
 use rusqlite::params;
 use thiserror::Error;
 
-// `PROMTUZ_CIPHERSUITE` is the single cipher suite used across promtuz.
-// Defined once in `mls::group` and re-exported from `mls::mod`; we
-// import it here to keep this module independent of the rest of `group.rs`.
+// We mint on every suite in `SUPPORTED_CIPHERSUITES`, and every leaf
+// declares all of them. Defined once in `mls::group` and re-exported from
+// `mls::mod`; we import them here to keep this module independent of the
+// rest of `group.rs`.
 use super::group::GROUP_META_EXTENSION;
+use super::group::PREDECESSOR_EXTENSION;
 use super::group::PROMTUZ_CIPHERSUITE;
+use super::group::SUPPORTED_CIPHERSUITES;
 use super::provider::PromtuzMlsProvider;
 use super::types::PromtuzMlsStorageError;
 
+/// Unconsumed KeyPackages the stash keeps on each suite. A peer on an older
+/// build can join only a classical group and has to find a classical
+/// KeyPackage of ours to add us, so the stash holds both side by side until
+/// no such peer is left; the home hands out whichever it picks.
+pub const KP_SUITE_TARGET: usize = KP_STASH_TARGET / SUPPORTED_CIPHERSUITES.len();
+
 /// Result alias for fallible stash operations.
 pub type Result<T> = std::result::Result<T, KeyPackageStashError>;
 
@@ -175,17 +185,27 @@ impl std::fmt::Debug for KeyPackageStash {
 /// undecodable KP counts as not declaring it, on the same "unusable, re-mint"
 /// footing as a bad signature.
 fn declares_group_meta(kp_bytes: &[u8]) -> bool {
+    decode_stashed(kp_bytes).is_some_and(|kp| {
+        kp.leaf_node()
+            .capabilities()
+            .extensions()
+            .contains(&GROUP_META_EXTENSION)
+    })
+}
+
+/// The suite a stashed KeyPackage was minted on; `None` if it won't decode.
+pub(crate) fn minted_on(kp_bytes: &[u8]) -> Option<Ciphersuite> {
+    decode_stashed(kp_bytes).map(|kp| kp.ciphersuite())
+}
+
+fn decode_stashed(kp_bytes: &[u8]) -> Option<KeyPackage> {
     use openmls::prelude::KeyPackageIn;
     use openmls::prelude::ProtocolVersion;
     use openmls::prelude::tls_codec::Deserialize as _;
 
-    let Ok(kp_in) = KeyPackageIn::tls_deserialize_exact(kp_bytes) else { return false };
+    let kp_in = KeyPackageIn::tls_deserialize_exact(kp_bytes).ok()?;
     let crypto = PromtuzMlsProvider::shared();
-    let Ok(kp) = kp_in.validate(crypto.crypto(), ProtocolVersion::Mls10) else { return false };
-    kp.leaf_node()
-        .capabilities()
-        .extensions()
-        .contains(&GROUP_META_EXTENSION)
+    kp_in.validate(crypto.crypto(), ProtocolVersion::Mls10).ok()
 }
 
 impl KeyPackageStash {
@@ -204,7 +224,15 @@ impl KeyPackageStash {
     // Generation
     // -----------------------------------------------------------------
 
-    /// Produce a single fresh KeyPackage and persist it.
+    /// Produce a single fresh KeyPackage on [`PROMTUZ_CIPHERSUITE`] and
+    /// persist it. See [`Self::generate_on`].
+    pub fn generate_one(
+        &self, provider: &PromtuzMlsProvider, ipk_signer: &SigningKey,
+    ) -> Result<KeyPackageRecord> {
+        self.generate_on(provider, ipk_signer, PROMTUZ_CIPHERSUITE)
+    }
+
+    /// Produce a single fresh KeyPackage on `suite` and persist it.
     ///
     /// Steps:
     /// 1. Build a `BasicCredential` carrying the IPK bytes.
@@ -212,14 +240,13 @@ impl KeyPackageStash {
     ///    distinct from IPK, see `signer.rs` doc-comment). Persist it
     ///    via `SignatureKeyPair::store(provider.storage())` so openmls
     ///    can find it on Welcome receipt.
-    /// 3. Build the openmls `KeyPackage` with our pinned cipher suite,
-    ///    a 30-day lifetime, and a `Capabilities` advertising only
-    ///    suite `0x0003`. The build call writes the
+    /// 3. Build the openmls `KeyPackage` on `suite`,
+    ///    with a 30-day lifetime and a `Capabilities` advertising every
+    ///    supported suite. The build call writes the
     ///    `KeyPackageBundle` (KP + init+enc private keys) into
     ///    `provider.storage()` keyed by `kp_ref`.
     /// 4. Compute `kp_ref` via openmls's `KeyPackage::hash_ref` (RFC
-    ///    9420 §5.2 = SHA-256 of label-prefixed TLS-encoded KP under
-    ///    suite 0x0003).
+    ///    9420 §5.2 = SHA-256 of label-prefixed TLS-encoded KP).
     /// 5. TLS-serialise the KP into `kp_bytes`.
     /// 6. Sign `kp_record_signing_input(MLS_WIRE_VERSION, ipk, kp_ref,
     ///    expires_at_ms)` under the IPK to produce `owner_sig`.
@@ -229,8 +256,27 @@ impl KeyPackageStash {
     /// leaf-key bundle is now persisted in openmls's storage and will
     /// be retrieved automatically when a Welcome consuming this KP
     /// arrives.
-    pub fn generate_one(
+    pub fn generate_on(
+        &self, provider: &PromtuzMlsProvider, ipk_signer: &SigningKey, suite: Ciphersuite,
+    ) -> Result<KeyPackageRecord> {
+        self.generate_declaring(provider, ipk_signer, suite, &SUPPORTED_CIPHERSUITES)
+    }
+
+    /// A classical KeyPackage declaring nothing else, as builds before the
+    /// PQ-hybrid suite minted them.
+    #[cfg(test)]
+    pub(crate) fn generate_as_old_build(
         &self, provider: &PromtuzMlsProvider, ipk_signer: &SigningKey,
+    ) -> Result<KeyPackageRecord> {
+        use super::group::CLASSICAL_CIPHERSUITE;
+        let classical = CLASSICAL_CIPHERSUITE;
+        self.generate_declaring(provider, ipk_signer, classical, &[classical])
+    }
+
+    /// [`Self::generate_on`], with `declared` as the leaf's suites.
+    fn generate_declaring(
+        &self, provider: &PromtuzMlsProvider, ipk_signer: &SigningKey, suite: Ciphersuite,
+        declared: &[Ciphersuite],
     ) -> Result<KeyPackageRecord> {
         let now = now_ms();
         let ipk: [u8; 32] = ipk_signer.verifying_key().to_bytes();
@@ -269,16 +315,17 @@ impl KeyPackageStash {
             .key_package_lifetime(lifetime)
             .leaf_node_capabilities(Capabilities::new(
                 None, /* protocol versions: openmls picks `Mls10` */
-                Some(&[PROMTUZ_CIPHERSUITE]),
-                // A group carries its identity in a group-context extension,
-                // and RFC 9420 refuses to add a leaf that doesn't declare
-                // support for every extension already in the context. Without
-                // this the add fails outright with InsufficientCapabilities.
-                Some(&[GROUP_META_EXTENSION]),
+                Some(declared),
+                // A group carries its identity (and, once re-founded, its
+                // predecessor) in group-context extensions, and RFC 9420
+                // refuses to add a leaf that doesn't declare support for every
+                // extension already in the context. Without this the add fails
+                // outright with InsufficientCapabilities.
+                Some(&[GROUP_META_EXTENSION, PREDECESSOR_EXTENSION]),
                 None, /* proposals */
                 None, /* credentials */
             ))
-            .build(PROMTUZ_CIPHERSUITE, provider, &leaf_kp, cwk)
+            .build(suite, provider, &leaf_kp, cwk)
             .map_err(|e| KeyPackageStashError::OpenMlsBuild(format!("{e:?}")))?;
 
         let kp = bundle.key_package().clone();
@@ -358,6 +405,40 @@ impl KeyPackageStash {
         Ok(out)
     }
 
+    /// Drop unconsumed records minted under an identity key other than
+    /// `owner`. After a key rotation they still verify, but carry the retired
+    /// key as their credential, so nobody can add us with them. Returns the
+    /// number purged.
+    pub fn purge_foreign_records(&self, owner: &[u8; 32]) -> usize {
+        let conn = self.db.lock();
+        let foreign: Vec<Vec<u8>> = {
+            let Ok(mut stmt) = conn.prepare(
+                "SELECT kp_ref, record_blob FROM mls_keypackage_stash \
+                 WHERE consumed = 0 AND record_blob IS NOT NULL",
+            ) else {
+                return 0;
+            };
+            let Ok(rows) =
+                stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get::<_, Vec<u8>>(1)?)))
+            else {
+                return 0;
+            };
+            rows.flatten()
+                .filter(|(_, blob)| {
+                    KeyPackageRecord::deser(blob).is_ok_and(|rec| rec.ipk.0 != *owner)
+                })
+                .map(|(kp_ref, _)| kp_ref)
+                .collect()
+        };
+        let mut n = 0usize;
+        for kp_ref in &foreign {
+            n += conn
+                .execute("DELETE FROM mls_keypackage_stash WHERE kp_ref = ?1", [kp_ref])
+                .unwrap_or(0);
+        }
+        n
+    }
+
     /// Drop unconsumed records whose `owner_sig` no longer verifies under the
     /// CURRENT [`MLS_WIRE_VERSION`] — the transcript mixes the version in, so
     /// a wire bump silently invalidates every previously-minted record. Left
@@ -420,9 +501,10 @@ impl KeyPackageStash {
         n
     }
 
-    /// Fill the stash up to [`KP_STASH_TARGET`] unconsumed in-lifetime
-    /// KPs. Returns the freshly-generated records (so the caller can
-    /// publish them via the QUIC client's `DhtClient::publish_keypackages`).
+    /// Fill the stash up to [`KP_SUITE_TARGET`] unconsumed in-lifetime
+    /// KPs on each suite — [`KP_STASH_TARGET`] in all. Returns the
+    /// freshly-generated records (so the caller can publish them via the
+    /// QUIC client's `DhtClient::publish_keypackages`).
     ///
     /// Idempotent: if the stash is already at target, returns an empty
     /// vec without minting anything.
@@ -430,11 +512,12 @@ impl KeyPackageStash {
         &self, provider: &PromtuzMlsProvider, ipk_signer: &SigningKey,
     ) -> Result<Vec<KeyPackageRecord>> {
         let now = now_ms();
-        let existing = self.count_unconsumed_in_lifetime(now);
-        let to_mint = KP_STASH_TARGET.saturating_sub(existing);
-        let mut out = Vec::with_capacity(to_mint);
-        for _ in 0..to_mint {
-            out.push(self.generate_one(provider, ipk_signer)?);
+        let mut out = Vec::new();
+        for suite in SUPPORTED_CIPHERSUITES {
+            let to_mint = KP_SUITE_TARGET.saturating_sub(self.count_unconsumed_on(suite, now));
+            for _ in 0..to_mint {
+                out.push(self.generate_on(provider, ipk_signer, suite)?);
+            }
         }
         Ok(out)
     }
@@ -528,8 +611,10 @@ impl KeyPackageStash {
         };
 
         let mut out = Vec::with_capacity(KP_STASH_TARGET);
-        for _ in 0..KP_STASH_TARGET {
-            out.push(self.generate_one(provider, ipk_signer)?);
+        for suite in SUPPORTED_CIPHERSUITES {
+            for _ in 0..KP_SUITE_TARGET {
+                out.push(self.generate_on(provider, ipk_signer, suite)?);
+            }
         }
 
         // One transaction: the first sweep after a stalled rotation can face a
@@ -548,6 +633,25 @@ impl KeyPackageStash {
         Ok(out)
     }
 
+    /// [`Self::count_unconsumed_in_lifetime`] for the records minted on
+    /// `suite`. Decodes each one, which is fine at stash sizes.
+    pub fn count_unconsumed_on(&self, suite: Ciphersuite, now_ms: u64) -> usize {
+        let conn = self.db.lock();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT record_blob FROM mls_keypackage_stash \
+             WHERE consumed = 0 AND expires_at_ms > ?1 AND record_blob IS NOT NULL",
+        ) else {
+            return 0;
+        };
+        let Ok(rows) = stmt.query_map(params![now_ms as i64], |r| r.get::<_, Vec<u8>>(0)) else {
+            return 0;
+        };
+        rows.flatten()
+            .filter_map(|blob| KeyPackageRecord::deser(&blob).ok())
+            .filter(|rec| minted_on(&rec.kp_bytes.0) == Some(suite))
+            .count()
+    }
+
     /// Count of unconsumed records whose `expires_at_ms > now_ms`.
     ///
     /// Public for use by the scheduler to surface a UI-level "your
@@ -595,6 +699,7 @@ mod tests {
 
     use super::*;
     use crate::db::mls::apply_mls_migrations;
+    use crate::mls::group::CLASSICAL_CIPHERSUITE;
 
     /// Open a fresh in-memory MLS DB and wrap it in the shared
     /// connection handle the stash + provider both consume.
@@ -654,6 +759,24 @@ mod tests {
         assert_eq!(left[0].kp_ref.0, good.kp_ref.0, "current-version record survives");
     }
 
+    /// KeyPackages minted before a key rotation still verify, but name the
+    /// retired key; only the current key's survive.
+    #[test]
+    fn purge_foreign_records_keeps_only_the_current_keys() {
+        let (stash, provider) = build_pair();
+        let retired = fresh_ipk_signer();
+        let current = SigningKey::from_bytes(&[0x43u8; 32]);
+
+        stash.generate_one(&provider, &retired).expect("gen retired");
+        let kept = stash.generate_one(&provider, &current).expect("gen current");
+
+        let owner = current.verifying_key().to_bytes();
+        assert_eq!(stash.purge_foreign_records(&owner), 1);
+        let left = stash.unconsumed_records(now_ms()).expect("read");
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].kp_ref.0, kept.kp_ref.0);
+    }
+
     // -----------------------------------------------------------------
     // 1. Single KP generation produces a valid record
     // -----------------------------------------------------------------
@@ -713,11 +836,30 @@ mod tests {
             KP_STASH_TARGET
         );
 
+        // Half on each suite, so a peer on an older build can still add us.
+        for suite in SUPPORTED_CIPHERSUITES {
+            assert_eq!(stash.count_unconsumed_on(suite, now_ms()), KP_SUITE_TARGET);
+        }
+
         // Second call is a no-op (idempotent).
         let recs2 = stash.ensure_stash_full(&provider, &signer).expect("fill again");
         assert!(recs2.is_empty());
     }
 
+    /// A stash left all-hybrid by an earlier build tops up its classical half
+    /// rather than counting itself full.
+    #[test]
+    fn ensure_stash_full_tops_up_the_missing_suite() {
+        let (stash, provider) = build_pair();
+        let signer = fresh_ipk_signer();
+        for _ in 0..KP_STASH_TARGET {
+            stash.generate_one(&provider, &signer).expect("gen");
+        }
+        let recs = stash.ensure_stash_full(&provider, &signer).expect("fill");
+        assert_eq!(recs.len(), KP_SUITE_TARGET);
+        assert!(recs.iter().all(|r| minted_on(&r.kp_bytes.0) == Some(CLASSICAL_CIPHERSUITE)));
+    }
+
     // -----------------------------------------------------------------
     // 4. Owner sig verifies — covered by test 1; here we additionally
     // pin that *changing* any signed field invalidates the sig.
@@ -1,13 +1,16 @@
 //! MLS (RFC 9420) layer.
 //!
-//! Cipher suite: `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`
-//! (suite ID `0x0003`).
+//! Cipher suites: `MLS_256_XWING_CHACHA20POLY1305_SHA256_Ed25519`
+//! (`0x004D`, PQ-hybrid) for new groups, and
+//! `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519` (`0x0003`) for
+//! groups founded before it and for peers still on it. See `group.rs`.
 //!
 //! # Module layout
 //!
-//! - `provider.rs`, `storage.rs`, `types.rs`: `PromtuzMlsProvider`
-//!   (the openmls `OpenMlsProvider`), the rusqlite-backed
-//!   `PromtuzStorageProvider`, and the storage error enum.
+//! - `provider.rs`, `crypto.rs`, `storage.rs`, `types.rs`:
+//!   `PromtuzMlsProvider` (the openmls `OpenMlsProvider`), its X-Wing-capable
+//!   crypto, the rusqlite-backed `PromtuzStorageProvider`, and the storage
+//!   error enum.
 //! - `common/src/proto/mls_wire.rs`: wire types
 //!   (`MlsApplicationEnvelopeP`, `WelcomeEnvelopeP`,
 //!   `KeyPackagePublishReq` etc.) and signing-input helpers.
@@ -21,9 +24,11 @@
 //! - `libcore/src/api/messaging.rs`: wires MLS into the messaging
 //!   path.
 
+pub mod crypto;
 pub mod epoch_catchup;
 pub mod group;
 pub mod keypackage;
+pub mod padding;
 pub mod provider;
 pub mod scheduler;
 pub mod signer;
@@ -37,7 +42,10 @@ pub mod welcome;
 #[allow(unused_imports)]
 pub use epoch_catchup::{EpochCatchupBuffer, PushOutcome};
 #[allow(unused_imports)]
-pub use group::{GROUP_META_EXTENSION, GroupMeta, MlsGroupHandle, PROMTUZ_CIPHERSUITE};
+pub use group::{
+    CLASSICAL_CIPHERSUITE, GROUP_META_EXTENSION, GroupMeta, MlsGroupHandle, PREDECESSOR_EXTENSION,
+    PROMTUZ_CIPHERSUITE, SUPPORTED_CIPHERSUITES,
+};
 #[allow(unused_imports)]
 pub use keypackage::{KeyPackageStash, KeyPackageStashError};
 #[allow(unused_imports)]
@@ -63,9 +71,15 @@ pub const MLS_GROUP_STATE_BUDGET_BYTES: u64 = 1024 * 1024;
 /// merging a staged commit.
 pub const MAX_GROUP_MEMBERS: usize = 256;
 
+/// Ceiling on members in a group on [`PROMTUZ_CIPHERSUITE`]. Its Welcome
+/// carries ~2.8 KiB per member, more once the tree fills in, and has to fit
+/// `MAX_WELCOME_BYTES`; a bigger group stays on [`CLASSICAL_CIPHERSUITE`].
+pub const MAX_PQ_GROUP_MEMBERS: usize = 48;
+
 /// Application plaintext is padded up to a multiple of this before sealing, so
 /// ciphertext length reports a bucket rather than the message length. Applied
 /// by the sender; openmls strips it on decrypt, so it needs no wire change.
+/// The group default and the smallest size class ([`padding`]).
 pub const MLS_PADDING_SIZE: usize = 256;
 
 /// Per-group cap on application messages held for future epochs.
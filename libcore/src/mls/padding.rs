@@ -0,0 +1,91 @@
+//! Size classes for application ciphertext.
+//!
+//! openmls pads each `PrivateMessage` to a multiple of the group's
+//! `padding_size`. Left at one block for every message, ciphertext length
+//! still tracks plaintext length block by block, so a relay can tell a long
+//! text from a short one. Before each application message is sealed, the
+//! group's padding parameter is set to the size class the plaintext falls in,
+//! and the ciphertext reports only that class.
+//!
+//! The [`PaddingPolicy`] picks the classes. Wider classes hide more and cost
+//! more bytes per message. Receipts, reactions and short texts share the
+//! smallest class under every policy.
+
+use super::MLS_PADDING_SIZE;
+
+/// What openmls adds inside the padded block beyond the plaintext itself:
+/// the content's length prefix, the Ed25519 signature with its prefix, and
+/// the AEAD tag. Rounded up. A low estimate costs nothing in privacy: a
+/// message that spills over its class is padded to twice the class, which
+/// is the next class.
+const SEAL_OVERHEAD: usize = 96;
+
+/// The largest size class. Past it, messages pad to a multiple of it, which
+/// caps the overhead at one class.
+pub const MAX_SIZE_CLASS: usize = 64 * 1024;
+
+/// The smallest class under [`PaddingPolicy::Uniform`]: every text message
+/// of ordinary length lands in it.
+pub const UNIFORM_FLOOR: usize = 4 * 1024;
+
+/// How application ciphertext is padded: the privacy/bandwidth tradeoff.
+#[derive(uniffi::Enum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaddingPolicy {
+    /// A multiple of [`MLS_PADDING_SIZE`]. The least overhead; length shows
+    /// to within a block.
+    Block,
+    /// Powers of two from [`MLS_PADDING_SIZE`] to [`MAX_SIZE_CLASS`]. At most
+    /// doubles a message; length shows only its order of magnitude.
+    #[default]
+    SizeClass,
+    /// As [`Self::SizeClass`], but nothing under [`UNIFORM_FLOOR`]. The most
+    /// overhead; a text of any ordinary length looks like a receipt.
+    Uniform,
+}
+
+/// The padding policy for messages this process sends. Process-lifetime like
+/// the wake policy: the app sets it on launch and whenever the user changes it.
+static PADDING_POLICY: parking_lot::RwLock<PaddingPolicy> =
+    parking_lot::RwLock::new(PaddingPolicy::SizeClass);
+
+pub fn set_policy(policy: PaddingPolicy) {
+    *PADDING_POLICY.write() = policy;
+}
+
+pub fn policy() -> PaddingPolicy {
+    *PADDING_POLICY.read()
+}
+
+/// The openmls `padding_size` to seal `plaintext_len` bytes under `policy`.
+pub fn padding_size_for(policy: PaddingPolicy, plaintext_len: usize) -> usize {
+    let floor = match policy {
+        PaddingPolicy::Block => return MLS_PADDING_SIZE,
+        PaddingPolicy::SizeClass => MLS_PADDING_SIZE,
+        PaddingPolicy::Uniform => UNIFORM_FLOOR,
+    };
+    plaintext_len
+        .saturating_add(SEAL_OVERHEAD)
+        .checked_next_power_of_two()
+        .unwrap_or(MAX_SIZE_CLASS)
+        .clamp(floor, MAX_SIZE_CLASS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every class is a power of two within bounds, and no policy pads less
+    /// than the one before it.
+    #[test]
+    fn classes_widen_from_block_to_uniform() {
+        for len in [0, 1, 100, 159, 160, 1_000, 5_000, 60_000, 200_000] {
+            let block = padding_size_for(PaddingPolicy::Block, len);
+            let class = padding_size_for(PaddingPolicy::SizeClass, len);
+            let uniform = padding_size_for(PaddingPolicy::Uniform, len);
+            assert_eq!(block, MLS_PADDING_SIZE);
+            assert!(class.is_power_of_two() && class <= MAX_SIZE_CLASS);
+            assert!(block <= class && class <= uniform, "{len}: {block} {class} {uniform}");
+        }
+        assert_eq!(padding_size_for(PaddingPolicy::Uniform, 3_000), UNIFORM_FLOOR);
+    }
+}
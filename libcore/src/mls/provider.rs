@@ -3,27 +3,27 @@
 //!
 //! Composition:
 //!
-//! - **Crypto**: `openmls_rust_crypto::RustCrypto` (stock). All low-level
-//!   primitives (Ed25519, X25519, AEAD, hash, HKDF) are exactly the
-//!   crates already in the workspace; we don't re-implement.
-//! - **Rand**: same `RustCrypto` instance — it implements both
-//!   `OpenMlsCrypto` and `OpenMlsRand` over a `ChaCha20Rng` seeded from
-//!   `OsRng`. No need for a separate randomness wrapper.
+//! - **Crypto**: [`PromtuzCrypto`] — stock `openmls_rust_crypto::RustCrypto`
+//!   for every primitive (Ed25519, X25519, AEAD, hash, HKDF), plus X-Wing
+//!   HPKE for the PQ-hybrid suite (see `crypto.rs`).
+//! - **Rand**: same instance — it forwards `OpenMlsRand` to `RustCrypto`'s
+//!   `ChaCha20Rng` seeded from `OsRng`. No need for a separate randomness
+//!   wrapper.
 //! - **Storage**: [`PromtuzStorageProvider`] (rusqlite-backed,
 //!   per-group budget enforcement, see `storage.rs`).
 //!
 //! The provider is `Clone`-cheap: storage holds an `Arc<Mutex<…>>`,
-//! `RustCrypto` is `Default`-constructed lazily inside if needed —
-//! we keep one `RustCrypto` per provider since it owns its own RNG
-//! state and there's no benefit to sharing across providers.
+//! `PromtuzCrypto` is `Default`-constructed lazily inside if needed —
+//! we keep one per provider since it owns its own RNG state and there's
+//! no benefit to sharing across providers.
 
 use std::sync::Arc;
 
-use openmls_rust_crypto::RustCrypto;
 use openmls_traits::OpenMlsProvider;
 use parking_lot::Mutex;
 use rusqlite::Connection;
 
+use super::crypto::PromtuzCrypto;
 use super::storage::PromtuzStorageProvider;
 
 /// The promtuz `OpenMlsProvider`.
@@ -31,7 +31,7 @@ use super::storage::PromtuzStorageProvider;
 /// Group lifecycle, KeyPackage stash, and Welcome handling are built
 /// on top of this provider.
 pub struct PromtuzMlsProvider {
-    crypto: RustCrypto,
+    crypto: PromtuzCrypto,
     storage: PromtuzStorageProvider,
 }
 
@@ -51,7 +51,7 @@ impl PromtuzMlsProvider {
     /// `db::mls::apply_mls_migrations`.
     pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
         Self {
-            crypto: RustCrypto::default(),
+            crypto: PromtuzCrypto::default(),
             storage: PromtuzStorageProvider::new(conn),
         }
     }
@@ -76,8 +76,8 @@ impl PromtuzMlsProvider {
 }
 
 impl OpenMlsProvider for PromtuzMlsProvider {
-    type CryptoProvider = RustCrypto;
-    type RandProvider = RustCrypto;
+    type CryptoProvider = PromtuzCrypto;
+    type RandProvider = PromtuzCrypto;
     type StorageProvider = PromtuzStorageProvider;
 
     fn storage(&self) -> &Self::StorageProvider {
@@ -157,6 +157,11 @@ pub async fn ensure_kp_published<C: DhtClient>(
     if purged > 0 {
         log::info!("ensure_kp_published: purged {purged} stale-version KP records; re-minting");
     }
+    // After a key rotation the stash still holds KeyPackages for the old key.
+    let purged = stash.purge_foreign_records(&ipk_signer.verifying_key().to_bytes());
+    if purged > 0 {
+        log::info!("ensure_kp_published: purged {purged} KP records of a retired key; re-minting");
+    }
     // Guarantee a full stash first (mints if low — covers a fresh or migration-wiped stash).
     if let Err(e) = stash.ensure_stash_full(provider, ipk_signer) {
         log::warn!("ensure_kp_published: ensure_stash_full failed: {e}");
@@ -98,6 +98,12 @@ impl PromtuzStorageProvider {
         Self { conn }
     }
 
+    /// The connection itself, for the tables libcore keeps beside openmls's
+    /// own in the MLS database (a successor's staged Welcomes, suite probes).
+    pub(crate) fn conn(&self) -> &Arc<Mutex<Connection>> {
+        &self.conn
+    }
+
     // ---- internals ---------------------------------------------------
 
     /// CBOR-encode a value, mapping the encoder error onto our enum.
@@ -82,10 +82,9 @@ pub enum MlsGroupError {
     #[error("envelope signature failed verification")]
     BadSignature,
 
-    /// Cipher suite mismatch between the wire and our pinned suite
-    /// (`MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`,
-    /// `0x0003`).
-    #[error("cipher suite mismatch (expected MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519)")]
+    /// A KeyPackage on a suite outside `SUPPORTED_CIPHERSUITES`, or
+    /// founding members whose KeyPackages disagree on one.
+    #[error("cipher suite mismatch (unsupported, or members on different suites)")]
     BadCipherSuite,
 
     /// The buffered message belongs to a future epoch beyond the
@@ -54,12 +54,10 @@ use ed25519_dalek::SigningKey;
 use ed25519_dalek::VerifyingKey;
 use openmls::prelude::tls_codec::Deserialize as _;
 use openmls::prelude::tls_codec::Serialize as _;
-use openmls::prelude::MlsGroupJoinConfig;
 use openmls::prelude::MlsMessageBodyIn;
 use openmls::prelude::MlsMessageIn;
 use openmls::prelude::MlsMessageOut;
 use openmls::prelude::StagedWelcome;
-use openmls::prelude::PURE_CIPHERTEXT_WIRE_FORMAT_POLICY;
 
 use super::group::MlsGroupHandle;
 use super::provider::PromtuzMlsProvider;
@@ -146,7 +144,7 @@ pub fn make_welcome_envelope(
 /// - `MlsGroupError::BadSignature` — outer envelope sig failed
 ///   verification under `sender_ipk`.
 /// - `MlsGroupError::BadCipherSuite` — the embedded `Welcome`'s
-///   cipher suite is not `0x0003`.
+///   cipher suite is not one of `SUPPORTED_CIPHERSUITES`.
 /// - `MlsGroupError::Codec` — the `welcome_blob` bytes don't
 ///   parse as a TLS-encoded `Welcome`.
 /// - `MlsGroupError::OpenMls(...)` — openmls rejected the welcome
@@ -208,10 +206,7 @@ pub fn process_welcome(
     // 3. Hand to openmls — it'll look up the matching KP bundle
     //    by its hash_ref and decrypt the joiner secret.
     // ---------------------------------------------------------
-    let join_config = MlsGroupJoinConfig::builder()
-        .wire_format_policy(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY)
-        .padding_size(super::MLS_PADDING_SIZE)
-        .build();
+    let join_config = super::group::join_config(super::MLS_PADDING_SIZE);
     let staged = StagedWelcome::new_from_welcome(provider, &join_config, welcome, None)
         .map_err(MlsGroupError::from_openmls)?;
 
@@ -366,6 +361,7 @@ mod tests {
             alice.sig_kp.public(),
             &gid,
             meta,
+            PROMTUZ_CIPHERSUITE,
         )
         .expect("create");
         let bob_kp = make_kp(provider_b, bob);
@@ -99,6 +99,12 @@ pub enum Disclosure {
 /// auto-accept below) racing a button-initiated one for the same peer.
 static CONNECTING: Lazy<Mutex<HashSet<[u8; 32]>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
+/// Drop buffered offers and stop listening for more; the account the
+/// sessions were for is gone.
+pub(crate) fn forget_all() {
+    signal::forget_all();
+}
+
 /// Disco channel → the session waiting on pokes for it. The receive loop
 /// routes each inbound poke to the right session by its channel tag.
 type Sessions = Arc<Mutex<HashMap<[u8; 8], mpsc::UnboundedSender<Poke>>>>;
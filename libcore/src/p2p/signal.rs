@@ -43,6 +43,12 @@ static LISTENERS: Lazy<Listeners> = Lazy::new(|| Mutex::new(HashMap::new()));
 /// bound comes with the wake-rendezvous later.
 static PENDING: Lazy<Mutex<HashMap<[u8; 32], Offer>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// [`stop`] every session at once; the account they were for is gone.
+pub(crate) fn forget_all() {
+    LISTENERS.lock().clear();
+    PENDING.lock().clear();
+}
+
 /// Start listening for `peer`'s candidate offers. Returns the receiver;
 /// any offer that already arrived is delivered immediately.
 pub fn listen(peer: [u8; 32]) -> mpsc::UnboundedReceiver<Offer> {
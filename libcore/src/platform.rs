@@ -23,6 +23,9 @@ use crate::events::messaging::MessageEv;
 pub trait SecureStore: Send + Sync {
     fn seal(&self, plaintext: Vec<u8>) -> Result<Vec<u8>, CoreError>;
     fn open(&self, ciphertext: Vec<u8>) -> Result<Vec<u8>, CoreError>;
+    /// Destroy the wrapping key, so nothing sealed under it opens again. Once
+    /// the account is wiped; the next `seal` makes a fresh key.
+    fn forget(&self) -> Result<(), CoreError>;
 }
 
 /// A contact's presence, for the client. `Idle`/`Offline` carry a unix-ms
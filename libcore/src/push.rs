@@ -2,30 +2,57 @@
 //! keypair, unrelated to the IPK) and tells the home relay `IPK → P`, so the
 //! relay can wake this device when a message queues while we're offline. The
 //! device token never touches the relay — only the gateway learns it, under
-//! `P` (that half is a separate registration).
+//! `P` and under the pseudonym of each open mailbox (that half is a separate
+//! registration). Holding one token under all of them, the gateway can tell
+//! they are one device, though not which IPK or mailbox each stands for.
+//!
+//! It also publishes the X25519 push key senders seal wake hints to, so a
+//! wake can name the sender and conversation before the device has drained.
+//! The hint is sealed end-to-end: relay and gateway only carry ciphertext.
+
+use std::collections::HashMap;
 
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::anyhow;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::XNonce;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::aead::Payload;
 use common::node::capability::CAPABILITY_OID;
 use common::node::capability::NodeCapabilities;
 use common::proto::RelayId;
 use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::SRelayPacket;
 use common::proto::client_res::ClientRequest;
 use common::proto::client_res::ClientResponse;
 use common::proto::client_res::GatewayDescriptor;
+use common::proto::dht_p2p::PushKeyRecord;
+use common::proto::dht_p2p::WakePolicy;
+use common::proto::dht_p2p::push_key_signing_input;
 use common::proto::dht_p2p::push_pseudonym_signing_input;
+use common::proto::dht_p2p::wake_policy_signing_input;
 use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
 use common::proto::push::PushProvider;
 use common::proto::push::PushRequest;
 use common::proto::push::RegisterToken;
+use common::proto::push::SealedWakeHint;
+use common::proto::push::WAKE_HINT_SEAL_INFO;
+use common::proto::push::WakeHint;
+use common::proto::push::wake_hint_signing_input;
+use common::types::bytes::ByteVec;
 use common::types::bytes::Bytes;
+use ed25519_dalek::Signature;
 use ed25519_dalek::SigningKey;
+use ed25519_dalek::VerifyingKey;
 use ed25519_dalek::ed25519::signature::rand_core::OsRng;
 use ed25519_dalek::ed25519::signature::rand_core::RngCore;
+use hkdf::Hkdf;
 use once_cell::sync::Lazy;
 use rusqlite::params;
+use sha2::Sha256;
 use x509_parser::der_parser::Oid;
 use x509_parser::prelude::FromDer;
 use x509_parser::prelude::X509Certificate;
@@ -55,6 +82,27 @@ pub fn push_pseudonym() -> [u8; 32] {
     PUSH_KEY.verifying_key().to_bytes()
 }
 
+/// HKDF info for the push pseudonym published under one mailbox.
+const MAILBOX_PUSH_INFO: &[u8] = b"promtuz-mailbox-push-v1";
+
+/// The pseudonym keypair for `mailbox` (`crate::mailbox`). Derived from
+/// [`PUSH_KEY`] per mailbox, so the homes of two mailboxes, or of a mailbox
+/// and the IPK, cannot link them by pseudonym. The gateway can: every one is
+/// registered there with the same device token.
+fn mailbox_push_key(mailbox: &[u8; 32]) -> SigningKey {
+    let secret = zeroize::Zeroizing::new(PUSH_KEY.to_bytes());
+    let mut seed = zeroize::Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, secret.as_slice())
+        .expand_multi_info(&[MAILBOX_PUSH_INFO, mailbox], seed.as_mut())
+        .expect("32 bytes is a valid HKDF length");
+    SigningKey::from_bytes(&seed)
+}
+
+/// The push pseudonym published under `mailbox`.
+pub(crate) fn mailbox_pseudonym(mailbox: &[u8; 32]) -> [u8; 32] {
+    mailbox_push_key(mailbox).verifying_key().to_bytes()
+}
+
 /// Tell the connected home relay our `P`. Fire-and-forget; the relay binds it
 /// to the connection-authenticated IPK. Called on each relay connect.
 pub async fn register_push() -> Result<()> {
@@ -76,6 +124,278 @@ pub async fn register_push() -> Result<()> {
         let _ = tx.write_all(&bytes).await;
         let _ = tx.finish();
     }
+    send_wake_policy().await?;
+    publish_push_key().await
+}
+
+/// When the home relays may wake us. Process-lifetime like [`PUSH_TOKEN`]: the
+/// app sets it on launch and whenever the user changes it.
+static WAKE_POLICY: parking_lot::RwLock<Option<WakePolicy>> = parking_lot::RwLock::new(None);
+
+/// Store `policy` and sign it over to the connected relay, which replicates it
+/// to our DHT homes. Re-sent on every connect with [`register_push`].
+pub async fn set_wake_policy(policy: WakePolicy) -> Result<()> {
+    *WAKE_POLICY.write() = Some(policy);
+    send_wake_policy().await
+}
+
+/// The wake policy last set, if any.
+pub(crate) fn wake_policy() -> Option<WakePolicy> {
+    WAKE_POLICY.read().clone()
+}
+
+async fn send_wake_policy() -> Result<()> {
+    let Some(policy) = WAKE_POLICY.read().clone() else {
+        return Ok(());
+    };
+    let timestamp = now_ms();
+    let ipk = crate::data::identity::Identity::get().context("no identity")?.ipk();
+    let sig = IdentitySigner::sign(&wake_policy_signing_input(&ipk, &policy, timestamp))?
+        .to_bytes();
+    let bytes = CRelayPacket::SetWakePolicy { policy, timestamp, sig: Bytes(sig) }
+        .pack()
+        .map_err(|e| anyhow!("pack set_wake_policy: {e}"))?;
+    let conn = {
+        let relay = RELAY.read();
+        relay.as_ref().and_then(|r| r.connection.clone())
+    };
+    let Some(conn) = conn else { return Ok(()) };
+    if let Ok((mut tx, _rx)) = conn.open_bi().await {
+        let _ = tx.write_all(&bytes).await;
+        let _ = tx.finish();
+    }
+    Ok(())
+}
+
+/// Sign our push key over to the connected relay, which replicates it to our
+/// DHT homes. Re-sent on every connect with [`register_push`].
+async fn publish_push_key() -> Result<()> {
+    let push_pk = x25519_dalek::PublicKey::from(&IdentitySigner::push_seal_secret()?).to_bytes();
+    let timestamp = now_ms();
+    let ipk = crate::data::identity::Identity::get().context("no identity")?.ipk();
+    let sig = IdentitySigner::sign(&push_key_signing_input(&ipk, &push_pk, timestamp))?.to_bytes();
+    let record = PushKeyRecord {
+        user_ipk: Bytes(ipk),
+        push_pk: Bytes(push_pk),
+        timestamp,
+        user_sig: Bytes(sig),
+    };
+    let bytes = CRelayPacket::PublishPushKey(record)
+        .pack()
+        .map_err(|e| anyhow!("pack publish_push_key: {e}"))?;
+    let conn = {
+        let relay = RELAY.read();
+        relay.as_ref().and_then(|r| r.connection.clone())
+    };
+    let Some(conn) = conn else { return Ok(()) };
+    if let Ok((mut tx, _rx)) = conn.open_bi().await {
+        let _ = tx.write_all(&bytes).await;
+        let _ = tx.finish();
+    }
+    Ok(())
+}
+
+/// Contacts' verified push keys, by IPK. Process-lifetime; a miss is filled
+/// in the background, so the first message to a contact goes without a hint.
+static PEER_PUSH_KEYS: Lazy<parking_lot::Mutex<HashMap<[u8; 32], PushKeyRecord>>> =
+    Lazy::new(Default::default);
+
+/// When we last asked for a contact's push key, by IPK. Keeps a contact who
+/// never published one from costing a relay round trip on every dispatch.
+static PUSH_KEY_ASKED: Lazy<parking_lot::Mutex<HashMap<[u8; 32], u64>>> =
+    Lazy::new(Default::default);
+
+/// Drop what we learnt of contacts' push keys and what we told the gateway
+/// and homes; the account they were for is gone. The platform token and our
+/// pseudonym belong to the install and stay.
+pub(crate) fn forget_all() {
+    PEER_PUSH_KEYS.lock().clear();
+    PUSH_KEY_ASKED.lock().clear();
+    GATEWAY_MAILBOXES.lock().clear();
+    *WAKE_POLICY.write() = None;
+}
+
+/// How long a push key lookup that came back empty, or failed, stands before
+/// the next dispatch to that contact asks again.
+const PUSH_KEY_RETRY_MS: u64 = 10 * 60 * 1000;
+
+/// `to`'s verified push key, if cached. A miss starts a background fetch,
+/// at most once per [`PUSH_KEY_RETRY_MS`], and returns `None`, so the first
+/// dispatch to a contact goes without whatever needed the key.
+pub(crate) fn peer_push_key(to: &[u8; 32]) -> Option<[u8; 32]> {
+    let cached = PEER_PUSH_KEYS.lock().get(to).map(|r| r.push_pk.0);
+    if cached.is_none() && should_ask(&mut PUSH_KEY_ASKED.lock(), to, now_ms()) {
+        let to = *to;
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = fetch_push_key(to).await {
+                log::debug!("PUSH: push key fetch for {} failed: {e}", hex::encode(&to[..4]));
+            }
+        });
+    }
+    cached
+}
+
+/// Stamps `to` as asked at `now` unless it was asked within
+/// [`PUSH_KEY_RETRY_MS`].
+fn should_ask(asked: &mut HashMap<[u8; 32], u64>, to: &[u8; 32], now: u64) -> bool {
+    asked.retain(|_, at| now.saturating_sub(*at) < PUSH_KEY_RETRY_MS);
+    if asked.contains_key(to) {
+        return false;
+    }
+    asked.insert(*to, now);
+    true
+}
+
+/// Seal a [`WakeHint`] for `to` announcing dispatch `dispatch_id` in
+/// `conversation`, ready for `DispatchP::wake_hint`. `None` while `to`'s push
+/// key isn't cached yet (a fetch is started) or sealing fails; the dispatch
+/// then wakes contentless as before.
+pub(crate) fn seal_wake_hint(
+    to: &[u8; 32], our_ipk: &[u8; 32], signer: &SigningKey, conversation: [u8; 16],
+    dispatch_id: &[u8; 16],
+) -> Option<ByteVec> {
+    let push_pk = peer_push_key(to)?;
+    seal_hint_to(&push_pk, to, our_ipk, signer, conversation, dispatch_id)
+}
+
+fn seal_hint_to(
+    push_pk: &[u8; 32], to: &[u8; 32], our_ipk: &[u8; 32], signer: &SigningKey,
+    conversation: [u8; 16], dispatch_id: &[u8; 16],
+) -> Option<ByteVec> {
+    use ed25519_dalek::Signer;
+
+    let input = wake_hint_signing_input(to, our_ipk, &conversation, dispatch_id);
+    let hint = WakeHint {
+        sender: Bytes(*our_ipk),
+        conversation: Bytes(conversation),
+        dispatch_id: Bytes(*dispatch_id),
+        sig: Bytes(signer.sign(&input).to_bytes()),
+    };
+    let plain = hint.ser().ok()?;
+    let (eph_pk, nonce, ciphertext) = seal_to_push_key(push_pk, WAKE_HINT_SEAL_INFO, to, &plain)?;
+    let sealed = SealedWakeHint { eph_pk: Bytes(eph_pk), nonce: Bytes(nonce), ciphertext };
+    sealed.ser().ok().map(ByteVec)
+}
+
+/// Open a wake payload addressed to us. `None` for an empty (contentless)
+/// payload, one sealed to a key we no longer hold, or a hint whose sender
+/// signature doesn't verify.
+pub fn open_wake_hint(payload: &[u8]) -> Option<WakeHint> {
+    let secret = IdentitySigner::push_seal_secret().ok()?;
+    let our_ipk = crate::data::identity::Identity::get()?.ipk();
+    open_hint_with(&secret, &our_ipk, payload)
+}
+
+fn open_hint_with(
+    secret: &x25519_dalek::StaticSecret, our_ipk: &[u8; 32], payload: &[u8],
+) -> Option<WakeHint> {
+    let sealed = SealedWakeHint::deser(payload).ok()?;
+    let plain = open_with_push_secret(
+        secret,
+        WAKE_HINT_SEAL_INFO,
+        &sealed.eph_pk.0,
+        &sealed.nonce.0,
+        our_ipk,
+        &sealed.ciphertext,
+    )?;
+    let hint = WakeHint::deser(&plain).ok()?;
+    let input = wake_hint_signing_input(
+        our_ipk,
+        &hint.sender.0,
+        &hint.conversation.0,
+        &hint.dispatch_id.0,
+    );
+    VerifyingKey::from_bytes(&hint.sender.0)
+        .ok()?
+        .verify_strict(&input, &Signature::from_bytes(&hint.sig.0))
+        .ok()?;
+    Some(hint)
+}
+
+/// Encrypt `plain` to a push key: ephemeral X25519 against `push_pk`, then
+/// [`seal_cipher`] under `info` with `aad`. Returns `(eph_pk, nonce,
+/// ciphertext)`; `None` on a non-contributory exchange.
+pub(crate) fn seal_to_push_key(
+    push_pk: &[u8; 32], info: &[u8], aad: &[u8], plain: &[u8],
+) -> Option<([u8; 32], [u8; 24], Vec<u8>)> {
+    let eph = x25519_dalek::EphemeralSecret::random_from_rng(OsRng);
+    let eph_pk = x25519_dalek::PublicKey::from(&eph).to_bytes();
+    let shared = eph.diffie_hellman(&x25519_dalek::PublicKey::from(*push_pk));
+    if !shared.was_contributory() {
+        return None;
+    }
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = seal_cipher(info, shared.as_bytes(), &eph_pk, push_pk)
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plain, aad })
+        .ok()?;
+    Some((eph_pk, nonce, ciphertext))
+}
+
+/// Inverse of [`seal_to_push_key`] for the holder of the push key's secret.
+pub(crate) fn open_with_push_secret(
+    secret: &x25519_dalek::StaticSecret, info: &[u8], eph_pk: &[u8; 32], nonce: &[u8; 24],
+    aad: &[u8], ciphertext: &[u8],
+) -> Option<Vec<u8>> {
+    let push_pk = x25519_dalek::PublicKey::from(secret).to_bytes();
+    let shared = secret.diffie_hellman(&x25519_dalek::PublicKey::from(*eph_pk));
+    if !shared.was_contributory() {
+        return None;
+    }
+    seal_cipher(info, shared.as_bytes(), eph_pk, &push_pk)
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .ok()
+}
+
+/// XChaCha20-Poly1305 under `HKDF-SHA256(shared, info = info || eph_pk ||
+/// push_pk)`, binding the key to both halves of the exchange. `info` keeps
+/// wake hints and sealed-sender payloads from ever sharing a key.
+fn seal_cipher(
+    info: &[u8], shared: &[u8; 32], eph_pk: &[u8; 32], push_pk: &[u8; 32],
+) -> XChaCha20Poly1305 {
+    let mut full = Vec::with_capacity(info.len() + 64);
+    full.extend_from_slice(info);
+    full.extend_from_slice(eph_pk);
+    full.extend_from_slice(push_pk);
+    let mut key = zeroize::Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, shared)
+        .expand(&full, key.as_mut())
+        .expect("32 bytes is a valid HKDF length");
+    XChaCha20Poly1305::new((&*key).into())
+}
+
+/// Ask our relay for `target`'s push key and cache it once the owner
+/// signature checks out.
+async fn fetch_push_key(target: [u8; 32]) -> Result<()> {
+    let conn = {
+        let relay = RELAY.read();
+        relay.as_ref().and_then(|r| r.connection.clone())
+    };
+    let Some(conn) = conn else { return Ok(()) };
+    let bytes = CRelayPacket::FetchPushKey { target_ipk: Bytes(target) }
+        .pack()
+        .map_err(|e| anyhow!("pack fetch_push_key: {e}"))?;
+    let (mut tx, mut rx) = conn.open_bi().await?;
+    tx.write_all(&bytes).await?;
+    tx.finish()?;
+    let record = match SRelayPacket::unpack(&mut rx).await? {
+        SRelayPacket::PushKey { record: Some(record), .. } => record,
+        SRelayPacket::PushKey { record: None, .. } => return Ok(()),
+        other => return Err(anyhow!("FetchPushKey: unexpected variant {other:?}")),
+    };
+    let input = push_key_signing_input(&target, &record.push_pk.0, record.timestamp);
+    if record.user_ipk.0 != target
+        || VerifyingKey::from_bytes(&target)?
+            .verify_strict(&input, &Signature::from_bytes(&record.user_sig.0))
+            .is_err()
+    {
+        return Err(anyhow!("push key for {} fails its signature", hex::encode(&target[..4])));
+    }
+    PUSH_KEY_ASKED.lock().remove(&target);
+    let mut keys = PEER_PUSH_KEYS.lock();
+    if keys.get(&target).is_none_or(|cached| cached.timestamp < record.timestamp) {
+        keys.insert(target, record);
+    }
     Ok(())
 }
 
@@ -86,6 +406,10 @@ fn now_ms() -> u64 {
         .unwrap_or(0)
 }
 
+/// Mailboxes the gateway last registered a pseudonym for.
+static GATEWAY_MAILBOXES: Lazy<parking_lot::Mutex<Vec<[u8; 32]>>> =
+    Lazy::new(|| parking_lot::Mutex::new(Vec::new()));
+
 /// The platform push token (e.g. FCM registration token), pushed in by the app
 /// from its onNewToken callback. Registered with a gateway under `P`.
 static PUSH_TOKEN: parking_lot::RwLock<Option<Vec<u8>>> = parking_lot::RwLock::new(None);
@@ -123,6 +447,18 @@ pub async fn register_token_at_gateway() -> Result<()> {
     Ok(())
 }
 
+/// [`register_token_at_gateway`] again if a mailbox is open that the gateway
+/// has no pseudonym for: a new epoch's opens on a presence renewal, long after
+/// the connect that registered the others.
+pub(crate) async fn register_new_mailboxes() -> Result<()> {
+    let open = crate::mailbox::open_ids(now_ms())?;
+    let registered = GATEWAY_MAILBOXES.lock().clone();
+    if open.iter().all(|mailbox| registered.contains(mailbox)) {
+        return Ok(());
+    }
+    register_token_at_gateway().await
+}
+
 /// The CA-attested capabilities in a dialed node's leaf cert, if it carries the
 /// extension. The dialed node is the TLS server, so its chain is always
 /// present and was validated against the root CA during the handshake.
@@ -138,7 +474,9 @@ fn capabilities_from_conn(conn: &quinn::Connection) -> Option<NodeCapabilities>
 async fn send_registration(gateway: &GatewayDescriptor, token: Vec<u8>) -> Result<()> {
     // ponytail: Fcm-only for now (Android). Pass the provider from the app when
     // iOS / UnifiedPush land.
-    let reg = RegisterToken::signed(&PUSH_KEY, PushProvider::Fcm, token);
+    let reg = RegisterToken::signed(&PUSH_KEY, PushProvider::Fcm, token.clone());
+    // One more registration per open mailbox, so its homes can wake us too.
+    let mailboxes = crate::mailbox::open_ids(now_ms()).unwrap_or_default();
     let endpoint = ENDPOINT.get().context("endpoint not initialized")?;
     let conn = endpoint.connect(gateway.addr, &gateway.id.to_string())?.await?;
 
@@ -152,13 +490,19 @@ async fn send_registration(gateway: &GatewayDescriptor, token: Vec<u8>) -> Resul
         return Err(anyhow!("gateway {} lacks PUSH_GATEWAY", gateway.id));
     }
 
-    let (mut tx, _rx) = conn.open_bi().await?;
-    tx.write_all(&PushRequest::Register(reg).pack()?).await?;
-    tx.finish()?;
-    // finish() only marks the stream done locally; await the peer's ack or close() drops the unsent
-    // op.
-    let _ = tx.stopped().await;
+    let regs = std::iter::once(reg).chain(mailboxes.iter().map(|mailbox| {
+        RegisterToken::signed(&mailbox_push_key(mailbox), PushProvider::Fcm, token.clone())
+    }));
+    for reg in regs {
+        let (mut tx, _rx) = conn.open_bi().await?;
+        tx.write_all(&PushRequest::Register(reg).pack()?).await?;
+        tx.finish()?;
+        // finish() only marks the stream done locally; await the peer's ack or close() drops the
+        // unsent op.
+        let _ = tx.stopped().await;
+    }
     conn.close(0u32.into(), b"registered");
+    *GATEWAY_MAILBOXES.lock() = mailboxes;
     Ok(())
 }
 
@@ -215,3 +559,55 @@ fn evict_gateway(id: &RelayId) {
     let conn = NETWORK_DB.lock();
     let _ = conn.execute("DELETE FROM gateways WHERE id = ?1", params![id.to_string()]);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_pair() -> (x25519_dalek::StaticSecret, [u8; 32]) {
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let public = x25519_dalek::PublicKey::from(&secret).to_bytes();
+        (secret, public)
+    }
+
+    #[test]
+    fn a_sealed_wake_hint_opens_for_its_recipient() {
+        let (secret, push_pk) = push_pair();
+        let sender = SigningKey::from_bytes(&[7; 32]);
+        let sender_ipk = sender.verifying_key().to_bytes();
+        let to = SigningKey::from_bytes(&[9; 32]).verifying_key().to_bytes();
+
+        let sealed = seal_hint_to(&push_pk, &to, &sender_ipk, &sender, [3; 16], &[5; 16])
+            .expect("seal");
+        let hint = open_hint_with(&secret, &to, &sealed.0).expect("open");
+        assert_eq!(hint.sender.0, sender_ipk);
+        assert_eq!(hint.conversation.0, [3; 16]);
+        assert_eq!(hint.dispatch_id.0, [5; 16]);
+    }
+
+    #[test]
+    fn a_wake_hint_stays_shut_to_anyone_else() {
+        let (_, push_pk) = push_pair();
+        let (other_secret, _) = push_pair();
+        let sender = SigningKey::from_bytes(&[7; 32]);
+        let sender_ipk = sender.verifying_key().to_bytes();
+        let to = SigningKey::from_bytes(&[9; 32]).verifying_key().to_bytes();
+
+        let sealed = seal_hint_to(&push_pk, &to, &sender_ipk, &sender, [3; 16], &[5; 16])
+            .expect("seal");
+        assert!(open_hint_with(&other_secret, &to, &sealed.0).is_none(), "wrong push key");
+        let (secret, push_pk) = push_pair();
+        let sealed = seal_hint_to(&push_pk, &to, &sender_ipk, &sender, [3; 16], &[5; 16])
+            .expect("seal");
+        assert!(open_hint_with(&secret, &sender_ipk, &sealed.0).is_none(), "wrong recipient");
+    }
+
+    #[test]
+    fn a_missing_push_key_is_asked_for_once_per_retry_window() {
+        let mut asked = HashMap::new();
+        assert!(should_ask(&mut asked, &[1; 32], 1_000));
+        assert!(!should_ask(&mut asked, &[1; 32], 1_000 + PUSH_KEY_RETRY_MS - 1));
+        assert!(should_ask(&mut asked, &[2; 32], 1_000), "other contacts still ask");
+        assert!(should_ask(&mut asked, &[1; 32], 1_000 + PUSH_KEY_RETRY_MS));
+    }
+}
@@ -262,6 +262,9 @@ pub(crate) mod tests {
         /// Optional pinned outcome for `publish_welcome_to_homes` —
         /// useful to simulate a home returning `Failed`.
         pub forced_publish_welcome: Mutex<Option<PublishOutcome>>,
+        /// Recipients `deliver_welcome` can't reach, as if offline with no
+        /// home answering.
+        pub unreachable:            Mutex<Vec<[u8; 32]>>,
     }
 
     impl FakeDhtClient {
@@ -343,6 +346,9 @@ pub(crate) mod tests {
         async fn deliver_welcome(
             &self, envelope: &WelcomeEnvelopeP,
         ) -> DhtClientResult<()> {
+            if self.unreachable.lock().contains(&envelope.recipient_ipk.0) {
+                return Err(DhtClientError::Transport("recipient unreachable".into()));
+            }
             // Mirror publish's side effects so fetch_welcomes round-trips work.
             self.welcomes_published.lock().push(envelope.clone());
             let id_src = blake3::hash(&envelope.welcome_blob.0);
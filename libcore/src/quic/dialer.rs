@@ -1,12 +1,106 @@
 use std::io;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use common::node::config::DEFAULT_RESOLVER_PORT;
+use common::node::config::DEFAULT_TUNNEL_PORT;
+use common::quic::config::build_tunnel_client_tls;
+use common::quic::tunnel::Framing;
+use common::quic::tunnel::StreamSocket;
+use common::quic::tunnel::TUNNEL_ALPN;
+use common::quic::tunnel::TUNNEL_PREAMBLE;
+use common::quic::tunnel::ws_client_handshake;
+use once_cell::sync::OnceCell;
+use quinn::Connecting;
 use quinn::Connection;
+use quinn::Endpoint;
+use quinn::EndpointConfig;
+use quinn::TokioRuntime;
+use rustls::RootCertStore;
+use rustls::pki_types::ServerName;
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
 
 use crate::ENDPOINT;
 use crate::data::ResolverSeed;
+use crate::db::network::Transport;
+
+/// Budget for the TCP connect + outer TLS (+ WebSocket upgrade) of a tunnel
+/// dial. The QUIC handshake inside gets the caller's own timeout on top.
+const TUNNEL_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a tunnel dial needs beyond the UDP endpoint, captured at `init`.
+struct TunnelCfg {
+    /// Same QUIC client config as [`ENDPOINT`]'s default: the QUIC inside a
+    /// tunnel is the ordinary `client/N` session.
+    quic:    quinn::ClientConfig,
+    /// Outer TLS to a relay's own tunnel port, under the bundled root CA.
+    direct:  TlsConnector,
+    /// Outer TLS to a bridge or fronting CDN, under the public web roots.
+    fronted: TlsConnector,
+}
+
+static TUNNEL_CFG: OnceCell<TunnelCfg> = OnceCell::new();
+
+/// Captures the configs tunnel dials reuse. Called once from `init`.
+pub fn init_tunnels(quic: quinn::ClientConfig, roots: &RootCertStore) {
+    let direct = TlsConnector::from(Arc::new(build_tunnel_client_tls(roots)));
+
+    let web_roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let mut fronted = rustls::ClientConfig::builder()
+        .with_root_certificates(web_roots)
+        .with_no_client_auth();
+    fronted.alpn_protocols = vec![TUNNEL_ALPN.to_vec()];
+
+    TUNNEL_CFG
+        .set(TunnelCfg { quic, direct, fronted: TlsConnector::from(Arc::new(fronted)) })
+        .ok();
+}
+
+/// A WebSocket bridge in front of one relay, as configured by the user:
+/// `wss://host[:port]/path`, optionally reached through `front` — the
+/// domain put in DNS and the TLS SNI, while `host` only appears in the
+/// encrypted `Host` header (domain fronting).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bridge {
+    pub host:  String,
+    pub port:  u16,
+    pub path:  String,
+    pub front: Option<String>,
+}
+
+impl Bridge {
+    pub fn parse(url: &str, front: Option<String>) -> Result<Self, DialerError> {
+        let bad = || {
+            let msg = "bridge url must be wss://host[:port]/path";
+            DialerError::Error(io::Error::new(io::ErrorKind::InvalidInput, msg))
+        };
+
+        let rest = url.strip_prefix("wss://").ok_or_else(bad)?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((h, p)) => (h, p.parse::<u16>().map_err(|_| bad())?),
+            None => (authority, 443),
+        };
+        if host.is_empty() || front.as_deref().is_some_and(str::is_empty) {
+            return Err(bad());
+        }
+
+        Ok(Self { host: host.to_owned(), port, path: path.to_owned(), front })
+    }
+
+    /// The name dialed and presented as SNI.
+    fn tls_host(&self) -> &str {
+        self.front.as_deref().unwrap_or(&self.host)
+    }
+}
 
 pub fn quinn_err<E>(e: E) -> DialerError
 where
@@ -62,3 +156,104 @@ pub async fn connect_to_any_seed(seeds: &[ResolverSeed]) -> Result<Connection, D
 
     Err(last_err.unwrap_or_else(|| io::Error::other("no resolver seed succeeded")).into())
 }
+
+/// Starts a QUIC connect to the relay at `addr` (its UDP address, as
+/// resolved) over `transport`. Tunnel transports do their TCP/TLS setup here
+/// and hand back a `Connecting` on a private endpoint, so callers time out
+/// and classify the QUIC handshake the same way for every transport.
+pub async fn dial_relay(
+    addr: SocketAddr, server_name: &str, transport: Transport, tunnel_port: Option<u16>,
+    bridge: Option<&Bridge>,
+) -> Result<Connecting, DialerError> {
+    if matches!(transport, Transport::Quic) {
+        return ENDPOINT.get().unwrap().connect(addr, server_name).map_err(quinn_err);
+    }
+    let tunnel = async {
+        if matches!(transport, Transport::Tls) {
+            let port = tunnel_port.unwrap_or(DEFAULT_TUNNEL_PORT);
+            return tunnel_direct(addr, port, server_name).await;
+        }
+        let bridge = bridge.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no bridge configured for relay")
+        })?;
+        tunnel_bridge(addr, bridge).await
+    };
+
+    let socket = tokio::time::timeout(TUNNEL_SETUP_TIMEOUT, tunnel)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "tunnel setup timed out"))??;
+
+    let cfg = TUNNEL_CFG.get().ok_or_else(|| io::Error::other("tunnels not initialised"))?;
+    // No handle is kept: quinn keeps the endpoint alive for as long as the
+    // connection is, and dropping it then drops the socket and the stream.
+    let endpoint = Endpoint::new_with_abstract_socket(
+        EndpointConfig::default(),
+        None,
+        socket,
+        Arc::new(TokioRuntime),
+    )?;
+    endpoint.connect_with(cfg.quic.clone(), addr, server_name).map_err(quinn_err)
+}
+
+/// TLS to the relay's own tunnel `port` on the relay's host, then the
+/// length-framed tunnel.
+async fn tunnel_direct(
+    addr: SocketAddr, port: u16, server_name: &str,
+) -> Result<Arc<StreamSocket>, DialerError> {
+    let cfg = TUNNEL_CFG.get().ok_or_else(|| io::Error::other("tunnels not initialised"))?;
+    let name = ServerName::try_from(server_name.to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let tcp = TcpStream::connect(SocketAddr::new(addr.ip(), port)).await?;
+    _ = tcp.set_nodelay(true);
+    let mut stream = cfg.direct.connect(name, tcp).await?;
+    stream.write_all(TUNNEL_PREAMBLE).await?;
+
+    let socket = StreamSocket::new((Ipv6Addr::UNSPECIFIED, 0).into());
+    socket.attach(addr, stream, Framing::Length);
+    Ok(socket)
+}
+
+/// TLS to the bridge (or its front), then a WebSocket upgrade naming the
+/// bridge. The QUIC inside is still addressed to — and authenticates — the
+/// relay; the bridge only ever sees ciphertext.
+async fn tunnel_bridge(
+    addr: SocketAddr, bridge: &Bridge,
+) -> Result<Arc<StreamSocket>, DialerError> {
+    let cfg = TUNNEL_CFG.get().ok_or_else(|| io::Error::other("tunnels not initialised"))?;
+    let name = ServerName::try_from(bridge.tls_host().to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let tcp = TcpStream::connect((bridge.tls_host(), bridge.port)).await?;
+    _ = tcp.set_nodelay(true);
+    let mut stream = cfg.fronted.connect(name, tcp).await?;
+    ws_client_handshake(&mut stream, &bridge.host, &bridge.path, rand::random()).await?;
+
+    let socket = StreamSocket::new((Ipv6Addr::UNSPECIFIED, 0).into());
+    socket.attach(addr, stream, Framing::ws_client());
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_url_parses_host_port_and_path() {
+        let front = Some("cdn.example.net".to_owned());
+        let b = Bridge::parse("wss://relay-1.example.org/ws", front).unwrap();
+        assert_eq!((b.host.as_str(), b.port, b.path.as_str()), ("relay-1.example.org", 443, "/ws"));
+        assert_eq!(b.tls_host(), "cdn.example.net");
+
+        let b = Bridge::parse("wss://bridge.example:8443", None).unwrap();
+        assert_eq!((b.port, b.path.as_str(), b.tls_host()), (8443, "/", "bridge.example"));
+    }
+
+    #[test]
+    fn bridge_url_rejects_non_wss_and_empty_parts() {
+        assert!(Bridge::parse("ws://bridge.example/", None).is_err());
+        assert!(Bridge::parse("wss:///path", None).is_err());
+        assert!(Bridge::parse("wss://bridge.example:x/", None).is_err());
+        assert!(Bridge::parse("wss://bridge.example/", Some(String::new())).is_err());
+    }
+}
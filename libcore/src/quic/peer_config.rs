@@ -2,6 +2,8 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
+use common::quic::config::hybrid_client_tls;
+use common::quic::config::hybrid_server_tls;
 use common::quic::protorole::ProtoRole;
 use ed25519_dalek::Signature as Ed25519Signature;
 use ed25519_dalek::Signer as _;
@@ -380,11 +382,11 @@ fn peer_transport_cfg() -> Arc<TransportConfig> {
 pub fn build_peer_server_cfg(identity: &PeerIdentity) -> Result<ServerConfig> {
     let certified_key = generate_identity_cert(identity)?;
 
-    let mut crypto = rustls::ServerConfig::builder()
+    let mut crypto = hybrid_server_tls()
         .with_client_cert_verifier(Arc::new(PeerClientCertVerifier))
         .with_cert_resolver(Arc::new(rustls::sign::SingleCertAndKey::from(certified_key)));
 
-    crypto.alpn_protocols = vec![ProtoRole::Peer.alpn().into()];
+    crypto.alpn_protocols = ProtoRole::Peer.alpns();
 
     let mut cfg = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(crypto)?));
     cfg.transport_config(peer_transport_cfg());
@@ -401,12 +403,12 @@ pub fn build_peer_server_cfg(identity: &PeerIdentity) -> Result<ServerConfig> {
 pub fn build_peer_client_cfg(identity: &PeerIdentity) -> Result<ClientConfig> {
     let certified_key = generate_identity_cert(identity)?;
 
-    let mut tls = rustls::ClientConfig::builder()
+    let mut tls = hybrid_client_tls()
         .dangerous()
         .with_custom_certificate_verifier(Arc::new(PeerServerCertVerifier))
         .with_client_cert_resolver(Arc::new(rustls::sign::SingleCertAndKey::from(certified_key)));
 
-    tls.alpn_protocols = vec![ProtoRole::Peer.alpn().into()];
+    tls.alpn_protocols = ProtoRole::Peer.alpns();
 
     let quic_config = QuicClientConfig::try_from(tls)?;
 
@@ -432,19 +434,19 @@ pub(crate) fn test_peer_configs(key: &SigningKey) -> Result<(ServerConfig, Clien
         CertifiedKey::new(vec![CertificateDer::from(cert_der)], signing_key)
     }
 
-    let mut server_tls = rustls::ServerConfig::builder()
+    let mut server_tls = hybrid_server_tls()
         .with_client_cert_verifier(Arc::new(PeerClientCertVerifier))
         .with_cert_resolver(Arc::new(rustls::sign::SingleCertAndKey::from(certified(key))));
-    server_tls.alpn_protocols = vec![ProtoRole::Peer.alpn().into()];
+    server_tls.alpn_protocols = ProtoRole::Peer.alpns();
     let mut server = ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(server_tls)?));
     server.transport_config(peer_transport_cfg());
     server.migration(false);
 
-    let mut client_tls = rustls::ClientConfig::builder()
+    let mut client_tls = hybrid_client_tls()
         .dangerous()
         .with_custom_certificate_verifier(Arc::new(PeerServerCertVerifier))
         .with_client_cert_resolver(Arc::new(rustls::sign::SingleCertAndKey::from(certified(key))));
-    client_tls.alpn_protocols = vec![ProtoRole::Peer.alpn().into()];
+    client_tls.alpn_protocols = ProtoRole::Peer.alpns();
     let mut client = ClientConfig::new(Arc::new(QuicClientConfig::try_from(client_tls)?));
     client.transport_config(peer_transport_cfg());
 
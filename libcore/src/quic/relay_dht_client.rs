@@ -222,6 +222,8 @@ impl DhtClient for RelayDhtClient {
             accepted_at_ms: 0,
             // First-contact welcome: the peer must be woken to receive it.
             wake:    true,
+            wake_hint: None,
+            token: None,
         };
 
         match self.rpc(CRelayPacket::Dispatch(fwd)).await? {
@@ -9,9 +9,11 @@ use std::time::Duration;
 use anyhow::Result;
 use anyhow::anyhow;
 use anyhow::bail;
+use common::MIN_PROTOCOL_VERSION;
 use common::PROTOCOL_VERSION;
 use common::proto::Sender;
 use common::proto::client_rel::CHandshakePacket;
+use common::proto::client_rel::LEGACY_HELLO_VERSION;
 use common::proto::client_rel::CRelayPacket;
 use common::proto::client_rel::DeliverP;
 use common::proto::client_rel::QueryP;
@@ -20,15 +22,19 @@ use common::proto::client_rel::SHandshakePacket as SHSP;
 use common::proto::client_rel::SRelayPacket;
 use common::proto::client_rel::ServerHandshakeResultP as SHSRP;
 use common::proto::client_rel::dispatch_sig_message;
+use common::proto::client_rel::relay_auth_message;
 use common::proto::dht_p2p::MAX_FETCH_QUEUE_ACK_IDS;
 use common::proto::dht_p2p::queue_fetch_ack_signing_input;
 use common::proto::dht_p2p::queue_fetch_signing_input;
 use common::proto::mls_wire::AppPayload;
 use common::proto::mls_wire::Body;
+use common::proto::mls_wire::EXT_RECOVERY_REVOKE;
+use common::proto::mls_wire::EXT_RECOVERY_SHARE;
 use common::proto::mls_wire::ReceiptKind;
 use common::proto::pack::Unpacker;
 use common::proto::pack::unpack;
 use common::quic::id::NodeId;
+use common::quic::protorole::ProtoRole;
 use common::types::bytes::Bytes;
 use ed25519_dalek::VerifyingKey;
 use log::debug;
@@ -41,16 +47,17 @@ use tokio::sync::Semaphore;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::ENDPOINT;
 use crate::data::contact::Contact;
 use crate::data::conversation::Conversation;
 use crate::data::identity::IdentitySigner;
 use crate::data::message::Message;
 use crate::data::relay::Relay;
+use crate::db::network::Transport;
 use crate::db::mls::stash_db_handle;
 use crate::events::Emittable;
 use crate::events::connection::ConnectionState;
 use crate::events::messaging::MessageEv;
+use crate::quic::dialer::dial_relay;
 use crate::quic::relay_dht_client::RelayDhtClient;
 use crate::ret_err;
 use crate::state::CONNECTION_START_TIME;
@@ -125,10 +132,33 @@ impl Relay {
     ) -> Result<JoinHandle<ConnectionError>, RelayConnError> {
         let addr = SocketAddr::new(IpAddr::from_str(&self.host)?, self.port);
 
-        info!("connecting to relay {} ({})", node_short(&self.id), addr_short(addr));
+        info!(
+            "connecting to relay {} ({}) over {}",
+            node_short(&self.id),
+            addr_short(addr),
+            self.transport
+        );
         ConnectionState::Connecting.emit();
 
-        let connecting = ENDPOINT.get().unwrap().connect(addr, &self.id)?;
+        let bridge = match self.transport {
+            Transport::WebSocket => self.bridge().map_err(|e| RelayConnError::Error(e.into()))?,
+            _ => None,
+        };
+        let dial = dial_relay(addr, &self.id, self.transport, self.tunnel_port, bridge.as_ref());
+        let connecting = match dial.await {
+            Ok(connecting) => connecting,
+            Err(err) => {
+                warn!(
+                    "relay {} ({}) {} dial failed: {err}",
+                    node_short(&self.id),
+                    addr_short(addr),
+                    self.transport
+                );
+                ConnectionState::Failed.emit();
+                _ = self.record_dial_failure();
+                return Err(RelayConnError::Continue);
+            },
+        };
         let conn = match tokio::time::timeout(CONNECT_TIMEOUT, connecting).await {
             Ok(Ok(conn)) => conn,
             Ok(Err(err)) => {
@@ -146,7 +176,7 @@ impl Relay {
                         node_short(&self.id),
                         addr_short(addr)
                     );
-                    _ = self.record_failure();
+                    _ = self.record_dial_failure();
                 }
                 return Err(RelayConnError::Continue);
             },
@@ -158,7 +188,7 @@ impl Relay {
                     CONNECT_TIMEOUT.as_secs()
                 );
                 ConnectionState::Failed.emit();
-                _ = self.record_failure();
+                _ = self.record_dial_failure();
                 return Err(RelayConnError::Continue);
             },
         };
@@ -173,19 +203,46 @@ impl Relay {
 
         //===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
 
-        // 1. Server is expecting `Hello` from client
-
-        CHandshakePacket::Hello { ipk: ipk.to_bytes().into() }.send(&mut tx).await?;
+        // 1. Server is expecting `Hello` from client, naming every version we
+        //    speak. A relay that only offered `client/6` predates negotiation
+        //    and reads nothing but the plain `Hello`.
+
+        let session = ProtoRole::version_from_conn(&conn).unwrap_or(LEGACY_HELLO_VERSION);
+        if session <= LEGACY_HELLO_VERSION {
+            CHandshakePacket::Hello { ipk: ipk.to_bytes().into() }.send(&mut tx).await?;
+        } else {
+            CHandshakePacket::HelloVersions {
+                ipk:         ipk.to_bytes().into(),
+                min_version: MIN_PROTOCOL_VERSION,
+                max_version: PROTOCOL_VERSION,
+            }
+            .send(&mut tx)
+            .await?;
+        }
 
         //===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
 
-        // 2. Server must respond with challenge
+        // 2. Server must respond with challenge, and the version it settled on
+        //    (implied by a plain `Challenge`). A relay past our window rejects
+        //    here instead.
 
-        let SHSP::Challenge { nonce } = SHSP::unpack(&mut rx).await? else {
-            return Err(RelayConnError::Error(anyhow!("Handshake Packet Order Mismatch")));
+        let (nonce, version) = match SHSP::unpack(&mut rx).await? {
+            SHSP::ChallengeAt { nonce, version } => (nonce, version),
+            SHSP::Challenge { nonce } => (nonce, LEGACY_HELLO_VERSION),
+            SHSP::HandshakeResult(SHSRP::Reject { reason }) => {
+                warn!("relay handshake failed : {reason}");
+                _ = self.record_failure();
+                return Err(RelayConnError::Continue);
+            },
+            _ => {
+                return Err(RelayConnError::Error(anyhow!("Handshake Packet Order Mismatch")));
+            },
         };
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+            return Err(RelayConnError::Error(anyhow!("relay settled on v{version}")));
+        }
 
-        let msg = [b"relay-auth-v" as &[u8], &PROTOCOL_VERSION.to_be_bytes(), &*nonce].concat();
+        let msg = relay_auth_message(version, &nonce);
 
         CHandshakePacket::Proof {
             sig: IdentitySigner::sign(&msg).map_err(RelayConnError::Error)?.to_bytes().into(),
@@ -215,7 +272,8 @@ impl Relay {
             },
         };
 
-        info!("authenticated with relay {}", node_short(&self.id));
+        info!("authenticated with relay {} at v{version}", node_short(&self.id));
+        self.version = version;
         CONNECTION_START_TIME.store(timestamp, Ordering::Relaxed);
         // Auth is up but the offline backlog (welcomes, deferred sends, queued
         // messages) isn't drained yet — surface that as "Syncing…". `handle`
@@ -361,19 +419,32 @@ impl Relay {
         }
         _ = tx.finish();
 
-        // Optional follow-up: absent when the drain was local-only (the
-        // relay's stream task just ends → read errors out).
-        if let Ok(Ok(SRelayPacket::AckAuthRequest {
-            requester_relay_id,
-            delivered_ids,
-            suggested_timestamp,
-        })) = tokio::time::timeout(Duration::from_secs(10), SRelayPacket::unpack(&mut rx)).await
+        // Optional follow-ups, one per drained queue with remote homes: the
+        // IPK's, then each open mailbox's. Absent when the drain was
+        // local-only (the relay's stream task just ends → read errors out).
+        while let Ok(Ok(request)) =
+            tokio::time::timeout(Duration::from_secs(10), SRelayPacket::unpack(&mut rx)).await
         {
+            let (mailbox, requester_relay_id, delivered_ids, suggested_timestamp) = match request {
+                SRelayPacket::AckAuthRequest {
+                    requester_relay_id,
+                    delivered_ids,
+                    suggested_timestamp,
+                } => (None, requester_relay_id, delivered_ids, suggested_timestamp),
+                SRelayPacket::MailboxAckAuthRequest {
+                    mailbox,
+                    requester_relay_id,
+                    delivered_ids,
+                    suggested_timestamp,
+                } => (Some(mailbox.0), requester_relay_id, delivered_ids, suggested_timestamp),
+                _ => break,
+            };
             match conn.open_bi().await {
                 Ok((mut ack_tx, _ack_rx)) => {
                     if let Err(e) = handle_ack_auth_request(
                         &mut ack_tx,
                         ipk,
+                        mailbox,
                         requester_relay_id,
                         delivered_ids,
                         suggested_timestamp,
@@ -386,7 +457,9 @@ impl Relay {
                     _ = ack_tx.finish();
                 },
                 Err(e) => {
-                    warn!("relay {} ack_drain: open_bi for AckAuth failed: {e}", node_short(&self.id))
+                    let relay = node_short(&self.id);
+                    warn!("relay {relay} ack_drain: open_bi for AckAuth failed: {e}");
+                    return;
                 },
             }
         }
@@ -424,6 +497,18 @@ impl Relay {
         if let Err(err) = self.send_drain_auth(conn, ipk).await {
             warn!("relay {} drain-auth send failed: {err}", node_short(&self.id));
         }
+        // Our blinded mailboxes drain with the same DrainQueue, so they are
+        // opened before it. Same best-effort footing as the permit above.
+        if let Some(home) = self.home_node_id
+            && let Err(err) = crate::mailbox::open_on(conn, home).await
+        {
+            warn!("relay {} mailbox open failed: {err}", node_short(&self.id));
+        }
+        // So are the revocations of tokens we granted forgotten contacts: the
+        // homes of those mailboxes may have changed since they last heard.
+        if let Err(err) = crate::sealed::revoke_on(conn).await {
+            warn!("relay {} token revocation failed: {err}", node_short(&self.id));
+        }
 
         // Re-dispatch durably-queued outbox rows (enqueued while offline, or
         // whose ack was lost) now that a live relay connection exists.
@@ -593,7 +678,8 @@ impl Relay {
                         },
                         // An ack authorization only ever answers our own
                         // AckDrain, on the stream we opened for it.
-                        SRelayPacket::AckAuthRequest { .. } => {
+                        SRelayPacket::AckAuthRequest { .. }
+                        | SRelayPacket::MailboxAckAuthRequest { .. } => {
                             debug!("ignoring unsolicited AckAuthRequest");
                             Ok(())
                         },
@@ -708,11 +794,18 @@ fn handle_presence(list: Vec<common::proto::client_rel::PresenceP>) {
 /// stream (batch-acked via `AckDrain`). `Ok(())` means the message
 /// reached a terminal state (stored / buffered / correctly dropped);
 /// `Err` means it was dropped without effect.
-/// True if `payload` is an `MlsEnvelopeP::Welcome`. Pure → gate is testable.
-fn is_welcome_envelope(payload: &[u8]) -> bool {
+/// True if `payload` may come from someone we share nothing with: a Welcome
+/// (a first pair), social recovery's request and return, which pass between a
+/// fresh install and the contacts it used to have, or a contact's succession,
+/// which comes from the key we don't know yet. Pure → gate is testable.
+fn admits_strangers(payload: &[u8]) -> bool {
+    use common::proto::mls_wire::MlsEnvelopeP;
     matches!(
-        common::proto::mls_wire::MlsEnvelopeP::deser(payload),
-        Ok(common::proto::mls_wire::MlsEnvelopeP::Welcome(_))
+        MlsEnvelopeP::deser(payload),
+        Ok(MlsEnvelopeP::Welcome(_)
+            | MlsEnvelopeP::RecoveryRequest(_)
+            | MlsEnvelopeP::RecoveryReturn(_)
+            | MlsEnvelopeP::Succession(_))
     )
 }
 
@@ -751,11 +844,47 @@ fn verify_dispatch_sig(our_ipk: &VerifyingKey, msg: &DeliverP) -> Result<()> {
         .map_err(|e| anyhow!("dispatch signature: {e}"))
 }
 
+/// Which of our keys `msg` is signed to: the current one, or one it succeeded.
+/// Homes hand what was queued for a retired key on to its successor with the
+/// sender's signature as it was (see `crate::succession`).
+fn addressed_to(our_ipk: &VerifyingKey, msg: &DeliverP) -> Result<VerifyingKey> {
+    addressed_to_any(our_ipk, msg, crate::data::identity::Identity::predecessors)
+}
+
+fn addressed_to_any(
+    our_ipk: &VerifyingKey, msg: &DeliverP, predecessors: impl FnOnce() -> Vec<[u8; 32]>,
+) -> Result<VerifyingKey> {
+    let Err(e) = verify_dispatch_sig(our_ipk, msg) else { return Ok(*our_ipk) };
+    predecessors()
+        .iter()
+        .filter_map(|old| VerifyingKey::from_bytes(old).ok())
+        .find(|old| verify_dispatch_sig(old, msg).is_ok())
+        .ok_or(e)
+}
+
 async fn process_deliver(
     our_ipk: VerifyingKey, msg: DeliverP, dht_client: Option<Arc<RelayDhtClient>>,
 ) -> Result<()> {
-    if let Err(e) = verify_dispatch_sig(&our_ipk, &msg) {
-        warn!("MESSAGE: rejected unsigned/forged dispatch from {}: {e}", hex::encode(&msg.from[..4]));
+    let to = match addressed_to(&our_ipk, &msg) {
+        Ok(to) => to,
+        Err(e) => {
+            let from = hex::encode(&msg.from[..4]);
+            warn!("MESSAGE: rejected unsigned/forged dispatch from {from}: {e}");
+            bail!("bad dispatch signature");
+        },
+    };
+    // A sealed dispatch's outer `from` is a one-shot key; the sender and their
+    // signature are inside. Open it and hold the result to the same check, so
+    // everything below sees an identified delivery either way.
+    let msg = match crate::sealed::open(msg, to.as_bytes()) {
+        Ok(msg) => msg,
+        Err(e) => {
+            warn!("MESSAGE: sealed dispatch did not open: {e}");
+            bail!("unopenable sealed dispatch");
+        },
+    };
+    if let Err(e) = verify_dispatch_sig(&to, &msg) {
+        warn!("MESSAGE: sealed dispatch from {} is forged: {e}", hex::encode(&msg.from[..4]));
         bail!("bad dispatch signature");
     }
 
@@ -773,14 +902,16 @@ async fn process_deliver(
     //
     // Drop Application envelopes from senders we have no standing with. A
     // Welcome from a stranger is a legit first-pair — let it reach the invite
-    // gate downstream.
+    // gate downstream. Recovery envelopes check their own signatures and
+    // never act without the user; a succession must be signed by a key we
+    // already know.
     //
     // Sharing a group counts, not just the address book. Otherwise a group of
     // three where two members have never paired half-works: each can hear
     // whoever invited them and neither can hear the other, with no error on
     // either side. Membership changes ride this same path, so the silence
     // would eventually strand them at an old epoch too.
-    if !is_welcome_envelope(&msg.payload)
+    if !admits_strangers(&msg.payload)
         && !Contact::exists(&msg.from)
         && !Conversation::shares_a_chat_with(&msg.from)
     {
@@ -844,253 +975,11 @@ async fn process_deliver(
             // the group works, so a PENDING contact is now confirmed. No-op if
             // already paired. Fires for PairAck and any real message alike.
             Contact::mark_paired(&msg.from);
-            match AppPayload::deser(&plaintext) {
-                // Content of any wire vintage: Post carries the quote target beside
-                // the body, pre-v12 payloads convert to the same pair. One persist
-                // and one receipt regardless of body kind.
-                Ok(p @ (AppPayload::Post { .. }
-                    | AppPayload::Text(..)
-                    | AppPayload::Reply { .. }
-                    | AppPayload::Image { .. }
-                    | AppPayload::Attachment { .. })) => {
-                    let pair = match p {
-                        AppPayload::Post { reply_to, body } => Some((reply_to, body)),
-                        other => crate::messaging::legacy_body(other),
-                    };
-                    let Some((reply_to, body)) = pair else {
-                        warn!(
-                            "MESSAGE: content payload with no body from {}",
-                            hex::encode(&msg.from[..4])
-                        );
-                        bail!("bad content payload");
-                    };
-                    let did = msg.id.0;
-                    let timestamp = accepted_at_secs(msg.accepted_at_ms);
-                    // Read off before the body moves into the persist.
-                    let auto = match &body {
-                        Body::Attachment { size, file_id, .. } => Some((*size, *file_id)),
-                        _ => None,
-                    };
-                    match crate::messaging::save_inbound_body(
-                        &conv, &author, &did, timestamp, reply_to, body,
-                    ) {
-                        Ok(Some((saved, content))) => {
-                            MessageEv::Received {
-                                id: saved.inner.id,
-                                conversation: conv,
-                                sender: author,
-                                content,
-                                timestamp,
-                            }
-                            .emit();
-                            info!("MESSAGE: received from {}", hex::encode(&msg.from[..4]));
-                            // Auto-Delivered receipt (high-water-mark = this id).
-                            // Spawned so we don't delay the relay's DeliverAck.
-                            let from = *msg.from;
-                            crate::RUNTIME.spawn(async move {
-                                let _ = crate::messaging::send_receipt(
-                                    conv,
-                                    ReceiptKind::Delivered,
-                                    did,
-                                )
-                                .await;
-                            });
-                            // Fetch the bytes without a tap only from a paired contact
-                            // over a trusted network; otherwise the UI drives the pull.
-                            // ponytail: on_wifi is hardcoded false until the platform
-                            // feeds real network state — no-op today, correct and ready.
-                            if let Some((size, file_id)) = auto {
-                                if crate::transfer::should_auto_download(&from, size, false) {
-                                    crate::RUNTIME.spawn(async move {
-                                        let _ = crate::transfer::download(file_id).await;
-                                    });
-                                }
-                            }
-                        },
-                        // Relay redelivered a dispatch_id we already stored: no
-                        // re-emit, but still Ok so the caller acks and the relay GCs.
-                        Ok(None) => {
-                            debug!(
-                                "MESSAGE: duplicate from {}, already stored",
-                                hex::encode(&msg.from[..4])
-                            );
-                        },
-                        Err(e) => {
-                            warn!("MESSAGE: failed to save incoming: {e}");
-                            bail!("save failed: {e}");
-                        },
-                    }
-                },
-                Ok(AppPayload::Receipt { kind, upto }) => {
-                    let status = match kind {
-                        ReceiptKind::Delivered => crate::data::message::STATUS_DELIVERED,
-                        ReceiptKind::Read => crate::data::message::STATUS_READ,
-                    };
-                    // Record this member's watermark; the shared status only
-                    // advances once the slowest member has crossed it, so a
-                    // group tick means everyone, not anyone.
-                    if Message::group_receipt_upto(&conv, &author, &upto, status) {
-                        MessageEv::Receipt { conversation: conv, member: author, upto, status }
-                            .emit();
-                    }
-                },
-                Ok(AppPayload::Edit { target, content }) => {
-                    // own=false plus an author check: a member may only edit
-                    // messages IT sent (outgoing=0 AND sender_ipk = them), so
-                    // one member cannot rewrite another's words.
-                    match Message::apply_edit(&conv, &target, &content, false, Some(&author)) {
-                        Some(row) => {
-                            info!("MESSAGE: edit from {}", hex::encode(&msg.from[..4]));
-                            MessageEv::Edited { id: row.id, conversation: conv, content }.emit();
-                        },
-                        // Out-of-order: target not stored yet. Rare in 1:1
-                        // same-epoch (the original precedes) — drop.
-                        None => debug!(
-                            "MESSAGE: edit for unknown target from {}",
-                            hex::encode(&msg.from[..4])
-                        ),
-                    }
-                },
-                Ok(AppPayload::Revise { target, body }) => {
-                    // own=false: a peer may only revise messages IT sent us. The
-                    // matrix check lives in apply_revise_body — a refused swap
-                    // errors rather than half-applying.
-                    match crate::messaging::apply_revise_body(&conv, &target, body, false) {
-                        Ok(Some((row, content))) => {
-                            info!("MESSAGE: revise from {}", hex::encode(&msg.from[..4]));
-                            MessageEv::Edited { id: row.id, conversation: conv, content }.emit();
-                        },
-                        // Out-of-order: target not stored yet. Rare in 1:1
-                        // same-epoch (the original precedes) — drop.
-                        Ok(None) => debug!(
-                            "MESSAGE: revise for unknown target from {}",
-                            hex::encode(&msg.from[..4])
-                        ),
-                        Err(e) => {
-                            warn!("MESSAGE: revise from {} rejected: {e}", hex::encode(&msg.from[..4]))
-                        },
-                    }
-                },
-                Ok(AppPayload::Delete { target }) => {
-                    // own=false plus an author check: a member may only delete
-                    // messages IT sent, never another member's.
-                    match Message::apply_delete(&conv, &target, false, Some(&author)) {
-                        Some(row) => {
-                            info!("MESSAGE: delete from {}", hex::encode(&msg.from[..4]));
-                            MessageEv::Deleted { id: row.id, conversation: conv }.emit();
-                        },
-                        None => debug!(
-                            "MESSAGE: delete for unknown target from {}",
-                            hex::encode(&msg.from[..4])
-                        ),
-                    }
-                },
-                Ok(AppPayload::React { target, emoji, add }) => {
-                    // Reactor is the MLS sender (`msg.from`) — attributed to its
-                    // own IPK, so this is already group-correct.
-                    let ts = accepted_at_secs(msg.accepted_at_ms);
-                    if crate::data::reaction::Reaction::apply(
-                        &conv, &target, &author, &emoji, add, ts,
-                    ) {
-                        crate::events::messaging::ReactionEv {
-                            conversation: conv,
-                            dispatch_id: target,
-                            reactor: author,
-                            emoji,
-                            add,
-                        }
-                        .emit();
-                    }
-                },
-                Ok(AppPayload::System(event)) => {
-                    use common::proto::mls_wire::SystemEvent;
-                    use crate::db::messages::SYSTEM_ADDED;
-                    use crate::db::messages::SYSTEM_LEFT;
-                    use crate::db::messages::SYSTEM_REMOVED;
-                    use crate::db::messages::SYSTEM_TITLED;
-
-                    let ts = accepted_at_secs(msg.accepted_at_ms);
-                    let (code, target) = match &event {
-                        SystemEvent::Added { who } => (SYSTEM_ADDED, hex::encode(who.0)),
-                        SystemEvent::Left { who } => (SYSTEM_LEFT, hex::encode(who.0)),
-                        SystemEvent::Removed { who } => (SYSTEM_REMOVED, hex::encode(who.0)),
-                        SystemEvent::Titled { title } => (SYSTEM_TITLED, title.clone()),
-                    };
-                    // A rename has no Commit behind it, so the event itself is
-                    // the change. Membership events only narrate — the Commit
-                    // is what actually moved the roster, and syncing from the
-                    // MLS group after merging it is the authoritative path.
-                    if let SystemEvent::Titled { title } = &event {
-                        // Only a group has a shared name. Renaming a direct
-                        // chat from the wire would let a peer relabel a DM,
-                        // and did whenever a group was mis-homed into one.
-                        let is_group = Conversation::get(&conv).is_some_and(|c| {
-                            c.kind == crate::data::conversation::KIND_GROUP
-                        });
-                        if !is_group {
-                            warn!("GROUP: ignored a rename aimed at a direct chat");
-                        } else if let Err(e) = Conversation::set_title(&conv, title) {
-                            warn!("GROUP: could not apply a title change: {e}");
-                        }
-                    }
-                    // Someone joined after us, so they never heard the
-                    // introduction we made on our own way in. Say it again,
-                    // to them alone.
-                    if let SystemEvent::Added { who } = &event {
-                        if who.0 != our_ipk.to_bytes() {
-                            crate::messaging::introduce_ourselves_to(conv, who.0);
-                        }
-                    }
-                    match Message::save_system(conv, author, &msg.id.0, code, &target, ts, false) {
-                        Ok(Some(row)) => MessageEv::Received {
-                            id: row.inner.id,
-                            conversation: conv,
-                            sender: author,
-                            content: target,
-                            timestamp: ts,
-                        }
-                        .emit(),
-                        Ok(None) => debug!("GROUP: duplicate system event, already stored"),
-                        Err(e) => warn!("GROUP: could not store a system event: {e}"),
-                    }
-                },
-                Ok(AppPayload::Profile { name }) => {
-                    // Their claim about themselves, kept apart from the address
-                    // book so it can never overwrite a name we chose. Stored,
-                    // never shown as a message — nobody said anything.
-                    if let Err(e) = crate::data::peer_name::put(&author, &name) {
-                        warn!("PROFILE: could not record a self-asserted name: {e}");
-                    }
-                },
-                Ok(AppPayload::PairAck) => {
-                    // Proof-of-pair — its whole job was the mark_paired above.
-                    info!("PAIR: confirmed by {}", hex::encode(&msg.from[..4]));
-                },
-                Ok(AppPayload::P2p { candidates, relay, token, disco_key }) => {
-                    // Candidate offer for a direct connection — hand to the
-                    // P2P layer (routed to the waiting session), never stored.
-                    info!(
-                        "P2P[{}]: received offer — {} cands",
-                        hex::encode(&msg.from[..4]),
-                        candidates.len()
-                    );
-                    crate::p2p::deliver_offer(*msg.from, candidates, relay, token, disco_key);
-                },
-                Ok(AppPayload::FileWant { file_id }) => {
-                    // Reverse-wake control message — routed, never stored. The push
-                    // wake already revived us; bring the P2P listener up so the
-                    // receiver's retry-dial can land (they drive the connect).
-                    info!("P2P: FileWant received from {}", hex::encode(&msg.from[..4]));
-                    crate::transfer::on_file_want(*msg.from, file_id);
-                },
-                Err(e) => {
-                    warn!(
-                        "MESSAGE: undecodable AppPayload from {}: {e}",
-                        hex::encode(&msg.from[..4])
-                    );
-                    bail!("bad AppPayload");
-                },
-            }
+            // A paired contact who reaches us may reach us sealed. No-op while
+            // the token we last gave them has an epoch left to run.
+            crate::sealed::grant_if_due(*msg.from);
+            let (from, did, at) = (*msg.from, msg.id.0, msg.accepted_at_ms);
+            apply_payload(our_ipk.to_bytes(), conv, from, author, did, at, &plaintext).await?;
         },
         Ok(Some(crate::messaging::InboundDecoded::Welcome)) => {
             crate::data::seen::Seen::record(&msg.from, &msg.id.0, systime().as_secs());
@@ -1117,6 +1006,13 @@ async fn process_deliver(
             crate::data::seen::Seen::record(&msg.from, &msg.id.0, systime().as_secs());
             // Already applied (contact REJECTED, messages failed) — just ack.
         },
+        Ok(Some(
+            crate::messaging::InboundDecoded::RejoinRequest
+            | crate::messaging::InboundDecoded::Recovery
+            | crate::messaging::InboundDecoded::Succession,
+        )) => {
+            crate::data::seen::Seen::record(&msg.from, &msg.id.0, systime().as_secs());
+        },
         Ok(Some(crate::messaging::InboundDecoded::ApplicationBuffered)) => {
             // Buffered for a future epoch / staged commit merged.
             // Terminal-good: the caller acks so the relay GCs the entry.
@@ -1164,6 +1060,439 @@ async fn process_deliver(
     Ok(())
 }
 
+/// Act on one decrypted application payload: `from` sent the dispatch,
+/// `author` is its MLS sender and `did` its dispatch id. Split out of
+/// [`process_deliver`] so a payload kept aside by [`crate::data::unsupported`]
+/// takes the same path once an update can read it ([`reparse_unsupported`]).
+pub(crate) async fn apply_payload(
+    our_ipk: [u8; 32], conv: [u8; 16], from: [u8; 32], author: [u8; 32], did: [u8; 16],
+    accepted_at_ms: u64, plaintext: &[u8],
+) -> Result<()> {
+    match AppPayload::deser(plaintext) {
+        // Newer than this build: keep it for one that can read it, rather than
+        // let it vanish with its spent ratchet key.
+        Ok(p) if !crate::data::unsupported::readable(&p) => {
+            let content = matches!(p, AppPayload::Post { .. } | AppPayload::Forward { .. });
+            info!("MESSAGE: kept an unreadable payload from {}", hex::encode(&from[..4]));
+            keep_unreadable(conv, from, author, did, accepted_at_ms, plaintext, content)?;
+        },
+        // Content of any wire vintage: Post carries the quote target beside
+        // the body, pre-v12 payloads convert to the same pair, and a
+        // Forward is a quote-less Post flagged as passed along. One
+        // persist and one receipt regardless of body kind.
+        Ok(p @ (AppPayload::Post { .. }
+            | AppPayload::Forward { .. }
+            | AppPayload::Text(..)
+            | AppPayload::Reply { .. }
+            | AppPayload::Image { .. }
+            | AppPayload::Attachment { .. })) => {
+            let forwarded = matches!(p, AppPayload::Forward { .. });
+            let pair = match p {
+                AppPayload::Post { reply_to, body } => Some((reply_to, body)),
+                AppPayload::Forward { body } => Some((None, body)),
+                other => crate::messaging::legacy_body(other),
+            };
+            let Some((reply_to, body)) = pair else {
+                warn!(
+                    "MESSAGE: content payload with no body from {}",
+                    hex::encode(&from[..4])
+                );
+                bail!("bad content payload");
+            };
+            let timestamp = accepted_at_secs(accepted_at_ms);
+            // Read off before the body moves into the persist.
+            let auto = match &body {
+                Body::Attachment { size, file_id, .. }
+                | Body::Video { size, file_id, .. } => Some((*size, *file_id)),
+                Body::Voice { size, file_id: Some(file_id), .. } => {
+                    Some((*size, *file_id))
+                },
+                _ => None,
+            };
+            let pack = match &body {
+                Body::Sticker { pack, .. } => Some(*pack),
+                _ => None,
+            };
+            match crate::messaging::save_inbound_body(
+                &conv, &author, &did, timestamp, reply_to, body, forwarded,
+            ) {
+                Ok(Some((saved, content))) => {
+                    MessageEv::Received {
+                        id: saved.inner.id,
+                        conversation: conv,
+                        sender: author,
+                        content,
+                        timestamp,
+                    }
+                    .emit();
+                    info!("MESSAGE: received from {}", hex::encode(&from[..4]));
+                    // Auto-Delivered receipt (high-water-mark = this id).
+                    // Spawned so we don't delay the relay's DeliverAck.
+                    crate::RUNTIME.spawn(async move {
+                        let _ = crate::messaging::send_receipt(
+                            conv,
+                            ReceiptKind::Delivered,
+                            did,
+                        )
+                        .await;
+                    });
+                    // Fetch the bytes without a tap only from a paired contact
+                    // over a trusted network; otherwise the UI drives the pull.
+                    // ponytail: on_wifi is hardcoded false until the platform
+                    // feeds real network state — no-op today, correct and ready.
+                    if let Some((size, file_id)) = auto
+                        && crate::transfer::should_auto_download(&from, size, false)
+                    {
+                        crate::RUNTIME.spawn(async move {
+                            let _ = crate::transfer::download(file_id).await;
+                        });
+                    }
+                    // A sticker from a pack we lack: ask the room for it.
+                    if let Some(pack) = pack {
+                        crate::stickers::want(conv, pack);
+                    }
+                },
+                // Relay redelivered a dispatch_id we already stored: no
+                // re-emit, but still Ok so the caller acks and the relay GCs.
+                Ok(None) => {
+                    debug!(
+                        "MESSAGE: duplicate from {}, already stored",
+                        hex::encode(&from[..4])
+                    );
+                },
+                Err(e) => {
+                    warn!("MESSAGE: failed to save incoming: {e}");
+                    bail!("save failed: {e}");
+                },
+            }
+        },
+        Ok(AppPayload::Receipt { kind, upto }) => {
+            let status = match kind {
+                ReceiptKind::Delivered => crate::data::message::STATUS_DELIVERED,
+                ReceiptKind::Read => crate::data::message::STATUS_READ,
+            };
+            // Record this member's watermark; the shared status only
+            // advances once the slowest member has crossed it, so a
+            // group tick means everyone, not anyone.
+            if Message::group_receipt_upto(&conv, &author, &upto, status) {
+                MessageEv::Receipt { conversation: conv, member: author, upto, status }
+                    .emit();
+            }
+        },
+        Ok(AppPayload::Edit { target, content }) => {
+            // own=false plus an author check: a member may only edit
+            // messages IT sent (outgoing=0 AND sender_ipk = them), so
+            // one member cannot rewrite another's words.
+            match Message::apply_edit(&conv, &target, &content, false, Some(&author)) {
+                Some(row) => {
+                    info!("MESSAGE: edit from {}", hex::encode(&from[..4]));
+                    MessageEv::Edited { id: row.id, conversation: conv, content }.emit();
+                },
+                // Out-of-order: target not stored yet. Rare in 1:1
+                // same-epoch (the original precedes) — drop.
+                None => debug!(
+                    "MESSAGE: edit for unknown target from {}",
+                    hex::encode(&from[..4])
+                ),
+            }
+        },
+        Ok(AppPayload::Revise { target, body }) => {
+            // own=false: a peer may only revise messages IT sent us. The
+            // matrix check lives in apply_revise_body — a refused swap
+            // errors rather than half-applying.
+            let pack = match &body {
+                Body::Sticker { pack, .. } => Some(*pack),
+                _ => None,
+            };
+            match crate::messaging::apply_revise_body(&conv, &target, body, false) {
+                Ok(Some((row, content))) => {
+                    info!("MESSAGE: revise from {}", hex::encode(&from[..4]));
+                    MessageEv::Edited { id: row.id, conversation: conv, content }.emit();
+                    if let Some(pack) = pack {
+                        crate::stickers::want(conv, pack);
+                    }
+                },
+                // Out-of-order: target not stored yet. Rare in 1:1
+                // same-epoch (the original precedes) — drop.
+                Ok(None) => debug!(
+                    "MESSAGE: revise for unknown target from {}",
+                    hex::encode(&from[..4])
+                ),
+                Err(e) => {
+                    warn!("MESSAGE: revise from {} rejected: {e}", hex::encode(&from[..4]))
+                },
+            }
+        },
+        Ok(AppPayload::Delete { target }) => {
+            // own=false plus an author check: a member may only delete
+            // messages IT sent, never another member's.
+            match Message::apply_delete(&conv, &target, false, Some(&author)) {
+                Some(row) => {
+                    info!("MESSAGE: delete from {}", hex::encode(&from[..4]));
+                    MessageEv::Deleted { id: row.id, conversation: conv }.emit();
+                },
+                None => debug!(
+                    "MESSAGE: delete for unknown target from {}",
+                    hex::encode(&from[..4])
+                ),
+            }
+        },
+        Ok(AppPayload::React { target, emoji, add }) => {
+            // Reactor is the MLS sender (`from`) — attributed to its
+            // own IPK, so this is already group-correct.
+            let ts = accepted_at_secs(accepted_at_ms);
+            if crate::data::reaction::Reaction::apply(
+                &conv, &target, &author, &emoji, add, ts,
+            ) {
+                crate::events::messaging::ReactionEv {
+                    conversation: conv,
+                    dispatch_id: target,
+                    reactor: author,
+                    emoji,
+                    add,
+                }
+                .emit();
+            }
+        },
+        Ok(AppPayload::System(event)) => {
+            use common::proto::mls_wire::SystemEvent;
+            use crate::db::messages::SYSTEM_ADDED;
+            use crate::db::messages::SYSTEM_LEFT;
+            use crate::db::messages::SYSTEM_REMOVED;
+            use crate::db::messages::SYSTEM_TITLED;
+
+            let ts = accepted_at_secs(accepted_at_ms);
+            let (code, target) = match &event {
+                SystemEvent::Added { who } => (SYSTEM_ADDED, hex::encode(who.0)),
+                SystemEvent::Left { who } => (SYSTEM_LEFT, hex::encode(who.0)),
+                SystemEvent::Removed { who } => (SYSTEM_REMOVED, hex::encode(who.0)),
+                SystemEvent::Titled { title } => (SYSTEM_TITLED, title.clone()),
+            };
+            // A rename has no Commit behind it, so the event itself is
+            // the change. Membership events only narrate — the Commit
+            // is what actually moved the roster, and syncing from the
+            // MLS group after merging it is the authoritative path.
+            if let SystemEvent::Titled { title } = &event {
+                // Only a group has a shared name. Renaming a direct
+                // chat from the wire would let a peer relabel a DM,
+                // and did whenever a group was mis-homed into one.
+                let is_group = Conversation::get(&conv).is_some_and(|c| {
+                    c.kind == crate::data::conversation::KIND_GROUP
+                });
+                if !is_group {
+                    warn!("GROUP: ignored a rename aimed at a direct chat");
+                } else if let Err(e) = Conversation::set_title(&conv, title) {
+                    warn!("GROUP: could not apply a title change: {e}");
+                }
+            }
+            // Someone joined after us, so they never heard the
+            // introduction we made on our own way in. Say it again,
+            // to them alone.
+            if let SystemEvent::Added { who } = &event
+                && who.0 != our_ipk
+            {
+                crate::messaging::introduce_ourselves_to(conv, who.0);
+            }
+            match Message::save_system(conv, author, &did, code, &target, ts, false) {
+                Ok(Some(row)) => MessageEv::Received {
+                    id: row.inner.id,
+                    conversation: conv,
+                    sender: author,
+                    content: target,
+                    timestamp: ts,
+                }
+                .emit(),
+                Ok(None) => debug!("GROUP: duplicate system event, already stored"),
+                Err(e) => warn!("GROUP: could not store a system event: {e}"),
+            }
+        },
+        Ok(AppPayload::Profile { name }) => {
+            // Their claim about themselves, kept apart from the address
+            // book so it can never overwrite a name we chose. Stored,
+            // never shown as a message — nobody said anything.
+            if let Err(e) = crate::data::peer_name::put(&author, &name) {
+                warn!("PROFILE: could not record a self-asserted name: {e}");
+            }
+        },
+        Ok(AppPayload::PairAck) => {
+            // Proof-of-pair — its whole job was the mark_paired above.
+            info!("PAIR: confirmed by {}", hex::encode(&from[..4]));
+        },
+        Ok(AppPayload::P2p { candidates, relay, token, disco_key }) => {
+            // Candidate offer for a direct connection — hand to the
+            // P2P layer (routed to the waiting session), never stored.
+            info!(
+                "P2P[{}]: received offer — {} cands",
+                hex::encode(&from[..4]),
+                candidates.len()
+            );
+            crate::p2p::deliver_offer(from, candidates, relay, token, disco_key);
+        },
+        Ok(AppPayload::FileWant { file_id }) => {
+            // Reverse-wake control message — routed, never stored. The push
+            // wake already revived us; bring the P2P listener up so the
+            // receiver's retry-dial can land (they drive the connect).
+            info!("P2P: FileWant received from {}", hex::encode(&from[..4]));
+            crate::transfer::on_file_want(from, file_id);
+        },
+        Ok(AppPayload::FileHave { file_id }) => {
+            // A member finished pulling an attachment and can now serve
+            // it; record them as a source. Control-only, never stored.
+            crate::transfer::on_file_have(from, file_id);
+        },
+        Ok(AppPayload::FileBlob { file_id, host, blob_id, key, expires_at }) => {
+            // The sender parked an encrypted copy on its relay; keep the
+            // locator for when no holder is reachable. Control-only.
+            use crate::transfer::store::BlobLocator;
+            let loc = BlobLocator { host, blob_id, key, expires_at };
+            crate::transfer::blob::on_file_blob(from, file_id, loc);
+        },
+        Ok(AppPayload::PackWant { pack }) => {
+            // A member can't draw one of a pack's stickers; offer it if
+            // we hold it. Control-only, never stored.
+            crate::stickers::on_pack_want(conv, pack);
+        },
+        Ok(AppPayload::PackOffer { pack, file_id, size }) => {
+            // Someone serves a pack we asked for; pull and install it.
+            crate::stickers::on_pack_offer(author, conv, pack, file_id, size);
+        },
+        Ok(AppPayload::Vote { poll, choices }) => {
+            // Voter is the MLS sender, as for a reaction; the poll
+            // itself decides what it will take. The tally is read off
+            // the table, which rings `on_db_changed`.
+            let ts = accepted_at_secs(accepted_at_ms);
+            if let Err(e) =
+                crate::data::poll::apply_vote(&conv, &poll, &author, &choices, ts)
+            {
+                debug!("POLL: vote from {} refused: {e}", hex::encode(&from[..4]));
+            }
+        },
+        Ok(AppPayload::PollClose { poll }) => {
+            // Only the poll's author may close it; `close` checks.
+            let ts = accepted_at_secs(accepted_at_ms);
+            match crate::data::poll::close(&conv, &poll, Some(&author), ts) {
+                Ok(true) => info!("POLL: closed by {}", hex::encode(&from[..4])),
+                Ok(false) => debug!(
+                    "POLL: close from {} for a poll not theirs or not open",
+                    hex::encode(&from[..4])
+                ),
+                Err(e) => warn!("POLL: could not apply a close: {e}"),
+            }
+        },
+        Ok(AppPayload::DeliveryToken { token }) => {
+            // Their permission to reach them sealed. Control-only.
+            if let Err(e) = crate::sealed::accept(&from, &token) {
+                debug!("SEALED: token from {} refused: {e}", hex::encode(&from[..4]));
+            }
+        },
+        Ok(AppPayload::MailboxGrant { seed, tokens }) => {
+            // Their mailbox seed and the tokens to reach it sealed.
+            if let Err(e) = crate::mailbox::accept(&from, seed, tokens) {
+                debug!("SEALED: grant from {} refused: {e}", hex::encode(&from[..4]));
+            }
+        },
+        Ok(AppPayload::Extension { tag: EXT_RECOVERY_SHARE, body }) => {
+            // A share of their key to keep for them. Control-only.
+            if let Err(e) = crate::social_recovery::on_share(&author, &body.0) {
+                warn!("RECOVERY: share from {} refused: {e}", hex::encode(&from[..4]));
+            }
+        },
+        Ok(AppPayload::Extension { tag: EXT_RECOVERY_REVOKE, body }) => {
+            if let Err(e) = crate::social_recovery::on_revoke(&author, &body.0) {
+                debug!("RECOVERY: revoke from {} refused: {e}", hex::encode(&from[..4]));
+            }
+        },
+        Ok(AppPayload::Extension { tag, .. }) => {
+            // Listed in KNOWN_EXTENSIONS yet given no arm above: our bug, so
+            // nothing to keep for a later build.
+            warn!("MESSAGE: extension {tag} is known but unhandled");
+        },
+        // A variant from past the last ordinal this build knows, or garbage.
+        // Either way a later build may read it, so it is kept within the caps
+        // `data::unsupported` puts on size, count per sender and age.
+        Err(e) => {
+            warn!(
+                "MESSAGE: undecodable AppPayload from {}: {e}",
+                hex::encode(&from[..4])
+            );
+            keep_unreadable(conv, from, author, did, accepted_at_ms, plaintext, false)?;
+        },
+    }
+    Ok(())
+}
+
+/// Keep a payload this build can't read (see [`crate::data::unsupported`]).
+/// Content also leaves a placeholder in the chat, receipted like any message
+/// — it did arrive, however it ends up drawn.
+pub(crate) fn keep_unreadable(
+    conv: [u8; 16], from: [u8; 32], author: [u8; 32], did: [u8; 16], accepted_at_ms: u64,
+    plaintext: &[u8], content: bool,
+) -> Result<()> {
+    use crate::data::unsupported::Unsupported;
+    use crate::db::messages::SYSTEM_UNSUPPORTED;
+
+    let plaintext = plaintext.to_vec();
+    let kept = crate::data::unsupported::keep(&Unsupported {
+        conversation: conv,
+        from,
+        author,
+        dispatch_id: did,
+        accepted_at_ms,
+        plaintext,
+    })?;
+    if !kept {
+        debug!("MESSAGE: {} is past the unreadable caps, not kept", hex::encode(&from[..4]));
+    }
+    if !content {
+        return Ok(());
+    }
+    let ts = accepted_at_secs(accepted_at_ms);
+    let saved = Message::save_system(conv, author, &did, SYSTEM_UNSUPPORTED, "", ts, false)?;
+    if let Some(row) = saved {
+        MessageEv::Received {
+            id: row.inner.id,
+            conversation: conv,
+            sender: author,
+            content: String::new(),
+            timestamp: ts,
+        }
+        .emit();
+        crate::RUNTIME.spawn(async move {
+            let _ = crate::messaging::send_receipt(conv, ReceiptKind::Delivered, did).await;
+        });
+    }
+    Ok(())
+}
+
+/// Re-read every payload kept by an earlier build. Run once at startup: what
+/// this build now understands is applied as if it had just arrived, its
+/// placeholder making way for the real row; the rest waits for the next build.
+pub(crate) async fn reparse_unsupported() {
+    use crate::data::unsupported;
+    use crate::db::messages::SYSTEM_UNSUPPORTED;
+
+    let Some(our_ipk) = crate::data::identity::Identity::get().map(|i| i.ipk()) else { return };
+    for u in unsupported::untried() {
+        let readable = AppPayload::deser(&u.plaintext).is_ok_and(|p| unsupported::readable(&p));
+        if !readable {
+            unsupported::tried(&u.conversation, &u.dispatch_id);
+            continue;
+        }
+        let placeholder = Message::get_by_dispatch(&u.conversation, &u.dispatch_id)
+            .is_some_and(|m| m.inner.system == SYSTEM_UNSUPPORTED);
+        if placeholder {
+            Message::hard_delete(&u.conversation, &u.dispatch_id);
+        }
+        unsupported::remove(&u.conversation, &u.dispatch_id);
+        let (conv, from, author, did) = (u.conversation, u.from, u.author, u.dispatch_id);
+        if let Err(e) =
+            apply_payload(our_ipk, conv, from, author, did, u.accepted_at_ms, &u.plaintext).await
+        {
+            warn!("MESSAGE: a kept payload failed on re-read: {e}");
+        }
+    }
+}
+
 /// Handle a relay-issued `SRelayPacket::AckAuthRequest`.
 ///
 /// The relay asks us (the client) to sign a `QueueFetchAck`
@@ -1203,9 +1532,15 @@ async fn process_deliver(
 /// request is only ever legitimate as the reply to our own `AckDrain`
 /// (`relay/src/quic/handler/client/events/drain.rs`), so an unsolicited
 /// one has an empty `drained` and is refused.
+///
+/// **Mailboxes**: a `MailboxAckAuthRequest` names one of our blinded
+/// mailboxes (`crate::mailbox`), whose homes only accept a signature under
+/// that mailbox. `mailbox` is then `Some`, and we sign with its key instead,
+/// provided it is one we keep open.
 async fn handle_ack_auth_request(
-    tx: &mut SendStream, ipk: VerifyingKey, requester_relay_id: NodeId,
-    delivered_ids: Vec<[u8; 16]>, suggested_timestamp: u64, drained: &HashSet<[u8; 16]>,
+    tx: &mut SendStream, ipk: VerifyingKey, mailbox: Option<[u8; 32]>,
+    requester_relay_id: NodeId, delivered_ids: Vec<[u8; 16]>, suggested_timestamp: u64,
+    drained: &HashSet<[u8; 16]>,
 ) -> Result<()> {
     if delivered_ids.len() > MAX_FETCH_QUEUE_ACK_IDS {
         warn!(
@@ -1219,14 +1554,24 @@ async fn handle_ack_auth_request(
         warn!("ACK_AUTH: refusing to sign undelivered id {}", hex::encode(&stray[..4]));
         return Ok(());
     }
-    let self_ipk = ipk.to_bytes();
+    let signer = mailbox.unwrap_or_else(|| ipk.to_bytes());
     let transcript = queue_fetch_ack_signing_input(
-        &self_ipk,
+        &signer,
         &requester_relay_id,
         &delivered_ids,
         suggested_timestamp,
     );
-    let sig = IdentitySigner::sign(&transcript)?;
+    let sig = match mailbox {
+        None => IdentitySigner::sign(&transcript)?,
+        Some(mailbox) => match crate::mailbox::key_for(&mailbox)? {
+            Some(key) => key.sign(&transcript),
+            None => {
+                let mailbox = hex::encode(&mailbox[..4]);
+                warn!("ACK_AUTH: refusing to sign for mailbox {mailbox}, not one we keep open");
+                return Ok(());
+            },
+        },
+    };
     CRelayPacket::AckAuth {
         sig:       Bytes::from(sig.to_bytes()),
         timestamp: suggested_timestamp,
@@ -1279,7 +1624,8 @@ async fn poll_welcomes_once(client: Arc<RelayDhtClient>) -> Result<()> {
 /// [`crate::messaging::MlsContext`] (fresh DB handles + the connection's
 /// dialer, mirroring `poll_welcomes_once`) and re-drives every still-
 /// pending first-send whose contact has no group yet — the ones deferred
-/// earlier because the peer had no published KeyPackage.
+/// earlier because the peer had no published KeyPackage. Then sends any
+/// successor-group Welcome that couldn't go out when the successor was founded.
 async fn retry_pending_sends_once(client: Arc<RelayDhtClient>) {
     let provider = crate::mls::PromtuzMlsProvider::shared();
     let stash_db = stash_db_handle();
@@ -1292,6 +1638,7 @@ async fn retry_pending_sends_once(client: Arc<RelayDhtClient>) {
         dht:      client.as_ref(),
     };
     crate::messaging::retry_pending_sends(&ctx).await;
+    crate::groups::resume_successors(&ctx).await;
 }
 
 /// KP-rotation scheduler loop — production wiring.
@@ -1321,6 +1668,15 @@ async fn run_scheduler_loop(client: Arc<RelayDhtClient>, cancel: CancellationTok
     // Republish our KP to this relay on connect (idempotent) — fixes the case where
     // the relay lost our KP but our local stash is still full so `should_refill` never fires.
     crate::mls::scheduler::ensure_kp_published(&provider, &stash, &signing, client.as_ref()).await;
+    // With fresh KeyPackages out, whoever re-adds a restored device can fetch one.
+    // A rotated key is announced first, so the admins asked know it.
+    crate::RUNTIME.spawn({
+        let client = client.clone();
+        async move {
+            crate::succession::announce().await;
+            crate::rejoin::resume(client).await;
+        }
+    });
     run_scheduler_inner(
         &provider,
         &stash,
@@ -1464,7 +1820,7 @@ mod gate_tests {
     #[test]
     fn welcome_envelope_bypasses_contact_gate() {
         // A garbage / non-Welcome payload must stay gated (returns false).
-        assert!(!is_welcome_envelope(b"not an envelope"), "garbage must stay gated");
+        assert!(!admits_strangers(b"not an envelope"), "garbage must stay gated");
         // A real Welcome envelope must be recognized so it bypasses the gate.
         let env = WelcomeEnvelopeP {
             version:       0,
@@ -1477,7 +1833,7 @@ mod gate_tests {
             pairing:       None,
         };
         let bytes = MlsEnvelopeP::Welcome(env).ser().expect("ser");
-        assert!(is_welcome_envelope(&bytes), "a Welcome envelope must bypass the contact gate");
+        assert!(admits_strangers(&bytes), "a Welcome envelope must bypass the contact gate");
     }
 
     fn signed_deliver(sender: &SigningKey, to: &VerifyingKey, payload: &[u8]) -> DeliverP {
@@ -1519,6 +1875,32 @@ mod gate_tests {
         assert!(verify_dispatch_sig(&me, &forged).is_err());
     }
 
+    #[test]
+    fn a_dispatch_for_a_retired_key_is_addressed_to_it() {
+        let sender = SigningKey::from_bytes(&[0x11; 32]);
+        let me = SigningKey::from_bytes(&[0x22; 32]).verifying_key();
+        let retired = SigningKey::from_bytes(&[0x33; 32]).verifying_key();
+        let older = SigningKey::from_bytes(&[0x44; 32]).verifying_key();
+        let ours = || vec![older.to_bytes(), retired.to_bytes()];
+
+        let current = signed_deliver(&sender, &me, b"envelope");
+        let asked = std::cell::Cell::new(false);
+        let to = addressed_to_any(&me, &current, || {
+            asked.set(true);
+            Vec::new()
+        });
+        assert_eq!(to.unwrap(), me);
+        assert!(!asked.get(), "the current key needs no lookup");
+
+        let handed_over = signed_deliver(&sender, &retired, b"envelope");
+        assert_eq!(addressed_to_any(&me, &handed_over, ours).unwrap(), retired);
+        assert!(addressed_to_any(&me, &handed_over, Vec::new).is_err(), "not a key we held");
+
+        let mut tampered = handed_over.clone();
+        tampered.payload = b"other".to_vec().into();
+        assert!(addressed_to_any(&me, &tampered, ours).is_err());
+    }
+
     #[test]
     fn accepted_at_is_capped_at_the_local_clock() {
         let now = systime().as_secs();
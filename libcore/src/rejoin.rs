@@ -0,0 +1,313 @@
+//! Getting back into MLS groups after a restore. A backup carries chats,
+//! rosters and history but no group secrets, so a restored device holds
+//! conversations it can neither decrypt nor send in. [`after_restore`] marks
+//! each of them in [`crate::data::rejoin`]; once a relay is up and fresh
+//! KeyPackages are published, [`resume`] works through the list:
+//!
+//! - A direct chat simply founds a new pair group, as a send would; the peer's
+//!   Welcome handling repoints their side.
+//! - A group we run is founded again as a successor naming the old one
+//!   ([`crate::groups::refound`]), exactly like a suite upgrade.
+//! - Any other group needs its admin — the only member v1 lets add — so we
+//!   send them a signed [`RejoinRequestP`]. They swap our dead leaf for a
+//!   fresh KeyPackage and Welcome us in ([`crate::groups::readmit`]).
+//!
+//! An MLS external commit would let us rejoin without the admin online, but
+//! needs every group's GroupInfo published somewhere we can fetch it, which
+//! nothing does yet. Nothing sent while we were out is recoverable either way.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use common::proto::mls_wire::RejoinRequestP;
+use common::proto::mls_wire::rejoin_signing_input;
+use ed25519_dalek::Signature;
+use ed25519_dalek::VerifyingKey;
+use log::info;
+use log::warn;
+
+use crate::data::backup::SuiteRow;
+use crate::data::conversation::Conversation;
+use crate::data::conversation::KIND_GROUP;
+use crate::data::conversation::ROLE_ADMIN;
+use crate::data::identity::Identity;
+use crate::data::rejoin;
+use crate::data::rejoin::REJOIN_DONE;
+use crate::data::rejoin::REJOIN_FAILED;
+use crate::data::rejoin::REJOIN_REQUESTED;
+use crate::mls::MlsGroupHandle;
+use crate::mls::PromtuzMlsProvider;
+use crate::quic::relay_dht_client::RelayDhtClient;
+use crate::utils::systime;
+
+/// An unanswered request is sent again after this long: the admin may have
+/// been offline past its queue, or lost it.
+const REQUEST_RESEND_SECS: u64 = 24 * 60 * 60;
+
+/// How old a request may be when it reaches us. Generous, since it waits in
+/// our queue for as long as we are offline.
+const REQUEST_MAX_AGE_MS: u64 = 14 * 24 * 60 * 60 * 1000;
+
+/// How far ahead of our clock a request's timestamp may be.
+const REQUEST_MAX_SKEW_MS: u64 = 5 * 60 * 1000;
+
+/// Least time between two re-adds of one member to one group. Each swaps
+/// their leaf in a Commit every member processes, so a member sending fresh
+/// requests can't keep the group churning.
+const READMIT_MIN_GAP_MS: u64 = 60 * 60 * 1000;
+
+/// Mark every conversation bound to a group we hold no state for. Called once
+/// a backup has been imported, with the suites it recorded; returns how many
+/// need rejoining.
+pub fn after_restore(suites: &[SuiteRow]) -> Result<usize> {
+    after_restore_on(&PromtuzMlsProvider::shared(), suites)
+}
+
+fn after_restore_on(provider: &PromtuzMlsProvider, suites: &[SuiteRow]) -> Result<usize> {
+    let mut marked = 0;
+    for row in Conversation::list() {
+        let Some(gid) = row.mls_group_id.as_deref().and_then(|g| <[u8; 32]>::try_from(g).ok())
+        else {
+            continue;
+        };
+        if MlsGroupHandle::load(provider, &gid).map_err(|e| anyhow!("load group: {e}"))?.is_none()
+        {
+            let suite = suites.iter().find(|s| s.conversation_id == row.id).map(|s| s.suite);
+            rejoin::mark_needed(&row.id, suite)?;
+            marked += 1;
+        }
+    }
+    if marked > 0 {
+        info!("REJOIN: {marked} restored conversation(s) need their group back");
+    }
+    Ok(marked)
+}
+
+/// Work through every conversation still waiting to rejoin. Run on connect,
+/// after our KeyPackages are published so whoever re-adds us can fetch one.
+pub(crate) async fn resume(client: Arc<RelayDhtClient>) {
+    let asked_before = systime().as_secs().saturating_sub(REQUEST_RESEND_SECS);
+    for row in rejoin::open(asked_before) {
+        let id = row.conversation;
+        match step(&client, &id).await {
+            Ok(state) => rejoin::set(&id, state, ""),
+            Err(e) => {
+                warn!("REJOIN: {} failed: {e}", hex::encode(&id[..4]));
+                rejoin::set(&id, REJOIN_FAILED, &e.to_string());
+            },
+        }
+    }
+}
+
+/// Take one conversation as far as we can on our own, returning where it now
+/// stands.
+async fn step(client: &RelayDhtClient, conversation: &[u8; 16]) -> Result<u8> {
+    let row = Conversation::get(conversation).ok_or_else(|| anyhow!("conversation is gone"))?;
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+
+    if row.kind != KIND_GROUP {
+        let ipk_signer = crate::data::identity::secret_key_signing(&our_ipk)?;
+        let provider = PromtuzMlsProvider::shared();
+        let stash_db = crate::db::mls::stash_db_handle();
+        let stash = crate::mls::KeyPackageStash::new(stash_db.clone());
+        let buffer = crate::mls::EpochCatchupBuffer::new(stash_db);
+        let ctx = crate::messaging::MlsContext {
+            provider: &provider,
+            stash:    &stash,
+            buffer:   &buffer,
+            dht:      client,
+        };
+        crate::messaging::group_for_conversation(&ctx, conversation, &our_ipk, &ipk_signer)
+            .await?;
+        return Ok(REJOIN_DONE);
+    }
+
+    if Conversation::is_admin(conversation, &our_ipk) {
+        crate::groups::refound(*conversation).await?;
+        return Ok(REJOIN_DONE);
+    }
+    let group_id = Conversation::group_of(conversation)
+        .ok_or_else(|| anyhow!("conversation has no group to rejoin"))?;
+    let admin = Conversation::members(conversation)
+        .into_iter()
+        .find(|m| m.active && m.role == ROLE_ADMIN)
+        .map(|m| m.member_ipk)
+        .ok_or_else(|| anyhow!("nobody left who can add us back"))?;
+    crate::messaging::send_rejoin_request(admin, group_id).await?;
+    Ok(REJOIN_REQUESTED)
+}
+
+/// The suite `group` runs on, as a wire value, if we hold its state.
+pub(crate) fn suite_of(provider: &PromtuzMlsProvider, group: &[u8; 32]) -> Option<u16> {
+    let group = MlsGroupHandle::load(provider, group).ok().flatten()?;
+    Some(group.ciphersuite() as u16)
+}
+
+/// A member asks back into one of our groups. Checked here; the re-add runs in
+/// the background since it needs their KeyPackage from the network.
+pub(crate) fn on_request(sender_ipk: [u8; 32], req: RejoinRequestP) -> Result<()> {
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let now = systime().as_millis() as u64;
+    let conversation = check_request(our_ipk, sender_ipk, &req, now)?;
+
+    crate::RUNTIME.spawn(async move {
+        match crate::groups::readmit(conversation, sender_ipk).await {
+            Ok(()) => info!("REJOIN: let {} back in", hex::encode(&sender_ipk[..4])),
+            Err(e) => warn!("REJOIN: re-adding {} failed: {e}", hex::encode(&sender_ipk[..4])),
+        }
+    });
+    Ok(())
+}
+
+/// Everything [`on_request`] checks before acting, returning the conversation
+/// to let the requester back into. A request that passes is recorded, so the
+/// same one, or another inside [`READMIT_MIN_GAP_MS`], is refused after.
+fn check_request(
+    our_ipk: [u8; 32], sender_ipk: [u8; 32], req: &RejoinRequestP, now: u64,
+) -> Result<[u8; 16]> {
+    if req.sender_ipk.0 != sender_ipk {
+        bail!("rejoin sender_ipk mismatch");
+    }
+    if req.recipient_ipk.0 != our_ipk {
+        bail!("rejoin not addressed to us");
+    }
+    let vk = VerifyingKey::from_bytes(&sender_ipk).map_err(|e| anyhow!("requester ipk: {e}"))?;
+    let msg = rejoin_signing_input(&sender_ipk, &our_ipk, &req.group_id.0, req.timestamp);
+    vk.verify_strict(&msg, &Signature::from_bytes(&req.sig.0))
+        .map_err(|_| anyhow!("rejoin signature invalid"))?;
+
+    if !fresh(req.timestamp, now) {
+        bail!("rejoin request outside the accepted window");
+    }
+    let conversation = Conversation::for_group(&req.group_id.0)
+        .ok_or_else(|| anyhow!("rejoin for a group we don't hold"))?;
+    let group = req.group_id.0;
+    if !rejoin::admit_request(&sender_ipk, &group, req.timestamp, now, READMIT_MIN_GAP_MS)? {
+        bail!("rejoin request already handled, or the last one was too recent");
+    }
+    Ok(conversation)
+}
+
+fn fresh(timestamp: u64, now: u64) -> bool {
+    timestamp <= now + REQUEST_MAX_SKEW_MS && now.saturating_sub(timestamp) <= REQUEST_MAX_AGE_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::types::bytes::Bytes;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+    use parking_lot::Mutex;
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::db::mls::apply_mls_migrations;
+    use crate::mls::PROMTUZ_CIPHERSUITE;
+    use crate::mls::group::CLASSICAL_CIPHERSUITE;
+
+    /// Point the conversation store at a scratch dir before anything opens it.
+    fn scratch_data_dir() {
+        let dir = std::env::temp_dir().join("promtuz-rejoin-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+    }
+
+    fn request(from: &SigningKey, to: [u8; 32], group: [u8; 32], ts: u64) -> RejoinRequestP {
+        let sender = from.verifying_key().to_bytes();
+        let sig = from.sign(&rejoin_signing_input(&sender, &to, &group, ts)).to_bytes();
+        RejoinRequestP {
+            sender_ipk:    Bytes(sender),
+            recipient_ipk: Bytes(to),
+            group_id:      Bytes(group),
+            timestamp:     ts,
+            sig:           Bytes(sig),
+        }
+    }
+
+    #[test]
+    fn stale_and_future_requests_are_refused() {
+        let now = 100 * REQUEST_MAX_AGE_MS;
+        assert!(fresh(now, now));
+        assert!(fresh(now - REQUEST_MAX_AGE_MS, now));
+        assert!(!fresh(now - REQUEST_MAX_AGE_MS - 1, now));
+        assert!(!fresh(now + REQUEST_MAX_SKEW_MS + 1, now));
+    }
+
+    /// A request is acted on once: not replayed, not re-sent fresh inside the
+    /// gap, and only when it is signed by its sender, to us, for a group we
+    /// hold.
+    #[test]
+    fn requests_are_checked_and_rate_limited() {
+        scratch_data_dir();
+        let us = SigningKey::from_bytes(&[0x91; 32]).verifying_key().to_bytes();
+        let bob = SigningKey::from_bytes(&[0x92; 32]);
+        let bob_ipk = bob.verifying_key().to_bytes();
+        let group: [u8; 32] = rand::random();
+        let conversation = Conversation::join_group(&us, &[us, bob_ipk]).unwrap();
+        Conversation::bind_group(&conversation, &group).unwrap();
+        let now = 100 * REQUEST_MAX_AGE_MS;
+
+        let forged = RejoinRequestP { timestamp: now - 1, ..request(&bob, us, group, now) };
+        assert!(check_request(us, bob_ipk, &forged, now).is_err(), "signature");
+        let elsewhere = request(&bob, [0x93; 32], group, now);
+        assert!(check_request(us, bob_ipk, &elsewhere, now).is_err(), "not to us");
+        let unheld = request(&bob, us, rand::random(), now);
+        assert!(check_request(us, bob_ipk, &unheld, now).is_err(), "not our group");
+        assert!(check_request(us, [0x94; 32], &request(&bob, us, group, now), now).is_err());
+
+        let first = request(&bob, us, group, now);
+        assert_eq!(check_request(us, bob_ipk, &first, now).unwrap(), conversation);
+        assert!(check_request(us, bob_ipk, &first, now + 1).is_err(), "replay");
+        let again = request(&bob, us, group, now + 1);
+        assert!(check_request(us, bob_ipk, &again, now + 1).is_err(), "inside the gap");
+        let later = now + READMIT_MIN_GAP_MS;
+        let after = request(&bob, us, group, later);
+        assert_eq!(check_request(us, bob_ipk, &after, later).unwrap(), conversation);
+    }
+
+    /// Only a conversation whose group we hold no state for is marked, with
+    /// the suite the backup recorded for it.
+    #[test]
+    fn a_restore_marks_the_groups_we_lack_with_their_recorded_suite() {
+        scratch_data_dir();
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_mls_migrations(&mut conn);
+        let provider = PromtuzMlsProvider::new(Arc::new(Mutex::new(conn)));
+
+        let us = SigningKey::from_bytes(&[0x95; 32]).verifying_key().to_bytes();
+        let (held, lost): ([u8; 32], [u8; 32]) = (rand::random(), rand::random());
+        let (leaf_kp, _) = crate::messaging::build_self_credential(&us).unwrap();
+        leaf_kp.store(provider.storage()).unwrap();
+        MlsGroupHandle::create(
+            &provider,
+            &leaf_kp,
+            &us,
+            leaf_kp.public(),
+            &held,
+            None,
+            PROMTUZ_CIPHERSUITE,
+        )
+        .unwrap();
+        assert_eq!(suite_of(&provider, &held), Some(PROMTUZ_CIPHERSUITE as u16));
+
+        let kept = Conversation::join_group(&us, &[us, [0x96; 32]]).unwrap();
+        let restored = Conversation::join_group(&us, &[us, [0x97; 32]]).unwrap();
+        Conversation::bind_group(&kept, &held).unwrap();
+        Conversation::bind_group(&restored, &lost).unwrap();
+        let classical = CLASSICAL_CIPHERSUITE as u16;
+        let suites = [
+            SuiteRow { conversation_id: kept, suite: classical },
+            SuiteRow { conversation_id: restored, suite: classical },
+        ];
+
+        assert!(after_restore_on(&provider, &suites).unwrap() >= 1);
+        assert_eq!(rejoin::get(&kept), None, "we still hold that group");
+        let row = rejoin::get(&restored).unwrap();
+        assert_eq!(row.state, crate::data::rejoin::REJOIN_PENDING);
+        assert_eq!(row.suite, Some(classical));
+    }
+}
@@ -0,0 +1,663 @@
+//! Sealed-sender dispatch. A relay that queues a message for us would
+//! otherwise learn, and keep on disk, who sent it: `DispatchP::from` is
+//! cleartext and signed. Sealing moves the sender inside an encryption only
+//! the recipient opens, and hands the relay something else to authorize the
+//! dispatch with — a [`DeliveryToken`] the recipient issued to that contact.
+//!
+//! Tokens are granted lazily over the MLS channel to paired contacts that
+//! message us, and re-granted when the one they hold nears expiry. While our
+//! relay opens mailboxes they come with our mailbox seed (`crate::mailbox`),
+//! so a sealed dispatch goes to our current blinded mailbox, not the IPK, and
+//! only the one-shot outer signature and the token are checked against that
+//! mailbox. Every grant also carries a token for the IPK, which is all a build
+//! older than mailboxes can read (each drops the payload it cannot decode)
+//! and what mail falls back to when we were granted no mailboxes. Tokens
+//! rotate with the grant, once an epoch, so what a relay can link is one
+//! contact's sends within an epoch. Forgetting a contact revokes every token
+//! we granted them: a signed [`TokenRevocation`] per key they verify under,
+//! re-sent on each connect until the tokens lapse.
+//!
+//! A relay that cannot route a sealed dispatch — it has no DHT, or the
+//! recipient's homes predate tokens — says so, and that recipient's
+//! dispatches go identified for [`UNSEALED_BACKOFF_MS`].
+//!
+//! The ingress relay still sees the authenticated session that sent the
+//! dispatch. What sealing hides is the sender from every home that queues it
+//! and from the rows it queues.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use common::crypto::mailbox::MAILBOX_EPOCH_MS;
+use common::crypto::mailbox::MailboxKey;
+use common::crypto::mailbox::epoch_of;
+use common::crypto::mailbox::seed_for;
+use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::DeliverP;
+use common::proto::client_rel::DispatchAckP;
+use common::proto::client_rel::DeliveryToken;
+use common::proto::client_rel::DispatchP;
+use common::proto::client_rel::dispatch_sig_message;
+use common::proto::dht_p2p::MAX_REVOKED_TOKENS;
+use common::proto::dht_p2p::TokenRevocation;
+use common::proto::dht_p2p::token_revocation_signing_input;
+use common::proto::mls_wire::AppPayload;
+use common::proto::mls_wire::MlsEnvelopeP;
+use common::proto::mls_wire::SEALED_SENDER_SEAL_INFO;
+use common::proto::mls_wire::SealedInnerP;
+use common::proto::mls_wire::SealedSenderP;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::types::bytes::ByteVec;
+use common::types::bytes::Bytes;
+use ed25519_dalek::Signer;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::ed25519::signature::rand_core::OsRng;
+use ed25519_dalek::ed25519::signature::rand_core::RngCore;
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rusqlite::params;
+
+use crate::data::contact::Contact;
+use crate::data::conversation::Conversation;
+use crate::data::identity::Identity;
+use crate::data::identity::IdentitySigner;
+use crate::db::peers::CONTACTS_DB;
+use crate::delivery::LastOutcome;
+use crate::delivery::outcome_for_ack;
+use crate::state::RELAY;
+use crate::utils::systime;
+
+/// Tokens are minted to expire at the end of the epoch after the current one,
+/// and re-granted once one epoch or less remains, so a contact in touch at
+/// least once an epoch never holds a lapsed token. One token per mailbox, so
+/// the mailbox epoch.
+const TOKEN_EPOCH_MS: u64 = MAILBOX_EPOCH_MS;
+
+/// A held token this close to expiry is not attached: it could lapse between
+/// a relay accepting the dispatch and a home re-checking it.
+const TOKEN_EXPIRY_MARGIN_MS: u64 = 3_600_000;
+
+/// How long a recipient's dispatches go identified after a relay could not
+/// route one sealed to them.
+const UNSEALED_BACKOFF_MS: u64 = 3_600_000;
+
+/// Recipients in that backoff, and when it ends. In memory: after a restart
+/// the first sealed attempt finds out again.
+static UNSEALED_UNTIL: Lazy<Mutex<HashMap<[u8; 32], u64>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Drop the backoffs; the account they were for is gone.
+pub(crate) fn forget_all() {
+    UNSEALED_UNTIL.lock().clear();
+}
+
+fn now_ms() -> u64 {
+    systime().as_millis() as u64
+}
+
+/// Expiry for a grant made at `now_ms`: that of the next epoch's mailbox
+/// token. Under `MAX_DELIVERY_TOKEN_LIFETIME_MS` by construction.
+fn grant_expiry(now_ms: u64) -> u64 {
+    crate::mailbox::token_expiry(epoch_of(now_ms) + 1)
+}
+
+/// Give `peer` a fresh token if the last one we granted them expires within an
+/// epoch, or there is none. Paired contacts only, and only once their chat
+/// exists. The grant is recorded before the send; the send is outboxed, so a
+/// token issued while offline still arrives.
+pub(crate) fn grant_if_due(peer: [u8; 32]) {
+    if !Contact::is_paired(&peer) {
+        return;
+    }
+    let now = now_ms();
+    let granted: Option<u64> = CONTACTS_DB
+        .lock()
+        .query_row(
+            "SELECT expires_at FROM delivery_grants WHERE peer = ?1",
+            [peer.as_slice()],
+            |r| r.get(0),
+        )
+        .ok();
+    if granted.is_some_and(|expires| expires.saturating_sub(now) > TOKEN_EPOCH_MS) {
+        return;
+    }
+    if let Err(e) = grant(peer, now) {
+        debug!("SEALED: could not grant {} a token: {e}", hex::encode(&peer[..4]));
+    }
+}
+
+/// Grant `peer` an IPK token and, if our relay will drain them, our
+/// mailboxes: a relay without a DHT never opens a mailbox, so mail addressed
+/// to one would sit at its homes until it expired.
+fn grant(peer: [u8; 32], now: u64) -> Result<()> {
+    let conversation = Conversation::for_peer(&peer)?;
+    let identity = identity_key()?;
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    let ipk_token = DeliveryToken::issue(&identity, id, grant_expiry(now));
+    let mailboxes = relay_routes_sealed().then(|| crate::mailbox::grant_payload(&identity, now));
+
+    let mut conn = CONTACTS_DB.lock();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO delivery_grants (peer, expires_at) VALUES (?1, ?2) \
+         ON CONFLICT(peer) DO UPDATE SET expires_at = excluded.expires_at",
+        params![peer.as_slice(), grant_expiry(now)],
+    )?;
+    let record = |token: &DeliveryToken, epoch: u64, on_ipk: bool| {
+        tx.execute(
+            "INSERT OR IGNORE INTO issued_tokens (id, peer, epoch, expires_at, on_ipk) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![token.id.0.as_slice(), peer.as_slice(), epoch, token.expires_at_ms, on_ipk],
+        )
+    };
+    record(&ipk_token, epoch_of(now), true)?;
+    if let Some(AppPayload::MailboxGrant { tokens, .. }) = &mailboxes {
+        for (epoch, token) in (epoch_of(now)..).zip(tokens) {
+            record(token, epoch, false)?;
+        }
+    }
+    tx.commit()?;
+    drop(conn);
+
+    let payloads = mailboxes.into_iter().chain([AppPayload::DeliveryToken { token: ipk_token }]);
+    crate::RUNTIME.spawn(async move {
+        for payload in payloads {
+            if let Err(e) = crate::messaging::send_control_to(conversation, payload, peer).await {
+                debug!("SEALED: token grant to {} not sent: {e}", hex::encode(&peer[..4]));
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Whether our relay can take a sealed dispatch and open our mailboxes: it
+/// has a DHT and takes dispatches whole.
+fn relay_routes_sealed() -> bool {
+    crate::messaging::relay_takes_whole_dispatches()
+        && RELAY.read().as_ref().is_some_and(|r| r.home_node_id.is_some())
+}
+
+/// Keep the token `peer` issued us, if it is theirs and live. Replaces any
+/// held before.
+pub(crate) fn accept(peer: &[u8; 32], token: &DeliveryToken) -> Result<()> {
+    if !token.verify(peer, now_ms()) {
+        return Err(anyhow!("token not issued by {} or not live", hex::encode(&peer[..4])));
+    }
+    let bytes = token.ser().map_err(|e| anyhow!("encode token: {e}"))?;
+    CONTACTS_DB.lock().execute(
+        "INSERT INTO delivery_tokens (peer, token) VALUES (?1, ?2) \
+         ON CONFLICT(peer) DO UPDATE SET token = excluded.token",
+        params![peer.as_slice(), bytes],
+    )?;
+    Ok(())
+}
+
+/// Drop the token `peer` issued us — a relay refused it, so later dispatches
+/// go identified until they grant another.
+pub(crate) fn forget_token(peer: &[u8; 32]) {
+    CONTACTS_DB
+        .lock()
+        .execute("DELETE FROM delivery_tokens WHERE peer = ?1", [peer.as_slice()])
+        .ok();
+}
+
+/// Act on a relay's answer to a dispatch for `to`, `sealed` or not, and
+/// return its durability verdict. A refused token is dropped, with the
+/// mailbox grant it may have come from, so the retry goes identified. A
+/// sealed dispatch nobody could route, or one whose signature failed where the
+/// token went unread, backs `to` off sealing; either way the retry is worth
+/// making, so neither is terminal.
+pub(crate) fn note_ack(to: &[u8; 32], ack: &DispatchAckP, sealed: bool) -> LastOutcome {
+    match ack {
+        DispatchAckP::TokenRejected => {
+            debug!("SEALED: relay refused our token for {}", hex::encode(&to[..4]));
+            forget_token(to);
+            crate::mailbox::forget(to);
+        },
+        DispatchAckP::SealedUnroutable | DispatchAckP::InvalidSig if sealed => {
+            debug!("SEALED: {} unroutable sealed ({ack:?}); backing off", hex::encode(&to[..4]));
+            UNSEALED_UNTIL.lock().insert(*to, now_ms() + UNSEALED_BACKOFF_MS);
+            return LastOutcome::Reachable;
+        },
+        _ => {},
+    }
+    outcome_for_ack(ack)
+}
+
+fn backed_off(to: &[u8; 32], now: u64) -> bool {
+    let mut until = UNSEALED_UNTIL.lock();
+    until.retain(|_, end| *end > now);
+    until.contains_key(to)
+}
+
+/// Drop every token held from or granted to `peer` (forget-contact cascade),
+/// and revoke the ones we granted them.
+pub(crate) fn forget(peer: &[u8; 32]) {
+    let conn = CONTACTS_DB.lock();
+    conn.execute("DELETE FROM delivery_tokens WHERE peer = ?1", [peer.as_slice()]).ok();
+    conn.execute("DELETE FROM delivery_grants WHERE peer = ?1", [peer.as_slice()]).ok();
+    conn.execute("UPDATE issued_tokens SET revoked = 1 WHERE peer = ?1", [peer.as_slice()]).ok();
+    drop(conn);
+    crate::mailbox::forget(peer);
+    let Some(conn) = RELAY.read().as_ref().and_then(|r| r.connection.clone()) else {
+        return;
+    };
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = revoke_on(&conn).await {
+            debug!("SEALED: revocations not sent: {e}");
+        }
+    });
+}
+
+/// Send every live revocation to the relay on `conn`, one signed list per
+/// mailbox, for its homes. Re-run on each connect: homes change, and a list
+/// only has to outlast the tokens it names. Expired rows are dropped first.
+pub(crate) async fn revoke_on(conn: &quinn::Connection) -> Result<()> {
+    let now = now_ms();
+    let revocations = revocations_in(&CONTACTS_DB.lock(), &identity_key()?, now)?;
+    for revocation in revocations {
+        let bytes = CRelayPacket::RevokeTokens(revocation)
+            .pack()
+            .map_err(|e| anyhow!("pack revoke_tokens: {e}"))?;
+        if let Ok((mut tx, _rx)) = conn.open_bi().await {
+            let _ = tx.write_all(&bytes).await;
+            let _ = tx.finish();
+        }
+    }
+    Ok(())
+}
+
+fn revocations_in(
+    conn: &rusqlite::Connection, identity: &SigningKey, now: u64,
+) -> Result<Vec<TokenRevocation>> {
+    conn.execute("DELETE FROM issued_tokens WHERE expires_at <= ?1", [now])?;
+    // Grouped by the key the tokens verify under: the IPK, or an epoch's
+    // mailbox. `None` sorts first.
+    let mut by_key: Vec<(Option<u64>, Vec<Bytes<16>>, u64)> = Vec::new();
+    let mut stmt = conn.prepare(
+        "SELECT CASE WHEN on_ipk THEN NULL ELSE epoch END AS mailbox, id, expires_at \
+         FROM issued_tokens WHERE revoked = 1 ORDER BY mailbox, expires_at DESC",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok((r.get::<_, Option<u64>>(0)?, r.get::<_, [u8; 16]>(1)?, r.get::<_, u64>(2)?))
+    })?;
+    for (mailbox, id, expires_at) in rows.filter_map(|r| r.ok()) {
+        match by_key.last_mut() {
+            Some((last, ids, _)) if *last == mailbox => {
+                if ids.len() < MAX_REVOKED_TOKENS {
+                    ids.push(Bytes(id));
+                }
+            },
+            _ => by_key.push((mailbox, vec![Bytes(id)], expires_at)),
+        }
+    }
+    let seed = seed_for(identity);
+    Ok(by_key
+        .into_iter()
+        .map(|(mailbox, ids, expires_at_ms)| {
+            let (user, sig) = match mailbox {
+                Some(epoch) => {
+                    let key = MailboxKey::derive(identity, &seed, epoch);
+                    let id = key.id();
+                    (id, key.sign(&token_revocation_signing_input(&id, &ids, expires_at_ms, now)))
+                },
+                None => {
+                    let ipk = identity.verifying_key().to_bytes();
+                    let input = token_revocation_signing_input(&ipk, &ids, expires_at_ms, now);
+                    (ipk, identity.sign(&input))
+                },
+            };
+            TokenRevocation {
+                user_ipk: Bytes(user),
+                ids,
+                expires_at_ms,
+                timestamp: now,
+                user_sig: Bytes(sig.to_bytes()),
+            }
+        })
+        .collect())
+}
+
+fn identity_key() -> Result<SigningKey> {
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    crate::data::identity::secret_key_signing(&our_ipk)
+}
+
+fn held_token(peer: &[u8; 32], now: u64) -> Option<DeliveryToken> {
+    let bytes: Vec<u8> = CONTACTS_DB
+        .lock()
+        .query_row("SELECT token FROM delivery_tokens WHERE peer = ?1", [peer.as_slice()], |r| {
+            r.get(0)
+        })
+        .ok()?;
+    DeliveryToken::deser(&bytes)
+        .ok()
+        .filter(|t| t.expires_at_ms > now.saturating_add(TOKEN_EXPIRY_MARGIN_MS))
+}
+
+/// Seal an identified `DispatchP` if we can: a live token from `to`, `to`'s
+/// push key, a relay with a DHT (the only path a sealed dispatch may take)
+/// that takes dispatches whole, and no recent sign that `to`'s homes
+/// cannot take one. Otherwise it goes out as it came in. A token for `to`'s
+/// current mailbox wins over one for the IPK, and readdresses the dispatch
+/// there.
+pub(crate) fn seal(dispatch: DispatchP) -> DispatchP {
+    if dispatch.token.is_some() {
+        return dispatch;
+    }
+    let now = now_ms();
+    if !relay_routes_sealed() || backed_off(&dispatch.to.0, now) {
+        return dispatch;
+    }
+    let Some((to, token)) = crate::mailbox::address(&dispatch.to.0, now)
+        .or_else(|| held_token(&dispatch.to.0, now).map(|token| (dispatch.to.0, token)))
+    else {
+        return dispatch;
+    };
+    let Some(push_pk) = crate::push::peer_push_key(&dispatch.to.0) else {
+        return dispatch;
+    };
+    seal_with(&dispatch, to, token, &push_pk).unwrap_or(dispatch)
+}
+
+/// [`seal`] over a framed dispatch, the shape the outbox stores, and whether
+/// it came out sealed (for [`note_ack`]). The outbox keeps the identified
+/// frame and each attempt seals afresh, so a token dropped after a refusal
+/// is not replayed on retry. A relay that doesn't take dispatches whole (see
+/// [`crate::messaging::relay_takes_whole_dispatches`]) gets the v6
+/// `Dispatch` frame instead: unsealed, and without the wake hint it has no
+/// room for.
+pub(crate) fn seal_frame(framed: &[u8]) -> (Vec<u8>, bool) {
+    let dispatch = match framed.get(4..).map(CRelayPacket::deser) {
+        Some(Ok(CRelayPacket::DispatchV7(dispatch) | CRelayPacket::Dispatch(dispatch))) => {
+            dispatch
+        },
+        _ => return (framed.to_vec(), false),
+    };
+    let (packed, sealed) = if crate::messaging::relay_takes_whole_dispatches() {
+        let dispatch = seal(dispatch);
+        let sealed = dispatch.token.is_some();
+        (CRelayPacket::DispatchV7(dispatch).pack(), sealed)
+    } else {
+        (CRelayPacket::Dispatch(dispatch).pack(), false)
+    };
+    match packed {
+        Ok(packed) => (packed, sealed),
+        Err(_) => (framed.to_vec(), false),
+    }
+}
+
+/// The sealing itself: the identified `from`/`sig`/`payload` go inside a
+/// [`SealedSenderP`] for `push_pk`, and a one-shot key signs the outer
+/// dispatch, addressed to `to`, so every relay check written for identified
+/// dispatches holds. The envelope stays bound to the recipient's IPK whatever
+/// `to` is: that is what they open it under.
+fn seal_with(
+    dispatch: &DispatchP, to: [u8; 32], token: DeliveryToken, push_pk: &[u8; 32],
+) -> Option<DispatchP> {
+    let inner = SealedInnerP {
+        from:    dispatch.from,
+        sig:     dispatch.sig,
+        payload: dispatch.payload.clone(),
+    };
+    let aad = [dispatch.to.0.as_slice(), dispatch.id.0.as_slice()].concat();
+    let (eph_pk, nonce, ciphertext) = crate::push::seal_to_push_key(
+        push_pk,
+        SEALED_SENDER_SEAL_INFO,
+        &aad,
+        &inner.ser().ok()?,
+    )?;
+    let payload = MlsEnvelopeP::Sealed(SealedSenderP {
+        eph_pk:     Bytes(eph_pk),
+        nonce:      Bytes(nonce),
+        ciphertext: ByteVec(ciphertext),
+    })
+    .ser()
+    .ok()?;
+
+    let mut seed = zeroize::Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(seed.as_mut());
+    let one_shot = SigningKey::from_bytes(&seed);
+    let from = one_shot.verifying_key().to_bytes();
+    let sig = one_shot.sign(&dispatch_sig_message(&to, &from, &dispatch.id.0, &payload));
+    Some(DispatchP {
+        to: Bytes(to),
+        from: Bytes(from),
+        payload: ByteVec(payload),
+        sig: Bytes(sig.to_bytes()),
+        token: Some(token),
+        ..dispatch.clone()
+    })
+}
+
+/// Unwrap a sealed delivery into the identified one inside it. Anything not
+/// sealed passes through untouched. The caller verifies the result exactly
+/// as it would an identified delivery: the inner signature is the sender's.
+pub(crate) fn open(msg: DeliverP, our_ipk: &[u8; 32]) -> Result<DeliverP> {
+    if !matches!(MlsEnvelopeP::deser(&msg.payload), Ok(MlsEnvelopeP::Sealed(_))) {
+        return Ok(msg);
+    }
+    let secret = IdentitySigner::seal_secret_for(our_ipk)?;
+    open_with(msg, our_ipk, &secret)
+}
+
+fn open_with(
+    msg: DeliverP, our_ipk: &[u8; 32], secret: &x25519_dalek::StaticSecret,
+) -> Result<DeliverP> {
+    let Ok(MlsEnvelopeP::Sealed(sealed)) = MlsEnvelopeP::deser(&msg.payload) else {
+        return Ok(msg);
+    };
+    let aad = [our_ipk.as_slice(), msg.id.0.as_slice()].concat();
+    let plain = crate::push::open_with_push_secret(
+        secret,
+        SEALED_SENDER_SEAL_INFO,
+        &sealed.eph_pk.0,
+        &sealed.nonce.0,
+        &aad,
+        &sealed.ciphertext,
+    )
+    .ok_or_else(|| anyhow!("sealed envelope does not open"))?;
+    let inner = SealedInnerP::deser(&plain).map_err(|e| anyhow!("sealed inner: {e}"))?;
+    if matches!(MlsEnvelopeP::deser(&inner.payload), Ok(MlsEnvelopeP::Sealed(_))) {
+        return Err(anyhow!("sealed envelope nested inside another"));
+    }
+    Ok(DeliverP { from: inner.from, sig: inner.sig, payload: inner.payload, ..msg })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identified(signer: &SigningKey, to: [u8; 32]) -> DispatchP {
+        let from = signer.verifying_key().to_bytes();
+        let id = [0x11; 16];
+        let payload = b"envelope".to_vec();
+        let sig = signer.sign(&dispatch_sig_message(&to, &from, &id, &payload));
+        DispatchP {
+            to:             Bytes(to),
+            from:           Bytes(from),
+            id:             Bytes(id),
+            payload:        ByteVec(payload),
+            sig:            Bytes(sig.to_bytes()),
+            accepted_at_ms: 0,
+            wake:           true,
+            wake_hint:      None,
+            token:          None,
+        }
+    }
+
+    /// What a relay sees names no identity key but the recipient's, what the
+    /// recipient opens is the identified dispatch byte for byte, and a copy
+    /// re-addressed by a relay does not open.
+    #[test]
+    fn a_sealed_dispatch_hides_the_sender_and_opens_only_as_addressed() {
+        let sender = SigningKey::from_bytes(&[0x21; 32]);
+        let recipient = SigningKey::from_bytes(&[0x22; 32]);
+        let to = recipient.verifying_key().to_bytes();
+        let secret = x25519_dalek::StaticSecret::from([0x23; 32]);
+        let push_pk = x25519_dalek::PublicKey::from(&secret).to_bytes();
+        let token = DeliveryToken::issue(&recipient, [0x24; 16], grant_expiry(now_ms()));
+
+        let plain = identified(&sender, to);
+        let sealed = seal_with(&plain, to, token.clone(), &push_pk).expect("seals");
+        assert_ne!(sealed.from, plain.from, "the outer sender is a one-shot key");
+        assert_eq!(sealed.token, Some(token.clone()));
+        assert!(token.verify(&to, now_ms()), "a relay can authorize it on `to` alone");
+        let outer = dispatch_sig_message(&to, &sealed.from.0, &sealed.id.0, &sealed.payload);
+        ed25519_dalek::VerifyingKey::from_bytes(&sealed.from.0)
+            .unwrap()
+            .verify_strict(&outer, &ed25519_dalek::Signature::from_bytes(&sealed.sig.0))
+            .expect("the outer signature passes every relay check unchanged");
+
+        let delivered = DeliverP {
+            id:             sealed.id,
+            from:           sealed.from,
+            payload:        sealed.payload.clone(),
+            sig:            sealed.sig,
+            accepted_at_ms: 7,
+        };
+        let opened = open_with(delivered.clone(), &to, &secret).expect("opens");
+        assert_eq!(opened.from, plain.from);
+        assert_eq!(opened.sig, plain.sig);
+        assert_eq!(opened.payload, plain.payload);
+        assert_eq!(opened.accepted_at_ms, 7);
+
+        assert!(open_with(delivered, &[0x99; 32], &secret).is_err(), "bound to the recipient");
+    }
+
+    /// Addressed to a mailbox, the relay-facing half names only the mailbox:
+    /// the token and the outer signature check out against it, while the
+    /// recipient still opens the envelope under their IPK.
+    #[test]
+    fn a_mailbox_addressed_dispatch_names_only_the_mailbox() {
+        use common::crypto::mailbox::MailboxKey;
+        use common::crypto::mailbox::seed_for;
+
+        let sender = SigningKey::from_bytes(&[0x31; 32]);
+        let recipient = SigningKey::from_bytes(&[0x32; 32]);
+        let ipk = recipient.verifying_key().to_bytes();
+        let secret = x25519_dalek::StaticSecret::from([0x33; 32]);
+        let push_pk = x25519_dalek::PublicKey::from(&secret).to_bytes();
+        let key = MailboxKey::derive(&recipient, &seed_for(&recipient), epoch_of(now_ms()));
+        let mailbox = key.id();
+        let token = DeliveryToken::issue_for_mailbox(&key, [0x34; 16], grant_expiry(now_ms()));
+
+        let plain = identified(&sender, ipk);
+        let sealed = seal_with(&plain, mailbox, token.clone(), &push_pk).expect("seals");
+        assert_eq!(sealed.to.0, mailbox);
+        assert!(token.verify(&mailbox, now_ms()));
+        let outer = dispatch_sig_message(&mailbox, &sealed.from.0, &sealed.id.0, &sealed.payload);
+        ed25519_dalek::VerifyingKey::from_bytes(&sealed.from.0)
+            .unwrap()
+            .verify_strict(&outer, &ed25519_dalek::Signature::from_bytes(&sealed.sig.0))
+            .expect("signed over the mailbox it is addressed to");
+
+        let delivered = DeliverP {
+            id:             sealed.id,
+            from:           sealed.from,
+            payload:        sealed.payload,
+            sig:            sealed.sig,
+            accepted_at_ms: 0,
+        };
+        assert!(open_with(delivered.clone(), &mailbox, &secret).is_err());
+        let opened = open_with(delivered, &ipk, &secret).expect("opens under the IPK");
+        assert_eq!(opened.from, plain.from);
+    }
+
+    /// A relay that could not route a sealed dispatch backs its recipient off
+    /// sealing, and the retry stands; an identified dispatch's bad signature
+    /// is still final.
+    #[test]
+    fn an_unroutable_sealed_dispatch_is_retried_identified() {
+        let to = [0x41; 32];
+        assert_eq!(note_ack(&to, &DispatchAckP::InvalidSig, false), LastOutcome::Terminal);
+        assert!(!backed_off(&to, now_ms()));
+
+        assert_eq!(note_ack(&to, &DispatchAckP::SealedUnroutable, true), LastOutcome::Reachable);
+        assert!(backed_off(&to, now_ms()));
+        assert!(!backed_off(&to, now_ms() + UNSEALED_BACKOFF_MS + 1), "the backoff ends");
+
+        let old_home = [0x42; 32];
+        assert_eq!(note_ack(&old_home, &DispatchAckP::InvalidSig, true), LastOutcome::Reachable);
+        assert!(backed_off(&old_home, now_ms()));
+    }
+
+    /// Only revoked tokens are listed, one list per key they verify under,
+    /// signed by that key over exactly its ids; lapsed rows are dropped on
+    /// the way.
+    #[test]
+    fn revocations_are_signed_per_mailbox() {
+        use common::proto::client_rel::MAX_DELIVERY_TOKEN_LIFETIME_MS;
+
+        let conn = crate::db::peers::open_in_memory();
+        let identity = SigningKey::from_bytes(&[0x43; 32]);
+        let now = 1_700_000_000_000;
+        let epoch = epoch_of(now);
+        let rows: [(u8, u64, u64, bool, bool); 5] = [
+            (1, epoch, now + 1_000, true, false),
+            (2, epoch, now + 2_000, true, false),
+            (3, epoch + 1, now + 3_000, true, false),
+            (4, epoch, now + 4_000, false, false),
+            (6, epoch, now + 6_000, true, true),
+        ];
+        let peer = [0x44u8; 32];
+        for (id, epoch, expires_at, revoked, on_ipk) in rows {
+            conn.execute(
+                "INSERT INTO issued_tokens (id, peer, epoch, expires_at, revoked, on_ipk) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![[id; 16].as_slice(), peer.as_slice(), epoch, expires_at, revoked, on_ipk],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO issued_tokens (id, peer, epoch, expires_at, revoked) \
+             VALUES (?1, ?2, ?3, ?4, 1)",
+            params![[5u8; 16].as_slice(), peer.as_slice(), epoch, now],
+        )
+        .unwrap();
+
+        let lists = revocations_in(&conn, &identity, now).unwrap();
+        assert_eq!(lists.len(), 3);
+        let seed = seed_for(&identity);
+        let ipk = identity.verifying_key().to_bytes();
+        let expected = [
+            (ipk, vec![6u8], now + 6_000),
+            (MailboxKey::derive(&identity, &seed, epoch).id(), vec![2, 1], now + 2_000),
+            (MailboxKey::derive(&identity, &seed, epoch + 1).id(), vec![3], now + 3_000),
+        ];
+        for (list, (key, ids, expires)) in lists.iter().zip(expected) {
+            assert_eq!(list.user_ipk.0, key);
+            assert_eq!(list.ids, ids.into_iter().map(|id| Bytes([id; 16])).collect::<Vec<_>>());
+            assert_eq!(list.expires_at_ms, expires);
+            assert!(list.expires_at_ms - now <= MAX_DELIVERY_TOKEN_LIFETIME_MS);
+            let input = token_revocation_signing_input(&key, &list.ids, expires, now);
+            ed25519_dalek::VerifyingKey::from_bytes(&key)
+                .unwrap()
+                .verify_strict(&input, &ed25519_dalek::Signature::from_bytes(&list.user_sig.0))
+                .expect("signed by the key the tokens verify under");
+        }
+        let left: u32 =
+            conn.query_row("SELECT COUNT(*) FROM issued_tokens", [], |r| r.get(0)).unwrap();
+        assert_eq!(left, 5, "the lapsed row is gone");
+    }
+
+    /// Rotation: a grant outlives the current epoch by one more, stays inside
+    /// what relays accept, and is not re-issued while more than an epoch is
+    /// left on it.
+    #[test]
+    fn grants_expire_an_epoch_out_and_inside_the_relay_bound() {
+        use common::crypto::mailbox::MAILBOX_OVERLAP_MS;
+        use common::proto::client_rel::MAX_DELIVERY_TOKEN_LIFETIME_MS;
+
+        let now = 1_700_000_000_000;
+        let expiry = grant_expiry(now);
+        assert!(expiry - now > TOKEN_EPOCH_MS, "a fresh grant is not due again at once");
+        assert!(expiry - now <= 2 * TOKEN_EPOCH_MS + MAILBOX_OVERLAP_MS);
+        assert!(expiry - now <= MAX_DELIVERY_TOKEN_LIFETIME_MS);
+        assert_eq!(grant_expiry(now + 1), expiry, "everyone granted this epoch shares an expiry");
+    }
+}
@@ -0,0 +1,843 @@
+//! Social recovery: the identity key split k-of-n across contacts, for when
+//! both the phrase and platform escrow are gone.
+//!
+//! **Setting up.** [`distribute`] splits the isk with Shamir
+//! (`common::crypto::shamir`) and sends each chosen, paired contact one share
+//! over MLS as an [`EXT_RECOVERY_SHARE`] extension. Each share lists every
+//! holder. Splitting again revokes the shares of whoever is left out; the
+//! rest are simply replaced.
+//!
+//! **Recovering.** A fresh install has no identity to reach a relay with, so
+//! it enrolls a throwaway one first. [`ask`] then sends a holder a
+//! [`RecoveryRequestP`] naming an X25519 key derived from that throwaway. The
+//! holder's user sees the request with a [`safety_code`]. They approve it
+//! ([`approve`]) only once the person asking has read them the same code
+//! from their own screen, by phone or in person: the request's signature
+//! proves nothing about who is asking. The share comes back sealed to the
+//! named key. The first share also lists the other holders, and each of them
+//! is asked in turn. Once `threshold` shares rebuild a key that matches the
+//! owner's IPK, [`recovered_isk`] hands it to the platform. The platform
+//! discards the throwaway install and continues down the escrow path
+//! (`adopt_escrowed_secret`) on a clean one.
+//!
+//! Holders keep shares in `contacts.db`. Any one share reveals nothing; only
+//! `threshold` of them together do.
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use common::crypto::get_nonce;
+use common::crypto::shamir::MAX_SHARES;
+use common::proto::mls_wire::AppPayload;
+use common::proto::mls_wire::EXT_RECOVERY_REVOKE;
+use common::proto::mls_wire::EXT_RECOVERY_SHARE;
+use common::proto::mls_wire::MAX_RECOVERY_HOLDERS;
+use common::proto::mls_wire::MAX_RECOVERY_NAME_BYTES;
+use common::proto::mls_wire::MlsEnvelopeP;
+use common::proto::mls_wire::RECOVERY_SEAL_INFO;
+use common::proto::mls_wire::RecoveryRequestP;
+use common::proto::mls_wire::RecoveryReturnP;
+use common::proto::mls_wire::RecoveryRevokeP;
+use common::proto::mls_wire::RecoveryShareP;
+use common::proto::mls_wire::ReturnedShareP;
+use common::proto::mls_wire::recovery_request_signing_input;
+use common::proto::mls_wire::recovery_return_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::types::bytes::ByteVec;
+use common::types::bytes::Bytes;
+use ed25519_dalek::Signature;
+use ed25519_dalek::VerifyingKey;
+use log::info;
+use log::warn;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use sha2::Digest;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::data::contact::Contact;
+use crate::data::conversation::Conversation;
+use crate::data::identity::Identity;
+use crate::data::identity::IdentitySigner;
+use crate::db::peers::CONTACTS_DB;
+use crate::utils::systime;
+
+/// How old a request may be when it reaches a holder: it may wait in their
+/// queue while they are offline.
+const ASK_MAX_AGE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// How far ahead of our clock a request or return may be dated.
+const MAX_SKEW_MS: u64 = 5 * 60 * 1000;
+
+/// Requests kept awaiting our user at once. Strangers can send them, so the
+/// oldest make room.
+const MAX_PENDING_ASKS: usize = 16;
+
+fn now_ms() -> u64 {
+    systime().as_millis() as u64
+}
+
+fn our_ipk() -> Result<[u8; 32]> {
+    Ok(Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk())
+}
+
+/// Eight digits both sides of a recovery show, read aloud to prove the
+/// request on the holder's screen is the one the owner sent.
+pub fn safety_code(asker: &[u8; 32], seal_pk: &[u8; 32]) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"promtuz-recovery-code-v1")
+        .chain_update(asker)
+        .chain_update(seal_pk)
+        .finalize();
+    let n = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 100_000_000;
+    format!("{:04} {:04}", n / 10_000, n % 10_000)
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+// Owner: splitting the key across holders.
+
+/// One contact holding a share of our key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holder {
+    pub holder:    [u8; 32],
+    pub index:     u8,
+    pub threshold: u8,
+    /// Unix seconds.
+    pub sent_at:   u64,
+}
+
+/// Split our key across `holders`, any `threshold` of whom can give it back.
+/// Each must be a paired contact. Replaces the previous split: holders left
+/// out are told to drop theirs.
+///
+/// The split is recorded before any share goes out, so every share sent can
+/// be revoked later. If a send fails, every share out is revoked, of this
+/// split and the last: the holders it reached no longer hold the last one,
+/// so neither would reliably rebuild the key. A revoke that doesn't go out
+/// stays recorded and is retried by the next split or [`disable`].
+pub async fn distribute(holders: Vec<[u8; 32]>, threshold: u8) -> Result<()> {
+    let me = our_ipk()?;
+    if holders.len() > MAX_RECOVERY_HOLDERS.min(MAX_SHARES as usize) {
+        bail!("at most {MAX_RECOVERY_HOLDERS} contacts can hold a share");
+    }
+    for (n, h) in holders.iter().enumerate() {
+        if *h == me || holders[..n].contains(h) {
+            bail!("each holder must be a different contact");
+        }
+        if !Contact::is_paired(h) {
+            bail!("only a paired contact can hold a share");
+        }
+    }
+    let shares = crate::data::recovery::split_isk(threshold, holders.len() as u8)?;
+    let set_id: [u8; 16] = get_nonce();
+    let listed: Vec<Bytes<32>> = holders.iter().copied().map(Bytes).collect();
+    record_split_in(&mut CONTACTS_DB.lock(), &set_id, &holders, threshold, systime().as_secs())?;
+
+    for (n, (holder, (index, share))) in holders.iter().zip(shares).enumerate() {
+        let body = RecoveryShareP {
+            set_id: Bytes(set_id),
+            index,
+            threshold,
+            share: Bytes(share),
+            holders: listed.clone(),
+        };
+        let sent = send_share(holder, &body).await;
+        if let Err(e) = sent {
+            abandon_split_in(&CONTACTS_DB.lock(), &set_id, &holders[n..])?;
+            settle_revokes().await;
+            return Err(e.context("a share could not be sent; the split was abandoned"));
+        }
+    }
+    settle_revokes().await;
+    info!("RECOVERY: key split {threshold}-of-{} across contacts", holders.len());
+    Ok(())
+}
+
+async fn send_share(holder: &[u8; 32], share: &RecoveryShareP) -> Result<()> {
+    let body = Zeroizing::new(share.ser().map_err(|e| anyhow!("encode share: {e}"))?);
+    let conversation = Conversation::for_peer(holder)?;
+    let payload = AppPayload::Extension { tag: EXT_RECOVERY_SHARE, body: ByteVec(body.to_vec()) };
+    crate::messaging::send_control(conversation, payload).await
+}
+
+/// Record `set_id` as the current split across `holders`, in share order, and
+/// every earlier one as waiting to be revoked.
+fn record_split_in(
+    conn: &mut Connection, set_id: &[u8; 16], holders: &[[u8; 32]], threshold: u8, now: u64,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE recovery_holders SET revoking = 1", [])?;
+    for (i, holder) in holders.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO recovery_holders (holder, set_id, idx, threshold, sent_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (holder.as_slice(), set_id.as_slice(), i as u8 + 1, threshold, now),
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// A send failed part way through `set_id`: the holders in `unsent` never got
+/// theirs and are dropped, and everyone who did is to be revoked.
+fn abandon_split_in(conn: &Connection, set_id: &[u8; 16], unsent: &[[u8; 32]]) -> Result<()> {
+    for holder in unsent {
+        conn.execute(
+            "DELETE FROM recovery_holders WHERE holder = ?1 AND set_id = ?2",
+            (holder.as_slice(), set_id.as_slice()),
+        )?;
+    }
+    conn.execute(
+        "UPDATE recovery_holders SET revoking = 1 WHERE set_id = ?1",
+        [set_id.as_slice()],
+    )?;
+    Ok(())
+}
+
+/// Turn social recovery off: every holder is told to drop its share.
+pub async fn disable() -> Result<()> {
+    CONTACTS_DB.lock().execute("UPDATE recovery_holders SET revoking = 1", [])?;
+    settle_revokes().await;
+    Ok(())
+}
+
+/// Send every revoke still owed, forgetting each share once its revoke is out.
+async fn settle_revokes() {
+    let owed = to_revoke_in(&CONTACTS_DB.lock());
+    for (holder, set_id) in owed {
+        if revoke(holder, set_id).await {
+            let _ = CONTACTS_DB.lock().execute(
+                "DELETE FROM recovery_holders WHERE holder = ?1 AND set_id = ?2",
+                (holder.as_slice(), set_id.as_slice()),
+            );
+        }
+    }
+}
+
+/// Whether the revoke went out.
+async fn revoke(holder: [u8; 32], set_id: [u8; 16]) -> bool {
+    let body = match (RecoveryRevokeP { set_id: Bytes(set_id) }).ser() {
+        Ok(b) => b,
+        Err(e) => {
+            warn!("RECOVERY: encode revoke: {e}");
+            return false;
+        },
+    };
+    let payload = AppPayload::Extension { tag: EXT_RECOVERY_REVOKE, body: ByteVec(body) };
+    let sent = match Conversation::for_peer(&holder) {
+        Ok(conversation) => crate::messaging::send_control(conversation, payload).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &sent {
+        warn!("RECOVERY: revoke to {} not sent: {e}", hex::encode(&holder[..4]));
+    }
+    sent.is_ok()
+}
+
+/// Shares still waiting for their revoke to go out.
+fn to_revoke_in(conn: &Connection) -> Vec<([u8; 32], [u8; 16])> {
+    let Ok(mut stmt) =
+        conn.prepare("SELECT holder, set_id FROM recovery_holders WHERE revoking = 1")
+    else {
+        return Vec::new();
+    };
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get::<_, Vec<u8>>(1)?)));
+    let Ok(rows) = rows else { return Vec::new() };
+    rows.flatten()
+        .filter_map(|(h, s)| Some((h.try_into().ok()?, s.try_into().ok()?)))
+        .collect()
+}
+
+/// Who holds a share of our key, in share order.
+pub fn holders() -> Vec<Holder> {
+    let conn = CONTACTS_DB.lock();
+    holders_in(&conn)
+}
+
+fn holders_in(conn: &Connection) -> Vec<Holder> {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT holder, idx, threshold, sent_at FROM recovery_holders WHERE revoking = 0 \
+         ORDER BY idx",
+    ) else {
+        return Vec::new();
+    };
+    let rows = stmt.query_map([], |r| {
+        Ok((r.get::<_, Vec<u8>>(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+    });
+    let Ok(rows) = rows else { return Vec::new() };
+    rows.flatten()
+        .filter_map(|(holder, index, threshold, sent_at)| {
+            Some(Holder { holder: holder.try_into().ok()?, index, threshold, sent_at })
+        })
+        .collect()
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+// Holder: keeping a contact's share and handing it back.
+
+/// A fresh install asking for a share we hold, awaiting our user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ask {
+    pub asker:       [u8; 32],
+    /// Who they say they are. Unverified: the code is the check.
+    pub name:        String,
+    pub code:        String,
+    /// Unix seconds.
+    pub received_at: u64,
+}
+
+/// `owner` sent us a share of their key to keep ([`EXT_RECOVERY_SHARE`]).
+pub(crate) fn on_share(owner: &[u8; 32], body: &[u8]) -> Result<()> {
+    if !Contact::is_paired(owner) {
+        bail!("share from someone we haven't paired with");
+    }
+    let share = RecoveryShareP::deser(body).map_err(|e| anyhow!("decode share: {e}"))?;
+    check_share(&share, &our_ipk()?)?;
+    let conn = CONTACTS_DB.lock();
+    keep_share_in(&conn, owner, body, systime().as_secs())?;
+    info!("RECOVERY: holding a share for {}", hex::encode(&owner[..4]));
+    Ok(())
+}
+
+/// A share we can keep: its index and threshold fit the holders it lists, and
+/// we are the holder at its index. Anything else could never help rebuild the
+/// key, so holding it would only mislead the owner's recovery.
+fn check_share(share: &RecoveryShareP, me: &[u8; 32]) -> Result<()> {
+    let listed = share.holders.len();
+    if share.index == 0 || share.index > MAX_SHARES || share.threshold < 2 {
+        bail!("malformed share");
+    }
+    if share.threshold as usize > listed || share.index as usize > listed {
+        bail!("share needs more holders than it lists");
+    }
+    if share.holders[share.index as usize - 1].0 != *me {
+        bail!("share is not ours to hold");
+    }
+    Ok(())
+}
+
+fn keep_share_in(conn: &Connection, owner: &[u8; 32], body: &[u8], now: u64) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO recovery_held (owner, share, received_at) VALUES (?1, ?2, ?3)",
+        (owner.as_slice(), body, now),
+    )?;
+    Ok(())
+}
+
+/// `owner` split again without us, or turned recovery off
+/// ([`EXT_RECOVERY_REVOKE`]).
+pub(crate) fn on_revoke(owner: &[u8; 32], body: &[u8]) -> Result<()> {
+    let revoke = RecoveryRevokeP::deser(body).map_err(|e| anyhow!("decode revoke: {e}"))?;
+    let conn = CONTACTS_DB.lock();
+    // Only the split named: a revoke that overtook a newer share must not
+    // drop it.
+    if held_in(&conn, owner).is_some_and(|s| s.set_id == revoke.set_id) {
+        conn.execute("DELETE FROM recovery_held WHERE owner = ?1", [owner.as_slice()])?;
+    }
+    Ok(())
+}
+
+fn held_in(conn: &Connection, owner: &[u8; 32]) -> Option<RecoveryShareP> {
+    let body: Vec<u8> = conn
+        .query_row("SELECT share FROM recovery_held WHERE owner = ?1", [owner.as_slice()], |r| {
+            r.get(0)
+        })
+        .optional()
+        .ok()??;
+    RecoveryShareP::deser(&body).ok()
+}
+
+/// Contacts whose share we hold.
+pub fn held_for() -> Vec<[u8; 32]> {
+    let conn = CONTACTS_DB.lock();
+    let Ok(mut stmt) = conn.prepare("SELECT owner FROM recovery_held") else {
+        return Vec::new();
+    };
+    let Ok(rows) = stmt.query_map([], |r| r.get::<_, Vec<u8>>(0)) else { return Vec::new() };
+    rows.flatten().filter_map(|o| o.try_into().ok()).collect()
+}
+
+/// A fresh install asks for a share ([`MlsEnvelopeP::RecoveryRequest`]).
+/// Checked and queued for our user; nothing is sent without them.
+pub(crate) fn on_request(sender_ipk: [u8; 32], req: RecoveryRequestP) -> Result<()> {
+    if req.sender_ipk.0 != sender_ipk {
+        bail!("recovery request sender_ipk mismatch");
+    }
+    let me = our_ipk()?;
+    if req.recipient_ipk.0 != me {
+        bail!("recovery request not addressed to us");
+    }
+    if req.name.len() > MAX_RECOVERY_NAME_BYTES {
+        bail!("recovery request name too long");
+    }
+    let msg = recovery_request_signing_input(
+        &sender_ipk,
+        &me,
+        &req.seal_pk.0,
+        &req.name,
+        req.timestamp,
+    );
+    VerifyingKey::from_bytes(&sender_ipk)
+        .map_err(|e| anyhow!("asker ipk: {e}"))?
+        .verify_strict(&msg, &Signature::from_bytes(&req.sig.0))
+        .map_err(|_| anyhow!("recovery request signature invalid"))?;
+    let now = now_ms();
+    if req.timestamp > now + MAX_SKEW_MS || now.saturating_sub(req.timestamp) > ASK_MAX_AGE_MS {
+        bail!("recovery request outside the accepted window");
+    }
+    let conn = CONTACTS_DB.lock();
+    // Holding nothing, there is nothing to ask our user about.
+    let holding: u32 = conn.query_row("SELECT COUNT(*) FROM recovery_held", [], |r| r.get(0))?;
+    if holding == 0 {
+        return Ok(());
+    }
+    let body = req.ser().map_err(|e| anyhow!("encode request: {e}"))?;
+    queue_ask_in(&conn, &sender_ipk, &body, systime().as_secs())?;
+    info!("RECOVERY: {} asks for a share", hex::encode(&sender_ipk[..4]));
+    Ok(())
+}
+
+fn queue_ask_in(conn: &Connection, asker: &[u8; 32], body: &[u8], now: u64) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO recovery_asks (asker, request, received_at) VALUES (?1, ?2, ?3)",
+        (asker.as_slice(), body, now),
+    )?;
+    conn.execute(
+        "DELETE FROM recovery_asks WHERE asker NOT IN \
+         (SELECT asker FROM recovery_asks ORDER BY received_at DESC LIMIT ?1)",
+        [MAX_PENDING_ASKS],
+    )?;
+    Ok(())
+}
+
+fn ask_in(conn: &Connection, asker: &[u8; 32]) -> Option<RecoveryRequestP> {
+    let body: Vec<u8> = conn
+        .query_row("SELECT request FROM recovery_asks WHERE asker = ?1", [asker.as_slice()], |r| {
+            r.get(0)
+        })
+        .optional()
+        .ok()??;
+    RecoveryRequestP::deser(&body).ok()
+}
+
+/// Requests awaiting our user, newest first.
+pub fn asks() -> Vec<Ask> {
+    let conn = CONTACTS_DB.lock();
+    asks_in(&conn)
+}
+
+fn asks_in(conn: &Connection) -> Vec<Ask> {
+    let Ok(mut stmt) =
+        conn.prepare("SELECT request, received_at FROM recovery_asks ORDER BY received_at DESC")
+    else {
+        return Vec::new();
+    };
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get::<_, u64>(1)?)));
+    let Ok(rows) = rows else { return Vec::new() };
+    rows.flatten()
+        .filter_map(|(body, received_at)| {
+            let req = RecoveryRequestP::deser(&body).ok()?;
+            Some(Ask {
+                asker: req.sender_ipk.0,
+                code: safety_code(&req.sender_ipk.0, &req.seal_pk.0),
+                name: req.name,
+                received_at,
+            })
+        })
+        .collect()
+}
+
+/// Our user confirmed the code: hand `owner`'s share back to `asker`.
+pub async fn approve(asker: [u8; 32], owner: [u8; 32]) -> Result<()> {
+    let me = our_ipk()?;
+    let (req, share) = {
+        let conn = CONTACTS_DB.lock();
+        let req = ask_in(&conn, &asker).ok_or_else(|| anyhow!("no such request"))?;
+        let share = held_in(&conn, &owner).ok_or_else(|| anyhow!("we hold no share for them"))?;
+        (req, share)
+    };
+    let ret = seal_share(me, &req, owner, share, now_ms(), |input| {
+        Ok(IdentitySigner::sign(input)?.to_bytes())
+    })?;
+    let envelope = MlsEnvelopeP::RecoveryReturn(ret);
+    crate::messaging::send_plain_envelope(asker, envelope, true).await?;
+    decline(asker);
+    info!("RECOVERY: returned {}'s share", hex::encode(&owner[..4]));
+    Ok(())
+}
+
+/// `owner`'s `share`, sealed to the key `req` names and signed by us, `me`,
+/// as its holder: what [`on_return`] opens on the asker's side.
+fn seal_share(
+    me: [u8; 32], req: &RecoveryRequestP, owner: [u8; 32], share: RecoveryShareP, timestamp: u64,
+    sign: impl FnOnce(&[u8]) -> Result<[u8; 64]>,
+) -> Result<RecoveryReturnP> {
+    let asker = req.sender_ipk.0;
+    let plain = ReturnedShareP { owner_ipk: Bytes(owner), share };
+    let plain = Zeroizing::new(plain.ser().map_err(|e| anyhow!("encode share: {e}"))?);
+    let (eph_pk, nonce, ciphertext) =
+        crate::push::seal_to_push_key(&req.seal_pk.0, RECOVERY_SEAL_INFO, &asker, &plain)
+            .ok_or_else(|| anyhow!("the request's key is unusable"))?;
+    let input =
+        recovery_return_signing_input(&me, &asker, &eph_pk, &nonce, &ciphertext, timestamp);
+    Ok(RecoveryReturnP {
+        sender_ipk: Bytes(me),
+        recipient_ipk: Bytes(asker),
+        eph_pk: Bytes(eph_pk),
+        nonce: Bytes(nonce),
+        ciphertext: ByteVec(ciphertext),
+        timestamp,
+        sig: Bytes(sign(&input)?),
+    })
+}
+
+/// Drop a request without answering it.
+pub fn decline(asker: [u8; 32]) {
+    let _ = CONTACTS_DB
+        .lock()
+        .execute("DELETE FROM recovery_asks WHERE asker = ?1", [asker.as_slice()]);
+}
+
+//===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
+// Recovering: a fresh install collecting shares.
+
+/// Where a recovery stands on this (fresh) install.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Session {
+    /// What to read to each holder, so they know the request is ours.
+    pub code:      String,
+    /// The identity being recovered, once a holder has told us.
+    pub owner:     Option<[u8; 32]>,
+    /// Shares needed, once known.
+    pub threshold: u8,
+    /// Holders asked, and which of them answered.
+    pub asked:     Vec<([u8; 32], bool)>,
+}
+
+/// Ask `holder` for the share they keep for us.
+pub async fn ask(holder: [u8; 32]) -> Result<()> {
+    let identity = Identity::get().ok_or_else(|| anyhow!("identity not found"))?;
+    let me = identity.ipk();
+    if holder == me {
+        bail!("can't ask ourselves");
+    }
+    let seal_pk = seal_pk()?;
+    let mut name = identity.name();
+    truncate_to_boundary(&mut name, MAX_RECOVERY_NAME_BYTES);
+    let timestamp = now_ms();
+    let input = recovery_request_signing_input(&me, &holder, &seal_pk, &name, timestamp);
+    let sig = IdentitySigner::sign(&input)?.to_bytes();
+    let envelope = MlsEnvelopeP::RecoveryRequest(RecoveryRequestP {
+        sender_ipk: Bytes(me),
+        recipient_ipk: Bytes(holder),
+        seal_pk: Bytes(seal_pk),
+        name,
+        timestamp,
+        sig: Bytes(sig),
+    });
+    CONTACTS_DB.lock().execute(
+        "INSERT INTO recovery_session (holder, asked_at) VALUES (?1, ?2) \
+         ON CONFLICT(holder) DO UPDATE SET asked_at = excluded.asked_at",
+        (holder.as_slice(), systime().as_secs()),
+    )?;
+    crate::messaging::send_plain_envelope(holder, envelope, true).await
+}
+
+fn seal_pk() -> Result<[u8; 32]> {
+    let secret = IdentitySigner::recovery_seal_secret()?;
+    Ok(x25519_dalek::PublicKey::from(&secret).to_bytes())
+}
+
+fn truncate_to_boundary(s: &mut String, max: usize) {
+    if s.len() > max {
+        let mut end = max;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+}
+
+/// A holder answered ([`MlsEnvelopeP::RecoveryReturn`]). The share is kept,
+/// and every other holder it lists that we haven't asked yet is asked.
+pub(crate) fn on_return(sender_ipk: [u8; 32], ret: RecoveryReturnP) -> Result<()> {
+    let me = our_ipk()?;
+    let secret = IdentitySigner::recovery_seal_secret()?;
+    let (returned, plain) = open_share(me, &secret, sender_ipk, &ret, now_ms())?;
+
+    let to_ask = {
+        let conn = CONTACTS_DB.lock();
+        let updated = conn.execute(
+            "UPDATE recovery_session SET owner = ?2, share = ?3 WHERE holder = ?1",
+            (sender_ipk.as_slice(), returned.owner_ipk.0.as_slice(), plain.as_slice()),
+        )?;
+        if updated == 0 {
+            bail!("recovery return from a holder we never asked");
+        }
+        let asked = session_in(&conn).asked;
+        returned
+            .share
+            .holders
+            .iter()
+            .map(|h| h.0)
+            .filter(|h| *h != me && !asked.iter().any(|(a, _)| a == h))
+            .collect::<Vec<_>>()
+    };
+    info!("RECOVERY: share returned by {}", hex::encode(&sender_ipk[..4]));
+    for holder in to_ask {
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = ask(holder).await {
+                warn!("RECOVERY: asking {} failed: {e}", hex::encode(&holder[..4]));
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Check and open a holder's return: from `sender_ipk`, to us (`me`), signed
+/// by them and sealed to `secret`. Returns the share with the plaintext it
+/// came in, which is what the session keeps.
+fn open_share(
+    me: [u8; 32], secret: &x25519_dalek::StaticSecret, sender_ipk: [u8; 32],
+    ret: &RecoveryReturnP, now: u64,
+) -> Result<(ReturnedShareP, Zeroizing<Vec<u8>>)> {
+    if ret.sender_ipk.0 != sender_ipk {
+        bail!("recovery return sender_ipk mismatch");
+    }
+    if ret.recipient_ipk.0 != me {
+        bail!("recovery return not addressed to us");
+    }
+    let input = recovery_return_signing_input(
+        &sender_ipk,
+        &me,
+        &ret.eph_pk.0,
+        &ret.nonce.0,
+        &ret.ciphertext.0,
+        ret.timestamp,
+    );
+    VerifyingKey::from_bytes(&sender_ipk)
+        .map_err(|e| anyhow!("holder ipk: {e}"))?
+        .verify_strict(&input, &Signature::from_bytes(&ret.sig.0))
+        .map_err(|_| anyhow!("recovery return signature invalid"))?;
+    if ret.timestamp > now + MAX_SKEW_MS {
+        bail!("recovery return dated in the future");
+    }
+    let plain = crate::push::open_with_push_secret(
+        secret,
+        RECOVERY_SEAL_INFO,
+        &ret.eph_pk.0,
+        &ret.nonce.0,
+        &me,
+        &ret.ciphertext.0,
+    )
+    .map(Zeroizing::new)
+    .ok_or_else(|| anyhow!("recovery return doesn't open"))?;
+    let returned = ReturnedShareP::deser(&plain).map_err(|e| anyhow!("decode share: {e}"))?;
+    Ok((returned, plain))
+}
+
+/// Shares returned so far, with the owner and threshold they agree on.
+fn returned_in(conn: &Connection) -> Vec<ReturnedShareP> {
+    let Ok(mut stmt) =
+        conn.prepare("SELECT share FROM recovery_session WHERE share IS NOT NULL")
+    else {
+        return Vec::new();
+    };
+    let Ok(rows) = stmt.query_map([], |r| r.get::<_, Vec<u8>>(0)) else { return Vec::new() };
+    rows.flatten().filter_map(|b| ReturnedShareP::deser(&Zeroizing::new(b)).ok()).collect()
+}
+
+/// The split most returned shares belong to: `(owner, set_id, threshold)`.
+fn leading_split(returned: &[ReturnedShareP]) -> Option<([u8; 32], [u8; 16], u8)> {
+    returned
+        .iter()
+        .map(|r| (r.owner_ipk.0, r.share.set_id.0, r.share.threshold))
+        .max_by_key(|key| {
+            returned
+                .iter()
+                .filter(|r| (r.owner_ipk.0, r.share.set_id.0, r.share.threshold) == *key)
+                .count()
+        })
+}
+
+/// Where this install's recovery stands.
+pub fn session() -> Session {
+    let conn = CONTACTS_DB.lock();
+    session_in(&conn)
+}
+
+fn session_in(conn: &Connection) -> Session {
+    let returned = returned_in(conn);
+    let (owner, threshold) = match leading_split(&returned) {
+        Some((owner, _, threshold)) => (Some(owner), threshold),
+        None => (None, 0),
+    };
+    let asked = conn
+        .prepare("SELECT holder, share IS NOT NULL FROM recovery_session ORDER BY asked_at")
+        .and_then(|mut stmt| {
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, Vec<u8>>(0)?, r.get(1)?)))?;
+            Ok(rows.flatten().filter_map(|(h, got)| Some((h.try_into().ok()?, got))).collect())
+        })
+        .unwrap_or_default();
+    let code = seal_pk()
+        .ok()
+        .zip(Identity::get())
+        .map(|(pk, id)| safety_code(&id.ipk(), &pk))
+        .unwrap_or_default();
+    Session { code, owner, threshold, asked }
+}
+
+/// The recovered identity key, once enough shares are back. The platform
+/// passes it to `adopt_escrowed_secret` on a clean install.
+pub fn recovered_isk() -> Result<Zeroizing<[u8; 32]>> {
+    let returned = returned_in(&CONTACTS_DB.lock());
+    let (owner, set_id, threshold) =
+        leading_split(&returned).ok_or_else(|| anyhow!("no share has come back yet"))?;
+    let shares: Vec<_> = returned
+        .iter()
+        .filter(|r| r.owner_ipk.0 == owner && r.share.set_id.0 == set_id)
+        .map(|r| (r.share.index, r.share.share.0))
+        .collect();
+    crate::data::recovery::isk_from_shares(&owner, &shares, threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::peers::open_in_memory;
+
+    fn request(asker: u8) -> Vec<u8> {
+        RecoveryRequestP {
+            sender_ipk:    Bytes([asker; 32]),
+            recipient_ipk: Bytes([0; 32]),
+            seal_pk:       Bytes([asker; 32]),
+            name:          format!("asker {asker}"),
+            timestamp:     1,
+            sig:           Bytes([0; 64]),
+        }
+        .ser()
+        .unwrap()
+    }
+
+    #[test]
+    fn asks_are_capped_keeping_the_newest() {
+        let conn = open_in_memory();
+        for i in 0..MAX_PENDING_ASKS as u8 + 3 {
+            queue_ask_in(&conn, &[i; 32], &request(i), i as u64).unwrap();
+        }
+        let asks = asks_in(&conn);
+        assert_eq!(asks.len(), MAX_PENDING_ASKS);
+        assert_eq!(asks[0].asker, [MAX_PENDING_ASKS as u8 + 2; 32], "newest first");
+        assert!(!asks.iter().any(|a| a.asker == [2; 32]), "oldest made room");
+        assert_eq!(asks[0].code, safety_code(&asks[0].asker, &asks[0].asker));
+    }
+
+    #[test]
+    fn the_leading_split_wins_over_a_stray_share() {
+        let share = |owner: u8, set: u8, index| ReturnedShareP {
+            owner_ipk: Bytes([owner; 32]),
+            share:     RecoveryShareP {
+                set_id: Bytes([set; 16]),
+                index,
+                threshold: 2,
+                share: Bytes([0; 32]),
+                holders: vec![],
+            },
+        };
+        let returned = vec![share(1, 1, 1), share(9, 9, 1), share(1, 1, 2)];
+        assert_eq!(leading_split(&returned), Some(([1; 32], [1; 16], 2)));
+        assert_eq!(leading_split(&[]), None);
+    }
+
+    fn share_for(index: u8, threshold: u8, holders: &[[u8; 32]]) -> RecoveryShareP {
+        RecoveryShareP {
+            set_id: Bytes([7; 16]),
+            index,
+            threshold,
+            share: Bytes([index; 32]),
+            holders: holders.iter().copied().map(Bytes).collect(),
+        }
+    }
+
+    #[test]
+    fn a_share_must_fit_its_holders_and_name_us() {
+        let (me, other) = ([1; 32], [2; 32]);
+        assert!(check_share(&share_for(2, 2, &[other, me]), &me).is_ok());
+        assert!(check_share(&share_for(1, 2, &[other, me]), &me).is_err(), "someone else's");
+        assert!(check_share(&share_for(2, 3, &[other, me]), &me).is_err(), "unreachable k");
+        assert!(check_share(&share_for(3, 2, &[other, me]), &me).is_err(), "index past them");
+        assert!(check_share(&share_for(1, 2, &[]), &me).is_err());
+        assert!(check_share(&share_for(1, 1, &[me, other]), &me).is_err(), "k of 1");
+    }
+
+    /// Every share is recorded before it is sent, so whatever went out can be
+    /// revoked; a split that fails part way is revoked whole, with the last.
+    #[test]
+    fn a_split_is_recorded_before_sending_and_revoked_whole_if_abandoned() {
+        let mut conn = open_in_memory();
+        let (a, b, c) = ([1; 32], [2; 32], [3; 32]);
+        record_split_in(&mut conn, &[1; 16], &[a, b], 2, 10).unwrap();
+        assert!(to_revoke_in(&conn).is_empty());
+
+        record_split_in(&mut conn, &[2; 16], &[a, c], 2, 20).unwrap();
+        let current: Vec<_> = holders_in(&conn).iter().map(|h| (h.holder, h.index)).collect();
+        assert_eq!(current, vec![(a, 1), (c, 2)]);
+        let mut owed = to_revoke_in(&conn);
+        owed.sort();
+        assert_eq!(owed, vec![(a, [1; 16]), (b, [1; 16])], "the last split, a's included");
+
+        // The share for c never went out.
+        abandon_split_in(&conn, &[2; 16], &[c]).unwrap();
+        assert!(holders_in(&conn).is_empty());
+        let mut owed = to_revoke_in(&conn);
+        owed.sort();
+        assert_eq!(owed, vec![(a, [1; 16]), (a, [2; 16]), (b, [1; 16])]);
+    }
+
+    /// What a holder approves opens on the asking install, and only there.
+    #[test]
+    fn an_approved_share_opens_for_the_asker_it_was_sealed_to() {
+        use ed25519_dalek::Signer;
+        use ed25519_dalek::SigningKey;
+
+        let holder = SigningKey::from_bytes(&[0x21; 32]);
+        let holder_ipk = holder.verifying_key().to_bytes();
+        let asker_ipk = SigningKey::from_bytes(&[0x22; 32]).verifying_key().to_bytes();
+        let secret = x25519_dalek::StaticSecret::from([0x23; 32]);
+        let req = RecoveryRequestP {
+            sender_ipk:    Bytes(asker_ipk),
+            recipient_ipk: Bytes(holder_ipk),
+            seal_pk:       Bytes(x25519_dalek::PublicKey::from(&secret).to_bytes()),
+            name:          "owner".into(),
+            timestamp:     1,
+            sig:           Bytes([0; 64]),
+        };
+        let (owner, share) = ([0x24; 32], share_for(1, 2, &[holder_ipk, [9; 32]]));
+        let sign = |m: &[u8]| Ok(holder.sign(m).to_bytes());
+        let ret = seal_share(holder_ipk, &req, owner, share.clone(), 50, sign).unwrap();
+
+        let (returned, plain) = open_share(asker_ipk, &secret, holder_ipk, &ret, 50).unwrap();
+        assert_eq!(returned.owner_ipk.0, owner);
+        assert_eq!(returned.share, share);
+        assert_eq!(ReturnedShareP::deser(&plain).unwrap(), returned, "kept as it came");
+
+        let other = x25519_dalek::StaticSecret::from([0x25; 32]);
+        assert!(open_share(asker_ipk, &other, holder_ipk, &ret, 50).is_err(), "not our key");
+        assert!(open_share(asker_ipk, &secret, [9; 32], &ret, 50).is_err(), "other sender");
+        assert!(open_share(holder_ipk, &secret, holder_ipk, &ret, 50).is_err(), "not to us");
+        let redated = RecoveryReturnP { timestamp: 49, ..ret.clone() };
+        assert!(open_share(asker_ipk, &secret, holder_ipk, &redated, 50).is_err(), "signature");
+        let ahead = seal_share(holder_ipk, &req, owner, share, 51 + MAX_SKEW_MS, sign).unwrap();
+        assert!(open_share(asker_ipk, &secret, holder_ipk, &ahead, 50).is_err(), "future");
+    }
+
+    #[test]
+    fn the_safety_code_is_eight_digits_and_binds_both_keys() {
+        let code = safety_code(&[1; 32], &[2; 32]);
+        assert_eq!(code.len(), 9);
+        assert!(code.chars().all(|c| c.is_ascii_digit() || c == ' '));
+        assert_ne!(code, safety_code(&[1; 32], &[3; 32]));
+        assert_ne!(code, safety_code(&[3; 32], &[2; 32]));
+    }
+}
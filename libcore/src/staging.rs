@@ -78,6 +78,10 @@ impl Staged {
             blob:     self.blob.clone(),
             thumb:    self.thumb.clone(),
             file_id:  self.file_id.map(|f| f.to_vec()),
+            duration_ms: 0,
+            waveform: None,
+            sticker_pack: None,
+            sticker_id: 0,
         })
     }
 }
@@ -257,6 +261,9 @@ pub async fn commit(
         discard(s.id);
         let payload = crate::messaging::rebuild_pending_payload(&conversation, &msg)?;
         crate::messaging::send_prepared(conversation, &msg, payload).await?;
+        if let Some(file_id) = s.file_id {
+            crate::transfer::blob::publish(conversation, file_id);
+        }
     }
     Ok(())
 }
@@ -0,0 +1,354 @@
+//! Sticker packs: making one, installing one, and fetching the packs other
+//! people's stickers name.
+//!
+//! A received `Body::Sticker` whose pack we lack asks its conversation for
+//! the pack (`AppPayload::PackWant`). Any member with it installed answers
+//! with `AppPayload::PackOffer` and serves the pack file over the transfer
+//! engine exactly like an attachment, parking a relay blob copy too for when
+//! it goes offline. It doesn't matter who serves it: the file either hashes to
+//! the id the sticker named and carries its author's signature, or it is
+//! dropped.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::ensure;
+use common::proto::mls_wire::AppPayload;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::proto::sticker::MAX_PACK_BYTES;
+use common::proto::sticker::StickerPackP;
+use common::proto::sticker::pack_id;
+use common::proto::sticker::pack_sig_message;
+use common::types::bytes::ByteVec;
+use common::types::bytes::Bytes;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::data::identity::Identity;
+use crate::data::identity::IdentitySigner;
+use crate::data::sticker;
+use crate::data::sticker::PackRow;
+use crate::transfer::store;
+use crate::utils::systime;
+
+/// How long we keep serving a pack file after answering a want, matching
+/// attachment retention.
+const SERVE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// One ask (or one answer) from this device per conversation and pack in this
+/// window, so a burst of stickers from one pack costs us one `PackWant`, not
+/// dozens. Every member holding the pack still answers once.
+const ASK_BACKOFF_SECS: u64 = 10 * 60;
+
+/// Parsed packs kept in memory: a chat full of stickers draws from a few.
+const PACK_CACHE: usize = 4;
+
+/// Last ask or answer by `(conversation, pack_id)`, in unix seconds.
+type Backoff = Mutex<HashMap<([u8; 16], [u8; 16]), u64>>;
+
+/// Recently read packs by id, newest at the back.
+type Cache = Mutex<VecDeque<([u8; 16], Arc<StickerPackP>)>>;
+
+static ASKED: Lazy<Backoff> = Lazy::new(|| Mutex::new(HashMap::new()));
+static ANSWERED: Lazy<Backoff> = Lazy::new(|| Mutex::new(HashMap::new()));
+static CACHE: Lazy<Cache> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Drop the backoffs and cached packs; the account they were for is gone.
+pub(crate) fn forget_all() {
+    ASKED.lock().clear();
+    ANSWERED.lock().clear();
+    CACHE.lock().clear();
+}
+
+fn pack_path(pack_id: &[u8; 16]) -> String {
+    format!("{}/{}.pack", crate::db::files_dir("stickers"), hex::encode(pack_id))
+}
+
+/// Sign `frames` (encoded AVIF) as a pack authored by our identity and
+/// install it. Returns the pack id.
+pub fn create(title: &str, frames: Vec<Vec<u8>>) -> Result<[u8; 16]> {
+    let author = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let frames: Vec<ByteVec> = frames.into_iter().map(ByteVec).collect();
+    let id = pack_id(title, &author, &frames);
+    let sig = IdentitySigner::sign(&pack_sig_message(&id))?.to_bytes();
+    let pack = StickerPackP { title: title.into(), author: Bytes(author), frames, sig: Bytes(sig) };
+    install(&pack.ser().map_err(|e| anyhow!("encode pack: {e}"))?)
+}
+
+/// Install a serialized pack — one we made, or a file the user imported —
+/// once it verifies. Returns its id.
+pub fn install(bytes: &[u8]) -> Result<[u8; 16]> {
+    let pack = open(bytes)?;
+    put(&pack, bytes)
+}
+
+/// Forget an installed pack and delete its file.
+pub fn remove(pack_id: &[u8; 16]) -> Result<()> {
+    CACHE.lock().retain(|(id, _)| id != pack_id);
+    if let Some(row) = sticker::remove(pack_id)? {
+        let _ = std::fs::remove_file(&row.path);
+    }
+    Ok(())
+}
+
+/// An installed pack, parsed. Installs a fetched pack whose pull finished
+/// since we last looked, so a bubble drawn after a restart finds it.
+pub fn load(pack_id: &[u8; 16]) -> Result<Option<Arc<StickerPackP>>> {
+    if let Some((_, pack)) = CACHE.lock().iter().find(|(id, _)| id == pack_id) {
+        return Ok(Some(pack.clone()));
+    }
+    if !sticker::is_installed(pack_id) {
+        settle(pack_id);
+    }
+    let Some(row) = sticker::get(pack_id) else { return Ok(None) };
+    // Verified at install; what's on disk is ours.
+    let bytes = std::fs::read(&row.path).map_err(|e| anyhow!("read {}: {e}", row.path))?;
+    let pack = Arc::new(StickerPackP::deser(&bytes).map_err(|e| anyhow!("decode pack: {e}"))?);
+    let mut cache = CACHE.lock();
+    cache.push_back((*pack_id, pack.clone()));
+    if cache.len() > PACK_CACHE {
+        cache.pop_front();
+    }
+    Ok(Some(pack))
+}
+
+/// Sticker `id` of `pack_id` as AVIF, or `None` while the pack isn't here.
+pub fn frame(pack_id: &[u8; 16], id: u32) -> Result<Option<Vec<u8>>> {
+    Ok(load(pack_id)?.and_then(|p| p.frames.get(id as usize).map(|f| f.0.clone())))
+}
+
+/// A sticker from `pack_id` arrived in `conversation` and we can't draw it:
+/// ask the members for the pack.
+pub fn want(conversation: [u8; 16], pack_id: [u8; 16]) {
+    if sticker::is_installed(&pack_id) || settle(&pack_id) || !due(&ASKED, conversation, pack_id)
+    {
+        return;
+    }
+    crate::RUNTIME.spawn(async move {
+        let payload = AppPayload::PackWant { pack: pack_id };
+        if let Err(e) = crate::messaging::send_control(conversation, payload).await {
+            log::debug!("stickers: PackWant for {} not sent: {e}", hex::encode(pack_id));
+        }
+    });
+}
+
+/// A member of `conversation` lacks `pack_id`. If we have it, serve the pack
+/// file and say so. Who may pull it is the transfer engine's usual gate: a
+/// member of a conversation the pack was shown in.
+pub fn on_pack_want(conversation: [u8; 16], pack_id: [u8; 16]) {
+    let Some(row) = sticker::get(&pack_id) else { return };
+    if !due(&ANSWERED, conversation, pack_id) {
+        return;
+    }
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = offer(conversation, row).await {
+            log::warn!("stickers: could not offer {}: {e}", hex::encode(pack_id));
+        }
+    });
+}
+
+async fn offer(conversation: [u8; 16], row: PackRow) -> Result<()> {
+    let (file_id, size) = crate::transfer::prepare_send(&row.path, SERVE_TTL_SECS)?;
+    ensure!(file_id == row.file_id, "pack file changed on disk");
+    let payload = AppPayload::PackOffer { pack: row.pack_id, file_id, size };
+    crate::messaging::send_control(conversation, payload).await?;
+    crate::transfer::blob::publish(conversation, file_id);
+    Ok(())
+}
+
+/// `peer` serves `pack_id` as `file_id`. Pulled only when a message we hold
+/// names the pack and we don't have it yet; a second offer for the same file
+/// adds its sender as another source for the pull. A `file_id` we already
+/// know as something else — an attachment offered to us, or a file we sent —
+/// is refused, so an offer can't start a pull the user never asked for.
+pub fn on_pack_offer(
+    peer: [u8; 32], conversation: [u8; 16], pack_id: [u8; 16], file_id: [u8; 32], size: u64,
+) {
+    if size > MAX_PACK_BYTES
+        || sticker::is_installed(&pack_id)
+        || !sticker::is_referenced(&pack_id)
+        || !is_unclaimed(&file_id)
+    {
+        return;
+    }
+    match sticker::offer_put(&file_id, &pack_id, &peer, &conversation, size) {
+        Ok(true) => {},
+        Ok(false) => {
+            let _ = store::source_add(&file_id, &peer, systime().as_secs());
+        },
+        Err(e) => {
+            log::warn!("stickers: could not record an offer: {e}");
+            return;
+        },
+    }
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = crate::transfer::download(file_id).await {
+            log::warn!("stickers: pull of {} failed: {e}", hex::encode(pack_id));
+        }
+        if settle(&pack_id) {
+            log::info!("stickers: installed {}", hex::encode(pack_id));
+        }
+    });
+}
+
+/// Whether `file_id` is free to be a pack file: no attachment offer or
+/// retained send of ours uses it.
+fn is_unclaimed(file_id: &[u8; 32]) -> bool {
+    matches!(crate::data::media::attachment_offer(file_id), Ok(None))
+        && store::retention_get(file_id).is_none()
+}
+
+/// Install `pack_id` from an offered pull that has completed. An offer whose
+/// file turns out to be anything but that pack is dropped. `true` once it is
+/// installed.
+fn settle(pack_id: &[u8; 16]) -> bool {
+    for file_id in sticker::offers_for(pack_id).unwrap_or_default() {
+        let Some(p) = store::partial_get(&file_id).filter(|p| p.state == store::DONE) else {
+            continue;
+        };
+        let installed = std::fs::read(&p.path).map_err(Into::into).and_then(|bytes| {
+            let pack = open(&bytes)?;
+            ensure!(pack.pack_id() == *pack_id, "offered file is another pack");
+            put(&pack, &bytes)
+        });
+        match installed {
+            Ok(_) => return true,
+            Err(e) => {
+                log::warn!("stickers: offered {} rejected: {e}", hex::encode(&file_id[..4]));
+                let _ = sticker::offer_remove(&file_id);
+            },
+        }
+    }
+    false
+}
+
+/// Decode and verify a serialized pack.
+fn open(bytes: &[u8]) -> Result<StickerPackP> {
+    let pack = StickerPackP::deser(bytes).map_err(|e| anyhow!("decode pack: {e}"))?;
+    if !pack.verify() {
+        bail!("sticker pack fails its author's signature or its bounds");
+    }
+    Ok(pack)
+}
+
+/// Write a verified pack under `files/stickers` and index it.
+fn put(pack: &StickerPackP, bytes: &[u8]) -> Result<[u8; 16]> {
+    let pack_id = pack.pack_id();
+    let path = pack_path(&pack_id);
+    std::fs::write(&path, bytes).map_err(|e| anyhow!("write {path}: {e}"))?;
+    let file_id = crate::transfer::wire::Manifest::from_file(&path)?.file_id();
+    sticker::put(&PackRow {
+        pack_id,
+        title: pack.title.clone(),
+        author: pack.author.0,
+        count: pack.frames.len() as u32,
+        path,
+        file_id,
+    })?;
+    sticker::offers_clear(&pack_id)?;
+    Ok(pack_id)
+}
+
+/// Whether `(conversation, pack_id)` is outside its backoff in `map`; marks it
+/// if so.
+fn due(map: &Backoff, conversation: [u8; 16], pack_id: [u8; 16]) -> bool {
+    let now = systime().as_secs();
+    let mut map = map.lock();
+    map.retain(|_, at| now.saturating_sub(*at) < ASK_BACKOFF_SECS);
+    map.insert((conversation, pack_id), now).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+
+    fn signed(key: &SigningKey, title: &str, frames: Vec<Vec<u8>>) -> Vec<u8> {
+        StickerPackP::signed(key, title, frames.into_iter().map(ByteVec).collect()).ser().unwrap()
+    }
+
+    fn done_partial(file_id: [u8; 32], path: &str, total: u64) {
+        store::partial_put(&store::Partial {
+            file_id,
+            source_ipk: [1u8; 32],
+            total,
+            chunk_size: 256 * 1024,
+            manifest: None,
+            have: 1,
+            bitmap: None,
+            state: store::DONE,
+            path: path.into(),
+            updated_at: 0,
+        })
+        .unwrap();
+    }
+
+    /// An installed pack draws its frames, and tampering with its bytes keeps
+    /// it out.
+    #[test]
+    fn installs_only_what_its_author_signed() {
+        let dir = std::env::temp_dir().join("promtuz-stickers-install-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        let key = SigningKey::from_bytes(&[0x31u8; 32]);
+        let bytes = signed(&key, "cats", vec![vec![1, 2, 3], vec![4, 5]]);
+        let id = install(&bytes).unwrap();
+        assert_eq!(frame(&id, 1).unwrap(), Some(vec![4, 5]));
+        assert_eq!(frame(&id, 2).unwrap(), None, "past the end");
+        let row = sticker::get(&id).unwrap();
+        assert_eq!((row.title.as_str(), row.count), ("cats", 2));
+
+        let mut forged = bytes.clone();
+        *forged.last_mut().unwrap() ^= 1;
+        assert!(install(&forged).is_err());
+    }
+
+    /// A finished pull installs the pack an offer promised, and only that
+    /// one: an offered file holding some other valid pack is dropped, not
+    /// installed behind the user's back.
+    #[test]
+    fn settle_installs_the_offered_pack_and_drops_an_impostor() {
+        let dir = std::env::temp_dir().join("promtuz-stickers-settle-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        let key = SigningKey::from_bytes(&[0x32u8; 32]);
+        let wanted = signed(&key, "dogs", vec![vec![7; 10]]);
+        let other = signed(&key, "owls", vec![vec![8; 10]]);
+        let wanted_id = open(&wanted).unwrap().pack_id();
+        let (conv, sender) = ([0x33u8; 16], [0x34u8; 32]);
+
+        let (bad_fid, good_fid) = ([0x35u8; 32], [0x36u8; 32]);
+        let bad_path = dir.join("bad.part").to_string_lossy().into_owned();
+        let good_path = dir.join("good.part").to_string_lossy().into_owned();
+        std::fs::write(&bad_path, &other).unwrap();
+        std::fs::write(&good_path, &wanted).unwrap();
+        sticker::offer_put(&bad_fid, &wanted_id, &sender, &conv, other.len() as u64).unwrap();
+        done_partial(bad_fid, &bad_path, other.len() as u64);
+
+        assert!(!settle(&wanted_id));
+        assert!(sticker::offer(&bad_fid).unwrap().is_none(), "impostor offer dropped");
+        assert!(!sticker::is_installed(&open(&other).unwrap().pack_id()));
+
+        sticker::offer_put(&good_fid, &wanted_id, &sender, &conv, wanted.len() as u64).unwrap();
+        done_partial(good_fid, &good_path, wanted.len() as u64);
+        assert!(settle(&wanted_id));
+        assert_eq!(frame(&wanted_id, 0).unwrap(), Some(vec![7; 10]));
+        assert!(sticker::offers_for(&wanted_id).unwrap().is_empty(), "offers cleared");
+    }
+
+    #[test]
+    fn asks_once_per_backoff_window() {
+        let map: Backoff = Mutex::new(HashMap::new());
+        assert!(due(&map, [1; 16], [2; 16]));
+        assert!(!due(&map, [1; 16], [2; 16]));
+        assert!(due(&map, [3; 16], [2; 16]), "another conversation asks separately");
+    }
+}
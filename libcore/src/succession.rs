@@ -0,0 +1,322 @@
+//! Identity key rotation. [`rotate`] retires our key for a fresh one; the old
+//! key signs the new one over in an [`IdentitySuccession`], which the new key
+//! signs too, so neither can be named without the other.
+//!
+//! - The connected relay sends the statement to the old key's homes. Those
+//!   hand what is queued for it to the new key's homes and drop its
+//!   KeyPackage and Welcome stashes (`relay/src/dht/succession.rs`).
+//! - Every contact and chat member gets it as a plain envelope. They move our
+//!   contact row, roster places and history to the new key ([`on_succession`])
+//!   and drop our pair group, which carries the old credential.
+//! - Our groups need us back under the new credential. A group we run is
+//!   founded again; any other asks its admin to let us back in, whose
+//!   [`crate::groups::readmit`] finds our old leaf by the recorded predecessor.
+//!   Both run through [`crate::rejoin`], as after a restore.
+//!
+//! Until everyone has it, [`announce`] re-sends the statement on each connect
+//! to whoever hasn't taken it. Dispatches signed to the old key are still
+//! accepted, and sealed ones still open, so nothing in flight is lost. A
+//! contact who never hears of it keeps writing to the old key, whose homes
+//! pass it on.
+//!
+//! A rotation is for a key that may be exposed, so whoever else holds it can
+//! name an heir too. A contact who gets a second statement for the same old
+//! key can't tell the owner from the thief: both are kept, neither heir is
+//! sent anything, and the user settles it out of band ([`settle`]). The old
+//! key's homes likewise hand its queue to neither.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use common::proto::client_rel::CRelayPacket;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::mls_wire::MlsEnvelopeP;
+use common::proto::pack::Packer;
+use log::info;
+use log::warn;
+
+use crate::data::contact::Contact;
+use crate::data::conversation::Conversation;
+use crate::data::conversation::KIND_GROUP;
+use crate::data::identity::Identity;
+use crate::mls::MlsGroupHandle;
+use crate::mls::PromtuzMlsProvider;
+use crate::state::RELAY;
+
+/// Retire our identity key for a new one. Everything local moves at once; the
+/// relay connection is dropped so the next one is made under the new key,
+/// which is where the succession is [`announce`]d.
+pub fn rotate() -> Result<()> {
+    let succession = Identity::rotate()?;
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    info!("SUCCESSION: rotated {} to {}", hex::encode(&old[..4]), hex::encode(&new[..4]));
+    move_local(&succession)?;
+
+    if let Some(conn) = RELAY.read().as_ref().and_then(|r| r.connection.clone()) {
+        conn.close(quinn::VarInt::from_u32(0), b"identity rotated");
+    }
+    Ok(())
+}
+
+/// Finish any rotation a crash cut short. Run at start-up, before anything is
+/// sent or received under the new key.
+pub(crate) fn resume_moves() {
+    for succession in Identity::unmoved_successions() {
+        if let Err(e) = move_local(&succession) {
+            warn!("SUCCESSION: finishing the move off a retired key failed: {e}");
+        }
+    }
+}
+
+/// Move what we hold under the retired key to the new one: rosters and
+/// history, pair groups, and the groups to rejoin. The identity itself has
+/// already moved. Every step is safe to repeat and the rotation is only marked
+/// moved after the last, so [`resume_moves`] can run it again from the top.
+fn move_local(succession: &IdentitySuccession) -> Result<()> {
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    for group in crate::data::succession::replace_member(&old, &new)? {
+        drop_group_state(&group);
+    }
+    crate::data::succession::unbind_pair_groups()?;
+    for row in Conversation::list() {
+        if row.kind != KIND_GROUP {
+            continue;
+        }
+        let group = Conversation::group_of(&row.id);
+        let provider = crate::mls::PromtuzMlsProvider::shared();
+        let suite = group.and_then(|g| crate::rejoin::suite_of(&provider, &g));
+        // An admin founds a successor and needs nothing of the old group; a
+        // member is Welcomed back into the same one, so holds none of it.
+        if !Conversation::is_admin(&row.id, &new)
+            && let Some(group) = group
+        {
+            drop_group_state(&group);
+        }
+        crate::data::rejoin::mark_needed(&row.id, suite)?;
+    }
+    Identity::mark_moved(&old);
+    Ok(())
+}
+
+/// Publish every succession not yet settled, to our relay and to everyone we
+/// talk to who hasn't taken it yet, and split our recovery shares anew. Run
+/// on connect, ahead of [`crate::rejoin::resume`]: an admin who hasn't heard
+/// of the new key won't let it in.
+pub(crate) async fn announce() {
+    for (succession, reshared) in Identity::unsettled_successions() {
+        let old = succession.old_ipk.0;
+        if let Err(e) = publish(&succession).await {
+            warn!("SUCCESSION: relay publish failed: {e}");
+        }
+        let told = Identity::told(&old);
+        let mut told_all = true;
+        for to in audience(&succession).into_iter().filter(|to| !told.contains(to)) {
+            let envelope = MlsEnvelopeP::Succession(succession.clone());
+            match crate::messaging::send_plain_envelope(to, envelope, true).await {
+                Ok(()) => Identity::mark_told(&old, &to),
+                Err(e) => {
+                    warn!("SUCCESSION: not sent to {}: {e}", hex::encode(&to[..4]));
+                    told_all = false;
+                },
+            }
+        }
+        if told_all {
+            Identity::mark_announced(&old);
+        }
+        // The old shares rebuild the retired key, so they go whether or not
+        // every contact has heard yet.
+        if !reshared && reshare().await {
+            Identity::mark_reshared(&old);
+        }
+    }
+}
+
+/// Hand the statement to the connected relay, which takes it to the old key's
+/// homes. Fire-and-forget, like the other publishes on connect.
+async fn publish(succession: &IdentitySuccession) -> Result<()> {
+    let bytes = CRelayPacket::PublishSuccession(succession.clone())
+        .pack()
+        .map_err(|e| anyhow!("pack publish_succession: {e}"))?;
+    let conn = {
+        let relay = RELAY.read();
+        relay.as_ref().and_then(|r| r.connection.clone())
+    };
+    let Some(conn) = conn else { return Ok(()) };
+    if let Ok((mut tx, _rx)) = conn.open_bi().await {
+        let _ = tx.write_all(&bytes).await;
+        let _ = tx.finish();
+    }
+    Ok(())
+}
+
+/// Every contact and every member of a chat we're in, once each.
+fn audience(succession: &IdentitySuccession) -> BTreeSet<[u8; 32]> {
+    let ours = [succession.old_ipk.0, succession.new_ipk.0];
+    Contact::list()
+        .into_iter()
+        .map(|c| c.ipk)
+        .chain(Conversation::list().iter().flat_map(|c| Conversation::recipients(&c.id)))
+        .filter(|ipk| !ours.contains(ipk))
+        .collect()
+}
+
+/// Our recovery shares recover the key we retired. Split the new one across
+/// the same holders, which revokes the old shares. Returns whether that is
+/// done, or there were none.
+async fn reshare() -> bool {
+    let holders = crate::social_recovery::holders();
+    let Some(threshold) = holders.first().map(|h| h.threshold) else { return true };
+    let holders = holders.into_iter().map(|h| h.holder).collect();
+    match crate::social_recovery::distribute(holders, threshold).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("SUCCESSION: recovery shares not renewed: {e}");
+            false
+        },
+    }
+}
+
+/// A contact or chat member moved to a new key. Accepted only from that new
+/// key, signed by both, for an old key we know; a repeat is a no-op. One
+/// naming another heir than the recorded one contests the old key.
+pub(crate) fn on_succession(sender_ipk: [u8; 32], succession: IdentitySuccession) -> Result<()> {
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    let our_ipk = Identity::get().ok_or_else(|| anyhow!("identity not found"))?.ipk();
+    let recorded = crate::data::succession::successor(&old);
+    match judge(&our_ipk, &sender_ipk, &succession, recorded)? {
+        Verdict::Known => return Ok(()),
+        Verdict::Contest(heir) => {
+            crate::data::succession::contest(&succession)?;
+            warn!(
+                "SUCCESSION: {} named {} after {}; both held for the user",
+                hex::encode(&old[..4]),
+                hex::encode(&new[..4]),
+                hex::encode(&heir[..4])
+            );
+            return Ok(());
+        },
+        Verdict::Record => {},
+    }
+    if !Contact::exists(&old) && !Conversation::shares_a_chat_with(&old) {
+        bail!("succession for a key we don't know");
+    }
+
+    crate::data::succession::record(&succession)?;
+    for group in crate::data::succession::replace_member(&old, &new)? {
+        drop_group_state(&group);
+    }
+    info!("SUCCESSION: {} is now {}", hex::encode(&old[..4]), hex::encode(&new[..4]));
+    Ok(())
+}
+
+/// What [`on_succession`] makes of a statement, given the heir already
+/// `recorded` for its old key.
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    /// The heir recorded; nothing to do.
+    Known,
+    /// Another heir than the recorded one, which is named.
+    Contest([u8; 32]),
+    /// The first heir named.
+    Record,
+}
+
+fn judge(
+    our_ipk: &[u8; 32], sender_ipk: &[u8; 32], succession: &IdentitySuccession,
+    recorded: Option<[u8; 32]>,
+) -> Result<Verdict> {
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    if new != *sender_ipk {
+        bail!("succession not sent by its new key");
+    }
+    if !succession.verify() {
+        bail!("succession signature invalid");
+    }
+    if old == *our_ipk || new == *our_ipk {
+        bail!("succession names our own key");
+    }
+    Ok(match recorded {
+        Some(heir) if heir == new => Verdict::Known,
+        Some(heir) => Verdict::Contest(heir),
+        None => Verdict::Record,
+    })
+}
+
+/// The user checked with the contact that `heir` is who `old` became. The
+/// other heir is forgotten; if it held the contact's place, `heir` takes it.
+pub fn settle(old: &[u8; 32], heir: &[u8; 32]) -> Result<()> {
+    if let Some(taken) = crate::data::succession::settle(old, heir)? {
+        for group in crate::data::succession::replace_member(&taken, heir)? {
+            drop_group_state(&group);
+        }
+    }
+    info!("SUCCESSION: {} settled on {}", hex::encode(&old[..4]), hex::encode(&heir[..4]));
+    Ok(())
+}
+
+/// Delete our local state for `group`. Nothing sends in it again.
+fn drop_group_state(group: &[u8; 32]) {
+    let provider = PromtuzMlsProvider::shared();
+    match MlsGroupHandle::load(&provider, group) {
+        Ok(Some(mut handle)) => {
+            if let Err(e) = handle.delete(&provider) {
+                warn!("SUCCESSION: dropping group {} failed: {e}", hex::encode(&group[..4]));
+            }
+        },
+        Ok(None) => {},
+        Err(e) => warn!("SUCCESSION: loading group {} failed: {e}", hex::encode(&group[..4])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::dht_p2p::identity_succession_signing_input;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+
+    fn succession(old: &SigningKey, new: &SigningKey) -> IdentitySuccession {
+        let (old_ipk, new_ipk) = (old.verifying_key().to_bytes(), new.verifying_key().to_bytes());
+        let input = identity_succession_signing_input(&old_ipk, &new_ipk, 1_000);
+        IdentitySuccession {
+            old_ipk:   old_ipk.into(),
+            new_ipk:   new_ipk.into(),
+            timestamp: 1_000,
+            old_sig:   old.sign(&input).to_bytes().into(),
+            new_sig:   new.sign(&input).to_bytes().into(),
+        }
+    }
+
+    #[test]
+    fn a_second_heir_for_the_same_key_contests_it() {
+        let us = [9u8; 32];
+        let old = SigningKey::from_bytes(&[1; 32]);
+        let (owner, thief) = (SigningKey::from_bytes(&[2; 32]), SigningKey::from_bytes(&[3; 32]));
+        let (first, second) = (succession(&old, &thief), succession(&old, &owner));
+        let (thief_ipk, owner_ipk) = (first.new_ipk.0, second.new_ipk.0);
+
+        assert_eq!(judge(&us, &thief_ipk, &first, None).unwrap(), Verdict::Record);
+        assert_eq!(judge(&us, &thief_ipk, &first, Some(thief_ipk)).unwrap(), Verdict::Known);
+        assert_eq!(
+            judge(&us, &owner_ipk, &second, Some(thief_ipk)).unwrap(),
+            Verdict::Contest(thief_ipk),
+            "the later statement isn't dropped just for being later"
+        );
+    }
+
+    #[test]
+    fn a_succession_must_come_from_its_heir_and_not_name_us() {
+        let old = SigningKey::from_bytes(&[1; 32]);
+        let new = SigningKey::from_bytes(&[2; 32]);
+        let s = succession(&old, &new);
+        let (old_ipk, new_ipk) = (s.old_ipk.0, s.new_ipk.0);
+
+        assert!(judge(&[9; 32], &old_ipk, &s, None).is_err(), "relayed by the old key");
+        assert!(judge(&old_ipk, &new_ipk, &s, None).is_err(), "our own key retired");
+        let forged = IdentitySuccession { timestamp: 2_000, ..s };
+        assert!(judge(&[9; 32], &new_ipk, &forged, None).is_err());
+    }
+}
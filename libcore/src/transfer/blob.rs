@@ -0,0 +1,417 @@
+//! Relay blob-store fallback: an encrypted copy of an attachment parked on
+//! the sender's relay, for when no device holding the file is reachable.
+//!
+//! After an attachment is sent, the sender encrypts it chunk by chunk under
+//! a fresh key (ChaCha20-Poly1305, nonce = chunk index, AAD = `file_id`) and
+//! uploads the ciphertext to its own relay, if that relay carries
+//! `BLOB_STORE`. The blob is content-addressed over the ciphertext, so the
+//! relay checks every chunk without being able to read one. The key and the
+//! blob's location then go to the conversation in `AppPayload::FileBlob`.
+//!
+//! A receiver whose [`super::download`] finds no reachable source fetches
+//! the blob through its own relay, which proxies to the host over the DHT.
+//! Chunks land in a staging file beside the partial, never in its bitmap:
+//! the AEAD only proves who sealed a chunk, so the file is first checked
+//! against `file_id` whole, and only then becomes the finished partial. A
+//! blob that fails the check is forgotten along with its locator.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use chacha20poly1305::ChaCha20Poly1305;
+use chacha20poly1305::Nonce;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::aead::Payload;
+use common::proto::blob::BlobBeginOutcome;
+use common::proto::blob::BlobManifest;
+use common::proto::blob::chunk_hash;
+use common::proto::client_rel::CRelayPacket;
+use common::proto::client_rel::SRelayPacket;
+use common::proto::mls_wire::AppPayload;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use common::types::bytes::ByteVec;
+use common::types::bytes::Bytes;
+use ed25519_dalek::ed25519::signature::rand_core::OsRng;
+use ed25519_dalek::ed25519::signature::rand_core::RngCore;
+use parking_lot::Mutex;
+
+use super::store;
+use super::wire::CHUNK_SIZE;
+use super::wire::ChunkSet;
+use crate::state::RELAY;
+
+/// Files above this stay P2P-only: parking them would eat most of a
+/// relay's per-uploader quota.
+const UPLOAD_MAX: u64 = 256 * 1024 * 1024;
+
+/// Lifetime asked of the relay, matching sender retention; the relay may
+/// cap it lower and says so in its answer.
+const BLOB_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Chunk requests in flight to the relay at once, either direction.
+const BLOB_STREAMS: usize = 4;
+
+/// Poly1305 tag appended to every ciphertext chunk.
+const TAG_LEN: usize = 16;
+
+/// Park an encrypted copy of our retained `file_id` on our relay and tell
+/// `conversation` where it is. Background and best-effort: a relay without
+/// `BLOB_STORE`, or no relay at all, leaves the file P2P-only.
+pub fn publish(conversation: [u8; 16], file_id: [u8; 32]) {
+    crate::RUNTIME.spawn(async move {
+        if let Err(e) = upload(conversation, file_id).await {
+            log::warn!("transfer: blob upload of {} failed: {e}", hex::encode(&file_id[..4]));
+        }
+    });
+}
+
+async fn upload(conversation: [u8; 16], file_id: [u8; 32]) -> Result<()> {
+    let Some(host) = RELAY.read().as_ref().and_then(|r| r.home_node_id) else {
+        return Ok(()); // no DHT on our relay: nobody else could fetch from it
+    };
+    let ret = store::retention_get(&file_id).ok_or_else(|| anyhow!("file no longer retained"))?;
+    if ret.size == 0 || ret.size > UPLOAD_MAX {
+        return Ok(());
+    }
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    let path = ret.path.clone();
+    let manifest =
+        tokio::task::spawn_blocking(move || sealed_manifest(&path, &key, &file_id)).await??;
+    let blob_id = manifest.blob_id();
+
+    let begin = CRelayPacket::BlobBegin { manifest, ttl_secs: BLOB_TTL_SECS };
+    let (missing, expires_at) = match request(begin).await? {
+        SRelayPacket::BlobBegun {
+            outcome: BlobBeginOutcome::Accepted { missing, expires_at },
+            ..
+        } => (missing, expires_at),
+        SRelayPacket::BlobBegun { outcome, .. } => bail!("relay refused the blob: {outcome:?}"),
+        SRelayPacket::BlobUnavailable => return Ok(()),
+        other => bail!("BlobBegin: unexpected variant {other:?}"),
+    };
+
+    let queue = Arc::new(Mutex::new(VecDeque::from(missing)));
+    let mut workers = tokio::task::JoinSet::new();
+    for _ in 0..BLOB_STREAMS {
+        let (queue, path) = (queue.clone(), ret.path.clone());
+        workers.spawn(put_chunks(queue, path, key, file_id, blob_id));
+    }
+    while let Some(joined) = workers.join_next().await {
+        joined??;
+    }
+
+    let payload = AppPayload::FileBlob { file_id, host, blob_id, key, expires_at };
+    crate::messaging::send_control(conversation, payload).await
+}
+
+/// One upload worker: seal and store chunks off the shared queue until it is
+/// empty.
+async fn put_chunks(
+    queue: Arc<Mutex<VecDeque<u32>>>, path: String, key: [u8; 32], file_id: [u8; 32],
+    blob_id: [u8; 32],
+) -> Result<()> {
+    loop {
+        let Some(index) = queue.lock().pop_front() else { return Ok(()) };
+        let path = path.clone();
+        let data = tokio::task::spawn_blocking(move || sealed_chunk(&path, &key, &file_id, index))
+            .await??;
+        let put = CRelayPacket::BlobPut { blob_id: Bytes(blob_id), index, data: ByteVec(data) };
+        match request(put).await? {
+            SRelayPacket::BlobStored { stored: true, .. } => {},
+            other => bail!("chunk {index} not stored: {other:?}"),
+        }
+    }
+}
+
+/// Pull `file_id` from the relay blob `loc` names into the receiver partial.
+/// `offered_size` bounds the blob as it bounds a P2P manifest; the finished
+/// file must hash back to `file_id` before the partial sees any of it.
+pub(super) async fn fetch(
+    file_id: [u8; 32], sender: [u8; 32], offered_size: u64, loc: &store::BlobLocator,
+) -> Result<()> {
+    let host = NodeId::from_bytes(loc.host);
+    let want = CRelayPacket::FetchBlobManifest { host, blob_id: Bytes(loc.blob_id) };
+    let manifest = match request(want).await? {
+        SRelayPacket::BlobManifest { manifest: Some(m), .. } => m,
+        SRelayPacket::BlobManifest { manifest: None, .. } => bail!("blob is gone"),
+        other => bail!("FetchBlobManifest: unexpected variant {other:?}"),
+    };
+    // The same fail-closed checks a P2P manifest gets: it must be the blob we
+    // were told about, and exactly the sealed form of the offered size.
+    anyhow::ensure!(manifest.blob_id() == loc.blob_id, "manifest does not match blob_id");
+    let n = offered_size.div_ceil(CHUNK_SIZE as u64);
+    anyhow::ensure!(
+        manifest.is_well_formed()
+            && manifest.chunk_size as usize == CHUNK_SIZE + TAG_LEN
+            && manifest.chunks.len() as u64 == n
+            && manifest.total_size == offered_size + n * TAG_LEN as u64,
+        "blob does not fit the offered size"
+    );
+
+    let staging = format!("{}.blob", store::partial_path(&file_id));
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&staging)?;
+    file.set_len(offered_size)?;
+    let state = Arc::new(FetchState {
+        file_id,
+        host,
+        blob_id: loc.blob_id,
+        key: loc.key,
+        queue: Mutex::new((0..n as u32).collect()),
+        staged: Mutex::new(Staged { got: ChunkSet::empty(n as u32), file }),
+        manifest,
+    });
+    let mut workers = tokio::task::JoinSet::new();
+    for _ in 0..BLOB_STREAMS {
+        let state = state.clone();
+        workers.spawn(async move { state.drain().await });
+    }
+    let mut last_err = None;
+    while let Some(joined) = workers.join_next().await {
+        if let Ok(Err(e)) = joined {
+            last_err = Some(e);
+        }
+    }
+    let complete = state.staged.lock().got.complement().is_empty();
+    let r = if complete {
+        commit(&state, sender, offered_size, &staging).await
+    } else {
+        Err(last_err.unwrap_or_else(|| anyhow!("blob fetch stopped short")))
+    };
+    if r.is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+    r
+}
+
+/// Promote a fully staged blob to the finished partial once it hashes back
+/// to `file_id`. A blob that does not is a lie from whoever sealed it: its
+/// locator is dropped so no later pull fetches it again.
+async fn commit(state: &FetchState, sender: [u8; 32], size: u64, staging: &str) -> Result<()> {
+    state.staged.lock().file.sync_all()?;
+    // AEAD already ties each chunk to the sender's key; this ties the whole
+    // to the file they offered, and gives us a manifest to serve it by.
+    let path = staging.to_owned();
+    let manifest =
+        tokio::task::spawn_blocking(move || super::wire::Manifest::from_file(&path)).await??;
+    if manifest.file_id() != state.file_id {
+        store::blob_locator_forget(&state.file_id);
+        bail!("decrypted blob does not match file_id");
+    }
+    let path = store::partial_path(&state.file_id);
+    std::fs::rename(staging, &path)?;
+    let n = manifest.chunks.len() as u32;
+    let got = ChunkSet::prefix(n, n);
+    store::partial_put(&store::Partial {
+        file_id: state.file_id,
+        source_ipk: sender,
+        total: size,
+        chunk_size: CHUNK_SIZE as u32,
+        manifest: Some(postcard::to_allocvec(&manifest)?),
+        have: n,
+        bitmap: Some(got.as_bytes().to_vec()),
+        state: store::DONE,
+        path,
+        updated_at: crate::utils::systime().as_secs(),
+    })?;
+    Ok(())
+}
+
+/// One [`fetch`]: the blob's ciphertext layout plus what is still wanted.
+struct FetchState {
+    file_id: [u8; 32],
+    host: NodeId,
+    blob_id: [u8; 32],
+    key: [u8; 32],
+    manifest: BlobManifest,
+    queue: Mutex<VecDeque<u32>>,
+    staged: Mutex<Staged>,
+}
+
+/// Opened chunks so far, and the staging file they are written to.
+struct Staged {
+    got: ChunkSet,
+    file: std::fs::File,
+}
+
+impl FetchState {
+    /// Fetch, verify, open and stage chunks until the queue is empty. A chunk
+    /// that fails goes back for the other workers and stops this one.
+    async fn drain(&self) -> Result<()> {
+        loop {
+            let Some(index) = self.queue.lock().pop_front() else { return Ok(()) };
+            if let Err(e) = self.fetch_chunk(index).await {
+                self.queue.lock().push_back(index);
+                return Err(e);
+            }
+        }
+    }
+
+    async fn fetch_chunk(&self, index: u32) -> Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+        let want =
+            CRelayPacket::FetchBlobChunk { host: self.host, blob_id: Bytes(self.blob_id), index };
+        let data = match request(want).await? {
+            SRelayPacket::BlobChunk { data: Some(data), index: i, .. } if i == index => data.0,
+            SRelayPacket::BlobChunk { data: None, .. } => bail!("chunk {index} is gone"),
+            other => bail!("FetchBlobChunk: unexpected variant {other:?}"),
+        };
+        anyhow::ensure!(self.manifest.verifies(index, &data), "chunk {index} hash mismatch");
+        let plain = open_chunk(&self.key, &self.file_id, index, &data)?;
+        let mut staged = self.staged.lock();
+        staged.file.seek(SeekFrom::Start(index as u64 * CHUNK_SIZE as u64))?;
+        staged.file.write_all(&plain)?;
+        staged.got.insert(index);
+        Ok(())
+    }
+}
+
+/// Handle a member's `FileBlob`: remember where the file is parked if they
+/// are who offered it to us, and re-drive a pull that is parked on an
+/// unreachable sender. Anyone else could only point us at a blob of their
+/// own choosing.
+pub fn on_file_blob(peer: [u8; 32], file_id: [u8; 32], loc: store::BlobLocator) {
+    if !super::offer_of(&file_id).is_ok_and(|o| o.is_some_and(|o| o.sender == peer))
+        || loc.expires_at <= crate::utils::systime().as_millis() as u64
+    {
+        return;
+    }
+    if let Err(e) = store::blob_locator_put(&file_id, &loc) {
+        log::warn!("transfer: could not record a blob locator: {e}");
+        return;
+    }
+    if store::partial_get(&file_id).is_some_and(|p| p.state == store::HELD) {
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = super::download(file_id).await {
+                log::warn!("transfer: pull of {} failed: {e}", hex::encode(&file_id[..4]));
+            }
+        });
+    }
+}
+
+/// One request/reply on our relay connection.
+async fn request(packet: CRelayPacket) -> Result<SRelayPacket> {
+    let conn = RELAY.read().as_ref().and_then(|r| r.connection.clone());
+    let conn = conn.ok_or_else(|| anyhow!("not connected to a relay"))?;
+    let bytes = packet.pack().map_err(|e| anyhow!("pack blob request: {e}"))?;
+    let (mut tx, mut rx) = conn.open_bi().await?;
+    tx.write_all(&bytes).await?;
+    tx.finish()?;
+    Ok(SRelayPacket::unpack(&mut rx).await?)
+}
+
+/// The blob manifest of `path` sealed under `key`: one pass that encrypts
+/// each chunk only to hash it.
+fn sealed_manifest(path: &str, key: &[u8; 32], file_id: &[u8; 32]) -> Result<BlobManifest> {
+    let size = std::fs::metadata(path)?.len();
+    let n = size.div_ceil(CHUNK_SIZE as u64) as u32;
+    let chunks = (0..n)
+        .map(|index| sealed_chunk(path, key, file_id, index).map(|c| chunk_hash(&c)))
+        .collect::<Result<_>>()?;
+    Ok(BlobManifest {
+        total_size: size + n as u64 * TAG_LEN as u64,
+        chunk_size: (CHUNK_SIZE + TAG_LEN) as u32,
+        chunks,
+    })
+}
+
+/// Chunk `index` of `path`, sealed. Deterministic for a given key, so the
+/// upload re-derives exactly the ciphertext the manifest hashed.
+fn sealed_chunk(path: &str, key: &[u8; 32], file_id: &[u8; 32], index: u32) -> Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut f = std::fs::File::open(path)?;
+    f.seek(SeekFrom::Start(index as u64 * CHUNK_SIZE as u64))?;
+    let mut plain = Vec::with_capacity(CHUNK_SIZE);
+    f.take(CHUNK_SIZE as u64).read_to_end(&mut plain)?;
+    ChaCha20Poly1305::new(key.into())
+        .encrypt(&chunk_nonce(index), Payload { msg: &plain, aad: file_id })
+        .map_err(|_| anyhow!("seal chunk {index}"))
+}
+
+fn open_chunk(key: &[u8; 32], file_id: &[u8; 32], index: u32, sealed: &[u8]) -> Result<Vec<u8>> {
+    ChaCha20Poly1305::new(key.into())
+        .decrypt(&chunk_nonce(index), Payload { msg: sealed, aad: file_id })
+        .map_err(|_| anyhow!("chunk {index} fails to open"))
+}
+
+/// The key is single-use, so the chunk index alone keeps nonces unique.
+fn chunk_nonce(index: u32) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..4].copy_from_slice(&index.to_le_bytes());
+    nonce.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_chunks_match_their_manifest_and_open_only_in_place() {
+        let path = std::env::temp_dir().join("promtuz-blob-seal.bin");
+        std::fs::write(&path, vec![0x5au8; CHUNK_SIZE + 100]).unwrap();
+        let path = path.to_str().unwrap();
+        let (key, file_id) = ([7u8; 32], [9u8; 32]);
+
+        let manifest = sealed_manifest(path, &key, &file_id).unwrap();
+        assert!(manifest.is_well_formed());
+        assert_eq!(manifest.total_size, (CHUNK_SIZE + 100 + 2 * TAG_LEN) as u64);
+
+        let last = sealed_chunk(path, &key, &file_id, 1).unwrap();
+        assert!(manifest.verifies(1, &last));
+        assert_eq!(open_chunk(&key, &file_id, 1, &last).unwrap(), vec![0x5au8; 100]);
+        assert!(open_chunk(&key, &file_id, 0, &last).is_err(), "bound to its index");
+        assert!(open_chunk(&key, &[8u8; 32], 1, &last).is_err(), "bound to its file");
+    }
+
+    #[test]
+    fn only_the_offering_sender_can_point_at_a_blob() {
+        let dir = std::env::temp_dir().join("promtuz-blob-locator-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) };
+
+        let (sender, member) = ([0xb1u8; 32], [0xb2u8; 32]);
+        let file_id = [0xb3u8; 32];
+        let row = crate::data::media::MediaRow {
+            kind:     crate::data::media::KIND_ATTACHMENT,
+            group_id: None,
+            mime:     "application/octet-stream".into(),
+            name:     "f.bin".into(),
+            size:     1,
+            width:    0,
+            height:   0,
+            blob:     None,
+            thumb:    None,
+            file_id:  Some(file_id.to_vec()),
+            duration_ms: 0,
+            waveform: None,
+            sticker_pack: None,
+            sticker_id: 0,
+        };
+        let conv = crate::data::conversation::Conversation::for_peer(&sender).unwrap();
+        crate::data::media::save_incoming_with_media(&conv, &sender, &[0xb4; 16], "", 1, None, &row)
+            .unwrap();
+
+        let loc = store::BlobLocator {
+            host:       [1u8; 32],
+            blob_id:    [2u8; 32],
+            key:        [3u8; 32],
+            expires_at: 1 << 60,
+        };
+        on_file_blob(member, file_id, loc.clone());
+        assert_eq!(store::blob_locator_get(&file_id, 0), None, "a member cannot repoint it");
+        on_file_blob(sender, file_id, loc.clone());
+        assert_eq!(store::blob_locator_get(&file_id, 0), Some(loc));
+        store::blob_locator_forget(&file_id);
+        assert_eq!(store::blob_locator_get(&file_id, 0), None);
+    }
+}
@@ -1,13 +1,24 @@
 //! P2P attachment transfer: chunked-manifest protocol for files too big for
 //! the inline `Image` message (>256KB), carried over a direct link from
 //! [`crate::p2p`] rather than the store-and-forward relay.
+//!
+//! A receiver pulls any subset of chunks by bitmap, over several streams at
+//! once, from the original sender and from any member that completed its own
+//! download and announced it (`AppPayload::FileHave`). Every chunk is checked
+//! against the BLAKE3 [`wire::Manifest`] whichever peer served it, so a large
+//! attachment in a group no longer hinges on the sender staying online.
+//! When nobody holding it is reachable at all, the receiver falls back to
+//! the encrypted copy the sender parked on its relay ([`blob`]).
 
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Arc;
 
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 
 pub mod auth;
+pub mod blob;
 pub mod store;
 pub mod wire;
 
@@ -21,14 +32,32 @@ const DEAD_PARTIAL_TTL_SECS: u64 = 7 * 24 * 60 * 60;
 /// reverse-wake if the held partial hasn't been poked within this window.
 const WAKE_BACKOFF_SECS: u64 = 60;
 
+/// Sources (the origin included) a single pull dials.
+const MAX_PULL_SOURCES: usize = 4;
+
+/// Concurrent streams per source during a pull.
+const STREAMS_PER_SOURCE: usize = 2;
+
+/// Chunks requested per stream round-trip: 4 MiB at the default chunk size.
+const CHUNKS_PER_STREAM: usize = 16;
+
+/// Chunks per round-trip when pulling for playback. Small batches keep every
+/// stream working near the front of the queue, so the verified prefix grows
+/// steadily instead of in 4 MiB steps scattered across the file.
+const PLAYBACK_CHUNKS_PER_STREAM: usize = 2;
+
 /// Periodic housekeeping. Drops sender retention rows whose TTL has passed —
 /// a DB-row delete ONLY: the retained `path` is the user's own source file
 /// (the photo/document they chose to send) and is never unlinked. Then reaps
 /// abandoned receiver partials, unlinking only their junk `.part` bytes; a
-/// delivered `DONE` partial (the file the user keeps) is spared.
+/// delivered `DONE` partial (the file the user keeps) is spared. Source
+/// announcements age out on the same clock; blob locators once their blob
+/// has expired.
 pub fn gc(now: u64) {
     let _ = store::retention_gc(now);
     let _ = store::gc_dead_partials(now.saturating_sub(DEAD_PARTIAL_TTL_SECS));
+    let _ = store::sources_gc(now.saturating_sub(DEAD_PARTIAL_TTL_SECS));
+    let _ = store::blob_locators_gc(now.saturating_mul(1000));
 }
 
 /// Whether to pull an offered attachment without a user tap: only from a paired
@@ -38,6 +67,21 @@ pub fn should_auto_download(ipk: &[u8; 32], size: u64, on_wifi: bool) -> bool {
     on_wifi && size <= AUTO_MAX && crate::data::contact::Contact::is_paired(ipk)
 }
 
+/// Bytes of a partial a player may read from the start: the gap-free run of
+/// verified chunks, or the whole file once DONE. Anything past it is the
+/// pre-sized file's zero fill.
+pub fn playable_bytes(p: &store::Partial) -> u64 {
+    if p.state == store::DONE {
+        return p.total;
+    }
+    let n = p.total.div_ceil(p.chunk_size.max(1) as u64) as u32;
+    let leading = match &p.bitmap {
+        Some(bits) => wire::ChunkSet::from_bytes(n, bits.clone()).map_or(0, |s| s.leading()),
+        None => p.have.min(n),
+    };
+    (leading as u64 * p.chunk_size as u64).min(p.total)
+}
+
 /// Builds the manifest for `path`, retains it (and the source location) so we
 /// keep serving pulls until `ttl_secs` elapses, and returns the offer's
 /// `(file_id, size)`.
@@ -50,13 +94,43 @@ pub fn prepare_send(path: &str, ttl_secs: u64) -> anyhow::Result<([u8; 32], u64)
     Ok((file_id, size))
 }
 
+/// Keep serving `file_id`, which we already hold, for at least `ttl_secs` more
+/// — a forward hands it to people who will pull it from us. Our own retained
+/// source just has its expiry pushed out; a download we completed gets a
+/// retention row of its own, so it can be parked on the relay for them too.
+/// Errors when we hold no complete copy: there'd be nothing to serve.
+pub fn retain(file_id: &[u8; 32], ttl_secs: u64) -> anyhow::Result<()> {
+    let expires = crate::utils::systime().as_secs() + ttl_secs;
+    if let Some(ret) = store::retention_get(file_id) {
+        if ret.expires_at < expires {
+            store::retention_put(
+                file_id,
+                &ret.path,
+                ret.size,
+                ret.chunk_size,
+                &ret.manifest,
+                expires,
+            )?;
+        }
+        return Ok(());
+    }
+    let p = store::partial_get(file_id)
+        .filter(|p| p.state == store::DONE)
+        .ok_or_else(|| anyhow::anyhow!("not downloaded; nothing to forward"))?;
+    let manifest = p.manifest.ok_or_else(|| anyhow::anyhow!("download has no manifest"))?;
+    store::retention_put(file_id, &p.path, p.total, p.chunk_size, &manifest, expires)?;
+    Ok(())
+}
+
 /// Answer pulls over `link` until the peer stops opening streams: read one
 /// [`wire::Pull`] per bi-stream, then either reply [`wire::ServeResp::Gone`]
-/// (we no longer retain it) or frame the [`wire::Manifest`] and stream the
-/// requested chunk bytes raw from `have` to EOF.
+/// (we hold no complete copy, or it wasn't shared with them) or frame the
+/// [`wire::Manifest`] and stream the requested chunks raw, ascending.
 ///
 /// The framed manifest is the only length-delimited part; the chunk bytes ride
 /// after it unframed, since the puller sizes and counts them from that manifest.
+/// Each stream is served on its own task, so a puller's parallel streams
+/// actually run in parallel.
 ///
 /// Every stream starts with the mutual [`auth`] handshake pinning the peer's
 /// IPK to this connection's TLS key; a stream that fails it is dropped before
@@ -72,19 +146,22 @@ pub async fn serve_link(link: crate::p2p::PeerLink) {
     serve_streams(link, local).await
 }
 
-/// Whether we offered `file_id` to `peer`. Retention is keyed by content hash
-/// alone, so the outgoing message row is what scopes a pull — and its
-/// `Manifest`/`Gone` answer — to who the file was actually sent to. In a group
-/// that is every active member of the conversation it was posted in, not one
-/// contact.
-fn offered_to(file_id: &[u8; 32], peer: &[u8; 32]) -> bool {
+/// Whether `file_id` was shared with `peer`. Retention and partials are keyed
+/// by content hash alone, so the message row is what scopes a pull — and its
+/// `Manifest`/`Gone` answer — to who the file was actually posted to: every
+/// active member of a conversation it appeared in, whether we sent it or
+/// received it and now serve our completed copy. A sticker pack file counts
+/// as shared wherever one of its stickers was — but only the file of a pack
+/// we installed, whose id we hashed ourselves. An offer is a member's claim
+/// and never widens who may pull.
+fn shared_with(file_id: &[u8; 32], peer: &[u8; 32]) -> bool {
     let db = crate::db::messages::MESSAGES_DB.lock();
     db.query_row(
         "SELECT 1 FROM message_media mm
-           JOIN messages m
-             ON m.conversation_id = mm.conversation_id AND m.dispatch_id = mm.dispatch_id
            JOIN conversation_members cm ON cm.conversation_id = mm.conversation_id
-          WHERE mm.file_id = ?1 AND cm.member_ipk = ?2 AND cm.active = 1 AND m.outgoing = 1
+          WHERE (mm.file_id = ?1
+                 OR mm.sticker_pack IN (SELECT pack_id FROM sticker_packs WHERE file_id = ?1))
+            AND cm.member_ipk = ?2 AND cm.active = 1
           LIMIT 1",
         rusqlite::params![file_id.as_slice(), peer.as_slice()],
         |_| Ok(()),
@@ -92,61 +169,96 @@ fn offered_to(file_id: &[u8; 32], peer: &[u8; 32]) -> bool {
     .is_ok()
 }
 
+/// A complete local copy we can serve chunks from.
+struct Servable {
+    path: String,
+    manifest: wire::Manifest,
+}
+
+/// Our own retained source file, else a download we completed. A stored
+/// manifest that won't decode is our corruption, not the peer's; it reads as
+/// nothing to serve.
+fn servable(file_id: &[u8; 32]) -> Option<Servable> {
+    let (path, manifest) = match store::retention_get(file_id) {
+        Some(ret) => (ret.path, ret.manifest),
+        None => {
+            let p = store::partial_get(file_id).filter(|p| p.state == store::DONE)?;
+            (p.path, p.manifest?)
+        },
+    };
+    match postcard::from_bytes(&manifest) {
+        Ok(manifest) => Some(Servable { path, manifest }),
+        Err(e) => {
+            log::warn!("transfer: undecodable stored manifest: {e}");
+            None
+        },
+    }
+}
+
 /// [`serve_link`] minus the process-global identity, so a test can drive two
 /// in-process endpoints with distinct constructed identities.
 async fn serve_streams(link: crate::p2p::PeerLink, local: wire::Auth) {
     loop {
-        let (mut s, mut r) = match link.accept_stream().await {
+        let (s, r) = match link.accept_stream().await {
             Ok(x) => x,
             Err(_) => break,
         };
-        if let Err(e) = auth::exchange(&link.conn, &mut s, &mut r, link.ipk, &local).await {
-            log::warn!("transfer: stream auth failed: {e}");
-            continue;
-        }
-        let pull: wire::Pull = match wire::read_frame(&mut r).await {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
-        let retained =
-            store::retention_get(&pull.file_id).filter(|_| offered_to(&pull.file_id, &link.ipk));
-        match retained {
-            None => {
-                let _ = wire::write_frame(&mut s, &wire::ServeResp::Gone).await;
-                let _ = s.finish();
-            },
-            Some(ret) => {
-                // A stored manifest that won't decode is our corruption, not the
-                // peer's; treat it as gone rather than panic this detached loop.
-                let manifest: wire::Manifest = match postcard::from_bytes(&ret.manifest) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        log::warn!("transfer: undecodable retained manifest: {e}");
-                        let _ = wire::write_frame(&mut s, &wire::ServeResp::Gone).await;
-                        let _ = s.finish();
-                        continue;
-                    },
-                };
-                let _ = wire::write_frame(&mut s, &wire::ServeResp::Manifest(manifest)).await;
-                if let Ok(mut f) = std::fs::File::open(&ret.path) {
-                    use std::io::{Read, Seek, SeekFrom};
-                    let _ = f.seek(SeekFrom::Start(pull.have as u64 * ret.chunk_size as u64));
-                    let mut buf = vec![0u8; ret.chunk_size as usize];
-                    loop {
-                        let n = match f.read(&mut buf) {
-                            Ok(0) => break,
-                            Ok(n) => n,
-                            Err(_) => break,
-                        };
-                        if s.write_all(&buf[..n]).await.is_err() {
-                            break;
-                        }
-                    }
-                }
-                let _ = s.finish();
-            },
+        tokio::spawn(serve_stream(link.clone(), local.clone(), s, r));
+    }
+}
+
+async fn serve_stream(
+    link: crate::p2p::PeerLink, local: wire::Auth, mut s: quinn::SendStream,
+    mut r: quinn::RecvStream,
+) {
+    if let Err(e) = auth::exchange(&link.conn, &mut s, &mut r, link.ipk, &local).await {
+        log::warn!("transfer: stream auth failed: {e}");
+        return;
+    }
+    let Ok(pull) = wire::read_frame::<wire::Pull>(&mut r).await else {
+        return;
+    };
+    let want = match pull.have {
+        wire::WANT_FOLLOWS => match wire::read_frame::<wire::Want>(&mut r).await {
+            Ok(want) => Some(want.chunks),
+            Err(_) => return,
+        },
+        _ => None,
+    };
+    let Some(served) = servable(&pull.file_id).filter(|_| shared_with(&pull.file_id, &link.ipk))
+    else {
+        let _ = wire::write_frame(&mut s, &wire::ServeResp::Gone).await;
+        let _ = s.finish();
+        return;
+    };
+    let n = served.manifest.chunks.len() as u32;
+    let (resp, chunks) = match want {
+        Some(chunks) => (wire::ServeResp::Chunks(served.manifest.clone()), chunks),
+        // A puller from before chunk bitmaps: everything from `have` on.
+        None => (
+            wire::ServeResp::Manifest(served.manifest.clone()),
+            Some(wire::ChunkSet::prefix(n, pull.have).complement()),
+        ),
+    };
+    if wire::write_frame(&mut s, &resp).await.is_ok()
+        && let Some(chunks) = chunks
+        && chunks.len() == n
+        && let Ok(mut f) = std::fs::File::open(&served.path)
+    {
+        use std::io::{Read, Seek, SeekFrom};
+        let mut buf = vec![0u8; served.manifest.chunk_size as usize];
+        for idx in chunks.iter() {
+            let len = served.manifest.chunk_len(idx);
+            let at = idx as u64 * served.manifest.chunk_size as u64;
+            if f.seek(SeekFrom::Start(at)).is_err()
+                || f.read_exact(&mut buf[..len]).is_err()
+                || s.write_all(&buf[..len]).await.is_err()
+            {
+                break;
+            }
         }
     }
+    let _ = s.finish();
 }
 
 /// Pulls in flight by `file_id`. Two concurrent `download`s (auto-download
@@ -163,9 +275,11 @@ impl Drop for PullGuard {
     }
 }
 
-/// Pull `file_id` from the contact who offered it: resolve the sender from
-/// the media row, dial (or reuse) the P2P link, and run the resumable pull.
-/// No-op when the file is already downloaded or a pull is in flight.
+/// Pull `file_id` from whoever can serve it: the contact who offered it plus
+/// any member who announced a complete copy ([`on_file_have`]). Dials them
+/// (or reuses links), then runs the resumable multi-source pull; with no link
+/// at all it tries the sender's relay blob before holding. No-op when the
+/// file is already downloaded or a pull is in flight.
 pub async fn download(file_id: [u8; 32]) -> anyhow::Result<()> {
     if store::partial_get(&file_id).is_some_and(|p| p.state == store::DONE) {
         return Ok(());
@@ -174,54 +288,133 @@ pub async fn download(file_id: [u8; 32]) -> anyhow::Result<()> {
         return Ok(());
     }
     let _guard = PullGuard(file_id);
-    let (peer, offered_size) = crate::data::media::attachment_offer(&file_id)?
-        .ok_or_else(|| anyhow::anyhow!("no media row for that file_id"))?;
-    let link = match crate::p2p::link(peer).await {
-        Ok(l) => l,
-        Err(e) => {
-            // Sender unreachable — offline, or the punch/TURN path is exhausted
-            // for a large file. Reverse-wake them and hold; the receiver retries
-            // on reconnect or a user tap. Not an error: the UI reads HELD. But a
-            // sender we poked seconds ago won't have come up yet, so on a fresh
-            // reconnect re-drive we suppress the wake within the backoff (the row
-            // stays HELD with its wake time); a first-time hold always wakes.
-            let woke_recently = store::partial_get(&file_id)
-                .filter(|p| p.state == store::HELD)
-                .is_some_and(|p| {
-                    crate::utils::systime().as_secs().saturating_sub(p.updated_at)
-                        < WAKE_BACKOFF_SECS
-                });
-            log::warn!(
-                "transfer: {} unreachable ({e}); holding {}{}",
-                hex::encode(&peer[..4]),
-                hex::encode(&file_id[..4]),
-                if woke_recently { " (wake suppressed)" } else { ", reverse-waking" },
-            );
-            if !woke_recently {
-                // The reverse-wake targets the one device holding the bytes,
-                // so it goes to our direct conversation with them.
-                if let Ok(conversation) = crate::data::conversation::Conversation::for_peer(&peer) {
-                    let _ = crate::messaging::send_control_wake(
-                        conversation,
-                        common::proto::mls_wire::AppPayload::FileWant { file_id },
-                    )
-                    .await;
-                }
-                hold(&file_id, peer);
+    let offer =
+        offer_of(&file_id)?.ok_or_else(|| anyhow::anyhow!("no media row for that file_id"))?;
+    let peer = offer.sender;
+    let (links, origin_err) = reachable_sources(&file_id, peer).await;
+    let now_ms = crate::utils::systime().as_millis() as u64;
+    if links.is_empty()
+        && let Some(loc) = store::blob_locator_get(&file_id, now_ms)
+    {
+        match blob::fetch(file_id, peer, offer.size, &loc).await {
+            Ok(()) => {
+                announce(offer.conversation, file_id);
+                return Ok(());
+            },
+            Err(e) => log::warn!(
+                "transfer: relay blob for {} unusable: {e}",
+                hex::encode(&file_id[..4])
+            ),
+        }
+    }
+    if links.is_empty() {
+        // Nobody reachable — the sender offline, or the punch/TURN path is
+        // exhausted for a large file. Reverse-wake them and hold; the receiver
+        // retries on reconnect, a user tap, or another member announcing a
+        // copy. Not an error: the UI reads HELD. But a sender we poked seconds
+        // ago won't have come up yet, so on a fresh reconnect re-drive we
+        // suppress the wake within the backoff (the row stays HELD with its
+        // wake time); a first-time hold always wakes.
+        let woke_recently = store::partial_get(&file_id)
+            .filter(|p| p.state == store::HELD)
+            .is_some_and(|p| {
+                crate::utils::systime().as_secs().saturating_sub(p.updated_at) < WAKE_BACKOFF_SECS
+            });
+        log::warn!(
+            "transfer: {} unreachable ({}); holding {}{}",
+            hex::encode(&peer[..4]),
+            origin_err.map(|e| e.to_string()).unwrap_or_default(),
+            hex::encode(&file_id[..4]),
+            if woke_recently { " (wake suppressed)" } else { ", reverse-waking" },
+        );
+        if !woke_recently {
+            // The reverse-wake targets the one device holding the bytes,
+            // so it goes to our direct conversation with them.
+            if let Ok(conversation) = crate::data::conversation::Conversation::for_peer(&peer) {
+                let _ = crate::messaging::send_control_wake(
+                    conversation,
+                    common::proto::mls_wire::AppPayload::FileWant { file_id },
+                )
+                .await;
             }
-            return Ok(());
-        },
-    };
-    // A mid-pull failure (network drop, or the sender edited/deleted the source
-    // so a chunk hash mismatches) must land the partial in FAILED — not leave it
-    // spinning ACTIVE, which gc never reaps. A re-tap resumes from `have`.
-    let r = pull(&link, file_id, offered_size, &auth::local_auth()?).await;
-    if r.is_err() {
-        fail(&file_id);
+            hold(&file_id, peer);
+        }
+        return Ok(());
+    }
+    // A failure that exhausts every source (network drop, or the sender
+    // edited/deleted the source so a chunk hash mismatches) must land the
+    // partial in FAILED — not leave it spinning ACTIVE, which gc never reaps.
+    // A re-tap resumes from the stored bitmap.
+    let in_order = offer.kind == crate::data::media::KIND_VIDEO;
+    let r = pull(&links, file_id, offer.size, in_order, &auth::local_auth()?).await;
+    match &r {
+        Ok(()) => announce(offer.conversation, file_id),
+        Err(_) => fail(&file_id),
     }
     r
 }
 
+/// The offer we received `file_id` under: an attachment's, else a sticker
+/// pack file's.
+pub(crate) fn offer_of(
+    file_id: &[u8; 32],
+) -> anyhow::Result<Option<crate::data::media::AttachmentOffer>> {
+    match crate::data::media::attachment_offer(file_id)? {
+        Some(offer) => Ok(Some(offer)),
+        None => crate::data::sticker::offer(file_id),
+    }
+}
+
+/// Links to the offering sender and up to [`MAX_PULL_SOURCES`] - 1 members
+/// who announced a copy, dialed concurrently. The origin leads when it is
+/// reachable; its dial error is returned for the hold log.
+async fn reachable_sources(
+    file_id: &[u8; 32], origin: [u8; 32],
+) -> (Vec<crate::p2p::PeerLink>, Option<anyhow::Error>) {
+    let mut peers = vec![origin];
+    for ipk in store::sources_for(file_id) {
+        if peers.len() == MAX_PULL_SOURCES {
+            break;
+        }
+        if !peers.contains(&ipk) {
+            peers.push(ipk);
+        }
+    }
+    let mut dials = tokio::task::JoinSet::new();
+    for peer in peers {
+        dials.spawn(async move { (peer, crate::p2p::link(peer).await) });
+    }
+    let mut links = Vec::new();
+    let mut origin_err = None;
+    while let Some(joined) = dials.join_next().await {
+        match joined {
+            Ok((peer, Ok(link))) if peer == origin => links.insert(0, link),
+            Ok((_, Ok(link))) => links.push(link),
+            Ok((peer, Err(e))) if peer == origin => origin_err = Some(e),
+            Ok((peer, Err(e))) => {
+                log::debug!("transfer: source {} unreachable: {e}", hex::encode(&peer[..4]))
+            },
+            Err(_) => {},
+        }
+    }
+    (links, origin_err)
+}
+
+/// Tell the conversation we now hold `file_id`, so members still pulling can
+/// add us as a source. Only worth it where someone besides the sender might
+/// be pulling: a group.
+fn announce(conversation: [u8; 16], file_id: [u8; 32]) {
+    if crate::data::conversation::Conversation::recipients(&conversation).len() < 2 {
+        return;
+    }
+    crate::RUNTIME.spawn(async move {
+        let payload = common::proto::mls_wire::AppPayload::FileHave { file_id };
+        if let Err(e) = crate::messaging::send_control(conversation, payload).await {
+            log::debug!("transfer: FileHave for {} not sent: {e}", hex::encode(&file_id[..4]));
+        }
+    });
+}
+
 /// Mark a pull as HELD (sender offline, reverse-wake sent): upsert the partial
 /// so `get_media` surfaces the held state to the UI. Mirrors [`pull`]'s upsert
 /// but with no manifest yet — the real bytes arrive once the sender comes back.
@@ -233,6 +426,7 @@ fn hold(file_id: &[u8; 32], peer: [u8; 32]) {
         chunk_size: 0,
         manifest: None,
         have: 0,
+        bitmap: None,
         state: store::HELD,
         path: store::partial_path(file_id),
         updated_at: 0,
@@ -243,7 +437,7 @@ fn hold(file_id: &[u8; 32], peer: [u8; 32]) {
     let _ = store::partial_put(&p);
 }
 
-/// Flip an existing partial to FAILED, preserving `have` so a later re-tap
+/// Flip an existing partial to FAILED, preserving its chunks so a later re-tap
 /// resumes where it stopped. No-op when there's no partial yet — a failure
 /// before the first chunk landed left nothing to clean. `gc` only reaps
 /// FAILED/HELD, so an ACTIVE partial left by a mid-pull error would spin the
@@ -272,12 +466,32 @@ pub fn on_file_want(peer: [u8; 32], file_id: [u8; 32]) {
     });
 }
 
+/// Handle a member's announcement that they hold `file_id`: remember them as
+/// a source if the file was shared with them, and re-drive a pull that is
+/// parked on an unreachable sender.
+pub fn on_file_have(peer: [u8; 32], file_id: [u8; 32]) {
+    if !shared_with(&file_id, &peer) {
+        return;
+    }
+    if let Err(e) = store::source_add(&file_id, &peer, crate::utils::systime().as_secs()) {
+        log::warn!("transfer: could not record a source: {e}");
+        return;
+    }
+    if store::partial_get(&file_id).is_some_and(|p| p.state == store::HELD) {
+        crate::RUNTIME.spawn(async move {
+            if let Err(e) = download(file_id).await {
+                log::warn!("transfer: pull of {} failed: {e}", hex::encode(&file_id[..4]));
+            }
+        });
+    }
+}
+
 /// Re-drive every incomplete pull on reconnect (and app-open, which reconnects):
 /// HELD (sender was offline last time) and ACTIVE (a transfer whose process died
 /// mid-pull, so nothing is driving it now — the usual "stuck at 65% after a
 /// restart"). One spawn per file_id — the `DOWNLOADING` guard dedups a racing
 /// user tap or a genuinely-live pull, and [`download`] resumes from the stored
-/// `have` watermark, completing if the sender is up or falling to HELD if not.
+/// bitmap, completing if a source is up or falling to HELD if not.
 /// FAILED is excluded: a real error must not auto-loop.
 pub async fn resume_incomplete_downloads() {
     for file_id in store::incomplete_file_ids() {
@@ -289,27 +503,161 @@ pub async fn resume_incomplete_downloads() {
     }
 }
 
-/// The wire+disk half of [`download`] over an already-open link, split out so
-/// a test can drive it against [`serve_link`] on a direct loopback pair
+/// Chunks still wanted, shared by every stream of one [`pull`].
+struct PullState {
+    file_id: [u8; 32],
+    manifest: wire::Manifest,
+    /// Chunks per round-trip; see [`PLAYBACK_CHUNKS_PER_STREAM`].
+    batch: usize,
+    queue: Mutex<VecDeque<u32>>,
+    progress: Mutex<Progress>,
+}
+
+/// What has landed, and where. One lock so a chunk's bytes, its bit and the
+/// persisted row always move together.
+struct Progress {
+    part: store::Partial,
+    got: wire::ChunkSet,
+    file: std::fs::File,
+}
+
+impl PullState {
+    fn take_batch(&self) -> Vec<u32> {
+        let mut queue = self.queue.lock();
+        let n = queue.len().min(self.batch);
+        queue.drain(..n).collect()
+    }
+
+    /// Hand back whatever of `batch` didn't land, for the other streams. It
+    /// goes to the front: the queue is ascending, and a failed stream's chunks
+    /// are the earliest still missing.
+    fn requeue(&self, batch: &[u32]) {
+        let progress = self.progress.lock();
+        let mut queue = self.queue.lock();
+        for &idx in batch.iter().rev().filter(|&&idx| !progress.got.contains(idx)) {
+            queue.push_front(idx);
+        }
+    }
+
+    fn land(&self, idx: u32, bytes: &[u8]) -> anyhow::Result<()> {
+        self.progress.lock().land(idx, self.manifest.chunk_size, bytes)
+    }
+}
+
+impl Progress {
+    /// Open (or resume) the partial for `file_id` as ACTIVE: `n` chunks of
+    /// `chunk_size` over `total` bytes, with whatever a previous pull landed.
+    fn open(
+        file_id: [u8; 32], source_ipk: [u8; 32], total: u64, chunk_size: u32, n: u32,
+        manifest: Option<Vec<u8>>,
+    ) -> anyhow::Result<Progress> {
+        let got = match store::partial_get(&file_id) {
+            None => wire::ChunkSet::empty(n),
+            Some(p) => match p.bitmap {
+                Some(bits) => wire::ChunkSet::from_bytes(n, bits)
+                    .ok_or_else(|| anyhow::anyhow!("partial bitmap does not fit the manifest"))?,
+                None => {
+                    anyhow::ensure!(p.have <= n, "partial ahead of manifest");
+                    wire::ChunkSet::prefix(n, p.have)
+                },
+            },
+        };
+        let path = store::partial_path(&file_id);
+        let part = store::Partial {
+            file_id,
+            source_ipk,
+            total,
+            chunk_size,
+            manifest,
+            have: got.count(),
+            bitmap: Some(got.as_bytes().to_vec()),
+            state: store::ACTIVE,
+            path: path.clone(),
+            updated_at: crate::utils::systime().as_secs(),
+        };
+        store::partial_put(&part)?;
+        // Never truncate: a resumed partial keeps the chunks its bitmap records.
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .read(true)
+            .open(&path)?;
+        // Chunks land out of order; size the file up front so every offset exists.
+        file.set_len(total)?;
+        Ok(Progress { part, got, file })
+    }
+
+    /// Write a verified chunk, sync it, and only then record it — so a resume
+    /// never trusts a bit ahead of real bytes.
+    fn land(&mut self, idx: u32, chunk_size: u32, bytes: &[u8]) -> anyhow::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+        let Progress { part, got, file } = self;
+        file.seek(SeekFrom::Start(idx as u64 * chunk_size as u64))?;
+        file.write_all(bytes)?;
+        file.flush()?;
+        file.sync_data()?;
+        got.insert(idx);
+        part.have = got.count();
+        part.bitmap = Some(got.as_bytes().to_vec());
+        part.updated_at = crate::utils::systime().as_secs();
+        store::partial_put(part)?; // doorbell → UI progress
+        Ok(())
+    }
+
+    /// Flip the partial to DONE. No rename on completion: state==DONE over the
+    /// .part path IS the promote — a single DB flip with no rename/DB-ordering
+    /// crash window; get_media only exposes local_path once DONE.
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.part.state = store::DONE;
+        self.part.updated_at = crate::utils::systime().as_secs();
+        store::partial_put(&self.part)?;
+        Ok(())
+    }
+}
+
+/// The wire+disk half of [`download`] over already-open links, split out so
+/// a test can drive it against [`serve_link`] on direct loopback pairs
 /// (acquiring a real link needs the full punch choreography).
 ///
+/// The manifest comes from the first source that still serves the file and
+/// must match `file_id`; every chunk is verified against it whoever serves
+/// it. Missing chunks are split into batches across [`STREAMS_PER_SOURCE`]
+/// streams per source. A source that fails — dropped stream, `Gone`, a bad
+/// chunk — is dropped and its unfinished batch goes back to the others; the
+/// pull fails only once no source is left.
+///
+/// The queue is ascending and sources serve each batch in order, so chunks
+/// land roughly front-first. `in_order` (a video) shrinks the batches so the
+/// gap-free prefix — what [`playable_bytes`] reports — grows as the pull
+/// goes rather than only at the end.
+///
 /// Crash-safety contract: a chunk's bytes are synced to disk BEFORE the
-/// `have` watermark covering them is persisted, so a resume never trusts a
-/// watermark ahead of real bytes — the worst crash re-pulls one chunk.
+/// bitmap covering them is persisted (see [`PullState::land`]), so the worst
+/// crash re-pulls the chunks in flight.
 async fn pull(
-    link: &crate::p2p::PeerLink, file_id: [u8; 32], offered_size: u64, local: &wire::Auth,
+    links: &[crate::p2p::PeerLink], file_id: [u8; 32], offered_size: u64, in_order: bool,
+    local: &wire::Auth,
 ) -> anyhow::Result<()> {
-    let have0 = store::partial_get(&file_id).map(|p| p.have).unwrap_or(0);
-    let (mut s, mut r) = link.open_stream().await?;
-    auth::exchange(&link.conn, &mut s, &mut r, link.ipk, local).await?;
-    wire::write_frame(&mut s, &wire::Pull { file_id, have: have0 }).await?;
-    s.finish()?;
-    let manifest = match wire::read_frame::<wire::ServeResp>(&mut r).await? {
-        wire::ServeResp::Manifest(m) => m,
-        wire::ServeResp::Gone => {
-            fail(&file_id);
-            anyhow::bail!("sender no longer retains the file");
-        },
+    let mut manifest = None;
+    let mut live = Vec::new();
+    let mut any_gone = false;
+    let mut last_err = None;
+    for link in links {
+        match fetch_manifest(link, file_id, local).await {
+            Ok(Some((m, bitmaps))) => {
+                manifest.get_or_insert(m);
+                live.push(Source { link: link.clone(), bitmaps });
+            },
+            Ok(None) => any_gone = true,
+            Err(e) => last_err = Some(e),
+        }
+    }
+    let Some(manifest) = manifest else {
+        if any_gone {
+            anyhow::bail!("no source still retains the file");
+        }
+        return Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no source to pull from")));
     };
     // Fail closed before any bytes land: the manifest must be the exact one
     // the content-addressed file_id commits to, and self-consistent so a bad
@@ -323,7 +671,6 @@ async fn pull(
         manifest.chunks.len() as u64 == manifest.total_size.div_ceil(manifest.chunk_size as u64),
         "chunk count does not match total_size"
     );
-    anyhow::ensure!(have0 as usize <= manifest.chunks.len(), "partial ahead of manifest");
     // Fail closed on a disk-fill DoS: a hostile contact can offer a tiny `size`
     // (passing the auto-download policy and the size shown to the user) while
     // the manifest describes gigabytes. The offer is the ceiling we consented to.
@@ -332,49 +679,135 @@ async fn pull(
         "manifest size {} belies the offered {offered_size}",
         manifest.total_size,
     );
-
-    let path = store::partial_path(&file_id);
-    let mut part = store::Partial {
+    let progress = Progress::open(
         file_id,
-        source_ipk: link.ipk,
-        total: manifest.total_size,
-        chunk_size: manifest.chunk_size,
-        manifest: Some(postcard::to_allocvec(&manifest)?),
-        have: have0,
-        state: store::ACTIVE,
-        path: path.clone(),
-        updated_at: crate::utils::systime().as_secs(),
-    };
-    store::partial_put(&part)?;
+        links[0].ipk,
+        manifest.total_size,
+        manifest.chunk_size,
+        manifest.chunks.len() as u32,
+        Some(postcard::to_allocvec(&manifest)?),
+    )?;
+    let state = Arc::new(PullState {
+        file_id,
+        manifest,
+        batch: if in_order { PLAYBACK_CHUNKS_PER_STREAM } else { CHUNKS_PER_STREAM },
+        queue: Mutex::new(VecDeque::new()),
+        progress: Mutex::new(progress),
+    });
+    loop {
+        let missing = state.progress.lock().got.complement();
+        if missing.is_empty() {
+            break;
+        }
+        anyhow::ensure!(!live.is_empty(), "every source failed mid-pull");
+        state.queue.lock().extend(missing.iter());
+
+        let mut streams = tokio::task::JoinSet::new();
+        for source in live.iter().flat_map(|l| std::iter::repeat_n(l, STREAMS_PER_SOURCE)) {
+            let (source, state, local) = (source.clone(), state.clone(), local.clone());
+            streams.spawn(async move {
+                let r = pull_stream(&source, &state, &local).await;
+                (source.link.ipk, r)
+            });
+        }
+        while let Some(joined) = streams.join_next().await {
+            let Ok((ipk, Err(e))) = joined else { continue };
+            log::warn!("transfer: dropping source {}: {e}", hex::encode(&ipk[..4]));
+            live.retain(|l| l.link.ipk != ipk);
+        }
+    }
+
+    state.progress.lock().finish()
+}
+
+/// A link that still serves the file, and whether it takes a [`wire::Want`].
+#[derive(Clone)]
+struct Source {
+    link: crate::p2p::PeerLink,
+    /// `false` for a build from before chunk bitmaps, which serves from a
+    /// chunk index to the end of the file.
+    bitmaps: bool,
+}
+
+/// Ask `link` for the manifest alone; `None` when it answers `Gone`.
+async fn fetch_manifest(
+    link: &crate::p2p::PeerLink, file_id: [u8; 32], local: &wire::Auth,
+) -> anyhow::Result<Option<(wire::Manifest, bool)>> {
+    let (mut s, mut r) = link.open_stream().await?;
+    auth::exchange(&link.conn, &mut s, &mut r, link.ipk, local).await?;
+    wire::write_frame(&mut s, &wire::Pull { file_id, have: wire::WANT_FOLLOWS }).await?;
+    wire::write_frame(&mut s, &wire::Want { chunks: None }).await?;
+    s.finish()?;
+    match wire::read_frame::<wire::ServeResp>(&mut r).await? {
+        wire::ServeResp::Chunks(m) => Ok(Some((m, true))),
+        wire::ServeResp::Manifest(m) => Ok(Some((m, false))),
+        wire::ServeResp::Gone => Ok(None),
+    }
+}
+
+/// One stream's worth of a [`pull`]: take batches off the queue until it is
+/// empty. On any error the batch's unlanded chunks are handed back.
+async fn pull_stream(source: &Source, state: &PullState, local: &wire::Auth) -> anyhow::Result<()> {
+    loop {
+        let batch = state.take_batch();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        if let Err(e) = pull_batch(source, state, &batch, local).await {
+            state.requeue(&batch);
+            return Err(e);
+        }
+    }
+}
 
-    use std::io::{Seek, SeekFrom, Write};
-    let mut f = std::fs::OpenOptions::new().create(true).write(true).read(true).open(&path)?;
-    f.seek(SeekFrom::Start(have0 as u64 * manifest.chunk_size as u64))?;
+/// Pull one batch over a fresh stream. A source from before chunk bitmaps
+/// streams from the batch's first chunk to the end of the file; the chunks in
+/// between are checked and skipped, and the stream dropped after the last one
+/// we asked for.
+async fn pull_batch(
+    source: &Source, state: &PullState, batch: &[u32], local: &wire::Auth,
+) -> anyhow::Result<()> {
+    let manifest = &state.manifest;
+    let mut want = wire::ChunkSet::empty(manifest.chunks.len() as u32);
+    for &idx in batch {
+        want.insert(idx);
+    }
+    let (Some(first), Some(last)) = (want.iter().next(), want.iter().last()) else {
+        return Ok(());
+    };
+    let link = &source.link;
+    let (mut s, mut r) = link.open_stream().await?;
+    auth::exchange(&link.conn, &mut s, &mut r, link.ipk, local).await?;
+    let file_id = state.file_id;
+    if source.bitmaps {
+        wire::write_frame(&mut s, &wire::Pull { file_id, have: wire::WANT_FOLLOWS }).await?;
+        wire::write_frame(&mut s, &wire::Want { chunks: Some(want.clone()) }).await?;
+    } else {
+        wire::write_frame(&mut s, &wire::Pull { file_id, have: first }).await?;
+    }
+    s.finish()?;
+    let served = match wire::read_frame::<wire::ServeResp>(&mut r).await? {
+        wire::ServeResp::Chunks(m) if source.bitmaps => m,
+        wire::ServeResp::Manifest(m) if !source.bitmaps => m,
+        wire::ServeResp::Gone => anyhow::bail!("source no longer serves the file"),
+        _ => anyhow::bail!("source answered in the other pull form"),
+    };
+    anyhow::ensure!(served == *manifest, "source serves a different manifest");
     let mut buf = vec![0u8; manifest.chunk_size as usize];
-    for idx in have0 as usize..manifest.chunks.len() {
-        let expect = if idx + 1 == manifest.chunks.len() {
-            (manifest.total_size - idx as u64 * manifest.chunk_size as u64) as usize
-        } else {
-            manifest.chunk_size as usize
-        };
-        r.read_exact(&mut buf[..expect]).await?;
+    for idx in first..=last {
+        if source.bitmaps && !want.contains(idx) {
+            continue;
+        }
+        let len = manifest.chunk_len(idx);
+        r.read_exact(&mut buf[..len]).await?;
         anyhow::ensure!(
-            *blake3::hash(&buf[..expect]).as_bytes() == manifest.chunks[idx],
+            *blake3::hash(&buf[..len]).as_bytes() == manifest.chunks[idx as usize],
             "chunk {idx} hash mismatch"
         );
-        f.write_all(&buf[..expect])?;
-        f.flush()?;
-        f.sync_data()?;
-        part.have = idx as u32 + 1;
-        part.updated_at = crate::utils::systime().as_secs();
-        store::partial_put(&part)?; // doorbell → UI progress
-    }
-    // No rename on completion: state==DONE over the .part path IS the promote —
-    // a single DB flip with no rename/DB-ordering crash window; get_media only
-    // exposes local_path once DONE.
-    part.state = store::DONE;
-    part.updated_at = crate::utils::systime().as_secs();
-    store::partial_put(&part)?;
+        if want.contains(idx) {
+            state.land(idx, &buf[..len])?;
+        }
+    }
     Ok(())
 }
 
@@ -429,6 +862,7 @@ mod tests {
             chunk_size: 50,
             manifest: None,
             have: 3,
+            bitmap: None,
             state: store::ACTIVE,
             path: store::partial_path(&fid),
             updated_at: 0,
@@ -444,6 +878,37 @@ mod tests {
         fail(&[0xf2u8; 32]);
         assert!(store::partial_get(&[0xf2u8; 32]).is_none());
     }
+
+    /// A player may read up to the first missing chunk and no further, and a
+    /// ragged final chunk caps the figure at the file's size.
+    #[test]
+    fn playable_bytes_stop_at_the_first_gap() {
+        let mut got = wire::ChunkSet::prefix(4, 2);
+        got.insert(3);
+        let mut p = store::Partial {
+            file_id: [0xf3u8; 32],
+            source_ipk: [1u8; 32],
+            total: 170,
+            chunk_size: 50,
+            manifest: None,
+            have: got.count(),
+            bitmap: Some(got.as_bytes().to_vec()),
+            state: store::ACTIVE,
+            path: String::new(),
+            updated_at: 0,
+        };
+        assert_eq!(playable_bytes(&p), 100, "chunk 3 sits past the gap at 2");
+
+        got.insert(2);
+        p.bitmap = Some(got.as_bytes().to_vec());
+        assert_eq!(playable_bytes(&p), 170);
+
+        p.bitmap = None;
+        p.have = 1;
+        assert_eq!(playable_bytes(&p), 50, "a pre-bitmap row is a prefix");
+        p.state = store::DONE;
+        assert_eq!(playable_bytes(&p), 170);
+    }
 }
 
 #[cfg(test)]
@@ -492,6 +957,10 @@ mod download_resume {
             blob:     None,
             thumb:    None,
             file_id:  Some(file_id.to_vec()),
+            duration_ms: 0,
+            waveform: None,
+            sticker_pack: None,
+            sticker_id: 0,
         };
         // The media row is conversation-scoped; the peer only names who to
         // pull from, which lives on the message row.
@@ -499,6 +968,54 @@ mod download_resume {
         crate::data::media::save_outgoing_with_media(&conv, "", None, &row).unwrap();
     }
 
+    /// A member who names a file we hold in a made-up pack offer gains no
+    /// pull on it, and the offer starts no pull of ours: only a pack we
+    /// installed widens who its file is shared with.
+    #[test]
+    fn a_fake_pack_offer_grants_no_pull() {
+        let dir = std::env::temp_dir().join("promtuz-download-resume-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        let member = paired_identity([57u8; 32]).ipk;
+        let recipient = paired_identity([58u8; 32]).ipk;
+        let src = std::env::temp_dir().join("promtuz-dl-src-private.bin");
+        std::fs::write(&src, vec![0x5au8; 1024]).unwrap();
+        let (file_id, _) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
+        offer_to(recipient, file_id);
+        // The data dir outlives the run; forget a previous run's offer.
+        crate::data::sticker::offer_remove(&file_id).unwrap();
+
+        // The member shows a sticker from a pack nobody installed, then
+        // offers our file as that pack.
+        let pack = [0x59u8; 16];
+        let conv = crate::data::conversation::Conversation::for_peer(&member).unwrap();
+        let row = crate::data::media::MediaRow {
+            kind:         crate::data::media::KIND_STICKER,
+            group_id:     None,
+            mime:         "image/avif".into(),
+            name:         String::new(),
+            size:         0,
+            width:        0,
+            height:       0,
+            blob:         None,
+            thumb:        None,
+            file_id:      None,
+            duration_ms:  0,
+            waveform:     None,
+            sticker_pack: Some(pack.to_vec()),
+            sticker_id:   0,
+        };
+        crate::data::media::save_outgoing_with_media(&conv, "", None, &row).unwrap();
+        crate::stickers::on_pack_offer(member, conv, pack, file_id, 1024);
+        assert!(crate::data::sticker::offer(&file_id).unwrap().is_none(), "no pull started");
+
+        // Even on record, an offer grants nothing.
+        crate::data::sticker::offer_put(&file_id, &pack, &member, &conv, 1024).unwrap();
+        assert!(!shared_with(&file_id, &member));
+        assert!(shared_with(&file_id, &recipient));
+    }
+
     /// Two directly-connected peer endpoints on loopback — the real QUIC
     /// stack minus the punch layer, which a unit test can't drive. Each
     /// link's `ipk` is the peer that side expects on its streams.
@@ -544,7 +1061,7 @@ mod download_resume {
         offer_to(id_b.ipk, file_id);
 
         // Fresh pull: every chunk lands, verifies, and the partial promotes.
-        pull(&link_b, file_id, 300 * 1024, &id_b).await.unwrap();
+        pull(std::slice::from_ref(&link_b), file_id, 300 * 1024, false, &id_b).await.unwrap();
         let p = store::partial_get(&file_id).unwrap();
         assert_eq!(p.state, store::DONE);
         assert_eq!(p.have, 2);
@@ -569,13 +1086,14 @@ mod download_resume {
             chunk_size: wire::CHUNK_SIZE as u32,
             manifest: None,
             have: 1,
+            bitmap: None,
             state: store::ACTIVE,
             path: path2.clone(),
             updated_at: 0,
         })
         .unwrap();
 
-        pull(&link_b, file_id2, 300 * 1024, &id_b).await.unwrap();
+        pull(std::slice::from_ref(&link_b), file_id2, 300 * 1024, false, &id_b).await.unwrap();
         assert_eq!(store::partial_get(&file_id2).unwrap().state, store::DONE);
         let got = std::fs::read(&path2).unwrap();
         assert_eq!(got.len(), 300 * 1024);
@@ -583,6 +1101,160 @@ mod download_resume {
         assert_eq!(&got[wire::CHUNK_SIZE..], &bytes2[wire::CHUNK_SIZE..]);
     }
 
+    /// A video pulls in playback-sized batches: a gap near the front is
+    /// filled along with the tail, and the whole file verifies and promotes.
+    #[tokio::test]
+    async fn in_order_pull_fills_the_front_and_promotes() {
+        let dir = std::env::temp_dir().join("promtuz-download-resume-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) }; // set_var is unsafe in edition 2024
+
+        let id_a = paired_identity([55u8; 32]);
+        let id_b = paired_identity([56u8; 32]);
+        let (link_a, link_b, _ep_a, _ep_b) = linked_pair(id_b.ipk, id_a.ipk).await;
+        tokio::spawn(serve_streams(link_a, id_a));
+
+        let size = 5 * wire::CHUNK_SIZE - 100;
+        let bytes: Vec<u8> = (0..size).map(|i| (i / wire::CHUNK_SIZE) as u8 + 1).collect();
+        let src = std::env::temp_dir().join("promtuz-dl-src-video.bin");
+        std::fs::write(&src, &bytes).unwrap();
+        let (file_id, _) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
+        offer_to(id_b.ipk, file_id);
+
+        // Chunks 0 and 3 already held: 1, 2 and 4 are what the pull must fetch.
+        let path = store::partial_path(&file_id);
+        let mut seeded = vec![0u8; size];
+        for c in [0, 3] {
+            let at = c * wire::CHUNK_SIZE..(c + 1) * wire::CHUNK_SIZE;
+            seeded[at.clone()].copy_from_slice(&bytes[at]);
+        }
+        std::fs::write(&path, &seeded).unwrap();
+        let mut held = wire::ChunkSet::empty(5);
+        held.insert(0);
+        held.insert(3);
+        store::partial_put(&store::Partial {
+            file_id,
+            source_ipk: [1; 32],
+            total: size as u64,
+            chunk_size: wire::CHUNK_SIZE as u32,
+            manifest: None,
+            have: held.count(),
+            bitmap: Some(held.as_bytes().to_vec()),
+            state: store::ACTIVE,
+            path: path.clone(),
+            updated_at: 0,
+        })
+        .unwrap();
+        assert_eq!(playable_bytes(&store::partial_get(&file_id).unwrap()), wire::CHUNK_SIZE as u64);
+
+        pull(std::slice::from_ref(&link_b), file_id, size as u64, true, &id_b).await.unwrap();
+        let p = store::partial_get(&file_id).unwrap();
+        assert_eq!(p.state, store::DONE);
+        assert_eq!(playable_bytes(&p), size as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    }
+
+    #[tokio::test]
+    async fn pull_fills_bitmap_gaps_across_sources() {
+        let dir = std::env::temp_dir().join("promtuz-download-resume-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) };
+
+        let id_a = paired_identity([81u8; 32]);
+        let id_b = paired_identity([82u8; 32]);
+        let id_c = paired_identity([83u8; 32]);
+        let (link_a, link_ba, _ep_a, _ep_ba) = linked_pair(id_b.ipk, id_a.ipk).await;
+        let (link_c, link_bc, _ep_c, _ep_bc) = linked_pair(id_b.ipk, id_c.ipk).await;
+        tokio::spawn(serve_streams(link_a, id_a));
+        tokio::spawn(serve_streams(link_c, id_c));
+
+        let src = std::env::temp_dir().join("promtuz-dl-multi.bin");
+        let mut bytes = vec![0x01u8; 2 * wire::CHUNK_SIZE + 1000];
+        bytes[wire::CHUNK_SIZE..].fill(0x02);
+        bytes[2 * wire::CHUNK_SIZE..].fill(0x03);
+        std::fs::write(&src, &bytes).unwrap();
+        let (file_id, size) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
+        offer_to(id_b.ipk, file_id);
+
+        // Only the middle chunk landed before: garbage there must survive,
+        // which it can't if the pull re-transfers it.
+        let path = store::partial_path(&file_id);
+        std::fs::write(&path, vec![0x99u8; 2 * wire::CHUNK_SIZE]).unwrap();
+        let mut seeded = wire::ChunkSet::empty(3);
+        seeded.insert(1);
+        store::partial_put(&store::Partial {
+            file_id,
+            source_ipk: [1; 32],
+            total: size,
+            chunk_size: wire::CHUNK_SIZE as u32,
+            manifest: None,
+            have: 1,
+            bitmap: Some(seeded.as_bytes().to_vec()),
+            state: store::FAILED,
+            path: path.clone(),
+            updated_at: 0,
+        })
+        .unwrap();
+
+        pull(&[link_ba, link_bc], file_id, size, false, &id_b).await.unwrap();
+        let p = store::partial_get(&file_id).unwrap();
+        assert_eq!(p.state, store::DONE);
+        assert_eq!(p.have, 3);
+        let got = std::fs::read(&path).unwrap();
+        assert_eq!(got.len(), bytes.len());
+        assert_eq!(&got[..wire::CHUNK_SIZE], &bytes[..wire::CHUNK_SIZE]);
+        assert!(got[wire::CHUNK_SIZE..2 * wire::CHUNK_SIZE].iter().all(|&b| b == 0x99));
+        assert_eq!(&got[2 * wire::CHUNK_SIZE..], &bytes[2 * wire::CHUNK_SIZE..]);
+    }
+
+    #[tokio::test]
+    async fn pulls_from_before_chunk_bitmaps_still_work_both_ways() {
+        let dir = std::env::temp_dir().join("promtuz-download-resume-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) };
+
+        let id_a = paired_identity([91u8; 32]);
+        let id_b = paired_identity([92u8; 32]);
+        let (link_a, link_b, _ep_a, _ep_b) = linked_pair(id_b.ipk, id_a.ipk).await;
+        let source_ipk = id_a.ipk;
+        tokio::spawn(serve_streams(link_a, id_a));
+
+        let src = std::env::temp_dir().join("promtuz-dl-legacy.bin");
+        let mut bytes = vec![0x04u8; 2 * wire::CHUNK_SIZE + 1000];
+        bytes[wire::CHUNK_SIZE..].fill(0x05);
+        bytes[2 * wire::CHUNK_SIZE..].fill(0x06);
+        std::fs::write(&src, &bytes).unwrap();
+        let (file_id, size) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
+        offer_to(id_b.ipk, file_id);
+
+        // An old puller sends `have` alone and reads to the end of the file.
+        let (mut s, mut r) = link_b.open_stream().await.unwrap();
+        auth::exchange(&link_b.conn, &mut s, &mut r, link_b.ipk, &id_b).await.unwrap();
+        wire::write_frame(&mut s, &wire::Pull { file_id, have: 1 }).await.unwrap();
+        s.finish().unwrap();
+        let Ok(wire::ServeResp::Manifest(manifest)) = wire::read_frame(&mut r).await else {
+            panic!("an old puller gets the answer it knows");
+        };
+        assert_eq!(r.read_to_end(usize::MAX).await.unwrap(), bytes[wire::CHUNK_SIZE..]);
+
+        let (_, bitmaps) = fetch_manifest(&link_b, file_id, &id_b).await.unwrap().unwrap();
+        assert!(bitmaps, "a current server takes a Want");
+
+        // Talking to an old source: it streams 0..=2, and chunk 1 is skipped.
+        let progress = Progress::open(file_id, source_ipk, size, manifest.chunk_size, 3, None)
+            .unwrap();
+        let state = PullState {
+            file_id,
+            manifest,
+            batch: CHUNKS_PER_STREAM,
+            queue: Mutex::new(VecDeque::new()),
+            progress: Mutex::new(progress),
+        };
+        let old_source = Source { link: link_b.clone(), bitmaps: false };
+        pull_batch(&old_source, &state, &[0, 2], &id_b).await.unwrap();
+        assert_eq!(state.progress.lock().got.iter().collect::<Vec<_>>(), vec![0, 2]);
+    }
+
     #[tokio::test]
     async fn serve_refuses_wrong_ipk_before_any_chunk() {
         let dir = std::env::temp_dir().join("promtuz-download-resume-test");
@@ -601,7 +1273,11 @@ mod download_resume {
         std::fs::write(&src, vec![0x33u8; 300 * 1024]).unwrap();
         let (file_id, _) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
 
-        assert!(pull(&link_b, file_id, 300 * 1024, &imposter).await.is_err());
+        assert!(
+            pull(std::slice::from_ref(&link_b), file_id, 300 * 1024, false, &imposter)
+                .await
+                .is_err()
+        );
         assert!(store::partial_get(&file_id).is_none(), "no state before auth passes");
         assert!(
             !std::path::Path::new(&store::partial_path(&file_id)).exists(),
@@ -626,7 +1302,7 @@ mod download_resume {
         let (file_id, _) = prepare_send(src.to_str().unwrap(), 3600).unwrap();
         offer_to(other.ipk, file_id);
 
-        assert!(pull(&link_b, file_id, 300 * 1024, &id_b).await.is_err());
+        assert!(pull(std::slice::from_ref(&link_b), file_id, 300 * 1024, false, &id_b).await.is_err());
         assert!(store::retention_get(&file_id).is_some(), "still retained for its recipient");
     }
 
@@ -648,7 +1324,7 @@ mod download_resume {
 
         // The offer lied: claim 1KB while the manifest describes 300KB. The pull
         // must reject before allocating/writing a single .part byte.
-        assert!(pull(&link_b, file_id, 1024, &id_b).await.is_err());
+        assert!(pull(std::slice::from_ref(&link_b), file_id, 1024, false, &id_b).await.is_err());
         assert!(store::partial_get(&file_id).is_none(), "no partial when size belies offer");
         assert!(
             !std::path::Path::new(&store::partial_path(&file_id)).exists(),
@@ -1,6 +1,8 @@
 //! Local persistence for in-flight transfers: what the sender still holds
-//! (`retention`) and what a receiver has partially pulled (`partials`), plus
-//! the on-disk location of the partial bytes.
+//! (`retention`), what a receiver has partially pulled (`partials`), which
+//! other members announced they can serve a file (`sources`), where a relay
+//! parked an encrypted copy (`blob_locators`), plus the on-disk location of
+//! the partial bytes.
 
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
@@ -24,6 +26,17 @@ pub struct Retention {
     pub expires_at: u64,
 }
 
+/// Receiver-side: a relay blob holding an encrypted copy of one `file_id`,
+/// from the sender's `AppPayload::FileBlob`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobLocator {
+    pub host: [u8; 32],
+    pub blob_id: [u8; 32],
+    pub key: [u8; 32],
+    /// Unix ms, as the relay reported it.
+    pub expires_at: u64,
+}
+
 /// Receiver-side: how far a pull has progressed for one `file_id`.
 #[derive(Debug, Clone)]
 pub struct Partial {
@@ -32,14 +45,19 @@ pub struct Partial {
     pub total: u64,
     pub chunk_size: u32,
     pub manifest: Option<Vec<u8>>,
+    /// Chunks landed, for progress. Counts the `bitmap` when there is one;
+    /// a row without a bitmap holds exactly the chunks `[0, have)`.
     pub have: u32,
+    /// [`crate::transfer::wire::ChunkSet`] bytes of the chunks landed.
+    pub bitmap: Option<Vec<u8>>,
     pub state: u8,
     pub path: String,
     pub updated_at: u64,
 }
 
-const MIGRATION_ARRAY: &[M] = &[M::up(
-    r#"--sql
+const MIGRATION_ARRAY: &[M] = &[
+    M::up(
+        r#"--sql
         CREATE TABLE retention (
           file_id     BLOB PRIMARY KEY CHECK(length(file_id) = 32),
           path        TEXT NOT NULL,
@@ -60,7 +78,34 @@ const MIGRATION_ARRAY: &[M] = &[M::up(
           updated_at  INTEGER NOT NULL
         );
     "#,
-)];
+    ),
+    // Multi-source pulls land chunks out of order, so progress is a bitmap;
+    // `sources` remembers members who announced a complete copy.
+    M::up(
+        r#"--sql
+        ALTER TABLE partials ADD COLUMN bitmap BLOB;
+        CREATE TABLE sources (
+          file_id     BLOB NOT NULL CHECK(length(file_id) = 32),
+          ipk         BLOB NOT NULL CHECK(length(ipk) = 32),
+          added_at    INTEGER NOT NULL,
+          PRIMARY KEY (file_id, ipk)
+        );
+    "#,
+    ),
+    // A relay blob the sender parked the file in, for when no holder is
+    // reachable. The key decrypts it, so it lives only here.
+    M::up(
+        r#"--sql
+        CREATE TABLE blob_locators (
+          file_id     BLOB PRIMARY KEY CHECK(length(file_id) = 32),
+          host        BLOB NOT NULL CHECK(length(host) = 32),
+          blob_id     BLOB NOT NULL CHECK(length(blob_id) = 32),
+          key         BLOB NOT NULL CHECK(length(key) = 32),
+          expires_at  INTEGER NOT NULL
+        );
+    "#,
+    ),
+];
 const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATION_ARRAY);
 
 pub static TRANSFERS_DB: Lazy<Mutex<Connection>> = Lazy::new(|| {
@@ -138,6 +183,7 @@ pub fn partial_get(file_id: &[u8; 32]) -> Option<Partial> {
                 chunk_size: r.get("chunk_size")?,
                 manifest: r.get("manifest")?,
                 have: r.get("have")?,
+                bitmap: r.get("bitmap")?,
                 state: r.get("state")?,
                 path: r.get("path")?,
                 updated_at: r.get("updated_at")?,
@@ -150,11 +196,12 @@ pub fn partial_get(file_id: &[u8; 32]) -> Option<Partial> {
 pub fn partial_put(p: &Partial) -> rusqlite::Result<()> {
     TRANSFERS_DB.lock().execute(
         "INSERT OR REPLACE INTO partials
-           (file_id, source_ipk, total, chunk_size, manifest, have, state, path, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+           (file_id, source_ipk, total, chunk_size, manifest, have, bitmap, state, path,
+            updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
-            p.file_id, p.source_ipk, p.total, p.chunk_size, p.manifest, p.have, p.state, p.path,
-            p.updated_at
+            p.file_id, p.source_ipk, p.total, p.chunk_size, p.manifest, p.have, p.bitmap, p.state,
+            p.path, p.updated_at
         ],
     )?;
     Ok(())
@@ -188,6 +235,81 @@ pub fn gc_dead_partials(older_than: u64) -> Vec<String> {
     paths
 }
 
+/// Record that `ipk` announced a complete copy of `file_id`.
+pub fn source_add(file_id: &[u8; 32], ipk: &[u8; 32], now: u64) -> rusqlite::Result<()> {
+    TRANSFERS_DB.lock().execute(
+        "INSERT OR REPLACE INTO sources (file_id, ipk, added_at) VALUES (?1, ?2, ?3)",
+        params![file_id, ipk, now as i64],
+    )?;
+    Ok(())
+}
+
+/// Members who announced a complete copy of `file_id`, newest first.
+pub fn sources_for(file_id: &[u8; 32]) -> Vec<[u8; 32]> {
+    let conn = TRANSFERS_DB.lock();
+    let mut stmt = conn
+        .prepare("SELECT ipk FROM sources WHERE file_id = ?1 ORDER BY added_at DESC")
+        .expect("sources_for prepare");
+    stmt.query_map(params![file_id], |r| r.get(0))
+        .expect("sources_for query")
+        .collect::<rusqlite::Result<_>>()
+        .expect("sources_for rows")
+}
+
+/// Forget source announcements recorded before `older_than`.
+pub fn sources_gc(older_than: u64) -> usize {
+    TRANSFERS_DB
+        .lock()
+        .execute("DELETE FROM sources WHERE added_at < ?1", params![older_than as i64])
+        .expect("sources gc")
+}
+
+pub fn blob_locator_put(file_id: &[u8; 32], loc: &BlobLocator) -> rusqlite::Result<()> {
+    TRANSFERS_DB.lock().execute(
+        "INSERT OR REPLACE INTO blob_locators (file_id, host, blob_id, key, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![file_id, loc.host, loc.blob_id, loc.key, loc.expires_at as i64],
+    )?;
+    Ok(())
+}
+
+/// Drop the locator for `file_id`, for a blob that turned out not to be it.
+pub fn blob_locator_forget(file_id: &[u8; 32]) {
+    TRANSFERS_DB
+        .lock()
+        .execute("DELETE FROM blob_locators WHERE file_id = ?1", params![file_id])
+        .expect("blob_locator_forget");
+}
+
+/// The locator for `file_id` if its blob hasn't expired by `now_ms`.
+pub fn blob_locator_get(file_id: &[u8; 32], now_ms: u64) -> Option<BlobLocator> {
+    TRANSFERS_DB
+        .lock()
+        .query_row(
+            "SELECT host, blob_id, key, expires_at FROM blob_locators
+              WHERE file_id = ?1 AND expires_at > ?2",
+            params![file_id, now_ms as i64],
+            |r| {
+                Ok(BlobLocator {
+                    host: r.get(0)?,
+                    blob_id: r.get(1)?,
+                    key: r.get(2)?,
+                    expires_at: r.get::<_, i64>(3)? as u64,
+                })
+            },
+        )
+        .optional()
+        .expect("blob_locator_get query")
+}
+
+/// Forget locators whose blob expired before `now_ms`.
+pub fn blob_locators_gc(now_ms: u64) -> usize {
+    TRANSFERS_DB
+        .lock()
+        .execute("DELETE FROM blob_locators WHERE expires_at <= ?1", params![now_ms as i64])
+        .expect("blob_locators gc")
+}
+
 /// Every `file_id` whose partial is resumable — HELD (sender was offline) or
 /// ACTIVE (a pull the process died mid-way, so nothing drives it now). The
 /// reconnect retry re-drives each; the in-memory DOWNLOADING guard skips any a
@@ -222,6 +344,7 @@ mod tests {
                 chunk_size: 1,
                 manifest: None,
                 have: 0,
+                bitmap: None,
                 state,
                 path: partial_path(&fid),
                 updated_at: 0,
@@ -275,6 +398,7 @@ mod tests {
                 chunk_size: 5,
                 manifest: None,
                 have: 0,
+                bitmap: None,
                 state,
                 path: path.clone(),
                 updated_at,
@@ -298,4 +422,36 @@ mod tests {
         assert!(partial_get(&[0xd3; 32]).is_some(), "fresh FAILED row spared");
         assert!(std::path::Path::new(&fresh).exists(), "fresh FAILED .part kept");
     }
+
+    #[test]
+    fn sources_list_newest_first_and_age_out() {
+        let dir = std::env::temp_dir().join("promtuz-transfers-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) };
+
+        let fid = [0xc1u8; 32];
+        source_add(&fid, &[1u8; 32], 100).unwrap();
+        source_add(&fid, &[2u8; 32], 200).unwrap();
+        source_add(&fid, &[1u8; 32], 300).unwrap(); // re-announce refreshes
+        assert_eq!(sources_for(&fid), vec![[1u8; 32], [2u8; 32]]);
+
+        sources_gc(250);
+        assert_eq!(sources_for(&fid), vec![[1u8; 32]]);
+    }
+
+    #[test]
+    fn blob_locators_hide_and_age_out_once_expired() {
+        let dir = std::env::temp_dir().join("promtuz-transfers-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        unsafe { std::env::set_var("PROMTUZ_DATA_DIR", &dir) };
+
+        let fid = [0xb1u8; 32];
+        let loc = BlobLocator { host: [1; 32], blob_id: [2; 32], key: [3; 32], expires_at: 5_000 };
+        blob_locator_put(&fid, &loc).unwrap();
+        assert_eq!(blob_locator_get(&fid, 4_999), Some(loc));
+        assert_eq!(blob_locator_get(&fid, 5_000), None);
+
+        blob_locators_gc(5_000);
+        assert_eq!(blob_locator_get(&fid, 0), None);
+    }
 }
@@ -55,18 +55,126 @@ impl Manifest {
         }
         *h.finalize().as_bytes()
     }
+
+    /// Byte length of chunk `idx`: `chunk_size` for all but a short last one.
+    /// Only meaningful once the manifest has been checked self-consistent.
+    pub fn chunk_len(&self, idx: u32) -> usize {
+        let start = idx as u64 * self.chunk_size as u64;
+        (self.total_size - start).min(self.chunk_size as u64) as usize
+    }
+}
+
+/// A set of chunk indices as a bitmap, one bit per chunk (LSB-first). `len`
+/// is the manifest's chunk count; the server rejects a set sized for any
+/// other manifest.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct ChunkSet {
+    len: u32,
+    #[serde(with = "serde_bytes")]
+    bits: Vec<u8>,
+}
+
+impl ChunkSet {
+    pub fn empty(len: u32) -> Self {
+        ChunkSet { len, bits: vec![0u8; (len as usize).div_ceil(8)] }
+    }
+
+    /// The chunks `[0, have)` — what a partial recorded before bitmaps held.
+    pub fn prefix(len: u32, have: u32) -> Self {
+        let mut set = Self::empty(len);
+        for idx in 0..have.min(len) {
+            set.insert(idx);
+        }
+        set
+    }
+
+    /// Rebuild from stored bytes; `None` if they don't fit `len` chunks.
+    pub fn from_bytes(len: u32, bits: Vec<u8>) -> Option<Self> {
+        (bits.len() == (len as usize).div_ceil(8)).then_some(ChunkSet { len, bits })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    // Indexing goes through `get`: a set decoded off the wire skips
+    // `from_bytes`, so its `bits` may be short of `len`.
+    pub fn contains(&self, idx: u32) -> bool {
+        idx < self.len && self.bits.get(idx as usize / 8).is_some_and(|b| b & (1 << (idx % 8)) != 0)
+    }
+
+    pub fn insert(&mut self, idx: u32) {
+        if idx < self.len
+            && let Some(b) = self.bits.get_mut(idx as usize / 8)
+        {
+            *b |= 1 << (idx % 8);
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.iter().count() as u32
+    }
+
+    /// Member indices, ascending.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.len).filter(|&idx| self.contains(idx))
+    }
+
+    /// How many chunks from index 0 are present without a gap — the part of
+    /// the file a player can read front-to-back.
+    pub fn leading(&self) -> u32 {
+        (0..self.len).take_while(|&idx| self.contains(idx)).count() as u32
+    }
+
+    /// The chunks not in this set.
+    pub fn complement(&self) -> Self {
+        let mut out = Self::empty(self.len);
+        for idx in (0..self.len).filter(|&idx| !self.contains(idx)) {
+            out.insert(idx);
+        }
+        out
+    }
 }
 
+/// [`Pull::have`] meaning "a [`Want`] frame follows". A server from before
+/// chunk bitmaps seeks past the end of the file for it and streams nothing.
+pub const WANT_FOLLOWS: u32 = u32::MAX;
+
+/// First frame after [`Auth`] on a pull stream. The server answers with
+/// [`ServeResp`]; after a manifest it streams every chunk from index `have`
+/// to the end raw. Kept as it was before chunk bitmaps, so either side can
+/// still talk to a build that knows only this form.
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Pull {
     pub file_id: [u8; 32],
     pub have: u32,
 }
 
+/// Follows a [`Pull`] whose `have` is [`WANT_FOLLOWS`]. The server answers
+/// [`ServeResp::Chunks`] and streams the chunks in `chunks` raw, in ascending
+/// index order. `None` asks for the manifest alone, which is how a puller
+/// learns the chunk count before splitting work across streams.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Want {
+    pub chunks: Option<ChunkSet>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum ServeResp {
     Manifest(Manifest),
     Gone,
+    /// The answer to a [`Want`]. Appended last: a server that answers a
+    /// `Want` with [`ServeResp::Manifest`] predates chunk bitmaps and only
+    /// serves from `have` to the end.
+    Chunks(Manifest),
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
@@ -144,12 +252,32 @@ mod tests {
         assert_eq!(m.chunks.len(), 2);
     }
 
+    #[test]
+    fn chunk_set_tracks_members_and_complement() {
+        let mut set = ChunkSet::prefix(10, 3);
+        set.insert(8);
+        set.insert(10); // out of range: ignored
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 1, 2, 8]);
+        assert_eq!(set.count(), 4);
+        assert_eq!(set.leading(), 3, "chunk 8 sits past the gap");
+        assert_eq!(set.complement().iter().collect::<Vec<_>>(), vec![3, 4, 5, 6, 7, 9]);
+        assert!(ChunkSet::empty(10).is_empty());
+
+        let back = ChunkSet::from_bytes(10, set.as_bytes().to_vec()).unwrap();
+        assert_eq!(back, set);
+        assert!(ChunkSet::from_bytes(20, set.as_bytes().to_vec()).is_none());
+    }
+
     #[test]
     fn frame_types_roundtrip_through_postcard() {
-        let pull = Pull { file_id: [7u8; 32], have: 3 };
+        let pull = Pull { file_id: [7u8; 32], have: WANT_FOLLOWS };
         let got: Pull = postcard::from_bytes(&postcard::to_allocvec(&pull).unwrap()).unwrap();
         assert_eq!(pull, got);
 
+        let want = Want { chunks: Some(ChunkSet::prefix(9, 3)) };
+        let got: Want = postcard::from_bytes(&postcard::to_allocvec(&want).unwrap()).unwrap();
+        assert_eq!(want, got);
+
         let manifest = Manifest {
             total_size: 1,
             chunk_size: 1,
@@ -167,4 +295,19 @@ mod tests {
         let got: Auth = postcard::from_bytes(&postcard::to_allocvec(&auth).unwrap()).unwrap();
         assert_eq!(auth, got);
     }
+
+    #[test]
+    fn pull_frames_stay_readable_by_builds_before_bitmaps() {
+        // A pre-bitmap build read `Pull { file_id, have }` and knew only the
+        // first two `ServeResp` variants.
+        let bytes = postcard::to_allocvec(&Pull { file_id: [7u8; 32], have: 3 }).unwrap();
+        assert_eq!(bytes.len(), 33);
+        assert_eq!((&bytes[..32], bytes[32]), (&[7u8; 32][..], 3));
+
+        let manifest = Manifest { total_size: 1, chunk_size: 1, chunks: vec![[1u8; 32]] };
+        let old = postcard::to_allocvec(&ServeResp::Manifest(manifest.clone())).unwrap();
+        assert_eq!(old[0], 0);
+        assert_eq!(postcard::to_allocvec(&ServeResp::Gone).unwrap(), [1]);
+        assert_eq!(postcard::to_allocvec(&ServeResp::Chunks(manifest)).unwrap()[1..], old[1..]);
+    }
 }
@@ -46,7 +46,7 @@ fn main() -> anyhow::Result<()> {
             eprintln!("  invalid queue key len {}", key.len());
             continue;
         };
-        let from = DispatchP::deser(&value[..]).map(|d| short(&d.from.0)).unwrap_or_else(|_| "??".into());
+        let from = DispatchP::deser_stored(&value[..]).map(|d| short(&d.from.0)).unwrap_or_else(|_| "??".into());
         println!(
             "  to={} ts={} dispatch_id={} from={} ({} bytes)",
             short(&parsed.recipient), u64::from_be_bytes(parsed.ts_be), hex::encode(parsed.id), from, value.len()
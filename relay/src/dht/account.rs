@@ -0,0 +1,320 @@
+//! Account deletion: wiping everything the network holds for an identity.
+//!
+//! A device deleting its account sends an owner-signed [`AccountDeletion`]
+//! on its own connection. That relay wipes what it holds for the key, then
+//! sends the statement to every home (see [`super::succession::homes_of`]):
+//! queue homes drop the queue and the presence, push and wake records, stash
+//! homes the KeyPackage and Welcome stashes.
+//!
+//! Each relay that wipes keeps the statement as a tombstone. The store then
+//! refuses writes under the key, the publish handlers refuse its stashes and
+//! dispatches to it, and the client handshake refuses the key itself. A
+//! replica that was down for the wipe and later publishes back doesn't
+//! revive anything. A home asked to queue for the key answers
+//! `ForwardOutcome::Deleted`, which the sender's relay passes on as
+//! `NotFound` so the sender gives up instead of re-dispatching.
+//!
+//! Keys the identity rotated away from go the same way, one
+//! [`PredecessorDeletion`] each: the chain of successions from the old key
+//! to the deleting one stands in for the old key's signature, which its
+//! owner no longer holds. Their homes hold the succession and anything
+//! queued there since the hand-over.
+//!
+//! So do the identity's mailboxes (see `common::crypto::mailbox`), each
+//! deleted under its own blinded key: a mailbox id is a verifying key like
+//! the IPK, so its homes check, wipe and tombstone it the same way. That
+//! drops the sealed mail queued there, the token revocations and what
+//! opening it published, and a contact still holding a token for it gets
+//! `NotFound`.
+//!
+//! [`AccountDeletion`]: common::proto::dht_p2p::AccountDeletion
+
+use std::sync::Arc;
+
+use common::proto::dht_p2p::AccountDeletion;
+use common::proto::dht_p2p::AccountDeletionResp;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::MAX_ACCOUNT_DELETION_SKEW_MS;
+use common::proto::dht_p2p::PredecessorDeletion;
+use common::quic::id::NodeId;
+
+use super::Dht;
+use crate::storage::db::Store;
+
+/// The key signed it, and it isn't dated ahead of us: the timestamp is what
+/// the tombstone is swept by.
+pub(crate) fn valid_deletion(deletion: &AccountDeletion, now_ms: u64) -> bool {
+    deletion.timestamp <= now_ms.saturating_add(MAX_ACCOUNT_DELETION_SKEW_MS) && deletion.verify()
+}
+
+/// As [`valid_deletion`], with an unbroken chain from the old key to the
+/// deleting one.
+pub(crate) fn valid_predecessor_deletion(deletion: &PredecessorDeletion, now_ms: u64) -> bool {
+    valid_deletion(&deletion.deletion, now_ms) && deletion.verify()
+}
+
+/// Tombstone the key in `store` and drop what it holds for it. Returns how
+/// many rows went.
+pub(crate) fn forget(store: &Store, deletion: &AccountDeletion) -> usize {
+    forget_as(store, &deletion.ipk.0, deletion)
+}
+
+/// [`forget`] for `ipk`, which is the deleting key or one it succeeded.
+pub(crate) fn forget_as(store: &Store, ipk: &[u8; 32], deletion: &AccountDeletion) -> usize {
+    if store.put_tombstone(ipk, deletion).unwrap_or(false) {
+        common::info!("DHT: account {} deleted", hex::encode(&ipk[..4]));
+    }
+    store.wipe_identity(ipk).unwrap_or(0)
+}
+
+/// Apply `deletion` here if this relay is one of the key's homes, then send
+/// it to every other one. Returns how many homes wiped it, this relay
+/// included.
+pub(crate) async fn delete_from_homes(dht: Arc<Dht>, deletion: AccountDeletion) -> u32 {
+    let ipk = deletion.ipk.0;
+    let request = DhtRequest::DeleteAccount(deletion.clone());
+    fan_out(dht, &ipk, &deletion, request).await
+}
+
+/// [`delete_from_homes`] for a retired key, at that key's homes.
+pub(crate) async fn delete_predecessor_from_homes(
+    dht: Arc<Dht>, deletion: PredecessorDeletion,
+) -> u32 {
+    let Some(ipk) = deletion.ipk() else { return 0 };
+    let request = DhtRequest::DeletePredecessor(deletion.clone());
+    fan_out(dht, &ipk, &deletion.deletion, request).await
+}
+
+async fn fan_out(
+    dht: Arc<Dht>, ipk: &[u8; 32], deletion: &AccountDeletion, request: DhtRequest,
+) -> u32 {
+    let mut wiped = u32::from(wipe(&dht, ipk, deletion));
+    let mut set = tokio::task::JoinSet::new();
+    for home in super::succession::homes_of(&dht, ipk) {
+        set.spawn(super::succession::request_one(dht.clone(), home, request.clone()));
+    }
+    while let Some(joined) = set.join_next().await {
+        if let Ok(Some(
+            DhtResponse::DeleteAccount(AccountDeletionResp { accepted: true })
+            | DhtResponse::DeletePredecessor(AccountDeletionResp { accepted: true }),
+        )) = joined
+        {
+            wiped += 1;
+        }
+    }
+    wiped
+}
+
+/// A relay passing on a device's deletion. Accepted when it verifies and
+/// this relay is any of the key's homes.
+pub(crate) fn handle_delete_account(
+    dht: &Arc<Dht>, deletion: AccountDeletion, now_ms: u64,
+) -> AccountDeletionResp {
+    if !valid_deletion(&deletion, now_ms) {
+        return AccountDeletionResp { accepted: false };
+    }
+    AccountDeletionResp { accepted: wipe(dht, &deletion.ipk.0, &deletion) }
+}
+
+/// A relay passing on a retired key's deletion, accepted likewise.
+pub(crate) fn handle_delete_predecessor(
+    dht: &Arc<Dht>, deletion: PredecessorDeletion, now_ms: u64,
+) -> AccountDeletionResp {
+    let ipk = match deletion.ipk() {
+        Some(ipk) if valid_predecessor_deletion(&deletion, now_ms) => ipk,
+        _ => return AccountDeletionResp { accepted: false },
+    };
+    AccountDeletionResp { accepted: wipe(dht, &ipk, &deletion.deletion) }
+}
+
+/// Wipe `ipk`'s state if this relay is any of its homes. Returns whether it
+/// is; a relay that isn't keeps no tombstone for it.
+fn wipe(dht: &Arc<Dht>, ipk: &[u8; 32], deletion: &AccountDeletion) -> bool {
+    let mut home = super::routing::self_in_top_k(dht, &NodeId::from_bytes(*ipk));
+    for (stash, ks) in [
+        (super::mls::kp::stash_prefix(ipk), &dht.store.keypackage),
+        (super::mls::welcome::stash_prefix(ipk), &dht.store.welcome),
+    ] {
+        if super::routing::self_in_top_k(dht, &NodeId::from_bytes(stash)) {
+            home = true;
+            let _ = dht.store.remove_prefix(ks, &stash);
+        }
+    }
+    if home {
+        forget_as(&dht.store, ipk, deletion);
+    }
+    home
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+
+    use common::proto::client_rel::DispatchP;
+    use common::proto::client_rel::dispatch_sig_message;
+    use common::proto::dht_p2p::ForwardOutcome;
+    use common::proto::dht_p2p::IdentitySuccession;
+    use common::proto::dht_p2p::account_deletion_signing_input;
+    use common::proto::dht_p2p::identity_succession_signing_input;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+    use crate::dht::DhtConfig;
+    use crate::dht::store::enqueue_for_home;
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    fn fresh_dht() -> Arc<Dht> {
+        static SEQ: AtomicU64 = AtomicU64::new(0);
+        let id = SEQ.fetch_add(1, Ordering::SeqCst);
+        let path = std::env::temp_dir()
+            .join(format!("promtuz-account-test-{}-{id}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let store = Arc::new(Store::open(&path).expect("open store"));
+        let signing = SigningKey::from_bytes(&[id as u8 + 1; 32]);
+        let cfg = DhtConfig::default();
+        Arc::new(Dht::new(NodeId::from_bytes([7; 32]), signing, cfg, store).expect("dht"))
+    }
+
+    fn deletion(key: &SigningKey, timestamp: u64) -> AccountDeletion {
+        let ipk = key.verifying_key().to_bytes();
+        let sig = key.sign(&account_deletion_signing_input(&ipk, timestamp)).to_bytes();
+        AccountDeletion { ipk: ipk.into(), timestamp, sig: sig.into() }
+    }
+
+    fn succession(old: &SigningKey, new: &SigningKey) -> IdentitySuccession {
+        let (old_ipk, new_ipk) = (old.verifying_key().to_bytes(), new.verifying_key().to_bytes());
+        let input = identity_succession_signing_input(&old_ipk, &new_ipk, NOW);
+        IdentitySuccession {
+            old_ipk:   old_ipk.into(),
+            new_ipk:   new_ipk.into(),
+            timestamp: NOW,
+            old_sig:   old.sign(&input).to_bytes().into(),
+            new_sig:   new.sign(&input).to_bytes().into(),
+        }
+    }
+
+    fn dispatch(to: &[u8; 32], id: u8) -> DispatchP {
+        let from = SigningKey::from_bytes(&[9u8; 32]);
+        let from_ipk = from.verifying_key().to_bytes();
+        let sig = from.sign(&dispatch_sig_message(to, &from_ipk, &[id; 16], b"hi"));
+        DispatchP {
+            to:             (*to).into(),
+            from:           from_ipk.into(),
+            id:             [id; 16].into(),
+            payload:        b"hi".to_vec().into(),
+            sig:            sig.to_bytes().into(),
+            accepted_at_ms: 1,
+            wake:           false,
+            wake_hint:      None,
+            token:          None,
+        }
+    }
+
+    fn queued(dht: &Dht, ipk: &[u8; 32]) -> bool {
+        dht.store.queue.prefix(ipk).next().is_some()
+    }
+
+    #[test]
+    fn deletion_needs_the_keys_signature_and_no_future_date() {
+        let key = SigningKey::from_bytes(&[6u8; 32]);
+        let deletion = deletion(&key, NOW);
+        assert!(valid_deletion(&deletion, NOW));
+        assert!(valid_deletion(&deletion, NOW + 30 * 24 * 60 * 60 * 1000), "sent late");
+        assert!(!valid_deletion(&deletion, NOW - MAX_ACCOUNT_DELETION_SKEW_MS - 1), "dated ahead");
+
+        let other = SigningKey::from_bytes(&[7u8; 32]).verifying_key().to_bytes();
+        let someone_else = AccountDeletion { ipk: other.into(), ..deletion };
+        assert!(!valid_deletion(&someone_else, NOW), "nobody deletes another's account");
+    }
+
+    /// A home wipes the queue, keeps the tombstone, counts itself among the
+    /// homes that wiped, and refuses later dispatches as `Deleted`.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_home_wipes_the_account_counts_itself_and_refuses_it_after() {
+        let dht = fresh_dht();
+        let key = SigningKey::from_bytes(&[6u8; 32]);
+        let ipk = key.verifying_key().to_bytes();
+        assert_eq!(enqueue_for_home(&dht, &ipk, &dispatch(&ipk, 1), NOW), ForwardOutcome::Stored);
+
+        assert_eq!(delete_from_homes(dht.clone(), deletion(&key, NOW)).await, 1);
+        assert!(!queued(&dht, &ipk));
+        assert!(dht.store.is_tombstoned(&ipk));
+        assert_eq!(enqueue_for_home(&dht, &ipk, &dispatch(&ipk, 2), NOW), ForwardOutcome::Deleted);
+        assert!(!queued(&dht, &ipk));
+    }
+
+    #[test]
+    fn a_home_passed_a_forged_deletion_keeps_the_account() {
+        let dht = fresh_dht();
+        let key = SigningKey::from_bytes(&[6u8; 32]);
+        let ipk = key.verifying_key().to_bytes();
+        enqueue_for_home(&dht, &ipk, &dispatch(&ipk, 1), NOW);
+
+        let forged = AccountDeletion {
+            ipk: ipk.into(),
+            ..deletion(&SigningKey::from_bytes(&[8u8; 32]), NOW)
+        };
+        assert!(!handle_delete_account(&dht, forged, NOW).accepted);
+        assert!(queued(&dht, &ipk));
+        assert!(!dht.store.is_tombstoned(&ipk));
+        assert!(handle_delete_account(&dht, deletion(&key, NOW), NOW).accepted);
+        assert!(!queued(&dht, &ipk));
+    }
+
+    /// The old key's home drops the succession and what was queued since the
+    /// hand-over on the chain's word, and only on an unbroken chain.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_retired_key_goes_with_the_account_through_its_chain() {
+        let dht = fresh_dht();
+        let (old, mid, new) = (
+            SigningKey::from_bytes(&[1u8; 32]),
+            SigningKey::from_bytes(&[2u8; 32]),
+            SigningKey::from_bytes(&[3u8; 32]),
+        );
+        let old_ipk = old.verifying_key().to_bytes();
+        let chain = vec![succession(&old, &mid), succession(&mid, &new)];
+        dht.store.put_succession(&chain[0]).unwrap();
+        enqueue_for_home(&dht, &old_ipk, &dispatch(&old_ipk, 1), NOW);
+
+        let short =
+            PredecessorDeletion { chain: chain[..1].to_vec(), deletion: deletion(&new, NOW) };
+        assert!(!handle_delete_predecessor(&dht, short, NOW).accepted, "the chain stops short");
+        assert!(dht.store.get_succession(&old_ipk).is_some());
+
+        let whole = PredecessorDeletion { chain, deletion: deletion(&new, NOW) };
+        assert_eq!(delete_predecessor_from_homes(dht.clone(), whole).await, 1);
+        assert!(dht.store.get_succession(&old_ipk).is_none());
+        assert!(!queued(&dht, &old_ipk));
+        assert!(dht.store.is_tombstoned(&old_ipk));
+        assert!(!dht.store.is_tombstoned(&new.verifying_key().to_bytes()), "only the retired key");
+    }
+
+    /// A mailbox is deleted under its own blinded key: its home wipes the
+    /// sealed mail queued there and refuses later sends to it as `Deleted`.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_mailbox_goes_under_its_own_key() {
+        use common::crypto::mailbox::MailboxKey;
+        use common::crypto::mailbox::epoch_of;
+        use common::crypto::mailbox::seed_for;
+
+        let dht = fresh_dht();
+        let owner = SigningKey::from_bytes(&[6u8; 32]);
+        let key = MailboxKey::derive(&owner, &seed_for(&owner), epoch_of(NOW));
+        let mailbox = key.id();
+        enqueue_for_home(&dht, &mailbox, &dispatch(&mailbox, 1), NOW);
+        assert!(queued(&dht, &mailbox));
+
+        let sig = key.sign(&account_deletion_signing_input(&mailbox, NOW)).to_bytes();
+        let deletion = AccountDeletion { ipk: mailbox.into(), timestamp: NOW, sig: sig.into() };
+        assert_eq!(delete_from_homes(dht.clone(), deletion).await, 1);
+        assert!(!queued(&dht, &mailbox));
+        assert!(dht.store.is_tombstoned(&mailbox));
+        assert!(!dht.store.is_tombstoned(&owner.verifying_key().to_bytes()), "only the mailbox");
+        let again = enqueue_for_home(&dht, &mailbox, &dispatch(&mailbox, 2), NOW);
+        assert_eq!(again, ForwardOutcome::Deleted);
+    }
+}
@@ -0,0 +1,100 @@
+//! Relay-to-relay reads of the blob store.
+//!
+//! A blob lives only on the relay its uploader was connected to; the
+//! recipient names that host (from the `FileBlob` message) and its own relay
+//! asks the host for the manifest or a chunk over `peer/5`. The host is found
+//! like any other node: the routing table first, then an iterative lookup on
+//! its id. Nothing is replicated — a host that goes away takes its blobs with
+//! it, and the recipient falls back to asking the sender directly.
+//!
+//! The requester re-verifies each chunk against the manifest and the manifest
+//! against the `blob_id`, so a host can withhold a blob but not alter one.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::proto::blob::BlobManifest;
+use common::proto::dht_p2p::BlobFetch;
+use common::proto::dht_p2p::BlobFetchResp;
+use common::proto::dht_p2p::DhtPacket;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::NodeDescriptor;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use quinn::Connection;
+use tokio::time::timeout;
+
+use super::Dht;
+use super::config::FORWARD_TIMEOUT_MS;
+
+/// A blob's manifest from `host`, checked against `blob_id`.
+pub(crate) async fn fetch_manifest(
+    dht: Arc<Dht>, host: NodeId, blob_id: [u8; 32],
+) -> Option<BlobManifest> {
+    let req = BlobFetch { blob_id: blob_id.into(), index: None };
+    let manifest = request(dht, host, req).await?.manifest?;
+    (manifest.blob_id() == blob_id).then_some(manifest)
+}
+
+/// One ciphertext chunk from `host`, unverified: the caller holds the
+/// manifest it must match.
+pub(crate) async fn fetch_chunk(
+    dht: Arc<Dht>, host: NodeId, blob_id: [u8; 32], index: u32,
+) -> Option<Vec<u8>> {
+    let req = BlobFetch { blob_id: blob_id.into(), index: Some(index) };
+    request(dht, host, req).await?.chunk.map(|c| c.0)
+}
+
+/// Serve a peer's read from the local store; empty unless this relay carries
+/// `BLOB_STORE` and holds a live copy.
+pub(crate) fn handle_fetch(dht: &Dht, req: BlobFetch, now_ms: u64) -> BlobFetchResp {
+    let mut resp = BlobFetchResp { manifest: None, chunk: None };
+    if !dht.blob_store {
+        return resp;
+    }
+    match req.index {
+        None => resp.manifest = dht.store.blob_manifest(&req.blob_id.0, now_ms),
+        Some(index) => {
+            resp.chunk = dht.store.blob_chunk(&req.blob_id.0, index, now_ms).map(Into::into)
+        },
+    }
+    resp
+}
+
+async fn request(dht: Arc<Dht>, host: NodeId, req: BlobFetch) -> Option<BlobFetchResp> {
+    timeout(Duration::from_millis(FORWARD_TIMEOUT_MS), async {
+        let conn = host_connection(&dht, host).await?;
+        let bytes = DhtPacket::Request(DhtRequest::BlobFetch(req)).pack().ok()?;
+        let (mut tx, mut rx) = conn.open_bi().await.ok()?;
+        tx.write_all(&bytes).await.ok()?;
+        tx.finish().ok()?;
+        match DhtPacket::unpack(&mut rx).await.ok()? {
+            DhtPacket::Response(DhtResponse::BlobFetch(resp)) => Some(resp),
+            _ => None,
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// A live `peer/5` connection to `host`: the cached one, else a dial to the
+/// descriptor the routing table or a lookup turns up.
+async fn host_connection(dht: &Arc<Dht>, host: NodeId) -> Option<Connection> {
+    let cached = dht.peer_conns.read().get(&host).map(|(conn, _)| conn.clone());
+    if let Some(conn) = cached.filter(|c| c.close_reason().is_none()) {
+        return Some(conn);
+    }
+    let descriptor = host_descriptor(dht, host).await?;
+    super::lookup::connect_to_peer(dht, &descriptor).await.ok()
+}
+
+async fn host_descriptor(dht: &Arc<Dht>, host: NodeId) -> Option<NodeDescriptor> {
+    let known = dht.routing.read().find_closest(&host, 1).into_iter().find(|d| d.id == host);
+    if known.is_some() {
+        return known;
+    }
+    super::lookup::lookup_node(dht.clone(), host).await.ok()?.into_iter().find(|d| d.id == host)
+}
@@ -172,6 +172,10 @@ pub async fn bootstrap(
                 InsertOutcome::Inserted => inserted += 1,
                 InsertOutcome::Refreshed => refreshed += 1,
                 InsertOutcome::Discarded => deferred += 1,
+                InsertOutcome::PrefixLimited => {
+                    dht.metrics.inc_prefix_limited_inserts();
+                    deferred += 1;
+                },
                 outcome @ InsertOutcome::PendingPing(_) => {
                     deferred += 1;
                     pending.push(outcome);
@@ -44,6 +44,35 @@ pub const LOOKUP_MAX_HOPS: u32 = 8;
 /// the closest `MAX_LOOKUP_CANDIDATES` after each hop's merge.
 pub const MAX_LOOKUP_CANDIDATES: usize = 64;
 
+/// Disjoint paths per lookup (S/Kademlia's `d`). Each path walks with
+/// [`ALPHA`] parallelism and no peer is queried by two paths, so an
+/// attacker must sit on every path to hide the true K-closest set.
+pub const LOOKUP_DISJOINT_PATHS: usize = 3;
+
+// ---------------------------------------------------------------------------
+// Routing-table admission
+// ---------------------------------------------------------------------------
+
+/// IPv4 prefix length that counts as "one operator" for the limits below.
+pub const PEER_PREFIX_V4_BITS: u32 = 24;
+
+/// IPv6 prefix length that counts as "one operator" — a typical end-site
+/// allocation.
+pub const PEER_PREFIX_V6_BITS: u32 = 48;
+
+/// Most peers one address prefix may hold in a single bucket (active plus
+/// replacement cache). Keeps a host with many minted NodeIds from filling
+/// the bucket nearest a target.
+pub const MAX_PEERS_PER_PREFIX_PER_BUCKET: usize = 2;
+
+/// Most peers one address prefix may hold across the whole table.
+pub const MAX_PEERS_PER_PREFIX: usize = 8;
+
+/// Most dials a relay makes per minute to attest peers first seen inbound.
+/// Each inbound connection from a stranger costs a dial back, so without a
+/// cap a stream of connects turns this relay into a traffic amplifier.
+pub const MAX_ATTESTATION_DIALS_PER_MINUTE: u32 = 30;
+
 // ---------------------------------------------------------------------------
 // Presence record lifetimes
 // ---------------------------------------------------------------------------
@@ -255,6 +284,139 @@ pub const RATE_LIMIT_BULK_BURST: u32 = 250;
 pub const RATE_LIMIT_GLOBAL_PER_SEC: u32 = 10_000;
 pub const RATE_LIMIT_GLOBAL_BURST: u32 = 5_000;
 
+// ---------------------------------------------------------------------------
+// Peer reputation
+// ---------------------------------------------------------------------------
+//
+// Each authenticated NodeId accrues penalty points for provable misbehaviour;
+// points halve every `REPUTATION_HALF_LIFE_SECS`. Points are only ever
+// charged to an id bound by a verified `DhtHello` or dial cert, so a peer
+// cannot spend another relay's reputation.
+
+/// Half-life of a penalty. Long enough that a peer tripping the limiter
+/// once a minute climbs steadily, short enough that one bad burst is
+/// forgotten within the hour.
+pub const REPUTATION_HALF_LIFE_SECS: u64 = 15 * 60;
+
+/// A signature that fails to verify under a key the peer vouched for.
+pub const PENALTY_BAD_SIGNATURE: f64 = 20.0;
+
+/// A timestamp outside the skew window. Cheap: an honest relay with a
+/// drifting clock trips it too.
+pub const PENALTY_STALE_TIMESTAMP: f64 = 2.0;
+
+/// A rate-limit trip. The connection is closed as well, so each charge
+/// also costs the peer a reconnect.
+pub const PENALTY_FLOOD: f64 = 25.0;
+
+/// A frame that does not decode, or a packet on the wrong side.
+pub const PENALTY_MALFORMED: f64 = 10.0;
+
+/// Score at which a peer is deprioritised: left out of `FindNode` replies and
+/// first to be replaced when its bucket fills.
+pub const REPUTATION_DEPRIORITIZE_AT: f64 = 20.0;
+
+/// Score at which a peer is banned: evicted from routing, its connection
+/// closed, and refused on accept and dial for [`REPUTATION_BAN_SECS`].
+pub const REPUTATION_BAN_AT: f64 = 100.0;
+
+pub const REPUTATION_BAN_SECS: u64 = 60 * 60;
+
+/// Ceiling on tracked peers. Past it the lowest-scoring unbanned entries are
+/// forgotten first.
+pub const MAX_REPUTATION_ENTRIES: usize = 16_384;
+
+// ---------------------------------------------------------------------------
+// Home-relay storage quotas
+// ---------------------------------------------------------------------------
+//
+// Byte budgets layered on top of the row caps (`MAX_QUEUED_PER_RECIPIENT`,
+// `MAX_WELCOMES_PER_RECIPIENT`). Rows are bounded by `MAX_FRAME_BYTES`, so
+// a row cap alone still lets one sender park ~1 GiB per offline recipient.
+// Each default is overridable on [`DhtConfig`]; they are local storage policy,
+// not protocol, so relays need not agree.
+
+/// Queued bytes one recipient may hold across `dht_queue` (and, separately,
+/// the `messages` fallback queue). 16 MiB is the old ~4 KiB-per-row estimate
+/// at the row cap, with room for a handful of inline-media messages.
+pub const QUEUE_QUOTA_PER_RECIPIENT: u64 = 16 * 1024 * 1024;
+
+/// One sender's share of [`QUEUE_QUOTA_PER_RECIPIENT`]. A quarter, so one
+/// chatty or hostile sender can never crowd the rest out of the queue, while
+/// still fitting a few `MAX_FRAME_BYTES` messages.
+pub const QUEUE_QUOTA_PER_SENDER: u64 = 4 * 1024 * 1024;
+
+/// Stored `dht_welcome` bytes per recipient. The row cap times
+/// `MAX_WELCOME_BYTES` is 8 MiB; real Welcomes for small groups are a few KiB.
+pub const WELCOME_QUOTA_PER_RECIPIENT: u64 = 2 * 1024 * 1024;
+
+/// One inviter's share of [`WELCOME_QUOTA_PER_RECIPIENT`] — just under two
+/// maximal Welcomes once the envelope is counted.
+pub const WELCOME_QUOTA_PER_SENDER: u64 = 512 * 1024;
+
+/// Per-class overrides of the `RATE_LIMIT_*` constants, as `[dht.rate_limits]`.
+/// Each `None` keeps its constant. Unlike the protocol parameters these are
+/// local admission policy, so relays may disagree.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DhtRateLimits {
+    #[serde(default)]
+    pub cheap_per_sec:     Option<u32>,
+    #[serde(default)]
+    pub cheap_burst:       Option<u32>,
+    #[serde(default)]
+    pub expensive_per_sec: Option<u32>,
+    #[serde(default)]
+    pub expensive_burst:   Option<u32>,
+    #[serde(default)]
+    pub bulk_per_sec:      Option<u32>,
+    #[serde(default)]
+    pub bulk_burst:        Option<u32>,
+    #[serde(default)]
+    pub global_per_sec:    Option<u32>,
+    #[serde(default)]
+    pub global_burst:      Option<u32>,
+}
+
+impl DhtRateLimits {
+    /// Effective `(per_sec, burst)` for the cheap class.
+    pub fn cheap(&self) -> (u32, u32) {
+        (
+            self.cheap_per_sec.unwrap_or(RATE_LIMIT_CHEAP_PER_SEC),
+            self.cheap_burst.unwrap_or(RATE_LIMIT_CHEAP_BURST),
+        )
+    }
+
+    pub fn expensive(&self) -> (u32, u32) {
+        (
+            self.expensive_per_sec.unwrap_or(RATE_LIMIT_EXPENSIVE_PER_SEC),
+            self.expensive_burst.unwrap_or(RATE_LIMIT_EXPENSIVE_BURST),
+        )
+    }
+
+    pub fn bulk(&self) -> (u32, u32) {
+        (
+            self.bulk_per_sec.unwrap_or(RATE_LIMIT_BULK_PER_SEC),
+            self.bulk_burst.unwrap_or(RATE_LIMIT_BULK_BURST),
+        )
+    }
+
+    pub fn global(&self) -> (u32, u32) {
+        (
+            self.global_per_sec.unwrap_or(RATE_LIMIT_GLOBAL_PER_SEC),
+            self.global_burst.unwrap_or(RATE_LIMIT_GLOBAL_BURST),
+        )
+    }
+}
+
+/// Effective byte quota for one queue keyspace, resolved from [`DhtConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueQuota {
+    pub per_recipient: u64,
+    /// Never above `per_recipient`.
+    pub per_sender:    u64,
+}
+
 // ---------------------------------------------------------------------------
 // Operator-tunable config (TOML-deserialisable)
 // ---------------------------------------------------------------------------
@@ -291,6 +453,35 @@ pub struct DhtConfig {
     /// single-host test clusters.
     #[serde(default)]
     pub allow_local_peer_addrs: bool,
+
+    /// Only admit peers whose CA-signed leaf carries the `RELAY`
+    /// capability. Checked on every outbound dial; an inbound-only peer
+    /// enters the routing table once a dial back to it passes the check.
+    /// Off by default so meshes with pre-capability certs keep working;
+    /// turn it on once every relay cert is minted with `--cap relay`.
+    #[serde(default)]
+    pub require_relay_attestation: bool,
+
+    /// Override of [`QUEUE_QUOTA_PER_RECIPIENT`], in bytes. Applies to both
+    /// the home queue and the local fallback queue.
+    #[serde(default)]
+    pub queue_quota_per_recipient: Option<u64>,
+
+    /// Override of [`QUEUE_QUOTA_PER_SENDER`], in bytes.
+    #[serde(default)]
+    pub queue_quota_per_sender: Option<u64>,
+
+    /// Override of [`WELCOME_QUOTA_PER_RECIPIENT`], in bytes.
+    #[serde(default)]
+    pub welcome_quota_per_recipient: Option<u64>,
+
+    /// Override of [`WELCOME_QUOTA_PER_SENDER`], in bytes.
+    #[serde(default)]
+    pub welcome_quota_per_sender: Option<u64>,
+
+    /// Inbound-RPC rate-limit overrides.
+    #[serde(default)]
+    pub rate_limits: DhtRateLimits,
 }
 
 impl DhtConfig {
@@ -299,4 +490,19 @@ impl DhtConfig {
     pub fn bucket_size(&self) -> usize {
         self.bucket_size.unwrap_or(BUCKET_SIZE)
     }
+
+    /// Byte quota for the `dht_queue` and `messages` keyspaces.
+    pub fn queue_quota(&self) -> QueueQuota {
+        let per_recipient = self.queue_quota_per_recipient.unwrap_or(QUEUE_QUOTA_PER_RECIPIENT);
+        let per_sender = self.queue_quota_per_sender.unwrap_or(QUEUE_QUOTA_PER_SENDER);
+        QueueQuota { per_recipient, per_sender: per_sender.min(per_recipient) }
+    }
+
+    /// Byte quota for the `dht_welcome` keyspace.
+    pub fn welcome_quota(&self) -> QueueQuota {
+        let per_recipient =
+            self.welcome_quota_per_recipient.unwrap_or(WELCOME_QUOTA_PER_RECIPIENT);
+        let per_sender = self.welcome_quota_per_sender.unwrap_or(WELCOME_QUOTA_PER_SENDER);
+        QueueQuota { per_recipient, per_sender: per_sender.min(per_recipient) }
+    }
 }
@@ -44,6 +44,7 @@ use common::proto::dht_p2p::DhtResponse;
 use common::proto::dht_p2p::Forward;
 use common::proto::dht_p2p::ForwardOutcome;
 use common::proto::dht_p2p::ForwardResp;
+use common::proto::dht_p2p::ForwardVerifyError;
 use common::proto::dht_p2p::LiveForward;
 use common::proto::dht_p2p::LiveForwardResp;
 use common::proto::dht_p2p::NodeDescriptor;
@@ -67,6 +68,7 @@ use super::Dht;
 use super::config::FORWARD_K_MIN;
 use super::config::FORWARD_TIMEOUT_MS;
 use super::config::K;
+use super::sealed::SealedAdmission;
 
 // ---------------------------------------------------------------------------
 // Public types
@@ -122,6 +124,38 @@ impl ForwardSummary {
     pub fn meets_k_min(&self) -> bool {
         self.success_count() >= FORWARD_K_MIN
     }
+
+    /// True iff [`ForwardOutcome::QuotaExceeded`] refusals alone put quorum
+    /// out of reach. The sender relay then reports the quota to the client
+    /// instead of parking the dispatch in its own fallback queue, which would
+    /// let a sender sidestep the homes' quota.
+    pub fn refused_for_quota(&self) -> bool {
+        let refused = self
+            .failed_at
+            .iter()
+            .filter(|r| r.outcome == ForwardOutcome::QuotaExceeded)
+            .count();
+        refused > 0 && self.homes_tried.len() - refused < FORWARD_K_MIN
+    }
+
+    /// True iff [`ForwardOutcome::BadToken`] refusals alone put quorum out of
+    /// reach: the homes hold a revocation the ingress relay did not, so the
+    /// client hears `TokenRejected` rather than a generic failure.
+    pub fn refused_token(&self) -> bool {
+        let refused =
+            self.failed_at.iter().filter(|r| r.outcome == ForwardOutcome::BadToken).count();
+        refused > 0 && self.homes_tried.len() - refused < FORWARD_K_MIN
+    }
+
+    /// True iff [`ForwardOutcome::Deleted`] refusals alone put quorum out of
+    /// reach: the recipient's homes hold its tombstone, so the client hears
+    /// `NotFound` and stops, where the fallback queue would keep the dispatch
+    /// for nobody.
+    pub fn refused_deleted(&self) -> bool {
+        let refused =
+            self.failed_at.iter().filter(|r| r.outcome == ForwardOutcome::Deleted).count();
+        refused > 0 && self.homes_tried.len() - refused < FORWARD_K_MIN
+    }
 }
 
 /// Failure modes for the fan-out path. Distinguishes "we couldn't even
@@ -239,7 +273,7 @@ pub(crate) async fn forward_to_homes(
             if matches!(stored, ForwardOutcome::Stored)
                 && dht.store.persist_barrier().wait().await.is_err()
             {
-                ForwardOutcome::BadSig
+                ForwardOutcome::Internal
             } else {
                 stored
             }
@@ -250,7 +284,11 @@ pub(crate) async fn forward_to_homes(
                 summary.stored_at.push(self_id);
                 // Only new content push-wakes; receipts/edits/etc. wait for drain.
                 if dispatch.wake {
-                    dht.trigger_wake(&user_ipk_bytes);
+                    dht.trigger_wake(
+                        &user_ipk_bytes,
+                        &dispatch.from.0,
+                        dispatch.wake_hint.as_deref().map(Vec::as_slice),
+                    );
                 }
             },
             other => summary.failed_at.push(HomeReply { node_id: self_id, outcome: other }),
@@ -620,8 +658,14 @@ async fn remote_forward_one(
 ) -> Option<ForwardOutcome> {
     let conn = super::lookup::connect_to_peer(dht, peer).await.ok()?;
 
-    let pkt = DhtPacket::Request(DhtRequest::Forward(forward.clone()));
-    let bytes = pkt.pack().ok()?;
+    // A v6 home gets the dispatch without its wake hint; a sealed one has no
+    // v6 layout, so packing fails and that home counts as unreached.
+    let req = if super::carries_whole_dispatches(&conn) {
+        DhtRequest::ForwardV7(forward.clone())
+    } else {
+        DhtRequest::Forward(forward.clone())
+    };
+    let bytes = DhtPacket::Request(req).pack().ok()?;
 
     let (mut send, mut recv) = conn.open_bi().await.ok()?;
     send.write_all(&bytes).await.ok()?;
@@ -664,7 +708,7 @@ async fn remote_forward_one(
 ///    deliver via the same `try_deliver` path the sender-side `handle_forward` uses. Return
 ///    `Delivered` on success.
 /// 6. Otherwise enqueue via [`super::store::enqueue_for_home`] (which enforces the per-recipient
-///    cap and returns `Stored` / `QueueFull` accordingly).
+///    cap and byte quota and returns `Stored` / `QueueFull` / `QuotaExceeded` accordingly).
 ///
 /// **Outcome semantics**: see [`ForwardOutcome`] — every rejection
 /// path returns a distinct outcome variant; the dispatcher does not
@@ -683,12 +727,20 @@ pub(crate) async fn handle_forward_rpc(dht: &Arc<Dht>, fwd: Forward, now_ms: u64
     //    a verified pubkey when the routing entry doesn't.
     let sender_pubkey = match resolve_sender_pubkey(dht, &fwd.sender_relay_id) {
         Some(pk) => pk,
-        None => return ForwardResp { outcome: ForwardOutcome::BadSig },
+        None => return ForwardResp { outcome: ForwardOutcome::UnknownSender },
     };
 
-    // 2. Outer sender-relay signature + skew check.
-    if fwd.verify(&sender_pubkey, now_ms).is_err() {
-        return ForwardResp { outcome: ForwardOutcome::BadSig };
+    // 2. Outer sender-relay signature + skew check. Only a signature that
+    //    was actually checked and failed reads as `BadSig`.
+    match fwd.verify(&sender_pubkey, now_ms) {
+        Ok(()) => {},
+        Err(ForwardVerifyError::StaleTimestamp | ForwardVerifyError::FutureTimestamp) => {
+            return ForwardResp { outcome: ForwardOutcome::StaleTimestamp };
+        },
+        Err(ForwardVerifyError::MalformedField) => {
+            return ForwardResp { outcome: ForwardOutcome::UnknownSender };
+        },
+        Err(_) => return ForwardResp { outcome: ForwardOutcome::BadSig },
     }
 
     // 3. Are we in the recipient's K-closest? Defensive — sender shouldn't have routed here
@@ -698,10 +750,20 @@ pub(crate) async fn handle_forward_rpc(dht: &Arc<Dht>, fwd: Forward, now_ms: u64
         return ForwardResp { outcome: ForwardOutcome::NotOwner };
     }
 
-    // 4. Embedded user-layer dispatch signature.
+    // 4. Embedded user-layer dispatch signature, and for a sealed dispatch
+    //    the recipient's token: a home re-checks both rather than trust the
+    //    ingress relay's word. A token over its rate reads as a quota refusal;
+    //    a bad or revoked one is the recipient's call, not the sender relay's fault.
     if !verify_dispatch_user_sig(&fwd.dispatch) {
         return ForwardResp { outcome: ForwardOutcome::BadSig };
     }
+    match super::sealed::admit(dht, &fwd.dispatch, now_ms) {
+        SealedAdmission::Accept => {},
+        SealedAdmission::BadToken => return ForwardResp { outcome: ForwardOutcome::BadToken },
+        SealedAdmission::OverQuota => {
+            return ForwardResp { outcome: ForwardOutcome::QuotaExceeded };
+        },
+    }
 
     // 5. Online-recipient short-circuit. Snapshot the connection out of the lock before any await
     //    (project-wide rule); the `clients` map field is `Option` so unit-test fixtures can skip
@@ -744,10 +806,20 @@ pub(crate) async fn handle_forward_rpc(dht: &Arc<Dht>, fwd: Forward, now_ms: u64
     let outcome = super::store::enqueue_for_home(dht, &recipient_ipk, &fwd.dispatch, now_ms);
     if matches!(outcome, ForwardOutcome::Stored) {
         if dht.store.persist_barrier().wait().await.is_err() {
-            return ForwardResp { outcome: ForwardOutcome::BadSig };
+            return ForwardResp { outcome: ForwardOutcome::Internal };
         }
-        if fwd.dispatch.wake {
-            dht.trigger_wake(&recipient_ipk);
+        // A key that has been succeeded has no device left to wake; pass
+        // the dispatch on to the new key's homes instead. A contested one
+        // keeps it, unwoken, until the queue expires.
+        let successions = dht.store.successions(&recipient_ipk);
+        if let [succession] = successions.as_slice() {
+            tokio::spawn(super::succession::hand_over_queue(dht.clone(), succession.clone()));
+        } else if successions.is_empty() && fwd.dispatch.wake {
+            dht.trigger_wake(
+                &recipient_ipk,
+                &fwd.dispatch.from.0,
+                fwd.dispatch.wake_hint.as_deref().map(Vec::as_slice),
+            );
         }
     }
     ForwardResp { outcome }
@@ -772,14 +844,19 @@ fn forward_via_active_lease<'a>(
         let sig = dht.signing_key.sign(&live_forward_signing_input(
             &dispatch.id.0, &lease, &dht.node_id, timestamp,
         ));
-        let Ok(bytes) = DhtPacket::Request(DhtRequest::LiveForward(LiveForward {
+        let forward = LiveForward {
             dispatch: dispatch.clone(),
             lease,
             sender_relay_id: dht.node_id,
             timestamp,
             sig: sig.to_bytes().into(),
-        }))
-        .pack() else {
+        };
+        let req = if super::carries_whole_dispatches(&conn) {
+            DhtRequest::LiveForwardV7(forward)
+        } else {
+            DhtRequest::LiveForward(forward)
+        };
+        let Ok(bytes) = DhtPacket::Request(req).pack() else {
             return false;
         };
         let Ok((mut tx, mut rx)) = conn.open_bi().await else { return false };
@@ -841,7 +918,7 @@ fn resolve_sender_pubkey(dht: &Dht, sender_relay_id: &NodeId) -> Option<[u8; 32]
 /// the canonical [`dispatch_sig_message`] transcript. This is the
 /// *embedded* signature `Forward::verify` deliberately does not check
 /// (two-layer signing contract).
-fn verify_dispatch_user_sig(dispatch: &DispatchP) -> bool {
+pub(crate) fn verify_dispatch_user_sig(dispatch: &DispatchP) -> bool {
     let Ok(vk) = VerifyingKey::from_bytes(&dispatch.from.0) else {
         return false;
     };
@@ -937,6 +1014,8 @@ mod tests {
             sig: sig.to_bytes().into(),
             accepted_at_ms: 1,
             wake: false,
+            wake_hint: None,
+            token: None,
         }
     }
 
@@ -1041,4 +1120,31 @@ mod tests {
             "self-store must have written to dht_queue"
         );
     }
+
+    /// A home holding the recipient's tombstone refuses it as `Deleted`, and
+    /// that refusal alone losing quorum tells the caller to stop rather than
+    /// queue the dispatch in its fallback.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_deleted_recipient_is_refused_as_deleted_and_nothing_is_queued() {
+        use common::proto::dht_p2p::AccountDeletion;
+        use common::proto::dht_p2p::account_deletion_signing_input;
+
+        let dht = fresh_dht(id_for(1));
+        let to_user = fresh_signing_key();
+        let to_ipk: [u8; 32] = to_user.verifying_key().to_bytes();
+        let now: u64 = 1_700_000_000_000;
+        let sig = to_user.sign(&account_deletion_signing_input(&to_ipk, now)).to_bytes();
+        let deletion = AccountDeletion { ipk: to_ipk.into(), timestamp: now, sig: sig.into() };
+        assert!(dht.store.put_tombstone(&to_ipk, &deletion).unwrap());
+
+        let dispatch = build_dispatch(&fresh_signing_key(), &to_ipk, [2u8; 16], b"hi");
+        match forward_to_homes(dht.clone(), dispatch, now).await {
+            Err(ForwardError::InsufficientReplicas { summary, .. }) => {
+                assert!(summary.refused_deleted());
+                assert!(!summary.refused_for_quota());
+            },
+            other => panic!("expected InsufficientReplicas, got {other:?}"),
+        }
+        assert!(dht.store.queue.prefix(to_ipk).next().is_none());
+    }
 }
@@ -0,0 +1,266 @@
+//! Pooled `relay/5` connections to push gateways.
+//!
+//! The wake batcher in [`super::push_wake`] sends every batch over a
+//! connection held here instead of dialing per wake. A gateway is dialed and
+//! its `PUSH_GATEWAY` capability verified once; the connection is then reused
+//! until QUIC closes it (idle timeout, gateway restart) or a send over it
+//! fails.
+//!
+//! ## Health and failover
+//!
+//! Each gateway carries a consecutive-failure count. A failure drops the
+//! cached connection and backs the gateway off, doubling from
+//! [`GATEWAY_BACKOFF_BASE`] up to [`GATEWAY_BACKOFF_MAX`]; one success
+//! clears it. [`GatewayPool::targets`] ranks healthy gateways ahead of
+//! backed-off ones, so a dead gateway's slot in the fan-out passes to the
+//! next one in the directory and is only retried once its backoff lapses or
+//! nobody healthier is left.
+//!
+//! There is no probe: health is inferred from wake sends alone, so a gateway
+//! that went down between batches is only noticed by the next batch to it.
+//!
+//! ## Batch support
+//!
+//! A gateway that predates `WakeBatch` doesn't acknowledge one. The slot then
+//! notes that its connection takes single `Wake`s only; a fresh dial clears
+//! the note, so an upgraded gateway gets batches again.
+//!
+//! ## Lock contract
+//!
+//! One `parking_lot::Mutex` over the map, never held across an `await`:
+//! dials run outside it and the result is installed afterwards.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use common::node::capability::NodeCapabilities;
+use common::proto::client_res::GatewayDescriptor;
+use common::quic::id::NodeId;
+use parking_lot::Mutex;
+use quinn::Connection;
+use quinn::Endpoint;
+
+/// First backoff after a gateway fails; doubles per consecutive failure.
+const GATEWAY_BACKOFF_BASE: Duration = Duration::from_secs(5);
+
+/// Ceiling on the backoff, so a gateway that comes back is picked up again
+/// within a few minutes.
+const GATEWAY_BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+struct Slot {
+    conn:         Option<Connection>,
+    failures:     u32,
+    down_until:   Option<Instant>,
+    singles_only: bool,
+}
+
+impl Slot {
+    fn is_down(&self, now: Instant) -> bool {
+        self.down_until.is_some_and(|until| now < until)
+    }
+
+    /// The cached connection if QUIC still considers it open.
+    fn live_conn(&mut self) -> Option<Connection> {
+        if self.conn.as_ref().is_some_and(|c| c.close_reason().is_some()) {
+            self.conn = None;
+        }
+        self.conn.clone()
+    }
+}
+
+/// Verified gateway connections plus per-gateway health. See the module docs.
+#[derive(Debug, Default)]
+pub(crate) struct GatewayPool {
+    slots: Mutex<HashMap<NodeId, Slot>>,
+}
+
+impl GatewayPool {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Up to `n` gateways from `directory`, healthy ones first. Each group
+    /// keeps id order, so relays fan out to the same subset while it is up.
+    pub(crate) fn targets(
+        &self, directory: &[GatewayDescriptor], n: usize,
+    ) -> Vec<GatewayDescriptor> {
+        self.targets_at(directory, n, Instant::now())
+    }
+
+    fn targets_at(
+        &self, directory: &[GatewayDescriptor], n: usize, now: Instant,
+    ) -> Vec<GatewayDescriptor> {
+        let slots = self.slots.lock();
+        let mut ranked = directory.to_vec();
+        ranked.sort_by_key(|g| (slots.get(&g.id).is_some_and(|s| s.is_down(now)), g.id));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// The pooled connection to `gateway`, dialing and verifying a new one
+    /// if none is open.
+    pub(crate) async fn connection(
+        &self, endpoint: &Endpoint, gateway: &GatewayDescriptor,
+    ) -> Result<Connection> {
+        if let Some(conn) = self.slots.lock().get_mut(&gateway.id).and_then(Slot::live_conn) {
+            return Ok(conn);
+        }
+        let conn = dial(endpoint, gateway).await?;
+
+        let mut slots = self.slots.lock();
+        let slot = slots.entry(gateway.id).or_default();
+        // A concurrent flush may have dialed the same gateway; keep one.
+        if let Some(existing) = slot.live_conn() {
+            conn.close(0u32.into(), b"duplicate");
+            return Ok(existing);
+        }
+        slot.conn = Some(conn.clone());
+        slot.singles_only = false;
+        Ok(conn)
+    }
+
+    /// Whether `WakeBatch` is worth sending to `id`: false once its current
+    /// connection has left a batch unacknowledged.
+    pub(crate) fn takes_batches(&self, id: &NodeId) -> bool {
+        self.slots.lock().get(id).is_none_or(|slot| !slot.singles_only)
+    }
+
+    /// Send `id` single `Wake`s until it is next dialed.
+    pub(crate) fn record_singles_only(&self, id: &NodeId) {
+        self.slots.lock().entry(*id).or_default().singles_only = true;
+    }
+
+    pub(crate) fn record_success(&self, id: &NodeId) {
+        if let Some(slot) = self.slots.lock().get_mut(id) {
+            slot.failures = 0;
+            slot.down_until = None;
+        }
+    }
+
+    /// Drop the gateway's connection and back it off. Returns the backoff.
+    pub(crate) fn record_failure(&self, id: &NodeId) -> Duration {
+        self.record_failure_at(id, Instant::now())
+    }
+
+    fn record_failure_at(&self, id: &NodeId, now: Instant) -> Duration {
+        let mut slots = self.slots.lock();
+        let slot = slots.entry(*id).or_default();
+        if let Some(conn) = slot.conn.take() {
+            conn.close(0u32.into(), b"wake-failed");
+        }
+        slot.failures = slot.failures.saturating_add(1);
+        let backoff = GATEWAY_BACKOFF_BASE
+            .saturating_mul(1 << (slot.failures - 1).min(16))
+            .min(GATEWAY_BACKOFF_MAX);
+        slot.down_until = Some(now + backoff);
+        backoff
+    }
+
+    /// Forget gateways that have left the directory, closing their
+    /// connections.
+    pub(crate) fn retain(&self, directory: &[GatewayDescriptor]) {
+        self.slots.lock().retain(|id, slot| {
+            let keep = directory.iter().any(|g| g.id == *id);
+            if !keep && let Some(conn) = slot.conn.take() {
+                conn.close(0u32.into(), b"gateway-gone");
+            }
+            keep
+        });
+    }
+}
+
+/// Dial the gateway over `relay/5` (the endpoint's default client config)
+/// and verify it carries `PUSH_GATEWAY`.
+async fn dial(endpoint: &Endpoint, gateway: &GatewayDescriptor) -> Result<Connection> {
+    let conn = endpoint.connect(gateway.addr, &gateway.id.to_string())?.await?;
+
+    // The resolver directory is untrusted — verify the dialed node's CA-signed
+    // capability before handing it pseudonyms.
+    let caps = super::tls_extract::capabilities_from_conn(&conn)
+        .ok_or_else(|| anyhow!("gateway cert carries no capability extension"))?;
+    if !caps.contains(NodeCapabilities::PUSH_GATEWAY) {
+        conn.close(0u32.into(), b"not-a-gateway");
+        return Err(anyhow!("dialed {} lacks PUSH_GATEWAY", gateway.id));
+    }
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::types::bytes::Bytes;
+
+    use super::*;
+
+    fn gateway(seed: u8) -> GatewayDescriptor {
+        GatewayDescriptor {
+            id:     NodeId::from_bytes([seed; 32]),
+            addr:   ([192, 0, 2, seed], 4433).into(),
+            pubkey: Bytes([seed; 32]),
+        }
+    }
+
+    fn ids(gws: &[GatewayDescriptor]) -> Vec<NodeId> {
+        gws.iter().map(|g| g.id).collect()
+    }
+
+    #[test]
+    fn failed_gateway_yields_its_slot_until_backoff_lapses() {
+        let pool = GatewayPool::new();
+        let t0 = Instant::now();
+        let dir = vec![gateway(3), gateway(1), gateway(2)];
+
+        assert_eq!(ids(&pool.targets_at(&dir, 2, t0)), ids(&[gateway(1), gateway(2)]));
+
+        let backoff = pool.record_failure_at(&gateway(1).id, t0);
+        assert_eq!(ids(&pool.targets_at(&dir, 2, t0)), ids(&[gateway(2), gateway(3)]));
+        // With nobody healthier left, a backed-off gateway still makes the cut.
+        assert_eq!(pool.targets_at(&dir, 3, t0).len(), 3);
+
+        let later = t0 + backoff;
+        assert_eq!(ids(&pool.targets_at(&dir, 2, later)), ids(&[gateway(1), gateway(2)]));
+    }
+
+    #[test]
+    fn backoff_doubles_to_a_ceiling_and_success_clears_it() {
+        let pool = GatewayPool::new();
+        let t0 = Instant::now();
+        let id = gateway(1).id;
+
+        assert_eq!(pool.record_failure_at(&id, t0), GATEWAY_BACKOFF_BASE);
+        assert_eq!(pool.record_failure_at(&id, t0), GATEWAY_BACKOFF_BASE * 2);
+        for _ in 0..20 {
+            pool.record_failure_at(&id, t0);
+        }
+        assert_eq!(pool.record_failure_at(&id, t0), GATEWAY_BACKOFF_MAX);
+
+        pool.record_success(&id);
+        assert_eq!(pool.record_failure_at(&id, t0), GATEWAY_BACKOFF_BASE);
+    }
+
+    #[test]
+    fn an_unacked_batch_sends_that_gateway_singles() {
+        let pool = GatewayPool::new();
+        assert!(pool.takes_batches(&gateway(1).id), "unknown gateways get batches");
+
+        pool.record_singles_only(&gateway(1).id);
+        assert!(!pool.takes_batches(&gateway(1).id));
+        assert!(pool.takes_batches(&gateway(2).id));
+    }
+
+    #[test]
+    fn retain_forgets_departed_gateways() {
+        let pool = GatewayPool::new();
+        let t0 = Instant::now();
+        pool.record_failure_at(&gateway(1).id, t0);
+        pool.record_failure_at(&gateway(2).id, t0);
+
+        pool.retain(&[gateway(2)]);
+        let slots = pool.slots.lock();
+        assert!(!slots.contains_key(&gateway(1).id));
+        assert!(slots.contains_key(&gateway(2).id));
+    }
+}
@@ -65,7 +65,9 @@ use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
+use common::proto::dht_p2p::DHT_HELLO_V7_VERSION;
 use common::proto::dht_p2p::DhtHello;
+use common::proto::dht_p2p::DhtHelloV6;
 use common::proto::dht_p2p::DhtHelloVerifyError;
 use common::proto::dht_p2p::DhtPacket;
 use common::proto::dht_p2p::DhtRequest;
@@ -77,6 +79,7 @@ use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
 use common::quic::CloseReason;
 use common::quic::id::NodeId;
+use common::quic::protorole::ProtoRole;
 use quinn::Connection;
 use quinn::SendStream;
 use tokio::sync::Semaphore;
@@ -84,6 +87,8 @@ use tokio::time::timeout;
 
 use super::Dht;
 use super::rate_limit::RpcClass;
+use super::reputation::Offence;
+use super::reputation::Standing;
 use super::routing::RoutingTable;
 use super::tls_extract;
 
@@ -177,23 +182,40 @@ pub(crate) async fn handle_peer_connection(dht: Arc<Dht>, conn: Connection) {
     {
         dht.metrics.inc_dht_hello_rejected();
         common::warn!("DHT inbound: cert SPKI != DhtHello.pubkey for {}; closing", auth.node_id);
+        // The hello bound the id, so the contradicting cert is charged to it.
+        dht.penalize(&auth.node_id, Offence::BadSignature);
         CloseReason::DhtBadSignature.close(&conn);
         return;
     }
 
+    // A banned peer is turned away before it touches routing or the
+    // connection cache.
+    if dht.reputation.is_banned(&auth.node_id) {
+        dht.metrics.inc_banned_peer_rejections();
+        common::debug!("DHT inbound: {} is banned; closing", auth.node_id);
+        CloseReason::DhtBanned.close(&conn);
+        return;
+    }
+
     // Populate routing-table + peer_conns cache *now*, before any RPC
     // arrives. We do it once at this natural boundary — the
     // authenticated identity from the `DhtHello` is already in hand —
     // which also means RPCs that don't carry a `requester` field (Ping,
     // Store, Tombstone, etc.) still get routing-table coverage.
+    //
+    // Under `require_relay_attestation` a newcomer is instead dialed back,
+    // since only the dial sees its cert; see `lookup::attest_and_admit`.
     {
         let desc = NodeDescriptor {
             id: auth.node_id,
             addr: conn.remote_address(),
             pubkey: auth.pubkey.into(),
         };
-        let outcome = dht.routing.write().insert(desc);
-        super::lookup::probe_pending_ping(&dht, outcome);
+        if may_refresh_routing(&dht, &auth) {
+            note_peer(&dht, desc);
+        } else {
+            tokio::spawn(super::lookup::attest_and_admit(dht.clone(), desc));
+        }
     }
     {
         let mut map = dht.peer_conns.write();
@@ -284,6 +306,9 @@ pub(crate) async fn serve_peer_streams(dht: Arc<Dht>, conn: Connection, auth: Au
 pub(crate) struct AuthenticatedPeer {
     node_id: NodeId,
     pubkey: [u8; 32],
+    /// The identity came from a dial, so the cert (and, when required, its
+    /// `RELAY` attestation) has been checked. `false` for hello-only peers.
+    dialed: bool,
 }
 
 impl AuthenticatedPeer {
@@ -293,10 +318,27 @@ impl AuthenticatedPeer {
     /// is exchanged on the dialer's serve side: the dial already
     /// authenticated the peer.
     pub(crate) fn new(node_id: NodeId, pubkey: [u8; 32]) -> Self {
-        Self { node_id, pubkey }
+        Self { node_id, pubkey, dialed: true }
     }
 }
 
+/// Whether traffic from `auth` may insert or refresh its routing entry.
+/// Always, unless `require_relay_attestation` is on and the peer is a
+/// hello-only newcomer whose cert we have not seen.
+fn may_refresh_routing(dht: &Dht, auth: &AuthenticatedPeer) -> bool {
+    !dht.cfg.require_relay_attestation || auth.dialed || dht.routing.read().contains(&auth.node_id)
+}
+
+/// Insert or refresh `desc` and act on the outcome. The write guard is
+/// scoped to the insert, never held across `await`.
+fn note_peer(dht: &Arc<Dht>, desc: NodeDescriptor) {
+    let outcome = dht.routing.write().insert(desc);
+    if outcome == super::routing::InsertOutcome::PrefixLimited {
+        dht.metrics.inc_prefix_limited_inserts();
+    }
+    super::lookup::probe_pending_ping(dht, outcome);
+}
+
 /// Read the dialer's first uni-stream, decode as [`DhtHello`], verify,
 /// and on success return the authenticated `(node_id, pubkey)` pair.
 ///
@@ -306,10 +348,11 @@ impl AuthenticatedPeer {
 /// |---|---|
 /// | No uni-stream within `HELLO_RECV_TIMEOUT` | `DhtClockSkew` (re-used: peer "missed its window") |
 /// | Connection died before any frame | (connection already closed; no close-reason) |
-/// | Frame failed to decode as `DhtHello` | `DhtMalformedKey` |
+/// | Frame failed to decode as the session's hello layout | `DhtMalformedKey` |
 /// | `DhtHello::verify`: `IdMismatch` or `MalformedPubkey` | `DhtMalformedKey` |
 /// | `DhtHello::verify`: `BadSignature` | `DhtBadSignature` |
 /// | `DhtHello::verify`: `ClockSkew` | `DhtClockSkew` |
+/// | `DhtHello::verify`: `NoCommonVersion` | `UnsupportedVersion` |
 ///
 /// All paths bump `metrics.dht_hello_rejected` exactly once on failure,
 /// `metrics.dht_hello_accepted` once on success.
@@ -342,9 +385,15 @@ async fn recv_and_verify_hello(dht: &Arc<Dht>, conn: &Connection) -> Result<Auth
         },
     };
 
-    // Decode the framed DhtHello. `unpack` reads the u16 length prefix
-    // and the body, applying `DhtHello`'s `Deserialize` impl.
-    let hello: DhtHello = match DhtHello::unpack(&mut recv).await {
+    // Decode the framed hello in the layout the session's version uses.
+    // `unpack` reads the u16 length prefix and the body.
+    let session = ProtoRole::version_from_conn(conn).unwrap_or(common::MIN_PROTOCOL_VERSION);
+    let decoded = if session < DHT_HELLO_V7_VERSION {
+        DhtHelloV6::unpack(&mut recv).await.map(InboundHello::V6)
+    } else {
+        DhtHello::unpack(&mut recv).await.map(InboundHello::Ranged)
+    };
+    let hello = match decoded {
         Ok(h) => h,
         Err(e) => {
             dht.metrics.inc_dht_hello_rejected();
@@ -359,8 +408,12 @@ async fn recv_and_verify_hello(dht: &Arc<Dht>, conn: &Connection) -> Result<Auth
 
     // Verify (id-binding, pubkey shape, signature, timestamp window).
     let now = now_ms();
-    match verify_hello_with_close_reason(&hello, now) {
-        Ok(()) => Ok(AuthenticatedPeer { node_id: hello.node_id, pubkey: hello.pubkey.0 }),
+    match verify_hello_with_close_reason(&hello, session, now) {
+        Ok(()) => {
+            let (node_id, pubkey) = hello.identity();
+            common::debug!("DHT inbound from {node_id} settled on v{session}");
+            Ok(AuthenticatedPeer { node_id, pubkey, dialed: false })
+        },
         Err(reason) => {
             dht.metrics.inc_dht_hello_rejected();
             common::warn!(
@@ -374,10 +427,30 @@ async fn recv_and_verify_hello(dht: &Arc<Dht>, conn: &Connection) -> Result<Auth
     }
 }
 
-/// Pure helper: verify `hello` against `now_ms`, mapping any
-/// [`DhtHelloVerifyError`] to the `CloseReason::Dht*` we'd send on the
-/// wire. Extracted from [`recv_and_verify_hello`] so the close-reason
-/// mapping can be unit-tested without spinning up QUIC.
+/// A dialer's hello, in the layout of the session it arrived on: a
+/// [`DhtHelloV6`] on `peer/6`, a [`DhtHello`] from
+/// [`DHT_HELLO_V7_VERSION`] on.
+#[derive(Debug)]
+enum InboundHello {
+    V6(DhtHelloV6),
+    Ranged(DhtHello),
+}
+
+impl InboundHello {
+    /// The claimed `(node_id, pubkey)`; only bound once verified.
+    fn identity(&self) -> (NodeId, [u8; 32]) {
+        match self {
+            Self::V6(h) => (h.node_id, h.pubkey.0),
+            Self::Ranged(h) => (h.node_id, h.pubkey.0),
+        }
+    }
+}
+
+/// Pure helper: verify `hello` against `now_ms` on a connection whose ALPN
+/// settled on `session`, mapping any [`DhtHelloVerifyError`] to the
+/// `CloseReason::Dht*` we'd send on the wire. Extracted from
+/// [`recv_and_verify_hello`] so the close-reason mapping can be unit-tested
+/// without spinning up QUIC.
 ///
 /// The mapping table — same as the doc on
 /// [`recv_and_verify_hello`] — is:
@@ -387,13 +460,21 @@ async fn recv_and_verify_hello(dht: &Arc<Dht>, conn: &Connection) -> Result<Auth
 /// | `IdMismatch` / `MalformedPubkey` | `DhtMalformedKey` |
 /// | `BadSignature` | `DhtBadSignature` |
 /// | `ClockSkew` | `DhtClockSkew` |
-fn verify_hello_with_close_reason(hello: &DhtHello, now_ms: u64) -> Result<(), CloseReason> {
-    hello.verify(now_ms).map_err(|e| match e {
+/// | `NoCommonVersion` | `UnsupportedVersion` |
+fn verify_hello_with_close_reason(
+    hello: &InboundHello, session: u16, now_ms: u64,
+) -> Result<(), CloseReason> {
+    let verified = match hello {
+        InboundHello::V6(h) => h.verify(now_ms),
+        InboundHello::Ranged(h) => h.verify(session, now_ms),
+    };
+    verified.map_err(|e| match e {
         DhtHelloVerifyError::IdMismatch | DhtHelloVerifyError::MalformedPubkey => {
             CloseReason::DhtMalformedKey
         },
         DhtHelloVerifyError::BadSignature => CloseReason::DhtBadSignature,
         DhtHelloVerifyError::ClockSkew => CloseReason::DhtClockSkew,
+        DhtHelloVerifyError::NoCommonVersion => CloseReason::UnsupportedVersion,
     })
 }
 
@@ -417,10 +498,18 @@ async fn handle_one_stream(
     dht: Arc<Dht>, conn: Connection, mut send: SendStream, recv: &mut quinn::RecvStream,
     auth: AuthenticatedPeer,
 ) {
+    // A ban imposed mid-connection (by another stream, or a lapse in the
+    // cached-connection race) stops further RPCs here.
+    if dht.reputation.is_banned(&auth.node_id) {
+        CloseReason::DhtBanned.close(&conn);
+        return;
+    }
+
     // Read request packet.
     let pkt = match DhtPacket::unpack(recv).await {
         Ok(p) => p,
         Err(_) => {
+            dht.penalize(&auth.node_id, Offence::Malformed);
             CloseReason::DhtMalformedKey.close(&conn);
             return;
         },
@@ -430,6 +519,7 @@ async fn handle_one_stream(
         // A client side sending a Response on this stream is a protocol
         // violation — close.
         DhtPacket::Response(_) => {
+            dht.penalize(&auth.node_id, Offence::Malformed);
             CloseReason::PacketMismatch.close(&conn);
             return;
         },
@@ -446,26 +536,48 @@ async fn handle_one_stream(
             "DHT inbound rate limit tripped (peer={}, class={class:?}); closing connection",
             auth.node_id
         );
+        dht.penalize(&auth.node_id, Offence::Flood);
         CloseReason::DhtFlood.close(&conn);
         return;
     }
 
     let resp = handle_dht_request(&dht, req, auth.node_id).await;
 
+    // Outcomes that prove the peer misbehaved feed its reputation. A ban
+    // skips the routing refresh below, which would otherwise re-insert it.
+    if let Some(offence) = offence_for(&resp)
+        && dht.penalize(&auth.node_id, offence) == Standing::Banned
+    {
+        CloseReason::DhtBanned.close(&conn);
+        return;
+    }
+
     // Routing-table feedback: refresh the peer's last-seen status.
     // Insertion already happened at connection accept time; this
     // is the LRU-rotate-to-tail path inside `RoutingTable::insert`.
-    {
+    if may_refresh_routing(&dht, &auth) {
         let desc = NodeDescriptor {
             id: auth.node_id,
             addr: conn.remote_address(),
             pubkey: auth.pubkey.into(),
         };
-        // Scoped write guard, never held across `await`.
-        let outcome = dht.routing.write().insert(desc);
-        super::lookup::probe_pending_ping(&dht, outcome);
+        note_peer(&dht, desc);
     }
 
+    // `handle_dht_request` answers a fetch in the v6 shape every peer reads.
+    // A peer at 7 gets the dispatches whole; one below never sees a sealed
+    // dispatch it has no layout for, which waits for a newer relay's fetch.
+    let resp = match resp {
+        DhtResponse::QueueFetch(r) if super::carries_whole_dispatches(&conn) => {
+            DhtResponse::QueueFetchV7(r)
+        },
+        DhtResponse::QueueFetch(mut r) => {
+            r.messages.retain(|d| d.token.is_none());
+            DhtResponse::QueueFetch(r)
+        },
+        other => other,
+    };
+
     // Write response.
     let bytes = match DhtPacket::Response(resp).pack() {
         Ok(b) => b,
@@ -509,7 +621,7 @@ pub(crate) async fn handle_dht_request(
         // batch from cf_dht_queue oldest-first, and `QueueFetchAck`
         // deletes by-id. Per-RPC metrics live inside the per-handler
         // bodies (`forwards_*` / `dht_queue_*` / `queue_fetches_*`).
-        DhtRequest::Forward(fwd) => {
+        DhtRequest::Forward(fwd) | DhtRequest::ForwardV7(fwd) => {
             DhtResponse::Forward(super::forward::handle_forward_rpc(dht, fwd, now_ms()).await)
         },
         DhtRequest::ActivityForward(activity) => DhtResponse::ActivityForward(
@@ -526,9 +638,16 @@ pub(crate) async fn handle_dht_request(
             super::forward::handle_presence_lease_rpc(dht, lease, authenticated_peer_id, now_ms())
                 .await,
         ),
-        DhtRequest::LiveForward(forward) => DhtResponse::LiveForward(
-            super::forward::handle_live_forward_rpc(dht, forward, authenticated_peer_id, now_ms()).await,
-        ),
+        DhtRequest::LiveForward(forward) | DhtRequest::LiveForwardV7(forward) => {
+            let outcome = super::forward::handle_live_forward_rpc(
+                dht,
+                forward,
+                authenticated_peer_id,
+                now_ms(),
+            )
+            .await;
+            DhtResponse::LiveForward(outcome)
+        },
         DhtRequest::PushPseudonymPublish(publish) => DhtResponse::PushPseudonymPublish(
             super::push_replication::handle_publish(dht, publish, now_ms()),
         ),
@@ -594,6 +713,33 @@ pub(crate) async fn handle_dht_request(
         DhtRequest::WelcomeAck(req) => DhtResponse::WelcomeAck(
             super::mls::welcome::handle_welcome_ack(dht, req, authenticated_peer_id, now_ms()),
         ),
+        DhtRequest::WakePolicyPublish(publish) => DhtResponse::WakePolicyPublish(
+            super::wake_policy::handle_publish(dht, publish, now_ms()),
+        ),
+        DhtRequest::TokenRevocation(revocation) => DhtResponse::TokenRevocation(
+            super::sealed::handle_revocation(dht, revocation, now_ms()),
+        ),
+        DhtRequest::PushKeyPublish(record) => DhtResponse::PushKeyPublish(
+            super::push_key::handle_publish(dht, record, now_ms()),
+        ),
+        DhtRequest::PushKeyFetch(req) => {
+            DhtResponse::PushKeyFetch(super::push_key::handle_fetch(dht, req))
+        },
+        DhtRequest::BlobFetch(req) => {
+            DhtResponse::BlobFetch(super::blob::handle_fetch(dht, req, now_ms()))
+        },
+        DhtRequest::IdentitySuccession(succession) => DhtResponse::IdentitySuccession(
+            super::succession::handle_succession(dht, succession, now_ms()),
+        ),
+        DhtRequest::QueueHandover(handover) => DhtResponse::QueueHandover(
+            super::succession::handle_handover(dht, handover, now_ms()).await,
+        ),
+        DhtRequest::DeleteAccount(deletion) => DhtResponse::DeleteAccount(
+            super::account::handle_delete_account(dht, deletion, now_ms()),
+        ),
+        DhtRequest::DeletePredecessor(deletion) => DhtResponse::DeletePredecessor(
+            super::account::handle_delete_predecessor(dht, deletion, now_ms()),
+        ),
     }
 }
 
@@ -608,14 +754,53 @@ fn now_ms() -> u64 {
     SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
 }
 
+/// The [`Offence`], if any, that `resp` proves against the requester.
+///
+/// Only outcomes the requester could have avoided count: a signature over
+/// data it signed or vouched for that was checked and failed, or a
+/// timestamp it stamped outside the skew window. Soft rejects (`QueueFull`,
+/// `NotOwner`, `Deleted`, rate limits) are load, not misbehaviour, and a home's own
+/// failures (`Internal`, `UnknownSender`, `IdConflict`) prove nothing.
+fn offence_for(resp: &DhtResponse) -> Option<Offence> {
+    use common::proto::dht_p2p::ForwardOutcome;
+    use common::proto::mls_wire::KeyPackagePublishOutcome;
+    use common::proto::mls_wire::KeyPackageRefillOutcome;
+    use common::proto::mls_wire::WelcomeFetchOutcome;
+    use common::proto::mls_wire::WelcomePublishOutcome;
+
+    match resp {
+        DhtResponse::Forward(r) => match r.outcome {
+            ForwardOutcome::BadSig => Some(Offence::BadSignature),
+            ForwardOutcome::StaleTimestamp => Some(Offence::StaleTimestamp),
+            _ => None,
+        },
+        DhtResponse::KeyPackagePublish(r) if r.outcome == KeyPackagePublishOutcome::BadSig => {
+            Some(Offence::BadSignature)
+        },
+        DhtResponse::KeyPackageRefill(r) if r.outcome == KeyPackageRefillOutcome::BadSig => {
+            Some(Offence::BadSignature)
+        },
+        DhtResponse::WelcomePublish(r) => match r.outcome {
+            WelcomePublishOutcome::BadSig => Some(Offence::BadSignature),
+            WelcomePublishOutcome::StaleTimestamp => Some(Offence::StaleTimestamp),
+            _ => None,
+        },
+        DhtResponse::WelcomeFetch(r) if matches!(r.outcome, WelcomeFetchOutcome::BadSig) => {
+            Some(Offence::BadSignature)
+        },
+        _ => None,
+    }
+}
+
 /// Top-(MAX_FIND_NODE_RESULTS) descriptors closest to `target`, **excluding**
 /// the `exclude` peer. Excluding the requester saves them from receiving
-/// their own descriptor back, which they already know about.
+/// their own descriptor back, which they already know about. Demoted peers
+/// are never advertised.
 fn closest_excluding(
     routing: &RoutingTable, target: &NodeId, exclude: &NodeId,
 ) -> Vec<NodeDescriptor> {
     routing
-        .find_closest(target, MAX_FIND_NODE_RESULTS + 1)
+        .find_closest_advertisable(target, MAX_FIND_NODE_RESULTS + 1)
         .into_iter()
         .filter(|d| &d.id != exclude)
         .take(MAX_FIND_NODE_RESULTS)
@@ -640,6 +825,7 @@ mod tests {
     use common::proto::dht_p2p::DhtRequest;
     use common::proto::dht_p2p::DhtResponse;
     use common::proto::dht_p2p::FindNode;
+    use common::proto::dht_p2p::ForwardResp;
     use ed25519_dalek::Signer;
     use ed25519_dalek::SigningKey;
 
@@ -725,7 +911,7 @@ mod tests {
         NodeId::new([0xFAu8; 32])
     }
 
-    use common::proto::dht_p2p::DhtHello;
+    use common::PROTOCOL_VERSION as V;
     use common::proto::dht_p2p::dht_hello_signing_input;
     use common::types::bytes::Bytes;
 
@@ -734,9 +920,29 @@ mod tests {
     fn make_hello(key: &SigningKey, timestamp: u64) -> DhtHello {
         let pubkey: [u8; 32] = key.verifying_key().to_bytes();
         let node_id = NodeId::new(pubkey);
-        let msg = dht_hello_signing_input(&node_id, &pubkey, timestamp);
+        let (min_version, max_version) = (common::MIN_PROTOCOL_VERSION, common::PROTOCOL_VERSION);
+        let msg = dht_hello_signing_input(
+            max_version,
+            &node_id,
+            &pubkey,
+            timestamp,
+            min_version,
+            max_version,
+        );
         let sig = key.sign(&msg).to_bytes();
-        DhtHello { node_id, pubkey: Bytes(pubkey), timestamp, sig: Bytes(sig) }
+        DhtHello {
+            node_id,
+            pubkey: Bytes(pubkey),
+            timestamp,
+            min_version,
+            max_version,
+            sig: Bytes(sig),
+        }
+    }
+
+    /// `hello` as it arrives on a session at our newest version.
+    fn ranged(hello: &DhtHello) -> InboundHello {
+        InboundHello::Ranged(hello.clone())
     }
 
     #[test]
@@ -745,7 +951,7 @@ mod tests {
         let key = fresh_signing_key();
         let now: u64 = 1_700_000_000_000;
         let stale = make_hello(&key, now - 120_000); // 2 min in the past
-        match verify_hello_with_close_reason(&stale, now) {
+        match verify_hello_with_close_reason(&ranged(&stale), V, now) {
             Err(CloseReason::DhtClockSkew) => {},
             other => panic!("expected DhtClockSkew, got {other:?}"),
         }
@@ -758,7 +964,7 @@ mod tests {
         let now: u64 = 1_700_000_000_000;
         let mut hello = make_hello(&key, now);
         hello.sig.0[0] ^= 0xFF;
-        match verify_hello_with_close_reason(&hello, now) {
+        match verify_hello_with_close_reason(&ranged(&hello), V, now) {
             Err(CloseReason::DhtBadSignature) => {},
             other => panic!("expected DhtBadSignature, got {other:?}"),
         }
@@ -776,19 +982,63 @@ mod tests {
         // Replace node_id with a different identity's id while keeping
         // the original (a-derived) pubkey + sig.
         hello.node_id = NodeId::new(key_b.verifying_key().to_bytes());
-        match verify_hello_with_close_reason(&hello, now) {
+        match verify_hello_with_close_reason(&ranged(&hello), V, now) {
             Err(CloseReason::DhtMalformedKey) => {},
             other => panic!("expected DhtMalformedKey, got {other:?}"),
         }
     }
 
+    #[test]
+    fn verify_hello_close_reason_maps_a_disjoint_range() {
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let mut hello = make_hello(&key, now);
+        hello.min_version = common::PROTOCOL_VERSION + 1;
+        hello.max_version = common::PROTOCOL_VERSION + 1;
+        match verify_hello_with_close_reason(&ranged(&hello), V, now) {
+            Err(CloseReason::UnsupportedVersion) => {},
+            other => panic!("expected UnsupportedVersion, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn verify_hello_close_reason_maps_a_narrowed_session() {
+        // We both speak v7, so a session settled on v6 had its ALPNs
+        // stripped on the way.
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let hello = make_hello(&key, now);
+        match verify_hello_with_close_reason(&ranged(&hello), DHT_HELLO_V7_VERSION - 1, now) {
+            Err(CloseReason::UnsupportedVersion) => {},
+            other => panic!("expected UnsupportedVersion, got {other:?}"),
+        }
+    }
+
+    /// A relay from before version negotiation only dials `peer/6` and
+    /// sends the v6 hello; it still gets in.
+    #[test]
+    fn verify_hello_close_reason_passes_a_v6_hello() {
+        use common::proto::dht_p2p::dht_hello_v6_signing_input;
+
+        let key = fresh_signing_key();
+        let now: u64 = 1_700_000_000_000;
+        let pubkey: [u8; 32] = key.verifying_key().to_bytes();
+        let node_id = NodeId::new(pubkey);
+        let sig = key.sign(&dht_hello_v6_signing_input(&node_id, &pubkey, now)).to_bytes();
+        let hello = DhtHelloV6 { node_id, pubkey: Bytes(pubkey), timestamp: now, sig: Bytes(sig) };
+        let inbound = InboundHello::V6(hello);
+        verify_hello_with_close_reason(&inbound, 6, now).expect("a v6 hello must pass on peer/6");
+        assert_eq!(inbound.identity(), (node_id, pubkey));
+    }
+
     #[test]
     fn verify_hello_close_reason_passes_freshly_signed() {
         let key = fresh_signing_key();
         let now: u64 = 1_700_000_000_000;
         let hello = make_hello(&key, now);
-        verify_hello_with_close_reason(&hello, now).expect("valid hello must pass");
-        verify_hello_with_close_reason(&hello, now + 5).expect("inside skew window must pass");
+        verify_hello_with_close_reason(&ranged(&hello), V, now).expect("valid hello must pass");
+        verify_hello_with_close_reason(&ranged(&hello), V, now + 5)
+            .expect("inside skew window must pass");
     }
 
     // -----------------------------------------------------------------
@@ -820,6 +1070,8 @@ mod tests {
             sig: sig.to_bytes().into(),
             accepted_at_ms: 1,
             wake: false,
+            wake_hint: None,
+            token: None,
         }
     }
 
@@ -913,6 +1165,30 @@ mod tests {
         }
     }
 
+    /// Only a checked, failed signature costs the requester reputation; a
+    /// home's own failures and refusals it couldn't judge cost nothing.
+    #[test]
+    fn only_a_verified_bad_signature_is_charged_as_one() {
+        let forward = |outcome| DhtResponse::Forward(ForwardResp { outcome });
+
+        assert_eq!(offence_for(&forward(ForwardOutcome::BadSig)), Some(Offence::BadSignature));
+        assert_eq!(
+            offence_for(&forward(ForwardOutcome::StaleTimestamp)),
+            Some(Offence::StaleTimestamp)
+        );
+        for outcome in [
+            ForwardOutcome::Internal,
+            ForwardOutcome::UnknownSender,
+            ForwardOutcome::IdConflict,
+            ForwardOutcome::NotOwner,
+            ForwardOutcome::QuotaExceeded,
+            ForwardOutcome::BadToken,
+            ForwardOutcome::Deleted,
+        ] {
+            assert_eq!(offence_for(&forward(outcome)), None, "{outcome:?}");
+        }
+    }
+
     /// Embedded user-layer `dispatch.sig` invalid → BadSig.
     #[tokio::test(flavor = "current_thread")]
     async fn handle_forward_rpc_rejects_bad_dispatch_sig() {
@@ -1,5 +1,5 @@
-//! Iterative `FindNode` / `FindValue` walks with α=3 parallelism and
-//! per-hop hedging.
+//! Iterative `FindNode` / `FindValue` walks over disjoint paths, each
+//! with α=3 parallelism and per-hop hedging.
 //!
 //! ## Algorithm
 //!
@@ -10,6 +10,7 @@
 //! - **`in_flight`**: the peers we've sent a request to and are still
 //!   waiting for. Bounded at `α = 3`.
 //! - **`queried`**: peers that have already responded (or been hedged-out).
+//!   Shared by every path of one lookup — see below.
 //!
 //! Termination:
 //! 1. We've contacted the K strictly-closest peers in `pending` and none
@@ -17,6 +18,14 @@
 //! 2. `LOOKUP_MAX_HOPS` exceeded, OR
 //! 3. `LOOKUP_RPC_TIMEOUT_MS` total wall-clock elapsed.
 //!
+//! ## Disjoint paths
+//!
+//! A single walk trusts whoever answers first near the target, so a
+//! cluster of minted NodeIds there can own the whole K-closest set.
+//! Following S/Kademlia, a lookup instead runs `LOOKUP_DISJOINT_PATHS`
+//! walks from disjoint seeds, and a peer queried by one path is off
+//! limits to the others. The results are merged by distance.
+//!
 //! ## Hedging
 //!
 //! When a request hasn't returned within `LOOKUP_HEDGE_MS`, we *don't*
@@ -40,7 +49,11 @@ use std::time::Instant;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
+use common::MIN_PROTOCOL_VERSION;
+use common::PROTOCOL_VERSION;
+use common::proto::dht_p2p::DHT_HELLO_V7_VERSION;
 use common::proto::dht_p2p::DhtHello;
+use common::proto::dht_p2p::DhtHelloV6;
 use common::proto::dht_p2p::DhtPacket;
 use common::proto::dht_p2p::DhtRequest;
 use common::proto::dht_p2p::DhtResponse;
@@ -48,12 +61,15 @@ use common::proto::dht_p2p::FindNode;
 use common::proto::dht_p2p::MAX_FIND_NODE_RESULTS;
 use common::proto::dht_p2p::NodeDescriptor;
 use common::proto::dht_p2p::dht_hello_signing_input;
+use common::proto::dht_p2p::dht_hello_v6_signing_input;
 use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
 use common::quic::id::NodeId;
+use common::quic::protorole::ProtoRole;
 use common::quic::xor32;
 use common::types::bytes::Bytes;
 use ed25519_dalek::Signer;
+use parking_lot::Mutex;
 use quinn::Connection;
 use thiserror::Error;
 use tokio::time::timeout;
@@ -61,6 +77,7 @@ use tokio::time::timeout;
 use super::Dht;
 use super::config::ALPHA;
 use super::config::K;
+use super::config::LOOKUP_DISJOINT_PATHS;
 use super::config::LOOKUP_HEDGE_MS;
 use super::config::LOOKUP_MAX_HOPS;
 use super::config::LOOKUP_RPC_TIMEOUT_MS;
@@ -214,6 +231,17 @@ pub(crate) async fn connect_to_peer(
             return Ok(conn);
         }
 
+    dial_peer(dht, peer).await
+}
+
+/// The cache-miss half of [`connect_to_peer`]: dial, verify, say hello,
+/// cache, serve. Called directly by [`attest_and_admit`], which must see
+/// the peer's cert even when an inbound connection from it is cached.
+async fn dial_peer(dht: &Arc<Dht>, peer: &NodeDescriptor) -> anyhow::Result<Connection> {
+    if dht.reputation.is_banned(&peer.id) {
+        return Err(anyhow::anyhow!("refusing to dial banned peer {}", peer.id));
+    }
+
     if !is_dialable_peer_addr(&peer.addr, dht.cfg.allow_local_peer_addrs) {
         return Err(anyhow::anyhow!(
             "refusing to dial {} at non-routable address {}",
@@ -253,6 +281,17 @@ pub(crate) async fn connect_to_peer(
         }
     };
 
+    // CA attestation: a NodeId is one keygen, a `RELAY`-stamped cert is a
+    // CA signature, so requiring one makes minting identities in bulk cost
+    // whatever the CA charges for issuance.
+    if dht.cfg.require_relay_attestation && !crate::dht::tls_extract::is_attested_relay(&conn) {
+        dht.metrics.inc_unattested_peer_rejections();
+        common::warn!("DHT connect_to_peer: {} has no RELAY attestation; closing", peer.id);
+        common::quic::CloseReason::DhtUnattested.close(&conn);
+        dht.routing.write().remove(&peer.id);
+        return Err(anyhow::anyhow!("{} is not a CA-attested relay", peer.id));
+    }
+
     // Send our signed `DhtHello` as the first frame on the connection.
     // Failure here is non-fatal-to-the-handshake (the peer will simply
     // close on its end), but we surface it so the dialer sees the
@@ -298,11 +337,45 @@ pub(crate) async fn connect_to_peer(
     Ok(conn)
 }
 
+/// Admit a peer first seen inbound once it proves a `RELAY` attestation.
+///
+/// Inbound `peer/5` runs without client certs, so the attestation is only
+/// visible on a dial back to `peer`. Used by the inbound handler when
+/// `require_relay_attestation` is on; spawned once per inbound connection
+/// from a peer not already in the table.
+///
+/// Dials only peers the table has room for, and at most
+/// [`super::config::MAX_ATTESTATION_DIALS_PER_MINUTE`] a minute across all
+/// of them.
+pub(crate) async fn attest_and_admit(dht: Arc<Dht>, peer: NodeDescriptor) {
+    if !dht.routing.read().has_room_for(&peer) {
+        return;
+    }
+    if dht.attest_limiter.check().is_err() {
+        common::debug!("DHT: attestation dial budget spent; not dialing {}", peer.id);
+        return;
+    }
+    if let Err(e) = dial_peer(&dht, &peer).await {
+        common::debug!("DHT: not admitting inbound peer {}: {e}", peer.id);
+        return;
+    }
+    let outcome = dht.routing.write().insert(peer);
+    if outcome == InsertOutcome::PrefixLimited {
+        dht.metrics.inc_prefix_limited_inserts();
+    }
+    probe_pending_ping(&dht, outcome);
+}
+
 /// Send our signed [`DhtHello`] on a freshly-opened uni-stream. The
 /// transcript is built via [`dht_hello_signing_input`], so dialer
 /// (this) and receiver (`relay/src/dht/handler.rs::recv_and_verify_hello`)
 /// always agree byte-for-byte.
 ///
+/// Signed at the version the TLS handshake's ALPN settled on — the same
+/// one the receiver derives from our advertised range. A session below
+/// [`DHT_HELLO_V7_VERSION`] is a relay from before negotiation, which only
+/// reads a [`DhtHelloV6`].
+///
 /// Lives next to [`connect_to_peer`] (rather than a free fn in
 /// `dht/mod.rs`) because it's the only call-site and stays close to
 /// the dial-path it serves.
@@ -311,16 +384,36 @@ async fn send_dht_hello(dht: &Arc<Dht>, conn: &Connection) -> anyhow::Result<()>
     // The dialer's own pubkey: derivable from the signing key.
     let pubkey: [u8; 32] = dht.signing_key.verifying_key().to_bytes();
     let timestamp = now_ms();
-    let msg = dht_hello_signing_input(&node_id, &pubkey, timestamp);
-    let sig = dht.signing_key.sign(&msg).to_bytes();
-
-    let hello = DhtHello {
-        node_id,
-        pubkey: Bytes(pubkey),
-        timestamp,
-        sig: Bytes(sig),
+    let version = ProtoRole::version_from_conn(conn)
+        .filter(|v| (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(v))
+        .ok_or_else(|| anyhow::anyhow!("peer settled on no version we speak"))?;
+
+    let bytes = if version < DHT_HELLO_V7_VERSION {
+        let msg = dht_hello_v6_signing_input(&node_id, &pubkey, timestamp);
+        let sig = Bytes(dht.signing_key.sign(&msg).to_bytes());
+        DhtHelloV6 { node_id, pubkey: Bytes(pubkey), timestamp, sig }.pack()?
+    } else {
+        let (min_version, max_version) = (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION);
+        let msg = dht_hello_signing_input(
+            version,
+            &node_id,
+            &pubkey,
+            timestamp,
+            min_version,
+            max_version,
+        );
+        let sig = dht.signing_key.sign(&msg).to_bytes();
+
+        let hello = DhtHello {
+            node_id,
+            pubkey: Bytes(pubkey),
+            timestamp,
+            min_version,
+            max_version,
+            sig: Bytes(sig),
+        };
+        hello.pack()?
     };
-    let bytes = hello.pack()?;
 
     let mut send = conn.open_uni().await?;
     send.write_all(&bytes).await?;
@@ -435,7 +528,15 @@ pub(crate) fn probe_pending_ping(dht: &Arc<Dht>, outcome: InsertOutcome) {
 /// - the publish path to find STORE recipients,
 /// - bucket-refresh to re-discover stale ranges.
 ///
-/// Returns the top-k peers by XOR distance the walk converged on.
+/// The walk runs as [`LOOKUP_DISJOINT_PATHS`] independent paths
+/// (S/Kademlia §4.4): the routing-table seeds are dealt round-robin across
+/// them, and a shared claim set keeps any peer from being queried by more
+/// than one path. An attacker clustered near `target` can steer each path
+/// it answers on, but has to answer on every path to keep the honest
+/// closest peers out of the merged result.
+///
+/// Returns the top-k peers by XOR distance across every path that
+/// converged; fails only if none did.
 pub(crate) async fn lookup_node(
     dht: Arc<Dht>, target: NodeId,
 ) -> Result<Vec<NodeDescriptor>, LookupError> {
@@ -447,7 +548,7 @@ pub(crate) async fn lookup_node(
     // taking the read guard and dropping it — clone descriptors out.
     let initial: Vec<NodeDescriptor> = {
         let routing = dht.routing.read();
-        routing.find_closest(&target, K * 2)
+        routing.find_closest(&target, (K * 2).max(LOOKUP_DISJOINT_PATHS * ALPHA))
     };
 
     if initial.is_empty() {
@@ -455,52 +556,75 @@ pub(crate) async fn lookup_node(
         return Err(LookupError::NoCandidates);
     }
 
-    // The "shortlist" — kept sorted by distance ascending.
-    let mut candidates: Vec<Candidate> = initial
-        .into_iter()
-        .map(|desc| {
-            let distance = distance(&target_bytes, &desc.id);
-            Candidate { desc, distance }
-        })
-        .collect();
-    candidates.sort_by_key(|a| a.distance);
-
-    let mut queried: HashSet<NodeId> = HashSet::new();
-    queried.insert(dht.node_id); // never query self
-    let mut closest_so_far: Vec<Candidate> = Vec::with_capacity(K);
+    // `find_closest` is distance-sorted, so dealing round-robin gives
+    // every path a comparable start. Small tables leave later paths empty.
+    let mut shortlists: Vec<Vec<Candidate>> = vec![Vec::new(); LOOKUP_DISJOINT_PATHS];
+    for (i, desc) in initial.into_iter().enumerate() {
+        let distance = distance(&target_bytes, &desc.id);
+        shortlists[i % LOOKUP_DISJOINT_PATHS].push(Candidate { desc, distance });
+    }
 
+    let claimed: Arc<Mutex<HashSet<NodeId>>> = Arc::new(Mutex::new(HashSet::from([dht.node_id])));
     let deadline = Instant::now() + Duration::from_millis(LOOKUP_RPC_TIMEOUT_MS);
-    let mut hops: u32 = 0;
-
-    let res = run_iterative_loop(
-        &dht,
-        &target_bytes,
-        &mut candidates,
-        &mut queried,
-        &mut closest_so_far,
-        &mut hops,
-        deadline,
-    )
-    .await;
 
-    match res {
-        Ok(_) => {
-            dht.metrics.inc_lookups_succeeded();
-            Ok(closest_so_far.into_iter().take(K).map(|c| c.desc).collect())
-        }
-        Err(e) => {
-            dht.metrics.inc_lookups_failed();
-            Err(e)
+    let mut paths = tokio::task::JoinSet::new();
+    for mut candidates in shortlists.into_iter().filter(|c| !c.is_empty()) {
+        let dht = dht.clone();
+        let claimed = claimed.clone();
+        paths.spawn(async move {
+            let mut closest_so_far: Vec<Candidate> = Vec::with_capacity(K);
+            let mut hops: u32 = 0;
+            run_iterative_loop(
+                &dht,
+                &target_bytes,
+                &mut candidates,
+                &claimed,
+                &mut closest_so_far,
+                &mut hops,
+                deadline,
+            )
+            .await
+            .map(|()| closest_so_far)
+        });
+    }
+
+    let mut merged: Vec<Candidate> = Vec::new();
+    let mut converged = false;
+    let mut first_err: Option<LookupError> = None;
+    while let Some(joined) = paths.join_next().await {
+        match joined {
+            Ok(Ok(closest)) => {
+                converged = true;
+                merged.extend(closest);
+            },
+            Ok(Err(e)) => {
+                first_err.get_or_insert(e);
+            },
+            Err(_join) => {},
         }
     }
+
+    if !converged {
+        dht.metrics.inc_lookups_failed();
+        return Err(first_err.unwrap_or(LookupError::Timeout));
+    }
+    dht.metrics.inc_lookups_succeeded();
+    merged.sort_by_key(|c| c.distance);
+    merged.dedup_by_key(|c| c.desc.id);
+    Ok(merged.into_iter().take(K).map(|c| c.desc).collect())
 }
 
 // ---------------------------------------------------------------------------
 // Shared iterative loop
 // ---------------------------------------------------------------------------
 
-/// Drive the α-parallel iterative `FindNode` loop with hedging. Used by
-/// `lookup_node`.
+/// Drive one path's α-parallel iterative `FindNode` loop with hedging.
+/// Used by `lookup_node`, once per disjoint path.
+///
+/// `claimed` is shared by every path of the lookup: a peer is claimed
+/// when a path queries it, and no other path will then query it or wait
+/// on it for convergence. The mutex is only held to scan the shortlist,
+/// never across `await`.
 ///
 /// Returns `Ok(())` when the walk converged peacefully (`closest_so_far`
 /// is now populated), `Err(LookupError)` on timeout / max-hops.
@@ -511,7 +635,7 @@ pub(crate) async fn lookup_node(
 #[allow(clippy::too_many_arguments)]
 async fn run_iterative_loop(
     dht: &Arc<Dht>, target: &[u8; 32], candidates: &mut Vec<Candidate>,
-    queried: &mut HashSet<NodeId>, closest_so_far: &mut Vec<Candidate>, hops: &mut u32,
+    claimed: &Mutex<HashSet<NodeId>>, closest_so_far: &mut Vec<Candidate>, hops: &mut u32,
     deadline: Instant,
 ) -> Result<(), LookupError> {
     use tokio::task::JoinSet;
@@ -524,13 +648,16 @@ async fn run_iterative_loop(
             return Err(LookupError::MaxHopsExceeded);
         }
 
-        // 1. Snapshot the next α candidates that haven't been queried.
+        // 1. Claim the next α candidates no path has queried yet.
         let mut batch: Vec<NodeDescriptor> = Vec::with_capacity(ALPHA);
-        for c in candidates.iter() {
-            if !queried.contains(&c.desc.id) {
-                batch.push(c.desc.clone());
-                if batch.len() >= ALPHA {
-                    break;
+        {
+            let mut claimed = claimed.lock();
+            for c in candidates.iter() {
+                if claimed.insert(c.desc.id) {
+                    batch.push(c.desc.clone());
+                    if batch.len() >= ALPHA {
+                        break;
+                    }
                 }
             }
         }
@@ -542,7 +669,6 @@ async fn run_iterative_loop(
         // 2. Fire α requests in parallel.
         let mut set: JoinSet<RpcResult> = JoinSet::new();
         for desc in batch.iter() {
-            queried.insert(desc.id);
             let dht_ref = dht.clone();
             let desc_clone = desc.clone();
             let target_arr = *target;
@@ -600,10 +726,13 @@ async fn run_iterative_loop(
             closest_so_far.push(c.clone());
         }
         let farthest = closest_so_far.last().map(|c| c.distance);
-        let any_closer_unqueried = candidates.iter().any(|c| {
-            !queried.contains(&c.desc.id)
-                && farthest.map(|f| c.distance < f).unwrap_or(true)
-        });
+        let any_closer_unqueried = {
+            let claimed = claimed.lock();
+            candidates.iter().any(|c| {
+                !claimed.contains(&c.desc.id)
+                    && farthest.map(|f| c.distance < f).unwrap_or(true)
+            })
+        };
         if !any_closer_unqueried {
             return Ok(());
         }
@@ -48,6 +48,25 @@ pub struct Metrics {
     /// connection still bump exactly once before the close.
     pub rate_limit_rejections: AtomicU64,
 
+    /// Offences charged to an authenticated peer's reputation (see
+    /// `dht::reputation`), across every kind.
+    pub reputation_penalties: AtomicU64,
+
+    /// Peers whose reputation crossed the ban threshold. Counts ban
+    /// events, not banned peers — a peer banned twice counts twice.
+    pub peers_banned: AtomicU64,
+
+    /// Inbound `peer/5` connections refused because the dialer is
+    /// currently banned.
+    pub banned_peer_rejections: AtomicU64,
+
+    /// Outbound dials refused because the peer's cert lacks the `RELAY`
+    /// capability while `require_relay_attestation` is on.
+    pub unattested_peer_rejections: AtomicU64,
+
+    /// Routing-table inserts refused by the per-address-prefix limits.
+    pub prefix_limited_inserts: AtomicU64,
+
     /// Inbound `peer/5` connection rejected because the post-handshake
     /// TLS-pubkey extraction failed (cert chain absent, malformed
     /// SPKI, self-sig invalid, or `BLAKE3(spki) != claimed_node_id`).
@@ -106,6 +125,10 @@ pub struct Metrics {
     /// `MAX_QUEUED_PER_RECIPIENT` cap was hit on `cf_dht_queue`.
     pub dht_queue_full_rejections: AtomicU64,
 
+    /// `enqueue_for_home` rejections because the recipient's or sender's
+    /// byte quota on `cf_dht_queue` would have been exceeded.
+    pub dht_queue_quota_rejections: AtomicU64,
+
     // --- sticky-home recipient drain ---
 
     /// `CRelayPacket::DrainAuth` packets that verified successfully
@@ -253,6 +276,26 @@ impl Metrics {
         self.rate_limit_rejections.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn inc_reputation_penalties(&self) {
+        self.reputation_penalties.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_peers_banned(&self) {
+        self.peers_banned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_banned_peer_rejections(&self) {
+        self.banned_peer_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_unattested_peer_rejections(&self) {
+        self.unattested_peer_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_prefix_limited_inserts(&self) {
+        self.prefix_limited_inserts.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn inc_cert_pubkey_extraction_failures(&self) {
         self.cert_pubkey_extraction_failures.fetch_add(1, Ordering::Relaxed);
     }
@@ -293,6 +336,10 @@ impl Metrics {
         self.dht_queue_full_rejections.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn inc_dht_queue_quota_rejections(&self) {
+        self.dht_queue_quota_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
     // --- sticky-home recipient drain ---
 
     pub fn inc_drain_auth_received(&self) {
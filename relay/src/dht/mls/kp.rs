@@ -207,7 +207,7 @@ impl KpFetchLimiters {
 /// `per_hour` tokens per hour with a burst equal to the hourly allowance.
 /// `governor::Quota` takes no fractional rate, so the period per token is the
 /// natural expression.
-fn hourly_quota(per_hour: u32) -> Quota {
+pub(crate) fn hourly_quota(per_hour: u32) -> Quota {
     let period = std::time::Duration::from_secs(3600 / per_hour.max(1) as u64);
     let burst = NonZeroU32::new(per_hour).unwrap_or(NonZeroU32::MIN);
     Quota::with_period(period).expect("non-zero period per token").allow_burst(burst)
@@ -543,8 +543,8 @@ pub(crate) fn handle_keypackage_publish(
         return KeyPackagePublishOutcome::BadSig;
     }
 
-    // 3. Ownership.
-    if !self_is_owner_for_stash(dht, &req.ipk.0) {
+    // 3. Ownership. A deleted identity has no stash to publish into.
+    if !self_is_owner_for_stash(dht, &req.ipk.0) || dht.store.is_tombstoned(&req.ipk.0) {
         return KeyPackagePublishOutcome::NotOwner;
     }
 
@@ -634,7 +634,7 @@ pub(crate) fn handle_keypackage_refill(
         return KeyPackageRefillOutcome::BadSig;
     }
 
-    if !self_is_owner_for_stash(dht, &req.ipk.0) {
+    if !self_is_owner_for_stash(dht, &req.ipk.0) || dht.store.is_tombstoned(&req.ipk.0) {
         return KeyPackageRefillOutcome::NotOwner;
     }
 
@@ -103,6 +103,7 @@ use governor::clock::DefaultClock;
 use governor::state::keyed::DefaultKeyedStateStore;
 
 use crate::dht::Dht;
+use crate::dht::config::QueueQuota;
 
 /// Length of the BLAKE3 stash-prefix, in bytes (the `prefix()` seek length
 /// for per-recipient welcome scans).
@@ -317,23 +318,32 @@ fn iterate_welcomes(
 }
 
 /// `true` once the recipient is at [`MAX_WELCOMES_PER_RECIPIENT`] rows or
-/// `sender_ipk` is at [`MAX_WELCOMES_PER_SENDER`] of them. A republish of an id
+/// `sender_ipk` is at [`MAX_WELCOMES_PER_SENDER`] of them, or once a new row of
+/// `len` stored bytes would take either past its `quota`. A republish of an id
 /// already on disk overwrites its own row and is never refused.
 fn welcome_queue_full(
-    dht: &Dht, ipk: &[u8; 32], sender_ipk: &[u8; 32], key: &[u8; STORAGE_KEY_LEN],
+    dht: &Dht, ipk: &[u8; 32], sender_ipk: &[u8; 32], key: &[u8; STORAGE_KEY_LEN], len: usize,
+    quota: QueueQuota,
 ) -> bool {
     let mut total: usize = 0;
     let mut from_sender: usize = 0;
-    for (k, env, _, _) in iterate_welcomes(dht, ipk) {
+    let mut total_bytes = len as u64;
+    let mut sender_bytes = len as u64;
+    for (k, env, _, stored_len) in iterate_welcomes(dht, ipk) {
         if k == *key {
             return false;
         }
         total += 1;
+        total_bytes += stored_len as u64;
         if env.sender_ipk.0 == *sender_ipk {
             from_sender += 1;
+            sender_bytes += stored_len as u64;
         }
     }
-    total >= MAX_WELCOMES_PER_RECIPIENT || from_sender >= MAX_WELCOMES_PER_SENDER
+    total >= MAX_WELCOMES_PER_RECIPIENT
+        || from_sender >= MAX_WELCOMES_PER_SENDER
+        || total_bytes > quota.per_recipient
+        || sender_bytes > quota.per_sender
 }
 
 // ---------------------------------------------------------------------------
@@ -349,8 +359,10 @@ fn welcome_queue_full(
 /// 3. Self is in K-closest for `stash_prefix(recipient_ipk)`.
 /// 4. Verify the embedded envelope's `sender_sig` via
 ///    [`verify_welcome_envelope`].
-/// 5. Per-recipient cap check
-///    ([`MAX_WELCOMES_PER_RECIPIENT`]).
+/// 5. Per-recipient and per-sender cap and byte-quota check
+///    ([`MAX_WELCOMES_PER_RECIPIENT`], `DhtConfig::welcome_quota`). Quota
+///    refusals report `QueueFull` too; the welcome wire has no finer
+///    outcome and the sender's response is the same.
 /// 6. Mint random `welcome_id`, persist
 ///    `(stash_prefix(recipient) || welcome_id) → expires_at_ms ||
 ///    postcard(envelope)`.
@@ -388,8 +400,9 @@ pub(crate) fn handle_welcome_publish(
         return WelcomePublishOutcome::RateLimited;
     }
 
-    // 3. Ownership.
-    if !self_is_owner_for_recipient(dht, &req.envelope.recipient_ipk.0) {
+    // 3. Ownership. Nobody holds Welcomes for a deleted identity.
+    let recipient = &req.envelope.recipient_ipk.0;
+    if !self_is_owner_for_recipient(dht, recipient) || dht.store.is_tombstoned(recipient) {
         return WelcomePublishOutcome::NotOwner;
     }
 
@@ -405,7 +418,14 @@ pub(crate) fn handle_welcome_publish(
         }
     }
 
-    // 5. Per-recipient and per-sender caps.
+    // 5. Per-recipient and per-sender caps and byte quotas.
+    let envelope_bytes = match req.envelope.ser() {
+        Ok(b) => b,
+        Err(e) => {
+            common::warn!("MLS welcome_publish: envelope encode failed: {e:?}");
+            return WelcomePublishOutcome::BadSig;
+        }
+    };
     let id = welcome_id(&req.envelope);
     let key = storage_key(&req.envelope.recipient_ipk.0, &id);
     if welcome_queue_full(
@@ -413,6 +433,8 @@ pub(crate) fn handle_welcome_publish(
         &req.envelope.recipient_ipk.0,
         &req.envelope.sender_ipk.0,
         &key,
+        8 + envelope_bytes.len(),
+        dht.cfg.welcome_quota(),
     ) {
         common::warn!(
             "MLS welcome_publish: queue full for recipient={}",
@@ -422,14 +444,6 @@ pub(crate) fn handle_welcome_publish(
     }
 
     // 6. Persist.
-    let envelope_bytes = match req.envelope.ser() {
-        Ok(b) => b,
-        Err(e) => {
-            common::warn!("MLS welcome_publish: envelope encode failed: {e:?}");
-            return WelcomePublishOutcome::BadSig;
-        }
-    };
-
     let expires_at_ms = now_ms.saturating_add(WELCOME_LIFETIME_MS);
     let mut value = Vec::with_capacity(8 + envelope_bytes.len());
     value.extend_from_slice(&expires_at_ms.to_be_bytes());
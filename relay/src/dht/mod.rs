@@ -12,23 +12,31 @@
 
 // config + metrics are `pub` because they're referenced from public
 // types like `DhtConfig` in `Dht::new` (already re-exported below).
+pub(crate) mod account;
+pub(crate) mod blob;
 pub(crate) mod bootstrap;
 pub mod config;
 pub(crate) mod forward;
+pub(crate) mod gateway_pool;
 pub(crate) mod handler;
 
 pub(crate) mod lookup;
 pub mod metrics;
 pub(crate) mod mls;
 pub(crate) mod peer_dial;
+pub(crate) mod push_key;
 pub(crate) mod push_replication;
 pub(crate) mod push_wake;
 pub(crate) mod queue_drain;
 pub(crate) mod rate_limit;
+pub(crate) mod reputation;
 pub(crate) mod routing;
+pub(crate) mod sealed;
 pub(crate) mod store;
+pub(crate) mod succession;
 pub(crate) mod sync;
 pub(crate) mod tls_extract;
+pub(crate) mod wake_policy;
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -46,7 +54,13 @@ use quinn::Endpoint;
 use self::metrics::Metrics;
 use self::mls::kp::KpFetchLimiters;
 use self::mls::welcome::WelcomeLimiters;
+use self::push_wake::WakeGate;
+use self::push_wake::WakeQueue;
+use self::reputation::Offence;
+use self::reputation::Reputation;
+use self::reputation::Standing;
 use self::routing::RoutingTable;
+use self::sealed::SealedLimiter;
 use crate::quic::resolver_link::ResolverLinkHandle;
 use crate::storage::db::Store;
 
@@ -149,6 +163,11 @@ pub struct Dht {
     /// limiter on every inbound stream.
     pub(crate) rate_limiters: rate_limit::PerPeerLimiters,
 
+    /// Decaying abuse score per authenticated peer. Charged through
+    /// [`Self::penalize`], which also applies the routing and connection
+    /// consequences; read on accept and dial to refuse banned peers.
+    pub(crate) reputation: Reputation,
+
     /// Per-`(target_ipk, requester_relay_id)` rate limiter for
     /// `KeyPackageFetch` (`MAX_KP_FETCH_PER_HOUR = 60`).
     /// Distinct from [`Self::rate_limiters`] (which is keyed on the
@@ -167,6 +186,15 @@ pub struct Dht {
     /// Mirrors the [`Self::kp_fetch_limiters`] pattern.
     pub(crate) welcome_limiters: WelcomeLimiters,
 
+    /// Per-token rate limiter for sealed-sender dispatches, which carry no
+    /// sender to key [`Self::rate_limiters`]-style quotas on. Checked at
+    /// ingress and again at the home. See [`sealed`].
+    pub(crate) sealed_limiter: SealedLimiter,
+
+    /// Relay-wide budget for the dials back that attest peers first seen
+    /// inbound. See [`lookup::attest_and_admit`].
+    pub(crate) attest_limiter: rate_limit::GlobalLimiter,
+
     /// Shared reference to the relay's connected-clients map.
     ///
     /// The home-side `Forward` handler in
@@ -198,11 +226,18 @@ pub struct Dht {
     /// Shared `IPK -> P` map for offline push wake-up.
     pub(crate) push_pseudonyms: Option<PushMap>,
 
-    /// Cached push-gateway directory, refreshed from the resolver. The enqueue
-    /// path dials one of these to send a [`WakeRequest`], verifying its
+    /// Cached push-gateway directory, refreshed from the resolver. The wake
+    /// batcher sends to these over pooled connections, verifying each one's
     /// `PUSH_GATEWAY` capability at dial. Empty → no wakes.
     pub(crate) push_gateways: PushGateways,
 
+    /// Wakes queued by the enqueue path for `push_wake::run_wake_batcher`.
+    pub(crate) wake_queue: WakeQueue,
+
+    /// Per-recipient wake coalescing and backoff, reset when the recipient
+    /// drains. Consulted by [`Self::trigger_wake`].
+    pub(crate) wake_gate: WakeGate,
+
     /// Latches once [`routing`] has been observed holding `K` or more
     /// peers. Read by [`routing::self_in_top_k`] to tell "this network
     /// is smaller than K" apart from "this relay lost sight of a network
@@ -210,6 +245,10 @@ pub struct Dht {
     ///
     /// [`routing`]: Self::routing
     routing_dense: std::sync::atomic::AtomicBool,
+
+    /// Mirrors `Relay::blob_store`: whether [`blob::handle_fetch`] may serve
+    /// from the local blob store. `false` in unit-test fixtures.
+    pub(crate) blob_store: bool,
 }
 
 /// Shared reference to the relay's connected-clients map. Aliased so
@@ -233,6 +272,7 @@ impl Dht {
     pub fn new(
         node_id: NodeId, signing_key: SigningKey, cfg: DhtConfig, store: Arc<Store>,
     ) -> Result<Self> {
+        let rate_limiters = rate_limit::PerPeerLimiters::new(&cfg.rate_limits);
         Ok(Self {
             routing: RwLock::new(RoutingTable::empty(node_id)),
             store,
@@ -244,14 +284,20 @@ impl Dht {
             metrics: Metrics::new(),
             endpoint: None,
             peer_client_cfg: None,
-            rate_limiters: rate_limit::PerPeerLimiters::new(),
+            rate_limiters,
+            reputation: Reputation::new(),
             kp_fetch_limiters: KpFetchLimiters::new(),
             welcome_limiters: WelcomeLimiters::new(),
+            sealed_limiter: SealedLimiter::new(),
+            attest_limiter: rate_limit::per_minute(config::MAX_ATTESTATION_DIALS_PER_MINUTE),
             clients: None,
             presence_leases: None,
             push_pseudonyms: None,
             push_gateways: Arc::new(RwLock::new(Vec::new())),
+            wake_queue: WakeQueue::new(),
+            wake_gate: WakeGate::new(),
             routing_dense: std::sync::atomic::AtomicBool::new(false),
+            blob_store: false,
         })
     }
 
@@ -309,6 +355,38 @@ impl Dht {
         *self.resolver.write() = Some(handle);
     }
 
+    /// Charge `offence` to an authenticated `peer` and act on the
+    /// resulting standing: a deprioritized peer is demoted in its bucket;
+    /// a newly banned one is dropped from routing and its cached
+    /// connection closed with `CloseReason::DhtBanned`.
+    ///
+    /// Callers must only pass ids bound by a verified hello or dial
+    /// cert — see the attribution rule in [`reputation`].
+    pub(crate) fn penalize(&self, peer: &NodeId, offence: Offence) -> Standing {
+        use common::quic::CloseReason;
+
+        self.metrics.inc_reputation_penalties();
+        let was_banned = self.reputation.is_banned(peer);
+        let standing = self.reputation.penalize(peer, offence);
+        match standing {
+            Standing::Good => {},
+            Standing::Deprioritized => self.routing.write().demote(peer),
+            Standing::Banned => {
+                if !was_banned {
+                    self.metrics.inc_peers_banned();
+                    common::warn!("banning DHT peer {peer} after {offence:?}");
+                }
+                self.routing.write().remove(peer);
+                let conn = self.peer_conns.write().remove(peer);
+                if let Some((conn, _pk)) = conn {
+                    CloseReason::DhtBanned.close(&conn);
+                    self.metrics.inc_peer_conns_closed();
+                }
+            },
+        }
+        standing
+    }
+
     /// Close every cached peer connection and clear the map. Called by
     /// the `Relay`-level shutdown handler so in-flight DHT RPCs cleanly
     /// finish before the QUIC endpoint is torn down.
@@ -329,3 +407,13 @@ impl Dht {
         }
     }
 }
+
+/// Whether `conn` settled on a version that carries dispatches whole, wake
+/// hint and token included. Below [`DISPATCH_V7_VERSION`] they travel in the
+/// v6 layout.
+///
+/// [`DISPATCH_V7_VERSION`]: common::proto::client_rel::DISPATCH_V7_VERSION
+pub(crate) fn carries_whole_dispatches(conn: &Connection) -> bool {
+    common::quic::protorole::ProtoRole::version_from_conn(conn)
+        .is_some_and(|v| v >= common::proto::client_rel::DISPATCH_V7_VERSION)
+}
@@ -26,6 +26,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
+use common::quic::config::hybrid_client_tls;
 use common::quic::protorole::ProtoRole;
 use ed25519_dalek::Signature as Ed25519Signature;
 use ed25519_dalek::Verifier as _;
@@ -102,11 +103,11 @@ impl ServerCertVerifier for PeerServerCertVerifier {
 /// verifier + the `peer` ALPN. Transport settings mirror the private
 /// `common::quic::config::default_client_transport`.
 pub(crate) fn build_peer_client_cfg() -> Result<quinn::ClientConfig> {
-    let mut tls = rustls::ClientConfig::builder()
+    let mut tls = hybrid_client_tls()
         .dangerous()
         .with_custom_certificate_verifier(Arc::new(PeerServerCertVerifier))
         .with_no_client_auth();
-    tls.alpn_protocols = vec![ProtoRole::Peer.alpn().into()];
+    tls.alpn_protocols = ProtoRole::Peer.alpns();
 
     let quic = QuicClientConfig::try_from(tls)?;
     let mut cfg = quinn::ClientConfig::new(Arc::new(quic));
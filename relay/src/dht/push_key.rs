@@ -0,0 +1,158 @@
+//! Device push keys, replicated to the owner's DHT homes and served to
+//! senders.
+//!
+//! A device publishes an owner-signed [`PushKeyRecord`] on connect, the same
+//! way it re-sends its wake policy (see [`super::wake_policy`]): the relay
+//! verifies it and fans it out to every home, which keeps the newest by
+//! timestamp. A sender's relay asks the homes for it when its client wants
+//! to seal a wake hint, and takes the newest record that still verifies.
+//! The client re-checks the signature, so a home that serves a stale or
+//! forged key gets, at worst, a hint the device can't open.
+//!
+//! [`PushKeyRecord`]: common::proto::dht_p2p::PushKeyRecord
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::proto::dht_p2p::DhtPacket;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::MAX_DHT_HELLO_SKEW_MS;
+use common::proto::dht_p2p::NodeDescriptor;
+use common::proto::dht_p2p::PushKeyFetch;
+use common::proto::dht_p2p::PushKeyFetchResp;
+use common::proto::dht_p2p::PushKeyPublishResp;
+use common::proto::dht_p2p::PushKeyRecord;
+use common::proto::dht_p2p::push_key_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use ed25519_dalek::Signature;
+use ed25519_dalek::VerifyingKey;
+use tokio::time::timeout;
+
+use super::Dht;
+use super::config::FORWARD_TIMEOUT_MS;
+use super::config::K;
+
+/// Store locally if this relay is a home, then send the record to every other
+/// home. Best-effort: a home that misses it catches up on the next connect.
+pub(crate) async fn replicate_to_homes(dht: Arc<Dht>, record: PushKeyRecord) {
+    let target = NodeId::from_bytes(record.user_ipk.0);
+    if super::routing::self_in_top_k(&dht, &target) {
+        let _ = dht.store.put_push_key(&record);
+    }
+    let homes = dht.routing.read().find_closest(&target, K);
+    let mut set = tokio::task::JoinSet::new();
+    for home in homes {
+        let request = DhtRequest::PushKeyPublish(record.clone());
+        set.spawn(request_one(dht.clone(), home, request));
+    }
+    while set.join_next().await.is_some() {}
+}
+
+/// The newest valid push key for `target_ipk` across its homes, this relay
+/// included when it is one.
+pub(crate) async fn fetch_from_homes(dht: Arc<Dht>, target_ipk: [u8; 32]) -> Option<PushKeyRecord> {
+    let target = NodeId::from_bytes(target_ipk);
+    let mut newest = super::routing::self_in_top_k(&dht, &target)
+        .then(|| dht.store.get_push_key(&target_ipk))
+        .flatten();
+
+    let homes = dht.routing.read().find_closest(&target, K);
+    let mut set = tokio::task::JoinSet::new();
+    for home in homes {
+        let request = DhtRequest::PushKeyFetch(PushKeyFetch { target_ipk: target_ipk.into() });
+        set.spawn(request_one(dht.clone(), home, request));
+    }
+    while let Some(joined) = set.join_next().await {
+        let Ok(Some(DhtResponse::PushKeyFetch(PushKeyFetchResp { record: Some(record) }))) = joined
+        else {
+            continue;
+        };
+        if record.user_ipk.0 == target_ipk
+            && valid_signature(&record)
+            && newest.as_ref().is_none_or(|n| record.timestamp > n.timestamp)
+        {
+            newest = Some(record);
+        }
+    }
+    newest
+}
+
+async fn request_one(
+    dht: Arc<Dht>, home: NodeDescriptor, request: DhtRequest,
+) -> Option<DhtResponse> {
+    timeout(Duration::from_millis(FORWARD_TIMEOUT_MS), async {
+        let conn = super::lookup::connect_to_peer(&dht, &home).await.ok()?;
+        let bytes = DhtPacket::Request(request).pack().ok()?;
+        let (mut tx, mut rx) = conn.open_bi().await.ok()?;
+        tx.write_all(&bytes).await.ok()?;
+        tx.finish().ok()?;
+        match DhtPacket::unpack(&mut rx).await.ok()? {
+            DhtPacket::Response(resp) => Some(resp),
+            _ => None,
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Validate owner signature and freshness, require target-home ownership,
+/// then keep the record if it is newer than the one stored.
+pub(crate) fn handle_publish(dht: &Dht, record: PushKeyRecord, now_ms: u64) -> PushKeyPublishResp {
+    if !valid_publish(&record, now_ms)
+        || !super::routing::self_in_top_k(dht, &NodeId::from_bytes(record.user_ipk.0))
+    {
+        return PushKeyPublishResp { accepted: false };
+    }
+    PushKeyPublishResp { accepted: dht.store.put_push_key(&record).unwrap_or(false) }
+}
+
+/// Serve the stored record. Push keys are public, so any relay may ask.
+pub(crate) fn handle_fetch(dht: &Dht, req: PushKeyFetch) -> PushKeyFetchResp {
+    PushKeyFetchResp { record: dht.store.get_push_key(&req.target_ipk.0) }
+}
+
+/// A fresh publish: signed by its owner within the skew window. Stored
+/// records are served later without the freshness check.
+pub(crate) fn valid_publish(record: &PushKeyRecord, now_ms: u64) -> bool {
+    now_ms.abs_diff(record.timestamp) <= MAX_DHT_HELLO_SKEW_MS && valid_signature(record)
+}
+
+fn valid_signature(record: &PushKeyRecord) -> bool {
+    let Ok(key) = VerifyingKey::from_bytes(&record.user_ipk.0) else {
+        return false;
+    };
+    let input = push_key_signing_input(&record.user_ipk.0, &record.push_pk.0, record.timestamp);
+    key.verify_strict(&input, &Signature::from_bytes(&record.user_sig.0)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+
+    #[test]
+    fn push_key_needs_owner_signature_and_freshness_only_to_publish() {
+        let key = SigningKey::from_bytes(&[6u8; 32]);
+        let ipk = key.verifying_key().to_bytes();
+        let now = 1_700_000_000_000;
+        let sig = key.sign(&push_key_signing_input(&ipk, &[9u8; 32], now));
+        let record = PushKeyRecord {
+            user_ipk:  ipk.into(),
+            push_pk:   [9u8; 32].into(),
+            timestamp: now,
+            user_sig:  sig.to_bytes().into(),
+        };
+        assert!(valid_publish(&record, now));
+        assert!(!valid_publish(&record, now + MAX_DHT_HELLO_SKEW_MS + 1));
+        assert!(valid_signature(&record), "a stored record stays servable");
+
+        let swapped = PushKeyRecord { push_pk: [8u8; 32].into(), ..record };
+        assert!(!valid_signature(&swapped));
+    }
+}
@@ -4,33 +4,72 @@
 //! Best-effort by design: a failed wake is logged and dropped — the message is
 //! already durably queued and delivers on the recipient's next foreground
 //! drain. Nothing here is on the correctness path.
+//!
+//! ## Who gets woken, and how often
+//!
+//! A wake must first pass the recipient's signed wake policy (see
+//! [`super::wake_policy`]), then the [`WakeGate`]: the first wake opens a
+//! coalescing window during which further messages just queue, since the
+//! device will drain them all at once. Each window that ends without a drain
+//! doubles the next, so a device that is off or out of coverage is not
+//! pushed at for every message. A drain resets it.
+//!
+//! ## Batching
+//!
+//! [`Dht::trigger_wake`] only queues the pseudonym. [`run_wake_batcher`]
+//! holds the first wake of a burst for [`WAKE_BATCH_WINDOW`], coalesces
+//! whatever else arrives (up to [`MAX_WAKE_BATCH`]) into one
+//! [`PushRequest::WakeBatch`], and sends it over the connections pooled in
+//! [`GatewayPool`], so a burst of offline messages costs one stream per
+//! gateway rather than one TLS handshake per recipient. A gateway that
+//! doesn't acknowledge the batch is sent the same wakes as single `Wake`s.
+//!
+//! ## Payload
+//!
+//! A wake carries the dispatch's sealed wake hint (see
+//! [`common::proto::push::SealedWakeHint`]) as its payload when the sender
+//! attached one, so the device can name the sender and conversation before
+//! it drains. Relay and gateway only pass the ciphertext along; a batch that
+//! coalesced several wakes for one pseudonym keeps the latest hint.
 
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::sync::LazyLock;
 use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Result;
 use anyhow::anyhow;
 use common::debug;
-use common::node::capability::NodeCapabilities;
 use common::proto::client_res::GatewayDescriptor;
 use common::proto::pack::Packer;
+use common::proto::push::MAX_WAKE_BATCH;
+use common::proto::push::MAX_WAKE_HINT_BYTES;
 use common::proto::push::PushRequest;
+use common::proto::push::WAKE_BATCH_ACK;
+use common::proto::push::WakeBatch;
 use common::proto::push::WakeRequest;
 use common::types::bytes::Bytes;
 use governor::Quota;
 use governor::RateLimiter;
 use governor::clock::DefaultClock;
 use governor::state::keyed::DefaultKeyedStateStore;
+use parking_lot::Mutex;
+use quinn::Connection;
 use quinn::Endpoint;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
 use tokio::time::timeout;
 
 use super::Dht;
+use super::gateway_pool::GatewayPool;
 use crate::quic::resolver_link::ResolverLinkHandle;
 
-/// Sustained and burst wake budget per recipient. A wake costs a QUIC dial, so
-/// the enqueue path cannot be allowed to mint one per injected dispatch.
+/// Sustained and burst wake budget per recipient. Each wake still fans out to
+/// every gateway, so the enqueue path cannot be allowed to mint one per
+/// injected dispatch.
 const MAX_WAKES_PER_RECIPIENT_PER_HOUR: u32 = 120;
 const MAX_WAKE_BURST: u32 = 12;
 
@@ -39,9 +78,25 @@ const MAX_WAKE_BURST: u32 = 12;
 /// has to reach every gateway that could be holding the mapping.
 const MAX_WAKE_GATEWAYS: usize = 8;
 
-/// Per-gateway wall-clock budget for one wake.
+/// Per-gateway wall-clock budget for one batch, dial included.
 const WAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// How long the first wake of a burst waits for company before its batch is
+/// sent. Short next to push-service latency, long enough to catch a fan-out.
+const WAKE_BATCH_WINDOW: Duration = Duration::from_millis(50);
+
+/// Wakes queued ahead of the batcher. Past this, new wakes are dropped.
+const WAKE_QUEUE_DEPTH: usize = 4096;
+
+/// First coalescing window per recipient; doubles per undrained wake.
+const WAKE_COALESCE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Ceiling on the coalescing window while a device keeps not draining.
+const WAKE_BACKOFF_MAX: Duration = Duration::from_secs(30 * 60);
+
+/// Recipients tracked by the [`WakeGate`].
+const MAX_WAKE_GATE_ENTRIES: usize = 16_384;
+
 type WakeLimiter = RateLimiter<[u8; 32], DefaultKeyedStateStore<[u8; 32]>, DefaultClock>;
 
 static WAKE_LIMITER: LazyLock<WakeLimiter> = LazyLock::new(|| {
@@ -52,47 +107,221 @@ static WAKE_LIMITER: LazyLock<WakeLimiter> = LazyLock::new(|| {
     RateLimiter::keyed(quota)
 });
 
+/// A queued wake: the pseudonym and its payload (possibly empty).
+type QueuedWake = ([u8; 32], Vec<u8>);
+
+/// Wakes waiting for [`run_wake_batcher`]. The receiver sits here until the
+/// batcher task takes it.
+#[derive(Debug)]
+pub(crate) struct WakeQueue {
+    tx: mpsc::Sender<QueuedWake>,
+    rx: Mutex<Option<mpsc::Receiver<QueuedWake>>>,
+}
+
+impl WakeQueue {
+    pub(crate) fn new() -> Self {
+        let (tx, rx) = mpsc::channel(WAKE_QUEUE_DEPTH);
+        Self { tx, rx: Mutex::new(Some(rx)) }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    quiet_until: Instant,
+    undrained:   u32,
+}
+
+/// What [`WakeGate::admit`] decided for one wake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GateVerdict {
+    Wake,
+    /// An earlier wake's window is still open.
+    Coalesced,
+    /// The per-recipient quota refused it; the window stays as it was.
+    OverQuota,
+}
+
+/// Per-recipient coalescing window and undrained-wake backoff. See the module
+/// docs.
+#[derive(Debug, Default)]
+pub(crate) struct WakeGate {
+    recipients: Mutex<HashMap<[u8; 32], Backoff>>,
+}
+
+impl WakeGate {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a wake may go out for `ipk` now; if so, opens the next window.
+    /// `quota` is asked only once the window is clear, and a refusal leaves
+    /// the window closed, so a rate-limited wake never extends it.
+    pub(crate) fn admit(&self, ipk: &[u8; 32], quota: impl FnOnce() -> bool) -> GateVerdict {
+        self.admit_at(ipk, Instant::now(), quota)
+    }
+
+    fn admit_at(
+        &self, ipk: &[u8; 32], now: Instant, quota: impl FnOnce() -> bool,
+    ) -> GateVerdict {
+        let mut recipients = self.recipients.lock();
+        let undrained = match recipients.get(ipk) {
+            Some(b) if now < b.quiet_until => return GateVerdict::Coalesced,
+            Some(b) => b.undrained.saturating_add(1),
+            None => 0,
+        };
+        if !quota() {
+            return GateVerdict::OverQuota;
+        }
+        if !recipients.contains_key(ipk) && recipients.len() >= MAX_WAKE_GATE_ENTRIES {
+            // Anyone a full backoff past their window has gone quiet anyway.
+            recipients.retain(|_, b| now < b.quiet_until + WAKE_BACKOFF_MAX);
+            if recipients.len() >= MAX_WAKE_GATE_ENTRIES {
+                // Untracked, so still capped by the per-recipient limiter.
+                return GateVerdict::Wake;
+            }
+        }
+        let window = WAKE_COALESCE_WINDOW.saturating_mul(1 << undrained.min(16));
+        recipients
+            .insert(*ipk, Backoff { quiet_until: now + window.min(WAKE_BACKOFF_MAX), undrained });
+        GateVerdict::Wake
+    }
+
+    /// The device drained its queue: the next message may wake it at once.
+    pub(crate) fn reset(&self, ipk: &[u8; 32]) {
+        self.recipients.lock().remove(ipk);
+    }
+}
+
 impl Dht {
-    /// Wake `recipient_ipk`'s device if we hold its pseudonym and know a
-    /// gateway. No-op otherwise. Fire-and-forget: spawns the dial so the
+    /// Wake `recipient_ipk`'s device for a dispatch from `sender_ipk` if we
+    /// hold its pseudonym, its wake policy and [`WakeGate`] allow it, and we
+    /// know a gateway. No-op otherwise. Fire-and-forget: queues the pseudonym
+    /// and the dispatch's sealed `hint` for [`run_wake_batcher`] so the
     /// enqueue path never blocks on the gateway.
-    pub(crate) fn trigger_wake(&self, recipient_ipk: &[u8; 32]) {
+    pub(crate) fn trigger_wake(
+        &self, recipient_ipk: &[u8; 32], sender_ipk: &[u8; 32], hint: Option<&[u8]>,
+    ) {
         let who = hex::encode(&recipient_ipk[..8]);
-        let Some(endpoint) = &self.endpoint else {
+        if self.endpoint.is_none() {
             debug!("wake({who}) skipped: no DHT endpoint attached");
             return;
-        };
+        }
         let Some(pseudonym) = self.store.get_push_pseudonym(recipient_ipk) else {
             debug!("wake({who}) skipped: no IPK→P mapping (recipient never registered a pseudonym here)");
             return;
         };
-        if WAKE_LIMITER.check_key(recipient_ipk).is_err() {
-            debug!("wake({who}) skipped: per-recipient wake quota exhausted");
+        let now_ms = crate::util::systime().as_millis() as u64;
+        if let Some(publish) = self.store.get_wake_policy(recipient_ipk)
+            && !publish.policy.allows(sender_ipk, now_ms)
+        {
+            debug!("wake({who}) skipped: recipient's wake policy");
             return;
         }
-        let mut gateways = self.push_gateways.read().clone();
-        if gateways.is_empty() {
+        let quota = || WAKE_LIMITER.check_key(recipient_ipk).is_ok();
+        match self.wake_gate.admit(recipient_ipk, quota) {
+            GateVerdict::Wake => {},
+            GateVerdict::Coalesced => {
+                debug!("wake({who}) coalesced: an earlier wake is still outstanding");
+                return;
+            },
+            GateVerdict::OverQuota => {
+                debug!("wake({who}) skipped: per-recipient wake quota exhausted");
+                return;
+            },
+        }
+        if self.push_gateways.read().is_empty() {
             debug!("wake({who}) skipped: gateway directory empty (is a gateway registered with the resolver?)");
             return;
         }
-        gateways.sort_by_key(|g| g.id);
-        gateways.truncate(MAX_WAKE_GATEWAYS);
-        debug!(
-            "wake({who} P={}): dialing {} gateway(s)",
-            hex::encode(&pseudonym[..8]),
-            gateways.len()
-        );
-
-        let endpoint = endpoint.clone();
-        tokio::spawn(async move {
-            for gateway in &gateways {
-                match timeout(WAKE_TIMEOUT, send_wake(&endpoint, gateway, pseudonym)).await {
-                    Ok(Ok(())) => debug!("wake({who}): delivered to gateway {}", gateway.id),
-                    Ok(Err(e)) => debug!("wake({who}): gateway {} failed: {e}", gateway.id),
-                    Err(_) => debug!("wake({who}): gateway {} timed out", gateway.id),
-                }
+        // Homes re-check the bound: the hint arrived through another relay.
+        let payload = hint.filter(|h| h.len() <= MAX_WAKE_HINT_BYTES).unwrap_or_default();
+        match self.wake_queue.tx.try_send((pseudonym, payload.to_vec())) {
+            Ok(()) => debug!("wake({who} P={}): queued", hex::encode(&pseudonym[..8])),
+            Err(e) => debug!("wake({who}) dropped: {e}"),
+        }
+    }
+}
+
+/// Drain [`Dht::trigger_wake`]'s queue into batches and send each to the
+/// gateways. Runs for the life of the relay; spawned next to
+/// [`refresh_gateways`].
+pub(crate) async fn run_wake_batcher(dht: Arc<Dht>) {
+    let Some(endpoint) = dht.endpoint.clone() else {
+        debug!("wake batcher not started: no DHT endpoint attached");
+        return;
+    };
+    let Some(mut rx) = dht.wake_queue.rx.lock().take() else {
+        return;
+    };
+    let pool = Arc::new(GatewayPool::new());
+
+    while let Some((pseudonym, payload)) = rx.recv().await {
+        // Keyed by pseudonym: a later wake for the same device replaces the
+        // earlier one's payload.
+        let mut pending = BTreeMap::from([(pseudonym, payload)]);
+        let window = tokio::time::sleep(WAKE_BATCH_WINDOW);
+        tokio::pin!(window);
+        while pending.len() < MAX_WAKE_BATCH {
+            tokio::select! {
+                _ = &mut window => break,
+                next = rx.recv() => match next {
+                    Some((pseudonym, payload)) => {
+                        pending.insert(pseudonym, payload);
+                    },
+                    None => break,
+                },
             }
+        }
+        let batch: Vec<QueuedWake> = pending.into_iter().collect();
+
+        let directory = dht.push_gateways.read().clone();
+        pool.retain(&directory);
+        if directory.is_empty() {
+            debug!("wake batch of {} dropped: gateway directory empty", batch.len());
+            continue;
+        }
+        // Detached so a slow gateway can't hold up collecting the next batch.
+        tokio::spawn(flush(endpoint.clone(), pool.clone(), directory, Arc::new(batch)));
+    }
+}
+
+/// Send `batch` to [`MAX_WAKE_GATEWAYS`] gateways, healthy ones first. Each
+/// gateway that fails hands its slot to the next one in the directory.
+async fn flush(
+    endpoint: Endpoint, pool: Arc<GatewayPool>, directory: Vec<GatewayDescriptor>,
+    batch: Arc<Vec<QueuedWake>>,
+) {
+    let mut ranked = pool.targets(&directory, directory.len()).into_iter();
+    let mut inflight = JoinSet::new();
+    let send = |inflight: &mut JoinSet<_>, gateway: GatewayDescriptor| {
+        let (endpoint, pool, batch) = (endpoint.clone(), pool.clone(), batch.clone());
+        inflight.spawn(async move {
+            let sent = timeout(WAKE_TIMEOUT, send_batch(&endpoint, &pool, &gateway, &batch))
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("timed out")));
+            (gateway, sent)
         });
+    };
+    for gateway in ranked.by_ref().take(MAX_WAKE_GATEWAYS) {
+        send(&mut inflight, gateway);
+    }
+
+    while let Some(joined) = inflight.join_next().await {
+        let Ok((gateway, sent)) = joined else { continue };
+        match sent {
+            Ok(()) => {
+                pool.record_success(&gateway.id);
+                debug!("wake batch of {}: delivered to gateway {}", batch.len(), gateway.id);
+            },
+            Err(e) => {
+                let backoff = pool.record_failure(&gateway.id);
+                debug!("wake batch: gateway {} failed ({e}); backing off {backoff:?}", gateway.id);
+                if let Some(next) = ranked.next() {
+                    debug!("wake batch: failing over to gateway {}", next.id);
+                    send(&mut inflight, next);
+                }
+            },
+        }
     }
 }
 
@@ -108,34 +337,115 @@ pub(crate) async fn refresh_gateways(dht: Arc<Dht>, resolver: ResolverLinkHandle
     }
 }
 
-/// Dial the gateway over `relay/5` (the endpoint's default client config),
-/// verify it carries `PUSH_GATEWAY`, and send one [`WakeRequest`]. Contentless
-/// payload — the device wakes and drains via the normal sticky-home path.
-async fn send_wake(
-    endpoint: &Endpoint, gateway: &GatewayDescriptor, pseudonym: [u8; 32],
+/// Send one batch over the pooled connection to `gateway`. Payloads are
+/// sealed hints or empty; either way the device then drains via the normal
+/// sticky-home path.
+async fn send_batch(
+    endpoint: &Endpoint, pool: &GatewayPool, gateway: &GatewayDescriptor, batch: &[QueuedWake],
 ) -> Result<()> {
-    // ponytail: one QUIC dial per wake. Pool/cache the gateway connection if
-    // wake volume ever makes the per-message handshake hurt.
-    let conn = endpoint.connect(gateway.addr, &gateway.id.to_string())?.await?;
+    let conn = pool.connection(endpoint, gateway).await?;
+
+    let wakes: Vec<WakeRequest> = batch
+        .iter()
+        .map(|(p, payload)| WakeRequest { pseudonym: Bytes(*p), payload: payload.clone() })
+        .collect();
+    // A lone wake goes out as `Wake`, which every gateway understands.
+    if wakes.len() > 1 && pool.takes_batches(&gateway.id) {
+        let (mut send, mut recv) = conn.open_bi().await?;
+        let req = PushRequest::WakeBatch(WakeBatch { wakes: wakes.clone() });
+        send.write_all(&req.pack()?).await?;
+        send.finish()?;
+        // A gateway acks a batch it decoded. One that predates `WakeBatch`
+        // drops the stream unread, which `stopped()` can't tell apart from a
+        // read-and-done stream — so no ack means resend the wakes singly.
+        if recv.read_to_end(1).await.is_ok_and(|ack| ack == [WAKE_BATCH_ACK]) {
+            return Ok(());
+        }
+        debug!("wake batch: gateway {} sent no ack; sending wakes singly", gateway.id);
+        pool.record_singles_only(&gateway.id);
+    }
 
-    // The resolver directory is untrusted — verify the dialed node's CA-signed
-    // capability before handing it a pseudonym + wake.
-    let caps = super::tls_extract::capabilities_from_conn(&conn)
-        .ok_or_else(|| anyhow!("gateway cert carries no capability extension"))?;
-    if !caps.contains(NodeCapabilities::PUSH_GATEWAY) {
-        conn.close(0u32.into(), b"not-a-gateway");
-        return Err(anyhow!("dialed {} lacks PUSH_GATEWAY", gateway.id));
+    let mut inflight = JoinSet::new();
+    for wake in wakes {
+        let conn = conn.clone();
+        inflight.spawn(async move { send_wake(&conn, PushRequest::Wake(wake)).await });
     }
+    while let Some(joined) = inflight.join_next().await {
+        joined??;
+    }
+    Ok(())
+}
 
+/// One `Wake` on its own stream of `conn`.
+async fn send_wake(conn: &Connection, req: PushRequest) -> Result<()> {
     let (mut send, _recv) = conn.open_bi().await?;
-    let req = PushRequest::Wake(WakeRequest { pseudonym: Bytes(pseudonym), payload: Vec::new() });
     send.write_all(&req.pack()?).await?;
     send.finish()?;
-    // finish() only marks the stream done locally; close() would drop the
-    // still-in-flight frame before the gateway reads it. Await the gateway
-    // consuming it first — same handshake the token-registration path uses
-    // (libcore push::send_registration).
-    let _ = send.stopped().await;
-    conn.close(0u32.into(), b"wake-sent");
+    // finish() only marks the stream done locally. Await the gateway consuming
+    // the frame so a failure shows up here and counts against its health —
+    // same handshake the token-registration path uses (libcore
+    // push::send_registration). The connection stays open for the next batch.
+    send.stopped().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> bool {
+        true
+    }
+
+    #[test]
+    fn wakes_coalesce_and_back_off_until_a_drain() {
+        let gate = WakeGate::new();
+        let t0 = Instant::now();
+        let ipk = [1u8; 32];
+
+        assert_eq!(gate.admit_at(&ipk, t0, open), GateVerdict::Wake);
+        let early = t0 + WAKE_COALESCE_WINDOW / 2;
+        assert_eq!(gate.admit_at(&ipk, early, open), GateVerdict::Coalesced);
+        let other = gate.admit_at(&[2u8; 32], t0, open);
+        assert_eq!(other, GateVerdict::Wake, "other recipients are independent");
+
+        // Undrained: the second window is twice the first.
+        let t1 = t0 + WAKE_COALESCE_WINDOW;
+        assert_eq!(gate.admit_at(&ipk, t1, open), GateVerdict::Wake);
+        assert_eq!(gate.admit_at(&ipk, t1 + WAKE_COALESCE_WINDOW, open), GateVerdict::Coalesced);
+        assert_eq!(gate.admit_at(&ipk, t1 + WAKE_COALESCE_WINDOW * 2, open), GateVerdict::Wake);
+
+        gate.reset(&ipk);
+        assert_eq!(gate.admit_at(&ipk, t1 + WAKE_COALESCE_WINDOW * 2, open), GateVerdict::Wake);
+    }
+
+    #[test]
+    fn a_rate_limited_wake_leaves_the_window_shut() {
+        let gate = WakeGate::new();
+        let t0 = Instant::now();
+        let ipk = [4u8; 32];
+
+        assert_eq!(gate.admit_at(&ipk, t0, || false), GateVerdict::OverQuota);
+        // The refusal opened no window: a wake with quota goes out at once.
+        assert_eq!(gate.admit_at(&ipk, t0, open), GateVerdict::Wake);
+        // A coalesced wake never spends quota.
+        let spent = std::cell::Cell::new(false);
+        let verdict = gate.admit_at(&ipk, t0, || {
+            spent.set(true);
+            true
+        });
+        assert_eq!(verdict, GateVerdict::Coalesced);
+        assert!(!spent.get());
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let gate = WakeGate::new();
+        let mut now = Instant::now();
+        let ipk = [3u8; 32];
+        for _ in 0..24 {
+            assert_eq!(gate.admit_at(&ipk, now, open), GateVerdict::Wake);
+            now += WAKE_BACKOFF_MAX;
+        }
+    }
+}
@@ -30,6 +30,16 @@
 //! also dedupes cross-home replicas by `id` before returning so the
 //! down-stream drain count is honest.
 //!
+//! ## Mailbox queues
+//!
+//! `user_ipk` here is the queue's key, which is the IPK or a rotating
+//! mailbox id (`common::crypto::mailbox`). A mailbox id is an Ed25519 key
+//! that only its owner can sign for, so `QueueFetch` and `QueueFetchAck`
+//! verify under it unchanged. A home never learns which IPK a mailbox
+//! belongs to. The client keeps the previous epoch's mailbox open for one
+//! [`super::store`] retention past its overlap window, so a mailbox never
+//! rotates away with mail still queued under it.
+//!
 //! ## Lock contract
 //!
 //! `parking_lot::RwLock<RoutingTable>` is read once to compute the
@@ -315,9 +325,10 @@ async fn remote_fetch_one(
     send.finish().ok()?;
 
     match DhtPacket::unpack(&mut recv).await.ok()? {
-        DhtPacket::Response(DhtResponse::QueueFetch(QueueFetchResp { messages, .. })) => {
-            Some(messages)
-        },
+        DhtPacket::Response(
+            DhtResponse::QueueFetch(QueueFetchResp { messages, .. })
+            | DhtResponse::QueueFetchV7(QueueFetchResp { messages, .. }),
+        ) => Some(messages),
         _ => None,
     }
 }
@@ -378,6 +389,9 @@ pub(crate) async fn handle_queue_fetch_rpc(
         return QueueFetchResp { messages: Vec::new(), exhausted: true };
     }
 
+    // The owner is online and draining; its next message may wake at once.
+    dht.wake_gate.reset(&user_ipk);
+
     // 4. Read one count- and byte-bounded batch.
     let (batch, exhausted) =
         super::store::queue_batch_for_user(dht, &user_ipk, MAX_FETCH_QUEUE_BATCH);
@@ -435,6 +449,7 @@ pub(crate) async fn handle_queue_fetch_ack_rpc(
         return QueueFetchAckResp { ok: false };
     }
     let user_ipk = req.user_ipk.0;
+    dht.wake_gate.reset(&user_ipk);
     let _deleted = super::store::delete_queue_entries(dht, &user_ipk, &req.delivered_ids);
     QueueFetchAckResp { ok: true }
 }
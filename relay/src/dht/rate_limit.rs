@@ -5,13 +5,16 @@
 //! Three keyed `governor` limiters — one per RPC cost class (cheap /
 //! expensive / bulk), each keyed on the *requester* NodeId — sit under a
 //! single unkeyed global limiter. Tripping any of them closes the
-//! inbound connection with `CloseReason::DhtFlood` and bumps a metrics
-//! counter.
+//! inbound connection with `CloseReason::DhtFlood`, bumps a metrics
+//! counter, and charges the requester a `Flood` offence in
+//! [`super::reputation`] — so a peer that keeps reconnecting to flood
+//! ends up banned rather than merely disconnected.
 //!
 //! ## Why three classes
 //!
 //! The cost of an RPC drives the quota — see the `RATE_LIMIT_*`
-//! constants in `super::config` for the values and their sizing:
+//! constants in `super::config` for the defaults and their sizing, and
+//! `[dht.rate_limits]` (`DhtRateLimits`) for the operator overrides:
 //!
 //! - **Cheap** (`FindNode`): no signature verification, no disk I/O; a routing-table read and a
 //!   bounded descriptor list back.
@@ -46,14 +49,7 @@ use governor::state::InMemoryState;
 use governor::state::NotKeyed;
 use governor::state::keyed::DefaultKeyedStateStore;
 
-use super::config::RATE_LIMIT_BULK_BURST;
-use super::config::RATE_LIMIT_BULK_PER_SEC;
-use super::config::RATE_LIMIT_CHEAP_BURST;
-use super::config::RATE_LIMIT_CHEAP_PER_SEC;
-use super::config::RATE_LIMIT_EXPENSIVE_BURST;
-use super::config::RATE_LIMIT_EXPENSIVE_PER_SEC;
-use super::config::RATE_LIMIT_GLOBAL_BURST;
-use super::config::RATE_LIMIT_GLOBAL_PER_SEC;
+use super::config::DhtRateLimits;
 
 /// Keyed limiter type alias — one entry per NodeId, with automatic
 /// eviction of idle entries (`DefaultKeyedStateStore` handles that
@@ -62,7 +58,7 @@ use super::config::RATE_LIMIT_GLOBAL_PER_SEC;
 type NodeLimiter = RateLimiter<NodeId, DefaultKeyedStateStore<NodeId>, DefaultClock>;
 
 /// Unkeyed limiter type alias for the aggregate budget.
-type GlobalLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+pub(crate) type GlobalLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
 
 /// Three per-peer limiters, one per RPC cost class, plus the aggregate
 /// budget every inbound RPC also draws from.
@@ -75,29 +71,35 @@ pub(crate) struct PerPeerLimiters {
 }
 
 impl PerPeerLimiters {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(limits: &DhtRateLimits) -> Self {
+        let (cheap_rate, cheap_burst) = limits.cheap();
+        let (expensive_rate, expensive_burst) = limits.expensive();
+        let (bulk_rate, bulk_burst) = limits.bulk();
+        let (global_rate, global_burst) = limits.global();
         Self {
-            cheap: build_limiter(RATE_LIMIT_CHEAP_PER_SEC, RATE_LIMIT_CHEAP_BURST),
-            expensive: build_limiter(RATE_LIMIT_EXPENSIVE_PER_SEC, RATE_LIMIT_EXPENSIVE_BURST),
-            bulk: build_limiter(RATE_LIMIT_BULK_PER_SEC, RATE_LIMIT_BULK_BURST),
-            global: RateLimiter::direct(quota(
-                RATE_LIMIT_GLOBAL_PER_SEC,
-                RATE_LIMIT_GLOBAL_BURST,
-            )),
+            cheap: build_limiter(cheap_rate, cheap_burst),
+            expensive: build_limiter(expensive_rate, expensive_burst),
+            bulk: build_limiter(bulk_rate, bulk_burst),
+            global: RateLimiter::direct(quota(global_rate, global_burst)),
         }
     }
 }
 
-/// `per_second(rate).allow_burst(burst)`. Constants come from
-/// `config.rs`; we use `NonZeroU32::MIN` (= 1) as a defensive fallback
-/// in case a future edit zeros one of them, mirroring the resolver
-/// acceptor pattern.
+/// `per_second(rate).allow_burst(burst)`. Values come from
+/// [`DhtRateLimits`]; we use `NonZeroU32::MIN` (= 1) as a defensive
+/// fallback in case an operator zeros one of them, mirroring the
+/// resolver acceptor pattern.
 fn quota(rate_per_sec: u32, burst: u32) -> Quota {
     let rate = NonZeroU32::new(rate_per_sec).unwrap_or(NonZeroU32::MIN);
     let burst = NonZeroU32::new(burst).unwrap_or(NonZeroU32::MIN);
     Quota::per_second(rate).allow_burst(burst)
 }
 
+/// Unkeyed limiter allowing `per_min` a minute, bursting to the full minute.
+pub(crate) fn per_minute(per_min: u32) -> GlobalLimiter {
+    RateLimiter::direct(Quota::per_minute(NonZeroU32::new(per_min).unwrap_or(NonZeroU32::MIN)))
+}
+
 fn build_limiter(rate_per_sec: u32, burst: u32) -> NodeLimiter {
     RateLimiter::keyed(quota(rate_per_sec, burst))
 }
@@ -119,8 +121,9 @@ impl RpcClass {
     pub(crate) fn for_request(req: &common::proto::dht_p2p::DhtRequest) -> Self {
         use common::proto::dht_p2p::DhtRequest;
         match req {
-            // `FindNode` is a routing-table read and nothing else.
-            DhtRequest::FindNode(_) => RpcClass::Cheap,
+            // `FindNode` is a routing-table read and nothing else;
+            // `PushKeyFetch` a single point read.
+            DhtRequest::FindNode(_) | DhtRequest::PushKeyFetch(_) => RpcClass::Cheap,
             // Sticky-home: `Forward` does an outer-sig verify plus a
             // disk write (queue) or stream open (deliver).
             // `QueueFetch` does a user-sig verify plus a per-recipient
@@ -130,19 +133,32 @@ impl RpcClass {
             // I/O — same cost shape. A separate per-pair
             // `(target_ipk, requester_relay_id)` quota lives inside
             // `mls/kp.rs` for the anti-pinning policy; this per-peer
-            // bucket is the coarser first line.
+            // bucket is the coarser first line. `IdentitySuccession`
+            // verifies two signatures and may sweep a stash;
+            // `QueueHandover` a batch of sender sigs plus queue writes;
+            // `DeleteAccount` one verify plus a sweep of every keyspace;
+            // `TokenRevocation` one verify plus a write.
             DhtRequest::QueueFetchAck(_)
             | DhtRequest::Forward(_)
+            | DhtRequest::ForwardV7(_)
             | DhtRequest::ActivityForward(_)
             | DhtRequest::PresenceConsent(_)
             | DhtRequest::PresenceState(_)
             | DhtRequest::PresenceLease(_)
             | DhtRequest::LiveForward(_)
+            | DhtRequest::LiveForwardV7(_)
             | DhtRequest::PushPseudonymPublish(_)
+            | DhtRequest::WakePolicyPublish(_)
+            | DhtRequest::PushKeyPublish(_)
             | DhtRequest::QueueFetch(_)
             | DhtRequest::KeyPackagePublish(_)
             | DhtRequest::KeyPackageFetch(_)
-            | DhtRequest::KeyPackageRefill(_) => RpcClass::Expensive,
+            | DhtRequest::KeyPackageRefill(_)
+            | DhtRequest::IdentitySuccession(_)
+            | DhtRequest::QueueHandover(_)
+            | DhtRequest::DeleteAccount(_)
+            | DhtRequest::DeletePredecessor(_)
+            | DhtRequest::TokenRevocation(_) => RpcClass::Expensive,
             // MLS welcome publish carries up to a few KB of
             // `welcome_blob` plus envelope metadata; fetch returns up
             // to `MAX_WELCOMES_PER_RECIPIENT = 32` rows in a single
@@ -150,10 +166,11 @@ impl RpcClass {
             // because `welcome_blob` can hit
             // `MAX_WELCOME_BYTES = 256 KiB` in the worst case (large
             // groups), making them the heaviest single-RPC payload in
-            // the DHT family.
+            // the DHT family. A `BlobFetch` chunk can be twice that.
             DhtRequest::WelcomePublish(_)
             | DhtRequest::WelcomeFetch(_)
-            | DhtRequest::WelcomeAck(_) => RpcClass::Bulk,
+            | DhtRequest::WelcomeAck(_)
+            | DhtRequest::BlobFetch(_) => RpcClass::Bulk,
         }
     }
 }
@@ -175,6 +192,7 @@ impl PerPeerLimiters {
 
 #[cfg(test)]
 mod tests {
+    use super::super::config::RATE_LIMIT_EXPENSIVE_BURST;
     use super::*;
 
     fn id_from_seed(seed: u8) -> NodeId {
@@ -209,7 +227,7 @@ mod tests {
     fn global_budget_denies_a_peer_that_is_under_its_own_quota() {
         use super::super::config::RATE_LIMIT_GLOBAL_BURST;
 
-        let limiters = PerPeerLimiters::new();
+        let limiters = PerPeerLimiters::new(&DhtRateLimits::default());
         // Spread the drain across distinct peers, each staying well
         // under `RATE_LIMIT_CHEAP_BURST`, so only the global bucket can
         // be the one that trips.
@@ -227,7 +245,7 @@ mod tests {
         // Time-based quotas under `governor` are forgiving in test
         // environments (real-time wall clock), so we don't measure the
         // steady-state rate, only the burst behaviour.
-        let limiters = PerPeerLimiters::new();
+        let limiters = PerPeerLimiters::new(&DhtRateLimits::default());
         let peer = id_from_seed(7);
 
         // Drain the burst.
@@ -264,7 +282,7 @@ mod tests {
     #[test]
     fn limiter_isolates_per_peer() {
         // Different peers do not share quota.
-        let limiters = PerPeerLimiters::new();
+        let limiters = PerPeerLimiters::new(&DhtRateLimits::default());
         let peer_a = id_from_seed(1);
         let peer_b = id_from_seed(2);
 
@@ -276,4 +294,20 @@ mod tests {
         // Peer B should still get allowed at least once.
         assert!(limiters.check(&peer_b, RpcClass::Expensive).is_ok());
     }
+
+    #[test]
+    fn operator_override_replaces_the_default_burst() {
+        // One token a second, so nothing refills between the three calls.
+        let limits = DhtRateLimits {
+            bulk_per_sec: Some(1),
+            bulk_burst: Some(2),
+            ..DhtRateLimits::default()
+        };
+        let limiters = PerPeerLimiters::new(&limits);
+        let peer = id_from_seed(9);
+
+        assert!(limiters.check(&peer, RpcClass::Bulk).is_ok());
+        assert!(limiters.check(&peer, RpcClass::Bulk).is_ok());
+        assert!(limiters.check(&peer, RpcClass::Bulk).is_err());
+    }
 }
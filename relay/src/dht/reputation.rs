@@ -0,0 +1,253 @@
+//! Per-NodeId abuse scoring for `peer/5`.
+//!
+//! The rate limiters in [`super::rate_limit`] and the per-RPC validators
+//! reject bad traffic but forget the sender as soon as the connection
+//! closes. This module remembers: each provable offence adds points to
+//! the peer's score, the score halves every
+//! [`REPUTATION_HALF_LIFE_SECS`], and two thresholds turn a score into a
+//! [`Standing`]:
+//!
+//! - **Deprioritized** — the peer stays routable but is left out of our
+//!   `FindNode` replies and is the first to go when its bucket fills.
+//! - **Banned** — evicted from routing, its connection closed with
+//!   `CloseReason::DhtBanned`, and refused on accept and dial until the
+//!   ban lapses. A ban outlives the score: decay below the threshold does
+//!   not lift it early.
+//!
+//! ## Attribution
+//!
+//! Only ids authenticated by a verified `DhtHello` (inbound) or a pinned
+//! dial cert (outbound) are ever charged. A failure *before* that point —
+//! a bad hello signature, say — names an id the sender has not proven it
+//! owns, so charging it would let anyone spend another relay's standing.
+//!
+//! ## Lock contract
+//!
+//! One `parking_lot::Mutex` over the map; every method is a short
+//! critical section with no I/O, never held across an `await`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use common::quic::id::NodeId;
+use parking_lot::Mutex;
+
+use super::config::MAX_REPUTATION_ENTRIES;
+use super::config::PENALTY_BAD_SIGNATURE;
+use super::config::PENALTY_FLOOD;
+use super::config::PENALTY_MALFORMED;
+use super::config::PENALTY_STALE_TIMESTAMP;
+use super::config::REPUTATION_BAN_AT;
+use super::config::REPUTATION_BAN_SECS;
+use super::config::REPUTATION_DEPRIORITIZE_AT;
+use super::config::REPUTATION_HALF_LIFE_SECS;
+
+/// A kind of misbehaviour a peer can be charged for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Offence {
+    /// A signature the peer relayed or produced failed to verify.
+    BadSignature,
+    /// A signed timestamp fell outside the skew window.
+    StaleTimestamp,
+    /// An inbound-RPC rate limit tripped.
+    Flood,
+    /// A frame failed to decode, or arrived on the wrong side.
+    Malformed,
+}
+
+impl Offence {
+    fn weight(self) -> f64 {
+        match self {
+            Offence::BadSignature => PENALTY_BAD_SIGNATURE,
+            Offence::StaleTimestamp => PENALTY_STALE_TIMESTAMP,
+            Offence::Flood => PENALTY_FLOOD,
+            Offence::Malformed => PENALTY_MALFORMED,
+        }
+    }
+}
+
+/// What the current score means for how we treat the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Standing {
+    Good,
+    Deprioritized,
+    Banned,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Score {
+    /// Points as of `at`; decayed lazily on read.
+    points: f64,
+    at: Instant,
+    banned_until: Option<Instant>,
+}
+
+impl Score {
+    fn decayed(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.at).as_secs_f64();
+        self.points * 0.5f64.powf(elapsed / REPUTATION_HALF_LIFE_SECS as f64)
+    }
+
+    fn standing(&self, now: Instant) -> Standing {
+        if self.banned_until.is_some_and(|until| now < until) {
+            return Standing::Banned;
+        }
+        if self.decayed(now) >= REPUTATION_DEPRIORITIZE_AT {
+            Standing::Deprioritized
+        } else {
+            Standing::Good
+        }
+    }
+}
+
+/// Decaying score per NodeId. See the module docs.
+#[derive(Debug, Default)]
+pub(crate) struct Reputation {
+    peers: Mutex<HashMap<NodeId, Score>>,
+}
+
+impl Reputation {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charge `offence` to `peer` and return its standing afterwards.
+    /// The return is [`Standing::Banned`] on the call that imposes the ban
+    /// and on every call during it.
+    pub(crate) fn penalize(&self, peer: &NodeId, offence: Offence) -> Standing {
+        self.penalize_at(peer, offence, Instant::now())
+    }
+
+    fn penalize_at(&self, peer: &NodeId, offence: Offence, now: Instant) -> Standing {
+        let mut peers = self.peers.lock();
+        if !peers.contains_key(peer) && peers.len() >= MAX_REPUTATION_ENTRIES {
+            evict_one(&mut peers, now);
+        }
+        let score =
+            peers.entry(*peer).or_insert(Score { points: 0.0, at: now, banned_until: None });
+        score.points = score.decayed(now) + offence.weight();
+        score.at = now;
+        let banning = score.points >= REPUTATION_BAN_AT;
+        if banning && score.banned_until.is_none_or(|until| until <= now) {
+            score.banned_until = Some(now + Duration::from_secs(REPUTATION_BAN_SECS));
+        }
+        score.standing(now)
+    }
+
+    pub(crate) fn standing(&self, peer: &NodeId) -> Standing {
+        self.standing_at(peer, Instant::now())
+    }
+
+    fn standing_at(&self, peer: &NodeId, now: Instant) -> Standing {
+        self.peers.lock().get(peer).map_or(Standing::Good, |s| s.standing(now))
+    }
+
+    pub(crate) fn is_banned(&self, peer: &NodeId) -> bool {
+        self.standing(peer) == Standing::Banned
+    }
+}
+
+/// Make room for one entry: forget anyone whose score has decayed to
+/// nothing and who is not banned; failing that, the lowest unbanned score;
+/// failing that (everyone banned), the ban closest to lapsing.
+fn evict_one(peers: &mut HashMap<NodeId, Score>, now: Instant) {
+    peers.retain(|_, s| s.banned_until.is_some_and(|u| now < u) || s.decayed(now) >= 1.0);
+    if peers.len() < MAX_REPUTATION_ENTRIES {
+        return;
+    }
+    let victim = peers
+        .iter()
+        .filter(|(_, s)| s.standing(now) != Standing::Banned)
+        .min_by(|a, b| a.1.decayed(now).total_cmp(&b.1.decayed(now)))
+        .or_else(|| peers.iter().min_by_key(|(_, s)| s.banned_until))
+        .map(|(id, _)| *id);
+    if let Some(id) = victim {
+        peers.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(seed: u8) -> NodeId {
+        NodeId::from_bytes([seed; 32])
+    }
+
+    #[test]
+    fn scores_decay_by_half_each_half_life() {
+        let rep = Reputation::new();
+        let t0 = Instant::now();
+        let p = peer(1);
+
+        assert_eq!(rep.penalize_at(&p, Offence::BadSignature, t0), Standing::Deprioritized);
+        let later = t0 + Duration::from_secs(REPUTATION_HALF_LIFE_SECS);
+        // PENALTY_BAD_SIGNATURE halved sits below the deprioritize line.
+        assert_eq!(rep.standing_at(&p, later), Standing::Good);
+        assert_eq!(rep.standing_at(&peer(2), t0), Standing::Good);
+    }
+
+    #[test]
+    fn repeated_floods_ban_and_the_ban_lapses() {
+        let rep = Reputation::new();
+        let t0 = Instant::now();
+        let p = peer(3);
+
+        let mut standing = Standing::Good;
+        let mut floods = 0;
+        while standing != Standing::Banned {
+            standing = rep.penalize_at(&p, Offence::Flood, t0);
+            floods += 1;
+            assert!(floods < 10, "flooding must eventually ban");
+        }
+        assert_eq!(rep.standing_at(&p, t0 + Duration::from_secs(60)), Standing::Banned);
+
+        // Decay alone cannot lift a ban early, but its expiry does.
+        let after = t0 + Duration::from_secs(REPUTATION_BAN_SECS + 1);
+        assert_ne!(rep.standing_at(&p, after), Standing::Banned);
+    }
+
+    #[test]
+    fn clock_drift_alone_does_not_ban_quickly() {
+        let rep = Reputation::new();
+        let t0 = Instant::now();
+        let p = peer(4);
+
+        for i in 0..10 {
+            let now = t0 + Duration::from_secs(i * 60);
+            assert_ne!(rep.penalize_at(&p, Offence::StaleTimestamp, now), Standing::Banned);
+        }
+    }
+
+    #[test]
+    fn a_full_table_forgets_a_quiet_peer_not_a_banned_one() {
+        let rep = Reputation::new();
+        let t0 = Instant::now();
+        let banned = peer(5);
+        while rep.penalize_at(&banned, Offence::Flood, t0) != Standing::Banned {}
+
+        {
+            let mut peers = rep.peers.lock();
+            for i in 0..MAX_REPUTATION_ENTRIES as u32 {
+                let mut id = [0u8; 32];
+                id[..4].copy_from_slice(&i.to_be_bytes());
+                id[31] = 0xFF;
+                peers.insert(NodeId::from_bytes(id), Score {
+                    points: 5.0,
+                    at: t0,
+                    banned_until: None,
+                });
+                if peers.len() >= MAX_REPUTATION_ENTRIES {
+                    break;
+                }
+            }
+        }
+
+        rep.penalize_at(&peer(6), Offence::Malformed, t0);
+        let peers = rep.peers.lock();
+        assert!(peers.len() <= MAX_REPUTATION_ENTRIES);
+        assert!(peers.contains_key(&banned));
+        assert!(peers.contains_key(&peer(6)));
+    }
+}
@@ -21,7 +21,11 @@
 //! counter — keeps the routing table free of side-channel dependencies
 //! and trivially unit-testable.
 
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
 use std::net::SocketAddr;
+use std::time::Duration;
 use std::time::Instant;
 
 use common::proto::dht_p2p::NodeDescriptor;
@@ -33,6 +37,11 @@ use super::Dht;
 use super::config::BUCKETS;
 use super::config::BUCKET_SIZE;
 use super::config::K;
+use super::config::MAX_PEERS_PER_PREFIX;
+use super::config::MAX_PEERS_PER_PREFIX_PER_BUCKET;
+use super::config::PEER_PREFIX_V4_BITS;
+use super::config::PEER_PREFIX_V6_BITS;
+use super::config::REPUTATION_HALF_LIFE_SECS;
 
 // ---------------------------------------------------------------------------
 // Module-private constants
@@ -106,6 +115,12 @@ pub struct RoutingEntry {
     /// table never *owns* the connection — the strong reference lives on
     /// `Dht::peer_conns`. Lookup hops `upgrade()` opportunistically.
     pub conn: Option<std::sync::Weak<Connection>>,
+
+    /// Set by [`RoutingTable::demote`] when the peer's reputation drops to
+    /// `Deprioritized`. While in the future the entry is left out of the
+    /// descriptors we advertise and is the first to go when its bucket
+    /// fills; our own lookups may still use it.
+    pub demoted_until: Option<Instant>,
 }
 
 impl RoutingEntry {
@@ -130,15 +145,20 @@ impl RoutingEntry {
     /// completes.
     pub(crate) fn from_descriptor(desc: &NodeDescriptor) -> Self {
         Self {
-            id:           desc.id,
-            addr:         desc.addr,
-            pubkey:       desc.pubkey.0,
-            last_seen:    Instant::now(),
-            rtt_ema_ms:   None,
-            failed_pings: 0,
-            conn:         None,
+            id:            desc.id,
+            addr:          desc.addr,
+            pubkey:        desc.pubkey.0,
+            last_seen:     Instant::now(),
+            rtt_ema_ms:    None,
+            failed_pings:  0,
+            conn:          None,
+            demoted_until: None,
         }
     }
+
+    fn is_demoted(&self, now: Instant) -> bool {
+        self.demoted_until.is_some_and(|until| now < until)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -224,6 +244,12 @@ pub enum InsertOutcome {
     /// would otherwise displace good replacements with junk on every
     /// inbound packet).
     Discarded,
+
+    /// Peer was previously unknown and its address prefix already holds
+    /// [`MAX_PEERS_PER_PREFIX_PER_BUCKET`] peers in the bucket or
+    /// [`MAX_PEERS_PER_PREFIX`] across the table. Dropped: many NodeIds
+    /// behind one prefix is what an eclipse attempt looks like.
+    PrefixLimited,
 }
 
 // ---------------------------------------------------------------------------
@@ -332,13 +358,31 @@ impl RoutingTable {
             return InsertOutcome::Refreshed;
         }
 
+        // Step 1b — a newcomer must not push its address prefix past the
+        // per-bucket or table-wide limit. Counted before the bucket borrow
+        // below so the table-wide scan can read every bucket.
+        if self.prefix_full(bucket_idx, &descriptor.addr) {
+            return InsertOutcome::PrefixLimited;
+        }
+        let bucket = &mut self.buckets[bucket_idx];
+
         // Step 2 — spare capacity in the active set? Append.
         if bucket.entries.len() < BUCKET_SIZE {
             bucket.entries.push(RoutingEntry::from_descriptor(&descriptor));
             return InsertOutcome::Inserted;
         }
 
-        // Step 3 — bucket full. The LRU entry is the eviction candidate;
+        // Step 3 — bucket full. A demoted entry gives up its slot to the
+        // newcomer outright: no PING, since liveness isn't what's wrong
+        // with it.
+        if let Some(pos) = bucket.entries.iter().position(|e| e.is_demoted(now)) {
+            bucket.entries.remove(pos);
+            bucket.candidates.retain(|c| c.id != descriptor.id);
+            bucket.entries.push(RoutingEntry::from_descriptor(&descriptor));
+            return InsertOutcome::Inserted;
+        }
+
+        // Otherwise the LRU entry is the eviction candidate;
         // park the newcomer in `candidates` and ask the caller to PING
         // the LRU.
         if bucket.candidates.len() < BUCKET_SIZE {
@@ -356,6 +400,41 @@ impl RoutingTable {
         InsertOutcome::Discarded
     }
 
+    /// Whether [`Self::insert`] would take `descriptor` into the active set
+    /// or the replacement cache. Read-only, so callers can skip network work
+    /// for a peer the table would discard anyway.
+    pub(crate) fn has_room_for(&self, descriptor: &NodeDescriptor) -> bool {
+        let Some(bucket_idx) = bucket_for(&self.self_id, &descriptor.id) else {
+            return false;
+        };
+        let bucket = &self.buckets[bucket_idx];
+        if bucket.entries.iter().any(|e| e.id == descriptor.id) {
+            return true;
+        }
+        if self.prefix_full(bucket_idx, &descriptor.addr) {
+            return false;
+        }
+        let now = Instant::now();
+        bucket.entries.len() < BUCKET_SIZE
+            || bucket.entries.iter().any(|e| e.is_demoted(now))
+            || (bucket.candidates.len() < BUCKET_SIZE
+                && !bucket.candidates.iter().any(|c| c.id == descriptor.id))
+    }
+
+    /// True iff one more peer at `addr` would push its address prefix past
+    /// the per-bucket or table-wide limit.
+    fn prefix_full(&self, bucket_idx: usize, addr: &SocketAddr) -> bool {
+        let Some(prefix) = addr_prefix(addr) else {
+            return false;
+        };
+        let same = |e: &RoutingEntry| addr_prefix(&e.addr) == Some(prefix);
+        let bucket = &self.buckets[bucket_idx];
+        let in_bucket = bucket.entries.iter().chain(&bucket.candidates).filter(|e| same(e));
+        let in_table = self.buckets.iter().flat_map(|b| &b.entries).filter(|e| same(e));
+        in_bucket.count() >= MAX_PEERS_PER_PREFIX_PER_BUCKET
+            || in_table.count() >= MAX_PEERS_PER_PREFIX
+    }
+
     /// Record a failed `PING` against `peer_id`. Increments the entry's
     /// `failed_pings` counter and, on reaching
     /// [`PING_FAILURES_BEFORE_EVICTION`], evicts the entry and promotes
@@ -394,6 +473,51 @@ impl RoutingTable {
         }
     }
 
+    /// Whether `peer_id` holds an active slot.
+    pub(crate) fn contains(&self, peer_id: &NodeId) -> bool {
+        bucket_for(&self.self_id, peer_id)
+            .is_some_and(|i| self.buckets[i].entries.iter().any(|e| e.id == *peer_id))
+    }
+
+    /// Mark `peer_id` deprioritized for one reputation half-life and move
+    /// it to the head of its bucket's LRU, so it is also the first entry
+    /// the next PING-for-eviction round probes. No-op for unknown peers.
+    pub(crate) fn demote(&mut self, peer_id: &NodeId) {
+        let Some(bucket_idx) = bucket_for(&self.self_id, peer_id) else {
+            return;
+        };
+        let bucket = &mut self.buckets[bucket_idx];
+        let Some(pos) = bucket.entries.iter().position(|e| e.id == *peer_id) else {
+            return;
+        };
+        let mut entry = bucket.entries.remove(pos);
+        entry.demoted_until = Some(Instant::now() + Duration::from_secs(REPUTATION_HALF_LIFE_SECS));
+        bucket.entries.insert(0, entry);
+    }
+
+    /// Drop `peer_id` from its bucket (active set or candidates) outright,
+    /// promoting the head of `candidates` into a freed active slot. Used
+    /// for banned peers. Returns whether the peer was known.
+    pub(crate) fn remove(&mut self, peer_id: &NodeId) -> bool {
+        let Some(bucket_idx) = bucket_for(&self.self_id, peer_id) else {
+            return false;
+        };
+        let bucket = &mut self.buckets[bucket_idx];
+        let parked = bucket.candidates.len();
+        bucket.candidates.retain(|c| c.id != *peer_id);
+        let Some(pos) = bucket.entries.iter().position(|e| e.id == *peer_id) else {
+            return bucket.candidates.len() != parked;
+        };
+        bucket.entries.remove(pos);
+        if !bucket.candidates.is_empty() {
+            let mut promoted = bucket.candidates.remove(0);
+            promoted.last_seen = Instant::now();
+            promoted.failed_pings = 0;
+            bucket.entries.push(promoted);
+        }
+        true
+    }
+
     /// Record a successful `PING` against `peer_id`. Resets the failure
     /// counter, refreshes `last_seen`, and folds `rtt_ms` into the EMA.
     ///
@@ -455,6 +579,24 @@ impl RoutingTable {
     /// `BinaryHeap`-of-k would shave a constant factor but is harder to
     /// reason about, and not worth it at this size.
     pub(crate) fn closest(&self, target: &NodeId, count: usize) -> Vec<RoutingEntry> {
+        self.closest_where(target, count, |_| true)
+    }
+
+    /// [`Self::find_closest`] minus currently-demoted peers: what we are
+    /// willing to hand other relays in a `FindNodeResp`.
+    pub(crate) fn find_closest_advertisable(
+        &self, target: &NodeId, count: usize,
+    ) -> Vec<NodeDescriptor> {
+        let now = Instant::now();
+        self.closest_where(target, count, |e| !e.is_demoted(now))
+            .into_iter()
+            .map(|e| e.descriptor())
+            .collect()
+    }
+
+    fn closest_where(
+        &self, target: &NodeId, count: usize, keep: impl Fn(&RoutingEntry) -> bool,
+    ) -> Vec<RoutingEntry> {
         if count == 0 {
             return Vec::new();
         }
@@ -463,7 +605,7 @@ impl RoutingTable {
         // Single pass: collect (distance, &entry) pairs.
         let mut scratch: Vec<([u8; 32], &RoutingEntry)> = Vec::new();
         for bucket in self.buckets.iter() {
-            for entry in &bucket.entries {
+            for entry in bucket.entries.iter().filter(|e| keep(e)) {
                 let dist = xor_bytes(entry.id.as_bytes(), target_bytes);
                 scratch.push((dist, entry));
             }
@@ -594,6 +736,30 @@ pub(crate) fn random_id_in_bucket(self_id: &NodeId, bucket_idx: usize) -> Option
     Some(NodeId::from_bytes(out))
 }
 
+/// The address prefix `addr` counts against for the per-prefix insertion
+/// limits: the /[`PEER_PREFIX_V4_BITS`] or /[`PEER_PREFIX_V6_BITS`] network,
+/// with v4-mapped v6 folded to v4. `None` for loopback and private ranges,
+/// which are only dialable in single-host test clusters
+/// (`allow_local_peer_addrs`) where every peer shares one prefix.
+fn addr_prefix(addr: &SocketAddr) -> Option<IpAddr> {
+    match addr.ip().to_canonical() {
+        IpAddr::V4(v4) => {
+            if v4.is_loopback() || v4.is_private() {
+                return None;
+            }
+            let mask = u32::MAX << (32 - PEER_PREFIX_V4_BITS);
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask)))
+        },
+        IpAddr::V6(v6) => {
+            if v6.is_loopback() || v6.segments()[0] & 0xfe00 == 0xfc00 {
+                return None;
+            }
+            let mask = u128::MAX << (128 - PEER_PREFIX_V6_BITS);
+            Some(IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask)))
+        },
+    }
+}
+
 /// Byte-wise XOR of two 32-byte ids. Used both by the bucket-index
 /// computation and the `find_closest` distance metric.
 #[inline]
@@ -985,6 +1151,133 @@ mod tests {
         assert!(bucket.candidates.is_empty());
     }
 
+    // -------- per-prefix admission -------------------------------------
+
+    fn public_desc(id: NodeId, ip: &str) -> NodeDescriptor {
+        NodeDescriptor { id, addr: format!("{ip}:4433").parse().unwrap(), pubkey: [0u8; 32].into() }
+    }
+
+    #[test]
+    fn one_prefix_cannot_crowd_a_bucket() {
+        let mut t = fresh_table(0);
+        let mut cursor = 0u32;
+        let mut ids = Vec::new();
+        while ids.len() < 4 {
+            let id = id_in_bucket(&t.self_id, 255, &mut cursor);
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        assert_eq!(t.insert(public_desc(ids[0], "203.0.113.7")), InsertOutcome::Inserted);
+        assert_eq!(t.insert(public_desc(ids[1], "203.0.113.8")), InsertOutcome::Inserted);
+        assert_eq!(t.insert(public_desc(ids[2], "203.0.113.9")), InsertOutcome::PrefixLimited);
+        // A neighbouring /24 is a different operator.
+        assert_eq!(t.insert(public_desc(ids[2], "203.0.114.9")), InsertOutcome::Inserted);
+        // Known peers refresh regardless.
+        assert_eq!(t.insert(public_desc(ids[0], "203.0.113.7")), InsertOutcome::Refreshed);
+        // The v4-mapped form counts against the same /24.
+        let mapped = public_desc(ids[3], "[::ffff:203.0.113.10]");
+        assert_eq!(t.insert(mapped), InsertOutcome::PrefixLimited);
+    }
+
+    #[test]
+    fn one_prefix_is_capped_across_the_table() {
+        let mut t = fresh_table(0);
+        let mut cursor = 0u32;
+        for (n, bucket) in (BUCKETS - MAX_PEERS_PER_PREFIX..BUCKETS).enumerate() {
+            let id = id_in_bucket(&t.self_id, bucket, &mut cursor);
+            let ip = format!("2001:db8:1:{n:x}::1");
+            assert_eq!(t.insert(public_desc(id, &format!("[{ip}]"))), InsertOutcome::Inserted);
+        }
+        let id = id_in_bucket(&t.self_id, BUCKETS - MAX_PEERS_PER_PREFIX - 1, &mut cursor);
+        assert_eq!(t.insert(public_desc(id, "[2001:db8:1:ff::1]")), InsertOutcome::PrefixLimited);
+        assert_eq!(t.insert(public_desc(id, "[2001:db8:2::1]")), InsertOutcome::Inserted);
+    }
+
+    #[test]
+    fn room_check_agrees_with_insert() {
+        let mut t = fresh_table(0);
+        let mut cursor = 0u32;
+        assert!(!t.has_room_for(&desc(t.self_id)));
+
+        let active = fill_bucket(&mut t, 255, &mut cursor);
+        let parked = loop {
+            let id = id_in_bucket(&t.self_id, 255, &mut cursor);
+            if !active.contains(&id) {
+                break id;
+            }
+        };
+        assert!(t.has_room_for(&desc(parked)));
+        assert!(matches!(t.insert(desc(parked)), InsertOutcome::PendingPing(_)));
+        // Parked already: a second insert is discarded, so no dial is worth it.
+        assert!(!t.has_room_for(&desc(parked)));
+        assert_eq!(t.insert(desc(parked)), InsertOutcome::Discarded);
+        // Known peers always have room: insert refreshes them.
+        assert!(t.has_room_for(&desc(active[0])));
+
+        let mut p = fresh_table(0);
+        let mut cursor = 0u32;
+        let a = id_in_bucket(&p.self_id, 255, &mut cursor);
+        let b = id_in_bucket(&p.self_id, 255, &mut cursor);
+        let c = id_in_bucket(&p.self_id, 255, &mut cursor);
+        p.insert(public_desc(a, "203.0.113.7"));
+        p.insert(public_desc(b, "203.0.113.8"));
+        assert!(!p.has_room_for(&public_desc(c, "203.0.113.9")));
+        assert!(p.has_room_for(&public_desc(c, "203.0.114.9")));
+    }
+
+    // -------- reputation hooks -----------------------------------------
+
+    #[test]
+    fn demoted_peer_is_not_advertised_and_yields_its_slot() {
+        let mut t = fresh_table(0);
+        let mut cursor = 0u32;
+        let target_bucket = 255;
+        let active = fill_bucket(&mut t, target_bucket, &mut cursor);
+        let bad = active[BUCKET_SIZE - 1];
+
+        t.demote(&bad);
+        assert_eq!(t.buckets[target_bucket].entries[0].id, bad);
+        let advertised = t.find_closest_advertisable(&bad, BUCKET_SIZE);
+        assert!(advertised.iter().all(|d| d.id != bad));
+        // Our own lookups still see it.
+        assert_eq!(t.find_closest(&bad, 1)[0].id, bad);
+
+        let newcomer = loop {
+            let id = id_in_bucket(&t.self_id, target_bucket, &mut cursor);
+            if !active.contains(&id) {
+                break id;
+            }
+        };
+        assert_eq!(t.insert(desc(newcomer)), InsertOutcome::Inserted);
+        let bucket = &t.buckets[target_bucket];
+        assert!(bucket.entries.iter().any(|e| e.id == newcomer));
+        assert!(!bucket.entries.iter().any(|e| e.id == bad));
+    }
+
+    #[test]
+    fn remove_drops_the_peer_and_promotes_a_candidate() {
+        let mut t = fresh_table(0);
+        let mut cursor = 0u32;
+        let target_bucket = 255;
+        let active = fill_bucket(&mut t, target_bucket, &mut cursor);
+        let candidate_id = loop {
+            let id = id_in_bucket(&t.self_id, target_bucket, &mut cursor);
+            if !active.contains(&id) {
+                break id;
+            }
+        };
+        assert!(matches!(t.insert(desc(candidate_id)), InsertOutcome::PendingPing(_)));
+
+        assert!(t.remove(&active[3]));
+        assert!(!t.remove(&active[3]));
+        let bucket = &t.buckets[target_bucket];
+        assert_eq!(bucket.entries.len(), BUCKET_SIZE);
+        assert!(bucket.entries.iter().any(|e| e.id == candidate_id));
+        assert!(bucket.candidates.is_empty());
+    }
+
     #[test]
     fn ping_succeeded_resets_failure_counter() {
         let mut t = fresh_table(0);
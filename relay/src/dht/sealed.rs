@@ -0,0 +1,246 @@
+//! Sealed-sender admission.
+//!
+//! A sealed `DispatchP` names no sender: `from` is a one-shot key and the
+//! real identity travels encrypted to the recipient. What authorizes it
+//! instead is a [`DeliveryToken`] the recipient issued to one contact, and
+//! the per-sender rules that key on `from` elsewhere key on that token here —
+//! [`DispatchP::quota_key`] for queue shares, [`SealedLimiter`] for rate.
+//!
+//! Checked at the ingress relay before fan-out and again at each home before
+//! it queues, so a home never trusts another relay's word for a token. Only a
+//! home also knows which tokens the recipient revoked: the owner-signed
+//! [`TokenRevocation`] is replicated to the homes like a wake policy.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::proto::client_rel::DispatchP;
+use common::proto::client_rel::MAX_DELIVERY_TOKEN_LIFETIME_MS;
+use common::proto::dht_p2p::DhtPacket;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::MAX_DHT_HELLO_SKEW_MS;
+use common::proto::dht_p2p::NodeDescriptor;
+use common::proto::dht_p2p::TokenRevocation;
+use common::proto::dht_p2p::TokenRevocationResp;
+use common::proto::dht_p2p::token_revocation_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use ed25519_dalek::Signature;
+use ed25519_dalek::VerifyingKey;
+use governor::RateLimiter;
+use governor::clock::DefaultClock;
+use governor::state::keyed::DefaultKeyedStateStore;
+use tokio::time::timeout;
+
+use super::config::FORWARD_TIMEOUT_MS;
+use super::config::K;
+use crate::dht::Dht;
+use crate::dht::mls::kp::hourly_quota;
+
+/// Sealed dispatches one token may place per hour, per relay. A token is one
+/// contact's, so this is the sealed counterpart of a per-sender limit; it is
+/// generous because ordinary chat traffic, receipts included, runs on it.
+pub(crate) const MAX_SEALED_PER_TOKEN_PER_HOUR: u32 = 1_200;
+
+type TokenLimiter = RateLimiter<[u8; 32], DefaultKeyedStateStore<[u8; 32]>, DefaultClock>;
+
+/// Per-token rate limiter. One instance lives on [`Dht::sealed_limiter`];
+/// idle keys are evicted by `governor`, so expired tokens age out on their own.
+#[derive(Debug)]
+pub(crate) struct SealedLimiter(TokenLimiter);
+
+impl SealedLimiter {
+    pub(crate) fn new() -> Self {
+        Self(RateLimiter::keyed(hourly_quota(MAX_SEALED_PER_TOKEN_PER_HOUR)))
+    }
+
+    fn check(&self, key: &[u8; 32]) -> bool {
+        self.0.check_key(key).is_ok()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SealedAdmission {
+    /// Identified dispatch, or a sealed one with a live token under quota.
+    Accept,
+    /// Token expired, too long-lived, not issued by `to`, or revoked.
+    BadToken,
+    /// Token valid but over [`MAX_SEALED_PER_TOKEN_PER_HOUR`].
+    OverQuota,
+}
+
+/// Admit `dispatch` on its token. Identified dispatches pass straight through:
+/// their authorization is the sender signature the caller already checks.
+pub(crate) fn admit(dht: &Arc<Dht>, dispatch: &DispatchP, now_ms: u64) -> SealedAdmission {
+    let Some(token) = &dispatch.token else {
+        return SealedAdmission::Accept;
+    };
+    if !token.verify(&dispatch.to.0, now_ms)
+        || dht.store.is_token_revoked(&dispatch.to.0, &token.id.0)
+    {
+        return SealedAdmission::BadToken;
+    }
+    if !dht.sealed_limiter.check(&token.quota_key()) {
+        return SealedAdmission::OverQuota;
+    }
+    SealedAdmission::Accept
+}
+
+/// Store locally if this relay is a home of the revoking key, then send the
+/// record to every other home. Best-effort like
+/// [`super::wake_policy::replicate_to_homes`]: the owner re-sends it.
+pub(crate) async fn replicate_revocation(dht: Arc<Dht>, revocation: TokenRevocation) {
+    let target = NodeId::from_bytes(revocation.user_ipk.0);
+    if super::routing::self_in_top_k(&dht, &target) {
+        let _ = dht.store.put_token_revocation(&revocation);
+    }
+    let homes = dht.routing.read().find_closest(&target, K);
+    let mut set = tokio::task::JoinSet::new();
+    for home in homes {
+        let dht = dht.clone();
+        let revocation = revocation.clone();
+        set.spawn(async move {
+            let _ = timeout(
+                Duration::from_millis(FORWARD_TIMEOUT_MS),
+                revoke_one(dht, home, revocation),
+            )
+            .await;
+        });
+    }
+    while set.join_next().await.is_some() {}
+}
+
+async fn revoke_one(dht: Arc<Dht>, home: NodeDescriptor, revocation: TokenRevocation) -> bool {
+    let Ok(conn) = super::lookup::connect_to_peer(&dht, &home).await else { return false };
+    let Ok(bytes) = DhtPacket::Request(DhtRequest::TokenRevocation(revocation)).pack() else {
+        return false;
+    };
+    let Ok((mut tx, mut rx)) = conn.open_bi().await else { return false };
+    if tx.write_all(&bytes).await.is_err() || tx.finish().is_err() {
+        return false;
+    }
+    matches!(
+        DhtPacket::unpack(&mut rx).await,
+        Ok(DhtPacket::Response(DhtResponse::TokenRevocation(TokenRevocationResp {
+            accepted: true
+        })))
+    )
+}
+
+/// Validate the owner signature and freshness, require that we are a home of
+/// the revoking key, then keep the list if it is newer than the one stored.
+pub(crate) fn handle_revocation(
+    dht: &Dht, revocation: TokenRevocation, now_ms: u64,
+) -> TokenRevocationResp {
+    if !valid_revocation(&revocation, now_ms)
+        || !super::routing::self_in_top_k(dht, &NodeId::from_bytes(revocation.user_ipk.0))
+    {
+        return TokenRevocationResp { accepted: false };
+    }
+    TokenRevocationResp { accepted: dht.store.put_token_revocation(&revocation).unwrap_or(false) }
+}
+
+/// Fresh, signed by the key it names, and kept no longer than a token it
+/// lists could live.
+pub(crate) fn valid_revocation(revocation: &TokenRevocation, now_ms: u64) -> bool {
+    if now_ms.abs_diff(revocation.timestamp) > MAX_DHT_HELLO_SKEW_MS
+        || revocation.expires_at_ms.saturating_sub(now_ms) > MAX_DELIVERY_TOKEN_LIFETIME_MS
+    {
+        return false;
+    }
+    let Ok(key) = VerifyingKey::from_bytes(&revocation.user_ipk.0) else {
+        return false;
+    };
+    let input = token_revocation_signing_input(
+        &revocation.user_ipk.0,
+        &revocation.ids,
+        revocation.expires_at_ms,
+        revocation.timestamp,
+    );
+    key.verify_strict(&input, &Signature::from_bytes(&revocation.user_sig.0)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::client_rel::DeliveryToken;
+    use common::types::bytes::ByteVec;
+    use common::types::bytes::Bytes;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+    use crate::dht::DhtConfig;
+
+    fn fresh_dht(self_id: NodeId) -> Arc<Dht> {
+        let path = std::env::temp_dir()
+            .join(format!("promtuz-sealed-test-{}-{}", std::process::id(), rand::random::<u64>()));
+        let store = Arc::new(crate::storage::db::Store::open(&path).expect("open store"));
+        let signing = SigningKey::from_bytes(&rand::random());
+        Arc::new(Dht::new(self_id, signing, DhtConfig::default(), store).expect("dht"))
+    }
+
+    fn revocation(key: &SigningKey, ids: Vec<Bytes<16>>, now: u64) -> TokenRevocation {
+        let user = key.verifying_key().to_bytes();
+        let expires_at_ms = now + 60_000;
+        let sig = key.sign(&token_revocation_signing_input(&user, &ids, expires_at_ms, now));
+        TokenRevocation {
+            user_ipk: Bytes(user),
+            ids,
+            expires_at_ms,
+            timestamp: now,
+            user_sig: Bytes(sig.to_bytes()),
+        }
+    }
+
+    #[test]
+    fn only_the_owner_can_revoke_and_only_for_a_token_lifetime() {
+        let owner = SigningKey::from_bytes(&[0x61; 32]);
+        let now = 1_700_000_000_000;
+        let signed = revocation(&owner, vec![Bytes([1; 16])], now);
+        assert!(valid_revocation(&signed, now));
+        assert!(!valid_revocation(&signed, now + MAX_DHT_HELLO_SKEW_MS + 1));
+
+        let mut widened = signed.clone();
+        widened.ids.push(Bytes([2; 16]));
+        assert!(!valid_revocation(&widened, now), "the list is signed whole");
+
+        let mut forever = signed;
+        forever.expires_at_ms = now + MAX_DELIVERY_TOKEN_LIFETIME_MS + 1;
+        assert!(!valid_revocation(&forever, now));
+    }
+
+    /// A revoked token is refused at a home exactly like a forged one, and a
+    /// newer list replaces the older.
+    #[tokio::test(flavor = "current_thread")]
+    async fn a_home_refuses_a_revoked_token() {
+        let dht = fresh_dht(NodeId::new([0x62; 32]));
+        let owner = SigningKey::from_bytes(&[0x63; 32]);
+        let to = owner.verifying_key().to_bytes();
+        let now = crate::util::systime().as_millis() as u64;
+        let token = DeliveryToken::issue(&owner, [0x64; 16], now + 3_600_000);
+        let dispatch = DispatchP {
+            to:             Bytes(to),
+            from:           Bytes([0x65; 32]),
+            id:             Bytes([0x66; 16]),
+            payload:        ByteVec(vec![1]),
+            sig:            Bytes([0; 64]),
+            accepted_at_ms: now,
+            wake:           false,
+            wake_hint:      None,
+            token:          Some(token),
+        };
+        assert_eq!(admit(&dht, &dispatch, now), SealedAdmission::Accept);
+
+        assert!(dht.store.put_token_revocation(&revocation(&owner, vec![Bytes([0x64; 16])], now))
+            .unwrap());
+        assert_eq!(admit(&dht, &dispatch, now), SealedAdmission::BadToken);
+
+        let older = revocation(&owner, Vec::new(), now - 1);
+        assert!(!dht.store.put_token_revocation(&older).unwrap(), "an older list cannot undo it");
+        let newer = revocation(&owner, Vec::new(), now + 1);
+        assert!(dht.store.put_token_revocation(&newer).unwrap());
+        assert_eq!(admit(&dht, &dispatch, now), SealedAdmission::Accept);
+    }
+}
@@ -8,18 +8,28 @@
 //!
 //! Values are postcard-encoded [`DispatchP`] keyed by the 56-byte
 //! [`MessageKey`] (`recipient(32) || ts_be(8) || dispatch_id(16)`), so a
-//! 32-byte prefix scan groups a recipient's queue oldest-first.
+//! 32-byte prefix scan groups a recipient's queue oldest-first. Rows written
+//! before protocol 7 hold the [`DispatchV6P`] layout, so every read goes
+//! through [`DispatchP::deser_stored`].
+//!
+//! [`DispatchV6P`]: common::proto::client_rel::DispatchV6P
+//!
+//! `recipient` is the dispatch's `to`. That is the IPK, or for sealed mail one
+//! of the recipient's rotating mailbox ids, which a new id replaces every
+//! `MAILBOX_EPOCH_MS`. Rows under a mailbox id name no IPK. Each epoch's mail
+//! also sits under a different prefix at a different XOR distance, so a home
+//! cannot follow one recipient's queue across epochs.
 
 use common::proto::client_rel::DispatchP;
 use common::proto::dht_p2p::ForwardOutcome;
 use common::proto::pack::MAX_FRAME_BYTES;
 use common::proto::pack::Packer;
-use common::proto::pack::Unpacker;
 use common::quic::id::NodeId;
 use common::quic::xor32;
 
 use super::Dht;
 use super::config::K;
+use super::config::QueueQuota;
 use crate::storage::MAX_QUEUED_PER_RECIPIENT;
 use crate::storage::MessageKey;
 
@@ -28,10 +38,6 @@ use crate::storage::MessageKey;
 /// pages normally.
 const MAX_QUEUED_PER_SENDER: usize = 128;
 
-/// Ceiling on queued bytes deserialized to attribute rows to their sender
-/// during one admission scan.
-const SENDER_SCAN_BYTE_BUDGET: usize = 8 * 1024 * 1024;
-
 pub(crate) enum QueueAdmission {
     Insert,
     /// This exact `(dispatch_id, sender)` is already queued — a retransmit.
@@ -39,24 +45,34 @@ pub(crate) enum QueueAdmission {
     /// A different sender holds this `dispatch_id` for this recipient.
     IdTakenByOther,
     Full,
+    /// Under the row caps, but the new row would take the recipient or this
+    /// sender past its [`QueueQuota`] bytes.
+    OverQuota,
     ScanFailed,
 }
 
-/// Admission scan for a queue of [`MessageKey`]-shaped keys: per-recipient cap,
-/// per-sender quota, and duplicate-id rejection.
+/// Admission scan for a queue of [`MessageKey`]-shaped keys: per-recipient
+/// row cap and byte quota, per-sender row and byte share, and duplicate-id
+/// rejection. `len` is the encoded size of the row about to be written.
 ///
 /// `sender_of` decodes a row's sender because the home queue stores `DispatchP`
-/// and the local fallback queue stores `DeliverP`. Attribution is byte-budgeted
-/// — past [`SENDER_SCAN_BYTE_BUDGET`] only the per-recipient cap binds, so a
-/// queue of MiB-scale rows is never read whole on an inbound dispatch.
+/// and the local fallback queue stores `DeliverP`. The duplicate-id check runs
+/// over the whole prefix so a retransmit from a sender at its limits is still
+/// told `AlreadyQueued`, but rows stop being decoded once the recipient or
+/// this sender hits a cap or quota — a queue of MiB-scale rows is never
+/// decoded whole on an inbound dispatch.
 pub(crate) fn admit_to_queue(
     ks: &fjall::Keyspace, recipient: &[u8; 32], dispatch_id: &[u8; 16], sender: &[u8; 32],
-    sender_of: impl Fn(&[u8]) -> Option<[u8; 32]>,
+    len: usize, quota: QueueQuota, sender_of: impl Fn(&[u8]) -> Option<[u8; 32]>,
 ) -> QueueAdmission {
+    let len = len as u64;
     let mut total: usize = 0;
     let mut from_sender: usize = 0;
-    let mut attributed_bytes: usize = 0;
-    let stop_at = MAX_QUEUED_PER_RECIPIENT.saturating_add(1);
+    let mut total_bytes: u64 = 0;
+    let mut sender_bytes: u64 = 0;
+    // Set once the outcome is `Full` or `OverQuota` unless a duplicate turns
+    // up; from then on rows are only matched by id.
+    let mut settled = false;
     for guard in ks.prefix(recipient) {
         // Treat a corrupted iterator as "we can't be sure we're under the
         // cap" — better to reject than silently overrun.
@@ -66,32 +82,35 @@ pub(crate) fn admit_to_queue(
         if key_bytes.len() != MessageKey::SIZE {
             continue;
         }
-        let attributable = attributed_bytes < SENDER_SCAN_BYTE_BUDGET;
-        let same_sender = attributable && sender_of(&value).is_some_and(|f| f == *sender);
-        if attributable {
-            attributed_bytes += value.len();
-        }
         if key_bytes[40..56] == *dispatch_id {
-            return if same_sender {
+            return if sender_of(&value).is_some_and(|f| f == *sender) {
                 QueueAdmission::AlreadyQueued
             } else {
                 QueueAdmission::IdTakenByOther
             };
         }
+        if settled {
+            continue;
+        }
         total += 1;
-        if same_sender {
+        total_bytes += value.len() as u64;
+        if sender_of(&value).is_some_and(|f| f == *sender) {
             from_sender += 1;
-            if from_sender >= MAX_QUEUED_PER_SENDER {
-                break;
-            }
-        }
-        if total >= stop_at {
-            break;
+            sender_bytes += value.len() as u64;
         }
+        settled = total >= MAX_QUEUED_PER_RECIPIENT
+            || from_sender >= MAX_QUEUED_PER_SENDER
+            || total_bytes.saturating_add(len) > quota.per_recipient
+            || sender_bytes.saturating_add(len) > quota.per_sender;
     }
     if total >= MAX_QUEUED_PER_RECIPIENT || from_sender >= MAX_QUEUED_PER_SENDER {
         return QueueAdmission::Full;
     }
+    if total_bytes.saturating_add(len) > quota.per_recipient
+        || sender_bytes.saturating_add(len) > quota.per_sender
+    {
+        return QueueAdmission::OverQuota;
+    }
     QueueAdmission::Insert
 }
 
@@ -99,6 +118,11 @@ pub(crate) fn admit_to_queue(
 /// whether the recipient ever drained.
 const QUEUE_ENTRY_TTL_MS: u64 = 7 * 24 * 3_600_000;
 
+// A recipient drains a mailbox for one epoch after the last mail a late
+// sender can address to it (see `common::crypto::mailbox::open_epochs`).
+// Retention longer than that would strand rows under an id nobody opens.
+const _: () = assert!(QUEUE_ENTRY_TTL_MS <= common::crypto::mailbox::MAILBOX_EPOCH_MS);
+
 /// Rows evicted per expiry sweep.
 const MAX_EXPIRE_PER_SWEEP: usize = 1024;
 
@@ -207,7 +231,7 @@ pub(crate) fn plan_drift_migrations(
         let Some(key) = MessageKey::parse(&key_bytes) else {
             continue;
         };
-        let Ok(dispatch) = DispatchP::deser(&value) else {
+        let Ok(dispatch) = DispatchP::deser_stored(&value) else {
             continue;
         };
         out.push((key, dispatch));
@@ -243,10 +267,13 @@ pub(crate) fn delete_migrated_entry(dht: &Dht, key: &MessageKey) -> bool {
 ///   inbound dispatch for an offline recipient.
 ///
 /// **Cap enforcement.** One bounded exact `prefix()` scan over the `dht_queue`
-/// keyspace enforces three things: the per-recipient
+/// keyspace enforces: the per-recipient
 /// [`crate::storage::MAX_QUEUED_PER_RECIPIENT`] cap, the per-sender
-/// [`MAX_QUEUED_PER_SENDER`] share of it, and single-occupancy of a
-/// `dispatch.id` within the recipient's queue.
+/// [`MAX_QUEUED_PER_SENDER`] share of it, the byte quotas from
+/// [`super::config::DhtConfig::queue_quota`], and single-occupancy of a
+/// `dispatch.id` within the recipient's queue. "Sender" is
+/// [`DispatchP::quota_key`]: the token for a sealed dispatch, whose `from` is
+/// a throwaway, so a resealed retry still counts as the same sender.
 ///
 /// `now_ms` is the *home's* clock, never a wire-supplied timestamp: it is the
 /// secondary sort key of [`MessageKey`] and drives the retention sweep, so an
@@ -260,12 +287,15 @@ pub(crate) fn delete_migrated_entry(dht: &Dht, key: &MessageKey) -> bool {
 ///   `MAX_QUEUED_PER_RECIPIENT`, or this sender is at
 ///   `MAX_QUEUED_PER_SENDER` for this recipient; the dispatch is *not*
 ///   stored.
-/// - [`ForwardOutcome::BadSig`] as a defensive surface for an internal
-///   error (postcard serialisation failure, fjall write failure) and for a
-///   `dispatch.id` already queued under a different sender. The
-///   on-the-wire semantics of `BadSig` is "we will
-///   not accept this dispatch" — surfacing infrastructure failures the
-///   same way avoids a silent message-loss path.
+/// - [`ForwardOutcome::QuotaExceeded`] when the row caps leave room but the
+///   recipient's or this sender's queued bytes would pass the quota.
+/// - [`ForwardOutcome::Deleted`] when the recipient deleted its account: no
+///   home holds anything for it any more.
+/// - [`ForwardOutcome::IdConflict`] for a `dispatch.id` already queued under
+///   a different sender.
+/// - [`ForwardOutcome::Internal`] for an error of our own (postcard
+///   serialisation, a failed queue scan, a fjall write). The dispatch is
+///   refused rather than silently lost, without blaming the sender.
 ///
 /// Durability: writes use `WriteOptions::set_sync(true)` so the WAL fsyncs
 /// before this returns — same pattern as
@@ -275,31 +305,43 @@ pub(crate) fn delete_migrated_entry(dht: &Dht, key: &MessageKey) -> bool {
 pub(crate) fn enqueue_for_home(
     dht: &Dht, user_ipk: &[u8; 32], dispatch: &DispatchP, now_ms: u64,
 ) -> ForwardOutcome {
-    match admit_to_queue(&dht.store.queue, user_ipk, &dispatch.id.0, &dispatch.from.0, |v| {
-        DispatchP::deser(v).ok().map(|d| d.from.0)
-    }) {
+    if dht.store.is_tombstoned(user_ipk) {
+        return ForwardOutcome::Deleted;
+    }
+    let value = match dispatch.ser() {
+        Ok(b) => b,
+        Err(_) => return ForwardOutcome::Internal,
+    };
+    match admit_to_queue(
+        &dht.store.queue,
+        user_ipk,
+        &dispatch.id.0,
+        &dispatch.quota_key(),
+        value.len(),
+        dht.cfg.queue_quota(),
+        |v| DispatchP::deser_stored(v).ok().map(|d| d.quota_key()),
+    ) {
         QueueAdmission::Insert => {},
         QueueAdmission::AlreadyQueued => return ForwardOutcome::Stored,
-        QueueAdmission::IdTakenByOther | QueueAdmission::ScanFailed => {
-            return ForwardOutcome::BadSig;
-        },
+        QueueAdmission::IdTakenByOther => return ForwardOutcome::IdConflict,
+        QueueAdmission::ScanFailed => return ForwardOutcome::Internal,
         QueueAdmission::Full => {
             dht.metrics.inc_dht_queue_full_rejections();
             return ForwardOutcome::QueueFull;
         },
+        QueueAdmission::OverQuota => {
+            dht.metrics.inc_dht_queue_quota_rejections();
+            return ForwardOutcome::QuotaExceeded;
+        },
     }
 
     let key = MessageKey::new(user_ipk, now_ms, &dispatch.id.0);
-    let value = match dispatch.ser() {
-        Ok(b) => b,
-        Err(_) => return ForwardOutcome::BadSig,
-    };
 
     // Queues the group-commit fsync. `Stored` is a durable promise, so the
     // caller must await `Store::persist_barrier` before it puts that on the
     // wire.
     if dht.store.put_sync(&dht.store.queue, key.as_bytes(), &value).is_err() {
-        return ForwardOutcome::BadSig;
+        return ForwardOutcome::Internal;
     }
 
     dht.metrics.inc_dht_queue_writes();
@@ -384,7 +426,7 @@ pub(crate) fn queue_batch_for_user(
             exhausted = false;
             break;
         }
-        let Ok(dispatch) = DispatchP::deser(&value) else {
+        let Ok(dispatch) = DispatchP::deser_stored(&value) else {
             continue;
         };
         used += value.len();
@@ -477,6 +519,7 @@ mod tests {
     use std::sync::atomic::Ordering as AtomicOrdering;
 
     use common::proto::client_rel::dispatch_sig_message;
+    use common::proto::pack::Unpacker;
     use common::quic::id::NodeId;
     use ed25519_dalek::Signer;
     use ed25519_dalek::SigningKey;
@@ -538,6 +581,8 @@ mod tests {
             sig:     sig.to_bytes().into(),
             accepted_at_ms: 1,
             wake:    false,
+            wake_hint: None,
+            token: None,
         }
     }
 
@@ -647,6 +692,7 @@ mod tests {
         let hog: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
         let other: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
         let decode = |v: &[u8]| DeliverP::deser(v).ok().map(|d| d.from.0);
+        let quota = dht.cfg.queue_quota();
 
         let row = |from: [u8; 32], id: [u8; 16]| DeliverP {
             id:             id.into(),
@@ -660,7 +706,7 @@ mod tests {
             let mut id = [0u8; 16];
             id[0..8].copy_from_slice(&(i as u64).to_be_bytes());
             assert!(matches!(
-                admit_to_queue(&dht.store.messages, &to_ipk, &id, &hog, decode),
+                admit_to_queue(&dht.store.messages, &to_ipk, &id, &hog, 1, quota, decode),
                 QueueAdmission::Insert
             ));
             let key = MessageKey::new(&to_ipk, i as u64, &id);
@@ -668,15 +714,127 @@ mod tests {
         }
 
         assert!(matches!(
-            admit_to_queue(&dht.store.messages, &to_ipk, &[0xEE; 16], &hog, decode),
+            admit_to_queue(&dht.store.messages, &to_ipk, &[0xEE; 16], &hog, 1, quota, decode),
             QueueAdmission::Full
         ));
         assert!(matches!(
-            admit_to_queue(&dht.store.messages, &to_ipk, &[0xEF; 16], &other, decode),
+            admit_to_queue(&dht.store.messages, &to_ipk, &[0xEF; 16], &other, 1, quota, decode),
             QueueAdmission::Insert
         ));
     }
 
+    /// Byte quotas bind before the row caps when rows are large: the hog is
+    /// refused at its share while another sender still fits, and nobody fits
+    /// once the recipient's total is spent.
+    #[test]
+    fn byte_quota_refuses_the_hog_but_not_other_senders() {
+        use common::proto::client_rel::DeliverP;
+
+        let relay = fresh_signing_key();
+        let dht = fresh_dht(NodeId::new(relay.verifying_key().to_bytes()));
+        let to_ipk: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let hog: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let other: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let decode = |v: &[u8]| DeliverP::deser(v).ok().map(|d| d.from.0);
+        let quota = QueueQuota { per_recipient: 64 * 1024, per_sender: 16 * 1024 };
+
+        let mut seq = 0u64;
+        let mut put = |from: [u8; 32]| {
+            seq += 1;
+            let mut id = [0u8; 16];
+            id[0..8].copy_from_slice(&seq.to_be_bytes());
+            let row = DeliverP {
+                id:             id.into(),
+                from:           from.into(),
+                payload:        vec![0u8; 4096].into(),
+                sig:            [0u8; 64].into(),
+                accepted_at_ms: 0,
+            }
+            .ser()
+            .unwrap();
+            let admission = admit_to_queue(
+                &dht.store.messages,
+                &to_ipk,
+                &id,
+                &from,
+                row.len(),
+                quota,
+                decode,
+            );
+            if matches!(admission, QueueAdmission::Insert) {
+                let key = MessageKey::new(&to_ipk, seq, &id);
+                dht.store.messages.insert(key.as_bytes(), row).unwrap();
+            }
+            admission
+        };
+
+        // ~4.2 KiB rows: three fit in a 16 KiB share, the fourth does not.
+        for _ in 0..3 {
+            assert!(matches!(put(hog), QueueAdmission::Insert));
+        }
+        assert!(matches!(put(hog), QueueAdmission::OverQuota));
+        assert!(matches!(put(other), QueueAdmission::Insert));
+
+        // Fill the recipient's 64 KiB from fresh senders until it refuses.
+        let mut refused = false;
+        for _ in 0..32 {
+            if matches!(put(fresh_signing_key().verifying_key().to_bytes()), QueueAdmission::OverQuota)
+            {
+                refused = true;
+                break;
+            }
+        }
+        assert!(refused, "recipient quota never bound");
+        assert!(matches!(put(other), QueueAdmission::OverQuota));
+    }
+
+    /// A retransmit is `AlreadyQueued` even when its row sits past the point
+    /// where the byte quota already refuses anything new.
+    #[test]
+    fn retransmit_past_the_byte_quota_is_already_queued() {
+        use common::proto::client_rel::DeliverP;
+
+        let relay = fresh_signing_key();
+        let dht = fresh_dht(NodeId::new(relay.verifying_key().to_bytes()));
+        let to_ipk: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let from: [u8; 32] = fresh_signing_key().verifying_key().to_bytes();
+        let decode = |v: &[u8]| DeliverP::deser(v).ok().map(|d| d.from.0);
+        let quota = QueueQuota { per_recipient: 64 * 1024, per_sender: 16 * 1024 };
+
+        let row = |from: [u8; 32], id: [u8; 16]| {
+            DeliverP {
+                id:             id.into(),
+                from:           from.into(),
+                payload:        vec![0u8; 4096].into(),
+                sig:            [0u8; 64].into(),
+                accepted_at_ms: 0,
+            }
+            .ser()
+            .unwrap()
+        };
+
+        // Fifteen ~4.2 KiB rows from other senders, then one from `from`: the
+        // recipient's 64 KiB is spent before the scan reaches `from`'s row.
+        let mut id = [0u8; 16];
+        for seq in 1..=16u64 {
+            id[0..8].copy_from_slice(&seq.to_be_bytes());
+            let sender =
+                if seq == 16 { from } else { fresh_signing_key().verifying_key().to_bytes() };
+            let key = MessageKey::new(&to_ipk, seq, &id);
+            dht.store.messages.insert(key.as_bytes(), row(sender, id)).unwrap();
+        }
+
+        let len = row(from, id).len();
+        assert!(matches!(
+            admit_to_queue(&dht.store.messages, &to_ipk, &id, &from, len, quota, decode),
+            QueueAdmission::AlreadyQueued
+        ));
+        assert!(matches!(
+            admit_to_queue(&dht.store.messages, &to_ipk, &[0xEE; 16], &from, len, quota, decode),
+            QueueAdmission::OverQuota
+        ));
+    }
+
     #[test]
     fn enqueue_for_home_caps_one_sender_below_the_recipient_cap() {
         let relay = fresh_signing_key();
@@ -723,13 +881,54 @@ mod tests {
         assert_eq!(enqueue_for_home(&dht, &to_ipk, &first, now + 500), ForwardOutcome::Stored);
 
         let squat = build_dispatch(&mallory, &to_ipk, [7u8; 16], b"squat");
-        assert_eq!(enqueue_for_home(&dht, &to_ipk, &squat, now + 900), ForwardOutcome::BadSig);
+        assert_eq!(enqueue_for_home(&dht, &to_ipk, &squat, now + 900), ForwardOutcome::IdConflict);
 
         let queued = lookup_queue_for_user(&dht, &to_ipk, 8);
         assert_eq!(queued.len(), 1);
         assert_eq!(queued[0].1.payload.0, b"first");
     }
 
+    /// A sealed dispatch's `from` is fresh on every attempt, so the home
+    /// accounts for it by token: a resealed retry is the same row, not an id
+    /// squat, and one contact's token fills only its own share.
+    #[test]
+    fn sealed_dispatches_share_the_queue_by_token_not_by_from() {
+        use common::proto::client_rel::DeliveryToken;
+
+        let relay = fresh_signing_key();
+        let to_user = fresh_signing_key();
+        let dht = fresh_dht(NodeId::new(relay.verifying_key().to_bytes()));
+        let to_ipk: [u8; 32] = to_user.verifying_key().to_bytes();
+        let now = wall_clock_ms();
+        let hog_token = DeliveryToken::issue(&to_user, [1u8; 16], now + 3_600_000);
+        let other_token = DeliveryToken::issue(&to_user, [2u8; 16], now + 3_600_000);
+        let sealed = |token: &DeliveryToken, id: [u8; 16]| DispatchP {
+            token: Some(token.clone()),
+            ..build_dispatch(&fresh_signing_key(), &to_ipk, id, b"sealed")
+        };
+
+        let first = sealed(&hog_token, [7u8; 16]);
+        assert_eq!(enqueue_for_home(&dht, &to_ipk, &first, now), ForwardOutcome::Stored);
+        let resealed = sealed(&hog_token, [7u8; 16]);
+        assert_eq!(enqueue_for_home(&dht, &to_ipk, &resealed, now + 1), ForwardOutcome::Stored);
+        assert_eq!(lookup_queue_for_user(&dht, &to_ipk, 8).len(), 1, "a retry, not a second row");
+
+        for i in 1..MAX_QUEUED_PER_SENDER {
+            let mut id = [0u8; 16];
+            id[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+            let dispatch = sealed(&hog_token, id);
+            assert_eq!(
+                enqueue_for_home(&dht, &to_ipk, &dispatch, now + i as u64),
+                ForwardOutcome::Stored
+            );
+        }
+        let overflow = sealed(&hog_token, [0xEE; 16]);
+        assert_eq!(enqueue_for_home(&dht, &to_ipk, &overflow, now), ForwardOutcome::QueueFull);
+
+        let from_other = sealed(&other_token, [0xEF; 16]);
+        assert_eq!(enqueue_for_home(&dht, &to_ipk, &from_other, now), ForwardOutcome::Stored);
+    }
+
     #[test]
     fn enqueue_for_home_does_not_count_other_recipients_against_cap() {
         // Cap is per-recipient. Filling user A's queue must not cause
@@ -0,0 +1,405 @@
+//! Identity successions: moving an identity's relay-held state from its old
+//! key to its new one.
+//!
+//! A device that rotates its key publishes an [`IdentitySuccession`] from the
+//! new key's connection. This relay sends it to every home of the old key:
+//! the queue homes (closest to the IPK) and the KeyPackage and Welcome stash
+//! homes (closest to their `BLAKE3(domain || ipk)` keys). Each home checks
+//! both signatures and applies what it holds:
+//!
+//! - A queue home keeps the statement and hands its queue to the new key's
+//!   homes in [`QueueHandover`] batches, dropping each row a quorum took. A
+//!   dispatch that reaches it for the old key later is queued, then passed on
+//!   the same way, so a contact who hasn't heard yet still gets through.
+//!   The new key's homes wake its device as for any queued dispatch.
+//! - A second statement naming another heir means the old key is in two
+//!   hands. The home keeps both and hands the queue to neither; it stays
+//!   under the old key until it expires. The device tells its contacts the
+//!   same way, and they hold both statements for the user to settle.
+//! - A stash home drops the stash. KeyPackages and the Welcomes built on them
+//!   carry the old key as their MLS credential, so the new key has no use for
+//!   them; the device publishes a fresh stash from the new key on connect.
+//!
+//! Handed-over dispatches keep their sender signatures, which name the old
+//! key as `to`. The device accepts those for the keys it succeeded.
+//!
+//! [`IdentitySuccession`]: common::proto::dht_p2p::IdentitySuccession
+//! [`QueueHandover`]: common::proto::dht_p2p::QueueHandover
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use common::proto::dht_p2p::DhtPacket;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::ForwardOutcome;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::dht_p2p::IdentitySuccessionResp;
+use common::proto::dht_p2p::MAX_FETCH_QUEUE_BATCH;
+use common::proto::dht_p2p::MAX_SUCCESSION_SKEW_MS;
+use common::proto::dht_p2p::NodeDescriptor;
+use common::proto::dht_p2p::QueueHandover;
+use common::proto::dht_p2p::QueueHandoverResp;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use parking_lot::Mutex;
+use tokio::time::timeout;
+
+use super::Dht;
+use super::config::FORWARD_K_MIN;
+use super::config::FORWARD_TIMEOUT_MS;
+use super::config::K;
+use crate::storage::db::SuccessionPut;
+
+/// Old keys whose queue this relay is handing over right now, so a burst of
+/// late dispatches doesn't start one hand-over each.
+static HANDING_OVER: LazyLock<Mutex<HashSet<[u8; 32]>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Both keys signed it, and it isn't dated ahead of us: the timestamp is what
+/// the stored statement is swept by.
+pub(crate) fn valid_publish(succession: &IdentitySuccession, now_ms: u64) -> bool {
+    succession.timestamp <= now_ms.saturating_add(MAX_SUCCESSION_SKEW_MS) && succession.verify()
+}
+
+/// Every relay that may hold something under `ipk`: its queue homes and the
+/// homes of its KeyPackage and Welcome stashes, each once.
+pub(super) fn homes_of(dht: &Dht, ipk: &[u8; 32]) -> Vec<NodeDescriptor> {
+    let targets = [
+        NodeId::from_bytes(*ipk),
+        NodeId::from_bytes(super::mls::kp::stash_prefix(ipk)),
+        NodeId::from_bytes(super::mls::welcome::stash_prefix(ipk)),
+    ];
+    let routing = dht.routing.read();
+    let mut seen = HashSet::new();
+    targets
+        .iter()
+        .flat_map(|target| routing.find_closest(target, K))
+        .filter(|home| home.id != dht.node_id && seen.insert(home.id))
+        .collect()
+}
+
+/// Apply `succession` here if this relay is one of the old key's homes, then
+/// send it to every other one. Best-effort: the device re-publishes it on its
+/// next few connects.
+pub(crate) async fn publish_to_homes(dht: Arc<Dht>, succession: IdentitySuccession) {
+    retire(&dht, &succession);
+    let mut set = tokio::task::JoinSet::new();
+    for home in homes_of(&dht, &succession.old_ipk.0) {
+        let request = DhtRequest::IdentitySuccession(succession.clone());
+        set.spawn(request_one(dht.clone(), home, request));
+    }
+    while set.join_next().await.is_some() {}
+}
+
+/// A relay passing on a device's succession. Accepted when it verifies and
+/// this relay holds any of the old key's state.
+pub(crate) fn handle_succession(
+    dht: &Arc<Dht>, succession: IdentitySuccession, now_ms: u64,
+) -> IdentitySuccessionResp {
+    if !valid_publish(&succession, now_ms) {
+        return IdentitySuccessionResp { accepted: false };
+    }
+    IdentitySuccessionResp { accepted: retire(dht, &succession) }
+}
+
+/// Act on `succession` for whichever of the old key's homes this relay is.
+/// Returns whether it is any of them.
+fn retire(dht: &Arc<Dht>, succession: &IdentitySuccession) -> bool {
+    let old = succession.old_ipk.0;
+    let mut home = false;
+    if super::routing::self_in_top_k(dht, &NodeId::from_bytes(old)) {
+        home = true;
+        let new = succession.new_ipk.0;
+        match dht.store.put_succession(succession) {
+            Ok(SuccessionPut::Stored) => {
+                common::info!("DHT: {} succeeded by {}", short(&old), short(&new));
+            },
+            Ok(SuccessionPut::Contested) => {
+                common::warn!("DHT: {} contested by {}; its queue stays", short(&old), short(&new));
+            },
+            _ => {},
+        }
+        // The queue goes to the one heir held, whichever statement this was,
+        // and nowhere while two are.
+        if let Some(heir) = dht.store.get_succession(&old) {
+            tokio::spawn(hand_over_queue(dht.clone(), heir));
+        }
+    }
+    for (stash, ks) in [
+        (super::mls::kp::stash_prefix(&old), &dht.store.keypackage),
+        (super::mls::welcome::stash_prefix(&old), &dht.store.welcome),
+    ] {
+        if super::routing::self_in_top_k(dht, &NodeId::from_bytes(stash)) {
+            home = true;
+            let _ = dht.store.remove_prefix(ks, &stash);
+        }
+    }
+    home
+}
+
+/// Move everything queued for the old key to the new key's homes, a batch at
+/// a time, until the queue is empty or a batch finds no taker.
+///
+/// Boxed: it is spawned from the DHT request handlers and itself dials
+/// peers, which would otherwise make its future type depend on its own.
+pub(crate) fn hand_over_queue(
+    dht: Arc<Dht>, succession: IdentitySuccession,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let old = succession.old_ipk.0;
+        if !HANDING_OVER.lock().insert(old) {
+            return;
+        }
+        loop {
+            let (batch, exhausted) =
+                super::store::queue_batch_for_user(&dht, &old, MAX_FETCH_QUEUE_BATCH);
+            if batch.is_empty() {
+                break;
+            }
+            let dispatches = batch.into_iter().map(|(_, d)| d).collect();
+            let moved: Vec<[u8; 16]> =
+                hand_over(&dht, &succession, dispatches).await.into_iter().collect();
+            super::store::delete_queue_entries(&dht, &old, &moved);
+            if moved.is_empty() || exhausted {
+                break;
+            }
+        }
+        HANDING_OVER.lock().remove(&old);
+    })
+}
+
+/// Offer `dispatches` to the new key's homes, this relay included if it is
+/// one. Returns the ids enough of them took to drop the old copies: a quorum
+/// of [`FORWARD_K_MIN`], or every home there is on a network smaller than
+/// that.
+async fn hand_over(
+    dht: &Arc<Dht>, succession: &IdentitySuccession,
+    dispatches: Vec<common::proto::client_rel::DispatchP>,
+) -> HashSet<[u8; 16]> {
+    let new = NodeId::from_bytes(succession.new_ipk.0);
+    let mut takers: HashMap<[u8; 16], usize> = HashMap::new();
+    let mut homes = 0;
+    if super::routing::self_in_top_k(dht, &new) {
+        homes += 1;
+        let local = QueueHandover { succession: succession.clone(), dispatches: dispatches.clone() };
+        for id in handle_handover(dht, local, now_ms()).await.stored {
+            *takers.entry(id.0).or_default() += 1;
+        }
+    }
+    let remote: Vec<NodeDescriptor> = dht
+        .routing
+        .read()
+        .find_closest(&new, K)
+        .into_iter()
+        .filter(|home| home.id != dht.node_id)
+        .collect();
+    homes += remote.len();
+    let mut set = tokio::task::JoinSet::new();
+    for home in remote {
+        let request = DhtRequest::QueueHandover(QueueHandover {
+            succession: succession.clone(),
+            dispatches: dispatches.clone(),
+        });
+        set.spawn(request_one(dht.clone(), home, request));
+    }
+    while let Some(joined) = set.join_next().await {
+        let Ok(Some(DhtResponse::QueueHandover(resp))) = joined else { continue };
+        for id in resp.stored {
+            *takers.entry(id.0).or_default() += 1;
+        }
+    }
+    let quorum = FORWARD_K_MIN.min(homes).max(1);
+    takers.into_iter().filter(|(_, n)| *n >= quorum).map(|(id, _)| id).collect()
+}
+
+/// An old-key home handing over its queue. Each dispatch must be addressed to
+/// the old key and still carry its sender's valid signature; those are queued
+/// for the new key like any inbound dispatch, waking its device if they ask.
+pub(crate) async fn handle_handover(
+    dht: &Arc<Dht>, handover: QueueHandover, now_ms: u64,
+) -> QueueHandoverResp {
+    let succession = &handover.succession;
+    if !succession.verify()
+        || !super::routing::self_in_top_k(dht, &NodeId::from_bytes(succession.new_ipk.0))
+    {
+        return QueueHandoverResp { stored: Vec::new() };
+    }
+    let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+    let stored: Vec<_> = handover
+        .dispatches
+        .iter()
+        .filter(|d| d.to.0 == old && super::forward::verify_dispatch_user_sig(d))
+        .filter(|d| {
+            super::store::enqueue_for_home(dht, &new, d, now_ms) == ForwardOutcome::Stored
+        })
+        .map(|d| d.id)
+        .collect();
+    if !stored.is_empty() && dht.store.persist_barrier().wait().await.is_err() {
+        return QueueHandoverResp { stored: Vec::new() };
+    }
+    for d in handover.dispatches.iter().filter(|d| d.wake && stored.contains(&d.id)) {
+        dht.trigger_wake(&new, &d.from.0, d.wake_hint.as_deref().map(Vec::as_slice));
+    }
+    QueueHandoverResp { stored }
+}
+
+pub(super) async fn request_one(
+    dht: Arc<Dht>, home: NodeDescriptor, request: DhtRequest,
+) -> Option<DhtResponse> {
+    timeout(Duration::from_millis(FORWARD_TIMEOUT_MS), async {
+        let conn = super::lookup::connect_to_peer(&dht, &home).await.ok()?;
+        let bytes = DhtPacket::Request(request).pack().ok()?;
+        let (mut tx, mut rx) = conn.open_bi().await.ok()?;
+        tx.write_all(&bytes).await.ok()?;
+        tx.finish().ok()?;
+        match DhtPacket::unpack(&mut rx).await.ok()? {
+            DhtPacket::Response(resp) => Some(resp),
+            _ => None,
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+fn short(ipk: &[u8; 32]) -> String {
+    hex::encode(&ipk[..4])
+}
+
+fn now_ms() -> u64 {
+    crate::util::systime().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+
+    use common::proto::client_rel::DispatchP;
+    use common::proto::client_rel::dispatch_sig_message;
+    use common::proto::dht_p2p::identity_succession_signing_input;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+    use crate::dht::DhtConfig;
+
+    fn fresh_dht() -> Arc<Dht> {
+        static SEQ: AtomicU64 = AtomicU64::new(0);
+        let id = SEQ.fetch_add(1, Ordering::SeqCst);
+        let path = std::env::temp_dir()
+            .join(format!("promtuz-succession-test-{}-{id}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let store = Arc::new(crate::storage::db::Store::open(&path).expect("open store"));
+        let signing = SigningKey::from_bytes(&[id as u8 + 1; 32]);
+        let cfg = DhtConfig::default();
+        Arc::new(Dht::new(NodeId::from_bytes([7; 32]), signing, cfg, store).expect("dht"))
+    }
+
+    fn succession(old: &SigningKey, new: &SigningKey, timestamp: u64) -> IdentitySuccession {
+        let (old_ipk, new_ipk) = (old.verifying_key().to_bytes(), new.verifying_key().to_bytes());
+        let input = identity_succession_signing_input(&old_ipk, &new_ipk, timestamp);
+        IdentitySuccession {
+            old_ipk: old_ipk.into(),
+            new_ipk: new_ipk.into(),
+            timestamp,
+            old_sig: old.sign(&input).to_bytes().into(),
+            new_sig: new.sign(&input).to_bytes().into(),
+        }
+    }
+
+    fn dispatch(from: &SigningKey, to: &[u8; 32], id: u8) -> DispatchP {
+        let from_ipk = from.verifying_key().to_bytes();
+        let sig = from.sign(&dispatch_sig_message(to, &from_ipk, &[id; 16], b"hi"));
+        DispatchP {
+            to:             (*to).into(),
+            from:           from_ipk.into(),
+            id:             [id; 16].into(),
+            payload:        b"hi".to_vec().into(),
+            sig:            sig.to_bytes().into(),
+            accepted_at_ms: 1,
+            wake:           true,
+            wake_hint:      None,
+            token:          None,
+        }
+    }
+
+    fn queued(dht: &Dht, ipk: &[u8; 32]) -> usize {
+        super::super::store::queue_batch_for_user(dht, ipk, MAX_FETCH_QUEUE_BATCH).0.len()
+    }
+
+    #[test]
+    fn succession_needs_both_keys_and_no_future_date_to_publish() {
+        let old = SigningKey::from_bytes(&[4u8; 32]);
+        let new = SigningKey::from_bytes(&[5u8; 32]);
+        let now = 1_700_000_000_000;
+        let succession = succession(&old, &new, now);
+        let (old_ipk, new_ipk) = (succession.old_ipk.0, succession.new_ipk.0);
+        let input = identity_succession_signing_input(&old_ipk, &new_ipk, now);
+        assert!(valid_publish(&succession, now));
+        assert!(valid_publish(&succession, now + 30 * 24 * 60 * 60 * 1000), "rotated offline");
+        assert!(!valid_publish(&succession, now - MAX_SUCCESSION_SKEW_MS - 1), "dated ahead");
+
+        let hijack = IdentitySuccession { new_sig: old.sign(&input).to_bytes().into(), ..succession };
+        assert!(!valid_publish(&hijack, now), "the old key alone can't name a successor");
+    }
+
+    #[tokio::test]
+    async fn the_queue_moves_to_the_heir_with_its_signatures() {
+        let dht = fresh_dht();
+        let old = SigningKey::from_bytes(&[4; 32]);
+        let (new, sender) = (SigningKey::from_bytes(&[5; 32]), SigningKey::from_bytes(&[6; 32]));
+        let now = now_ms();
+        let succession = succession(&old, &new, now);
+        let (old_ipk, new_ipk) = (succession.old_ipk.0, succession.new_ipk.0);
+        for id in 1..=3 {
+            let d = dispatch(&sender, &old_ipk, id);
+            let outcome = super::super::store::enqueue_for_home(&dht, &old_ipk, &d, now);
+            assert_eq!(outcome, ForwardOutcome::Stored);
+        }
+        let mut forged = dispatch(&sender, &old_ipk, 4);
+        forged.payload = b"other".to_vec().into();
+
+        let resp = handle_handover(
+            &dht,
+            QueueHandover { succession: succession.clone(), dispatches: vec![forged] },
+            now,
+        )
+        .await;
+        assert!(resp.stored.is_empty(), "a dispatch its sender never signed is not taken");
+
+        hand_over_queue(dht.clone(), succession).await;
+        assert_eq!(queued(&dht, &old_ipk), 0);
+        let (moved, _) =
+            super::super::store::queue_batch_for_user(&dht, &new_ipk, MAX_FETCH_QUEUE_BATCH);
+        assert_eq!(moved.len(), 3);
+        assert!(moved.iter().all(|(_, d)| d.to.0 == old_ipk), "still signed to the old key");
+    }
+
+    #[tokio::test]
+    async fn a_contested_key_hands_its_queue_to_neither_heir() {
+        let dht = fresh_dht();
+        let old = SigningKey::from_bytes(&[4; 32]);
+        let (owner, thief) = (SigningKey::from_bytes(&[5; 32]), SigningKey::from_bytes(&[8; 32]));
+        let now = now_ms();
+        let (first, second) = (succession(&old, &thief, now), succession(&old, &owner, now + 1));
+        let old_ipk = first.old_ipk.0;
+        let d = dispatch(&SigningKey::from_bytes(&[6; 32]), &old_ipk, 1);
+        super::super::store::enqueue_for_home(&dht, &old_ipk, &d, now);
+
+        assert_eq!(dht.store.put_succession(&first).unwrap(), SuccessionPut::Stored);
+        assert!(handle_succession(&dht, second.clone(), now).accepted);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(queued(&dht, &old_ipk), 1, "the queue stays with the old key");
+        assert_eq!(queued(&dht, &first.new_ipk.0) + queued(&dht, &second.new_ipk.0), 0);
+        assert_eq!(dht.store.successions(&old_ipk), vec![first, second], "both are kept");
+    }
+}
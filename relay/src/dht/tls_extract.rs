@@ -78,6 +78,23 @@ pub(crate) fn capabilities_from_conn(conn: &quinn::Connection) -> Option<NodeCap
     capabilities_from_leaf_der(chain.first()?.as_ref())
 }
 
+/// Whether the dialed peer's leaf carries the CA-attested `RELAY`
+/// capability. The `peer/5` admission check behind
+/// `DhtConfig::require_relay_attestation`; like [`capabilities_from_conn`]
+/// it only works on the dialing side.
+pub(crate) fn is_attested_relay(conn: &quinn::Connection) -> bool {
+    capabilities_from_conn(conn).is_some_and(|caps| caps.contains(NodeCapabilities::RELAY))
+}
+
+/// This relay's own capabilities, read off the leaf of its CA-issued cert
+/// chain at `cert_path`. `None` when the file is unreadable or the cert
+/// predates the capability extension.
+pub(crate) fn capabilities_from_pem_file(cert_path: &std::path::Path) -> Option<NodeCapabilities> {
+    let pem = std::fs::read(cert_path).ok()?;
+    let leaf = rustls_pemfile::certs(&mut pem.as_slice()).next()?.ok()?;
+    capabilities_from_leaf_der(leaf.as_ref())
+}
+
 fn capabilities_from_leaf_der(der: &[u8]) -> Option<NodeCapabilities> {
     let (_, cert) = X509Certificate::from_der(der).ok()?;
     let oid = Oid::from(CAPABILITY_OID).ok()?;
@@ -0,0 +1,139 @@
+//! Owner-signed wake policy, replicated to the recipient's DHT homes.
+//!
+//! The device signs a [`WakePolicy`] (quiet hours, mute-all, sender
+//! allow-list) and sends it to its relay next to `RegisterPush`. The relay
+//! verifies it and fans the same record out to every home, which keeps the
+//! newest by timestamp and consults it in [`Dht::trigger_wake`]. The policy
+//! only reads the dispatch's `from` and the clock, so enforcing it never
+//! needs message content.
+//!
+//! Unlike [`super::push_replication`] there is no pending-retry keyspace: a
+//! lost replica just means that home applies the previous policy until the
+//! device reconnects and re-sends it.
+//!
+//! [`WakePolicy`]: common::proto::dht_p2p::WakePolicy
+//! [`Dht::trigger_wake`]: super::Dht::trigger_wake
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::proto::dht_p2p::DhtPacket;
+use common::proto::dht_p2p::DhtRequest;
+use common::proto::dht_p2p::DhtResponse;
+use common::proto::dht_p2p::MAX_DHT_HELLO_SKEW_MS;
+use common::proto::dht_p2p::NodeDescriptor;
+use common::proto::dht_p2p::WakePolicyPublish;
+use common::proto::dht_p2p::WakePolicyPublishResp;
+use common::proto::dht_p2p::wake_policy_signing_input;
+use common::proto::pack::Packer;
+use common::proto::pack::Unpacker;
+use common::quic::id::NodeId;
+use ed25519_dalek::Signature;
+use ed25519_dalek::VerifyingKey;
+use tokio::time::timeout;
+
+use super::Dht;
+use super::config::FORWARD_TIMEOUT_MS;
+use super::config::K;
+
+/// Store locally if this relay is a home, then send the record to every other
+/// home. Best-effort; see the module docs.
+pub(crate) async fn replicate_to_homes(dht: Arc<Dht>, publish: WakePolicyPublish) {
+    let target = NodeId::from_bytes(publish.user_ipk.0);
+    if super::routing::self_in_top_k(&dht, &target) {
+        let _ = dht.store.put_wake_policy(&publish);
+    }
+    let homes = dht.routing.read().find_closest(&target, K);
+    let mut set = tokio::task::JoinSet::new();
+    for home in homes {
+        let dht = dht.clone();
+        let publish = publish.clone();
+        set.spawn(async move {
+            let _ = timeout(
+                Duration::from_millis(FORWARD_TIMEOUT_MS),
+                publish_one(dht, home, publish),
+            )
+            .await;
+        });
+    }
+    while set.join_next().await.is_some() {}
+}
+
+async fn publish_one(dht: Arc<Dht>, home: NodeDescriptor, publish: WakePolicyPublish) -> bool {
+    let Ok(conn) = super::lookup::connect_to_peer(&dht, &home).await else { return false };
+    let Ok(bytes) = DhtPacket::Request(DhtRequest::WakePolicyPublish(publish)).pack() else {
+        return false;
+    };
+    let Ok((mut tx, mut rx)) = conn.open_bi().await else { return false };
+    if tx.write_all(&bytes).await.is_err() || tx.finish().is_err() {
+        return false;
+    }
+    matches!(
+        DhtPacket::unpack(&mut rx).await,
+        Ok(DhtPacket::Response(DhtResponse::WakePolicyPublish(WakePolicyPublishResp {
+            accepted: true
+        })))
+    )
+}
+
+/// Validate owner signature and freshness, require target-home ownership,
+/// then keep the policy if it is newer than the one stored.
+pub(crate) fn handle_publish(
+    dht: &Dht, publish: WakePolicyPublish, now_ms: u64,
+) -> WakePolicyPublishResp {
+    if !valid_publish(&publish, now_ms)
+        || !super::routing::self_in_top_k(dht, &NodeId::from_bytes(publish.user_ipk.0))
+    {
+        return WakePolicyPublishResp { accepted: false };
+    }
+    WakePolicyPublishResp { accepted: dht.store.put_wake_policy(&publish).unwrap_or(false) }
+}
+
+pub(crate) fn valid_publish(publish: &WakePolicyPublish, now_ms: u64) -> bool {
+    if now_ms.abs_diff(publish.timestamp) > MAX_DHT_HELLO_SKEW_MS {
+        return false;
+    }
+    let Ok(key) = VerifyingKey::from_bytes(&publish.user_ipk.0) else {
+        return false;
+    };
+    let input = wake_policy_signing_input(&publish.user_ipk.0, &publish.policy, publish.timestamp);
+    key.verify_strict(&input, &Signature::from_bytes(&publish.user_sig.0)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::dht_p2p::QuietHours;
+    use common::proto::dht_p2p::WakePolicy;
+    use ed25519_dalek::Signer;
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+
+    fn signed(key: &SigningKey, policy: WakePolicy, timestamp: u64) -> WakePolicyPublish {
+        let ipk = key.verifying_key().to_bytes();
+        let sig = key.sign(&wake_policy_signing_input(&ipk, &policy, timestamp));
+        WakePolicyPublish {
+            user_ipk: ipk.into(),
+            policy,
+            timestamp,
+            user_sig: sig.to_bytes().into(),
+        }
+    }
+
+    #[test]
+    fn owner_signed_policy_verifies_and_tampering_does_not() {
+        let key = SigningKey::from_bytes(&[4u8; 32]);
+        let now = 1_700_000_000_000;
+        let policy = WakePolicy {
+            quiet_hours: Some(QuietHours { start_min: 22 * 60, end_min: 7 * 60 }),
+            ..Default::default()
+        };
+        let publish = signed(&key, policy, now);
+        assert!(valid_publish(&publish, now));
+        assert!(!valid_publish(&publish, now + MAX_DHT_HELLO_SKEW_MS + 1));
+
+        let mut unmuted = publish.clone();
+        unmuted.policy.quiet_hours = None;
+        assert!(!valid_publish(&unmuted, now));
+    }
+}
@@ -10,6 +10,7 @@ use crate::dht::bootstrap;
 use crate::dht::sync;
 use crate::quic::acceptor::Acceptor;
 use crate::quic::resolver_link::ResolverLink;
+use crate::quic::tunnel::Tunnel;
 use crate::relay::Relay;
 use crate::util::config::AppConfig;
 
@@ -64,6 +65,23 @@ async fn main() -> Result<()> {
         async move { acceptor.run(relay, cancel).await }
     });
 
+    // QUIC-over-TCP for UDP-blocked clients: a second endpoint fed by the
+    // tunnel listener, drained by its own acceptor into the same handlers.
+    let tunnel_endpoint = if relay.cfg.tunnel.enabled {
+        let tunnel = Tunnel::bind(&relay).await?;
+        let endpoint = tunnel.endpoint.clone();
+        let acceptor = Acceptor::new(endpoint.clone());
+        tokio::spawn({
+            let relay = relay.clone();
+            let cancel = cancel.clone();
+            async move { acceptor.run(relay, cancel).await }
+        });
+        tokio::spawn(tunnel.serve(cancel.clone()));
+        Some(endpoint)
+    } else {
+        None
+    };
+
     // Control socket for `pzrelay clear-db` (and future subcommands).
     tokio::spawn(control::serve(relay.store.clone(), control_sock, cancel.clone()));
 
@@ -87,11 +105,13 @@ async fn main() -> Result<()> {
             dht.attach_resolver(resolver_handle.clone());
 
             // Keep the cached push-gateway directory fresh so `trigger_wake`
-            // has targets. Detached; degrades to no-wakes when empty.
+            // has targets, and drain its queue in batches. Detached; degrades
+            // to no-wakes when empty.
             tokio::spawn(crate::dht::push_wake::refresh_gateways(
                 dht.clone(),
                 resolver_handle.clone(),
             ));
+            tokio::spawn(crate::dht::push_wake::run_wake_batcher(dht.clone()));
 
             // Detached so a slow/absent resolver can't delay QUIC accept; on
             // failure the relay serves with an empty table until a retry wins.
@@ -136,6 +156,9 @@ async fn main() -> Result<()> {
             }
 
             relay.endpoint.close(CloseReason::ShuttingDown.code(), b"ShuttingDown");
+            if let Some(endpoint) = &tunnel_endpoint {
+                endpoint.close(CloseReason::ShuttingDown.code(), b"ShuttingDown");
+            }
 
             // Bounded flush window for in-flight frames (close, DispatchAcks,
             // Deliver) — a misbehaving peer can't stall shutdown past this.
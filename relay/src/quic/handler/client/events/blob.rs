@@ -0,0 +1,102 @@
+//! Client packets for the blob store: uploads to this relay, and reads of a
+//! blob held here or, through the DHT, on the relay that `host` names.
+//!
+//! Uploads are gated on this relay's `BLOB_STORE` capability and charged to
+//! the connection-authenticated IPK; see [`common::proto::blob`] for what the
+//! relay can and cannot learn from them.
+
+use anyhow::Result;
+use common::proto::Sender;
+use common::proto::blob::BlobBeginOutcome;
+use common::proto::blob::BlobManifest;
+use common::proto::client_rel::SRelayPacket;
+use common::quic::id::NodeId;
+use quinn::SendStream;
+
+use crate::quic::handler::client::ClientCtxHandle;
+
+pub(super) async fn handle_blob_begin(
+    manifest: BlobManifest, ttl_secs: u64, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if !ctx.relay.blob_store {
+        return Ok(SRelayPacket::BlobUnavailable.send(tx).await?);
+    }
+    if ctx.limits.blob_begin.check().is_err() {
+        return Ok(());
+    }
+    let blob_id = manifest.blob_id();
+    let cfg = &ctx.relay.cfg.blob;
+    let outcome = if manifest.is_well_formed() {
+        let now = crate::util::systime().as_millis() as u64;
+        let ttl_ms = ttl_secs.min(cfg.max_ttl_secs()).saturating_mul(1000);
+        let (uploader, quota) = (ctx.ipk.to_bytes(), cfg.quota());
+        let store = ctx.relay.store.clone();
+        tokio::task::spawn_blocking(move || {
+            store.blob_begin(&uploader, &manifest, now.saturating_add(ttl_ms), quota, now)
+        })
+        .await??
+    } else {
+        BlobBeginOutcome::Rejected
+    };
+    SRelayPacket::BlobBegun { blob_id: blob_id.into(), outcome }.send(tx).await?;
+    Ok(())
+}
+
+pub(super) async fn handle_blob_put(
+    blob_id: [u8; 32], index: u32, data: Vec<u8>, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if !ctx.relay.blob_store {
+        return Ok(SRelayPacket::BlobUnavailable.send(tx).await?);
+    }
+    if ctx.limits.blob_chunk.check().is_err() {
+        return Ok(());
+    }
+    let now = crate::util::systime().as_millis() as u64;
+    let store = ctx.relay.store.clone();
+    let stored =
+        tokio::task::spawn_blocking(move || store.blob_put(&blob_id, index, &data, now)).await??;
+    SRelayPacket::BlobStored { blob_id: blob_id.into(), index, stored }.send(tx).await?;
+    Ok(())
+}
+
+/// Served locally when `host` is this relay, otherwise fetched from `host`
+/// over the DHT; `None` either way when nobody can produce it.
+pub(super) async fn handle_fetch_blob_manifest(
+    host: NodeId, blob_id: [u8; 32], ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if ctx.limits.blob_chunk.check().is_err() {
+        return Ok(());
+    }
+    let manifest = if host == ctx.relay.key.id() {
+        let now = crate::util::systime().as_millis() as u64;
+        ctx.relay.blob_store.then(|| ctx.relay.store.blob_manifest(&blob_id, now)).flatten()
+    } else {
+        match ctx.relay.dht.clone() {
+            Some(dht) => crate::dht::blob::fetch_manifest(dht, host, blob_id).await,
+            None => None,
+        }
+    };
+    SRelayPacket::BlobManifest { blob_id: blob_id.into(), manifest }.send(tx).await?;
+    Ok(())
+}
+
+pub(super) async fn handle_fetch_blob_chunk(
+    host: NodeId, blob_id: [u8; 32], index: u32, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if ctx.limits.blob_chunk.check().is_err() {
+        return Ok(());
+    }
+    let data = if host == ctx.relay.key.id() {
+        let now = crate::util::systime().as_millis() as u64;
+        ctx.relay.blob_store.then(|| ctx.relay.store.blob_chunk(&blob_id, index, now)).flatten()
+    } else {
+        match ctx.relay.dht.clone() {
+            Some(dht) => crate::dht::blob::fetch_chunk(dht, host, blob_id, index).await,
+            None => None,
+        }
+    };
+    SRelayPacket::BlobChunk { blob_id: blob_id.into(), index, data: data.map(Into::into) }
+        .send(tx)
+        .await?;
+    Ok(())
+}
@@ -27,6 +27,13 @@
 //! the local CFs are drained — graceful degradation for clients that
 //! don't supply one.
 //!
+//! ## Mailboxes
+//!
+//! Contacts address sealed dispatches to the recipient's rotating mailbox
+//! ids rather than its IPK, so a drain covers the IPK's home queue and then
+//! each mailbox the connection opened (`events::mailbox`). Each mailbox is
+//! fetched under its own `DrainAuth` and acked under its own key.
+//!
 //! ## Ack-to-home path
 //!
 //! The remote-fetch path delivers messages, and the matching
@@ -127,49 +134,81 @@ pub(crate) async fn handle_drain_queue_with(
 ) -> Result<()> {
     let recipient_arr: [u8; 32] = *ctx.ipk.as_bytes();
 
-    // 1. Compute `i_am_home` for this user. Branches:
-    //    - DHT disabled → `i_am_home = true` (degenerate but
-    //      correct: the local cf_messages drain is exactly what a
-    //      pre-DHT relay does).
-    //    - Routing table holds < K peers → `i_am_home = true`
-    //      (sparse-network permissive: same policy as `forward.rs::self_is_in_k`).
-    //    - Otherwise: `i_am_home = self ∈ find_closest(user_ipk, K)`.
-    let i_am_home = match ctx.relay.dht.as_ref() {
-        Some(dht) => self_is_in_k_closest(dht, &recipient_arr),
-        None => true,
-    };
-
-    // 2. Stream local cf_messages straight to the wire. `MessageKey`s of
+    // 1. Stream local cf_messages straight to the wire. `MessageKey`s of
     //    everything read (from BOTH local keyspaces) are tracked in
-    //    `delivered_keys` so the follow-up `AckDrain` deletes them. The
+    //    `round.keys` so the follow-up `AckDrain` deletes them. The
     //    remote-home source is GC'd separately via `QueueFetchAck` after
-    //    the ack lands.
-    let mut delivered_keys: Vec<MessageKey> = Vec::new();
-    let mut batch = DrainBatch::default();
+    //    the ack lands. Only the IPK has a local queue: the safety net never
+    //    takes a sealed dispatch, and only sealed ones go to mailboxes.
+    let mut round = DrainRound::default();
 
     stream_keyspace(
         &ctx.relay.store.messages,
         &recipient_arr,
         decode_deliver,
         tx,
-        &mut batch,
-        &mut delivered_keys,
+        &mut round.batch,
+        &mut round.keys,
     )
     .await?;
 
-    // 3. If `i_am_home`, also stream the `dht_queue` keyspace for the
-    //    user's prefix. Both keyspaces share the same `MessageKey` shape.
+    // 2. The home queues: the IPK's under the connect-time `DrainAuth`, then
+    //    each open mailbox's under the auth its `OpenMailbox` carried.
+    //    Snapshot the auth out of the mutex *without* holding the guard
+    //    across the await.
+    let auth_snapshot: Option<DrainAuth> = ctx.drain_auth.lock().clone();
+    drain_home_queue(&ctx, tx, recipient_arr, auth_snapshot, &remote_fetcher, &mut round).await?;
+    for (mailbox, auth) in super::mailbox::open_mailboxes(&ctx) {
+        if round.batch.is_full() {
+            break;
+        }
+        drain_home_queue(&ctx, tx, mailbox, Some(auth), &remote_fetcher, &mut round).await?;
+    }
+
+    // 3. Replace (rather than extend) so a re-drain before ack still
+    //    captures the live set. The previous batch is naturally a
+    //    subset of what's still on disk (we haven't deleted yet),
+    //    so we'd otherwise grow the pending list with duplicates.
+    //    Holds keys from both local keyspaces; `handle_ack_drain`
+    //    removes each key from both (wrong-keyspace remove = no-op).
+    *ctx.pending_remote_drain.lock() = round.remote;
+    *ctx.pending_drain.lock() = round.keys;
+
+    Ok(())
+}
+
+/// Drain the home queue kept under `recipient_arr` — the IPK or one of its
+/// mailbox ids — into `round`.
+async fn drain_home_queue(
+    ctx: &ClientCtxHandle, tx: &mut SendStream, recipient_arr: [u8; 32],
+    auth_snapshot: Option<DrainAuth>, remote_fetcher: &RemoteFetcher, round: &mut DrainRound,
+) -> Result<()> {
+    // 1. Compute `i_am_home` for this queue. Branches:
+    //    - DHT disabled → `i_am_home = true` (degenerate but
+    //      correct: the local cf_messages drain is exactly what a
+    //      pre-DHT relay does).
+    //    - Routing table holds < K peers → `i_am_home = true`
+    //      (sparse-network permissive: same policy as `forward.rs::self_is_in_k`).
+    //    - Otherwise: `i_am_home = self ∈ find_closest(user_ipk, K)`.
+    let i_am_home = match ctx.relay.dht.as_ref() {
+        Some(dht) => self_is_in_k_closest(dht, &recipient_arr),
+        None => true,
+    };
+
+    // 2. If `i_am_home`, stream the `dht_queue` keyspace for the
+    //    queue's prefix. It shares the local queue's `MessageKey` shape.
     //    A self-as-home relay's `dht_queue` can hold dispatches that
     //    arrived via either the sender fan-out or the inbound `Forward`
     //    handler.
     if i_am_home && let Some(dht) = ctx.relay.dht.as_ref().cloned() {
+        dht.wake_gate.reset(&recipient_arr);
         let dht_ids = stream_keyspace(
             &dht.store.queue,
             &recipient_arr,
             decode_dispatch,
             tx,
-            &mut batch,
-            &mut delivered_keys,
+            &mut round.batch,
+            &mut round.keys,
         )
         .await?;
         // The sender fan-out stored these same dispatches at ALL K homes. We
@@ -186,57 +225,45 @@ pub(crate) async fn handle_drain_queue_with(
             };
             if !others.is_empty() {
                 let per_home = others.iter().map(|h| (h.id, dht_ids.clone())).collect();
-                *ctx.pending_remote_drain.lock() = Some(RemoteDrainState {
+                round.remote.push(RemoteDrainState {
                     user_ipk: recipient_arr,
                     per_home,
                     homes: others,
                 });
             }
         }
+        return Ok(());
     }
 
-    // 4. If !i_am_home AND drain_auth set AND DHT is enabled, fetch
-    //    from remote homes. Snapshot the auth out of the mutex
-    //    *without* holding the guard across the await.
-    let auth_snapshot: Option<DrainAuth> = ctx.drain_auth.lock().clone();
-
-    let mut remote_msgs: Vec<DispatchP> = Vec::new();
-    let mut remote_per_home: std::collections::HashMap<NodeId, Vec<[u8; 16]>> =
-        std::collections::HashMap::new();
-    let mut remote_homes = Vec::new();
-    if !i_am_home && !batch.is_full() {
-        if let (Some(auth), Some(dht)) =
-            (auth_snapshot, ctx.relay.dht.as_ref().cloned())
-        {
-            let self_id = dht.node_id;
-            // Hand off to the (possibly-stubbed) remote fetcher.
-            let result: RemoteFetchResult =
-                (remote_fetcher)(dht.clone(), recipient_arr, auth, self_id).await;
-            remote_msgs = result.messages;
-            remote_per_home = result.per_home;
-            remote_homes = result.homes;
-        } else {
-            // Either we have no auth (legacy client) or DHT is
-            // disabled. Log and degrade to local-only — same shape
-            // as the local-only drain.
-            trace!(
-                "DRAIN: !i_am_home but drain_auth/dht missing — serving local only"
-            );
-        }
+    // 3. If !i_am_home AND drain_auth set AND DHT is enabled, fetch
+    //    from remote homes.
+    if round.batch.is_full() {
+        return Ok(());
     }
+    let (Some(auth), Some(dht)) = (auth_snapshot, ctx.relay.dht.as_ref().cloned()) else {
+        // Either we have no auth (legacy client) or DHT is
+        // disabled. Log and degrade to local-only — same shape
+        // as the local-only drain.
+        trace!("DRAIN: !i_am_home but drain_auth/dht missing — serving local only");
+        return Ok(());
+    };
+    let self_id = dht.node_id;
+    // Hand off to the (possibly-stubbed) remote fetcher.
+    let result: RemoteFetchResult = (remote_fetcher)(dht.clone(), recipient_arr, auth, self_id).await;
+    let mut remote_per_home = result.per_home;
 
-    // 5. Stream the remote-fetched dispatches. The local keyspaces went out
+    // 4. Stream the remote-fetched dispatches. The local keyspaces went out
     //    first, so a message that landed in BOTH — possible when a sender's
     //    local-fallback path coexisted with a home-store path during a
     //    routing transition — ships once, from the local side.
     let mut delivered_remote: std::collections::HashSet<[u8; 16]> =
         std::collections::HashSet::new();
-    for dispatch in remote_msgs {
-        if batch.is_full() {
+    for dispatch in result.messages {
+        if round.batch.is_full() {
             break;
         }
         let deliver = dispatch_to_deliver(dispatch);
-        if !batch.admit(deliver.id.0, deliver.payload.0.len()) {
+        if !round.batch.admit(deliver.id.0, deliver.payload.0.len()) {
             continue;
         }
         trace!("DRAIN: sending queued message id={}", hex::encode(deliver.id));
@@ -249,26 +276,17 @@ pub(crate) async fn handle_drain_queue_with(
     // refuses to sign for anything it did not receive, and the byte budget
     // above can cut the stream short. Ids left behind stay queued at their
     // home and come back on the next drain.
-    if !remote_homes.is_empty() {
+    if !result.homes.is_empty() {
         for ids in remote_per_home.values_mut() {
             ids.retain(|id| delivered_remote.contains(id));
         }
         remote_per_home.retain(|_, ids| !ids.is_empty());
-        *ctx.pending_remote_drain.lock() = Some(RemoteDrainState {
+        round.remote.push(RemoteDrainState {
             user_ipk: recipient_arr,
             per_home: remote_per_home,
-            homes:    remote_homes,
+            homes:    result.homes,
         });
     }
-
-    // 6. Replace (rather than extend) so a re-drain before ack still
-    //    captures the live set. The previous batch is naturally a
-    //    subset of what's still on disk (we haven't deleted yet),
-    //    so we'd otherwise grow the pending list with duplicates.
-    //    Holds keys from both local keyspaces; `handle_ack_drain`
-    //    removes each key from both (wrong-keyspace remove = no-op).
-    *ctx.pending_drain.lock() = delivered_keys;
-
     Ok(())
 }
 
@@ -279,7 +297,7 @@ pub(crate) async fn handle_drain_queue_with(
 ///
 /// **Order of operations**:
 /// 1. Local `cf_messages` deletion via WriteBatch (durable).
-/// 2. If `pending_remote_drain` is set: ask libcore to sign an ack
+/// 2. For each queue in `pending_remote_drain`: ask libcore to sign an ack
 ///    over the union `delivered_ids` (5s timeout via
 ///    `oneshot::Receiver`), then send `QueueFetchAck` to each home
 ///    in parallel (3s total wall-clock budget). Best-effort;
@@ -311,12 +329,15 @@ pub(super) async fn handle_ack_drain(
         trace!("DRAIN: cleared {} acked messages", keys.len());
     }
 
-    // 2. Remote `QueueFetchAck` fan-out.
-    let remote_state = ctx.pending_remote_drain.lock().take();
-    if let Some(state) = remote_state
-        && let Err(err) = run_remote_ack_round(&ctx, tx, state).await {
+    // 2. Remote `QueueFetchAck` fan-out, one round per drained queue. The
+    //    rounds share this stream and the single parked `ack_auth`, so they
+    //    run one after another.
+    let remote_states = std::mem::take(&mut *ctx.pending_remote_drain.lock());
+    for state in remote_states {
+        if let Err(err) = run_remote_ack_round(&ctx, tx, state).await {
             trace!("DRAIN: remote ack-fanout fell through: {err}");
         }
+    }
 
     Ok(())
 }
@@ -327,7 +348,8 @@ pub(super) async fn handle_ack_drain(
 ///    overflow truncates oldest-first because per-home iteration
 ///    order already chronological).
 /// 2. Park a `oneshot::Sender<AckAuthPayload>` on `ctx.ack_auth`.
-/// 3. Send `SRelayPacket::AckAuthRequest` to the client.
+/// 3. Send `SRelayPacket::AckAuthRequest` to the client, or
+///    `MailboxAckAuthRequest` when the queue is a mailbox's.
 /// 4. Await `CRelayPacket::AckAuth` via the oneshot (5s timeout).
 /// 5. Fan out `QueueFetchAck` to each home in parallel (3s total
 ///    via `queue_drain::ack_remote_queues`).
@@ -385,13 +407,21 @@ async fn run_remote_ack_round(
         // was carried in from a different drain round.
         None => return Ok(()),
     };
-    SRelayPacket::AckAuthRequest {
-        requester_relay_id,
-        delivered_ids: union.clone(),
-        suggested_timestamp,
-    }
-    .send(tx)
-    .await?;
+    let request = if state.user_ipk == *ctx.ipk.as_bytes() {
+        SRelayPacket::AckAuthRequest {
+            requester_relay_id,
+            delivered_ids: union.clone(),
+            suggested_timestamp,
+        }
+    } else {
+        SRelayPacket::MailboxAckAuthRequest {
+            mailbox: state.user_ipk.into(),
+            requester_relay_id,
+            delivered_ids: union.clone(),
+            suggested_timestamp,
+        }
+    };
+    request.send(tx).await?;
 
     // 4. Await the client's signed ack with a 5s timeout. On timeout
     //    or channel close, drop the pending sender (best-effort:
@@ -455,6 +485,16 @@ fn self_is_in_k_closest(dht: &Dht, user_ipk: &[u8; 32]) -> bool {
     self_dist < kth_dist
 }
 
+/// Everything one `DrainQueue` has sent and must clean up on `AckDrain`.
+#[derive(Default)]
+struct DrainRound {
+    batch:  DrainBatch,
+    /// Local keys to delete, from both local keyspaces.
+    keys:   Vec<MessageKey>,
+    /// Remote copies to GC, one entry per drained queue.
+    remote: Vec<RemoteDrainState>,
+}
+
 /// Running state of one drain: which ids have already gone out and how many
 /// serialized bytes that cost.
 #[derive(Default)]
@@ -484,7 +524,7 @@ fn decode_deliver(value: &[u8]) -> Option<DeliverP> {
 }
 
 fn decode_dispatch(value: &[u8]) -> Option<DeliverP> {
-    DispatchP::deser(value).ok().map(dispatch_to_deliver)
+    DispatchP::deser_stored(value).ok().map(dispatch_to_deliver)
 }
 
 /// Walk `ks` for `recipient`, sending each decoded entry to the client as it is
@@ -624,6 +664,8 @@ mod tests {
             sig:     [7u8; 64].into(),
             accepted_at_ms: 1,
             wake:    false,
+            wake_hint: None,
+            token: None,
         };
         let deliver = dispatch_to_deliver(dispatch.clone());
         assert_eq!(deliver.id, dispatch.id);
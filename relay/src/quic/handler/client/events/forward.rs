@@ -12,6 +12,7 @@ use common::proto::client_rel::activity_sig_message;
 use common::proto::client_rel::dispatch_sig_message;
 use common::proto::pack::Packer;
 use common::proto::pack::Unpacker;
+use common::proto::push::MAX_WAKE_HINT_BYTES;
 use common::debug;
 use common::trace;
 use common::types::bytes::Bytes;
@@ -21,8 +22,11 @@ use quinn::Connection;
 use quinn::ConnectionError;
 use quinn::SendStream;
 
+use crate::dht::forward::ForwardError;
 use crate::dht::forward::ForwardSummary;
 use crate::dht::forward::forward_to_homes;
+use crate::dht::sealed::SealedAdmission;
+use crate::dht::sealed::admit as admit_sealed;
 use crate::dht::store::QueueAdmission;
 use crate::dht::store::admit_to_queue;
 use crate::quic::handler::client::ClientCtxHandle;
@@ -45,7 +49,30 @@ pub(super) async fn handle_forward(
     //    This binding **stays first** — DHT fan-out can only run
     //    after we've confirmed `from == authenticated session`. (Recently-
     //    landed security fix in 1326573; see commit message for context.)
-    if fwd.from.as_slice() != ctx.ipk.as_bytes().as_slice() {
+    //
+    //    A sealed dispatch is the exception: `from` is a one-shot key by
+    //    design and the session is not who it names. Its authorization is the
+    //    recipient-issued token instead, checked (with its rate limit) before
+    //    anything else is spent on it. Sealed dispatches only travel the DHT —
+    //    the local fallback queue keys shares on `from` — so a DHT-less relay
+    //    refuses them outright, and says so, so the sender re-sends identified.
+    if let Some(token) = &fwd.token {
+        let Some(dht) = ctx.relay.dht.as_ref() else {
+            SRelayPacket::DispatchAck(DispatchAckP::SealedUnroutable).send(tx).await?;
+            return Ok(());
+        };
+        let ack = match admit_sealed(dht, &fwd, systime().as_millis() as u64) {
+            SealedAdmission::Accept => None,
+            SealedAdmission::BadToken => Some(DispatchAckP::TokenRejected),
+            SealedAdmission::OverQuota => Some(DispatchAckP::QuotaExceeded),
+        };
+        if let Some(ack) = ack {
+            let token = hex::encode(&token.id.0[..4]);
+            trace!("FORWARD: sealed dispatch refused ({ack:?}) for token {token}");
+            SRelayPacket::DispatchAck(ack).send(tx).await?;
+            return Ok(());
+        }
+    } else if fwd.from.as_slice() != ctx.ipk.as_bytes().as_slice() {
         SRelayPacket::DispatchAck(DispatchAckP::InvalidSig).send(tx).await?;
         return Ok(());
     }
@@ -64,10 +91,23 @@ pub(super) async fn handle_forward(
         return Ok(());
     }
 
+    // A recipient that deleted its account through this relay is gone
+    // everywhere; no queue, here or at its homes, should hold anything for it.
+    if ctx.relay.store.is_tombstoned(&fwd.to.0) {
+        SRelayPacket::DispatchAck(DispatchAckP::NotFound).send(tx).await?;
+        return Ok(());
+    }
+
     // Never accept a client-provided clock. This ingress relay owns the
     // display timestamp and carries it unchanged through every later hop.
     let accepted_at_ms = systime().as_millis() as u64;
-    let fwd = DispatchP { accepted_at_ms, ..fwd };
+    // A wake hint only rides along with a dispatch that may wake, and only
+    // while it fits; anything else is dropped rather than queued K times.
+    let wake_hint = fwd
+        .wake_hint
+        .clone()
+        .filter(|hint| fwd.wake && hint.len() <= MAX_WAKE_HINT_BYTES);
+    let fwd = DispatchP { accepted_at_ms, wake_hint, ..fwd };
 
     // Snapshot the dispatch fields we need on multiple paths *without*
     // moving `fwd` yet — the K-closest path takes the whole `DispatchP`,
@@ -83,7 +123,10 @@ pub(super) async fn handle_forward(
         sig:     fwd.sig,
         accepted_at_ms,
         wake:    fwd.wake,
+        wake_hint: fwd.wake_hint.clone(),
+        token:   fwd.token.clone(),
     };
+    let sealed = fwd.token.is_some();
     let delivery = DeliverP {
         id:      fwd.id,
         from:    fwd.from,
@@ -130,6 +173,36 @@ pub(super) async fn handle_forward(
                 SRelayPacket::DispatchAck(ack).send(tx).await?;
                 return Ok(());
             }
+            Err(ForwardError::InsufficientReplicas { summary, .. })
+                if sealed && summary.refused_token() =>
+            {
+                trace!(
+                    "FORWARD: homes refused the token on sealed dispatch {}",
+                    hex::encode(&delivery.id.0[..8])
+                );
+                SRelayPacket::DispatchAck(DispatchAckP::TokenRejected).send(tx).await?;
+                return Ok(());
+            }
+            Err(ForwardError::InsufficientReplicas { summary, .. })
+                if summary.refused_deleted() =>
+            {
+                trace!(
+                    "FORWARD: recipient of dispatch {} deleted its account",
+                    hex::encode(&delivery.id.0[..8])
+                );
+                SRelayPacket::DispatchAck(DispatchAckP::NotFound).send(tx).await?;
+                return Ok(());
+            }
+            Err(ForwardError::InsufficientReplicas { summary, .. })
+                if summary.refused_for_quota() =>
+            {
+                trace!(
+                    "FORWARD: homes refused dispatch {} over quota",
+                    hex::encode(&delivery.id.0[..8])
+                );
+                SRelayPacket::DispatchAck(DispatchAckP::QuotaExceeded).send(tx).await?;
+                return Ok(());
+            }
             Err(err) => {
                 // Fan-out couldn't reach quorum (or routing was empty).
                 // Fall through to local-queue. Logging at trace because
@@ -146,7 +219,13 @@ pub(super) async fn handle_forward(
 
     // 5. Local-queue safety net. Pre-sticky-home behaviour preserved
     //    as a fallback so a transient DHT/network hiccup doesn't lose
-    //    messages.
+    //    messages. Not for a sealed dispatch: this queue would store it under
+    //    a throwaway `from`, outside every quota. The sender re-sends it
+    //    identified, which this queue can hold.
+    if sealed {
+        SRelayPacket::DispatchAck(DispatchAckP::SealedUnroutable).send(tx).await?;
+        return Ok(());
+    }
     let dispatch = store_in_rocks(&ctx, recipient, delivery).await?;
     SRelayPacket::DispatchAck(dispatch).send(tx).await?;
 
@@ -274,6 +353,8 @@ pub(crate) fn dispatch_to_deliver(d: &DispatchP) -> DeliverP {
 /// - `Queued` on success
 /// - `QueueFull` if the recipient already has `MAX_QUEUED_PER_RECIPIENT`
 ///   messages on disk; the message is *not* stored in this case.
+/// - `QuotaExceeded` if it would take the recipient's or this sender's
+///   queued bytes past `DhtConfig::queue_quota`; also not stored.
 async fn store_in_rocks(
     ctx: &ClientCtxHandle, recipient: Bytes<32>, delivery: DeliverP,
 ) -> Result<DispatchAckP> {
@@ -283,9 +364,16 @@ async fn store_in_rocks(
         hex::encode(&recipient.0[..8])
     );
 
-    match admit_to_queue(&ctx.relay.store.messages, &recipient.0, &delivery.id.0, &delivery.from.0, |v| {
-        DeliverP::deser(v).ok().map(|d| d.from.0)
-    }) {
+    let payload = delivery.ser()?;
+    match admit_to_queue(
+        &ctx.relay.store.messages,
+        &recipient.0,
+        &delivery.id.0,
+        &delivery.from.0,
+        payload.len(),
+        ctx.relay.cfg.dht.queue_quota(),
+        |v| DeliverP::deser(v).ok().map(|d| d.from.0),
+    ) {
         QueueAdmission::Insert => {},
         QueueAdmission::AlreadyQueued => {
             return Ok(DispatchAckP::Queued { accepted_at_ms: delivery.accepted_at_ms });
@@ -300,13 +388,16 @@ async fn store_in_rocks(
             trace!("FORWARD: queue full for recipient {}; rejecting", hex::encode(recipient));
             return Ok(DispatchAckP::QueueFull);
         },
+        QueueAdmission::OverQuota => {
+            trace!("FORWARD: quota exceeded for recipient {}; rejecting", hex::encode(recipient));
+            return Ok(DispatchAckP::QuotaExceeded);
+        },
     }
 
     let key = MessageKey::new(&recipient.0, delivery.accepted_at_ms, &delivery.id.0);
 
     // `Queued` is a durability promise, so the write must be on disk before we
     // reply — the barrier resolves on the group commit covering it.
-    let payload = delivery.ser()?;
     ctx.relay.store.put_sync(&ctx.relay.store.messages, key.as_bytes(), &payload)?;
     ctx.relay.store.persist_barrier().wait().await?;
 
@@ -0,0 +1,152 @@
+//! Handle `CRelayPacket::OpenMailbox`.
+//!
+//! A mailbox id is a blinded key that only its owner can sign for (see
+//! `common::crypto::mailbox`). Contacts address sealed dispatches to it in
+//! place of the IPK, so its homes queue mail without learning whose it is.
+//! Opening one here makes it an alias of this connection:
+//! - dispatches to it are delivered live here;
+//! - its homes' queue drains alongside the IPK's;
+//! - its presence lease, push pseudonym and wake policy are published under it.
+//!
+//! Every record is checked against the mailbox id alone, the way the
+//! IPK-keyed handlers check theirs against `ctx.ipk`. This relay sees both
+//! ids on one connection. The homes and every relay on the path see only one.
+
+use anyhow::Result;
+use common::crypto::PublicKey;
+use common::crypto::mailbox::MAX_OPEN_MAILBOXES;
+use common::proto::client_rel::OpenMailboxP;
+use common::trace;
+
+use crate::quic::handler::client::ClientCtxHandle;
+use crate::quic::handler::client::events::drain_auth::DrainAuth;
+use crate::quic::handler::client::events::drain_auth::verify_drain_auth;
+use crate::quic::handler::client::events::spawn_tied;
+use crate::quic::handler::client::remove_client_if_same;
+use crate::util::systime;
+
+/// Verify `open`, then make its mailbox an alias of this connection and store
+/// and replicate the records that came with it. A record that does not name
+/// the mailbox or does not verify is dropped on its own; a bad mailbox
+/// signature drops the whole packet.
+pub(super) async fn handle_open_mailbox(open: OpenMailboxP, ctx: ClientCtxHandle) -> Result<()> {
+    if ctx.limits.open_mailbox.check().is_err() {
+        return Ok(());
+    }
+    // The signature binds `dht.node_id`; without a DHT there are no homes to
+    // queue under the mailbox either.
+    let Some(dht) = ctx.relay.dht.as_ref().cloned() else { return Ok(()) };
+    let mailbox = open.mailbox.0;
+    if mailbox == ctx.ipk.to_bytes() {
+        return Ok(());
+    }
+    let Ok(key) = PublicKey::from_bytes(&mailbox) else { return Ok(()) };
+    let now_ms = systime().as_millis() as u64;
+    let auth = match verify_drain_auth(&key, &dht.node_id, now_ms, open.timestamp, open.sig.0) {
+        Ok(auth) => auth,
+        Err(reason) => {
+            trace!("OPEN_MAILBOX: rejected — {reason:?}");
+            return Ok(());
+        },
+    };
+
+    let evicted = admit(&mut ctx.open_mailboxes.lock(), mailbox, auth);
+    if let Some(evicted) = evicted {
+        close(&ctx, &evicted);
+    }
+    ctx.relay.clients.write().insert(mailbox, ctx.conn.clone());
+
+    let push = open
+        .push
+        .filter(|p| p.user_ipk.0 == mailbox)
+        .filter(|p| crate::dht::push_replication::valid_publish(p, now_ms));
+    let wake = open
+        .wake
+        .filter(|w| w.user_ipk.0 == mailbox)
+        .filter(|w| crate::dht::wake_policy::valid_publish(w, now_ms));
+    let lease =
+        open.lease.filter(|l| l.user.0 == mailbox && l.relay_id == dht.node_id && l.verify(now_ms));
+
+    let store = ctx.relay.store.clone();
+    let (push, wake, lease) = tokio::task::spawn_blocking(move || {
+        let push = push.filter(|p| store.put_push_pseudonym(&mailbox, &p.pseudonym.0).is_ok());
+        let wake = wake.filter(|w| store.put_wake_policy(w).is_ok());
+        let lease = lease.filter(|l| store.put_presence_lease(l).unwrap_or(false));
+        (push, wake, lease)
+    })
+    .await?;
+
+    if let Some(lease) = &lease {
+        ctx.relay.presence_leases.write().insert(mailbox, lease.clone());
+    }
+    spawn_tied(&ctx.cancel, async move {
+        if let Some(lease) = lease {
+            crate::dht::forward::forward_presence_lease(dht.clone(), lease).await;
+        }
+        if let Some(push) = push {
+            crate::dht::push_replication::replicate_to_homes(dht.clone(), push).await;
+        }
+        if let Some(wake) = wake {
+            crate::dht::wake_policy::replicate_to_homes(dht, wake).await;
+        }
+    });
+    trace!("OPEN_MAILBOX: opened {}", hex::encode(&mailbox[..8]));
+    Ok(())
+}
+
+/// Every mailbox open on this connection, the IPK's own queue excluded.
+pub(crate) fn open_mailboxes(ctx: &ClientCtxHandle) -> Vec<([u8; 32], DrainAuth)> {
+    ctx.open_mailboxes.lock().clone()
+}
+
+/// Drop this connection's mailbox aliases, leaving any a newer connection
+/// has since claimed. Called on disconnect next to the IPK's own eviction.
+pub(crate) fn close_all(ctx: &ClientCtxHandle) {
+    for (mailbox, _) in std::mem::take(&mut *ctx.open_mailboxes.lock()) {
+        close(ctx, &mailbox);
+    }
+}
+
+fn close(ctx: &ClientCtxHandle, mailbox: &[u8; 32]) {
+    if remove_client_if_same(&ctx.relay, mailbox, &ctx.conn) {
+        ctx.relay.presence_leases.write().remove(mailbox);
+    }
+}
+
+/// Record `mailbox` as open with a fresh `auth`. Re-opening one refreshes its
+/// auth in place. Past [`MAX_OPEN_MAILBOXES`] the one opened first is closed
+/// and returned: owners open in epoch order, so that is the oldest epoch.
+fn admit(
+    open: &mut Vec<([u8; 32], DrainAuth)>, mailbox: [u8; 32], auth: DrainAuth,
+) -> Option<[u8; 32]> {
+    if let Some(slot) = open.iter_mut().find(|(id, _)| *id == mailbox) {
+        slot.1 = auth;
+        return None;
+    }
+    let evicted = (open.len() >= MAX_OPEN_MAILBOXES).then(|| open.remove(0).0);
+    open.push((mailbox, auth));
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(timestamp: u64) -> DrainAuth {
+        DrainAuth { timestamp, sig: [0u8; 64] }
+    }
+
+    #[test]
+    fn reopening_refreshes_and_the_oldest_gives_way_at_the_cap() {
+        let mut open = Vec::new();
+        for i in 0..MAX_OPEN_MAILBOXES as u8 {
+            assert_eq!(admit(&mut open, [i; 32], auth(1)), None);
+        }
+        assert_eq!(admit(&mut open, [0; 32], auth(2)), None, "a renewal is not a new mailbox");
+        assert_eq!(open[0].1, auth(2));
+
+        assert_eq!(admit(&mut open, [0xFF; 32], auth(3)), Some([0; 32]));
+        assert_eq!(open.len(), MAX_OPEN_MAILBOXES);
+        assert!(open.iter().all(|(id, _)| *id != [0; 32]));
+    }
+}
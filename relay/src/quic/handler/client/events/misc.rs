@@ -4,7 +4,14 @@ use common::proto::Sender;
 use common::proto::client_rel::QueryP;
 use common::proto::client_rel::QueryResultP;
 use common::proto::client_rel::SRelayPacket;
+use common::proto::dht_p2p::AccountDeletion;
+use common::proto::dht_p2p::IdentitySuccession;
+use common::proto::dht_p2p::PredecessorDeletion;
+use common::proto::dht_p2p::PushKeyRecord;
 use common::proto::dht_p2p::PushPseudonymPublish;
+use common::proto::dht_p2p::TokenRevocation;
+use common::proto::dht_p2p::WakePolicy;
+use common::proto::dht_p2p::WakePolicyPublish;
 use quinn::SendStream;
 
 use crate::quic::handler::client::ClientCtxHandle;
@@ -58,3 +65,196 @@ pub(super) async fn handle_register_push(
     debug!("client({}) registered push-pseudonym", ctx.conn.remote_address());
     Ok(())
 }
+
+/// Store the device's signed wake policy and fan it to the DHT homes that
+/// enforce it. Bound to `ctx.ipk` like [`handle_register_push`]; the signature
+/// must still verify so the homes can check it independently.
+pub(super) async fn handle_set_wake_policy(
+    policy: WakePolicy, timestamp: u64, sig: [u8; 64], ctx: ClientCtxHandle,
+) -> Result<()> {
+    if ctx.limits.set_wake_policy.check().is_err() {
+        return Ok(());
+    }
+    let publish = WakePolicyPublish {
+        user_ipk: ctx.ipk.to_bytes().into(),
+        policy,
+        timestamp,
+        user_sig: sig.into(),
+    };
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::wake_policy::valid_publish(&publish, now_ms) {
+        return Ok(());
+    }
+    let store = ctx.relay.store.clone();
+    let local = publish.clone();
+    tokio::task::spawn_blocking(move || store.put_wake_policy(&local)).await??;
+    if let Some(dht) = ctx.relay.dht.clone() {
+        spawn_tied(&ctx.cancel, crate::dht::wake_policy::replicate_to_homes(dht, publish));
+    }
+    debug!("client({}) set wake policy", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Store the device's signed push key and fan it to its DHT homes. The record
+/// must name the connection's IPK; like the wake policy, it is replicated
+/// verbatim, so the signature has to verify here too.
+pub(super) async fn handle_publish_push_key(
+    record: PushKeyRecord, ctx: ClientCtxHandle,
+) -> Result<()> {
+    if record.user_ipk.0 != ctx.ipk.to_bytes() || ctx.limits.publish_push_key.check().is_err() {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::push_key::valid_publish(&record, now_ms) {
+        return Ok(());
+    }
+    let store = ctx.relay.store.clone();
+    let local = record.clone();
+    tokio::task::spawn_blocking(move || store.put_push_key(&local)).await??;
+    if let Some(dht) = ctx.relay.dht.clone() {
+        spawn_tied(&ctx.cancel, crate::dht::push_key::replicate_to_homes(dht, record));
+    }
+    debug!("client({}) published push key", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Pass on the device's identity succession to the old key's homes. It must
+/// name the connection's IPK as the heir, so only the new key's device can
+/// set a hand-over going; both signatures are re-checked at every home.
+pub(super) async fn handle_publish_succession(
+    succession: IdentitySuccession, ctx: ClientCtxHandle,
+) -> Result<()> {
+    if succession.new_ipk.0 != ctx.ipk.to_bytes() || ctx.limits.publish_succession.check().is_err()
+    {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::succession::valid_publish(&succession, now_ms) {
+        return Ok(());
+    }
+    if let Some(dht) = ctx.relay.dht.clone() {
+        spawn_tied(&ctx.cancel, crate::dht::succession::publish_to_homes(dht, succession));
+    }
+    debug!("client({}) published an identity succession", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Delete the connection's identity: wipe what this relay holds for it, send
+/// the deletion to every home and answer with how many wiped. Like a
+/// succession it must name the connection's IPK, which can't connect here
+/// again afterwards.
+pub(super) async fn handle_delete_account(
+    deletion: AccountDeletion, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if deletion.ipk.0 != ctx.ipk.to_bytes() || ctx.limits.delete_account.check().is_err() {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::account::valid_deletion(&deletion, now_ms) {
+        return Ok(());
+    }
+    let store = ctx.relay.store.clone();
+    let local = deletion.clone();
+    tokio::task::spawn_blocking(move || crate::dht::account::forget(&store, &local)).await?;
+    ctx.relay.push_pseudonyms.write().remove(&deletion.ipk.0);
+    let homes = match ctx.relay.dht.clone() {
+        Some(dht) => crate::dht::account::delete_from_homes(dht, deletion).await,
+        None => 0,
+    };
+    SRelayPacket::AccountDeleted { homes }.send(tx).await?;
+    debug!("client({}) deleted its account ({homes} homes)", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Delete a key the connection's identity rotated away from: wipe what this
+/// relay holds for it, send it to that key's homes and answer with how many
+/// wiped. The chain must end at the connection's IPK.
+pub(super) async fn handle_delete_predecessor(
+    deletion: PredecessorDeletion, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if deletion.deletion.ipk.0 != ctx.ipk.to_bytes()
+        || ctx.limits.delete_predecessor.check().is_err()
+    {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    let ipk = match deletion.ipk() {
+        Some(ipk) if crate::dht::account::valid_predecessor_deletion(&deletion, now_ms) => ipk,
+        _ => return Ok(()),
+    };
+    let store = ctx.relay.store.clone();
+    let local = deletion.deletion.clone();
+    tokio::task::spawn_blocking(move || crate::dht::account::forget_as(&store, &ipk, &local))
+        .await?;
+    ctx.relay.push_pseudonyms.write().remove(&ipk);
+    let homes = match ctx.relay.dht.clone() {
+        Some(dht) => crate::dht::account::delete_predecessor_from_homes(dht, deletion).await,
+        None => 0,
+    };
+    SRelayPacket::AccountDeleted { homes }.send(tx).await?;
+    debug!("client({}) deleted a retired key ({homes} homes)", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Delete one of the connection's owner's mailboxes: wipe and tombstone it
+/// here, send it to the mailbox's homes and answer with how many wiped. Like
+/// a token revocation it can't be bound to `ctx.ipk`; the mailbox key's
+/// signature is the authorization.
+pub(super) async fn handle_delete_mailbox(
+    deletion: AccountDeletion, ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if deletion.ipk.0 == ctx.ipk.to_bytes() || ctx.limits.delete_mailbox.check().is_err() {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::account::valid_deletion(&deletion, now_ms) {
+        return Ok(());
+    }
+    let store = ctx.relay.store.clone();
+    let local = deletion.clone();
+    tokio::task::spawn_blocking(move || crate::dht::account::forget(&store, &local)).await?;
+    ctx.relay.push_pseudonyms.write().remove(&deletion.ipk.0);
+    let homes = match ctx.relay.dht.clone() {
+        Some(dht) => crate::dht::account::delete_from_homes(dht, deletion).await,
+        None => 0,
+    };
+    SRelayPacket::AccountDeleted { homes }.send(tx).await?;
+    debug!("client({}) deleted a mailbox ({homes} homes)", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Fan a signed token revocation to the homes of the key it names. That key
+/// may be one of the device's mailboxes, so it is not bound to `ctx.ipk`; the
+/// owner signature is the authorization. Fire-and-forget — no reply.
+pub(super) async fn handle_revoke_tokens(
+    revocation: TokenRevocation, ctx: ClientCtxHandle,
+) -> Result<()> {
+    if ctx.limits.revoke_tokens.check().is_err() {
+        return Ok(());
+    }
+    let now_ms = crate::util::systime().as_millis() as u64;
+    if !crate::dht::sealed::valid_revocation(&revocation, now_ms) {
+        return Ok(());
+    }
+    if let Some(dht) = ctx.relay.dht.clone() {
+        spawn_tied(&ctx.cancel, crate::dht::sealed::replicate_revocation(dht, revocation));
+    }
+    debug!("client({}) revoked delivery tokens", ctx.conn.remote_address());
+    Ok(())
+}
+
+/// Look up a contact's push key: from its homes when the DHT is up, else from
+/// what this relay stored itself. Over quota, the stream is dropped unanswered.
+pub(super) async fn handle_fetch_push_key(
+    target_ipk: [u8; 32], ctx: ClientCtxHandle, tx: &mut SendStream,
+) -> Result<()> {
+    if ctx.limits.fetch_push_key.check_key(&target_ipk).is_err() {
+        return Ok(());
+    }
+    let record = match ctx.relay.dht.clone() {
+        Some(dht) => crate::dht::push_key::fetch_from_homes(dht, target_ipk).await,
+        None => ctx.relay.store.get_push_key(&target_ipk),
+    };
+    SRelayPacket::PushKey { target_ipk: target_ipk.into(), record }.send(tx).await?;
+    Ok(())
+}
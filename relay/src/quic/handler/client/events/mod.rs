@@ -17,9 +17,11 @@ use crate::quic::handler::client::{
     self as client_handler,
 };
 
+pub mod blob;
 pub mod drain;
 pub mod drain_auth;
 pub mod forward;
+pub mod mailbox;
 pub mod misc;
 pub mod mls_relay;
 pub mod presence;
@@ -68,7 +70,7 @@ pub(super) async fn handle_packet(
     match packet {
         // Handshake(packet) => handle_handshake(packet, ctx.clone(), tx).await,
         Query(query) => handle_misc(query, ctx.clone(), tx).await,
-        Dispatch(fwd) => handle_forward(fwd, ctx.clone(), tx).await,
+        Dispatch(fwd) | DispatchV7(fwd) => handle_forward(fwd, ctx.clone(), tx).await,
         DrainQueue => handle_drain_queue(ctx.clone(), tx).await,
         AckDrain => handle_ack_drain(ctx.clone(), tx).await,
         // Sticky-home. The packet has no response; we drop
@@ -118,6 +120,42 @@ pub(super) async fn handle_packet(
             misc::handle_register_push(pseudonym.0, timestamp, sig.0, ctx.clone()).await
         },
 
+        SetWakePolicy { policy, timestamp, sig } => {
+            misc::handle_set_wake_policy(policy, timestamp, sig.0, ctx.clone()).await
+        },
+
+        PublishPushKey(record) => misc::handle_publish_push_key(record, ctx.clone()).await,
+
+        FetchPushKey { target_ipk } => {
+            misc::handle_fetch_push_key(target_ipk.0, ctx.clone(), tx).await
+        },
+
+        BlobBegin { manifest, ttl_secs } => {
+            blob::handle_blob_begin(manifest, ttl_secs, ctx.clone(), tx).await
+        },
+        BlobPut { blob_id, index, data } => {
+            blob::handle_blob_put(blob_id.0, index, data.0, ctx.clone(), tx).await
+        },
+        FetchBlobManifest { host, blob_id } => {
+            blob::handle_fetch_blob_manifest(host, blob_id.0, ctx.clone(), tx).await
+        },
+        FetchBlobChunk { host, blob_id, index } => {
+            blob::handle_fetch_blob_chunk(host, blob_id.0, index, ctx.clone(), tx).await
+        },
+
+        OpenMailbox(open) => mailbox::handle_open_mailbox(open, ctx.clone()).await,
+
+        PublishSuccession(succession) => {
+            misc::handle_publish_succession(succession, ctx.clone()).await
+        },
+        DeleteAccount(deletion) => misc::handle_delete_account(deletion, ctx.clone(), tx).await,
+        DeletePredecessor(deletion) => {
+            misc::handle_delete_predecessor(deletion, ctx.clone(), tx).await
+        },
+        DeleteMailbox(deletion) => misc::handle_delete_mailbox(deletion, ctx.clone(), tx).await,
+
+        RevokeTokens(revocation) => misc::handle_revoke_tokens(revocation, ctx.clone()).await,
+
         // Ignore Extra
         _ => Ok(()),
     }
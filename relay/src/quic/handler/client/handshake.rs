@@ -1,12 +1,13 @@
 use anyhow::Result;
 use anyhow::bail;
-use common::PROTOCOL_VERSION;
 use common::crypto::PublicKey;
 use common::crypto::get_nonce;
 use common::proto::Sender;
 use common::proto::client_rel::CHandshakePacket;
+use common::proto::client_rel::LEGACY_HELLO_VERSION;
 use common::proto::client_rel::SHandshakePacket;
 use common::proto::client_rel::ServerHandshakeResultP;
+use common::proto::client_rel::relay_auth_message;
 use common::proto::pack::Unpacker;
 use common::quic::CloseReason;
 use ed25519_dalek::Signature;
@@ -15,21 +16,11 @@ use quinn::Connection;
 use crate::relay::RelayRef;
 use crate::util::systime;
 
-/// Canonical bytes a client signs to prove possession of its identity key.
-/// Mirrored in libcore at `libcore/src/quic/server.rs`.
-///
-/// TODO: bind the responder identity and the TLS exporter — as written, a relay
-/// can forward another relay's challenge and replay the answer. Needs a
-/// `PROTOCOL_VERSION` bump on both sides.
-fn client_auth_message(nonce: &[u8; 32]) -> Vec<u8> {
-    [b"relay-auth-v" as &[u8], &PROTOCOL_VERSION.to_be_bytes(), nonce].concat()
-}
-
-fn verify_client_proof(ipk: &PublicKey, nonce: &[u8; 32], sig: &[u8]) -> bool {
+fn verify_client_proof(ipk: &PublicKey, version: u16, nonce: &[u8; 32], sig: &[u8]) -> bool {
     let Ok(sig) = Signature::from_slice(sig) else {
         return false;
     };
-    ipk.verify_strict(&client_auth_message(nonce), &sig).is_ok()
+    ipk.verify_strict(&relay_auth_message(version, nonce), &sig).is_ok()
 }
 
 /// Handles handshake linearly
@@ -50,17 +41,36 @@ pub(super) async fn handle_handshake(
 
     //===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
 
-    // 1. Client must send `ClientHello`
+    // 1. Client must send `ClientHello`, with the versions it speaks unless it
+    //    predates negotiation. Settle on the highest we share.
 
-    let Hello { ipk } = CHandshakePacket::unpack(&mut rx).await? else {
-        order_mismatch.send(&mut tx).await.err();
-        bail!("Packet Mismatch");
+    let (ipk, min_version, max_version, legacy) = match CHandshakePacket::unpack(&mut rx).await? {
+        Hello { ipk } => (ipk, LEGACY_HELLO_VERSION, LEGACY_HELLO_VERSION, true),
+        HelloVersions { ipk, min_version, max_version } => (ipk, min_version, max_version, false),
+        _ => {
+            order_mismatch.send(&mut tx).await.err();
+            bail!("Packet Mismatch");
+        },
     };
     let ipk = PublicKey::from_bytes(&ipk)?;
 
+    let Some(version) = common::negotiate_version(min_version, max_version) else {
+        HandshakeResult(ServerHandshakeResultP::Reject {
+            reason: "Unsupported Protocol Version; update the app".into(),
+        })
+        .send(&mut tx)
+        .await
+        .err();
+        bail!("client({}) speaks v{min_version}..=v{max_version}", conn.remote_address());
+    };
+
     let nonce = get_nonce::<32>().into();
 
-    SHandshakePacket::Challenge { nonce }.send(&mut tx).await?;
+    if legacy {
+        SHandshakePacket::Challenge { nonce }.send(&mut tx).await?;
+    } else {
+        SHandshakePacket::ChallengeAt { nonce, version }.send(&mut tx).await?;
+    }
 
     //===:===:===:===:===:===:===:===:===:===:===:===:===:===:===//
 
@@ -73,7 +83,7 @@ pub(super) async fn handle_handshake(
 
     let ipk_bytes = ipk.to_bytes();
 
-    if !verify_client_proof(&ipk, &nonce, &*sig) {
+    if !verify_client_proof(&ipk, version, &nonce, &*sig) {
         HandshakeResult(ServerHandshakeResultP::Reject { reason: "Invalid Signature".into() })
             .send(&mut tx)
             .await
@@ -81,6 +91,14 @@ pub(super) async fn handle_handshake(
         bail!("client({}) failed auth for ipk({ipk:?})", conn.remote_address());
     }
 
+    if relay.store.is_tombstoned(&ipk_bytes) {
+        HandshakeResult(ServerHandshakeResultP::Reject { reason: "Account Deleted".into() })
+            .send(&mut tx)
+            .await
+            .err();
+        bail!("client({}) authenticated as a deleted account", conn.remote_address());
+    }
+
     // Advertise our DHT NodeId so the phone can sign welcome fetch/ack wrappers
     // bound to this home. `None` when DHT is disabled (those RPCs reply
     // DhtUnavailable).
@@ -121,13 +139,14 @@ pub(super) async fn handle_handshake(
 
 #[cfg(test)]
 mod tests {
+    use common::PROTOCOL_VERSION as V;
     use common::crypto::get_signing_key;
     use ed25519_dalek::Signer;
 
     use super::*;
 
     fn sign_nonce(key: &ed25519_dalek::SigningKey, nonce: &[u8; 32]) -> [u8; 64] {
-        key.sign(&client_auth_message(nonce)).to_bytes()
+        key.sign(&relay_auth_message(V, nonce)).to_bytes()
     }
 
     #[test]
@@ -135,15 +154,15 @@ mod tests {
         let key = get_signing_key();
         let nonce = [7u8; 32];
         let sig = sign_nonce(&key, &nonce);
-        assert!(verify_client_proof(&key.verifying_key(), &nonce, &sig));
+        assert!(verify_client_proof(&key.verifying_key(), V, &nonce, &sig));
     }
 
     #[test]
     fn rejects_garbage_signature() {
         let victim = get_signing_key().verifying_key();
         let nonce = [7u8; 32];
-        assert!(!verify_client_proof(&victim, &nonce, &[0u8; 64]));
-        assert!(!verify_client_proof(&victim, &nonce, &[0xffu8; 64]));
+        assert!(!verify_client_proof(&victim, V, &nonce, &[0u8; 64]));
+        assert!(!verify_client_proof(&victim, V, &nonce, &[0xffu8; 64]));
     }
 
     #[test]
@@ -152,21 +171,29 @@ mod tests {
         let victim = get_signing_key().verifying_key();
         let nonce = [7u8; 32];
         let sig = sign_nonce(&attacker, &nonce);
-        assert!(!verify_client_proof(&victim, &nonce, &sig));
+        assert!(!verify_client_proof(&victim, V, &nonce, &sig));
     }
 
     #[test]
     fn rejects_proof_for_a_different_nonce() {
         let key = get_signing_key();
         let sig = sign_nonce(&key, &[1u8; 32]);
-        assert!(!verify_client_proof(&key.verifying_key(), &[2u8; 32], &sig));
+        assert!(!verify_client_proof(&key.verifying_key(), V, &[2u8; 32], &sig));
+    }
+
+    #[test]
+    fn rejects_proof_signed_at_another_version() {
+        let key = get_signing_key();
+        let nonce = [7u8; 32];
+        let sig = key.sign(&relay_auth_message(V + 1, &nonce)).to_bytes();
+        assert!(!verify_client_proof(&key.verifying_key(), V, &nonce, &sig));
     }
 
     #[test]
     fn rejects_malformed_signature_length() {
         let key = get_signing_key();
         let nonce = [7u8; 32];
-        assert!(!verify_client_proof(&key.verifying_key(), &nonce, &[]));
-        assert!(!verify_client_proof(&key.verifying_key(), &nonce, &[0u8; 63]));
+        assert!(!verify_client_proof(&key.verifying_key(), V, &nonce, &[]));
+        assert!(!verify_client_proof(&key.verifying_key(), V, &nonce, &[0u8; 63]));
     }
 }
@@ -44,9 +44,29 @@ pub(crate) struct AckAuthPayload {
 const SUBSCRIBE_PRESENCE_PER_MIN: u32 = 6;
 const SET_PRESENCE_PER_MIN: u32 = 30;
 const REGISTER_PUSH_PER_MIN: u32 = 4;
+const SET_WAKE_POLICY_PER_MIN: u32 = 4;
+const PUBLISH_PUSH_KEY_PER_MIN: u32 = 4;
+/// Every open mailbox on connect, again on each presence renewal.
+const OPEN_MAILBOX_PER_MIN: u32 = 4 * common::crypto::mailbox::MAX_OPEN_MAILBOXES as u32;
+/// Senders cache a contact's push key, so a handful per hour is plenty.
+const FETCH_PUSH_KEY_PER_TARGET_PER_HOUR: u32 = 10;
+const BLOB_BEGIN_PER_MIN: u32 = 30;
+/// Chunk uploads and fetches together: ~5 MiB/s of 256 KiB chunks.
+const BLOB_CHUNKS_PER_MIN: u32 = 1200;
 /// Well below the home's `MAX_KP_FETCH_PER_HOUR`, which is keyed on the relay
 /// and would otherwise be spent by whichever co-tenant asks first.
 const FETCH_KEYPACKAGE_PER_TARGET_PER_HOUR: u32 = 10;
+/// A rotation is rare; the device re-sends its succession on a few connects.
+const PUBLISH_SUCCESSION_PER_HOUR: u32 = 4;
+/// Only a retry after a failed fan-out sends a second one.
+const DELETE_ACCOUNT_PER_HOUR: u32 = 4;
+/// One per retired key, which a deleting device sends all at once.
+const DELETE_PREDECESSOR_PER_HOUR: u32 = common::proto::dht_p2p::MAX_PREDECESSOR_CHAIN as u32;
+/// One per mailbox a contact may still address, for each account deletion.
+const DELETE_MAILBOX_PER_HOUR: u32 =
+    DELETE_ACCOUNT_PER_HOUR * common::crypto::mailbox::MAX_OPEN_MAILBOXES as u32;
+/// One per forgotten contact, for the IPK and each open mailbox.
+const REVOKE_TOKENS_PER_MIN: u32 = 4 * common::crypto::mailbox::MAX_OPEN_MAILBOXES as u32;
 
 type DirectLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
 type TargetLimiter = RateLimiter<[u8; 32], DefaultKeyedStateStore<[u8; 32]>, DefaultClock>;
@@ -57,7 +77,18 @@ pub(crate) struct ClientLimits {
     pub subscribe_presence: DirectLimiter,
     pub set_presence:       DirectLimiter,
     pub register_push:      DirectLimiter,
+    pub set_wake_policy:    DirectLimiter,
     pub fetch_keypackage:   TargetLimiter,
+    pub publish_push_key:   DirectLimiter,
+    pub fetch_push_key:     TargetLimiter,
+    pub blob_begin:         DirectLimiter,
+    pub blob_chunk:         DirectLimiter,
+    pub open_mailbox:       DirectLimiter,
+    pub publish_succession: DirectLimiter,
+    pub delete_account:     DirectLimiter,
+    pub delete_predecessor: DirectLimiter,
+    pub delete_mailbox:     DirectLimiter,
+    pub revoke_tokens:      DirectLimiter,
 }
 
 impl ClientLimits {
@@ -66,9 +97,20 @@ impl ClientLimits {
             subscribe_presence: RateLimiter::direct(per_minute(SUBSCRIBE_PRESENCE_PER_MIN)),
             set_presence:       RateLimiter::direct(per_minute(SET_PRESENCE_PER_MIN)),
             register_push:      RateLimiter::direct(per_minute(REGISTER_PUSH_PER_MIN)),
+            set_wake_policy:    RateLimiter::direct(per_minute(SET_WAKE_POLICY_PER_MIN)),
             fetch_keypackage:   RateLimiter::keyed(per_hour(
                 FETCH_KEYPACKAGE_PER_TARGET_PER_HOUR,
             )),
+            publish_push_key:   RateLimiter::direct(per_minute(PUBLISH_PUSH_KEY_PER_MIN)),
+            fetch_push_key:     RateLimiter::keyed(per_hour(FETCH_PUSH_KEY_PER_TARGET_PER_HOUR)),
+            blob_begin:         RateLimiter::direct(per_minute(BLOB_BEGIN_PER_MIN)),
+            blob_chunk:         RateLimiter::direct(per_minute(BLOB_CHUNKS_PER_MIN)),
+            open_mailbox:       RateLimiter::direct(per_minute(OPEN_MAILBOX_PER_MIN)),
+            publish_succession: RateLimiter::direct(per_hour(PUBLISH_SUCCESSION_PER_HOUR)),
+            delete_account:     RateLimiter::direct(per_hour(DELETE_ACCOUNT_PER_HOUR)),
+            delete_predecessor: RateLimiter::direct(per_hour(DELETE_PREDECESSOR_PER_HOUR)),
+            delete_mailbox:     RateLimiter::direct(per_hour(DELETE_MAILBOX_PER_HOUR)),
+            revoke_tokens:      RateLimiter::direct(per_minute(REVOKE_TOKENS_PER_MIN)),
         }
     }
 }
@@ -156,7 +198,18 @@ pub struct ClientContext {
     /// client has actually durably stored the messages (the `AckDrain`
     /// is the durability proof). Splitting the signing from the
     /// fetching mirrors the existing `DrainAuth` / `AckDrain` split.
-    pub pending_remote_drain: Mutex<Option<RemoteDrainState>>,
+    ///
+    /// One entry per queue the drain pulled from remote homes: the IPK's and
+    /// each open mailbox's. Each gets its own ack round, signed by the key
+    /// its queue is addressed to.
+    pub pending_remote_drain: Mutex<Vec<RemoteDrainState>>,
+
+    /// Mailbox ids this connection has opened, each with the `DrainAuth` its
+    /// `OpenMailbox` carried. Each id is also an alias for this connection
+    /// in the relay's client map. Capped at
+    /// [`common::crypto::mailbox::MAX_OPEN_MAILBOXES`]; see
+    /// `events::mailbox`.
+    pub open_mailboxes: Mutex<Vec<([u8; 32], DrainAuth)>>,
 }
 
 /// Buffered "messages just drained from remote homes" state. Lives on
@@ -165,9 +218,8 @@ pub struct ClientContext {
 /// union and fan a `QueueFetchAck` out to each home.
 #[derive(Clone, Debug)]
 pub(crate) struct RemoteDrainState {
-    /// User IPK whose queue was drained — same value as `ClientContext.ipk`,
-    /// duplicated here so the ack handler doesn't need to round-trip
-    /// through `PublicKey::as_bytes`. (Wire shape uses `[u8; 32]`.)
+    /// Key whose queue was drained: `ClientContext.ipk`, or one of its open
+    /// mailbox ids. The ack round asks the client to sign under it.
     pub user_ipk: [u8; 32],
     /// Per-home delivered-id map. Each entry's `Vec<[u8; 16]>` is the
     /// list of dispatch ids that home actually returned during the
@@ -224,7 +276,8 @@ impl Handler {
             pending_drain: Mutex::new(Vec::new()),
             drain_auth: Mutex::new(None),
             ack_auth: Mutex::new(None),
-            pending_remote_drain: Mutex::new(None),
+            pending_remote_drain: Mutex::new(Vec::new()),
+            open_mailboxes: Mutex::new(Vec::new()),
         });
 
         // only 16 concurrent streams can run at once per connection
@@ -273,6 +326,7 @@ impl Handler {
         // raced past our `accept_bi` failure would have already replaced
         // the entry; in that case we must leave it alone.
         let removed = remove_client_if_same(&relay, ipk.as_bytes(), &self.conn);
+        events::mailbox::close_all(&context);
 
         // Presence: record last-seen and notify mutual online contacts. Runs
         // after the eviction above so this IPK no longer reads as online.
@@ -1,4 +1,5 @@
 pub mod dialer;
 pub mod handler;
 pub mod acceptor;
-pub mod resolver_link;
\ No newline at end of file
+pub mod resolver_link;
+pub mod tunnel;
\ No newline at end of file
@@ -39,6 +39,7 @@ use common::proto::relay_res::LifetimeP;
 use common::proto::relay_res::ResolverPacket;
 use common::proto::relay_res::relay_heartbeat_signing_input;
 use common::proto::relay_res::relay_hello_signing_input;
+use common::proto::relay_res::relay_tunnel_signing_input;
 use common::quic::CloseReason;
 use common::quic::RESOLVER_RELAY_HEARTBEAT_INTERVAL;
 use common::quic::id::NodeId;
@@ -326,6 +327,33 @@ impl ResolverLink {
         Ok(())
     }
 
+    /// Tells the resolver which port the tunnel listener is on, so clients
+    /// behind UDP-blocking networks can find it. Sent after the ack: the
+    /// resolver only records it for a relay already registered on `conn`.
+    async fn advertise_tunnel(&self, conn: &Connection) -> Result<()> {
+        let relay_id = self.id();
+        let pubkey = self.relay.keys.public.to_bytes();
+        let timestamp = systime().as_millis();
+        let port = self.relay.cfg.tunnel.address.port();
+
+        let msg = relay_tunnel_signing_input(&relay_id, &pubkey, timestamp, port);
+        let sig = self.relay.keys.signing.sign(&msg).to_bytes();
+
+        let mut send = conn.open_uni().await?;
+        ResolverPacket::Lifetime(LifetimeP::RelayTunnel {
+            relay_id,
+            pubkey: Bytes(pubkey),
+            timestamp,
+            port,
+            sig: Bytes(sig),
+        })
+        .send(&mut send)
+        .await?;
+
+        send.finish()?;
+        Ok(())
+    }
+
     /// Sends a periodic, signed [`LifetimeP::RelayHeartbeat`] over a fresh
     /// uni-stream every [`RESOLVER_RELAY_HEARTBEAT_INTERVAL`] seconds.
     ///
@@ -408,6 +436,9 @@ impl ResolverLink {
                         conn.remote_address(),
                         resolver_time
                     );
+                    if self.relay.cfg.tunnel.enabled {
+                        self.advertise_tunnel(conn).await?;
+                    }
                 },
                 packet => {
                     debug!("recv packet {:?}", packet);
@@ -0,0 +1,127 @@
+//! TCP listener for clients whose network blocks UDP.
+//!
+//! Each accepted connection is TLS (the operator's CA-issued cert, an
+//! `http/1.1` ALPN) carrying either the length-framed tunnel or a WebSocket
+//! upgrade from a bridge; [`common::quic::tunnel`] has the wire details. The
+//! stream is attached to a [`StreamSocket`] behind a second quinn endpoint
+//! that serves `client/N` only, and that endpoint's connections go through
+//! the ordinary [`Acceptor`](crate::quic::acceptor::Acceptor) — so a
+//! tunneled client is rate-limited, authenticated and handled exactly like
+//! a UDP one.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use anyhow::Result;
+use common::debug;
+use common::info;
+use common::quic::config::build_server_cfg_with_alpn_split;
+use common::quic::config::build_tunnel_server_tls;
+use common::quic::protorole::ProtoRole;
+use common::quic::tunnel::Framing;
+use common::quic::tunnel::Sniffed;
+use common::quic::tunnel::StreamSocket;
+use common::quic::tunnel::sniff;
+use common::quic::tunnel::ws_server_handshake;
+use quinn::Endpoint;
+use quinn::EndpointConfig;
+use quinn::TokioRuntime;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+
+use crate::relay::Relay;
+
+/// Budget for TLS + sniff + WebSocket upgrade, before the stream is handed to
+/// quinn (which then applies its own handshake timeout).
+const STREAM_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ceiling on attached tunnel streams. Each is a TCP socket plus two tasks;
+/// the acceptor's connection cap only sees the ones that finish QUIC.
+const MAX_TUNNEL_STREAMS: usize = 4096;
+
+/// The bound listener plus the endpoint its streams feed.
+pub struct Tunnel {
+    pub endpoint: Endpoint,
+    listener: TcpListener,
+    socket: Arc<StreamSocket>,
+    tls: TlsAcceptor,
+}
+
+impl Tunnel {
+    pub async fn bind(relay: &Relay) -> Result<Self> {
+        let cfg = &relay.cfg;
+        let listener = TcpListener::bind(cfg.tunnel.address)
+            .await
+            .with_context(|| format!("binding the tunnel listener on {}", cfg.tunnel.address))?;
+        let local = listener.local_addr()?;
+
+        let tls = build_tunnel_server_tls(&cfg.network.cert_path, &cfg.network.key_path)?;
+        let server_cfg = build_server_cfg_with_alpn_split(
+            &cfg.network.cert_path,
+            &cfg.network.key_path,
+            relay.keys.signing.clone(),
+            &[ProtoRole::Client],
+        )?;
+
+        let socket = StreamSocket::new(local);
+        let endpoint = Endpoint::new_with_abstract_socket(
+            EndpointConfig::default(),
+            Some(server_cfg),
+            socket.clone(),
+            Arc::new(TokioRuntime),
+        )?;
+
+        info!("relay listening at TUNNEL({local})");
+        Ok(Self { endpoint, listener, socket, tls: TlsAcceptor::from(Arc::new(tls)) })
+    }
+
+    /// Accepts TCP until `cancel`. Streams already attached live on until
+    /// the endpoint is closed.
+    pub async fn serve(self, cancel: CancellationToken) {
+        loop {
+            let (tcp, peer) = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                accepted = self.listener.accept() => match accepted {
+                    Ok(a) => a,
+                    Err(e) => {
+                        debug!("tunnel accept failed: {e}");
+                        continue;
+                    },
+                },
+            };
+
+            if self.socket.streams() >= MAX_TUNNEL_STREAMS {
+                debug!("refusing tunnel from {peer}: at the {MAX_TUNNEL_STREAMS}-stream cap");
+                continue;
+            }
+            _ = tcp.set_nodelay(true);
+
+            let tls = self.tls.clone();
+            let socket = self.socket.clone();
+            tokio::spawn(async move {
+                let setup = async {
+                    let mut stream = tls.accept(tcp).await?;
+                    let framing = match sniff(&mut stream).await? {
+                        Some(Sniffed::Length) => Framing::Length,
+                        Some(Sniffed::WebSocket) => {
+                            ws_server_handshake(&mut stream).await?;
+                            Framing::WebSocket { mask: None }
+                        },
+                        None => return Ok(None),
+                    };
+                    Ok::<_, std::io::Error>(Some((stream, framing)))
+                };
+
+                match tokio::time::timeout(STREAM_SETUP_TIMEOUT, setup).await {
+                    Ok(Ok(Some((stream, framing)))) => socket.attach(peer, stream, framing),
+                    Ok(Ok(None)) => debug!("tunnel from {peer}: not a tunnel, closing"),
+                    Ok(Err(e)) => debug!("tunnel from {peer} failed setup: {e}"),
+                    Err(_) => debug!("tunnel from {peer} timed out in setup"),
+                }
+            });
+        }
+    }
+}
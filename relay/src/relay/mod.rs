@@ -5,6 +5,7 @@ use std::sync::Arc;
 use anyhow::Result;
 use common::graceful;
 use common::info;
+use common::node::capability::NodeCapabilities;
 use common::quic::config::build_client_cfg;
 use common::quic::config::build_server_cfg_with_alpn_split;
 use common::quic::config::load_root_ca;
@@ -115,6 +116,11 @@ pub struct Relay {
     /// point is to wake a device whose app is *not* connected. `Arc` so the
     /// DHT enqueue path (`dht/forward.rs`) sees the same map.
     pub push_pseudonyms: Arc<RwLock<HashMap<[u8; 32], [u8; 32]>>>,
+
+    /// Whether this relay's CA-issued cert carries `BLOB_STORE`. Read once at
+    /// startup; without it every blob upload is answered `BlobUnavailable`
+    /// and peers asking for a blob get nothing.
+    pub blob_store: bool,
 }
 
 impl Relay {
@@ -215,6 +221,12 @@ impl Relay {
         // Shared `IPK → P` map: the per-client handler writes it, the DHT
         // enqueue path reads it to wake offline recipients.
         let push_pseudonyms = Arc::new(RwLock::new(HashMap::new()));
+        let blob_store =
+            crate::dht::tls_extract::capabilities_from_pem_file(&cfg.network.cert_path)
+                .is_some_and(|caps| caps.contains(NodeCapabilities::BLOB_STORE));
+        if blob_store {
+            info!("blob store enabled (cert carries BLOB_STORE)");
+        }
 
         // DHT construction is gated on `cfg.dht.enabled`. When disabled,
         // the field stays `None` and every consumer falls through to
@@ -233,6 +245,7 @@ impl Relay {
                     d.attach_clients(clients.clone());
                     d.attach_presence_leases(presence_leases.clone());
                     d.attach_push(push_pseudonyms.clone());
+                    d.blob_store = blob_store;
                     // Wire the offline-wake path: the shared IPK→P map. The
                     // gateway list is filled from the resolver (see main.rs).
                     info!("DHT enabled (node_id = {node_id})");
@@ -265,6 +278,7 @@ impl Relay {
             presence_versions: RwLock::new(HashMap::new()),
             active_clients: RwLock::new(HashMap::new()),
             push_pseudonyms,
+            blob_store,
         }
     }
 }
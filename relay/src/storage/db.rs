@@ -5,6 +5,9 @@
 //! - `dht_queue`      home-replica offline queue (`MessageKey`, per-recipient prefix).
 //! - `dht_keypackage` MLS KeyPackage stash (per-IPK prefix).
 //! - `dht_welcome`    MLS Welcome stash (per-recipient prefix).
+//! - `blob_meta` / `blob_chunk` / `blob_owner` the `BLOB_STORE` blob store.
+//! - `dht_succession` identity successions held by the old key's homes.
+//! - `dht_tombstone`  deleted identities, refused any further writes.
 //!
 //! fjall does exact prefix scans natively, so no prefix-extractor config is
 //! needed (unlike RocksDB). Durability-critical writes go through
@@ -28,6 +31,8 @@ use std::time::UNIX_EPOCH;
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::bail;
+use common::proto::blob::BlobBeginOutcome;
+use common::proto::blob::BlobManifest;
 use fjall::Database;
 use fjall::Keyspace;
 use fjall::KeyspaceCreateOptions;
@@ -45,6 +50,14 @@ pub const KS_PRESENCE_STATE: &str = "presence_state";
 pub const KS_PRESENCE_LEASE: &str = "presence_lease";
 pub const KS_DHT_PUSH_PSEUDONYM: &str = "dht_push_pseudonym";
 pub const KS_DHT_PUSH_PENDING: &str = "dht_push_pending";
+pub const KS_DHT_WAKE_POLICY: &str = "dht_wake_policy";
+pub const KS_DHT_PUSH_KEY: &str = "dht_push_key";
+pub const KS_BLOB_META: &str = "blob_meta";
+pub const KS_BLOB_CHUNK: &str = "blob_chunk";
+pub const KS_BLOB_OWNER: &str = "blob_owner";
+pub const KS_DHT_SUCCESSION: &str = "dht_succession";
+pub const KS_DHT_TOMBSTONE: &str = "dht_tombstone";
+pub const KS_DHT_TOKEN_REVOCATION: &str = "dht_token_revocation";
 
 /// Mirrors `dht::config::PRESENCE_TTL_MS`; duplicated because the `ldb` lib
 /// target compiles `storage` without the DHT module.
@@ -92,6 +105,24 @@ pub struct Store {
     pub presence_lease:   Keyspace,
     pub push_pseudonym:   Keyspace,
     pub push_pending:     Keyspace,
+    /// IPK (32B) -> newest owner-signed `WakePolicyPublish`.
+    pub wake_policy:      Keyspace,
+    /// IPK (32B) -> newest owner-signed `PushKeyRecord`.
+    pub push_key:         Keyspace,
+    /// blob_id (32B) -> `expires_at(8) || uploader(32) || BlobManifest`.
+    pub blob_meta:        Keyspace,
+    /// `blob_id(32) || index_be(4)` -> `expires_at(8) || ciphertext`.
+    pub blob_chunk:       Keyspace,
+    /// `uploader(32) || blob_id(32)` -> `expires_at(8) || total_size(8)`, the
+    /// per-uploader index the quota is summed over.
+    pub blob_owner:       Keyspace,
+    /// Old IPK (32B) -> the `IdentitySuccession` that retired it, and
+    /// `old || new` (64B) -> any other that named a different heir.
+    pub succession:       Keyspace,
+    /// IPK (32B) -> the `AccountDeletion` that deleted it.
+    pub tombstone:        Keyspace,
+    /// IPK or mailbox id (32B) -> newest owner-signed `TokenRevocation`.
+    pub token_revocation: Keyspace,
     maintenance:          Arc<Maintenance>,
     worker:               Option<JoinHandle<()>>,
 }
@@ -146,6 +177,30 @@ impl Store {
         let push_pending = db
             .keyspace(KS_DHT_PUSH_PENDING, KeyspaceCreateOptions::default)
             .context("open `dht_push_pending`")?;
+        let wake_policy = db
+            .keyspace(KS_DHT_WAKE_POLICY, KeyspaceCreateOptions::default)
+            .context("open `dht_wake_policy`")?;
+        let push_key = db
+            .keyspace(KS_DHT_PUSH_KEY, KeyspaceCreateOptions::default)
+            .context("open `dht_push_key`")?;
+        let blob_meta = db
+            .keyspace(KS_BLOB_META, KeyspaceCreateOptions::default)
+            .context("open `blob_meta`")?;
+        let blob_chunk = db
+            .keyspace(KS_BLOB_CHUNK, KeyspaceCreateOptions::default)
+            .context("open `blob_chunk`")?;
+        let blob_owner = db
+            .keyspace(KS_BLOB_OWNER, KeyspaceCreateOptions::default)
+            .context("open `blob_owner`")?;
+        let succession = db
+            .keyspace(KS_DHT_SUCCESSION, KeyspaceCreateOptions::default)
+            .context("open `dht_succession`")?;
+        let tombstone = db
+            .keyspace(KS_DHT_TOMBSTONE, KeyspaceCreateOptions::default)
+            .context("open `dht_tombstone`")?;
+        let token_revocation = db
+            .keyspace(KS_DHT_TOKEN_REVOCATION, KeyspaceCreateOptions::default)
+            .context("open `dht_token_revocation`")?;
 
         let maintenance = Arc::new(Maintenance::default());
         let targets = vec![
@@ -156,6 +211,14 @@ impl Store {
             SweepTarget::new(&presence_state, presence_state_expired),
             SweepTarget::new(&presence_lease, presence_lease_expired),
             SweepTarget::new(&push_pseudonym, push_pseudonym_expired),
+            SweepTarget::new(&wake_policy, wake_policy_expired),
+            SweepTarget::new(&push_key, push_key_expired),
+            SweepTarget::new(&blob_meta, blob_expired),
+            SweepTarget::new(&blob_chunk, blob_expired),
+            SweepTarget::new(&blob_owner, blob_expired),
+            SweepTarget::new(&succession, succession_expired),
+            SweepTarget::new(&tombstone, tombstone_expired),
+            SweepTarget::new(&token_revocation, token_revocation_expired),
         ];
         let worker = std::thread::Builder::new()
             .name("pz-store-maint".into())
@@ -178,6 +241,14 @@ impl Store {
             presence_lease,
             push_pseudonym,
             push_pending,
+            wake_policy,
+            push_key,
+            blob_meta,
+            blob_chunk,
+            blob_owner,
+            succession,
+            tombstone,
+            token_revocation,
             maintenance,
             worker: Some(worker),
         })
@@ -201,6 +272,9 @@ impl Store {
     pub fn put_presence_consent(
         &self, consent: &common::proto::dht_p2p::PresenceConsent,
     ) -> fjall::Result<bool> {
+        if self.is_tombstoned(&consent.owner.0) {
+            return Ok(false);
+        }
         let mut key = [0u8; 64];
         key[..32].copy_from_slice(&consent.owner.0);
         key[32..].copy_from_slice(&consent.recipient.0);
@@ -297,6 +371,9 @@ impl Store {
         use common::proto::pack::Packer;
         use common::proto::pack::Unpacker;
 
+        if self.is_tombstoned(&lease.user.0) {
+            return Ok(false);
+        }
         if self.presence_lease.get(&lease.user.0)?.is_some_and(|v| {
             common::proto::dht_p2p::PresenceLease::deser(&v)
                 .ok()
@@ -322,6 +399,9 @@ impl Store {
     /// cannot reveal a platform token without the push gateway's database.
     /// Value layout: `pseudonym (32B) || refreshed_at_ms (u64 BE)`.
     pub fn put_push_pseudonym(&self, ipk: &[u8; 32], pseudonym: &[u8; 32]) -> fjall::Result<()> {
+        if self.is_tombstoned(ipk) {
+            return Ok(());
+        }
         let mut value = Vec::with_capacity(40);
         value.extend_from_slice(pseudonym);
         value.extend_from_slice(&now_ms().to_be_bytes());
@@ -338,6 +418,9 @@ impl Store {
     ) -> fjall::Result<()> {
         use common::proto::pack::Packer;
 
+        if self.is_tombstoned(&publish.user_ipk.0) {
+            return Ok(());
+        }
         let Ok(value) = publish.ser() else { return Ok(()) };
         self.put_sync(&self.push_pending, &publish.user_ipk.0, value)
     }
@@ -361,6 +444,340 @@ impl Store {
             .collect()
     }
 
+    /// Keep `publish` unless a policy with the same or a newer timestamp is
+    /// already stored, so a replayed older policy can't undo a newer one.
+    /// Returns whether it was stored.
+    pub fn put_wake_policy(
+        &self, publish: &common::proto::dht_p2p::WakePolicyPublish,
+    ) -> fjall::Result<bool> {
+        use common::proto::pack::Packer;
+
+        let stored = self.get_wake_policy(&publish.user_ipk.0);
+        if self.is_tombstoned(&publish.user_ipk.0)
+            || stored.is_some_and(|p| p.timestamp >= publish.timestamp)
+        {
+            return Ok(false);
+        }
+        let Ok(value) = publish.ser() else { return Ok(false) };
+        self.put_sync(&self.wake_policy, publish.user_ipk.0, value)?;
+        Ok(true)
+    }
+
+    pub fn get_wake_policy(
+        &self, ipk: &[u8; 32],
+    ) -> Option<common::proto::dht_p2p::WakePolicyPublish> {
+        use common::proto::pack::Unpacker;
+
+        let value = self.wake_policy.get(ipk).ok().flatten()?;
+        common::proto::dht_p2p::WakePolicyPublish::deser(&value).ok()
+    }
+
+    /// Keep `revocation` unless one with the same or a newer timestamp is
+    /// stored: each lists every token still withdrawn, so the newest is whole.
+    /// Returns whether it was stored.
+    pub fn put_token_revocation(
+        &self, revocation: &common::proto::dht_p2p::TokenRevocation,
+    ) -> fjall::Result<bool> {
+        use common::proto::pack::Packer;
+
+        let stored = self.get_token_revocation(&revocation.user_ipk.0);
+        if stored.is_some_and(|r| r.timestamp >= revocation.timestamp) {
+            return Ok(false);
+        }
+        let Ok(value) = revocation.ser() else { return Ok(false) };
+        self.put_sync(&self.token_revocation, revocation.user_ipk.0, value)?;
+        Ok(true)
+    }
+
+    pub fn get_token_revocation(
+        &self, ipk: &[u8; 32],
+    ) -> Option<common::proto::dht_p2p::TokenRevocation> {
+        use common::proto::pack::Unpacker;
+
+        let value = self.token_revocation.get(ipk).ok().flatten()?;
+        common::proto::dht_p2p::TokenRevocation::deser(&value).ok()
+    }
+
+    /// Whether `recipient` withdrew the token `id`.
+    pub fn is_token_revoked(&self, recipient: &[u8; 32], id: &[u8; 16]) -> bool {
+        self.get_token_revocation(recipient).is_some_and(|r| r.ids.iter().any(|i| &i.0 == id))
+    }
+
+    /// Keep `record` unless one with the same or a newer timestamp is stored.
+    /// Returns whether it was stored.
+    pub fn put_push_key(
+        &self, record: &common::proto::dht_p2p::PushKeyRecord,
+    ) -> fjall::Result<bool> {
+        use common::proto::pack::Packer;
+
+        let stored = self.get_push_key(&record.user_ipk.0);
+        if self.is_tombstoned(&record.user_ipk.0)
+            || stored.is_some_and(|r| r.timestamp >= record.timestamp)
+        {
+            return Ok(false);
+        }
+        let Ok(value) = record.ser() else { return Ok(false) };
+        self.put_sync(&self.push_key, record.user_ipk.0, value)?;
+        Ok(true)
+    }
+
+    pub fn get_push_key(&self, ipk: &[u8; 32]) -> Option<common::proto::dht_p2p::PushKeyRecord> {
+        use common::proto::pack::Unpacker;
+
+        let value = self.push_key.get(ipk).ok().flatten()?;
+        common::proto::dht_p2p::PushKeyRecord::deser(&value).ok()
+    }
+
+    /// Keep a succession for its old key. The first is stored under the old
+    /// key; a second naming another heir means two parties hold the old key,
+    /// and which is its owner is not ours to say, so it is kept beside the
+    /// first under `old || new` and the key is contested from then on: see
+    /// [`Self::get_succession`].
+    pub fn put_succession(
+        &self, succession: &common::proto::dht_p2p::IdentitySuccession,
+    ) -> fjall::Result<SuccessionPut> {
+        use common::proto::pack::Packer;
+
+        let (old, new) = (succession.old_ipk.0, succession.new_ipk.0);
+        let held = self.successions(&old);
+        if held.iter().any(|s| s.new_ipk.0 == new) {
+            return Ok(SuccessionPut::Known);
+        }
+        let Ok(value) = succession.ser() else { return Ok(SuccessionPut::Known) };
+        if held.is_empty() {
+            self.put_sync(&self.succession, old, value)?;
+            return Ok(SuccessionPut::Stored);
+        }
+        let mut key = old.to_vec();
+        key.extend_from_slice(&new);
+        self.put_sync(&self.succession, key, value)?;
+        Ok(SuccessionPut::Contested)
+    }
+
+    /// The heir `old_ipk` was retired for, while only one was ever named. A
+    /// contested key has none: nothing is handed on from it.
+    pub fn get_succession(
+        &self, old_ipk: &[u8; 32],
+    ) -> Option<common::proto::dht_p2p::IdentitySuccession> {
+        let mut held = self.successions(old_ipk);
+        if held.len() != 1 {
+            return None;
+        }
+        held.pop()
+    }
+
+    /// Every succession held for `old_ipk`, the first one first.
+    pub fn successions(
+        &self, old_ipk: &[u8; 32],
+    ) -> Vec<common::proto::dht_p2p::IdentitySuccession> {
+        use common::proto::pack::Unpacker;
+
+        self.succession
+            .prefix(old_ipk)
+            .filter_map(|guard| guard.into_inner().ok())
+            .filter_map(|(_, value)| {
+                common::proto::dht_p2p::IdentitySuccession::deser(&value).ok()
+            })
+            .collect()
+    }
+
+    /// Keep `deletion` as the tombstone for `ipk`: the key it deletes, or one
+    /// that key succeeded. The first one stands: a key is deleted once.
+    /// Returns whether it was stored.
+    pub fn put_tombstone(
+        &self, ipk: &[u8; 32], deletion: &common::proto::dht_p2p::AccountDeletion,
+    ) -> fjall::Result<bool> {
+        use common::proto::pack::Packer;
+
+        if self.tombstone.contains_key(ipk)? {
+            return Ok(false);
+        }
+        let Ok(value) = deletion.ser() else { return Ok(false) };
+        self.put_sync(&self.tombstone, ipk, value)?;
+        Ok(true)
+    }
+
+    /// Whether `ipk` was deleted. Writes under a deleted key are refused.
+    pub fn is_tombstoned(&self, ipk: &[u8; 32]) -> bool {
+        self.tombstone.contains_key(ipk).unwrap_or(false)
+    }
+
+    /// Drop every row held under `ipk` (an IPK or a mailbox id), returning
+    /// how many went: its queues, presence consents and last-seen, push and
+    /// wake records, the succession that retired it, the tokens it revoked
+    /// and the blobs it uploaded. The KeyPackage and Welcome stashes are keyed by their own
+    /// digests of the IPK, so the DHT wipes those with
+    /// [`Self::remove_prefix`]. Rows under other users' keys that merely name
+    /// `ipk` (their view of its presence) expire with their lease.
+    pub fn wipe_identity(&self, ipk: &[u8; 32]) -> fjall::Result<usize> {
+        let mut keys = Vec::new();
+        for ks in [
+            &self.messages,
+            &self.queue,
+            &self.last_seen,
+            &self.presence_consent,
+            &self.presence_state,
+            &self.presence_lease,
+            &self.push_pseudonym,
+            &self.push_pending,
+            &self.wake_policy,
+            &self.push_key,
+            &self.blob_owner,
+            &self.succession,
+            &self.token_revocation,
+        ] {
+            for guard in ks.prefix(ipk) {
+                keys.push((ks, guard.key()?));
+            }
+        }
+        for guard in self.blob_owner.prefix(ipk) {
+            let Ok(blob_id) = <[u8; 32]>::try_from(&guard.key()?[32..]) else { continue };
+            // A blob someone else uploaded first is theirs to keep.
+            let owned = self
+                .blob_meta
+                .get(blob_id)?
+                .is_some_and(|v| v.get(8..40).is_some_and(|uploader| uploader == ipk));
+            if !owned {
+                continue;
+            }
+            keys.push((&self.blob_meta, blob_id.into()));
+            for guard in self.blob_chunk.prefix(blob_id) {
+                keys.push((&self.blob_chunk, guard.key()?));
+            }
+        }
+        let mut batch = self.db.batch();
+        for (ks, key) in &keys {
+            batch.remove(ks, key.clone());
+        }
+        batch.commit()?;
+        self.request_persist();
+        Ok(keys.len())
+    }
+
+    /// Delete every row of `ks` under `prefix`, returning how many went.
+    pub fn remove_prefix(&self, ks: &Keyspace, prefix: &[u8]) -> fjall::Result<usize> {
+        let keys = ks.prefix(prefix).map(|guard| guard.key()).collect::<fjall::Result<Vec<_>>>()?;
+        let mut batch = self.db.batch();
+        for key in &keys {
+            batch.remove(ks, key.clone());
+        }
+        batch.commit()?;
+        self.request_persist();
+        Ok(keys.len())
+    }
+
+    /// Open (or resume) an upload of `manifest` for `uploader`. A blob already
+    /// held is shared, not re-charged: it answers with the chunks still
+    /// missing and keeps its original expiry. A new blob is charged its full
+    /// `total_size` up front, so a half-finished upload still counts, both to
+    /// `uploader` and to the relay-wide total.
+    pub fn blob_begin(
+        &self, uploader: &[u8; 32], manifest: &BlobManifest, expires_at: u64, quota: BlobQuota,
+        now_ms: u64,
+    ) -> fjall::Result<BlobBeginOutcome> {
+        use common::proto::pack::Packer;
+
+        let blob_id = manifest.blob_id();
+        if let Some((held_until, _, held)) = self.blob_meta_get(&blob_id, now_ms) {
+            return Ok(BlobBeginOutcome::Accepted {
+                missing:    self.blob_missing(&blob_id, &held),
+                expires_at: held_until,
+            });
+        }
+        if self.blob_usage(uploader, now_ms).saturating_add(manifest.total_size)
+            > quota.per_uploader
+            || self.blob_usage_total(now_ms).saturating_add(manifest.total_size) > quota.total
+        {
+            return Ok(BlobBeginOutcome::QuotaExceeded);
+        }
+        let Ok(encoded) = manifest.ser() else { return Ok(BlobBeginOutcome::Rejected) };
+        let mut meta = expires_at.to_be_bytes().to_vec();
+        meta.extend_from_slice(uploader);
+        meta.extend_from_slice(&encoded);
+        let mut owner_key = uploader.to_vec();
+        owner_key.extend_from_slice(&blob_id);
+        let mut owner = expires_at.to_be_bytes().to_vec();
+        owner.extend_from_slice(&manifest.total_size.to_be_bytes());
+
+        let mut batch = self.db.batch();
+        batch.insert(&self.blob_meta, blob_id, meta);
+        batch.insert(&self.blob_owner, owner_key, owner);
+        batch.commit()?;
+        self.request_persist();
+        Ok(BlobBeginOutcome::Accepted {
+            missing: (0..manifest.chunks.len() as u32).collect(),
+            expires_at,
+        })
+    }
+
+    /// Store chunk `index` of a live blob if it matches the manifest hash.
+    /// Returns whether it was stored.
+    pub fn blob_put(
+        &self, blob_id: &[u8; 32], index: u32, data: &[u8], now_ms: u64,
+    ) -> fjall::Result<bool> {
+        let Some((expires_at, _, manifest)) = self.blob_meta_get(blob_id, now_ms) else {
+            return Ok(false);
+        };
+        if !manifest.verifies(index, data) {
+            return Ok(false);
+        }
+        let mut value = expires_at.to_be_bytes().to_vec();
+        value.extend_from_slice(data);
+        self.put_sync(&self.blob_chunk, blob_chunk_key(blob_id, index), value)?;
+        Ok(true)
+    }
+
+    pub fn blob_manifest(&self, blob_id: &[u8; 32], now_ms: u64) -> Option<BlobManifest> {
+        self.blob_meta_get(blob_id, now_ms).map(|(_, _, manifest)| manifest)
+    }
+
+    pub fn blob_chunk(&self, blob_id: &[u8; 32], index: u32, now_ms: u64) -> Option<Vec<u8>> {
+        let value = self.blob_chunk.get(blob_chunk_key(blob_id, index)).ok().flatten()?;
+        be_u64(&value, 0).filter(|&expires_at| now_ms < expires_at)?;
+        Some(value[8..].to_vec())
+    }
+
+    /// Bytes charged to `uploader` by blobs that haven't expired.
+    pub fn blob_usage(&self, uploader: &[u8; 32], now_ms: u64) -> u64 {
+        self.blob_owner
+            .prefix(uploader)
+            .filter_map(|guard| guard.value().ok())
+            .filter(|v| be_u64(v, 0).is_some_and(|expires_at| now_ms < expires_at))
+            .filter_map(|v| be_u64(&v, 8))
+            .fold(0u64, u64::saturating_add)
+    }
+
+    /// Bytes charged to every uploader by blobs that haven't expired. A shared
+    /// blob is charged once, so this is what the store holds.
+    pub fn blob_usage_total(&self, now_ms: u64) -> u64 {
+        self.blob_owner
+            .iter()
+            .filter_map(|guard| guard.value().ok())
+            .filter(|v| be_u64(v, 0).is_some_and(|expires_at| now_ms < expires_at))
+            .filter_map(|v| be_u64(&v, 8))
+            .fold(0u64, u64::saturating_add)
+    }
+
+    /// `(expires_at, uploader, manifest)` of a blob that hasn't expired. The
+    /// sweep reaps expired rows lazily, so reads check the deadline.
+    fn blob_meta_get(
+        &self, blob_id: &[u8; 32], now_ms: u64,
+    ) -> Option<(u64, [u8; 32], BlobManifest)> {
+        use common::proto::pack::Unpacker;
+
+        let value = self.blob_meta.get(blob_id).ok().flatten()?;
+        let expires_at = be_u64(&value, 0).filter(|&t| now_ms < t)?;
+        let uploader = value.get(8..40)?.try_into().ok()?;
+        let manifest = BlobManifest::deser(value.get(40..)?).ok()?;
+        Some((expires_at, uploader, manifest))
+    }
+
+    fn blob_missing(&self, blob_id: &[u8; 32], manifest: &BlobManifest) -> Vec<u32> {
+        (0..manifest.chunks.len() as u32)
+            .filter(|&i| !self.blob_chunk.contains_key(blob_chunk_key(blob_id, i)).unwrap_or(false))
+            .collect()
+    }
+
     /// Insert, then hand the journal fsync to the maintenance thread, which
     /// coalesces concurrent requests into one `SyncAll`. The value is in the
     /// journal buffer on return; the group commit closes the machine-crash
@@ -414,6 +831,14 @@ impl Store {
             &self.presence_lease,
             &self.push_pseudonym,
             &self.push_pending,
+            &self.wake_policy,
+            &self.push_key,
+            &self.blob_meta,
+            &self.blob_chunk,
+            &self.blob_owner,
+            &self.succession,
+            &self.tombstone,
+            &self.token_revocation,
         ] {
             n += ks.len().context("count keyspace")?;
             ks.clear().context("clear keyspace")?;
@@ -619,6 +1044,84 @@ fn push_pseudonym_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
         .is_some_and(|refreshed_at| now_ms.saturating_sub(refreshed_at) > IDLE_IDENTITY_TTL_MS)
 }
 
+/// A policy the owner hasn't re-signed in the idle TTL goes with the pseudonym.
+fn wake_policy_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    use common::proto::pack::Unpacker;
+
+    common::proto::dht_p2p::WakePolicyPublish::deser(value)
+        .ok()
+        .is_none_or(|p| now_ms.saturating_sub(p.timestamp) > IDLE_IDENTITY_TTL_MS)
+}
+
+/// A revocation outlives every token it lists, and no more.
+fn token_revocation_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    use common::proto::pack::Unpacker;
+
+    common::proto::dht_p2p::TokenRevocation::deser(value)
+        .ok()
+        .is_none_or(|r| r.expires_at_ms <= now_ms)
+}
+
+/// Same idle TTL: the device re-publishes its push key on every connect.
+fn push_key_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    use common::proto::pack::Unpacker;
+
+    common::proto::dht_p2p::PushKeyRecord::deser(value)
+        .ok()
+        .is_none_or(|r| now_ms.saturating_sub(r.timestamp) > IDLE_IDENTITY_TTL_MS)
+}
+
+/// Kept as long as an idle identity's records: a contact who missed the
+/// succession for that long has stopped writing to the old key anyway.
+fn succession_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    use common::proto::pack::Unpacker;
+
+    common::proto::dht_p2p::IdentitySuccession::deser(value)
+        .ok()
+        .is_none_or(|s| now_ms.saturating_sub(s.timestamp) > IDLE_IDENTITY_TTL_MS)
+}
+
+/// Kept as long as an idle identity's records: by then anything a replica
+/// held under the key and missed the wipe of has expired on its own.
+fn tombstone_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    use common::proto::pack::Unpacker;
+
+    common::proto::dht_p2p::AccountDeletion::deser(value)
+        .ok()
+        .is_none_or(|d| now_ms.saturating_sub(d.timestamp) > IDLE_IDENTITY_TTL_MS)
+}
+
+/// Every blob row leads with its deadline.
+fn blob_expired(_key: &[u8], value: &[u8], now_ms: u64) -> bool {
+    be_u64(value, 0).is_none_or(|expires_at| now_ms >= expires_at)
+}
+
+/// Byte caps on live blobs: what one uploader may hold, and what the whole
+/// store may.
+#[derive(Clone, Copy, Debug)]
+pub struct BlobQuota {
+    pub per_uploader: u64,
+    pub total:        u64,
+}
+
+/// What [`Store::put_succession`] made of a statement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuccessionPut {
+    /// The first heir named for its old key.
+    Stored,
+    /// Already held, or unencodable.
+    Known,
+    /// Another heir than one already held: the old key is contested.
+    Contested,
+}
+
+fn blob_chunk_key(blob_id: &[u8; 32], index: u32) -> [u8; 36] {
+    let mut key = [0u8; 36];
+    key[..32].copy_from_slice(blob_id);
+    key[32..].copy_from_slice(&index.to_be_bytes());
+    key
+}
+
 fn be_u64(value: &[u8], offset: usize) -> Option<u64> {
     value.get(offset..offset + 8).and_then(|b| b.try_into().ok()).map(u64::from_be_bytes)
 }
@@ -632,6 +1135,7 @@ mod tests {
     use std::sync::atomic::AtomicU64;
     use std::sync::atomic::Ordering;
 
+    use common::proto::blob::chunk_hash;
     use common::proto::client_rel::PresenceState;
     use common::proto::dht_p2p::PresenceConsent;
 
@@ -706,6 +1210,26 @@ mod tests {
         assert_eq!(store.get_push_pseudonym(&ipk), Some([9u8; 32]));
     }
 
+    #[test]
+    fn wake_policy_keeps_the_newest() {
+        use common::proto::dht_p2p::WakePolicy;
+        use common::proto::dht_p2p::WakePolicyPublish;
+
+        let store = fresh_store();
+        let publish = |timestamp, mute_all| WakePolicyPublish {
+            user_ipk: [3u8; 32].into(),
+            policy: WakePolicy { mute_all, ..Default::default() },
+            timestamp,
+            user_sig: [0u8; 64].into(),
+        };
+        assert!(store.put_wake_policy(&publish(10, true)).unwrap());
+        assert!(!store.put_wake_policy(&publish(9, false)).unwrap());
+        assert!(!store.put_wake_policy(&publish(10, false)).unwrap());
+        assert!(store.get_wake_policy(&[3u8; 32]).unwrap().policy.mute_all);
+        assert!(store.put_wake_policy(&publish(11, false)).unwrap());
+        assert!(!store.get_wake_policy(&[3u8; 32]).unwrap().policy.mute_all);
+    }
+
     #[test]
     fn presence_consent_rejects_replayed_version() {
         let store = fresh_store();
@@ -802,6 +1326,134 @@ mod tests {
         );
     }
 
+    #[test]
+    fn blob_begin_charges_quota_once_and_put_verifies() {
+        let store = fresh_store();
+        let (alice, bob) = ([1u8; 32], [2u8; 32]);
+        let (a, b) = ([5u8; 4], [6u8; 2]);
+        let manifest = BlobManifest {
+            total_size: 6,
+            chunk_size: 4,
+            chunks:     vec![chunk_hash(&a), chunk_hash(&b)],
+        };
+        let id = manifest.blob_id();
+        let (now, until) = (1_000, 5_000);
+
+        let accepted = store.blob_begin(&alice, &manifest, until, quota(6), now).unwrap();
+        assert_eq!(accepted, BlobBeginOutcome::Accepted { missing: vec![0, 1], expires_at: until });
+        assert!(!store.blob_put(&id, 0, &b, now).unwrap(), "wrong chunk is refused");
+        assert!(store.blob_put(&id, 0, &a, now).unwrap());
+
+        let resumed = store.blob_begin(&bob, &manifest, until + 1, quota(0), now).unwrap();
+        assert_eq!(
+            resumed,
+            BlobBeginOutcome::Accepted { missing: vec![1], expires_at: until },
+            "a held blob is shared, not re-charged or extended"
+        );
+        assert_eq!(store.blob_usage(&bob, now), 0);
+        assert_eq!(store.blob_usage(&alice, now), 6);
+
+        let mut other = manifest.clone();
+        other.chunks.swap(0, 1);
+        assert_eq!(
+            store.blob_begin(&alice, &other, until, quota(6), now).unwrap(),
+            BlobBeginOutcome::QuotaExceeded
+        );
+
+        assert_eq!(store.blob_chunk(&id, 0, now), Some(a.to_vec()));
+        assert_eq!(store.blob_chunk(&id, 0, until), None, "expired chunks are not served");
+        assert_eq!(store.blob_manifest(&id, until), None);
+        assert_eq!(store.blob_usage(&alice, until), 0);
+    }
+
+    fn quota(per_uploader: u64) -> BlobQuota {
+        BlobQuota { per_uploader, total: u64::MAX }
+    }
+
+    #[test]
+    fn blob_begin_stops_at_the_relay_wide_total() {
+        let store = fresh_store();
+        let manifest =
+            BlobManifest { total_size: 4, chunk_size: 4, chunks: vec![chunk_hash(&[5u8; 4])] };
+        let other =
+            BlobManifest { total_size: 4, chunk_size: 4, chunks: vec![chunk_hash(&[6u8; 4])] };
+        let cap = BlobQuota { per_uploader: 100, total: 6 };
+        let (now, until) = (1_000, 5_000);
+
+        assert!(matches!(
+            store.blob_begin(&[1u8; 32], &manifest, until, cap, now).unwrap(),
+            BlobBeginOutcome::Accepted { .. }
+        ));
+        assert_eq!(
+            store.blob_begin(&[2u8; 32], &other, until, cap, now).unwrap(),
+            BlobBeginOutcome::QuotaExceeded,
+            "another uploader is still under its own quota"
+        );
+        assert!(
+            matches!(
+                store.blob_begin(&[2u8; 32], &other, 2 * until, cap, until).unwrap(),
+                BlobBeginOutcome::Accepted { .. }
+            ),
+            "expired blobs free the total"
+        );
+        assert_eq!(store.blob_usage_total(until), 4);
+    }
+
+    #[test]
+    fn wiping_an_identity_leaves_others_and_the_tombstone_refuses_it() {
+        let store = fresh_store();
+        let (alice, bob) = ([1u8; 32], [2u8; 32]);
+        let manifest =
+            BlobManifest { total_size: 4, chunk_size: 4, chunks: vec![chunk_hash(&[5u8; 4])] };
+        let now = 1_000;
+        for ipk in [alice, bob] {
+            store.put_last_seen(&ipk, now).unwrap();
+            store.put_push_pseudonym(&ipk, &[9u8; 32]).unwrap();
+        }
+        store.blob_begin(&alice, &manifest, 5_000, quota(4), now).unwrap();
+        store.blob_put(&manifest.blob_id(), 0, &[5u8; 4], now).unwrap();
+
+        assert_eq!(store.wipe_identity(&alice).unwrap(), 5);
+        assert_eq!(store.get_last_seen(&alice), None);
+        assert_eq!(store.get_push_pseudonym(&alice), None);
+        assert_eq!(store.blob_manifest(&manifest.blob_id(), now), None);
+        assert_eq!(store.get_last_seen(&bob), Some(now));
+        assert_eq!(store.get_push_pseudonym(&bob), Some([9u8; 32]));
+
+        let deletion = common::proto::dht_p2p::AccountDeletion {
+            ipk:       alice.into(),
+            timestamp: now,
+            sig:       [0u8; 64].into(),
+        };
+        assert!(store.put_tombstone(&deletion.ipk.0, &deletion).unwrap());
+        assert!(!store.put_tombstone(&deletion.ipk.0, &deletion).unwrap());
+        store.put_push_pseudonym(&alice, &[9u8; 32]).unwrap();
+        assert_eq!(store.get_push_pseudonym(&alice), None, "a deleted key is not written again");
+    }
+
+    #[test]
+    fn a_second_heir_contests_the_key_and_a_wipe_takes_both() {
+        let store = fresh_store();
+        let heir = |new: u8| common::proto::dht_p2p::IdentitySuccession {
+            old_ipk:   [1u8; 32].into(),
+            new_ipk:   [new; 32].into(),
+            timestamp: 1_000,
+            old_sig:   [0u8; 64].into(),
+            new_sig:   [0u8; 64].into(),
+        };
+        assert_eq!(store.put_succession(&heir(2)).unwrap(), SuccessionPut::Stored);
+        assert_eq!(store.put_succession(&heir(2)).unwrap(), SuccessionPut::Known);
+        assert_eq!(store.get_succession(&[1u8; 32]), Some(heir(2)));
+
+        assert_eq!(store.put_succession(&heir(3)).unwrap(), SuccessionPut::Contested);
+        assert_eq!(store.put_succession(&heir(3)).unwrap(), SuccessionPut::Known);
+        assert_eq!(store.get_succession(&[1u8; 32]), None, "no heir while two are named");
+        assert_eq!(store.successions(&[1u8; 32]), vec![heir(2), heir(3)]);
+
+        assert_eq!(store.wipe_identity(&[1u8; 32]).unwrap(), 2);
+        assert!(store.successions(&[1u8; 32]).is_empty());
+    }
+
     #[test]
     fn sweep_removes_only_expired_rows() {
         let store = fresh_store();
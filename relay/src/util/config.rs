@@ -1,14 +1,18 @@
 use std::fs;
 use std::io::Write;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
 
+use common::node::config::DEFAULT_TUNNEL_PORT;
 use common::node::config::NetworkConfig;
 use common::node::config::NodeConfig;
 use serde::Deserialize;
 
 use crate::dht::DhtConfig;
+use crate::storage::db::BlobQuota;
 
 fn default_control_socket() -> PathBuf {
     // Deployed sets this explicitly (packaged relay.toml → /run/pzrelay via the
@@ -41,9 +45,58 @@ pub struct AppConfig {
     #[serde(default)]
     pub assist: AssistConfig,
 
+    /// Optional QUIC-over-TCP listener for UDP-blocked clients. Default
+    /// **disabled**.
+    #[serde(default)]
+    pub tunnel: TunnelConfig,
+
     /// Optional logging block. Absent → info. `PZ_LOG` env overrides.
     #[serde(default)]
     pub log: LogConfig,
+
+    /// Blob-store limits. Only read when this relay's cert carries
+    /// `BLOB_STORE`; the CA stamp, not this block, turns the store on.
+    #[serde(default)]
+    pub blob: BlobConfig,
+}
+
+/// Bytes of live blobs one uploader IPK may hold here.
+pub const BLOB_QUOTA_PER_UPLOADER: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Bytes of live blobs this relay holds for everyone together.
+pub const BLOB_QUOTA_TOTAL: u64 = 64 * 1024 * 1024 * 1024;
+
+/// Longest a blob is kept, whatever TTL the uploader asks for.
+pub const BLOB_MAX_TTL_SECS: u64 = 14 * 24 * 60 * 60;
+
+/// Blob store for offline attachments (see `quic::handler::client::events::blob`).
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BlobConfig {
+    /// Override of [`BLOB_QUOTA_PER_UPLOADER`], in bytes.
+    #[serde(default)]
+    pub quota_per_uploader: Option<u64>,
+
+    /// Override of [`BLOB_QUOTA_TOTAL`], in bytes.
+    #[serde(default)]
+    pub quota_total: Option<u64>,
+
+    /// Override of [`BLOB_MAX_TTL_SECS`].
+    #[serde(default)]
+    pub max_ttl_secs: Option<u64>,
+}
+
+impl BlobConfig {
+    pub fn quota(&self) -> BlobQuota {
+        BlobQuota {
+            per_uploader: self.quota_per_uploader.unwrap_or(BLOB_QUOTA_PER_UPLOADER),
+            total:        self.quota_total.unwrap_or(BLOB_QUOTA_TOTAL),
+        }
+    }
+
+    pub fn max_ttl_secs(&self) -> u64 {
+        self.max_ttl_secs.unwrap_or(BLOB_MAX_TTL_SECS)
+    }
 }
 
 /// STUN echo + TURN bridge on the QUIC port (see [`crate::stunturn`]).
@@ -56,6 +109,31 @@ pub struct AssistConfig {
     pub enabled: bool,
 }
 
+fn default_tunnel_address() -> SocketAddr {
+    (Ipv6Addr::UNSPECIFIED, DEFAULT_TUNNEL_PORT).into()
+}
+
+/// TCP listener carrying client QUIC over TLS or WebSocket (see
+/// [`crate::quic::tunnel`]), for networks that block UDP.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TunnelConfig {
+    /// Off by default: it opens a TCP port, and binding 443 needs the
+    /// capability or a port redirect.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// TCP bind. Default `[::]:443`, the only port some networks pass.
+    #[serde(default = "default_tunnel_address")]
+    pub address: SocketAddr,
+}
+
+impl Default for TunnelConfig {
+    fn default() -> Self {
+        Self { enabled: false, address: default_tunnel_address() }
+    }
+}
+
 #[derive(Deserialize, Debug, Default)]
 pub struct LogConfig {
     /// trace|debug|info|warn|error. `PZ_LOG` env overrides. Default: info.
@@ -40,7 +40,7 @@ impl HandleRelay for Handler {
 }
 
 /// Uni-stream lifecycle loop: authenticated `RelayHello` / `RelayHeartbeat`
-/// packets that keep the relay in the registry.
+/// packets that keep the relay in the registry, and `RelayTunnel`.
 async fn lifecycle_loop(conn: Arc<Connection>, resolver: ResolverRef) {
     let addr = conn.remote_address();
     let session = Arc::new(Session::default());
@@ -180,6 +180,13 @@ async fn handle_lifetime(
             // so the auth path can't regress quietly.
             Ok(())
         },
+        ref tunnel @ RelayTunnel { .. } => {
+            if let Err(close) = resolver.set_tunnel(&conn, tunnel) {
+                close.close(&conn);
+                return Err(PacketError::PolicyClose);
+            }
+            Ok(())
+        },
         GatewayHello { gateway_id, pubkey, timestamp, sig } => {
             let claim = match session.claim(gateway_id) {
                 Claim::Conflict => {
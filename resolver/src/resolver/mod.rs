@@ -12,6 +12,7 @@ use common::proto::relay_res::LifetimeP;
 use common::proto::relay_res::gateway_hello_signing_input;
 use common::proto::relay_res::relay_heartbeat_signing_input;
 use common::proto::relay_res::relay_hello_signing_input;
+use common::proto::relay_res::relay_tunnel_signing_input;
 use common::quic::CloseReason;
 use common::quic::config::build_server_cfg;
 use common::quic::config::setup_crypto_provider;
@@ -21,6 +22,7 @@ use common::quic::p256::secret_from_key;
 use common::quic::protorole::ProtoRole;
 use common::warn;
 use ed25519_dalek::Signature;
+use ed25519_dalek::SigningKey;
 use ed25519_dalek::VerifyingKey;
 use parking_lot::RwLock;
 use quinn::Connection;
@@ -68,6 +70,12 @@ pub struct Resolver {
     /// gossip layer actually consumes it.
     #[allow(dead_code)]
     pub key: NodeKey,
+    /// Secret half of [`Self::key`]. Signs the [`RelayDirectory`] snapshots
+    /// handed out by `GetDirectory`, which clients verify against the
+    /// resolver keys in their seed list.
+    ///
+    /// [`RelayDirectory`]: common::proto::client_res::RelayDirectory
+    signer: SigningKey,
     /// Held for the same reason as [`Self::key`] — the gossip layer will
     /// need access to peer-resolver seed addresses, TLS roots, etc.
     #[allow(dead_code)]
@@ -87,8 +95,18 @@ pub struct Resolver {
     /// `GetRelays` response cached in [`rpc`].
     relays_generation: AtomicU64,
     relays_response: RwLock<Option<(u64, Arc<Vec<u8>>)>>,
+    /// Packed `GetTunnels` response, cached against the same generation
+    /// (a tunnel port change bumps it too).
+    tunnels_response: RwLock<Option<(u64, Arc<Vec<u8>>)>>,
+    /// Packed `GetDirectory` response: the generation it was signed at, when
+    /// it was signed, and the frame. Re-signed on a membership change or
+    /// once it ages past `DIRECTORY_RESIGN_INTERVAL`.
+    directory_response: RwLock<Option<SignedDirectory>>,
 }
 
+/// `(generation, signed_at, packed frame)` of the cached `GetDirectory` reply.
+type SignedDirectory = (u64, Instant, Arc<Vec<u8>>);
+
 impl Resolver {
     fn get_server_cfg(cfg: &AppConfig) -> Result<ServerConfig> {
         setup_crypto_provider()?;
@@ -100,7 +118,7 @@ impl Resolver {
         )
     }
 
-    fn key(cfg: &AppConfig) -> NodeKey {
+    fn key(cfg: &AppConfig) -> (NodeKey, SigningKey) {
         // `secret_from_key` returns `Result<_, ()>` and logs its own
         // detailed reason on the error path, so we just convert `()` into
         // a placeholder string for `graceful!`'s log line.
@@ -109,7 +127,8 @@ impl Resolver {
             "loading the resolver key"
         );
 
-        graceful!(NodeKey::new(secret.verifying_key()), "deriving the resolver node id")
+        let key = graceful!(NodeKey::new(secret.verifying_key()), "deriving the resolver node id");
+        (key, secret)
     }
 
     fn endpoint(cfg: &AppConfig) -> Endpoint {
@@ -127,17 +146,20 @@ impl Resolver {
     }
 
     pub fn new(cfg: AppConfig) -> Self {
-        let key = Self::key(&cfg);
+        let (key, signer) = Self::key(&cfg);
 
         info!("initializing resolver with IPK({})", key.key());
 
         Self {
             key,
+            signer,
             endpoint: Arc::new(Self::endpoint(&cfg)),
             relays: RwLock::new(HashMap::new()),
             gateways: RwLock::new(HashMap::new()),
             relays_generation: AtomicU64::new(0),
             relays_response: RwLock::new(None),
+            tunnels_response: RwLock::new(None),
+            directory_response: RwLock::new(None),
             cfg,
         }
     }
@@ -324,6 +346,46 @@ impl Resolver {
         Ok(())
     }
 
+    /// Authenticate an inbound [`LifetimeP::RelayTunnel`] and record its
+    /// port. Same checks as [`Self::verify_heartbeat`], including that
+    /// `relay_id` is registered on this very connection.
+    pub fn set_tunnel(
+        &self, conn: &Arc<Connection>, packet: &LifetimeP,
+    ) -> Result<(), CloseReason> {
+        let LifetimeP::RelayTunnel { relay_id, pubkey, timestamp, port, sig } = packet else {
+            return Err(CloseReason::PacketMismatch);
+        };
+        let (relay_id, timestamp) = (*relay_id, *timestamp);
+
+        let msg = relay_tunnel_signing_input(&relay_id, &pubkey.0, timestamp, *port);
+        verify_signed_packet(
+            conn.remote_address(),
+            "tunnel",
+            &relay_id,
+            &pubkey.0,
+            &sig.0,
+            &msg,
+            timestamp,
+        )?;
+
+        match self.relays.read().get(&relay_id) {
+            Some(entry) if Arc::ptr_eq(&entry.conn, conn) => {
+                if entry.set_tunnel_port(*port) {
+                    self.relays_generation.fetch_add(1, Ordering::Release);
+                }
+                Ok(())
+            },
+            _ => {
+                warn!(
+                    "relay({}) tunnel rejected: relay_id {} not registered on this connection",
+                    conn.remote_address(),
+                    relay_id
+                );
+                Err(CloseReason::PacketMismatch)
+            },
+        }
+    }
+
     /// Admit a gateway registration. Mirrors [`Self::register_relay`]:
     /// id↔pubkey binding + signature + freshness, then last-connection-wins.
     /// The `PUSH_GATEWAY` capability is deliberately NOT checked here — the
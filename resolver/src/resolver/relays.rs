@@ -9,6 +9,7 @@ use std::time::Instant;
 
 use common::proto::RelayId;
 use common::proto::client_res::RelayDescriptor;
+use common::proto::client_res::TunnelDescriptor;
 use common::types::bytes::Bytes;
 use parking_lot::Mutex;
 use quinn::Connection;
@@ -55,6 +56,9 @@ pub struct RelayEntry {
     /// one whose holder stayed connected past a heartbeat interval, which
     /// is what [`admit`] refuses to evict for a newcomer.
     established: Arc<AtomicBool>,
+    /// TCP port of the relay's tunnel listener, once an authenticated
+    /// `RelayTunnel` has named one. Served by `GetTunnels`.
+    tunnel_port: Arc<Mutex<Option<u16>>>,
 }
 
 impl RelayEntry {
@@ -65,6 +69,7 @@ impl RelayEntry {
             pubkey,
             last_heartbeat_at: Arc::new(Mutex::new(Instant::now())),
             established: Arc::new(AtomicBool::new(false)),
+            tunnel_port: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -72,6 +77,17 @@ impl RelayEntry {
         descriptor(self.id, self.conn.remote_address(), self.pubkey)
     }
 
+    /// The advertised tunnel port, if any, as a [`TunnelDescriptor`].
+    pub fn to_tunnel(&self) -> Option<TunnelDescriptor> {
+        self.tunnel_port.lock().map(|port| TunnelDescriptor { id: self.id, port })
+    }
+
+    /// Records the tunnel port from an authenticated `RelayTunnel`; `true`
+    /// if it changed.
+    pub fn set_tunnel_port(&self, port: u16) -> bool {
+        self.tunnel_port.lock().replace(port) != Some(port)
+    }
+
     /// Latest observation of this relay's liveness, as an [`Instant`].
     /// Cloned out of the per-entry `Mutex` so callers don't hold the
     /// lock across whatever they do next.
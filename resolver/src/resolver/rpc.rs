@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::sync::Arc;
 use std::sync::atomic::Ordering as AtomicOrdering;
+use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Result;
 use anyhow::anyhow;
@@ -9,11 +11,18 @@ use common::proto::client_res::ClientRequest;
 use common::proto::client_res::ClientResponse;
 use common::proto::client_res::MAX_BOOTSTRAP_RESULTS;
 use common::proto::client_res::RelayDescriptor;
+use common::proto::client_res::RelayDirectory;
 use common::proto::pack::Packer;
 use common::quic::xor32;
 
 use crate::resolver::Resolver;
 use crate::resolver::relays::RelayEntry;
+use crate::util::systime;
+
+/// Age past which a cached `GetDirectory` response is re-signed even with no
+/// membership change, so a client never caches a directory that is already
+/// most of the way through its TTL.
+const DIRECTORY_RESIGN_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 pub trait HandleRPC {
     /// Framed response bytes, ready to write to the requesting stream.
@@ -32,6 +41,8 @@ impl HandleRPC for Resolver {
                 let gateways = self.snapshot_gateways().iter().map(|g| g.to_descriptor()).collect();
                 Ok(Arc::new(ClientResponse::GetGateways { gateways }.pack()?))
             },
+            ClientRequest::GetDirectory() => self.directory_response(),
+            ClientRequest::GetTunnels() => self.tunnels_response(),
         }
     }
 }
@@ -58,6 +69,45 @@ impl Resolver {
 
         Ok(packet)
     }
+
+    /// Packed `GetTunnels` response, cached like [`Self::relays_response`].
+    fn tunnels_response(&self) -> Result<Arc<Vec<u8>>> {
+        let generation = self.relays_generation.load(AtomicOrdering::Acquire);
+
+        if let Some((cached, packet)) = self.tunnels_response.read().as_ref()
+            && *cached == generation
+        {
+            return Ok(packet.clone());
+        }
+
+        let tunnels = self.relays.read().values().filter_map(RelayEntry::to_tunnel).collect();
+        let packet = Arc::new(ClientResponse::GetTunnels { tunnels }.pack()?);
+        *self.tunnels_response.write() = Some((generation, packet.clone()));
+
+        Ok(packet)
+    }
+
+    /// Packed `GetDirectory` response. Cached like [`Self::relays_response`],
+    /// so a flood costs no signatures; additionally re-signed once the cached
+    /// copy is older than [`DIRECTORY_RESIGN_INTERVAL`].
+    fn directory_response(&self) -> Result<Arc<Vec<u8>>> {
+        let generation = self.relays_generation.load(AtomicOrdering::Acquire);
+
+        if let Some((cached, signed_at, packet)) = self.directory_response.read().as_ref()
+            && *cached == generation
+            && signed_at.elapsed() < DIRECTORY_RESIGN_INTERVAL
+        {
+            return Ok(packet.clone());
+        }
+
+        let relays: Vec<RelayDescriptor> =
+            self.relays.read().values().map(RelayEntry::to_descriptor).collect();
+        let directory = RelayDirectory::sign(&self.signer, relays, systime().as_millis() as u64);
+        let packet = Arc::new(ClientResponse::GetDirectory { directory }.pack()?);
+        *self.directory_response.write() = Some((generation, Instant::now(), packet.clone()));
+
+        Ok(packet)
+    }
 }
 
 /// Implementation of [`ClientRequest::GetBootstrapPeers`].